@@ -0,0 +1,121 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use http::HeaderMap;
+use integrationos_domain::{
+    access_key::{encrypted_data::EncryptedData, AccessKey},
+    connection::Connection,
+    event::Event,
+    id::{prefix::IdPrefix, Id},
+    Filter, HashExt, HashKecAlg, MongoQuery, QueryBackend,
+};
+use serde_json::json;
+
+const ACCESS_KEY: &str = "id_live_1_Q71YUIZydcgSwJQNOUCHhaTMqmIvslIafF5LluORJfJKydMGELHtYe_ydtBIrVuomEnOZ4jfZQgtkqWxtG-s7vhbyir4kNjLyHKyDyh1SDubBMlhSI7Mq-M5RVtwnwFqZiOeUkIgHJFgcGQn0Plb1AkAAAAAAAAAAAAAAAAAAAAAAMwWY_9_oDOV75noniBViOVmVPUQqzcW8G3P8nuUD6Q";
+const PASSWORD: &[u8; 32] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+
+fn connection() -> Connection {
+    serde_json::from_value(json!({
+        "_id": "conn::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+        "platformVersion": "1.0.0",
+        "connectionDefinitionId": "conn_def::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+        "type": { "api": {} },
+        "name": "Stripe",
+        "key": "stripe::test",
+        "group": "group",
+        "environment": "test",
+        "platform": "stripe",
+        "secretsServiceId": "secret-id",
+        "eventAccessId": "evt_ac::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+        "accessKey": ACCESS_KEY,
+        "settings": {
+            "parseWebhookBody": false,
+            "showSecret": false,
+            "allowCustomEvents": false,
+            "oauth": false
+        },
+        "throughput": { "key": "throughput-key", "limit": 100 },
+        "ownership": { "buildableId": "owner", "clientId": "owner" }
+    }))
+    .expect("Benchmark connection deserializes")
+}
+
+fn event() -> Event {
+    let access_key = AccessKey::parse_str(ACCESS_KEY, PASSWORD).expect("Benchmark key parses");
+    let encrypted = access_key
+        .encode(PASSWORD, &[0u8; 16])
+        .expect("Benchmark key encodes");
+    Event::new(
+        &access_key,
+        &encrypted,
+        "customer.created",
+        HeaderMap::new(),
+        json!({ "id": "cus_123", "email": "jane@example.com" }).to_string(),
+    )
+}
+
+fn access_keys(c: &mut Criterion) {
+    c.bench_function("access_key/parse_str", |b| {
+        b.iter(|| AccessKey::parse_str(black_box(ACCESS_KEY), PASSWORD).unwrap())
+    });
+}
+
+fn hashes(c: &mut Criterion) {
+    let payload = "x".repeat(1024);
+    c.bench_function("hash/keccak_1kb", |b| {
+        b.iter(|| HashKecAlg.hash(black_box(&payload)).unwrap())
+    });
+    c.bench_function("hash/encrypted_data_1kb", |b| {
+        b.iter(|| {
+            EncryptedData::compute_hash(black_box(payload.as_bytes()), &[0u8; 16], PASSWORD)
+                .unwrap()
+        })
+    });
+}
+
+fn ids(c: &mut Criterion) {
+    c.bench_function("id/now", |b| b.iter(|| Id::now(black_box(IdPrefix::Event))));
+    let id = Id::now(IdPrefix::Event).to_string();
+    c.bench_function("id/parse", |b| {
+        b.iter(|| black_box(id.as_str()).parse::<Id>().unwrap())
+    });
+}
+
+fn serde(c: &mut Criterion) {
+    let connection = connection();
+    let connection_json = serde_json::to_string(&connection).unwrap();
+    c.bench_function("serde/connection_serialize", |b| {
+        b.iter(|| serde_json::to_string(black_box(&connection)).unwrap())
+    });
+    c.bench_function("serde/connection_deserialize", |b| {
+        b.iter(|| serde_json::from_str::<Connection>(black_box(&connection_json)).unwrap())
+    });
+
+    let event = event();
+    let event_json = serde_json::to_string(&event).unwrap();
+    c.bench_function("serde/event_serialize", |b| {
+        b.iter(|| serde_json::to_string(black_box(&event)).unwrap())
+    });
+    c.bench_function("serde/event_deserialize", |b| {
+        b.iter(|| serde_json::from_str::<Event>(black_box(&event_json)).unwrap())
+    });
+    c.bench_function("serde/event_to_bson", |b| {
+        b.iter(|| bson::to_document(black_box(&event)).unwrap())
+    });
+}
+
+fn filters(c: &mut Criterion) {
+    let filter = Filter::and(vec![
+        Filter::eq("ownership.buildableId", json!("build-123")),
+        Filter::eq("deleted", json!(false)),
+        Filter::gte("createdAt", json!(1_700_000_000_000i64)),
+        Filter::or(vec![
+            Filter::eq("platform", json!("stripe")),
+            Filter::eq("platform", json!("shopify")),
+        ]),
+    ]);
+    c.bench_function("store/mongo_filter", |b| {
+        b.iter(|| MongoQuery::filter(black_box(&filter)).unwrap())
+    });
+}
+
+criterion_group!(benches, access_keys, hashes, ids, serde, filters);
+criterion_main!(benches);
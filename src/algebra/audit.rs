@@ -0,0 +1,288 @@
+use super::patch::apply_operation;
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    IntegrationOSError, InternalError, MongoStore, PatchOperation,
+};
+use bson::doc;
+use chrono::Utc;
+use futures::TryStreamExt;
+use mongodb::{options::FindOptions, Collection};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// One change to a record. Creations keep a full snapshot, updates only the JSON Patch
+/// from the previous state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub record_id: String,
+    pub action: AuditAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<Value>,
+    #[serde(default)]
+    pub changes: Vec<PatchOperation>,
+    pub modified_by: String,
+    pub timestamp: i64,
+}
+
+impl AuditEntry {
+    fn new(record_id: &str, action: AuditAction, modified_by: &str) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Log),
+            record_id: record_id.to_owned(),
+            action,
+            snapshot: None,
+            changes: vec![],
+            modified_by: modified_by.to_owned(),
+            timestamp: Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_into(before: &Value, after: &Value, path: &str, operations: &mut Vec<PatchOperation>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            for (key, old) in before {
+                let path = format!("{path}/{}", escape(key));
+                match after.get(key) {
+                    Some(new) => diff_into(old, new, &path, operations),
+                    None => operations.push(PatchOperation::Remove { path }),
+                }
+            }
+            for (key, new) in after {
+                if !before.contains_key(key) {
+                    operations.push(PatchOperation::Add {
+                        path: format!("{path}/{}", escape(key)),
+                        value: new.clone(),
+                    });
+                }
+            }
+        }
+        (before, after) if before != after => operations.push(PatchOperation::Replace {
+            path: path.to_owned(),
+            value: after.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// JSON Patch turning `before` into `after`. Arrays and scalars are replaced as a whole.
+pub fn diff(before: &Value, after: &Value) -> Vec<PatchOperation> {
+    let mut operations = vec![];
+    diff_into(before, after, "", &mut operations);
+    operations
+}
+
+/// State of a record after replaying its entries up to `as_of`, or `None` when it didn't
+/// exist then
+pub fn replay(entries: &[AuditEntry], as_of: i64) -> Result<Option<Value>, IntegrationOSError> {
+    let mut state = None;
+    for entry in entries.iter().take_while(|e| e.timestamp <= as_of) {
+        match entry.action {
+            AuditAction::Create => state = entry.snapshot.clone(),
+            AuditAction::Delete => state = None,
+            AuditAction::Update => {
+                let Some(current) = state.as_mut() else {
+                    return Err(InternalError::invalid_argument(
+                        &format!("Update {} has no prior snapshot to apply to", entry.id),
+                        Some("audit"),
+                    ));
+                };
+                for operation in &entry.changes {
+                    apply_operation(current, operation)?;
+                }
+            }
+        }
+    }
+    Ok(state)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, IntegrationOSError> {
+    serde_json::to_value(value)
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("audit")))
+}
+
+/// [`MongoStore`] that records every write in an audit log, from which past versions of
+/// a record can be rebuilt
+#[derive(Debug, Clone)]
+pub struct AuditedStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
+    pub store: MongoStore<T>,
+    audit: Collection<AuditEntry>,
+}
+
+impl<T> AuditedStore<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    pub fn new(store: MongoStore<T>, audit: Collection<AuditEntry>) -> Self {
+        Self { store, audit }
+    }
+
+    fn record_id(value: &Value) -> Result<String, IntegrationOSError> {
+        value
+            .get("_id")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| {
+                InternalError::invalid_argument("Audited records need a string _id", Some("audit"))
+            })
+    }
+
+    pub async fn create_one(&self, data: &T, modified_by: &str) -> Result<(), IntegrationOSError> {
+        let value = to_value(data)?;
+        let mut entry =
+            AuditEntry::new(&Self::record_id(&value)?, AuditAction::Create, modified_by);
+        entry.snapshot = Some(value);
+        self.store.create_one(data).await?;
+        self.audit.insert_one(&entry, None).await?;
+        Ok(())
+    }
+
+    /// Replaces the record, logging the changes from its current version. Records created
+    /// before auditing started get a snapshot of their current version first.
+    pub async fn replace_one(&self, data: &T, modified_by: &str) -> Result<(), IntegrationOSError> {
+        let after = to_value(data)?;
+        let id = Self::record_id(&after)?;
+        let before = self
+            .store
+            .get_one_by_id(&id)
+            .await?
+            .ok_or_else(|| {
+                InternalError::key_not_found(&format!("Record {id} not found"), Some("audit"))
+            })
+            .and_then(|before| to_value(&before))?;
+
+        if self.history(&id).await?.is_empty() {
+            let mut baseline = AuditEntry::new(&id, AuditAction::Create, modified_by);
+            baseline.snapshot = Some(before.clone());
+            self.audit.insert_one(&baseline, None).await?;
+        }
+
+        let mut entry = AuditEntry::new(&id, AuditAction::Update, modified_by);
+        entry.changes = diff(&before, &after);
+        self.store
+            .collection
+            .replace_one(doc! { "_id": &id }, data, None)
+            .await?;
+        self.audit.insert_one(&entry, None).await?;
+        Ok(())
+    }
+
+    pub async fn delete_one(&self, id: &str, modified_by: &str) -> Result<(), IntegrationOSError> {
+        self.store
+            .collection
+            .delete_one(doc! { "_id": id }, None)
+            .await?;
+        self.audit
+            .insert_one(AuditEntry::new(id, AuditAction::Delete, modified_by), None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn history(&self, id: &str) -> Result<Vec<AuditEntry>, IntegrationOSError> {
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": 1, "_id": 1 })
+            .build();
+        Ok(self
+            .audit
+            .find(doc! { "recordId": id }, options)
+            .await?
+            .try_collect()
+            .await?)
+    }
+
+    /// The record as it was at `timestamp` (epoch millis)
+    pub async fn get_as_of(
+        &self,
+        id: &str,
+        timestamp: i64,
+    ) -> Result<Option<T>, IntegrationOSError> {
+        replay(&self.history(id).await?, timestamp)?
+            .map(|value| {
+                serde_json::from_value(value)
+                    .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("audit")))
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(action: AuditAction, timestamp: i64) -> AuditEntry {
+        AuditEntry {
+            timestamp,
+            ..AuditEntry::new("conn::1", action, "system")
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = json!({ "name": "a", "settings": { "a/b": 1, "old": true }, "tags": [1] });
+        let after = json!({ "name": "a", "settings": { "a/b": 2, "new": null }, "tags": [1, 2] });
+
+        assert_eq!(
+            diff(&before, &after),
+            vec![
+                PatchOperation::Replace {
+                    path: "/settings/a~1b".to_owned(),
+                    value: json!(2)
+                },
+                PatchOperation::Remove {
+                    path: "/settings/old".to_owned()
+                },
+                PatchOperation::Add {
+                    path: "/settings/new".to_owned(),
+                    value: Value::Null
+                },
+                PatchOperation::Replace {
+                    path: "/tags".to_owned(),
+                    value: json!([1, 2])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let v1 = json!({ "_id": "conn::1", "name": "first", "active": true });
+        let v2 = json!({ "_id": "conn::1", "name": "second", "active": true });
+        let v3 = json!({ "_id": "conn::1", "name": "second" });
+
+        let mut created = entry(AuditAction::Create, 10);
+        created.snapshot = Some(v1.clone());
+        let mut renamed = entry(AuditAction::Update, 20);
+        renamed.changes = diff(&v1, &v2);
+        let mut deactivated = entry(AuditAction::Update, 30);
+        deactivated.changes = diff(&v2, &v3);
+        let entries = vec![
+            created,
+            renamed,
+            deactivated,
+            entry(AuditAction::Delete, 40),
+        ];
+
+        assert_eq!(replay(&entries, 5).unwrap(), None);
+        assert_eq!(replay(&entries, 10).unwrap(), Some(v1));
+        assert_eq!(replay(&entries, 25).unwrap(), Some(v2));
+        assert_eq!(replay(&entries, 35).unwrap(), Some(v3));
+        assert_eq!(replay(&entries, 45).unwrap(), None);
+        assert!(replay(&entries[1..], 25).is_err());
+    }
+}
@@ -1,13 +1,18 @@
-use crate::{cache::CacheConfig, IntegrationOSError};
+use crate::{cache::CacheConfig, IntegrationOSError, InternalError};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use futures::FutureExt;
 use redis::{
     aio::{ConnectionLike, ConnectionManager},
-    Client, Pipeline, RedisFuture, Value,
+    AsyncCommands, Client, LposOptions, Pipeline, RedisFuture, Value,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value as SerdeValue;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tracing::warn;
 
 #[async_trait]
@@ -23,7 +28,27 @@ pub trait CacheExt {
     async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError>;
     async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError>;
     async fn remove(&self, key: &str) -> Result<(), IntegrationOSError>;
+    /// Removes every entry, leaving the lists alone
     async fn clear(&self) -> Result<(), IntegrationOSError>;
+    /// Sets the time to live of an existing entry, returning whether it exists
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError>;
+    /// Pushes a payload to the head of the list stored at `key`
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError>;
+    /// Index of the first occurrence of `value` in the list, counting from the head
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError>;
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError>;
+    /// Atomically pops the tail of `source` and pushes it to the head of `destination`
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError>;
+    /// Removes the first occurrence of `value` from the list, returning whether it was found
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError>;
 }
 
 #[derive(Debug, Clone)]
@@ -75,6 +100,22 @@ impl RedisCache {
             retry_count: max_retries,
         })
     }
+
+    /// Dedicated connection subscribed to `channel`, since a subscribed connection
+    /// can't run other commands
+    pub async fn subscribe(&self, channel: &str) -> Result<redis::aio::PubSub> {
+        let mut pubsub = self
+            .client
+            .get_async_connection()
+            .await
+            .with_context(|| "Could not connect to redis")?
+            .into_pubsub();
+        pubsub
+            .subscribe(channel)
+            .await
+            .with_context(|| format!("Could not subscribe to {channel}"))?;
+        Ok(pubsub)
+    }
 }
 
 impl ConnectionLike for RedisCache {
@@ -117,3 +158,397 @@ impl ConnectionLike for RedisCache {
         .boxed()
     }
 }
+
+fn redis_error(e: redis::RedisError) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("redis_cache"))
+}
+
+/// Namespace of the entries of a [`RedisCache`], apart from the lists, semaphores and
+/// keys other services keep in the same database
+const ENTRY_PREFIX: &str = "cache::";
+
+/// Keys deleted by each `DEL` of [`CacheExt::clear`]
+const CLEAR_BATCH: usize = 500;
+
+fn entry_key(key: &str) -> String {
+    format!("{ENTRY_PREFIX}{key}")
+}
+
+/// Entries are stored as JSON strings under their key prefixed with `cache::`, and
+/// [`CacheExt::clear`] only deletes those. Lists are stored under their key as is.
+#[async_trait]
+impl CacheExt for RedisCache {
+    async fn get_or_insert_with<F>(
+        &self,
+        key: &str,
+        f: F,
+        expire: Option<u64>,
+    ) -> Result<CacheEntry, IntegrationOSError>
+    where
+        F: FnOnce() -> Result<CacheEntry, IntegrationOSError> + Send,
+    {
+        if let Some(entry) = self.get(key).await? {
+            return Ok(entry);
+        }
+        let entry = f()?;
+        self.set(entry.clone(), expire).await?;
+        Ok(entry)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError> {
+        let mut conn = self.clone();
+        let value: Option<String> = AsyncCommands::get(&mut conn, entry_key(key))
+            .await
+            .map_err(redis_error)?;
+        value
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map(|value| CacheEntry::new(key.to_owned(), value))
+                    .map_err(|e| {
+                        InternalError::deserialize_error(&e.to_string(), Some("redis_cache"))
+                    })
+            })
+            .transpose()
+    }
+
+    async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError> {
+        let mut conn = self.clone();
+        let key = entry_key(entry.key());
+        let value = entry.value().to_string();
+        match expire {
+            // Redis rejects `SETEX` with no time to live, the entry would expire right away
+            Some(0) => conn.del(key).await,
+            Some(seconds) => conn.set_ex(key, value, seconds as usize).await,
+            None => AsyncCommands::set(&mut conn, key, value).await,
+        }
+        .map_err(redis_error)
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.del(entry_key(key)).await.map_err(redis_error)
+    }
+
+    async fn clear(&self) -> Result<(), IntegrationOSError> {
+        let mut scan = self.clone();
+        let mut conn = self.clone();
+        let mut keys = scan
+            .scan_match::<_, String>(format!("{ENTRY_PREFIX}*"))
+            .await
+            .map_err(redis_error)?;
+        let mut batch = Vec::with_capacity(CLEAR_BATCH);
+        while let Some(key) = keys.next_item().await {
+            batch.push(key);
+            if batch.len() == CLEAR_BATCH {
+                conn.del::<_, ()>(std::mem::take(&mut batch))
+                    .await
+                    .map_err(redis_error)?;
+            }
+        }
+        if !batch.is_empty() {
+            conn.del::<_, ()>(batch).await.map_err(redis_error)?;
+        }
+        Ok(())
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError> {
+        let mut conn = self.clone();
+        AsyncCommands::expire(&mut conn, entry_key(key), seconds as usize)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.lpush(key, value).await.map_err(redis_error)
+    }
+
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.lpos(key, value, LposOptions::default())
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.llen(key).await.map_err(redis_error)
+    }
+
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.rpoplpush(source, destination)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError> {
+        let mut conn = self.clone();
+        let removed: usize = conn.lrem(key, 1, value).await.map_err(redis_error)?;
+        Ok(removed > 0)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Stored {
+    Entry(SerdeValue),
+    List(VecDeque<Vec<u8>>),
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    value: Stored,
+    expires_at: Option<Instant>,
+}
+
+impl Slot {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+/// Process local cache for tests and local development, mirroring the Redis semantics
+/// of [`CacheExt`] without a server
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryCache {
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+}
+
+impl InMemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_slots<R>(&self, f: impl FnOnce(&mut HashMap<String, Slot>) -> R) -> R {
+        let mut slots = self.slots.lock().expect("in-memory cache lock poisoned");
+        let now = Instant::now();
+        slots.retain(|_, slot| slot.is_live(now));
+        f(&mut slots)
+    }
+
+    fn wrong_type(key: &str) -> IntegrationOSError {
+        InternalError::invalid_argument(
+            &format!("Key {key} holds the wrong kind of value"),
+            Some("in_memory_cache"),
+        )
+    }
+}
+
+#[async_trait]
+impl CacheExt for InMemoryCache {
+    async fn get_or_insert_with<F>(
+        &self,
+        key: &str,
+        f: F,
+        expire: Option<u64>,
+    ) -> Result<CacheEntry, IntegrationOSError>
+    where
+        F: FnOnce() -> Result<CacheEntry, IntegrationOSError> + Send,
+    {
+        if let Some(entry) = self.get(key).await? {
+            return Ok(entry);
+        }
+        let entry = f()?;
+        self.set(entry.clone(), expire).await?;
+        Ok(entry)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError> {
+        self.with_slots(|slots| match slots.get(key) {
+            None => Ok(None),
+            Some(Slot {
+                value: Stored::Entry(value),
+                ..
+            }) => Ok(Some(CacheEntry::new(key.to_owned(), value.clone()))),
+            Some(_) => Err(Self::wrong_type(key)),
+        })
+    }
+
+    async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError> {
+        let expires_at = expire.map(|seconds| Instant::now() + Duration::from_secs(seconds));
+        self.with_slots(|slots| {
+            slots.insert(
+                entry.key().to_owned(),
+                Slot {
+                    value: Stored::Entry(entry.value().clone()),
+                    expires_at,
+                },
+            );
+        });
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.with_slots(|slots| slots.remove(key));
+        Ok(())
+    }
+
+    async fn clear(&self) -> Result<(), IntegrationOSError> {
+        self.with_slots(|slots| slots.retain(|_, slot| matches!(slot.value, Stored::List(_))));
+        Ok(())
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError> {
+        let expires_at = Instant::now() + Duration::from_secs(seconds);
+        Ok(self.with_slots(|slots| match slots.get_mut(key) {
+            Some(slot) => {
+                slot.expires_at = Some(expires_at);
+                true
+            }
+            None => false,
+        }))
+    }
+
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError> {
+        self.with_slots(|slots| {
+            let slot = slots.entry(key.to_owned()).or_insert_with(|| Slot {
+                value: Stored::List(VecDeque::new()),
+                expires_at: None,
+            });
+            match &mut slot.value {
+                Stored::List(list) => {
+                    list.push_front(value.to_vec());
+                    Ok(())
+                }
+                Stored::Entry(_) => Err(Self::wrong_type(key)),
+            }
+        })
+    }
+
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError> {
+        self.with_slots(|slots| match slots.get(key).map(|slot| &slot.value) {
+            None => Ok(None),
+            Some(Stored::List(list)) => Ok(list.iter().position(|item| item == value)),
+            Some(Stored::Entry(_)) => Err(Self::wrong_type(key)),
+        })
+    }
+
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError> {
+        self.with_slots(|slots| match slots.get(key).map(|slot| &slot.value) {
+            None => Ok(0),
+            Some(Stored::List(list)) => Ok(list.len()),
+            Some(Stored::Entry(_)) => Err(Self::wrong_type(key)),
+        })
+    }
+
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        self.with_slots(|slots| {
+            if let Some(Slot {
+                value: Stored::Entry(_),
+                ..
+            }) = slots.get(destination)
+            {
+                return Err(Self::wrong_type(destination));
+            }
+            let value = match slots.get_mut(source).map(|slot| &mut slot.value) {
+                None => return Ok(None),
+                Some(Stored::List(list)) => list.pop_back(),
+                Some(Stored::Entry(_)) => return Err(Self::wrong_type(source)),
+            };
+            if let Some(value) = &value {
+                let slot = slots.entry(destination.to_owned()).or_insert_with(|| Slot {
+                    value: Stored::List(VecDeque::new()),
+                    expires_at: None,
+                });
+                if let Stored::List(list) = &mut slot.value {
+                    list.push_front(value.clone());
+                }
+            }
+            Ok(value)
+        })
+    }
+
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError> {
+        self.with_slots(
+            |slots| match slots.get_mut(key).map(|slot| &mut slot.value) {
+                None => Ok(false),
+                Some(Stored::List(list)) => Ok(list
+                    .iter()
+                    .position(|item| item == value)
+                    .and_then(|index| list.remove(index))
+                    .is_some()),
+                Some(Stored::Entry(_)) => Err(Self::wrong_type(key)),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_in_memory_cache_entries() {
+        let cache = InMemoryCache::new();
+        cache
+            .set(CacheEntry::new("a".to_owned(), json!({ "b": 1 })), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get("a").await.unwrap().unwrap().value(),
+            &json!({ "b": 1 })
+        );
+
+        let entry = cache
+            .get_or_insert_with("c", || Ok(CacheEntry::new("c".to_owned(), json!(2))), None)
+            .await
+            .unwrap();
+        assert_eq!(entry.value(), &json!(2));
+
+        assert!(cache.expire("a", 0).await.unwrap());
+        assert!(cache.get("a").await.unwrap().is_none());
+        assert!(!cache.expire("a", 10).await.unwrap());
+
+        cache
+            .set(CacheEntry::new("d".to_owned(), json!(3)), Some(0))
+            .await
+            .unwrap();
+        assert!(cache.get("d").await.unwrap().is_none());
+
+        cache.list_push("queue", b"event").await.unwrap();
+        cache.clear().await.unwrap();
+        assert!(cache.get("c").await.unwrap().is_none());
+        // Clearing the cache keeps the queues
+        assert_eq!(cache.list_len("queue").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_cache_lists() {
+        let cache = InMemoryCache::new();
+        cache.list_push("queue", b"first").await.unwrap();
+        cache.list_push("queue", b"second").await.unwrap();
+
+        assert_eq!(cache.list_len("queue").await.unwrap(), 2);
+        assert_eq!(
+            cache.list_position("queue", b"first").await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(cache.list_position("queue", b"third").await.unwrap(), None);
+        assert!(cache.get("queue").await.is_err());
+
+        assert_eq!(
+            cache.list_move("queue", "processing").await.unwrap(),
+            Some(b"first".to_vec())
+        );
+        assert!(cache.list_remove("processing", b"first").await.unwrap());
+        assert!(!cache.list_remove("processing", b"first").await.unwrap());
+        assert_eq!(cache.list_len("queue").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{IntegrationOSError, InternalError, RedisCache};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use redis::AsyncCommands;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// Key of a probabilistic distinct counter, e.g. unique event senders for a given day
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CardinalityKey(String);
+
+impl CardinalityKey {
+    pub fn new(metric: &str) -> Self {
+        Self(format!("hll::{metric}"))
+    }
+
+    pub fn daily(metric: &str, date: NaiveDate) -> Self {
+        Self(format!("hll::{metric}::{}", date.format("%Y-%m-%d")))
+    }
+
+    pub fn per_connection(metric: &str, connection_key: &str) -> Self {
+        Self(format!("hll::{metric}::{connection_key}"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[async_trait]
+pub trait CardinalityExt {
+    /// Records members, returning true if the estimated cardinality changed
+    async fn observe(
+        &self,
+        key: &CardinalityKey,
+        members: &[&str],
+    ) -> Result<bool, IntegrationOSError>;
+    async fn estimate(&self, key: &CardinalityKey) -> Result<u64, IntegrationOSError>;
+    /// Estimated cardinality of the union of several counters, e.g. a week of daily keys
+    async fn estimate_union(&self, keys: &[CardinalityKey]) -> Result<u64, IntegrationOSError>;
+    async fn expire(&self, key: &CardinalityKey, seconds: u64) -> Result<(), IntegrationOSError>;
+}
+
+fn redis_error(e: redis::RedisError) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("hyperloglog"))
+}
+
+#[async_trait]
+impl CardinalityExt for RedisCache {
+    async fn observe(
+        &self,
+        key: &CardinalityKey,
+        members: &[&str],
+    ) -> Result<bool, IntegrationOSError> {
+        let mut conn = self.clone();
+        let changed: i64 = conn
+            .pfadd(key.as_str(), members)
+            .await
+            .map_err(redis_error)?;
+        Ok(changed == 1)
+    }
+
+    async fn estimate(&self, key: &CardinalityKey) -> Result<u64, IntegrationOSError> {
+        let mut conn = self.clone();
+        conn.pfcount(key.as_str()).await.map_err(redis_error)
+    }
+
+    async fn estimate_union(&self, keys: &[CardinalityKey]) -> Result<u64, IntegrationOSError> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.clone();
+        let keys: Vec<&str> = keys.iter().map(CardinalityKey::as_str).collect();
+        conn.pfcount(keys).await.map_err(redis_error)
+    }
+
+    async fn expire(&self, key: &CardinalityKey, seconds: u64) -> Result<(), IntegrationOSError> {
+        let mut conn = self.clone();
+        AsyncCommands::expire(&mut conn, key.as_str(), seconds as usize)
+            .await
+            .map_err(redis_error)
+    }
+}
+
+/// Fixed memory frequency estimator. Estimates never undercount, and overcount by at
+/// most `total / width` with a probability depending on `depth`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u64>,
+    total: u64,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        let width = width.max(1);
+        let depth = depth.max(1);
+        Self {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            total: 0,
+        }
+    }
+
+    fn index<T: Hash + ?Sized>(&self, item: &T, row: usize) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        item.hash(&mut hasher);
+        row * self.width + (hasher.finish() as usize % self.width)
+    }
+
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T, count: u64) {
+        for row in 0..self.depth {
+            let index = self.index(item, row);
+            self.counters[index] = self.counters[index].saturating_add(count);
+        }
+        self.total = self.total.saturating_add(count);
+    }
+
+    pub fn estimate<T: Hash + ?Sized>(&self, item: &T) -> u64 {
+        (0..self.depth)
+            .map(|row| self.counters[self.index(item, row)])
+            .min()
+            .unwrap_or_default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn merge(&mut self, other: &Self) -> Result<(), IntegrationOSError> {
+        if self.width != other.width || self.depth != other.depth {
+            return Err(InternalError::invalid_argument(
+                "Cannot merge sketches with different dimensions",
+                Some("count_min_sketch"),
+            ));
+        }
+        for (a, b) in self.counters.iter_mut().zip(other.counters.iter()) {
+            *a = a.saturating_add(*b);
+        }
+        self.total = self.total.saturating_add(other.total);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cardinality_keys() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_eq!(
+            CardinalityKey::daily("unique-senders", date).as_str(),
+            "hll::unique-senders::2024-01-31"
+        );
+        assert_eq!(
+            CardinalityKey::per_connection("external-ids", "stripe::test").as_str(),
+            "hll::external-ids::stripe::test"
+        );
+    }
+
+    #[test]
+    fn test_count_min_sketch() {
+        let mut sketch = CountMinSketch::new(256, 4);
+        for _ in 0..10 {
+            sketch.add("foo", 1);
+        }
+        sketch.add("bar", 3);
+
+        assert!(sketch.estimate("foo") >= 10);
+        assert!(sketch.estimate("bar") >= 3);
+        assert_eq!(sketch.total(), 13);
+
+        let mut other = CountMinSketch::new(256, 4);
+        other.add("foo", 5);
+        sketch.merge(&other).unwrap();
+        assert!(sketch.estimate("foo") >= 15);
+        assert!(sketch.merge(&CountMinSketch::new(8, 1)).is_err());
+    }
+}
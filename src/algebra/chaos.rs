@@ -0,0 +1,477 @@
+use crate::{CacheEntry, CacheExt, FecherExt, IntegrationOSError, InternalError, MongoStore};
+use async_trait::async_trait;
+use bson::Document;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChaosTarget {
+    Store,
+    Cache,
+    Fetcher,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChaosFault {
+    /// Only adds latency, the operation then runs normally
+    None,
+    Error,
+    /// Reads return a fraction of their results, batch writes only apply a fraction of
+    /// the batch before failing
+    Partial {
+        keep: f64,
+    },
+}
+
+/// A single fault injection rule. Rules without an operation or key match every
+/// operation or key of their target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosRule {
+    pub target: ChaosTarget,
+    pub operation: Option<String>,
+    pub key: Option<String>,
+    pub probability: f64,
+    pub latency: Option<Duration>,
+    pub fault: ChaosFault,
+}
+
+impl ChaosRule {
+    pub fn new(target: ChaosTarget, fault: ChaosFault) -> Self {
+        Self {
+            target,
+            operation: None,
+            key: None,
+            probability: 1.0,
+            latency: None,
+            fault,
+        }
+    }
+
+    pub fn operation(mut self, operation: &str) -> Self {
+        self.operation = Some(operation.to_owned());
+        self
+    }
+
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_owned());
+        self
+    }
+
+    pub fn probability(mut self, probability: f64) -> Self {
+        self.probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    fn matches(&self, target: ChaosTarget, operation: &str, key: Option<&str>) -> bool {
+        self.target == target
+            && self.operation.as_deref().is_none_or(|op| op == operation)
+            && self.key.as_deref().is_none_or(|k| key == Some(k))
+    }
+}
+
+/// Shared fault injection configuration for the chaos wrappers. Cloning shares the rules,
+/// so they can be changed while a test is running.
+#[derive(Debug, Clone)]
+pub struct ChaosLayer {
+    rules: Arc<Mutex<Vec<ChaosRule>>>,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl Default for ChaosLayer {
+    fn default() -> Self {
+        Self {
+            rules: Default::default(),
+            rng: Arc::new(Mutex::new(StdRng::from_entropy())),
+        }
+    }
+}
+
+impl ChaosLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deterministic layer for reproducible test runs
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rules: Default::default(),
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+
+    pub fn add_rule(&self, rule: ChaosRule) -> &Self {
+        self.rules.lock().expect("chaos lock poisoned").push(rule);
+        self
+    }
+
+    pub fn clear(&self) {
+        self.rules.lock().expect("chaos lock poisoned").clear();
+    }
+
+    /// Picks the fault to apply to an operation, if any of the matching rules fires
+    pub fn roll(
+        &self,
+        target: ChaosTarget,
+        operation: &str,
+        key: Option<&str>,
+    ) -> Option<ChaosRule> {
+        let rules = self.rules.lock().expect("chaos lock poisoned");
+        let mut rng = self.rng.lock().expect("chaos lock poisoned");
+        rules
+            .iter()
+            .filter(|rule| rule.matches(target, operation, key))
+            .find(|rule| rng.gen_bool(rule.probability))
+            .cloned()
+    }
+
+    /// Applies latency and fails the operation when an error rule fires. Partial faults
+    /// are returned so the caller can apply them to its results.
+    pub async fn inject(
+        &self,
+        target: ChaosTarget,
+        operation: &str,
+        key: Option<&str>,
+    ) -> Result<Option<f64>, IntegrationOSError> {
+        let Some(rule) = self.roll(target, operation, key) else {
+            return Ok(None);
+        };
+        if let Some(latency) = rule.latency {
+            tokio::time::sleep(latency).await;
+        }
+        match rule.fault {
+            ChaosFault::None => Ok(None),
+            ChaosFault::Error => Err(InternalError::io_err(
+                &format!("Injected failure for {target:?} {operation}"),
+                Some("chaos"),
+            )),
+            ChaosFault::Partial { keep } => Ok(Some(keep.clamp(0.0, 1.0))),
+        }
+    }
+}
+
+fn keep_count(len: usize, keep: f64) -> usize {
+    (len as f64 * keep).floor() as usize
+}
+
+/// Wraps a cache or fetcher and injects the faults configured in its [`ChaosLayer`]
+#[derive(Debug, Clone)]
+pub struct Chaos<T> {
+    inner: T,
+    layer: ChaosLayer,
+}
+
+impl<T> Chaos<T> {
+    pub fn new(inner: T, layer: ChaosLayer) -> Self {
+        Self { inner, layer }
+    }
+
+    pub fn layer(&self) -> &ChaosLayer {
+        &self.layer
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Deref for Chaos<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<T: CacheExt + Send + Sync> CacheExt for Chaos<T> {
+    async fn get_or_insert_with<F>(
+        &self,
+        key: &str,
+        f: F,
+        expire: Option<u64>,
+    ) -> Result<CacheEntry, IntegrationOSError>
+    where
+        F: FnOnce() -> Result<CacheEntry, IntegrationOSError> + Send,
+    {
+        self.layer
+            .inject(ChaosTarget::Cache, "get_or_insert_with", Some(key))
+            .await?;
+        self.inner.get_or_insert_with(key, f, expire).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError> {
+        // A partial read of a single entry is a miss
+        match self
+            .layer
+            .inject(ChaosTarget::Cache, "get", Some(key))
+            .await?
+        {
+            Some(keep) if keep < 1.0 => Ok(None),
+            _ => self.inner.get(key).await,
+        }
+    }
+
+    async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "set", Some(entry.key()))
+            .await?;
+        self.inner.set(entry, expire).await
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "remove", Some(key))
+            .await?;
+        self.inner.remove(key).await
+    }
+
+    async fn clear(&self) -> Result<(), IntegrationOSError> {
+        self.layer.inject(ChaosTarget::Cache, "clear", None).await?;
+        self.inner.clear().await
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "expire", Some(key))
+            .await?;
+        self.inner.expire(key, seconds).await
+    }
+
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "list_push", Some(key))
+            .await?;
+        self.inner.list_push(key, value).await
+    }
+
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "list_position", Some(key))
+            .await?;
+        self.inner.list_position(key, value).await
+    }
+
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "list_len", Some(key))
+            .await?;
+        self.inner.list_len(key).await
+    }
+
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "list_move", Some(source))
+            .await?;
+        self.inner.list_move(source, destination).await
+    }
+
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError> {
+        self.layer
+            .inject(ChaosTarget::Cache, "list_remove", Some(key))
+            .await?;
+        self.inner.list_remove(key, value).await
+    }
+}
+
+#[async_trait]
+impl<T: FecherExt + Send + Sync> FecherExt for Chaos<T> {
+    async fn get_token(&self, url: &str) -> anyhow::Result<String> {
+        self.layer
+            .inject(ChaosTarget::Fetcher, "get_token", Some(url))
+            .await?;
+        self.inner.get_token(url).await
+    }
+}
+
+/// [`MongoStore`] wrapper injecting faults on the common read and write paths. Other
+/// operations are reachable through `Deref` and run without faults.
+#[derive(Debug, Clone)]
+pub struct ChaosStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
+    inner: MongoStore<T>,
+    layer: ChaosLayer,
+}
+
+impl<T: Serialize + DeserializeOwned + Unpin + Sync> Deref for ChaosStore<T> {
+    type Target = MongoStore<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> ChaosStore<T> {
+    pub fn new(inner: MongoStore<T>, layer: ChaosLayer) -> Self {
+        Self { inner, layer }
+    }
+
+    async fn inject(
+        &self,
+        operation: &str,
+        key: Option<&str>,
+    ) -> Result<Option<f64>, IntegrationOSError> {
+        self.layer.inject(ChaosTarget::Store, operation, key).await
+    }
+
+    fn collection_name(&self) -> String {
+        self.inner.collection.name().to_owned()
+    }
+
+    pub async fn get_one(&self, filter: Document) -> Result<Option<T>, IntegrationOSError> {
+        match self
+            .inject("get_one", Some(&self.collection_name()))
+            .await?
+        {
+            Some(keep) if keep < 1.0 => Ok(None),
+            _ => self.inner.get_one(filter).await,
+        }
+    }
+
+    pub async fn get_one_by_id(&self, id: &str) -> Result<Option<T>, IntegrationOSError> {
+        match self.inject("get_one_by_id", Some(id)).await? {
+            Some(keep) if keep < 1.0 => Ok(None),
+            _ => self.inner.get_one_by_id(id).await,
+        }
+    }
+
+    pub async fn get_many(
+        &self,
+        filter: Option<Document>,
+        selection: Option<Document>,
+        sort: Option<Document>,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        let keep = self
+            .inject("get_many", Some(&self.collection_name()))
+            .await?;
+        let mut records = self
+            .inner
+            .get_many(filter, selection, sort, limit, skip)
+            .await?;
+        if let Some(keep) = keep {
+            records.truncate(keep_count(records.len(), keep));
+        }
+        Ok(records)
+    }
+
+    pub async fn create_one(&self, data: &T) -> Result<(), IntegrationOSError> {
+        self.inject("create_one", Some(&self.collection_name()))
+            .await?;
+        self.inner.create_one(data).await
+    }
+
+    pub async fn create_many(&self, data: &[T]) -> Result<(), IntegrationOSError> {
+        match self
+            .inject("create_many", Some(&self.collection_name()))
+            .await?
+        {
+            Some(keep) if keep < 1.0 => {
+                let written = keep_count(data.len(), keep);
+                if written > 0 {
+                    self.inner.create_many(&data[..written]).await?;
+                }
+                Err(InternalError::io_err(
+                    &format!("Injected partial write of {written}/{}", data.len()),
+                    Some("chaos"),
+                ))
+            }
+            _ => self.inner.create_many(data).await,
+        }
+    }
+
+    pub async fn update_one(&self, id: &str, data: Document) -> Result<(), IntegrationOSError> {
+        self.inject("update_one", Some(id)).await?;
+        self.inner.update_one(id, data).await
+    }
+
+    pub async fn update_many(
+        &self,
+        filter: Document,
+        data: Document,
+    ) -> Result<(), IntegrationOSError> {
+        self.inject("update_many", Some(&self.collection_name()))
+            .await?;
+        self.inner.update_many(filter, data).await
+    }
+
+    pub async fn count(
+        &self,
+        filter: Document,
+        limit: Option<u64>,
+    ) -> Result<u64, IntegrationOSError> {
+        self.inject("count", Some(&self.collection_name())).await?;
+        self.inner.count(filter, limit).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct StaticToken;
+
+    #[async_trait]
+    impl FecherExt for StaticToken {
+        async fn get_token(&self, _: &str) -> anyhow::Result<String> {
+            Ok("Bearer token".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_targeted_faults() {
+        let layer = ChaosLayer::with_seed(42);
+        layer.add_rule(ChaosRule::new(ChaosTarget::Fetcher, ChaosFault::Error).key("flaky"));
+        let fetcher = Chaos::new(StaticToken, layer.clone());
+
+        assert!(fetcher.get_token("stable").await.is_ok());
+        assert!(fetcher.get_token("flaky").await.is_err());
+
+        layer.clear();
+        assert!(fetcher.get_token("flaky").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_probability_and_partial_faults() {
+        let layer = ChaosLayer::with_seed(7);
+        layer.add_rule(
+            ChaosRule::new(ChaosTarget::Store, ChaosFault::Error)
+                .operation("count")
+                .probability(0.5),
+        );
+        let failures = (0..1000)
+            .filter(|_| layer.roll(ChaosTarget::Store, "count", None).is_some())
+            .count();
+        assert!((400..600).contains(&failures));
+        assert!(layer.roll(ChaosTarget::Cache, "count", None).is_none());
+
+        let layer = ChaosLayer::new();
+        layer.add_rule(ChaosRule::new(
+            ChaosTarget::Store,
+            ChaosFault::Partial { keep: 0.5 },
+        ));
+        let keep = layer
+            .inject(ChaosTarget::Store, "get_many", None)
+            .await
+            .unwrap();
+        assert_eq!(keep, Some(0.5));
+        assert_eq!(keep_count(5, 0.5), 2);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    prelude::connection::{connection_status::ConnectionStatus, Connection},
+    ApplicationError, IntegrationOSError, MongoStore,
+};
+use async_trait::async_trait;
+use bson::doc;
+
+/// Status changes of stored connections, see [`ConnectionStatus`] for the allowed ones.
+/// The connection is only replaced if its version didn't change since it was read, so
+/// concurrent changes fail with a conflict instead of overwriting each other.
+#[async_trait]
+pub trait ConnectionLifecycleExt {
+    async fn transition_connection(
+        &self,
+        id: &str,
+        to: ConnectionStatus,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<Connection, IntegrationOSError>;
+
+    /// Stops calls to the platform until the connection is activated again
+    async fn pause_connection(
+        &self,
+        id: &str,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<Connection, IntegrationOSError> {
+        self.transition_connection(id, ConnectionStatus::Paused, actor, reason)
+            .await
+    }
+
+    /// Stops calls to the platform for good
+    async fn revoke_connection(
+        &self,
+        id: &str,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<Connection, IntegrationOSError> {
+        self.transition_connection(id, ConnectionStatus::Revoked, actor, reason)
+            .await
+    }
+
+    /// Activates a draft or resumes a paused connection
+    async fn activate_connection(
+        &self,
+        id: &str,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<Connection, IntegrationOSError> {
+        self.transition_connection(id, ConnectionStatus::Active, actor, reason)
+            .await
+    }
+}
+
+#[async_trait]
+impl ConnectionLifecycleExt for MongoStore<Connection> {
+    async fn transition_connection(
+        &self,
+        id: &str,
+        to: ConnectionStatus,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<Connection, IntegrationOSError> {
+        let mut connection = self.get_one_by_id(id).await?.ok_or_else(|| {
+            ApplicationError::connection_not_found(&format!("No connection {id}"))
+        })?;
+        let version = connection.record_metadata.version.to_string();
+        connection.transition(to, actor, reason)?;
+
+        let result = self
+            .collection
+            .replace_one(doc! { "_id": id, "version": version }, &connection, None)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ApplicationError::conflict(
+                &format!("Connection {id} changed concurrently"),
+                Some("connection"),
+            ));
+        }
+        Ok(connection)
+    }
+}
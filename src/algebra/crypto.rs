@@ -13,6 +13,17 @@ pub trait CryptoExt<R = CreateSecretResponse, A = GetSecretRequest> {
         value: &serde_json::Value,
     ) -> Result<R, IntegrationOSError>;
     async fn decrypt(&self, secret: &A) -> Result<serde_json::Value, IntegrationOSError>;
+
+    /// Re-encrypts a secret under `key`, returning the new secret. The old one is left in
+    /// place so it can still be read until its owner points to the new one.
+    async fn rotate(&self, secret: &A, key: String) -> Result<R, IntegrationOSError>
+    where
+        A: Sync,
+        Self: Sync,
+    {
+        let value = self.decrypt(secret).await?;
+        self.encrypt(key, &value).await
+    }
 }
 
 #[async_trait]
@@ -36,3 +47,31 @@ impl CryptoExt for SecretsClient {
         })
     }
 }
+
+#[cfg(all(test, feature = "testkit"))]
+mod test {
+    use super::*;
+    use crate::testkit::secrets::MockSecrets;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_rotate_re_encrypts_under_new_key() {
+        let secrets = MockSecrets::new();
+        let value = json!({ "apiKey": "sk_test" });
+        secrets.insert("old", "old-key", value.clone());
+
+        let old = GetSecretRequest {
+            id: "old".to_owned(),
+            buildable_id: "old-key".to_owned(),
+        };
+        let rotated = secrets.rotate(&old, "new-key".to_owned()).await.unwrap();
+        assert_eq!(rotated.buildable_id, "new-key");
+
+        let new = GetSecretRequest {
+            id: rotated.id,
+            buildable_id: "new-key".to_owned(),
+        };
+        assert_eq!(secrets.decrypt(&new).await.unwrap(), value);
+        assert_eq!(secrets.decrypt(&old).await.unwrap(), value);
+    }
+}
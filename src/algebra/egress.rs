@@ -0,0 +1,409 @@
+use crate::{egress::EgressConfig, ApplicationError, IntegrationOSError, InternalError};
+use futures::future::join_all;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Url,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+
+/// Hostnames of cloud instance metadata services, blocked whatever they resolve to
+const METADATA_HOSTS: &[&str] = &[
+    "metadata",
+    "metadata.google.internal",
+    "metadata.azure.internal",
+    "instance-data",
+];
+
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn parse(cidr: &str) -> Result<Self, IntegrationOSError> {
+        let invalid = || {
+            InternalError::configuration_error(
+                &format!("Invalid network {cidr}"),
+                Some("egress_guard"),
+            )
+        };
+        let (address, prefix) = match cidr.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (cidr, None),
+        };
+        let address: IpAddr = address.trim().parse().map_err(|_| invalid())?;
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { address, prefix })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn is_blocked_v4(ip: &Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // Carrier grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved, 240.0.0.0/4
+        || a >= 240
+        || a == 0
+}
+
+fn is_blocked_v6(ip: &Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_blocked_v4(&v4);
+    }
+    let segments = ip.segments();
+    let first = segments[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // IPv4 compatible, ::a.b.c.d
+        || segments[..6] == [0; 6]
+        // NAT64, 64:ff9b::/96
+        || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+        // 6to4, 2002::/16
+        || first == 0x2002
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Whether the address is private, loopback, link local (which covers the
+/// `169.254.169.254` metadata endpoint) or otherwise not on the public internet
+pub fn is_blocked_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_blocked_v4(ip),
+        IpAddr::V6(ip) => is_blocked_v6(ip),
+    }
+}
+
+/// Validates user supplied URLs (webhook endpoints, custom base URLs) before they're
+/// called, so they can't be used to reach internal services
+#[derive(Debug, Clone, Default)]
+pub struct EgressGuard {
+    allowed_hosts: Vec<String>,
+    allowed_networks: Vec<IpNetwork>,
+}
+
+impl EgressGuard {
+    pub fn new(config: &EgressConfig) -> Result<Self, IntegrationOSError> {
+        Ok(Self {
+            allowed_hosts: config
+                .allowed_hosts()
+                .map(str::to_ascii_lowercase)
+                .collect(),
+            allowed_networks: config
+                .allowed_networks()
+                .map(IpNetwork::parse)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn rejected(url: &str, reason: &str) -> IntegrationOSError {
+        ApplicationError::forbidden(&format!("{url} is not allowed: {reason}"), Some("egress"))
+    }
+
+    pub fn is_allowed_ip(&self, ip: &IpAddr) -> bool {
+        !is_blocked_ip(ip) || self.allowed_networks.iter().any(|n| n.contains(ip))
+    }
+
+    /// Checks that don't need a DNS lookup: the scheme, and the host when it's an IP
+    fn target(&self, url: &Url) -> Result<(String, u16), IntegrationOSError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Self::rejected(
+                url.as_str(),
+                "only http and https are supported",
+            ));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| Self::rejected(url.as_str(), "missing host"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| Self::rejected(url.as_str(), "missing port"))?;
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if !self.allowed_hosts.contains(&host) && !self.is_allowed_ip(&ip) {
+                return Err(Self::rejected(
+                    url.as_str(),
+                    &format!("{ip} is a blocked address"),
+                ));
+            }
+        }
+        Ok((host, port))
+    }
+
+    /// Addresses of `host`, unless it's a metadata endpoint or resolves to a blocked
+    /// address. Allowed hosts skip the checks.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+        let allowed = self.allowed_hosts.iter().any(|allowed| allowed == host);
+        if !allowed && METADATA_HOSTS.contains(&host) {
+            return Err("metadata endpoints are blocked".to_owned());
+        }
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("could not resolve host: {e}"))?
+            .collect();
+        if allowed {
+            return Ok(addresses);
+        }
+        if addresses.is_empty() {
+            return Err("host did not resolve".to_owned());
+        }
+        if let Some(blocked) = addresses.iter().find(|a| !self.is_allowed_ip(&a.ip())) {
+            return Err(format!("resolves to blocked address {}", blocked.ip()));
+        }
+        Ok(addresses)
+    }
+
+    /// Resolves the URL's host and checks every address it resolves to, returning them
+    /// so the caller can pin the connection to the validated addresses
+    pub async fn validate(&self, url: &str) -> Result<Vec<SocketAddr>, IntegrationOSError> {
+        let parsed = Url::parse(url).map_err(|e| Self::rejected(url, &e.to_string()))?;
+        let (host, port) = self.target(&parsed)?;
+        self.resolve(&host, port)
+            .await
+            .map_err(|reason| Self::rejected(url, &reason))
+    }
+
+    /// Checks that can be made without a DNS lookup, for requests sent with a client
+    /// from [`EgressGuard::client`], which checks hostnames when it connects
+    pub fn check(&self, url: &str) -> Result<(), IntegrationOSError> {
+        let parsed = Url::parse(url).map_err(|e| Self::rejected(url, &e.to_string()))?;
+        self.target(&parsed).map(|_| ())
+    }
+
+    /// Validates many URLs concurrently, in the order given
+    pub async fn validate_all(
+        &self,
+        urls: &[&str],
+    ) -> Vec<Result<Vec<SocketAddr>, IntegrationOSError>> {
+        join_all(urls.iter().map(|url| self.validate(url))).await
+    }
+
+    /// HTTP client builder whose DNS lookups go through the guard, and which only
+    /// follows redirects to allowed targets
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        let guard = self.clone();
+        reqwest::Client::builder()
+            .dns_resolver(Arc::new(GuardedResolver(self.clone())))
+            .redirect(Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match guard.target(attempt.url()) {
+                    Ok(_) => attempt.follow(),
+                    Err(e) => attempt.error(e.to_string()),
+                }
+            }))
+    }
+
+    /// HTTP client built from [`EgressGuard::client_builder`], meant to be built once and
+    /// reused, with every URL passed to [`EgressGuard::check`] before it's requested
+    pub fn client(&self) -> Result<reqwest::Client, IntegrationOSError> {
+        self.client_builder()
+            .build()
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("egress_guard")))
+    }
+
+    /// HTTP client that only connects to the addresses validated for `url`, so a second
+    /// DNS lookup can't rebind the host to an internal address
+    pub async fn client_for(&self, url: &str) -> Result<reqwest::Client, IntegrationOSError> {
+        let addresses = self.validate(url).await?;
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned))
+            .unwrap_or_default();
+        self.client_builder()
+            .resolve_to_addrs(&host, &addresses)
+            .build()
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("egress_guard")))
+    }
+}
+
+/// Resolver of the guarded clients, so that hosts reached through redirects are
+/// checked like the requested one
+struct GuardedResolver(EgressGuard);
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.0.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_ascii_lowercase();
+            let addresses = guard.resolve(&host, 0).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blocked_ips() {
+        for ip in [
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b::808:808",
+            "2002:a9fe:a9fe::1",
+            "::127.0.0.1",
+            "::a9fe:a9fe",
+            "198.18.0.1",
+            "198.19.255.254",
+            "240.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(is_blocked_ip(&ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "8.8.8.8",
+            "2606:4700:4700::1111",
+            "172.32.0.1",
+            "198.20.0.1",
+            "198.17.255.255",
+        ] {
+            assert!(!is_blocked_ip(&ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_egress_guard() {
+        let guard = EgressGuard::default();
+        assert!(guard.validate("http://127.0.0.1:8080/hook").await.is_err());
+        assert!(guard.validate("http://[::1]/hook").await.is_err());
+        assert!(guard
+            .validate("http://metadata.google.internal/computeMetadata")
+            .await
+            .is_err());
+        assert!(guard.validate("file:///etc/passwd").await.is_err());
+        assert_eq!(
+            guard
+                .validate_all(&["http://10.0.0.1", "ftp://x"])
+                .await
+                .len(),
+            2
+        );
+
+        let guard = EgressGuard::new(&EgressConfig {
+            allowed_networks: "10.0.0.0/8, 127.0.0.1".to_owned(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(guard.validate("http://10.1.2.3:8080").await.is_ok());
+        assert!(guard.validate("http://127.0.0.1").await.is_ok());
+        assert!(guard.validate("http://192.168.0.1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_guarded_client_checks_redirects() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/internal")
+            .with_status(302)
+            .with_header("location", "http://10.0.0.1/admin")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/metadata")
+            .with_status(302)
+            .with_header("location", "http://metadata/computeMetadata/v1")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/moved")
+            .with_status(301)
+            .with_header("location", "/hook")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/hook")
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let guard = EgressGuard::new(&EgressConfig {
+            allowed_networks: "127.0.0.1".to_owned(),
+            ..Default::default()
+        })
+        .unwrap();
+        let url = server.url();
+        for client in [
+            guard.client_for(&url).await.unwrap(),
+            guard.client().unwrap(),
+        ] {
+            assert!(client.get(format!("{url}/internal")).send().await.is_err());
+            assert!(client.get(format!("{url}/metadata")).send().await.is_err());
+            let response = client.get(format!("{url}/moved")).send().await.unwrap();
+            assert_eq!(response.status(), 200);
+        }
+
+        // Hosts are checked when the shared client resolves them
+        let port = url.rsplit(':').next().unwrap();
+        let client = EgressGuard::default().client().unwrap();
+        assert!(client
+            .get(format!("http://localhost:{port}/hook"))
+            .send()
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_ip_network() {
+        let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains(&"10.255.0.1".parse().unwrap()));
+        assert!(!network.contains(&"11.0.0.1".parse().unwrap()));
+        assert!(IpNetwork::parse("10.0.0.0/33").is_err());
+        assert!(IpNetwork::parse("::/0")
+            .unwrap()
+            .contains(&"2001:db8::1".parse().unwrap()));
+    }
+}
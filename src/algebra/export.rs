@@ -0,0 +1,309 @@
+use crate::{
+    prelude::connection::{
+        connection_definition::ConnectionDefinition,
+        connection_model_definition::ConnectionModelDefinition,
+        connection_oauth_definition::ConnectionOAuthDefinition,
+    },
+    store::Store,
+    BulkItemOutcome, BulkWriteReport, IntegrationOSError, InternalError, MongoStore, Validate,
+    ValidationError,
+};
+use async_trait::async_trait;
+use bson::{doc, Document};
+use chrono::Utc;
+use mongodb::options::ReplaceOptions;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Version of the export layout itself, independent of the records' schema version
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+const SUBTYPE: &str = "export";
+
+/// First line of an export, the records following one per line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportHeader {
+    pub format_version: u32,
+    pub collection: String,
+    pub schema_version: u32,
+    pub exported_at: i64,
+    pub count: usize,
+}
+
+/// Records that can be moved between deployments with [`ExportExt`]
+pub trait Exportable: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static {
+    const STORE: Store;
+    /// Bumped when the records change in a way older deployments can't read
+    const SCHEMA_VERSION: u32 = 1;
+
+    /// Problems that keep the record from being imported
+    fn import_errors(&self) -> Vec<ValidationError> {
+        Vec::new()
+    }
+}
+
+impl Exportable for ConnectionDefinition {
+    const STORE: Store = Store::ConnectionDefinitions;
+}
+
+impl Exportable for ConnectionModelDefinition {
+    const STORE: Store = Store::ConnectionModelDefinitions;
+
+    fn import_errors(&self) -> Vec<ValidationError> {
+        self.validate()
+    }
+}
+
+impl Exportable for ConnectionOAuthDefinition {
+    const STORE: Store = Store::ConnectionOAuthDefinitions;
+}
+
+/// Rewrites applied to records on import, e.g. to seed staging with production data
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRemap {
+    /// Ids replaced wherever they appear, as the record's own id or as a reference
+    #[serde(default)]
+    pub ids: BTreeMap<String, String>,
+    /// URL prefixes replaced in every string starting with them, e.g. a production API
+    /// host by its sandbox one
+    #[serde(default)]
+    pub urls: BTreeMap<String, String>,
+}
+
+impl ImportRemap {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.urls.is_empty()
+    }
+
+    pub fn apply(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => {
+                if let Some(id) = self.ids.get(s.as_str()) {
+                    *s = id.clone();
+                } else if let Some((from, to)) = self
+                    .urls
+                    .iter()
+                    .rev()
+                    .find(|(from, _)| s.starts_with(from.as_str()))
+                {
+                    *s = format!("{to}{}", &s[from.len()..]);
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| self.apply(value)),
+            Value::Object(fields) => fields.values_mut().for_each(|value| self.apply(value)),
+            _ => {}
+        }
+    }
+}
+
+fn to_line<S: Serialize>(value: &S) -> Result<String, IntegrationOSError> {
+    serde_json::to_string(value)
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), Some(SUBTYPE)))
+}
+
+/// Header line followed by one line per record
+pub fn write_ndjson<T: Exportable>(records: &[T]) -> Result<String, IntegrationOSError> {
+    let header = ExportHeader {
+        format_version: EXPORT_FORMAT_VERSION,
+        collection: T::STORE.to_string(),
+        schema_version: T::SCHEMA_VERSION,
+        exported_at: Utc::now().timestamp_millis(),
+        count: records.len(),
+    };
+    let mut out = to_line(&header)?;
+    out.push('\n');
+    for record in records {
+        out.push_str(&to_line(record)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Records of an export after `remap`, failing unless the header matches `T` and every
+/// record is valid
+pub fn read_ndjson<T: Exportable>(
+    data: &str,
+    remap: &ImportRemap,
+) -> Result<(ExportHeader, Vec<T>), IntegrationOSError> {
+    let mut lines = data
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let header: ExportHeader = match lines.next() {
+        Some((_, line)) => serde_json::from_str(line).map_err(|e| {
+            InternalError::invalid_argument(&format!("Invalid export header: {e}"), Some(SUBTYPE))
+        })?,
+        None => {
+            return Err(InternalError::invalid_argument(
+                "Empty export",
+                Some(SUBTYPE),
+            ))
+        }
+    };
+    let collection = T::STORE.to_string();
+    if header.format_version != EXPORT_FORMAT_VERSION
+        || header.collection != collection
+        || header.schema_version > T::SCHEMA_VERSION
+    {
+        return Err(InternalError::invalid_argument(
+            &format!(
+                "Export of {} schema version {} (format {}) can't be imported into {collection} schema version {}",
+                header.collection,
+                header.schema_version,
+                header.format_version,
+                T::SCHEMA_VERSION
+            ),
+            Some(SUBTYPE),
+        ));
+    }
+
+    let mut records = Vec::with_capacity(header.count);
+    let mut problems = Vec::new();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let record = serde_json::from_str::<Value>(line).and_then(|mut value| {
+            remap.apply(&mut value);
+            serde_json::from_value::<T>(value)
+        });
+        match record {
+            Ok(record) => {
+                problems.extend(
+                    record
+                        .import_errors()
+                        .into_iter()
+                        .map(|error| format!("line {line_number}: {error}")),
+                );
+                records.push(record);
+            }
+            Err(e) => problems.push(format!("line {line_number}: {e}")),
+        }
+    }
+    if records.len() + problems.len() < header.count {
+        problems.push(format!(
+            "expected {} records, found {}",
+            header.count,
+            records.len()
+        ));
+    }
+    if !problems.is_empty() {
+        return Err(InternalError::invalid_argument(
+            &problems.join("; "),
+            Some(SUBTYPE),
+        ));
+    }
+    Ok((header, records))
+}
+
+/// Point-in-time dump and restore of a store as newline-delimited JSON. Imports upsert
+/// by id, so importing the same export twice leaves the store unchanged.
+#[async_trait]
+pub trait ExportExt<T: Exportable> {
+    async fn export(&self, filter: Option<Document>) -> Result<String, IntegrationOSError>;
+
+    /// Validates every record before writing any of them
+    async fn import(
+        &self,
+        data: &str,
+        remap: &ImportRemap,
+    ) -> Result<BulkWriteReport, IntegrationOSError>;
+}
+
+#[async_trait]
+impl<T: Exportable> ExportExt<T> for MongoStore<T> {
+    async fn export(&self, filter: Option<Document>) -> Result<String, IntegrationOSError> {
+        let records = self
+            .get_many(filter, None, Some(doc! { "_id": 1 }), None, None)
+            .await?;
+        write_ndjson(&records)
+    }
+
+    async fn import(
+        &self,
+        data: &str,
+        remap: &ImportRemap,
+    ) -> Result<BulkWriteReport, IntegrationOSError> {
+        let (_, records) = read_ndjson::<T>(data, remap)?;
+        let options = ReplaceOptions::builder().upsert(true).build();
+        let mut results = Vec::with_capacity(records.len());
+        for record in &records {
+            let id = bson::to_document(record)
+                .map_err(|e| InternalError::serialize_error(&e.to_string(), Some(SUBTYPE)))?
+                .get("_id")
+                .cloned()
+                .ok_or_else(|| {
+                    InternalError::invalid_argument("Record without _id", Some(SUBTYPE))
+                })?;
+            let outcome = match self
+                .collection
+                .replace_one(doc! { "_id": id }, record, options.clone())
+                .await
+            {
+                Ok(result) if result.upserted_id.is_some() => BulkItemOutcome::Inserted,
+                Ok(_) => BulkItemOutcome::Updated,
+                Err(e) => BulkItemOutcome::Failed {
+                    code: 0,
+                    message: e.to_string(),
+                },
+            };
+            results.push(outcome);
+        }
+        Ok(BulkWriteReport { results })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(rename = "_id")]
+        id: String,
+        definition_id: String,
+        base_url: String,
+    }
+
+    impl Exportable for Record {
+        const STORE: Store = Store::ConnectionDefinitions;
+        const SCHEMA_VERSION: u32 = 2;
+    }
+
+    #[test]
+    fn test_ndjson_round_trip_with_remap() {
+        let records = vec![Record {
+            id: "conn_def::a".to_owned(),
+            definition_id: "conn_def::b".to_owned(),
+            base_url: "https://api.example.com/v1".to_owned(),
+        }];
+        let data = write_ndjson(&records).unwrap();
+        assert_eq!(data.lines().count(), 2);
+
+        let (header, read) = read_ndjson::<Record>(&data, &ImportRemap::default()).unwrap();
+        assert_eq!(header.collection, "connection-definitions");
+        assert_eq!(header.schema_version, 2);
+        assert_eq!(read, records);
+
+        let remap = ImportRemap {
+            ids: BTreeMap::from([("conn_def::b".to_owned(), "conn_def::c".to_owned())]),
+            urls: BTreeMap::from([(
+                "https://api.example.com".to_owned(),
+                "https://sandbox.example.com".to_owned(),
+            )]),
+        };
+        let (_, read) = read_ndjson::<Record>(&data, &remap).unwrap();
+        assert_eq!(read[0].id, "conn_def::a");
+        assert_eq!(read[0].definition_id, "conn_def::c");
+        assert_eq!(read[0].base_url, "https://sandbox.example.com/v1");
+
+        let newer = data.replacen("\"schemaVersion\":2", "\"schemaVersion\":3", 1);
+        assert!(read_ndjson::<Record>(&newer, &remap).is_err());
+        let truncated = data.lines().next().unwrap().to_owned();
+        assert!(read_ndjson::<Record>(&truncated, &remap).is_err());
+        let invalid = format!("{truncated}\n{}", json!({ "_id": "conn_def::a" }));
+        assert!(read_ndjson::<Record>(&invalid, &remap).is_err());
+    }
+}
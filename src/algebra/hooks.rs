@@ -0,0 +1,197 @@
+use crate::{IntegrationOSError, InternalError, MongoStore};
+use async_trait::async_trait;
+use bson::{doc, Document};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+
+/// Invariants a model enforces on itself around persistence, e.g. normalizing keys,
+/// stripping transient fields or recomputing hashes. Both hooks default to doing nothing.
+pub trait ModelHooks {
+    fn before_save(&mut self) -> Result<(), IntegrationOSError> {
+        Ok(())
+    }
+
+    fn after_load(&mut self) -> Result<(), IntegrationOSError> {
+        Ok(())
+    }
+}
+
+/// Side effects of a write that live outside the model, e.g. updating a search index
+#[async_trait]
+pub trait SaveListener<T>: Send + Sync {
+    async fn after_save(&self, record: &T) -> Result<(), IntegrationOSError>;
+
+    async fn after_delete(&self, _id: &str) -> Result<(), IntegrationOSError> {
+        Ok(())
+    }
+}
+
+/// [`MongoStore`] running the [`ModelHooks`] of its records on every read and write and
+/// notifying its [`SaveListener`]s once a write went through
+#[derive(Clone)]
+pub struct HookedStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
+    pub store: MongoStore<T>,
+    listeners: Vec<Arc<dyn SaveListener<T>>>,
+}
+
+pub(crate) fn hook_before_save<T: ModelHooks>(mut record: T) -> Result<T, IntegrationOSError> {
+    record.before_save()?;
+    Ok(record)
+}
+
+pub(crate) fn hook_after_load<T: ModelHooks>(mut record: T) -> Result<T, IntegrationOSError> {
+    record.after_load()?;
+    Ok(record)
+}
+
+impl<T> HookedStore<T>
+where
+    T: ModelHooks + Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    pub fn new(store: MongoStore<T>) -> Self {
+        Self {
+            store,
+            listeners: vec![],
+        }
+    }
+
+    pub fn with_listener(mut self, listener: Arc<dyn SaveListener<T>>) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    async fn saved(&self, records: &[T]) -> Result<(), IntegrationOSError> {
+        for listener in &self.listeners {
+            for record in records {
+                listener.after_save(record).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the record and returns it as written
+    pub async fn create_one(&self, record: T) -> Result<T, IntegrationOSError> {
+        let record = hook_before_save(record)?;
+        self.store.create_one(&record).await?;
+        self.saved(std::slice::from_ref(&record)).await?;
+        Ok(record)
+    }
+
+    pub async fn create_many(&self, records: Vec<T>) -> Result<Vec<T>, IntegrationOSError> {
+        let records = records
+            .into_iter()
+            .map(hook_before_save)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.store.create_many(&records).await?;
+        self.saved(&records).await?;
+        Ok(records)
+    }
+
+    pub async fn replace_one(&self, id: &str, record: T) -> Result<T, IntegrationOSError> {
+        let record = hook_before_save(record)?;
+        let result = self
+            .store
+            .collection
+            .replace_one(doc! { "_id": id }, &record, None)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(InternalError::key_not_found(
+                &format!("Record {id} not found"),
+                Some("hooked_store"),
+            ));
+        }
+        self.saved(std::slice::from_ref(&record)).await?;
+        Ok(record)
+    }
+
+    pub async fn delete_one(&self, id: &str) -> Result<(), IntegrationOSError> {
+        self.store
+            .collection
+            .delete_one(doc! { "_id": id }, None)
+            .await?;
+        for listener in &self.listeners {
+            listener.after_delete(id).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_one(&self, filter: Document) -> Result<Option<T>, IntegrationOSError> {
+        self.store
+            .get_one(filter)
+            .await?
+            .map(hook_after_load)
+            .transpose()
+    }
+
+    pub async fn get_one_by_id(&self, id: &str) -> Result<Option<T>, IntegrationOSError> {
+        self.store
+            .get_one_by_id(id)
+            .await?
+            .map(hook_after_load)
+            .transpose()
+    }
+
+    pub async fn get_many(
+        &self,
+        filter: Option<Document>,
+        sort: Option<Document>,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        self.store
+            .get_many(filter, None, sort, limit, skip)
+            .await?
+            .into_iter()
+            .map(hook_after_load)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Tag {
+        key: String,
+        transient: Option<String>,
+        loaded: bool,
+    }
+
+    impl ModelHooks for Tag {
+        fn before_save(&mut self) -> Result<(), IntegrationOSError> {
+            if self.key.trim().is_empty() {
+                return Err(InternalError::invalid_argument(
+                    "Key is required",
+                    Some("tag"),
+                ));
+            }
+            self.key = self.key.trim().to_lowercase();
+            self.transient = None;
+            Ok(())
+        }
+
+        fn after_load(&mut self) -> Result<(), IntegrationOSError> {
+            self.loaded = true;
+            Ok(())
+        }
+    }
+
+    fn tag(key: &str) -> Tag {
+        Tag {
+            key: key.to_owned(),
+            transient: Some("cached".to_owned()),
+            loaded: false,
+        }
+    }
+
+    #[test]
+    fn test_hooks_run_around_persistence() {
+        let saved = hook_before_save(tag(" Stripe-Live ")).unwrap();
+        assert_eq!(saved.key, "stripe-live");
+        assert_eq!(saved.transient, None);
+        assert!(hook_after_load(saved).unwrap().loaded);
+
+        assert!(hook_before_save(tag("  ")).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    prelude::shared::settings::Settings, CacheEntry, CacheExt, Event, Id, InMemoryCache,
+    IntegrationOSError, InternalError, RedisCache,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Outcome of [`IdempotencyGuard::check_and_set`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyCheck {
+    /// First time the key is seen, the event should be processed
+    New,
+    /// The key was already used by this event, which should be returned instead
+    Duplicate(Id),
+}
+
+/// Atomically stores a value under a key unless one is there already
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Stores `value`, returning the value already stored under `key` if any
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>, IntegrationOSError>;
+}
+
+#[async_trait]
+impl IdempotencyStore for RedisCache {
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>, IntegrationOSError> {
+        let mut conn = self.clone();
+        // Sets and reads in one script so a concurrent first request cannot be missed
+        redis::Script::new(
+            r"
+            if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'EX', ARGV[2]) then
+                return false
+            end
+            return redis.call('GET', KEYS[1])
+            ",
+        )
+        .key(key)
+        .arg(value)
+        .arg(ttl_secs.max(1))
+        .invoke_async(&mut conn)
+        .await
+        .map_err(|e| InternalError::io_err(&e.to_string(), Some("idempotency")))
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryCache {
+    async fn set_if_absent(
+        &self,
+        key: &str,
+        value: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>, IntegrationOSError> {
+        if let Some(existing) = self.get(key).await? {
+            return Ok(existing.value().as_str().map(str::to_owned));
+        }
+        self.set(
+            CacheEntry::new(key.to_owned(), Value::String(value.to_owned())),
+            Some(ttl_secs.max(1)),
+        )
+        .await?;
+        Ok(None)
+    }
+}
+
+/// Detects events a producer sent more than once with the same idempotency key. Keys
+/// are scoped to the event's ownership so producers cannot collide.
+pub struct IdempotencyGuard<S> {
+    store: S,
+}
+
+impl<S: IdempotencyStore> IdempotencyGuard<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Records `event_id` as the event of `idempotency_key` for `ttl_secs`, or returns
+    /// the event that already claimed it
+    pub async fn check_and_set(
+        &self,
+        idempotency_key: &str,
+        event_id: &Id,
+        ttl_secs: u64,
+    ) -> Result<IdempotencyCheck, IntegrationOSError> {
+        let existing = self
+            .store
+            .set_if_absent(
+                &format!("idempotency::{idempotency_key}"),
+                &event_id.to_string(),
+                ttl_secs,
+            )
+            .await?;
+        match existing {
+            None => Ok(IdempotencyCheck::New),
+            Some(existing) if existing == event_id.to_string() => Ok(IdempotencyCheck::New),
+            Some(existing) => Ok(IdempotencyCheck::Duplicate(existing.parse()?)),
+        }
+    }
+
+    /// Same as [`IdempotencyGuard::check_and_set`] with the event's own key. Events
+    /// without one are always new.
+    pub async fn check_event(
+        &self,
+        event: &Event,
+        ttl_secs: u64,
+    ) -> Result<IdempotencyCheck, IntegrationOSError> {
+        match &event.idempotency_key {
+            Some(key) => {
+                let key = format!("{}::{key}", event.ownership.id);
+                self.check_and_set(&key, &event.id, ttl_secs).await
+            }
+            None => Ok(IdempotencyCheck::New),
+        }
+    }
+
+    /// Same as [`IdempotencyGuard::check_event`] for an event of a connection with
+    /// `settings`. Connections with [`Settings::skip_dedup`] always get `New`, without
+    /// the key being recorded.
+    pub async fn check_event_for(
+        &self,
+        event: &Event,
+        settings: &Settings,
+        ttl_secs: u64,
+    ) -> Result<IdempotencyCheck, IntegrationOSError> {
+        if settings.skip_dedup {
+            return Ok(IdempotencyCheck::New);
+        }
+        self.check_event(event, ttl_secs).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        id::prefix::IdPrefix,
+        prelude::{
+            access_key::{
+                access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+                encrypted_access_key::EncryptedAccessKey, event_type::EventType, AccessKey,
+            },
+            configuration::environment::Environment,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_duplicate_keys_return_original_event() {
+        let guard = IdempotencyGuard::new(InMemoryCache::new());
+        let original = Id::now(IdPrefix::Event);
+        let retry = Id::now(IdPrefix::Event);
+
+        assert_eq!(
+            guard.check_and_set("order-1", &original, 60).await.unwrap(),
+            IdempotencyCheck::New
+        );
+        assert_eq!(
+            guard.check_and_set("order-1", &retry, 60).await.unwrap(),
+            IdempotencyCheck::Duplicate(original)
+        );
+        // Checking the original again, e.g. after a crash, does not flag it
+        assert_eq!(
+            guard.check_and_set("order-1", &original, 60).await.unwrap(),
+            IdempotencyCheck::New
+        );
+        assert_eq!(
+            guard.check_and_set("order-2", &retry, 60).await.unwrap(),
+            IdempotencyCheck::New
+        );
+
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "foo".to_owned(),
+                event_type: "bar".to_owned(),
+                group: "baz".to_owned(),
+                namespace: "qux".to_owned(),
+                event_path: "quux".to_owned(),
+                event_object_id_path: None,
+                timestamp_path: None,
+                parent_access_key: None,
+                expires_at: None,
+            },
+        };
+        let mut event = Event::new(
+            &access_key,
+            &EncryptedAccessKey::parse("id_test_1_foo").unwrap(),
+            "order.created",
+            Default::default(),
+            "{}".to_owned(),
+        )
+        .with_idempotency_key("order-3");
+        let skip = Settings {
+            skip_dedup: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            guard.check_event_for(&event, &skip, 60).await.unwrap(),
+            IdempotencyCheck::New
+        );
+        assert_eq!(
+            guard
+                .check_event_for(&event, &Settings::default(), 60)
+                .await
+                .unwrap(),
+            IdempotencyCheck::New
+        );
+        let original = event.id;
+        event.id = Id::now(IdPrefix::Event);
+        assert_eq!(
+            guard.check_event_for(&event, &skip, 60).await.unwrap(),
+            IdempotencyCheck::New
+        );
+        assert_eq!(
+            guard
+                .check_event_for(&event, &Settings::default(), 60)
+                .await
+                .unwrap(),
+            IdempotencyCheck::Duplicate(original)
+        );
+    }
+}
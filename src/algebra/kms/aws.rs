@@ -0,0 +1,151 @@
+use super::KmsProvider;
+use crate::{
+    configuration::kms::AwsKmsConfig, sigv4_authorization, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use base64ct::{Base64, Encoding};
+use chrono::Utc;
+use reqwest::{Client, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+const SERVICE: &str = "kms";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EncryptResponse {
+    ciphertext_blob: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DecryptResponse {
+    plaintext: String,
+}
+
+/// AWS KMS key used through its JSON API, requests being signed with static credentials
+#[derive(Debug, Clone)]
+pub struct AwsKms {
+    config: AwsKmsConfig,
+    client: Client,
+}
+
+impl AwsKms {
+    pub fn new(config: AwsKmsConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        action: &str,
+        body: Value,
+    ) -> Result<T, IntegrationOSError> {
+        let endpoint = self.config.endpoint();
+        let url = Url::parse(&endpoint)
+            .map_err(|e| InternalError::configuration_error(&e.to_string(), Some("aws_kms")))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => {
+                return Err(InternalError::configuration_error(
+                    &format!("Invalid KMS endpoint {endpoint}"),
+                    Some("aws_kms"),
+                ))
+            }
+        };
+
+        let body = serde_json::to_vec(&body)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("aws_kms")))?;
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let target = format!("TrentService.{action}");
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1"),
+            ("host", host.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(token) = self.config.session_token() {
+            headers.push(("x-amz-security-token", token));
+        }
+        headers.push(("x-amz-target", target.as_str()));
+
+        let authorization = sigv4_authorization(
+            "POST",
+            &headers,
+            &body,
+            &self.config.access_key_id,
+            self.config.secret_access_key.expose_secret(),
+            &self.config.region,
+            SERVICE,
+            now,
+        );
+        let mut request = self
+            .client
+            .post(url)
+            .header("authorization", authorization)
+            .body(body);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, *value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("aws_kms")))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(InternalError::encryption_error(
+                &format!("KMS {action} failed with {status}: {message}"),
+                Some("aws_kms"),
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("aws_kms")))
+    }
+}
+
+fn decode(field: &str) -> Result<Vec<u8>, IntegrationOSError> {
+    Base64::decode_vec(field)
+        .map_err(|e| InternalError::decryption_error(&e.to_string(), Some("aws_kms")))
+}
+
+#[async_trait]
+impl KmsProvider for AwsKms {
+    fn key_id(&self) -> &str {
+        &self.config.key_id
+    }
+
+    async fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, IntegrationOSError> {
+        let response: EncryptResponse = self
+            .call(
+                "Encrypt",
+                json!({
+                    "KeyId": self.config.key_id,
+                    "Plaintext": Base64::encode_string(plaintext),
+                    "EncryptionContext": { "aad": Base64::encode_string(aad) },
+                }),
+            )
+            .await?;
+        decode(&response.ciphertext_blob)
+    }
+
+    async fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, IntegrationOSError> {
+        let response: DecryptResponse = self
+            .call(
+                "Decrypt",
+                json!({
+                    "KeyId": self.config.key_id,
+                    "CiphertextBlob": Base64::encode_string(ciphertext),
+                    "EncryptionContext": { "aad": Base64::encode_string(aad) },
+                }),
+            )
+            .await?;
+        decode(&response.plaintext)
+    }
+}
@@ -0,0 +1,143 @@
+use super::KmsProvider;
+use crate::{configuration::kms::GcpKmsConfig, IntegrationOSError, InternalError};
+use async_trait::async_trait;
+use base64ct::{Base64, Encoding};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct EncryptResponse {
+    ciphertext: String,
+}
+
+#[derive(Deserialize)]
+struct DecryptResponse {
+    plaintext: String,
+}
+
+/// Google Cloud KMS key used through its REST API. Access tokens come from the metadata
+/// server, or the configured static token, and are reused until shortly before expiry.
+#[derive(Debug, Clone)]
+pub struct GcpKms {
+    config: GcpKmsConfig,
+    client: Client,
+    token: Arc<Mutex<Option<(String, Instant)>>>,
+}
+
+impl GcpKms {
+    pub fn new(config: GcpKmsConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+            token: Default::default(),
+        }
+    }
+
+    async fn access_token(&self) -> Result<String, IntegrationOSError> {
+        if let Some(token) = self.config.access_token() {
+            return Ok(token.to_owned());
+        }
+        if let Some((token, expires_at)) = self.token.lock().expect("token lock poisoned").clone() {
+            if Instant::now() < expires_at {
+                return Ok(token);
+            }
+        }
+
+        let token: AccessToken = self
+            .client
+            .get(&self.config.token_url)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("gcp_kms")))?
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("gcp_kms")))?;
+        let expires_at = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        *self.token.lock().expect("token lock poisoned") =
+            Some((token.access_token.clone(), expires_at));
+        Ok(token.access_token)
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: Value,
+    ) -> Result<T, IntegrationOSError> {
+        let url = format!(
+            "{}/v1/{}:{method}",
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.key_name
+        );
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(self.access_token().await?)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("gcp_kms")))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(InternalError::encryption_error(
+                &format!("KMS {method} failed with {status}: {message}"),
+                Some("gcp_kms"),
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("gcp_kms")))
+    }
+}
+
+fn decode(field: &str) -> Result<Vec<u8>, IntegrationOSError> {
+    Base64::decode_vec(field)
+        .map_err(|e| InternalError::decryption_error(&e.to_string(), Some("gcp_kms")))
+}
+
+#[async_trait]
+impl KmsProvider for GcpKms {
+    fn key_id(&self) -> &str {
+        &self.config.key_name
+    }
+
+    async fn wrap(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, IntegrationOSError> {
+        let response: EncryptResponse = self
+            .call(
+                "encrypt",
+                json!({
+                    "plaintext": Base64::encode_string(plaintext),
+                    "additionalAuthenticatedData": Base64::encode_string(aad),
+                }),
+            )
+            .await?;
+        decode(&response.ciphertext)
+    }
+
+    async fn unwrap(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, IntegrationOSError> {
+        let response: DecryptResponse = self
+            .call(
+                "decrypt",
+                json!({
+                    "ciphertext": Base64::encode_string(ciphertext),
+                    "additionalAuthenticatedData": Base64::encode_string(aad),
+                }),
+            )
+            .await?;
+        decode(&response.plaintext)
+    }
+}
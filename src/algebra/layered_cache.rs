@@ -0,0 +1,327 @@
+use crate::{CacheEntry, CacheExt, IntegrationOSError, InternalError, RedisCache};
+use async_trait::async_trait;
+use futures::StreamExt;
+use redis::AsyncCommands;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+/// Payload published to clear every local entry instead of a single key
+pub const INVALIDATE_ALL: &str = "*";
+
+#[derive(Debug)]
+struct LocalEntry {
+    value: Value,
+    expires_at: Instant,
+    tick: u64,
+}
+
+/// Bounded map evicting the least recently used entry once full
+#[derive(Debug)]
+pub struct LruCache {
+    capacity: usize,
+    entries: HashMap<String, LocalEntry>,
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LruCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    pub fn get(&mut self, key: &str, now: Instant) -> Option<Value> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires_at <= now {
+            self.remove(key);
+            return None;
+        }
+        self.recency.remove(&entry.tick);
+        entry.tick = tick;
+        self.recency.insert(tick, key.to_owned());
+        Some(entry.value.clone())
+    }
+
+    pub fn insert(&mut self, key: &str, value: Value, expires_at: Instant) {
+        self.remove(key);
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        let tick = self.next_tick();
+        self.recency.insert(tick, key.to_owned());
+        self.entries.insert(
+            key.to_owned(),
+            LocalEntry {
+                value,
+                expires_at,
+                tick,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.tick);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Read-through cache keeping recently used entries in process in front of a shared
+/// cache, usually Redis. Local entries live for at most `local_ttl`; with an
+/// invalidation channel, writes also evict the key on every other instance.
+#[derive(Clone)]
+pub struct LayeredCache<C> {
+    local: Arc<Mutex<LruCache>>,
+    remote: C,
+    local_ttl: Duration,
+    invalidation: Option<(RedisCache, String)>,
+}
+
+impl<C: CacheExt + Send + Sync> LayeredCache<C> {
+    pub fn new(remote: C, capacity: usize, local_ttl: Duration) -> Self {
+        Self {
+            local: Arc::new(Mutex::new(LruCache::new(capacity))),
+            remote,
+            local_ttl,
+            invalidation: None,
+        }
+    }
+
+    /// Publishes invalidated keys on `channel`. Every instance should also run
+    /// [`LayeredCache::listen`] to receive them.
+    pub fn with_invalidation(mut self, redis: RedisCache, channel: &str) -> Self {
+        self.invalidation = Some((redis, channel.to_owned()));
+        self
+    }
+
+    pub fn remote(&self) -> &C {
+        &self.remote
+    }
+
+    fn with_local<R>(&self, f: impl FnOnce(&mut LruCache) -> R) -> R {
+        f(&mut self.local.lock().expect("layered cache lock poisoned"))
+    }
+
+    /// Evicts `key`, or everything for [`INVALIDATE_ALL`], from this instance only
+    pub fn invalidate_local(&self, key: &str) {
+        self.with_local(|local| match key {
+            INVALIDATE_ALL => local.clear(),
+            key => local.remove(key),
+        })
+    }
+
+    fn store_local(&self, key: &str, value: Value, expire: Option<u64>) {
+        let ttl = expire
+            .map(|seconds| Duration::from_secs(seconds).min(self.local_ttl))
+            .unwrap_or(self.local_ttl);
+        self.with_local(|local| local.insert(key, value, Instant::now() + ttl));
+    }
+
+    async fn publish(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.invalidate_local(key);
+        let Some((redis, channel)) = &self.invalidation else {
+            return Ok(());
+        };
+        let mut conn = redis.clone();
+        conn.publish(channel.as_str(), key)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("layered_cache")))
+    }
+}
+
+impl<C: CacheExt + Clone + Send + Sync + 'static> LayeredCache<C> {
+    /// Evicts local entries as other instances publish invalidations, until the
+    /// subscription drops
+    pub async fn listen(&self) -> Result<JoinHandle<()>, IntegrationOSError> {
+        let Some((redis, channel)) = &self.invalidation else {
+            return Err(InternalError::configuration_error(
+                "No invalidation channel configured",
+                Some("layered_cache"),
+            ));
+        };
+        let mut pubsub = redis
+            .subscribe(channel)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("layered_cache")))?;
+        let cache = self.clone();
+        Ok(tokio::spawn(async move {
+            let mut messages = pubsub.on_message();
+            while let Some(message) = messages.next().await {
+                match message.get_payload::<String>() {
+                    Ok(key) => cache.invalidate_local(&key),
+                    Err(e) => error!("Invalid cache invalidation message: {e}"),
+                }
+            }
+            warn!("Cache invalidation subscription ended");
+        }))
+    }
+}
+
+#[async_trait]
+impl<C: CacheExt + Send + Sync> CacheExt for LayeredCache<C> {
+    async fn get_or_insert_with<F>(
+        &self,
+        key: &str,
+        f: F,
+        expire: Option<u64>,
+    ) -> Result<CacheEntry, IntegrationOSError>
+    where
+        F: FnOnce() -> Result<CacheEntry, IntegrationOSError> + Send,
+    {
+        if let Some(entry) = self.get(key).await? {
+            return Ok(entry);
+        }
+        let entry = f()?;
+        self.set(entry.clone(), expire).await?;
+        Ok(entry)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError> {
+        if let Some(value) = self.with_local(|local| local.get(key, Instant::now())) {
+            return Ok(Some(CacheEntry::new(key.to_owned(), value)));
+        }
+        let entry = self.remote.get(key).await?;
+        if let Some(entry) = &entry {
+            self.store_local(key, entry.value().clone(), None);
+        }
+        Ok(entry)
+    }
+
+    async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError> {
+        self.remote.set(entry.clone(), expire).await?;
+        self.publish(entry.key()).await?;
+        self.store_local(entry.key(), entry.value().clone(), expire);
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.remote.remove(key).await?;
+        self.publish(key).await
+    }
+
+    async fn clear(&self) -> Result<(), IntegrationOSError> {
+        self.remote.clear().await?;
+        self.publish(INVALIDATE_ALL).await
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError> {
+        let exists = self.remote.expire(key, seconds).await?;
+        self.publish(key).await?;
+        Ok(exists)
+    }
+
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError> {
+        self.remote.list_push(key, value).await
+    }
+
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError> {
+        self.remote.list_position(key, value).await
+    }
+
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError> {
+        self.remote.list_len(key).await
+    }
+
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        self.remote.list_move(source, destination).await
+    }
+
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError> {
+        self.remote.list_remove(key, value).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InMemoryCache;
+    use serde_json::json;
+
+    #[test]
+    fn test_lru_eviction() {
+        let now = Instant::now();
+        let later = now + Duration::from_secs(60);
+        let mut lru = LruCache::new(2);
+        lru.insert("a", json!(1), later);
+        lru.insert("b", json!(2), later);
+        assert_eq!(lru.get("a", now), Some(json!(1)));
+
+        lru.insert("c", json!(3), later);
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get("b", now), None);
+        assert_eq!(lru.get("a", now), Some(json!(1)));
+        assert_eq!(lru.get("c", later), None);
+    }
+
+    #[tokio::test]
+    async fn test_layered_reads_through_and_invalidates() {
+        let remote = InMemoryCache::new();
+        let cache = LayeredCache::new(remote.clone(), 10, Duration::from_secs(60));
+        remote
+            .set(CacheEntry::new("conn".to_owned(), json!("v1")), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            cache.get("conn").await.unwrap().unwrap().value(),
+            &json!("v1")
+        );
+
+        // Served locally until invalidated
+        remote
+            .set(CacheEntry::new("conn".to_owned(), json!("v2")), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get("conn").await.unwrap().unwrap().value(),
+            &json!("v1")
+        );
+        cache.invalidate_local("conn");
+        assert_eq!(
+            cache.get("conn").await.unwrap().unwrap().value(),
+            &json!("v2")
+        );
+
+        cache.remove("conn").await.unwrap();
+        assert!(cache.get("conn").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    ApplicationError, Filter, IntegrationOSError, MongoQuery, MongoStore, QueryBackend, Sort,
+};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// What a list endpoint accepts. Sorting and filtering on fields outside the allowed
+/// lists is rejected rather than ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListPolicy {
+    pub default_limit: u64,
+    pub max_limit: u64,
+    pub sortable: Vec<String>,
+    pub filterable: Vec<String>,
+}
+
+impl Default for ListPolicy {
+    fn default() -> Self {
+        Self {
+            default_limit: 20,
+            max_limit: 100,
+            sortable: vec!["createdAt".to_owned(), "updatedAt".to_owned()],
+            filterable: vec![],
+        }
+    }
+}
+
+impl ListPolicy {
+    pub fn sortable<I: IntoIterator<Item = S>, S: Into<String>>(mut self, fields: I) -> Self {
+        self.sortable = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn filterable<I: IntoIterator<Item = S>, S: Into<String>>(mut self, fields: I) -> Self {
+        self.filterable = fields.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Pagination, sorting and filtering parameters of a list request, parsed from
+/// `?limit=20&cursor=...&sort=-createdAt,name&filter={"op":"eq",...}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListParams {
+    pub limit: u64,
+    pub cursor: Option<String>,
+    pub sort: Option<Sort>,
+    pub filter: Option<Filter>,
+}
+
+fn bad_request(message: &str) -> IntegrationOSError {
+    ApplicationError::bad_request(message, Some("list_params"))
+}
+
+/// Clients only compare against scalars or lists of them, objects could carry operators
+fn is_plain(value: &Value) -> bool {
+    match value {
+        Value::Object(_) => false,
+        Value::Array(values) => values
+            .iter()
+            .all(|value| !matches!(value, Value::Object(_) | Value::Array(_))),
+        _ => true,
+    }
+}
+
+impl ListParams {
+    pub fn parse<I, K, V>(pairs: I, policy: &ListPolicy) -> Result<Self, IntegrationOSError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut params = Self {
+            limit: policy.default_limit,
+            cursor: None,
+            sort: None,
+            filter: None,
+        };
+        for (key, value) in pairs {
+            let value = value.as_ref();
+            match key.as_ref() {
+                "limit" => {
+                    let limit: u64 = value
+                        .parse()
+                        .map_err(|_| bad_request(&format!("Invalid limit {value}")))?;
+                    params.limit = limit.clamp(1, policy.max_limit);
+                }
+                "cursor" if !value.is_empty() => {
+                    Self::decode_cursor(value)?;
+                    params.cursor = Some(value.to_owned());
+                }
+                "sort" => params.sort = Some(Self::parse_sort(value, policy)?),
+                "filter" => {
+                    let filter: Filter = serde_json::from_str(value)
+                        .map_err(|e| bad_request(&format!("Invalid filter: {e}")))?;
+                    if let Some(field) = filter
+                        .fields()
+                        .into_iter()
+                        .find(|f| !policy.filterable.iter().any(|allowed| allowed == f))
+                    {
+                        return Err(bad_request(&format!("Cannot filter on {field}")));
+                    }
+                    if let Some(value) = filter.values().into_iter().find(|v| !is_plain(v)) {
+                        return Err(bad_request(&format!("Cannot filter by {value}")));
+                    }
+                    params.filter = Some(filter);
+                }
+                _ => {}
+            }
+        }
+        Ok(params)
+    }
+
+    fn parse_sort(value: &str, policy: &ListPolicy) -> Result<Sort, IntegrationOSError> {
+        let mut sort = Sort::default();
+        for field in value.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (name, descending) = match field.strip_prefix('-') {
+                Some(name) => (name, true),
+                None => (field.strip_prefix('+').unwrap_or(field), false),
+            };
+            if !policy.sortable.iter().any(|allowed| allowed == name) {
+                return Err(bad_request(&format!("Cannot sort on {name}")));
+            }
+            sort = if descending {
+                sort.then_desc(name)
+            } else {
+                sort.then_asc(name)
+            };
+        }
+        Ok(sort)
+    }
+
+    fn decode_cursor(cursor: &str) -> Result<u64, IntegrationOSError> {
+        Base64UrlUnpadded::decode_vec(cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|offset| offset.parse().ok())
+            .ok_or_else(|| bad_request("Invalid cursor"))
+    }
+
+    pub fn skip(&self) -> u64 {
+        self.cursor
+            .as_deref()
+            .and_then(|cursor| Self::decode_cursor(cursor).ok())
+            .unwrap_or(0)
+    }
+
+    /// Cursor of the next page given how many records this page returned, `None` once
+    /// the last page was reached
+    pub fn next_cursor(&self, returned: usize) -> Option<String> {
+        (returned as u64 >= self.limit).then(|| {
+            Base64UrlUnpadded::encode_string((self.skip() + self.limit).to_string().as_bytes())
+        })
+    }
+}
+
+impl<T> MongoStore<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    /// Runs the list request, returning the page and the cursor of the next one
+    pub async fn list(
+        &self,
+        params: &ListParams,
+    ) -> Result<(Vec<T>, Option<String>), IntegrationOSError> {
+        let filter = params.filter.as_ref().map(MongoQuery::filter).transpose()?;
+        let sort = params.sort.as_ref().map(MongoQuery::sort).transpose()?;
+        let records = self
+            .get_many(filter, None, sort, Some(params.limit), Some(params.skip()))
+            .await?;
+        let next = params.next_cursor(records.len());
+        Ok((records, next))
+    }
+}
+
+/// Reads the [`ListPolicy`] from the app data, falling back to the default one
+#[cfg(feature = "actix-error")]
+impl actix_web::FromRequest for ListParams {
+    type Error = IntegrationOSError;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let policy = req.app_data::<ListPolicy>().cloned().unwrap_or_default();
+        std::future::ready(
+            actix_web::web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+                .map_err(|e| bad_request(&e.to_string()))
+                .and_then(|query| ListParams::parse(query.into_inner(), &policy)),
+        )
+    }
+}
+
+/// Reads the [`ListPolicy`] from the request extensions, falling back to the default one
+#[cfg(feature = "axum-error")]
+#[axum::async_trait]
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for ListParams {
+    type Rejection = IntegrationOSError;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let policy = parts
+            .extensions
+            .get::<ListPolicy>()
+            .cloned()
+            .unwrap_or_default();
+        let axum::extract::Query(query) =
+            axum::extract::Query::<Vec<(String, String)>>::try_from_uri(&parts.uri)
+                .map_err(|e| bad_request(&e.body_text()))?;
+        ListParams::parse(query, &policy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_list_params() {
+        let policy = ListPolicy::default()
+            .sortable(["createdAt", "name"])
+            .filterable(["platform", "active"]);
+
+        let params = ListParams::parse(
+            [
+                ("limit", "500"),
+                ("sort", "-createdAt,name"),
+                (
+                    "filter",
+                    r#"{"op":"and","filters":[{"op":"eq","field":"platform","value":"stripe"},{"op":"eq","field":"active","value":true}]}"#,
+                ),
+            ],
+            &policy,
+        )
+        .unwrap();
+        assert_eq!(params.limit, 100);
+        assert_eq!(params.sort, Some(Sort::desc("createdAt").then_asc("name")));
+        assert_eq!(
+            params.filter,
+            Some(Filter::and([
+                Filter::eq("platform", "stripe"),
+                Filter::eq("active", true)
+            ]))
+        );
+
+        let next = params.next_cursor(100).unwrap();
+        let second = ListParams::parse([("cursor", next.as_str())], &policy).unwrap();
+        assert_eq!(second.skip(), 100);
+        assert_eq!(second.next_cursor(5), None);
+
+        assert!(ListParams::parse([("sort", "secret")], &policy).is_err());
+        assert!(ListParams::parse(
+            [("filter", r#"{"op":"eq","field":"secret","value":1}"#)],
+            &policy
+        )
+        .is_err());
+        assert!(ListParams::parse([("cursor", "!!")], &policy).is_err());
+
+        for hostile in [
+            r#"{"op":"eq","field":"platform","value":{"$ne":null}}"#,
+            r#"{"op":"in","field":"platform","values":[{"$regex":".*"}]}"#,
+            r#"{"op":"not","filter":{"op":"eq","field":"active","value":[[{"$gt":""}]]}}"#,
+        ] {
+            assert!(
+                ListParams::parse([("filter", hostile)], &policy).is_err(),
+                "{hostile}"
+            );
+        }
+        assert!(ListParams::parse(
+            [(
+                "filter",
+                r#"{"op":"eq","field":"platform","value":["stripe","shopify"]}"#
+            )],
+            &policy
+        )
+        .is_ok());
+    }
+}
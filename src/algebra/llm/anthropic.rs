@@ -0,0 +1,239 @@
+use super::{
+    deserialize, http_client, send, token_stream, ChatRequest, ChatResponse, LlmExt, LlmRole,
+    LlmUsage, TokenStream,
+};
+use crate::{
+    configuration::llm::{LlmConfig, LlmProvider},
+    ApplicationError, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const SUBTYPE: &str = "anthropic";
+
+#[derive(Deserialize)]
+struct Message {
+    model: String,
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    usage: Usage,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    ContentBlockDelta {
+        delta: ContentBlock,
+    },
+    Error {
+        error: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+fn parse_event(data: &str) -> Result<Option<String>, IntegrationOSError> {
+    match deserialize(data, SUBTYPE)? {
+        StreamEvent::ContentBlockDelta { delta } => Ok(delta.text),
+        StreamEvent::Error { error } => Err(ApplicationError::failed_dependency(
+            &error["message"]
+                .as_str()
+                .map(str::to_owned)
+                .unwrap_or_else(|| error.to_string()),
+            Some(SUBTYPE),
+        )),
+        StreamEvent::Other => Ok(None),
+    }
+}
+
+/// Anthropic messages API. Anthropic has no embeddings, so [`LlmExt::embed`] is
+/// `NotImplemented`.
+#[derive(Debug, Clone)]
+pub struct AnthropicClient {
+    config: LlmConfig,
+    client: Client,
+}
+
+impl AnthropicClient {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+
+    /// System messages go in the `system` field, the others in `messages`
+    fn body(&self, request: ChatRequest, stream: bool) -> Value {
+        let (system, messages): (Vec<_>, Vec<_>) = request
+            .messages
+            .into_iter()
+            .partition(|message| message.role == LlmRole::System);
+        let mut body = json!({
+            "model": request.model.as_deref().unwrap_or(self.config.model()),
+            "messages": messages,
+            "max_tokens": request.max_tokens.unwrap_or(self.config.max_tokens),
+            "stream": stream,
+        });
+        if !system.is_empty() {
+            body["system"] = json!(system
+                .into_iter()
+                .map(|message| message.content)
+                .collect::<Vec<_>>()
+                .join("\n\n"));
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !request.stop.is_empty() {
+            body["stop_sequences"] = json!(request.stop);
+        }
+        body
+    }
+
+    async fn post(&self, body: &Value) -> Result<reqwest::Response, IntegrationOSError> {
+        let claude = &self.config.claude;
+        send(
+            self.client
+                .post(format!(
+                    "{}/messages",
+                    claude.base_url.trim_end_matches('/')
+                ))
+                .header("x-api-key", claude.api_key.expose_secret())
+                .header("anthropic-version", &claude.version)
+                .json(body),
+            SUBTYPE,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl LlmExt for AnthropicClient {
+    fn provider(&self) -> LlmProvider {
+        LlmProvider::Anthropic
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, IntegrationOSError> {
+        let message: Message = self
+            .post(&self.body(request, false))
+            .await?
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some(SUBTYPE)))?;
+
+        Ok(ChatResponse {
+            content: message
+                .content
+                .into_iter()
+                .filter_map(|block| block.text)
+                .collect(),
+            model: message.model,
+            stop_reason: message.stop_reason,
+            usage: LlmUsage {
+                input_tokens: message.usage.input_tokens,
+                output_tokens: message.usage.output_tokens,
+            },
+        })
+    }
+
+    async fn chat_stream(&self, request: ChatRequest) -> Result<TokenStream, IntegrationOSError> {
+        let response = self.post(&self.body(request, true)).await?;
+        Ok(token_stream(response, parse_event, SUBTYPE))
+    }
+
+    async fn embed(&self, _inputs: &[String]) -> Result<Vec<Vec<f32>>, IntegrationOSError> {
+        Err(ApplicationError::not_implemented(
+            "Anthropic has no embeddings API",
+            Some(SUBTYPE),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LlmMessage;
+    use envconfig::Envconfig;
+    use futures::StreamExt;
+    use mockito::{Matcher, Server};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_anthropic_chat_and_stream() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/messages")
+            .match_header("x-api-key", "sk-ant")
+            .match_header("anthropic-version", "2023-06-01")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "claude-3-5-sonnet-20240620",
+                "system": "Be brief",
+                "messages": [{ "role": "user", "content": "Hi" }],
+                "stream": false
+            })))
+            .with_body(
+                r#"{"model":"claude-3-5-sonnet-20240620","content":[{"type":"text","text":"Hello"}],"stop_reason":"end_turn","usage":{"input_tokens":12,"output_tokens":1}}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/messages")
+            .match_body(Matcher::PartialJson(json!({ "stream": true })))
+            .with_body(concat!(
+                "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n",
+                "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n",
+                "event: error\ndata: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n"
+            ))
+            .create_async()
+            .await;
+
+        let config = LlmConfig::init_from_hashmap(&HashMap::from([
+            ("LLM_PROVIDER".to_owned(), "claude".to_owned()),
+            ("ANTHROPIC_API_KEY".to_owned(), "sk-ant".to_owned()),
+            ("ANTHROPIC_BASE_URL".to_owned(), server.url()),
+        ]))
+        .unwrap();
+        let client = crate::llm_client(&config);
+        assert_eq!(client.provider(), LlmProvider::Anthropic);
+        let request =
+            ChatRequest::new(vec![LlmMessage::system("Be brief"), LlmMessage::user("Hi")]);
+
+        let response = client.chat(request.clone()).await.unwrap();
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.usage.input_tokens, 12);
+
+        let tokens = client
+            .chat_stream(request)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[..2]
+                .iter()
+                .map(|token| token.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["Hel", "lo"]
+        );
+        assert!(tokens[2].is_err());
+        assert!(client.embed(&["Hi".to_owned()]).await.is_err());
+    }
+}
@@ -0,0 +1,260 @@
+mod anthropic;
+mod openai;
+
+pub use anthropic::*;
+pub use openai::*;
+
+use crate::{
+    configuration::llm::{LlmConfig, LlmProvider},
+    IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::Arc};
+use strum::{AsRefStr, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LlmRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmMessage {
+    pub role: LlmRole,
+    pub content: String,
+}
+
+impl LlmMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Conversation to continue. Unset options fall back to the [`LlmConfig`] of the client.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatRequest {
+    pub messages: Vec<LlmMessage>,
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub stop: Vec<String>,
+}
+
+impl ChatRequest {
+    pub fn new(messages: Vec<LlmMessage>) -> Self {
+        Self {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    pub fn prompt(prompt: impl Into<String>) -> Self {
+        Self::new(vec![LlmMessage::user(prompt)])
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatResponse {
+    pub content: String,
+    pub model: String,
+    pub stop_reason: Option<String>,
+    pub usage: LlmUsage,
+}
+
+/// Text of a response as it is generated
+pub type TokenStream = BoxStream<'static, Result<String, IntegrationOSError>>;
+
+/// Language model generating text and embeddings, so services generating mappings or
+/// definitions don't depend on a given provider. See [`llm_client`] to pick one from
+/// config.
+#[async_trait]
+pub trait LlmExt: Send + Sync {
+    fn provider(&self) -> LlmProvider;
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, IntegrationOSError>;
+
+    /// Same as [`LlmExt::chat`], yielding the text as it is generated
+    async fn chat_stream(&self, request: ChatRequest) -> Result<TokenStream, IntegrationOSError>;
+
+    /// Embedding of each input, in order
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, IntegrationOSError>;
+
+    /// Answer to a single prompt
+    async fn complete(&self, prompt: &str) -> Result<String, IntegrationOSError> {
+        Ok(self.chat(ChatRequest::prompt(prompt)).await?.content)
+    }
+}
+
+/// Client of the provider selected by `LLM_PROVIDER`
+pub fn llm_client(config: &LlmConfig) -> Arc<dyn LlmExt> {
+    match config.provider {
+        LlmProvider::OpenAi => Arc::new(OpenAiClient::new(config.clone())),
+        LlmProvider::Anthropic => Arc::new(AnthropicClient::new(config.clone())),
+    }
+}
+
+fn http_client(config: &LlmConfig) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(config.timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Sends a request to the provider, turning error statuses into errors
+async fn send(request: RequestBuilder, subtype: &str) -> Result<Response, IntegrationOSError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| InternalError::connection_error(&e.to_string(), Some(subtype)))?;
+    let status = response.status();
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(IntegrationOSError::from_err_code(
+            status,
+            &message,
+            Some(subtype),
+        ));
+    }
+    Ok(response)
+}
+
+/// Splits a body of server-sent events into the data of each event
+#[derive(Debug, Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    /// Data of the events completed by `chunk`, multi line data joined with `\n`
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+        let mut events = vec![];
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event = self.buffer.drain(..end + 2).collect::<Vec<_>>();
+            let data = String::from_utf8_lossy(&event)
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data).to_owned())
+                .collect::<Vec<_>>();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+/// Token of an event's data, `None` for events without text
+type ParseEvent = fn(&str) -> Result<Option<String>, IntegrationOSError>;
+
+fn token_stream(response: Response, parse: ParseEvent, subtype: &'static str) -> TokenStream {
+    let state = (Some(response), SseDecoder::default(), VecDeque::new());
+    futures::stream::unfold(
+        state,
+        move |(mut response, mut decoder, mut pending)| async move {
+            loop {
+                if let Some(token) = pending.pop_front() {
+                    return Some((token, (response, decoder, pending)));
+                }
+                match response.as_mut()?.chunk().await {
+                    Ok(Some(chunk)) => {
+                        for data in decoder.push(&chunk) {
+                            match parse(&data) {
+                                Ok(Some(token)) if !token.is_empty() => {
+                                    pending.push_back(Ok(token))
+                                }
+                                Ok(_) => {}
+                                Err(e) => {
+                                    pending.push_back(Err(e));
+                                    response = None;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    Ok(None) => response = None,
+                    Err(e) => {
+                        pending.push_back(Err(InternalError::connection_error(
+                            &e.to_string(),
+                            Some(subtype),
+                        )));
+                        response = None;
+                    }
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+fn deserialize<T: serde::de::DeserializeOwned>(
+    data: &str,
+    subtype: &str,
+) -> Result<T, IntegrationOSError> {
+    serde_json::from_str(data)
+        .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some(subtype)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sse_decoder_splits_events() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"event: ping\r\ndata: {\"a\"").is_empty());
+        assert_eq!(
+            decoder.push(b":1}\r\n\r\n: comment\n\ndata: one\ndata:two\n\ndata: [DO"),
+            vec!["{\"a\":1}".to_owned(), "one\ntwo".to_owned()]
+        );
+        assert_eq!(decoder.push(b"NE]\n\n"), vec!["[DONE]".to_owned()]);
+    }
+}
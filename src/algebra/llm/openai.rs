@@ -0,0 +1,250 @@
+use super::{
+    deserialize, http_client, send, token_stream, ChatRequest, ChatResponse, LlmExt, LlmUsage,
+    TokenStream,
+};
+use crate::{
+    configuration::llm::{LlmConfig, LlmProvider},
+    ApplicationError, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const SUBTYPE: &str = "openai";
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    model: String,
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    #[serde(default)]
+    message: Option<Message>,
+    #[serde(default)]
+    delta: Option<Message>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct Embeddings {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+fn parse_event(data: &str) -> Result<Option<String>, IntegrationOSError> {
+    if data == "[DONE]" {
+        return Ok(None);
+    }
+    let chunk: ChatCompletion = deserialize(data, SUBTYPE)?;
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta)
+        .and_then(|delta| delta.content))
+}
+
+/// OpenAI chat completions and embeddings, or any API compatible with them through
+/// `OPENAI_BASE_URL`
+#[derive(Debug, Clone)]
+pub struct OpenAiClient {
+    config: LlmConfig,
+    client: Client,
+}
+
+impl OpenAiClient {
+    pub fn new(config: LlmConfig) -> Self {
+        Self {
+            client: http_client(&config),
+            config,
+        }
+    }
+
+    fn body(&self, request: ChatRequest, stream: bool) -> Value {
+        let mut body = json!({
+            "model": request.model.as_deref().unwrap_or(self.config.model()),
+            "messages": request.messages,
+            "max_tokens": request.max_tokens.unwrap_or(self.config.max_tokens),
+            "stream": stream,
+        });
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if !request.stop.is_empty() {
+            body["stop"] = json!(request.stop);
+        }
+        body
+    }
+
+    async fn post(
+        &self,
+        path: &str,
+        body: &Value,
+    ) -> Result<reqwest::Response, IntegrationOSError> {
+        let url = format!(
+            "{}/{path}",
+            self.config.openai.base_url.trim_end_matches('/')
+        );
+        send(
+            self.client
+                .post(url)
+                .bearer_auth(self.config.openai.api_key.expose_secret())
+                .json(body),
+            SUBTYPE,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl LlmExt for OpenAiClient {
+    fn provider(&self) -> LlmProvider {
+        LlmProvider::OpenAi
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, IntegrationOSError> {
+        let completion: ChatCompletion = self
+            .post("chat/completions", &self.body(request, false))
+            .await?
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some(SUBTYPE)))?;
+        let choice = completion.choices.into_iter().next().ok_or_else(|| {
+            InternalError::deserialize_error("Chat completion without choices", Some(SUBTYPE))
+        })?;
+        let usage = completion.usage.map(|usage| LlmUsage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        });
+
+        Ok(ChatResponse {
+            content: choice
+                .message
+                .and_then(|message| message.content)
+                .unwrap_or_default(),
+            model: completion.model,
+            stop_reason: choice.finish_reason,
+            usage: usage.unwrap_or_default(),
+        })
+    }
+
+    async fn chat_stream(&self, request: ChatRequest) -> Result<TokenStream, IntegrationOSError> {
+        let response = self
+            .post("chat/completions", &self.body(request, true))
+            .await?;
+        Ok(token_stream(response, parse_event, SUBTYPE))
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, IntegrationOSError> {
+        let model = self.config.embedding_model().ok_or_else(|| {
+            ApplicationError::not_implemented("No embedding model configured", Some(SUBTYPE))
+        })?;
+        let mut embeddings: Embeddings = self
+            .post("embeddings", &json!({ "model": model, "input": inputs }))
+            .await?
+            .json()
+            .await
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some(SUBTYPE)))?;
+        embeddings.data.sort_by_key(|embedding| embedding.index);
+        Ok(embeddings
+            .data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LlmMessage;
+    use envconfig::Envconfig;
+    use futures::TryStreamExt;
+    use mockito::{Matcher, Server};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_openai_chat_and_stream() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/chat/completions")
+            .match_header("authorization", "Bearer sk-test")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "gpt-4o-mini",
+                "messages": [
+                    { "role": "system", "content": "Be brief" },
+                    { "role": "user", "content": "Hi" }
+                ],
+                "stream": false
+            })))
+            .with_body(
+                r#"{"model":"gpt-4o-mini","choices":[{"message":{"role":"assistant","content":"Hello"},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":1}}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(json!({ "stream": true })))
+            .with_body(concat!(
+                "data: {\"model\":\"gpt-4o-mini\",\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                "data: {\"model\":\"gpt-4o-mini\",\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n",
+                "data: {\"model\":\"gpt-4o-mini\",\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+                "data: [DONE]\n\n"
+            ))
+            .create_async()
+            .await;
+
+        let config = LlmConfig::init_from_hashmap(&HashMap::from([
+            ("LLM_MODEL".to_owned(), "gpt-4o-mini".to_owned()),
+            ("OPENAI_API_KEY".to_owned(), "sk-test".to_owned()),
+            ("OPENAI_BASE_URL".to_owned(), server.url()),
+        ]))
+        .unwrap();
+        let client = OpenAiClient::new(config);
+        let request =
+            ChatRequest::new(vec![LlmMessage::system("Be brief"), LlmMessage::user("Hi")]);
+
+        let response = client.chat(request.clone()).await.unwrap();
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.stop_reason.as_deref(), Some("stop"));
+        assert_eq!(
+            response.usage,
+            LlmUsage {
+                input_tokens: 9,
+                output_tokens: 1
+            }
+        );
+
+        let tokens = client
+            .chat_stream(request)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(tokens, vec!["Hel", "lo"]);
+    }
+}
@@ -0,0 +1,134 @@
+use super::{Delivery, MessageBusExt, Subscription};
+use crate::{configuration::message_bus::KafkaConfig, IntegrationOSError, InternalError};
+use async_trait::async_trait;
+use futures::StreamExt;
+use rdkafka::{
+    consumer::{Consumer, StreamConsumer},
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig, Message,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+fn kafka_error(e: impl std::fmt::Display) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("kafka_bus"))
+}
+
+/// Message bus over Kafka topics. Subscribers join the configured consumer group and
+/// acking a delivery stores its offset for the next auto commit. Offsets are tracked per
+/// partition, so acking a delivery also settles the earlier ones of its partition.
+#[derive(Clone)]
+pub struct KafkaBus {
+    config: KafkaConfig,
+    producer: FutureProducer,
+    consumers: Arc<Mutex<HashMap<String, Arc<StreamConsumer>>>>,
+}
+
+impl KafkaBus {
+    pub fn new(config: &KafkaConfig) -> Result<Self, IntegrationOSError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            .create()
+            .map_err(kafka_error)?;
+        Ok(Self {
+            config: config.clone(),
+            producer,
+            consumers: Default::default(),
+        })
+    }
+
+    fn consumer(&self, topic: &str) -> Result<Arc<StreamConsumer>, IntegrationOSError> {
+        self.consumers
+            .lock()
+            .expect("kafka consumers lock poisoned")
+            .get(topic)
+            .cloned()
+            .ok_or_else(|| {
+                InternalError::key_not_found(
+                    &format!("Not subscribed to topic {topic}"),
+                    Some("kafka_bus"),
+                )
+            })
+    }
+
+    /// Partition and offset of a delivery, encoded in its receipt
+    fn position(delivery: &Delivery) -> Result<(i32, i64), IntegrationOSError> {
+        delivery
+            .receipt
+            .split_once(':')
+            .and_then(|(partition, offset)| Some((partition.parse().ok()?, offset.parse().ok()?)))
+            .ok_or_else(|| {
+                InternalError::invalid_argument(
+                    &format!("Invalid Kafka receipt {}", delivery.receipt),
+                    Some("kafka_bus"),
+                )
+            })
+    }
+
+    fn store_offset(&self, delivery: &Delivery) -> Result<(), IntegrationOSError> {
+        let (partition, offset) = Self::position(delivery)?;
+        self.consumer(&delivery.topic)?
+            .store_offset(&delivery.topic, partition, offset)
+            .map_err(kafka_error)
+    }
+}
+
+#[async_trait]
+impl MessageBusExt for KafkaBus {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), IntegrationOSError> {
+        self.producer
+            .send(
+                FutureRecord::<(), [u8]>::to(topic).payload(payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .map_err(|(e, _)| kafka_error(e))?;
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, IntegrationOSError> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &self.config.brokers)
+            .set("group.id", &self.config.group_id)
+            .set("enable.auto.commit", "true")
+            .set("enable.auto.offset.store", "false")
+            .create()
+            .map_err(kafka_error)?;
+        consumer.subscribe(&[topic]).map_err(kafka_error)?;
+        let consumer = Arc::new(consumer);
+        self.consumers
+            .lock()
+            .expect("kafka consumers lock poisoned")
+            .insert(topic.to_owned(), consumer.clone());
+
+        Ok(futures::stream::unfold(consumer, |consumer| async move {
+            let delivery = consumer
+                .recv()
+                .await
+                .map(|message| Delivery {
+                    topic: message.topic().to_owned(),
+                    payload: message.payload().unwrap_or_default().to_vec(),
+                    receipt: format!("{}:{}", message.partition(), message.offset()),
+                })
+                .map_err(kafka_error);
+            Some((delivery, consumer))
+        })
+        .boxed())
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> Result<(), IntegrationOSError> {
+        self.store_offset(delivery)
+    }
+
+    /// Kafka can't redeliver a single message, so a requeued delivery is published again
+    /// at the end of its topic before its offset is stored
+    async fn nack(&self, delivery: &Delivery, requeue: bool) -> Result<(), IntegrationOSError> {
+        if requeue {
+            self.publish(&delivery.topic, &delivery.payload).await?;
+        }
+        self.store_offset(delivery)
+    }
+}
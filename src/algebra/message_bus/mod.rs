@@ -0,0 +1,145 @@
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+#[cfg(feature = "kafka")]
+pub use kafka::*;
+#[cfg(feature = "nats")]
+pub use nats::*;
+
+use crate::{CacheExt, IntegrationOSError};
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use std::time::Duration;
+
+/// How long a cache backed subscription waits before polling an empty queue again
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A message handed to a subscriber, to be acknowledged once processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    /// Backend specific handle identifying the delivery when acknowledging it
+    pub receipt: String,
+}
+
+pub type Subscription = BoxStream<'static, Result<Delivery, IntegrationOSError>>;
+
+/// Broker the event queues run on, so deployments can choose between Redis lists, Kafka
+/// and NATS JetStream. Deliveries are at least once: a delivery that is neither acked nor
+/// nacked is redelivered by the broker eventually.
+#[async_trait]
+pub trait MessageBusExt {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), IntegrationOSError>;
+    /// Whether an identical payload is still waiting on the topic. Log based brokers can't
+    /// tell and always answer `false`.
+    async fn is_pending(&self, _topic: &str, _payload: &[u8]) -> Result<bool, IntegrationOSError> {
+        Ok(false)
+    }
+    /// Messages waiting on the topic, `None` when the broker can't tell
+    async fn pending_count(&self, _topic: &str) -> Result<Option<usize>, IntegrationOSError> {
+        Ok(None)
+    }
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, IntegrationOSError>;
+    async fn ack(&self, delivery: &Delivery) -> Result<(), IntegrationOSError>;
+    /// Gives a delivery up, putting it back on the topic when `requeue` is set and
+    /// dropping it otherwise
+    async fn nack(&self, delivery: &Delivery, requeue: bool) -> Result<(), IntegrationOSError>;
+}
+
+fn processing_key(topic: &str) -> String {
+    format!("{topic}::processing")
+}
+
+/// Reliable queue over the cache lists: publishers push to the head of the topic and
+/// subscribers move the tail to `{topic}::processing` until it is acked. Deliveries of a
+/// crashed subscriber stay in the processing list to be recovered from there.
+#[async_trait]
+impl<C> MessageBusExt for C
+where
+    C: CacheExt + Clone + Send + Sync + 'static,
+{
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), IntegrationOSError> {
+        self.list_push(topic, payload).await
+    }
+
+    async fn is_pending(&self, topic: &str, payload: &[u8]) -> Result<bool, IntegrationOSError> {
+        Ok(self.list_position(topic, payload).await?.is_some())
+    }
+
+    async fn pending_count(&self, topic: &str) -> Result<Option<usize>, IntegrationOSError> {
+        self.list_len(topic).await.map(Some)
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, IntegrationOSError> {
+        let state = (self.clone(), topic.to_owned());
+        Ok(futures::stream::unfold(state, |(cache, topic)| async move {
+            let processing = processing_key(&topic);
+            loop {
+                match cache.list_move(&topic, &processing).await {
+                    Ok(Some(payload)) => {
+                        let delivery = Delivery {
+                            topic: topic.clone(),
+                            payload,
+                            receipt: processing,
+                        };
+                        return Some((Ok(delivery), (cache, topic)));
+                    }
+                    Ok(None) => tokio::time::sleep(POLL_INTERVAL).await,
+                    Err(e) => return Some((Err(e), (cache, topic))),
+                }
+            }
+        })
+        .boxed())
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> Result<(), IntegrationOSError> {
+        self.list_remove(&delivery.receipt, &delivery.payload)
+            .await
+            .map(|_| ())
+    }
+
+    async fn nack(&self, delivery: &Delivery, requeue: bool) -> Result<(), IntegrationOSError> {
+        if self
+            .list_remove(&delivery.receipt, &delivery.payload)
+            .await?
+            && requeue
+        {
+            self.list_push(&delivery.topic, &delivery.payload).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InMemoryCache;
+
+    #[tokio::test]
+    async fn test_cache_bus_acks_and_requeues() {
+        let bus = InMemoryCache::new();
+        bus.publish("events", b"first").await.unwrap();
+        bus.publish("events", b"second").await.unwrap();
+        assert!(bus.is_pending("events", b"first").await.unwrap());
+        assert_eq!(bus.pending_count("events").await.unwrap(), Some(2));
+
+        let mut subscription = bus.subscribe("events").await.unwrap();
+        let first = subscription.next().await.unwrap().unwrap();
+        assert_eq!(first.payload, b"first");
+        assert!(!bus.is_pending("events", b"first").await.unwrap());
+        bus.nack(&first, true).await.unwrap();
+
+        let second = subscription.next().await.unwrap().unwrap();
+        assert_eq!(second.payload, b"second");
+        bus.ack(&second).await.unwrap();
+
+        let first = subscription.next().await.unwrap().unwrap();
+        assert_eq!(first.payload, b"first");
+        bus.ack(&first).await.unwrap();
+        assert_eq!(bus.list_len("events::processing").await.unwrap(), 0);
+        assert_eq!(bus.list_len("events").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Delivery, MessageBusExt, Subscription};
+use crate::{configuration::message_bus::NatsConfig, IntegrationOSError, InternalError};
+use async_nats::jetstream::{self, consumer::pull};
+use async_trait::async_trait;
+use futures::StreamExt;
+
+fn nats_error(e: impl std::fmt::Display) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("nats_bus"))
+}
+
+/// Message bus over NATS JetStream. Topics are subjects captured by the configured
+/// stream, and each topic is consumed through a durable pull consumer named after it so
+/// subscribers share its deliveries.
+#[derive(Clone)]
+pub struct NatsBus {
+    client: async_nats::Client,
+    jetstream: jetstream::Context,
+    stream: String,
+}
+
+impl NatsBus {
+    pub async fn new(config: &NatsConfig) -> Result<Self, IntegrationOSError> {
+        let client = async_nats::connect(config.url.as_str())
+            .await
+            .map_err(nats_error)?;
+        Ok(Self {
+            jetstream: jetstream::new(client.clone()),
+            client,
+            stream: config.stream.clone(),
+        })
+    }
+
+    /// Sends an acknowledgement to the reply subject of a delivery
+    async fn respond(
+        &self,
+        delivery: &Delivery,
+        kind: &'static [u8],
+    ) -> Result<(), IntegrationOSError> {
+        self.client
+            .publish(delivery.receipt.clone(), kind.into())
+            .await
+            .map_err(nats_error)
+    }
+}
+
+#[async_trait]
+impl MessageBusExt for NatsBus {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), IntegrationOSError> {
+        self.jetstream
+            .publish(topic.to_owned(), payload.to_vec().into())
+            .await
+            .map_err(nats_error)?
+            .await
+            .map_err(nats_error)?;
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Subscription, IntegrationOSError> {
+        let durable_name = topic.replace(['.', '*', '>'], "_");
+        let consumer = self
+            .jetstream
+            .get_stream(&self.stream)
+            .await
+            .map_err(nats_error)?
+            .get_or_create_consumer(
+                &durable_name,
+                pull::Config {
+                    durable_name: Some(durable_name.clone()),
+                    filter_subject: topic.to_owned(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(nats_error)?;
+        let messages = consumer.messages().await.map_err(nats_error)?;
+        let topic = topic.to_owned();
+        Ok(messages
+            .map(move |message| {
+                let message = message.map_err(nats_error)?;
+                let receipt = message.reply.as_ref().ok_or_else(|| {
+                    InternalError::invalid_argument(
+                        "JetStream message has no reply subject",
+                        Some("nats_bus"),
+                    )
+                })?;
+                Ok(Delivery {
+                    topic: topic.clone(),
+                    payload: message.payload.to_vec(),
+                    receipt: receipt.to_string(),
+                })
+            })
+            .boxed())
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> Result<(), IntegrationOSError> {
+        self.respond(delivery, b"+ACK").await
+    }
+
+    async fn nack(&self, delivery: &Delivery, requeue: bool) -> Result<(), IntegrationOSError> {
+        self.respond(delivery, if requeue { b"-NAK" } else { b"+TERM" })
+            .await
+    }
+}
@@ -0,0 +1,545 @@
+use crate::{CacheEntry, CacheExt, CollectionStats, IntegrationOSError, MongoStore, TimedExt};
+use async_trait::async_trait;
+use bson::Document;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    ops::Deref,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const SIZE_BUCKETS: [f64; 6] = [0.0, 1.0, 10.0, 100.0, 1_000.0, 10_000.0];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Histogram {
+    /// Non cumulative count per bucket, the last one being `+Inf`
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; bounds.len() + 1];
+        }
+        let bucket = bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(bounds.len());
+        self.buckets[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str, bounds: &[f64]) {
+        let mut cumulative = 0;
+        for (bound, count) in bounds.iter().zip(&self.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct OperationMetrics {
+    latency: Histogram,
+    sizes: Histogram,
+    errors: u64,
+}
+
+/// Which kind of backend an operation ran against, prefixing the metric names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MeteredTarget {
+    Store,
+    Cache,
+}
+
+impl MeteredTarget {
+    fn prefix(&self) -> &'static str {
+        match self {
+            MeteredTarget::Store => "store",
+            MeteredTarget::Cache => "cache",
+        }
+    }
+}
+
+type MetricKey = (MeteredTarget, String, &'static str);
+
+/// Name, help and value of the gauges exported for every collection
+type CollectionGauge = (&'static str, &'static str, fn(&CollectionStats) -> u64);
+
+/// Latency, error and result size metrics of the [`MeteredStore`] and [`MeteredCache`]
+/// wrappers, labelled by collection (or cache name) and operation. Cloning shares the
+/// metrics, [`MetricsRegistry::global`] is the registry services expose by default.
+/// Collection sizes are exported as gauges, see [`MeteredStore::stats`].
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    metrics: Arc<Mutex<BTreeMap<MetricKey, OperationMetrics>>>,
+    collections: Arc<Mutex<BTreeMap<String, CollectionStats>>>,
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn global() -> &'static MetricsRegistry {
+        static GLOBAL: OnceLock<MetricsRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(MetricsRegistry::new)
+    }
+
+    pub fn store<T>(&self, store: MongoStore<T>) -> MeteredStore<T>
+    where
+        T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+    {
+        MeteredStore::new(store, self.clone())
+    }
+
+    pub fn cache<C: CacheExt>(&self, name: impl Into<String>, cache: C) -> MeteredCache<C> {
+        MeteredCache::new(cache, name, self.clone())
+    }
+
+    /// Records an operation, `size` being the number of records or entries it read or
+    /// wrote when that makes sense
+    pub fn record(
+        &self,
+        target: MeteredTarget,
+        collection: &str,
+        operation: &'static str,
+        elapsed: Duration,
+        failed: bool,
+        size: Option<usize>,
+    ) {
+        let mut metrics = self.metrics.lock().expect("metrics lock poisoned");
+        let entry = metrics
+            .entry((target, collection.to_owned(), operation))
+            .or_default();
+        entry
+            .latency
+            .observe(&LATENCY_BUCKETS, elapsed.as_secs_f64());
+        if failed {
+            entry.errors += 1;
+        }
+        if let Some(size) = size {
+            entry.sizes.observe(&SIZE_BUCKETS, size as f64);
+        }
+    }
+
+    /// Latest stats of `collection`, replacing the previous ones
+    pub fn record_collection_stats(&self, collection: &str, stats: CollectionStats) {
+        self.collections
+            .lock()
+            .expect("metrics lock poisoned")
+            .insert(collection.to_owned(), stats);
+    }
+
+    /// Prometheus text exposition of every recorded operation and collection stats
+    pub fn to_prometheus(&self) -> String {
+        let metrics = self.metrics.lock().expect("metrics lock poisoned");
+        let mut out = String::new();
+        for target in [MeteredTarget::Store, MeteredTarget::Cache] {
+            let prefix = target.prefix();
+            let label = match target {
+                MeteredTarget::Store => "collection",
+                MeteredTarget::Cache => "cache",
+            };
+            let entries = metrics
+                .iter()
+                .filter(|((t, _, _), _)| *t == target)
+                .map(|((_, name, operation), m)| {
+                    (format!("{label}=\"{name}\",operation=\"{operation}\""), m)
+                })
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                continue;
+            }
+
+            let name = format!("{prefix}_operation_duration_seconds");
+            let _ = writeln!(out, "# HELP {name} Latency of {prefix} operations");
+            let _ = writeln!(out, "# TYPE {name} histogram");
+            for (labels, m) in &entries {
+                m.latency.render(&mut out, &name, labels, &LATENCY_BUCKETS);
+            }
+
+            let name = format!("{prefix}_operation_errors_total");
+            let _ = writeln!(out, "# HELP {name} Failed {prefix} operations");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            for (labels, m) in &entries {
+                let _ = writeln!(out, "{name}{{{labels}}} {}", m.errors);
+            }
+
+            let name = format!("{prefix}_operation_result_size");
+            let _ = writeln!(out, "# HELP {name} Records or entries read or written");
+            let _ = writeln!(out, "# TYPE {name} histogram");
+            for (labels, m) in entries.iter().filter(|(_, m)| m.sizes.count > 0) {
+                m.sizes.render(&mut out, &name, labels, &SIZE_BUCKETS);
+            }
+        }
+
+        let collections = self.collections.lock().expect("metrics lock poisoned");
+        if collections.is_empty() {
+            return out;
+        }
+        let gauges: [CollectionGauge; 4] = [
+            ("store_collection_documents", "Documents", |s| s.count),
+            (
+                "store_collection_size_bytes",
+                "Uncompressed data size",
+                |s| s.size,
+            ),
+            ("store_collection_storage_bytes", "Storage used", |s| {
+                s.storage_size
+            }),
+            ("store_collection_index_bytes", "Index size", |s| {
+                s.total_index_size
+            }),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(out, "# HELP {name} {help} of the collection");
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            for (collection, stats) in collections.iter() {
+                let _ = writeln!(
+                    out,
+                    "{name}{{collection=\"{collection}\"}} {}",
+                    value(stats)
+                );
+            }
+        }
+        out
+    }
+}
+
+/// [`MongoStore`] wrapper recording the common read and write paths in a
+/// [`MetricsRegistry`]. Other operations are reachable through `Deref` and aren't recorded.
+#[derive(Debug, Clone)]
+pub struct MeteredStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
+    inner: MongoStore<T>,
+    registry: MetricsRegistry,
+}
+
+impl<T: Serialize + DeserializeOwned + Unpin + Sync> Deref for MeteredStore<T> {
+    type Target = MongoStore<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MeteredStore<T> {
+    pub fn new(inner: MongoStore<T>, registry: MetricsRegistry) -> Self {
+        Self { inner, registry }
+    }
+
+    fn record<'a, R>(
+        &'a self,
+        operation: &'static str,
+        size: impl Fn(&R) -> Option<usize> + 'a,
+    ) -> impl FnMut(&Result<R, IntegrationOSError>, Duration) + 'a {
+        move |result, elapsed| {
+            self.registry.record(
+                MeteredTarget::Store,
+                self.inner.collection.name(),
+                operation,
+                elapsed,
+                result.is_err(),
+                result.as_ref().ok().and_then(&size),
+            )
+        }
+    }
+
+    /// Reads the collection stats and exports them as gauges of the registry, to be
+    /// called periodically
+    pub async fn stats(&self) -> Result<CollectionStats, IntegrationOSError> {
+        let stats = self
+            .inner
+            .stats()
+            .timed(self.record("stats", |_| None))
+            .await?;
+        self.registry
+            .record_collection_stats(self.inner.collection.name(), stats.clone());
+        Ok(stats)
+    }
+
+    pub async fn get_one(&self, filter: Document) -> Result<Option<T>, IntegrationOSError> {
+        self.inner
+            .get_one(filter)
+            .timed(self.record("get_one", |r: &Option<T>| Some(r.is_some() as usize)))
+            .await
+    }
+
+    pub async fn get_one_by_id(&self, id: &str) -> Result<Option<T>, IntegrationOSError> {
+        self.inner
+            .get_one_by_id(id)
+            .timed(self.record("get_one_by_id", |r: &Option<T>| Some(r.is_some() as usize)))
+            .await
+    }
+
+    pub async fn get_many(
+        &self,
+        filter: Option<Document>,
+        selection: Option<Document>,
+        sort: Option<Document>,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        self.inner
+            .get_many(filter, selection, sort, limit, skip)
+            .timed(self.record("get_many", |r: &Vec<T>| Some(r.len())))
+            .await
+    }
+
+    pub async fn create_one(&self, data: &T) -> Result<(), IntegrationOSError> {
+        self.inner
+            .create_one(data)
+            .timed(self.record("create_one", |_| Some(1)))
+            .await
+    }
+
+    pub async fn create_many(&self, data: &[T]) -> Result<(), IntegrationOSError> {
+        self.inner
+            .create_many(data)
+            .timed(self.record("create_many", |_| Some(data.len())))
+            .await
+    }
+
+    pub async fn update_one(&self, id: &str, data: Document) -> Result<(), IntegrationOSError> {
+        self.inner
+            .update_one(id, data)
+            .timed(self.record("update_one", |_| None))
+            .await
+    }
+
+    pub async fn update_many(
+        &self,
+        filter: Document,
+        data: Document,
+    ) -> Result<(), IntegrationOSError> {
+        self.inner
+            .update_many(filter, data)
+            .timed(self.record("update_many", |_| None))
+            .await
+    }
+
+    pub async fn count(
+        &self,
+        filter: Document,
+        limit: Option<u64>,
+    ) -> Result<u64, IntegrationOSError> {
+        self.inner
+            .count(filter, limit)
+            .timed(self.record("count", |_| None))
+            .await
+    }
+}
+
+/// Cache wrapper recording every operation in a [`MetricsRegistry`] under the cache name
+#[derive(Debug, Clone)]
+pub struct MeteredCache<C> {
+    inner: C,
+    name: String,
+    registry: MetricsRegistry,
+}
+
+impl<C> MeteredCache<C> {
+    pub fn new(inner: C, name: impl Into<String>, registry: MetricsRegistry) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            registry,
+        }
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn record<'a, R>(
+        &'a self,
+        operation: &'static str,
+        size: impl Fn(&R) -> Option<usize> + 'a,
+    ) -> impl FnMut(&Result<R, IntegrationOSError>, Duration) + 'a {
+        move |result, elapsed| {
+            self.registry.record(
+                MeteredTarget::Cache,
+                &self.name,
+                operation,
+                elapsed,
+                result.is_err(),
+                result.as_ref().ok().and_then(&size),
+            )
+        }
+    }
+}
+
+impl<C> Deref for MeteredCache<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<C: CacheExt + Send + Sync> CacheExt for MeteredCache<C> {
+    async fn get_or_insert_with<F>(
+        &self,
+        key: &str,
+        f: F,
+        expire: Option<u64>,
+    ) -> Result<CacheEntry, IntegrationOSError>
+    where
+        F: FnOnce() -> Result<CacheEntry, IntegrationOSError> + Send,
+    {
+        self.inner
+            .get_or_insert_with(key, f, expire)
+            .timed(self.record("get_or_insert_with", |_| None))
+            .await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, IntegrationOSError> {
+        self.inner
+            .get(key)
+            .timed(self.record("get", |r: &Option<CacheEntry>| Some(r.is_some() as usize)))
+            .await
+    }
+
+    async fn set(&self, entry: CacheEntry, expire: Option<u64>) -> Result<(), IntegrationOSError> {
+        self.inner
+            .set(entry, expire)
+            .timed(self.record("set", |_| None))
+            .await
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.inner
+            .remove(key)
+            .timed(self.record("remove", |_| None))
+            .await
+    }
+
+    async fn clear(&self) -> Result<(), IntegrationOSError> {
+        self.inner
+            .clear()
+            .timed(self.record("clear", |_| None))
+            .await
+    }
+
+    async fn expire(&self, key: &str, seconds: u64) -> Result<bool, IntegrationOSError> {
+        self.inner
+            .expire(key, seconds)
+            .timed(self.record("expire", |_| None))
+            .await
+    }
+
+    async fn list_push(&self, key: &str, value: &[u8]) -> Result<(), IntegrationOSError> {
+        self.inner
+            .list_push(key, value)
+            .timed(self.record("list_push", |_| None))
+            .await
+    }
+
+    async fn list_position(
+        &self,
+        key: &str,
+        value: &[u8],
+    ) -> Result<Option<usize>, IntegrationOSError> {
+        self.inner
+            .list_position(key, value)
+            .timed(self.record("list_position", |_| None))
+            .await
+    }
+
+    async fn list_len(&self, key: &str) -> Result<usize, IntegrationOSError> {
+        self.inner
+            .list_len(key)
+            .timed(self.record("list_len", |_| None))
+            .await
+    }
+
+    async fn list_move(
+        &self,
+        source: &str,
+        destination: &str,
+    ) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        self.inner
+            .list_move(source, destination)
+            .timed(self.record(
+                "list_move",
+                |r: &Option<Vec<u8>>| Some(r.is_some() as usize),
+            ))
+            .await
+    }
+
+    async fn list_remove(&self, key: &str, value: &[u8]) -> Result<bool, IntegrationOSError> {
+        self.inner
+            .list_remove(key, value)
+            .timed(self.record("list_remove", |_| None))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InMemoryCache;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_cache_operations_are_exported() {
+        let registry = MetricsRegistry::new();
+        let cache = registry.cache("local", InMemoryCache::default());
+
+        cache
+            .set(CacheEntry::new("a".to_owned(), json!(1)), None)
+            .await
+            .unwrap();
+        assert!(cache.get("a").await.unwrap().is_some());
+        assert!(cache.get("b").await.unwrap().is_none());
+
+        let metrics = registry.to_prometheus();
+        assert!(metrics.contains("# TYPE cache_operation_duration_seconds histogram"));
+        assert!(metrics.contains(
+            "cache_operation_duration_seconds_count{cache=\"local\",operation=\"get\"} 2"
+        ));
+        assert!(
+            metrics.contains("cache_operation_errors_total{cache=\"local\",operation=\"set\"} 0")
+        );
+        assert!(metrics.contains(
+            "cache_operation_result_size_bucket{cache=\"local\",operation=\"get\",le=\"0\"} 1"
+        ));
+        assert!(metrics
+            .contains("cache_operation_result_size_sum{cache=\"local\",operation=\"get\"} 1"));
+        assert!(!metrics.contains("store_operation"));
+        assert!(!metrics.contains("store_collection"));
+    }
+
+    #[test]
+    fn test_collection_stats_are_exported() {
+        let registry = MetricsRegistry::new();
+        registry.record_collection_stats(
+            "events",
+            CollectionStats {
+                count: 40,
+                size: 2000,
+                storage_size: 4000,
+                total_index_size: 200,
+                ..Default::default()
+            },
+        );
+
+        let metrics = registry.to_prometheus();
+        assert!(metrics.contains("# TYPE store_collection_documents gauge"));
+        assert!(metrics.contains("store_collection_documents{collection=\"events\"} 40"));
+        assert!(metrics.contains("store_collection_storage_bytes{collection=\"events\"} 4000"));
+        assert!(metrics.contains("store_collection_index_bytes{collection=\"events\"} 200"));
+    }
+}
@@ -1,20 +1,157 @@
+#[cfg(feature = "backend")]
+mod audit;
+#[cfg(feature = "backend")]
 mod cache;
+#[cfg(feature = "backend")]
+mod cardinality;
+#[cfg(all(feature = "backend", feature = "chaos"))]
+mod chaos;
+#[cfg(feature = "backend")]
+mod connection_lifecycle;
+#[cfg(feature = "backend")]
 mod crypto;
+#[cfg(feature = "backend")]
+mod egress;
+#[cfg(feature = "backend")]
+mod export;
+#[cfg(feature = "backend")]
 mod fetcher;
 mod hash;
+#[cfg(feature = "backend")]
+mod hooks;
+#[cfg(feature = "backend")]
+mod idempotency;
+#[cfg(feature = "backend")]
+mod kms;
+#[cfg(feature = "backend")]
+mod layered_cache;
+#[cfg(feature = "backend")]
+mod list_params;
+#[cfg(feature = "backend")]
+mod llm;
+#[cfg(feature = "backend")]
+mod message_bus;
+#[cfg(all(feature = "backend", feature = "metrics"))]
+mod metered;
+#[cfg(feature = "backend")]
+mod object_store;
+#[cfg(feature = "backend")]
+mod outbox;
+#[cfg(feature = "backend")]
+mod partitioned_store;
+#[cfg(feature = "backend")]
+mod patch;
+#[cfg(feature = "backend")]
 mod pipeline;
+#[cfg(feature = "backend")]
+mod profile;
+#[cfg(feature = "backend")]
+mod query;
+#[cfg(feature = "backend")]
+mod rate_limiter;
+#[cfg(feature = "backend")]
+mod replay;
+#[cfg(feature = "backend")]
+mod retry;
+#[cfg(feature = "backend")]
+mod semaphore;
+#[cfg(feature = "backend")]
+mod signature;
+#[cfg(all(feature = "backend", any(feature = "aws-kms", feature = "aws-s3")))]
+mod sigv4;
+#[cfg(feature = "backend")]
 mod store;
 mod string;
+#[cfg(feature = "backend")]
 mod template;
+#[cfg(feature = "backend")]
+mod throughput;
+#[cfg(feature = "backend")]
 mod timed;
+#[cfg(feature = "backend")]
+mod token_bucket;
+#[cfg(feature = "backend")]
+mod transaction;
+#[cfg(feature = "backend")]
+mod validate;
+#[cfg(feature = "backend")]
+mod watch;
 
+#[cfg(feature = "backend")]
+pub use audit::*;
+#[cfg(feature = "backend")]
 pub use cache::*;
+#[cfg(feature = "backend")]
+pub use cardinality::*;
+#[cfg(all(feature = "backend", feature = "chaos"))]
+pub use chaos::*;
+#[cfg(feature = "backend")]
+pub use connection_lifecycle::*;
+#[cfg(feature = "backend")]
 pub use crypto::*;
+#[cfg(feature = "backend")]
+pub use egress::*;
+#[cfg(feature = "backend")]
+pub use export::*;
+#[cfg(feature = "backend")]
 pub use fetcher::*;
 pub use hash::*;
+#[cfg(feature = "backend")]
+pub use hooks::*;
+#[cfg(feature = "backend")]
+pub use idempotency::*;
+#[cfg(feature = "backend")]
+pub use kms::*;
+#[cfg(feature = "backend")]
+pub use layered_cache::*;
+#[cfg(feature = "backend")]
+pub use list_params::*;
+#[cfg(feature = "backend")]
+pub use llm::*;
+#[cfg(feature = "backend")]
+pub use message_bus::*;
+#[cfg(all(feature = "backend", feature = "metrics"))]
+pub use metered::*;
+#[cfg(feature = "backend")]
+pub use object_store::*;
+#[cfg(feature = "backend")]
+pub use outbox::*;
+#[cfg(feature = "backend")]
+pub use partitioned_store::*;
+#[cfg(feature = "backend")]
+pub use patch::*;
+#[cfg(feature = "backend")]
 pub use pipeline::*;
+#[cfg(feature = "backend")]
+pub use profile::*;
+#[cfg(feature = "backend")]
+pub use query::*;
+#[cfg(feature = "backend")]
+pub use rate_limiter::*;
+#[cfg(feature = "backend")]
+pub use replay::*;
+#[cfg(feature = "backend")]
+pub use retry::*;
+#[cfg(feature = "backend")]
+pub use semaphore::*;
+#[cfg(feature = "backend")]
+pub use signature::*;
+#[cfg(all(feature = "backend", any(feature = "aws-kms", feature = "aws-s3")))]
+pub use sigv4::*;
+#[cfg(feature = "backend")]
 pub use store::*;
 pub use string::*;
+#[cfg(feature = "backend")]
 pub use template::*;
-#[cfg(feature = "metrics")]
+#[cfg(feature = "backend")]
+pub use throughput::*;
+#[cfg(all(feature = "backend", feature = "metrics"))]
 pub use timed::*;
+#[cfg(feature = "backend")]
+pub use token_bucket::*;
+#[cfg(feature = "backend")]
+pub use transaction::*;
+#[cfg(feature = "backend")]
+pub use validate::*;
+#[cfg(feature = "backend")]
+pub use watch::*;
@@ -0,0 +1,25 @@
+#[cfg(feature = "aws-s3")]
+mod s3;
+
+#[cfg(feature = "aws-s3")]
+pub use s3::*;
+
+use crate::IntegrationOSError;
+use async_trait::async_trait;
+
+/// Blob storage records are moved to once they are no longer queried often, e.g.
+/// archived events. Keys are `/` separated paths.
+#[async_trait]
+pub trait ObjectStoreExt: Send + Sync {
+    /// Writes the object, replacing any under the same key
+    async fn put(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), IntegrationOSError>;
+    /// `None` when there is no object under `key`
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, IntegrationOSError>;
+    /// Deleting a missing object succeeds
+    async fn delete(&self, key: &str) -> Result<(), IntegrationOSError>;
+}
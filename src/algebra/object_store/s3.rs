@@ -0,0 +1,202 @@
+use super::ObjectStoreExt;
+use crate::{
+    configuration::archive::S3Config, sigv4_authorization_for_path, sigv4_payload_hash,
+    IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::{Client, Method, Response, StatusCode, Url};
+
+const SERVICE: &str = "s3";
+
+/// Percent encodes everything but unreserved characters and the `/` separators
+fn encode_key(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Bucket of an S3 compatible object store, addressed path style and signed with static
+/// credentials
+#[derive(Debug, Clone)]
+pub struct S3ObjectStore {
+    config: S3Config,
+    client: Client,
+}
+
+impl S3ObjectStore {
+    pub fn new(config: S3Config) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<Response, IntegrationOSError> {
+        let endpoint = self.config.endpoint();
+        let path = format!("/{}/{}", self.config.bucket, encode_key(key));
+        let url = Url::parse(&format!("{}{path}", endpoint.trim_end_matches('/')))
+            .map_err(|e| InternalError::configuration_error(&e.to_string(), Some("s3")))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => {
+                return Err(InternalError::configuration_error(
+                    &format!("Invalid S3 endpoint {endpoint}"),
+                    Some("s3"),
+                ))
+            }
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = sigv4_payload_hash(&body);
+        let mut headers = vec![];
+        if let Some(content_type) = content_type {
+            headers.push(("content-type", content_type));
+        }
+        headers.push(("host", host.as_str()));
+        headers.push(("x-amz-content-sha256", payload_hash.as_str()));
+        headers.push(("x-amz-date", amz_date.as_str()));
+        if let Some(token) = self.config.session_token() {
+            headers.push(("x-amz-security-token", token));
+        }
+
+        let authorization = sigv4_authorization_for_path(
+            method.as_str(),
+            url.path(),
+            &headers,
+            &payload_hash,
+            &self.config.access_key_id,
+            self.config.secret_access_key.expose_secret(),
+            &self.config.region,
+            SERVICE,
+            now,
+        );
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("authorization", authorization)
+            .body(body);
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, *value);
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| InternalError::connection_error(&e.to_string(), Some("s3")))
+    }
+
+    async fn error(response: Response, action: &str, key: &str) -> IntegrationOSError {
+        let status = response.status();
+        let message = response.text().await.unwrap_or_default();
+        InternalError::io_err(
+            &format!("S3 {action} of {key} failed with {status}: {message}"),
+            Some("s3"),
+        )
+    }
+}
+
+#[async_trait]
+impl ObjectStoreExt for S3ObjectStore {
+    async fn put(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), IntegrationOSError> {
+        let response = self
+            .send(Method::PUT, key, body, Some(content_type))
+            .await?;
+        if !response.status().is_success() {
+            return Err(Self::error(response, "put", key).await);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        let response = self.send(Method::GET, key, vec![], None).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .bytes()
+                .await
+                .map(|bytes| Some(bytes.to_vec()))
+                .map_err(|e| InternalError::io_err(&e.to_string(), Some("s3"))),
+            _ => Err(Self::error(response, "get", key).await),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), IntegrationOSError> {
+        let response = self.send(Method::DELETE, key, vec![], None).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            _ => Err(Self::error(response, "delete", key).await),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use envconfig::Envconfig;
+    use mockito::{Matcher, Server};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_s3_object_round_trip() {
+        let mut server = Server::new_async().await;
+        let body = br#"{"id":"evt_1"}"#.to_vec();
+        server
+            .mock("PUT", "/archive/events/build%201/evt_1.json")
+            .match_header("content-type", "application/json")
+            .match_header("x-amz-content-sha256", sigv4_payload_hash(&body).as_str())
+            .match_header(
+                "authorization",
+                Matcher::Regex(r"^AWS4-HMAC-SHA256 Credential=AKID/\d{8}/eu-west-1/s3/".into()),
+            )
+            .match_body(body.clone())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/archive/events/build%201/evt_1.json")
+            .with_body(&body)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/archive/missing.json")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let config = S3Config::init_from_hashmap(&HashMap::from([
+            ("ARCHIVE_S3_BUCKET".to_owned(), "archive".to_owned()),
+            ("ARCHIVE_S3_REGION".to_owned(), "eu-west-1".to_owned()),
+            ("ARCHIVE_S3_ENDPOINT".to_owned(), server.url()),
+            ("AWS_ACCESS_KEY_ID".to_owned(), "AKID".to_owned()),
+            ("AWS_SECRET_ACCESS_KEY".to_owned(), "secret".to_owned()),
+        ]))
+        .unwrap();
+        let store = S3ObjectStore::new(config);
+        let key = "events/build 1/evt_1.json";
+
+        store
+            .put(key, body.clone(), "application/json")
+            .await
+            .unwrap();
+        assert_eq!(store.get(key).await.unwrap(), Some(body));
+        assert_eq!(store.get("missing.json").await.unwrap(), None);
+    }
+}
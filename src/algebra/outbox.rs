@@ -0,0 +1,35 @@
+use crate::{
+    prelude::queue::OutboxMessage, IntegrationOSError, MongoStore, TransactionContext,
+    TransactionalStore,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Inserts `record` and the messages announcing it in one transaction, so a message is
+/// published if and only if the record was written. The messages are then picked up by
+/// the [`OutboxRelay`](crate::prelude::outbox_relay::OutboxRelay).
+pub async fn write_with_outbox<T>(
+    store: &MongoStore<T>,
+    outbox: &MongoStore<OutboxMessage>,
+    record: T,
+    messages: Vec<OutboxMessage>,
+) -> Result<(), IntegrationOSError>
+where
+    T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    let store = MongoStore {
+        collection: store.collection.clone(),
+    };
+    let outbox = outbox.clone();
+    outbox
+        .clone()
+        .with_transaction(move |transaction: &mut TransactionContext| {
+            Box::pin(async move {
+                transaction.create_one(&store, &record).await?;
+                for message in &messages {
+                    transaction.create_one(&outbox, message).await?;
+                }
+                Ok(())
+            })
+        })
+        .await
+}
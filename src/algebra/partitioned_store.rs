@@ -0,0 +1,230 @@
+use crate::{prelude::event::Event, IntegrationOSError, MongoStore, Store};
+use bson::{doc, Document};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use futures::TryStreamExt;
+use mongodb::{options::FindOptions, Database};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::info;
+
+/// Record routed to a monthly partition by one of its timestamps
+pub trait Partitioned {
+    /// Field holding the timestamp, in milliseconds
+    const TIME_FIELD: &'static str;
+
+    fn partition_time(&self) -> DateTime<Utc>;
+}
+
+impl Partitioned for Event {
+    const TIME_FIELD: &'static str = "arrivedAt";
+
+    fn partition_time(&self) -> DateTime<Utc> {
+        self.arrived_at
+    }
+}
+
+fn month_start(year: i32, month: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or_default()
+}
+
+fn next_month(time: DateTime<Utc>) -> DateTime<Utc> {
+    match time.month() {
+        12 => month_start(time.year() + 1, 1),
+        month => month_start(time.year(), month + 1),
+    }
+}
+
+/// Collection per calendar month, e.g. `external-events-2024-03`. Writes go to the month
+/// of the record and reads fan out over the months of the requested range, newest first.
+#[derive(Debug, Clone)]
+pub struct PartitionedStore<T> {
+    database: Database,
+    base: String,
+    _record: std::marker::PhantomData<T>,
+}
+
+impl<T> PartitionedStore<T>
+where
+    T: Partitioned + Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    pub fn new(database: &Database, store: &Store) -> Self {
+        Self {
+            database: database.clone(),
+            base: store.to_string(),
+            _record: std::marker::PhantomData,
+        }
+    }
+
+    pub fn partition_name(&self, time: DateTime<Utc>) -> String {
+        format!("{}-{:04}-{:02}", self.base, time.year(), time.month())
+    }
+
+    /// Names of the partitions covering `[from, to]`, newest first
+    pub fn partitions_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<String> {
+        let mut names = vec![];
+        let mut month = month_start(from.year(), from.month());
+        while month <= to {
+            names.push(self.partition_name(month));
+            month = next_month(month);
+        }
+        names.reverse();
+        names
+    }
+
+    pub fn partition(&self, time: DateTime<Utc>) -> MongoStore<T> {
+        MongoStore {
+            collection: self.database.collection(&self.partition_name(time)),
+        }
+    }
+
+    /// Existing partitions, newest first
+    pub async fn partitions(&self) -> Result<Vec<String>, IntegrationOSError> {
+        let pattern = format!(r"^{}-\d{{4}}-\d{{2}}$", regex_escape(&self.base));
+        let mut names = self
+            .database
+            .list_collection_names(doc! { "name": { "$regex": pattern } })
+            .await?;
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(names)
+    }
+
+    pub async fn create_one(&self, record: &T) -> Result<(), IntegrationOSError> {
+        self.partition(record.partition_time())
+            .create_one(record)
+            .await
+    }
+
+    /// Writes the records, grouped by partition
+    pub async fn create_many(&self, records: &[T]) -> Result<(), IntegrationOSError>
+    where
+        T: Clone,
+    {
+        let mut by_partition: Vec<(String, Vec<T>)> = vec![];
+        for record in records {
+            let name = self.partition_name(record.partition_time());
+            match by_partition.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, records)) => records.push(record.clone()),
+                None => by_partition.push((name, vec![record.clone()])),
+            }
+        }
+        for (name, records) in by_partition {
+            self.database
+                .collection::<T>(&name)
+                .insert_many(records, None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Records in `[from, to]` matching `filter`, newest first. Partitions are read in
+    /// order until `limit` records were found.
+    pub async fn get_many(
+        &self,
+        filter: Option<Document>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        let mut filter = filter.unwrap_or_default();
+        filter.insert(
+            T::TIME_FIELD,
+            doc! { "$gte": from.timestamp_millis(), "$lte": to.timestamp_millis() },
+        );
+
+        let mut records = vec![];
+        for name in self.partitions_between(from, to) {
+            let remaining = match limit {
+                Some(limit) if records.len() as u64 >= limit => break,
+                Some(limit) => Some(limit as i64 - records.len() as i64),
+                None => None,
+            };
+            let options = FindOptions::builder()
+                .sort(doc! { T::TIME_FIELD: -1 })
+                .limit(remaining)
+                .build();
+            let cursor = self
+                .database
+                .collection::<T>(&name)
+                .find(filter.clone(), options)
+                .await?;
+            records.extend(cursor.try_collect::<Vec<_>>().await?);
+        }
+        Ok(records)
+    }
+
+    /// Looks the record up in every partition, newest first
+    pub async fn get_one_by_id(&self, id: &str) -> Result<Option<T>, IntegrationOSError> {
+        for name in self.partitions().await? {
+            let record = self
+                .database
+                .collection::<T>(&name)
+                .find_one(doc! { "_id": id }, None)
+                .await?;
+            if record.is_some() {
+                return Ok(record);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Drops the partitions entirely before `cutoff`, returning their names
+    pub async fn drop_before(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> Result<Vec<String>, IntegrationOSError> {
+        let current = self.partition_name(cutoff);
+        let mut dropped = vec![];
+        for name in self.partitions().await? {
+            if name < current {
+                self.database
+                    .collection::<Document>(&name)
+                    .drop(None)
+                    .await?;
+                info!("Dropped partition {name}");
+                dropped.push(name);
+            }
+        }
+        Ok(dropped)
+    }
+}
+
+fn regex_escape(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| match c {
+            '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' | '^' | '$' | '\\' => {
+                vec!['\\', c]
+            }
+            c => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_partitions_between() {
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let store =
+            PartitionedStore::<Event>::new(&client.database("events-service"), &Store::Events);
+
+        let from = Utc.with_ymd_and_hms(2023, 11, 15, 8, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            store.partitions_between(from, to),
+            vec![
+                "external-events-2024-02",
+                "external-events-2024-01",
+                "external-events-2023-12",
+                "external-events-2023-11",
+            ]
+        );
+        assert_eq!(store.partition_name(to), "external-events-2024-02");
+        assert!(store.partitions_between(to, from).is_empty());
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{IntegrationOSError, InternalError};
+use bson::{doc, Bson, Document};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// A single RFC 6902 operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "op")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    /// RFC 6902 JSON Patch
+    Json(Vec<PatchOperation>),
+    /// RFC 7386 JSON Merge Patch
+    Merge(Value),
+}
+
+impl Patch {
+    pub fn from_content_type(content_type: &str, body: Value) -> Result<Self, IntegrationOSError> {
+        match content_type.split(';').next().map(str::trim) {
+            Some(JSON_PATCH_CONTENT_TYPE) => serde_json::from_value(body)
+                .map(Patch::Json)
+                .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("json_patch"))),
+            Some(MERGE_PATCH_CONTENT_TYPE) | Some("application/json") => Ok(Patch::Merge(body)),
+            _ => Err(InternalError::invalid_argument(
+                &format!("Unsupported patch content type: {content_type}"),
+                Some("json_patch"),
+            )),
+        }
+    }
+}
+
+/// Decides which fields a PATCH request is allowed to touch. Paths use the dotted
+/// notation of the stored document, e.g. `settings.oauth`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PatchPolicy {
+    allowed: Vec<String>,
+    denied: Vec<String>,
+}
+
+impl PatchPolicy {
+    pub fn allow<I, S>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: fields.into_iter().map(Into::into).collect(),
+            denied: ["_id", "createdAt", "ownership"]
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    pub fn deny(mut self, field: &str) -> Self {
+        self.denied.push(field.to_owned());
+        self
+    }
+
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let matches = |prefix: &String| {
+            path == prefix || path.starts_with(&format!("{prefix}.")) || prefix.is_empty()
+        };
+        !self.denied.iter().any(matches) && self.allowed.iter().any(matches)
+    }
+}
+
+/// Result of applying a patch: the patched model and the equivalent Mongo update document
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchOutcome<T> {
+    pub value: T,
+    pub update: Document,
+}
+
+pub trait PatchExt: Serialize + DeserializeOwned {
+    fn apply_patch(
+        &self,
+        patch: &Patch,
+        policy: &PatchPolicy,
+    ) -> Result<PatchOutcome<Self>, IntegrationOSError> {
+        let original = serde_json::to_value(self)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("json_patch")))?;
+        let mut patched = original.clone();
+
+        let touched = match patch {
+            Patch::Json(operations) => {
+                let mut touched = vec![];
+                for operation in operations {
+                    touched.extend(apply_operation(&mut patched, operation)?);
+                }
+                touched
+            }
+            Patch::Merge(merge) => {
+                let mut touched = vec![];
+                merge_patch(&mut patched, merge, &mut vec![], &mut touched);
+                touched
+            }
+        };
+
+        let paths = normalize_paths(&original, &patched, touched);
+        if let Some(path) = paths.iter().find(|p| !policy.is_allowed(&p.join("."))) {
+            return Err(InternalError::invalid_argument(
+                &format!("Field {} cannot be patched", path.join(".")),
+                Some("json_patch"),
+            ));
+        }
+
+        let update = update_document(&patched, &paths)?;
+        let value = serde_json::from_value(patched)
+            .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("json_patch")))?;
+
+        Ok(PatchOutcome { value, update })
+    }
+}
+
+impl<T> PatchExt for T where T: Serialize + DeserializeOwned {}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, IntegrationOSError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    if !pointer.starts_with('/') {
+        return Err(InternalError::invalid_argument(
+            &format!("Invalid JSON pointer: {pointer}"),
+            Some("json_patch"),
+        ));
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|s| s.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+fn not_found(path: &[String]) -> IntegrationOSError {
+    InternalError::invalid_argument(
+        &format!("Path /{} does not exist", path.join("/")),
+        Some("json_patch"),
+    )
+}
+
+fn parent_mut<'a>(
+    target: &'a mut Value,
+    path: &[String],
+) -> Result<(&'a mut Value, String), IntegrationOSError> {
+    let (last, parents) = path.split_last().ok_or_else(|| {
+        InternalError::invalid_argument("The document root cannot be patched", Some("json_patch"))
+    })?;
+    let mut current = target;
+    for segment in parents {
+        current = match current {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| not_found(path))?;
+    }
+    Ok((current, last.to_owned()))
+}
+
+fn get<'a>(target: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(target, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn add(target: &mut Value, path: &[String], value: Value) -> Result<(), IntegrationOSError> {
+    let (parent, key) = parent_mut(target, path)?;
+    match parent {
+        Value::Object(map) => {
+            map.insert(key, value);
+        }
+        Value::Array(items) if key == "-" => items.push(value),
+        Value::Array(items) => match key.parse::<usize>() {
+            Ok(index) if index <= items.len() => items.insert(index, value),
+            _ => return Err(not_found(path)),
+        },
+        _ => return Err(not_found(path)),
+    }
+    Ok(())
+}
+
+fn remove(target: &mut Value, path: &[String]) -> Result<Value, IntegrationOSError> {
+    let (parent, key) = parent_mut(target, path)?;
+    match parent {
+        Value::Object(map) => map.remove(&key),
+        Value::Array(items) => match key.parse::<usize>() {
+            Ok(index) if index < items.len() => Some(items.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    }
+    .ok_or_else(|| not_found(path))
+}
+
+pub(crate) fn apply_operation(
+    target: &mut Value,
+    operation: &PatchOperation,
+) -> Result<Vec<Vec<String>>, IntegrationOSError> {
+    match operation {
+        PatchOperation::Add { path, value } => {
+            let path = parse_pointer(path)?;
+            add(target, &path, value.clone())?;
+            Ok(vec![path])
+        }
+        PatchOperation::Remove { path } => {
+            let path = parse_pointer(path)?;
+            remove(target, &path)?;
+            Ok(vec![path])
+        }
+        PatchOperation::Replace { path, value } => {
+            let path = parse_pointer(path)?;
+            remove(target, &path)?;
+            add(target, &path, value.clone())?;
+            Ok(vec![path])
+        }
+        PatchOperation::Move { from, path } => {
+            let from = parse_pointer(from)?;
+            let path = parse_pointer(path)?;
+            let value = remove(target, &from)?;
+            add(target, &path, value)?;
+            Ok(vec![from, path])
+        }
+        PatchOperation::Copy { from, path } => {
+            let from = parse_pointer(from)?;
+            let path = parse_pointer(path)?;
+            let value = get(target, &from)
+                .cloned()
+                .ok_or_else(|| not_found(&from))?;
+            add(target, &path, value)?;
+            Ok(vec![path])
+        }
+        PatchOperation::Test { path, value } => {
+            let path = parse_pointer(path)?;
+            match get(target, &path) {
+                Some(current) if current == value => Ok(vec![]),
+                _ => Err(InternalError::invalid_argument(
+                    &format!("Test failed for path /{}", path.join("/")),
+                    Some("json_patch"),
+                )),
+            }
+        }
+    }
+}
+
+fn merge_patch(
+    target: &mut Value,
+    patch: &Value,
+    path: &mut Vec<String>,
+    touched: &mut Vec<Vec<String>>,
+) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        touched.push(path.clone());
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+        touched.push(path.clone());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            path.push(key.to_owned());
+            if value.is_null() {
+                if map.remove(key).is_some() {
+                    touched.push(path.clone());
+                }
+            } else {
+                merge_patch(
+                    map.entry(key.to_owned()).or_insert(Value::Null),
+                    value,
+                    path,
+                    touched,
+                );
+            }
+            path.pop();
+        }
+    }
+}
+
+/// Mongo cannot express positional inserts/removals with `$set`, so any path going
+/// through an array is widened to the array itself. Nested paths covered by a shorter
+/// one are dropped to avoid conflicting update operators.
+fn normalize_paths(
+    original: &Value,
+    patched: &Value,
+    touched: Vec<Vec<String>>,
+) -> Vec<Vec<String>> {
+    let mut paths: Vec<Vec<String>> = touched
+        .into_iter()
+        .map(|path| {
+            let mut normalized = vec![];
+            for segment in path {
+                let is_array = |doc: &Value| get(doc, &normalized).is_some_and(Value::is_array);
+                if !normalized.is_empty() && (is_array(original) || is_array(patched)) {
+                    break;
+                }
+                normalized.push(segment);
+            }
+            normalized
+        })
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    paths.sort();
+    paths.dedup();
+    let mut result: Vec<Vec<String>> = vec![];
+    for path in paths {
+        if !result.iter().any(|p| path.starts_with(p)) {
+            result.push(path);
+        }
+    }
+    result
+}
+
+fn update_document(patched: &Value, paths: &[Vec<String>]) -> Result<Document, IntegrationOSError> {
+    let mut set = Document::new();
+    let mut unset = Document::new();
+    for path in paths {
+        let key = path.join(".");
+        match get(patched, path) {
+            Some(value) => {
+                let value = bson::to_bson(value).map_err(|e| {
+                    InternalError::serialize_error(&e.to_string(), Some("json_patch"))
+                })?;
+                set.insert(key, value);
+            }
+            None => {
+                unset.insert(key, Bson::String(String::new()));
+            }
+        }
+    }
+
+    let mut update = doc! {};
+    if !set.is_empty() {
+        update.insert("$set", set);
+    }
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    Ok(update)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Model {
+        #[serde(rename = "_id")]
+        id: String,
+        name: String,
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        settings: Value,
+    }
+
+    fn model() -> Model {
+        Model {
+            id: "id".to_owned(),
+            name: "name".to_owned(),
+            tags: vec!["a".to_owned()],
+            description: Some("description".to_owned()),
+            settings: json!({ "oauth": false, "showSecret": true }),
+        }
+    }
+
+    #[test]
+    fn test_json_patch_to_update_document() {
+        let patch = Patch::from_content_type(
+            JSON_PATCH_CONTENT_TYPE,
+            json!([
+                { "op": "replace", "path": "/name", "value": "new" },
+                { "op": "add", "path": "/tags/0", "value": "b" },
+                { "op": "remove", "path": "/description" },
+                { "op": "replace", "path": "/settings/oauth", "value": true },
+                { "op": "test", "path": "/settings/showSecret", "value": true }
+            ]),
+        )
+        .unwrap();
+        let policy = PatchPolicy::allow(["name", "tags", "description", "settings"]);
+
+        let outcome = model().apply_patch(&patch, &policy).unwrap();
+        assert_eq!(outcome.value.name, "new");
+        assert_eq!(outcome.value.tags, vec!["b", "a"]);
+        assert_eq!(outcome.value.description, None);
+        assert_eq!(
+            outcome.update,
+            doc! {
+                "$set": { "name": "new", "settings.oauth": true, "tags": ["b", "a"] },
+                "$unset": { "description": "" },
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_to_update_document() {
+        let patch = Patch::from_content_type(
+            MERGE_PATCH_CONTENT_TYPE,
+            json!({ "description": null, "settings": { "oauth": true } }),
+        )
+        .unwrap();
+        let policy = PatchPolicy::allow(["description", "settings"]);
+
+        let outcome = model().apply_patch(&patch, &policy).unwrap();
+        assert_eq!(
+            outcome.value.settings,
+            json!({ "oauth": true, "showSecret": true })
+        );
+        assert_eq!(
+            outcome.update,
+            doc! { "$set": { "settings.oauth": true }, "$unset": { "description": "" } }
+        );
+    }
+
+    #[test]
+    fn test_patch_policy_rejects_forbidden_fields() {
+        let policy = PatchPolicy::allow(["settings"]).deny("settings.showSecret");
+        let patch = Patch::Merge(json!({ "name": "other" }));
+        assert!(model().apply_patch(&patch, &policy).is_err());
+
+        let patch = Patch::Merge(json!({ "settings": { "showSecret": false } }));
+        assert!(model().apply_patch(&patch, &policy).is_err());
+
+        let patch = Patch::Json(vec![PatchOperation::Replace {
+            path: "/_id".to_owned(),
+            value: json!("other"),
+        }]);
+        assert!(model()
+            .apply_patch(&patch, &PatchPolicy::allow([""]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_json_patch_failed_test_operation() {
+        let patch = Patch::Json(vec![PatchOperation::Test {
+            path: "/name".to_owned(),
+            value: json!("other"),
+        }]);
+        assert!(model()
+            .apply_patch(&patch, &PatchPolicy::allow(["name"]))
+            .is_err());
+    }
+}
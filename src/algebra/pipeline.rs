@@ -1,6 +1,7 @@
-use crate::id::Id;
+use crate::{id::Id, prelude::shared::unknown_variant};
 use downcast_rs::{impl_downcast, Downcast};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::fmt::{Debug, Display};
 
 pub trait PipelineExt: Downcast + Sync + Send + Debug {
@@ -12,7 +13,12 @@ impl_downcast!(PipelineExt);
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PipelineStatus {
     Succeeded,
-    Dropped { reason: String },
+    Dropped {
+        reason: String,
+    },
+    /// Status written by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
 
 impl Display for PipelineStatus {
@@ -22,6 +28,7 @@ impl Display for PipelineStatus {
             Self::Dropped { reason } => {
                 write!(f, "Dropped {{ {reason} }}")
             }
+            Self::Unknown(status) => write!(f, "Unknown({status})"),
         }
     }
 }
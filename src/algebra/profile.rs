@@ -0,0 +1,291 @@
+use crate::{
+    prelude::{connection::Connection, event::Event},
+    IntegrationOSError, InternalError, StringExt,
+};
+use bson::Document;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Casing {
+    Camel,
+    Snake,
+}
+
+impl Casing {
+    fn apply(&self, key: &str) -> String {
+        // Mongo reserved keys such as `_id` keep their shape regardless of the casing
+        if key.starts_with('_') {
+            return key.to_owned();
+        }
+        match self {
+            Casing::Camel => key.camel_case(),
+            Casing::Snake => key.snake_case(),
+        }
+    }
+}
+
+/// Describes how a domain model is rendered for a given audience. Storage keeps the
+/// camelCase produced by the serde derives, while public APIs can opt into snake_case
+/// without changing the `rename_all` attributes of the models.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializationProfile {
+    pub casing: Casing,
+    /// Fields whose nested keys are user data (headers, payloads, ...) and must be kept verbatim
+    pub opaque_fields: Vec<String>,
+}
+
+impl SerializationProfile {
+    pub fn internal() -> Self {
+        Self {
+            casing: Casing::Camel,
+            opaque_fields: vec![],
+        }
+    }
+
+    pub fn public_api() -> Self {
+        Self {
+            casing: Casing::Snake,
+            opaque_fields: [
+                "headers",
+                "body",
+                "settings",
+                "mapping",
+                "metadata",
+                "change_log",
+            ]
+            .into_iter()
+            .map(str::to_owned)
+            .collect(),
+        }
+    }
+
+    pub fn with_opaque_field(mut self, field: &str) -> Self {
+        self.opaque_fields.push(field.to_owned());
+        self
+    }
+
+    pub fn is_internal(&self) -> bool {
+        self.casing == Casing::Camel
+    }
+
+    /// Whether `key` names one of `fields`, in either casing since values are rekeyed both
+    /// ways
+    fn is_opaque(key: &str, fields: &[impl AsRef<str>]) -> bool {
+        let key = Casing::Snake.apply(key);
+        fields
+            .iter()
+            .any(|field| Casing::Snake.apply(field.as_ref()) == key)
+    }
+
+    fn rekey(&self, value: Value, casing: Casing, opaque_fields: &[&str]) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| {
+                        let opaque = Self::is_opaque(&k, &self.opaque_fields)
+                            || Self::is_opaque(&k, opaque_fields);
+                        let key = casing.apply(&k);
+                        let v = if opaque {
+                            v
+                        } else {
+                            self.rekey(v, casing, opaque_fields)
+                        };
+                        (key, v)
+                    })
+                    .collect::<Map<String, Value>>(),
+            ),
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|v| self.rekey(v, casing, opaque_fields))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
+
+/// Conversions between the profiles, implemented by the models whose serde names are
+/// the camelCase of their fields so that rekeying round trips. Models with other renames,
+/// such as the UPPERCASE keys of secrets, must not implement it, or list the fields
+/// holding them in [`ProfileExt::OPAQUE_FIELDS`].
+pub trait ProfileExt: Serialize + DeserializeOwned {
+    /// Fields of the model kept verbatim, on top of the opaque fields of the profile
+    const OPAQUE_FIELDS: &'static [&'static str] = &[];
+
+    fn to_profile_value(
+        &self,
+        profile: &SerializationProfile,
+    ) -> Result<Value, IntegrationOSError> {
+        let value = serde_json::to_value(self).map_err(|e| {
+            InternalError::serialize_error(&e.to_string(), Some("serialization_profile"))
+        })?;
+        if profile.is_internal() {
+            return Ok(value);
+        }
+        Ok(profile.rekey(value, profile.casing, Self::OPAQUE_FIELDS))
+    }
+
+    fn from_profile_value(
+        value: Value,
+        profile: &SerializationProfile,
+    ) -> Result<Self, IntegrationOSError> {
+        let value = if profile.is_internal() {
+            value
+        } else {
+            profile.rekey(value, Casing::Camel, Self::OPAQUE_FIELDS)
+        };
+        serde_json::from_value(value).map_err(|e| {
+            InternalError::deserialize_error(&e.to_string(), Some("serialization_profile"))
+        })
+    }
+
+    fn to_internal_document(&self) -> Result<Document, IntegrationOSError> {
+        bson::to_document(self).map_err(|e| {
+            InternalError::serialize_error(&e.to_string(), Some("serialization_profile"))
+        })
+    }
+
+    fn to_public_json(&self) -> Result<Value, IntegrationOSError> {
+        self.to_profile_value(&SerializationProfile::public_api())
+    }
+}
+
+impl ProfileExt for Connection {
+    /// The fields of its enabled variant aren't renamed
+    const OPAQUE_FIELDS: &'static [&'static str] = &["oauth"];
+}
+
+impl ProfileExt for Event {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::shared::record_metadata::RecordMetadata;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Model {
+        #[serde(rename = "_id")]
+        id: String,
+        platform_version: String,
+        headers: Value,
+        #[serde(flatten)]
+        record_metadata: RecordMetadata,
+    }
+
+    impl ProfileExt for Model {}
+
+    #[test]
+    fn test_public_profile_round_trip() {
+        let model = Model {
+            id: "id".to_owned(),
+            platform_version: "v1".to_owned(),
+            headers: json!({ "X-Custom": "foo" }),
+            record_metadata: RecordMetadata::default(),
+        };
+
+        let value = model.to_public_json().unwrap();
+        assert_eq!(value["_id"], "id");
+        assert_eq!(value["platform_version"], "v1");
+        assert_eq!(value["headers"], json!({ "X-Custom": "foo" }));
+        assert!(value.get("created_at").is_some());
+        assert!(value.get("createdAt").is_none());
+
+        let back = Model::from_profile_value(value, &SerializationProfile::public_api()).unwrap();
+        assert_eq!(back, model);
+    }
+
+    #[test]
+    fn test_internal_profile_is_identity() {
+        let model = Model {
+            id: "id".to_owned(),
+            platform_version: "v1".to_owned(),
+            headers: json!({}),
+            record_metadata: RecordMetadata::default(),
+        };
+
+        let value = model
+            .to_profile_value(&SerializationProfile::internal())
+            .unwrap();
+        assert_eq!(value, serde_json::to_value(&model).unwrap());
+        assert!(model
+            .to_internal_document()
+            .unwrap()
+            .contains_key("platformVersion"));
+    }
+
+    #[test]
+    fn test_domain_models_round_trip() {
+        use crate::{
+            id::{prefix::IdPrefix, Id},
+            prelude::{
+                access_key::{
+                    access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+                    encrypted_access_key::EncryptedAccessKey, event_type::EventType, AccessKey,
+                },
+                configuration::environment::Environment,
+                connection::{connection_auth::ConnectionAuth, OAuth},
+            },
+        };
+
+        let mut connection =
+            Connection::builder("stripe", Id::now(IdPrefix::ConnectionDefinition)).build();
+        connection.oauth = Some(OAuth::Enabled {
+            connection_oauth_definition_id: Id::now(IdPrefix::ConnectionOAuthDefinition),
+            expires_in: Some(3600),
+            expires_at: None,
+        });
+        connection.auth = Some(ConnectionAuth::ApiKey {
+            header: "X-Api-Key".to_owned(),
+            prefix: None,
+        });
+        connection.pause("alice", Some("Too many errors")).unwrap();
+
+        let value = connection.to_public_json().unwrap();
+        assert!(value.get("connection_definition_id").is_some());
+        assert!(value["oauth"]["enabled"]
+            .get("connection_oauth_definition_id")
+            .is_some());
+        let back =
+            Connection::from_profile_value(value, &SerializationProfile::public_api()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&connection).unwrap()
+        );
+
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "foo".to_owned(),
+                event_type: "bar".to_owned(),
+                group: "baz".to_owned(),
+                namespace: "qux".to_owned(),
+                event_path: "quux".to_owned(),
+                event_object_id_path: None,
+                timestamp_path: None,
+                parent_access_key: None,
+                expires_at: None,
+            },
+        };
+        let event = Event::new(
+            &access_key,
+            &EncryptedAccessKey::parse("id_test_1_foo").unwrap(),
+            "customer.created",
+            Default::default(),
+            r#"{"customerId":"cus_123"}"#.to_owned(),
+        )
+        .with_idempotency_key("order-1");
+
+        let value = event.to_public_json().unwrap();
+        assert_eq!(value["idempotency_key"], "order-1");
+        let back = Event::from_profile_value(value, &SerializationProfile::public_api()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&event).unwrap()
+        );
+    }
+}
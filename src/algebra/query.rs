@@ -0,0 +1,471 @@
+use crate::{IntegrationOSError, InternalError};
+use bson::{doc, Bson, Document};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Backend agnostic filter. Each store translates it to its native representation through
+/// a [`QueryBackend`], so filters can be built once and run against Mongo or SQL stores.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "op")]
+pub enum Filter {
+    Eq { field: String, value: Value },
+    Ne { field: String, value: Value },
+    Gt { field: String, value: Value },
+    Gte { field: String, value: Value },
+    Lt { field: String, value: Value },
+    Lte { field: String, value: Value },
+    In { field: String, values: Vec<Value> },
+    Nin { field: String, values: Vec<Value> },
+    Exists { field: String, exists: bool },
+    And { filters: Vec<Filter> },
+    Or { filters: Vec<Filter> },
+    Not { filter: Box<Filter> },
+}
+
+impl Filter {
+    pub fn all() -> Self {
+        Filter::And { filters: vec![] }
+    }
+
+    pub fn eq(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Eq {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn ne(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Ne {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn gt(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Gt {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn gte(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Gte {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn lt(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Lt {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn lte(field: &str, value: impl Into<Value>) -> Self {
+        Filter::Lte {
+            field: field.to_owned(),
+            value: value.into(),
+        }
+    }
+
+    pub fn is_in<V: Into<Value>>(field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::In {
+            field: field.to_owned(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn not_in<V: Into<Value>>(field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::Nin {
+            field: field.to_owned(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn exists(field: &str, exists: bool) -> Self {
+        Filter::Exists {
+            field: field.to_owned(),
+            exists,
+        }
+    }
+
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::And {
+            filters: filters.into_iter().collect(),
+        }
+    }
+
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::Or {
+            filters: filters.into_iter().collect(),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(filter: Filter) -> Self {
+        Filter::Not {
+            filter: Box::new(filter),
+        }
+    }
+
+    /// Every field the filter refers to, including nested ones
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            Filter::Eq { field, .. }
+            | Filter::Ne { field, .. }
+            | Filter::Gt { field, .. }
+            | Filter::Gte { field, .. }
+            | Filter::Lt { field, .. }
+            | Filter::Lte { field, .. }
+            | Filter::In { field, .. }
+            | Filter::Nin { field, .. }
+            | Filter::Exists { field, .. } => vec![field.as_str()],
+            Filter::And { filters } | Filter::Or { filters } => {
+                filters.iter().flat_map(Filter::fields).collect()
+            }
+            Filter::Not { filter } => filter.fields(),
+        }
+    }
+
+    /// Every value the filter compares against, including those of `in` lists
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Filter::Eq { value, .. }
+            | Filter::Ne { value, .. }
+            | Filter::Gt { value, .. }
+            | Filter::Gte { value, .. }
+            | Filter::Lt { value, .. }
+            | Filter::Lte { value, .. } => vec![value],
+            Filter::In { values, .. } | Filter::Nin { values, .. } => values.iter().collect(),
+            Filter::Exists { .. } => vec![],
+            Filter::And { filters } | Filter::Or { filters } => {
+                filters.iter().flat_map(Filter::values).collect()
+            }
+            Filter::Not { filter } => filter.values(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sort {
+    pub fields: Vec<(String, SortDirection)>,
+}
+
+impl Sort {
+    pub fn asc(field: &str) -> Self {
+        Self::default().then_asc(field)
+    }
+
+    pub fn desc(field: &str) -> Self {
+        Self::default().then_desc(field)
+    }
+
+    pub fn then_asc(mut self, field: &str) -> Self {
+        self.fields.push((field.to_owned(), SortDirection::Asc));
+        self
+    }
+
+    pub fn then_desc(mut self, field: &str) -> Self {
+        self.fields.push((field.to_owned(), SortDirection::Desc));
+        self
+    }
+}
+
+pub trait QueryBackend {
+    type Filter;
+    type Sort;
+
+    fn filter(filter: &Filter) -> Result<Self::Filter, IntegrationOSError>;
+    fn sort(sort: &Sort) -> Result<Self::Sort, IntegrationOSError>;
+}
+
+pub struct MongoQuery;
+
+fn to_bson(value: &Value) -> Result<Bson, IntegrationOSError> {
+    bson::to_bson(value).map_err(|e| InternalError::serialize_error(&e.to_string(), Some("query")))
+}
+
+fn to_bson_array(values: &[Value]) -> Result<Bson, IntegrationOSError> {
+    values
+        .iter()
+        .map(to_bson)
+        .collect::<Result<Vec<_>, _>>()
+        .map(Bson::Array)
+}
+
+/// Rejects field paths Mongo would read as operators, e.g. `$where`, or that have empty
+/// segments
+fn mongo_field(field: &str) -> Result<&str, IntegrationOSError> {
+    if field
+        .split('.')
+        .any(|part| part.is_empty() || part.starts_with('$') || part.contains('\0'))
+    {
+        return Err(InternalError::invalid_argument(
+            &format!("Invalid field name {field}"),
+            Some("query"),
+        ));
+    }
+    Ok(field)
+}
+
+impl MongoQuery {
+    fn filters(filters: &[Filter]) -> Result<Vec<Document>, IntegrationOSError> {
+        filters.iter().map(Self::filter).collect()
+    }
+}
+
+impl QueryBackend for MongoQuery {
+    type Filter = Document;
+    type Sort = Document;
+
+    fn filter(filter: &Filter) -> Result<Document, IntegrationOSError> {
+        // Equality goes through `$eq` too, so an object value such as `{"$ne": null}` is
+        // matched literally instead of being read as an operator
+        let operator = |field: &str, op: &str, value: Bson| {
+            let mut document = Document::new();
+            document.insert(mongo_field(field)?, doc! { op: value });
+            Ok::<_, IntegrationOSError>(document)
+        };
+
+        Ok(match filter {
+            Filter::Eq { field, value } => operator(field, "$eq", to_bson(value)?)?,
+            Filter::Ne { field, value } => operator(field, "$ne", to_bson(value)?)?,
+            Filter::Gt { field, value } => operator(field, "$gt", to_bson(value)?)?,
+            Filter::Gte { field, value } => operator(field, "$gte", to_bson(value)?)?,
+            Filter::Lt { field, value } => operator(field, "$lt", to_bson(value)?)?,
+            Filter::Lte { field, value } => operator(field, "$lte", to_bson(value)?)?,
+            Filter::In { field, values } => operator(field, "$in", to_bson_array(values)?)?,
+            Filter::Nin { field, values } => operator(field, "$nin", to_bson_array(values)?)?,
+            Filter::Exists { field, exists } => operator(field, "$exists", Bson::Boolean(*exists))?,
+            Filter::And { filters } if filters.is_empty() => Document::new(),
+            Filter::And { filters } => doc! { "$and": Self::filters(filters)? },
+            Filter::Or { filters } => doc! { "$or": Self::filters(filters)? },
+            Filter::Not { filter } => doc! { "$nor": [Self::filter(filter)?] },
+        })
+    }
+
+    fn sort(sort: &Sort) -> Result<Document, IntegrationOSError> {
+        sort.fields
+            .iter()
+            .map(|(field, direction)| {
+                let direction = match direction {
+                    SortDirection::Asc => 1,
+                    SortDirection::Desc => -1,
+                };
+                mongo_field(field).map(|field| (field.to_owned(), Bson::Int32(direction)))
+            })
+            .collect()
+    }
+}
+
+/// A parameterized SQL fragment, with `$n` placeholders bound to `params` in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SqlFragment {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+pub struct SqlQuery;
+
+fn quote_identifier(field: &str) -> Result<String, IntegrationOSError> {
+    if field.is_empty()
+        || !field
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    {
+        return Err(InternalError::invalid_argument(
+            &format!("Invalid field name {field}"),
+            Some("query"),
+        ));
+    }
+    Ok(field
+        .split('.')
+        .map(|part| format!("\"{part}\""))
+        .collect::<Vec<_>>()
+        .join("."))
+}
+
+impl SqlQuery {
+    fn write(filter: &Filter, params: &mut Vec<Value>) -> Result<String, IntegrationOSError> {
+        let mut bind = |value: &Value| {
+            params.push(value.clone());
+            format!("${}", params.len())
+        };
+        let comparison = |field: &str, op: &str, placeholder: String| {
+            quote_identifier(field).map(|field| format!("{field} {op} {placeholder}"))
+        };
+
+        match filter {
+            Filter::Eq { field, value } if value.is_null() => {
+                Ok(format!("{} IS NULL", quote_identifier(field)?))
+            }
+            Filter::Ne { field, value } if value.is_null() => {
+                Ok(format!("{} IS NOT NULL", quote_identifier(field)?))
+            }
+            Filter::Eq { field, value } => comparison(field, "=", bind(value)),
+            Filter::Ne { field, value } => comparison(field, "<>", bind(value)),
+            Filter::Gt { field, value } => comparison(field, ">", bind(value)),
+            Filter::Gte { field, value } => comparison(field, ">=", bind(value)),
+            Filter::Lt { field, value } => comparison(field, "<", bind(value)),
+            Filter::Lte { field, value } => comparison(field, "<=", bind(value)),
+            Filter::In { values, .. } if values.is_empty() => Ok("FALSE".to_owned()),
+            Filter::Nin { values, .. } if values.is_empty() => Ok("TRUE".to_owned()),
+            Filter::In { field, values } | Filter::Nin { field, values } => {
+                let op = if matches!(filter, Filter::In { .. }) {
+                    "IN"
+                } else {
+                    "NOT IN"
+                };
+                let placeholders: Vec<String> = values.iter().map(&mut bind).collect();
+                comparison(field, op, format!("({})", placeholders.join(", ")))
+            }
+            Filter::Exists { field, exists } => Ok(format!(
+                "{} {}",
+                quote_identifier(field)?,
+                if *exists { "IS NOT NULL" } else { "IS NULL" }
+            )),
+            Filter::And { filters } if filters.is_empty() => Ok("TRUE".to_owned()),
+            Filter::Or { filters } if filters.is_empty() => Ok("FALSE".to_owned()),
+            Filter::And { filters } | Filter::Or { filters } => {
+                let separator = if matches!(filter, Filter::And { .. }) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                let parts = filters
+                    .iter()
+                    .map(|f| Self::write(f, params))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("({})", parts.join(separator)))
+            }
+            Filter::Not { filter } => Ok(format!("NOT ({})", Self::write(filter, params)?)),
+        }
+    }
+}
+
+impl QueryBackend for SqlQuery {
+    type Filter = SqlFragment;
+    type Sort = String;
+
+    fn filter(filter: &Filter) -> Result<SqlFragment, IntegrationOSError> {
+        let mut params = vec![];
+        let sql = Self::write(filter, &mut params)?;
+        Ok(SqlFragment { sql, params })
+    }
+
+    fn sort(sort: &Sort) -> Result<String, IntegrationOSError> {
+        sort.fields
+            .iter()
+            .map(|(field, direction)| {
+                quote_identifier(field).map(|field| match direction {
+                    SortDirection::Asc => format!("{field} ASC"),
+                    SortDirection::Desc => format!("{field} DESC"),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|fields| fields.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn filter() -> Filter {
+        Filter::and([
+            Filter::eq("ownership.buildableId", "buildable"),
+            Filter::or([
+                Filter::gte("createdAt", 10),
+                Filter::is_in("platform", ["stripe", "shopify"]),
+            ]),
+            Filter::not(Filter::exists("deleted", true)),
+        ])
+    }
+
+    #[test]
+    fn test_mongo_translation() {
+        assert_eq!(
+            MongoQuery::filter(&filter()).unwrap(),
+            doc! {
+                "$and": [
+                    { "ownership.buildableId": { "$eq": "buildable" } },
+                    { "$or": [
+                        { "createdAt": { "$gte": 10_i64 } },
+                        { "platform": { "$in": ["stripe", "shopify"] } },
+                    ] },
+                    { "$nor": [{ "deleted": { "$exists": true } }] },
+                ]
+            }
+        );
+        assert_eq!(MongoQuery::filter(&Filter::all()).unwrap(), doc! {});
+        assert_eq!(
+            MongoQuery::sort(&Sort::desc("createdAt").then_asc("key")).unwrap(),
+            doc! { "createdAt": -1, "key": 1 }
+        );
+    }
+
+    #[test]
+    fn test_mongo_operator_values_match_literally() {
+        assert_eq!(
+            MongoQuery::filter(&Filter::eq("key", json!({ "$ne": null }))).unwrap(),
+            doc! { "key": { "$eq": { "$ne": null } } }
+        );
+        assert_eq!(
+            MongoQuery::filter(&Filter::eq("name", json!({ "$regex": ".*" }))).unwrap(),
+            doc! { "name": { "$eq": { "$regex": ".*" } } }
+        );
+        for field in ["$where", "a.$gt", "a..b", "", "key."] {
+            assert!(
+                MongoQuery::filter(&Filter::eq(field, 1)).is_err(),
+                "{field}"
+            );
+        }
+        assert!(MongoQuery::sort(&Sort::asc("$natural")).is_err());
+    }
+
+    #[test]
+    fn test_sql_translation() {
+        let fragment = SqlQuery::filter(&filter()).unwrap();
+        assert_eq!(
+            fragment.sql,
+            r#"("ownership"."buildableId" = $1 AND ("createdAt" >= $2 OR "platform" IN ($3, $4)) AND NOT ("deleted" IS NOT NULL))"#
+        );
+        assert_eq!(
+            fragment.params,
+            vec![
+                json!("buildable"),
+                json!(10),
+                json!("stripe"),
+                json!("shopify")
+            ]
+        );
+        assert_eq!(
+            SqlQuery::sort(&Sort::desc("createdAt")).unwrap(),
+            r#""createdAt" DESC"#
+        );
+        assert!(SqlQuery::filter(&Filter::eq("a; DROP TABLE", 1)).is_err());
+    }
+
+    #[test]
+    fn test_filter_serde() {
+        let value = serde_json::to_value(Filter::eq("key", "value")).unwrap();
+        assert_eq!(
+            value,
+            json!({ "op": "eq", "field": "key", "value": "value" })
+        );
+        assert_eq!(
+            serde_json::from_value::<Filter>(value).unwrap(),
+            Filter::eq("key", "value")
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{prelude::connection::Throughput, IntegrationOSError, InternalError, RedisCache};
+use async_trait::async_trait;
+use chrono::Utc;
+use http::{HeaderMap, HeaderValue};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u64,
+    pub remaining: u64,
+    /// How long to wait before the request would fit, set when it was refused
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitDecision {
+    /// `X-RateLimit-*` and `Retry-After` headers describing the decision
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-RateLimit-Limit", HeaderValue::from(self.limit));
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from(self.remaining));
+        if let Some(retry_after) = self.retry_after {
+            headers.insert(
+                "Retry-After",
+                HeaderValue::from(retry_after.as_secs_f64().ceil() as u64),
+            );
+        }
+        headers
+    }
+}
+
+/// Enforces a [`Throughput`] limit over a sliding window, keyed by [`Throughput::key`]
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Takes `cost` units out of the quota if they fit, otherwise leaves it untouched
+    async fn acquire(
+        &self,
+        throughput: &Throughput,
+        cost: u64,
+    ) -> Result<RateLimitDecision, IntegrationOSError>;
+}
+
+/// Removes expired hits, then records `cost` hits at `now` if they fit in the window.
+/// Returns whether they were recorded, the remaining quota and the retry-after in ms.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+local cost = tonumber(ARGV[4])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+local count = redis.call('ZCARD', KEYS[1])
+if count + cost <= limit then
+  for i = 1, cost do
+    redis.call('ZADD', KEYS[1], now, ARGV[5] .. ':' .. i)
+  end
+  redis.call('PEXPIRE', KEYS[1], window)
+  return {1, limit - count - cost, 0}
+end
+local retry = window
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+if oldest[2] then
+  retry = tonumber(oldest[2]) + window - now
+end
+return {0, math.max(limit - count, 0), retry}
+"#;
+
+/// Sliding window shared by every instance through Redis. Each hit is a member of a
+/// sorted set scored by its timestamp, trimmed and counted atomically in a script.
+#[derive(Clone)]
+pub struct RedisRateLimiter {
+    cache: RedisCache,
+    window: Duration,
+    script: Arc<redis::Script>,
+}
+
+impl RedisRateLimiter {
+    pub fn new(cache: RedisCache, window: Duration) -> Self {
+        Self {
+            cache,
+            window,
+            script: Arc::new(redis::Script::new(SLIDING_WINDOW_SCRIPT)),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn acquire(
+        &self,
+        throughput: &Throughput,
+        cost: u64,
+    ) -> Result<RateLimitDecision, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let (allowed, remaining, retry_after): (u8, u64, u64) = self
+            .script
+            .key(format!("rate_limit::{}", throughput.key))
+            .arg(Utc::now().timestamp_millis())
+            .arg(self.window.as_millis() as u64)
+            .arg(throughput.limit)
+            .arg(cost)
+            .arg(uuid::Uuid::new_v4().to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("rate_limiter")))?;
+
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit: throughput.limit,
+            remaining,
+            retry_after: (allowed == 0).then(|| Duration::from_millis(retry_after)),
+        })
+    }
+}
+
+/// Process local sliding window, for tests and single instance deployments
+#[derive(Debug, Clone)]
+pub struct InMemoryRateLimiter {
+    window: Duration,
+    hits: Arc<Mutex<HashMap<String, VecDeque<i64>>>>,
+}
+
+impl InMemoryRateLimiter {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            hits: Default::default(),
+        }
+    }
+
+    pub fn acquire_at(&self, throughput: &Throughput, cost: u64, now: i64) -> RateLimitDecision {
+        let window = self.window.as_millis() as i64;
+        let mut hits = self.hits.lock().expect("rate limiter lock poisoned");
+        let hits = hits.entry(throughput.key.clone()).or_default();
+        while hits.front().is_some_and(|hit| *hit <= now - window) {
+            hits.pop_front();
+        }
+
+        let count = hits.len() as u64;
+        if count + cost <= throughput.limit {
+            hits.extend(std::iter::repeat_n(now, cost as usize));
+            return RateLimitDecision {
+                allowed: true,
+                limit: throughput.limit,
+                remaining: throughput.limit - count - cost,
+                retry_after: None,
+            };
+        }
+        let retry_after = hits
+            .front()
+            .map(|oldest| oldest + window - now)
+            .unwrap_or(window);
+        RateLimitDecision {
+            allowed: false,
+            limit: throughput.limit,
+            remaining: throughput.limit.saturating_sub(count),
+            retry_after: Some(Duration::from_millis(retry_after.max(0) as u64)),
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for InMemoryRateLimiter {
+    async fn acquire(
+        &self,
+        throughput: &Throughput,
+        cost: u64,
+    ) -> Result<RateLimitDecision, IntegrationOSError> {
+        Ok(self.acquire_at(throughput, cost, Utc::now().timestamp_millis()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sliding_window() {
+        let limiter = InMemoryRateLimiter::new(Duration::from_secs(1));
+        let throughput = Throughput {
+            key: "conn".to_owned(),
+            limit: 3,
+        };
+
+        assert_eq!(limiter.acquire_at(&throughput, 2, 0).remaining, 1);
+        assert!(limiter.acquire_at(&throughput, 1, 400).allowed);
+
+        let refused = limiter.acquire_at(&throughput, 1, 600);
+        assert!(!refused.allowed);
+        assert_eq!(refused.remaining, 0);
+        assert_eq!(refused.retry_after, Some(Duration::from_millis(400)));
+        assert_eq!(refused.headers()["Retry-After"], "1");
+
+        // The first two hits left the window
+        let decision = limiter.acquire_at(&throughput, 2, 1_000);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    prelude::platform::quirks::WebhookSignatureScheme, ApplicationError, CacheEntry, CacheExt,
+    InMemoryCache, IntegrationOSError, InternalError, RedisCache,
+};
+use async_trait::async_trait;
+use http::HeaderMap;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ReplayDetected {
+    #[error("Webhook timestamp {timestamp} is older than the {tolerance_secs}s tolerance")]
+    Stale { timestamp: i64, tolerance_secs: u64 },
+    #[error("Webhook timestamp {timestamp} is ahead of the {tolerance_secs}s tolerance")]
+    FromTheFuture { timestamp: i64, tolerance_secs: u64 },
+    #[error("Webhook timestamp is missing")]
+    MissingTimestamp,
+    #[error("Webhook nonce {nonce} was already delivered")]
+    DuplicateNonce { nonce: String },
+}
+
+impl From<ReplayDetected> for IntegrationOSError {
+    fn from(detected: ReplayDetected) -> Self {
+        ApplicationError::unauthorized(&detected.to_string(), Some("replay_detected"))
+    }
+}
+
+/// Remembers nonces for a while, atomically telling whether one was seen before
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    /// Records the nonce, returning `false` when it was already recorded
+    async fn remember(&self, key: &str, ttl_secs: u64) -> Result<bool, IntegrationOSError>;
+}
+
+#[async_trait]
+impl NonceStore for RedisCache {
+    async fn remember(&self, key: &str, ttl_secs: u64) -> Result<bool, IntegrationOSError> {
+        let mut conn = self.clone();
+        let set: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs.max(1))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("replay_guard")))?;
+        Ok(set.is_some())
+    }
+}
+
+#[async_trait]
+impl NonceStore for InMemoryCache {
+    async fn remember(&self, key: &str, ttl_secs: u64) -> Result<bool, IntegrationOSError> {
+        if self.get(key).await?.is_some() {
+            return Ok(false);
+        }
+        self.set(
+            CacheEntry::new(key.to_owned(), Value::Bool(true)),
+            Some(ttl_secs.max(1)),
+        )
+        .await?;
+        Ok(true)
+    }
+}
+
+/// Rejects webhook deliveries outside the timestamp tolerance window or whose nonce was
+/// already seen for the same endpoint. Signature verification alone accepts a captured
+/// delivery sent again.
+pub struct ReplayGuard<N> {
+    nonces: N,
+    tolerance_secs: u64,
+}
+
+impl<N: NonceStore> ReplayGuard<N> {
+    pub fn new(nonces: N, tolerance_secs: u64) -> Self {
+        Self {
+            nonces,
+            tolerance_secs,
+        }
+    }
+
+    pub fn check_timestamp(&self, timestamp: i64, now: i64) -> Result<(), ReplayDetected> {
+        let tolerance = self.tolerance_secs as i64;
+        if timestamp < now - tolerance {
+            return Err(ReplayDetected::Stale {
+                timestamp,
+                tolerance_secs: self.tolerance_secs,
+            });
+        }
+        if timestamp > now + tolerance {
+            return Err(ReplayDetected::FromTheFuture {
+                timestamp,
+                tolerance_secs: self.tolerance_secs,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the delivery timestamp (epoch seconds) and records the nonce. Nonces are
+    /// kept for twice the tolerance, past which the timestamp check rejects them anyway.
+    pub async fn check(
+        &self,
+        endpoint: &str,
+        timestamp: i64,
+        nonce: &str,
+        now: i64,
+    ) -> Result<(), IntegrationOSError> {
+        self.check_timestamp(timestamp, now)?;
+        let key = format!("webhook::nonce::{endpoint}::{nonce}");
+        if !self.nonces.remember(&key, self.tolerance_secs * 2).await? {
+            return Err(ReplayDetected::DuplicateNonce {
+                nonce: nonce.to_owned(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Same as [`ReplayGuard::check`] reading the timestamp from the headers of a
+    /// platform's signature scheme
+    pub async fn check_headers(
+        &self,
+        scheme: &WebhookSignatureScheme,
+        headers: &HeaderMap,
+        endpoint: &str,
+        nonce: &str,
+        now: i64,
+    ) -> Result<(), IntegrationOSError> {
+        let timestamp = scheme
+            .timestamp(headers)
+            .ok_or(ReplayDetected::MissingTimestamp)?;
+        self.check(endpoint, timestamp, nonce, now).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::HeaderValue;
+
+    #[tokio::test]
+    async fn test_replay_guard() {
+        let guard = ReplayGuard::new(InMemoryCache::new(), 300);
+        let now = 1_700_000_000;
+
+        assert!(guard.check("ep_1", now - 10, "evt_1", now).await.is_ok());
+        assert!(guard.check("ep_2", now - 10, "evt_1", now).await.is_ok());
+        let duplicate = guard.check("ep_1", now, "evt_1", now).await.unwrap_err();
+        assert_eq!(
+            duplicate.to_string(),
+            "Unauthorized: Webhook nonce evt_1 was already delivered"
+        );
+
+        assert_eq!(
+            guard.check_timestamp(now - 301, now),
+            Err(ReplayDetected::Stale {
+                timestamp: now - 301,
+                tolerance_secs: 300
+            })
+        );
+        assert!(matches!(
+            guard.check_timestamp(now + 301, now),
+            Err(ReplayDetected::FromTheFuture { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replay_guard_reads_signature_header() {
+        let scheme = WebhookSignatureScheme::TimestampedHmacSha256 {
+            header: "Stripe-Signature".to_owned(),
+            timestamp_key: "t".to_owned(),
+            signature_key: "v1".to_owned(),
+            tolerance_secs: 300,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Stripe-Signature",
+            HeaderValue::from_static("t=1700000000,v1=abc"),
+        );
+        let guard = ReplayGuard::new(InMemoryCache::new(), 300);
+
+        assert!(guard
+            .check_headers(&scheme, &headers, "ep", "evt", 1_700_000_100)
+            .await
+            .is_ok());
+        assert!(guard
+            .check_headers(&scheme, &HeaderMap::new(), "ep", "evt_2", 1_700_000_100)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    policies::{RetryAttempts, RetryPolicy},
+    IntegrationOSError,
+};
+use chrono::Utc;
+use std::future::Future;
+use tracing::warn;
+
+/// Runs `work` until it succeeds, fails with an error the policy does not retry, or
+/// runs out of attempts, sleeping the policy's backoff in between.
+///
+/// The attempts are returned alongside the result either way so callers can record
+/// them on the stage's context.
+pub async fn execute_with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    mut work: F,
+) -> (Result<T, IntegrationOSError>, RetryAttempts)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, IntegrationOSError>>,
+{
+    let maximum_attempts = policy.maximum_attempts.clamp(1, u32::MAX as u64) as u32;
+    let mut attempts = RetryAttempts {
+        attempts: 0,
+        last_error: None,
+        last_attempt_at: None,
+    };
+    loop {
+        attempts.attempts += 1;
+        attempts.last_attempt_at = Some(Utc::now());
+        let error = match work(attempts.attempts).await {
+            Ok(value) => return (Ok(value), attempts),
+            Err(error) => error,
+        };
+        attempts.last_error = Some(error.to_string());
+        if attempts.attempts >= maximum_attempts || !policy.retries(&error) {
+            return (Err(error), attempts);
+        }
+
+        let delay = match policy.delay(attempts.attempts) {
+            Ok(delay) => delay,
+            Err(e) => return (Err(e), attempts),
+        };
+        warn!(
+            "Attempt {} of {maximum_attempts} failed, retrying in {delay:?}: {error}",
+            attempts.attempts
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        policies::{BackoffStrategy, ErrorClass},
+        ApplicationError, InternalError,
+    };
+    use std::time::Duration;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            maximum_attempts: 3,
+            initial_interval: "0 seconds".to_owned(),
+            backoff: BackoffStrategy::Fixed,
+            maximum_interval: None,
+            jitter: false,
+            retry_on: ErrorClass::transient(),
+        }
+    }
+
+    #[test]
+    fn test_backoff_delays() {
+        let mut policy = policy();
+        policy.initial_interval = "1 second".to_owned();
+        policy.backoff = BackoffStrategy::Exponential { multiplier: 2 };
+        policy.maximum_interval = Some("5 seconds".to_owned());
+        let delays = (1..=4)
+            .map(|attempt| policy.delay(attempt).unwrap().as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![1, 2, 4, 5]);
+
+        policy.jitter = true;
+        let delay = policy.delay(3).unwrap();
+        assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_only() {
+        let (result, attempts) = execute_with_retry(&policy(), |attempt| async move {
+            if attempt < 3 {
+                Err(InternalError::timeout("upstream timed out", None))
+            } else {
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts.attempts, 3);
+        assert!(attempts.last_error.unwrap().contains("upstream timed out"));
+
+        let (result, attempts) = execute_with_retry(&policy(), |_| async {
+            Err::<(), _>(ApplicationError::bad_request("invalid payload", None))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.attempts, 1);
+
+        let (result, attempts) = execute_with_retry(&policy(), |_| async {
+            Err::<(), _>(ApplicationError::service_unavailable("down", None))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.attempts, 3);
+    }
+}
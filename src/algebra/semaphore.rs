@@ -0,0 +1,262 @@
+use crate::{
+    prelude::{connection::Connection, shared::settings::ConcurrencyLimit},
+    ApplicationError, IntegrationOSError, InternalError, RedisCache,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::warn;
+
+/// A slot held in a semaphore until it is released or its lease runs out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permit {
+    pub key: String,
+    pub holder: String,
+}
+
+/// Counting semaphore keyed by an arbitrary name. Permits carry a lease so a holder that
+/// dies without releasing does not block the key forever.
+#[async_trait]
+pub trait ConcurrencyLimiter: Send + Sync {
+    /// Takes a permit if fewer than `limit.max_concurrent` are held, without waiting
+    async fn try_acquire(
+        &self,
+        key: &str,
+        limit: &ConcurrencyLimit,
+    ) -> Result<Option<Permit>, IntegrationOSError>;
+
+    async fn release(&self, permit: &Permit) -> Result<(), IntegrationOSError>;
+
+    /// Polls for a permit until one frees up or `wait` elapses
+    async fn acquire(
+        &self,
+        key: &str,
+        limit: &ConcurrencyLimit,
+        wait: Duration,
+    ) -> Result<Permit, IntegrationOSError> {
+        let deadline = tokio::time::Instant::now() + wait;
+        let mut delay = Duration::from_millis(25);
+        loop {
+            if let Some(permit) = self.try_acquire(key, limit).await? {
+                return Ok(permit);
+            }
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                return Err(ApplicationError::too_many_requests(
+                    &format!(
+                        "More than {} concurrent executions for {key}",
+                        limit.max_concurrent
+                    ),
+                    Some("concurrency_limit"),
+                ));
+            }
+            tokio::time::sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(Duration::from_secs(1));
+        }
+    }
+}
+
+fn concurrency_key(key: &str) -> String {
+    format!("concurrency::{key}")
+}
+
+/// Removes expired holders, then adds `ARGV[4]` with its lease expiry if there is room.
+/// Returns 1 when the permit was taken.
+const SEMAPHORE_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local lease = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now)
+if redis.call('ZCARD', KEYS[1]) < limit then
+  redis.call('ZADD', KEYS[1], now + lease, ARGV[4])
+  redis.call('PEXPIRE', KEYS[1], lease)
+  return 1
+end
+return 0
+"#;
+
+/// Semaphore shared by every instance through Redis. Holders are members of a sorted set
+/// scored by their lease expiry, trimmed and counted atomically in a script.
+#[derive(Clone)]
+pub struct RedisSemaphore {
+    cache: RedisCache,
+    script: Arc<redis::Script>,
+}
+
+impl RedisSemaphore {
+    pub fn new(cache: RedisCache) -> Self {
+        Self {
+            cache,
+            script: Arc::new(redis::Script::new(SEMAPHORE_SCRIPT)),
+        }
+    }
+}
+
+#[async_trait]
+impl ConcurrencyLimiter for RedisSemaphore {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        limit: &ConcurrencyLimit,
+    ) -> Result<Option<Permit>, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let holder = uuid::Uuid::new_v4().to_string();
+        let acquired: u8 = self
+            .script
+            .key(concurrency_key(key))
+            .arg(Utc::now().timestamp_millis())
+            .arg(limit.lease_secs * 1000)
+            .arg(limit.max_concurrent)
+            .arg(&holder)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("semaphore")))?;
+
+        Ok((acquired == 1).then(|| Permit {
+            key: key.to_owned(),
+            holder,
+        }))
+    }
+
+    async fn release(&self, permit: &Permit) -> Result<(), IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        redis::cmd("ZREM")
+            .arg(concurrency_key(&permit.key))
+            .arg(&permit.holder)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("semaphore")))
+    }
+}
+
+/// Process local semaphore, for tests and single instance deployments
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySemaphore {
+    holders: Arc<Mutex<HashMap<String, HashMap<String, i64>>>>,
+}
+
+impl InMemorySemaphore {
+    pub fn try_acquire_at(&self, key: &str, limit: &ConcurrencyLimit, now: i64) -> Option<Permit> {
+        let mut holders = self.holders.lock().expect("semaphore lock poisoned");
+        let holders = holders.entry(key.to_owned()).or_default();
+        holders.retain(|_, expires_at| *expires_at > now);
+
+        if holders.len() >= limit.max_concurrent as usize {
+            return None;
+        }
+        let holder = uuid::Uuid::new_v4().to_string();
+        holders.insert(holder.clone(), now + limit.lease_secs as i64 * 1000);
+        Some(Permit {
+            key: key.to_owned(),
+            holder,
+        })
+    }
+}
+
+#[async_trait]
+impl ConcurrencyLimiter for InMemorySemaphore {
+    async fn try_acquire(
+        &self,
+        key: &str,
+        limit: &ConcurrencyLimit,
+    ) -> Result<Option<Permit>, IntegrationOSError> {
+        Ok(self.try_acquire_at(key, limit, Utc::now().timestamp_millis()))
+    }
+
+    async fn release(&self, permit: &Permit) -> Result<(), IntegrationOSError> {
+        if let Some(holders) = self
+            .holders
+            .lock()
+            .expect("semaphore lock poisoned")
+            .get_mut(&permit.key)
+        {
+            holders.remove(&permit.holder);
+        }
+        Ok(())
+    }
+}
+
+/// Runs pipeline work for a connection within its [`ConcurrencyLimit`] setting.
+/// Connections without a limit run unrestricted.
+#[derive(Clone)]
+pub struct ConnectionConcurrency<L> {
+    limiter: L,
+    wait: Duration,
+}
+
+impl<L: ConcurrencyLimiter> ConnectionConcurrency<L> {
+    pub fn new(limiter: L, wait: Duration) -> Self {
+        Self { limiter, wait }
+    }
+
+    pub async fn run<F, T>(&self, connection: &Connection, work: F) -> Result<T, IntegrationOSError>
+    where
+        F: Future<Output = Result<T, IntegrationOSError>> + Send,
+        T: Send,
+    {
+        let Some(limit) = connection.settings.concurrency_limit else {
+            return work.await;
+        };
+        let permit = self
+            .limiter
+            .acquire(&connection.id.to_string(), &limit, self.wait)
+            .await?;
+        let result = work.await;
+        if let Err(e) = self.limiter.release(&permit).await {
+            warn!("Could not release permit for {}: {e}", permit.key);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::StatusCode;
+
+    #[test]
+    fn test_in_memory_semaphore() {
+        let semaphore = InMemorySemaphore::default();
+        let limit = ConcurrencyLimit {
+            max_concurrent: 2,
+            lease_secs: 1,
+        };
+
+        assert!(semaphore.try_acquire_at("xero", &limit, 0).is_some());
+        assert!(semaphore.try_acquire_at("xero", &limit, 100).is_some());
+        assert!(semaphore.try_acquire_at("xero", &limit, 200).is_none());
+        assert!(semaphore.try_acquire_at("stripe", &limit, 200).is_some());
+
+        // The first permit's lease ran out
+        assert!(semaphore.try_acquire_at("xero", &limit, 1_000).is_some());
+        assert!(semaphore.try_acquire_at("xero", &limit, 1_000).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_release() {
+        let semaphore = InMemorySemaphore::default();
+        let limit = ConcurrencyLimit::new(1);
+
+        let permit = semaphore
+            .try_acquire("xero", &limit)
+            .await
+            .unwrap()
+            .unwrap();
+        let refused = semaphore
+            .acquire("xero", &limit, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert_eq!(StatusCode::from(&refused), StatusCode::TOO_MANY_REQUESTS);
+
+        semaphore.release(&permit).await.unwrap();
+        assert!(semaphore
+            .acquire("xero", &limit, Duration::from_millis(50))
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{ApplicationError, IntegrationOSError};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+pub const SIGNATURE_HEADER: &str = "X-IntegrationOS-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SignatureError {
+    #[error("Signature header is malformed: {0}")]
+    Malformed(String),
+    #[error("Signature timestamp {timestamp} is outside the {tolerance_secs}s tolerance")]
+    Expired { timestamp: i64, tolerance_secs: u64 },
+    #[error("Signature does not match the payload")]
+    Invalid,
+}
+
+impl From<SignatureError> for IntegrationOSError {
+    fn from(error: SignatureError) -> Self {
+        let subtype = match error {
+            SignatureError::Malformed(_) => "malformed_signature",
+            SignatureError::Expired { .. } => "expired_signature",
+            SignatureError::Invalid => "invalid_signature",
+        };
+        ApplicationError::unauthorized(&error.to_string(), Some(subtype))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub(crate) fn unhex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Signs payloads sent to a connection's consumers with HMAC-SHA256 over
+/// `{timestamp}.{payload}`, in a `t={timestamp},v1={hex signature}` header, and verifies
+/// them on the way back in. Replaying a signed payload is only possible within the
+/// tolerance; pair it with a [`crate::ReplayGuard`] to reject replays entirely.
+#[derive(Clone)]
+pub struct Signer {
+    secret: Vec<u8>,
+    tolerance_secs: u64,
+}
+
+impl std::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signer")
+            .field("tolerance_secs", &self.tolerance_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Signer {
+    pub fn new(secret: impl AsRef<[u8]>, tolerance_secs: u64) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            tolerance_secs,
+        }
+    }
+
+    fn mac(&self, timestamp: i64, payload: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any size");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(payload);
+        mac
+    }
+
+    /// Header value signing `payload` at `timestamp` (epoch seconds)
+    pub fn sign(&self, payload: &[u8], timestamp: i64) -> String {
+        let signature = self.mac(timestamp, payload).finalize().into_bytes();
+        format!("t={timestamp},v1={}", hex(&signature))
+    }
+
+    /// Checks the header against the payload at `now` (epoch seconds). Any of several
+    /// `v1` signatures may match, so that consumers can accept both secrets while one is
+    /// being rotated.
+    pub fn verify(&self, header: &str, payload: &[u8], now: i64) -> Result<(), SignatureError> {
+        let mut timestamp = None;
+        let mut signatures = vec![];
+        for (key, value) in header
+            .split(',')
+            .filter_map(|part| part.trim().split_once('='))
+        {
+            match key {
+                "t" => {
+                    timestamp = Some(value.parse::<i64>().map_err(|_| {
+                        SignatureError::Malformed(format!("invalid timestamp {value}"))
+                    })?)
+                }
+                "v1" => signatures.push(value),
+                _ => {}
+            }
+        }
+        let timestamp =
+            timestamp.ok_or_else(|| SignatureError::Malformed("missing timestamp".to_owned()))?;
+        if signatures.is_empty() {
+            return Err(SignatureError::Malformed("missing signature".to_owned()));
+        }
+        if now.abs_diff(timestamp) > self.tolerance_secs {
+            return Err(SignatureError::Expired {
+                timestamp,
+                tolerance_secs: self.tolerance_secs,
+            });
+        }
+
+        let mac = self.mac(timestamp, payload);
+        let matches = signatures
+            .iter()
+            .filter_map(|signature| unhex(signature))
+            .any(|signature| mac.clone().verify_slice(&signature).is_ok());
+        if matches {
+            Ok(())
+        } else {
+            Err(SignatureError::Invalid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = Signer::new("whsec_test", 300);
+        let payload = br#"{"id":"evt_1"}"#;
+        let header = signer.sign(payload, 1_700_000_000);
+        assert!(header.starts_with("t=1700000000,v1="));
+
+        assert_eq!(signer.verify(&header, payload, 1_700_000_100), Ok(()));
+        assert_eq!(
+            signer.verify(&header, br#"{"id":"evt_2"}"#, 1_700_000_100),
+            Err(SignatureError::Invalid)
+        );
+        assert_eq!(
+            Signer::new("other", 300).verify(&header, payload, 1_700_000_100),
+            Err(SignatureError::Invalid)
+        );
+        assert_eq!(
+            signer.verify(&header, payload, 1_700_000_301),
+            Err(SignatureError::Expired {
+                timestamp: 1_700_000_000,
+                tolerance_secs: 300
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_accepts_any_listed_signature() {
+        let old = Signer::new("old", 300);
+        let new = Signer::new("new", 300);
+        let payload = b"payload";
+        let signature = |signer: &Signer| {
+            signer
+                .sign(payload, 10)
+                .split_once(",v1=")
+                .unwrap()
+                .1
+                .to_owned()
+        };
+        let header = format!("t=10,v1={},v1={}", signature(&old), signature(&new));
+
+        assert_eq!(new.verify(&header, payload, 10), Ok(()));
+        assert_eq!(old.verify(&header, payload, 10), Ok(()));
+        assert!(matches!(
+            new.verify("v1=abc", payload, 10),
+            Err(SignatureError::Malformed(_))
+        ));
+        assert!(matches!(
+            new.verify("t=10", payload, 10),
+            Err(SignatureError::Malformed(_))
+        ));
+    }
+}
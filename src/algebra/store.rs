@@ -1,12 +1,264 @@
+use crate::bson_compat::id_filter;
 use crate::IntegrationOSError;
+use crate::InternalError;
 use crate::Store;
-use bson::doc;
+use crate::{Filter, MongoQuery, QueryBackend, Sort};
+use bson::{doc, RawDocumentBuf};
+use chrono::Utc;
 use futures::TryStreamExt;
 use mongodb::bson::Document;
-use mongodb::options::CountOptions;
+use mongodb::error::ErrorKind;
+use mongodb::options::{CountOptions, InsertManyOptions};
 use mongodb::{Collection, Database};
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, ops::Range};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionStats {
+    pub namespace: String,
+    pub count: u64,
+    pub size: u64,
+    pub storage_size: u64,
+    pub avg_obj_size: u64,
+    pub total_index_size: u64,
+    pub index_sizes: BTreeMap<String, u64>,
+}
+
+impl CollectionStats {
+    fn from_document(document: &Document) -> Result<Self, IntegrationOSError> {
+        let storage = document
+            .get_document("storageStats")
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("coll_stats")))?;
+
+        let number = |doc: &Document, key: &str| -> u64 {
+            match doc.get(key) {
+                Some(bson::Bson::Int32(v)) => *v as u64,
+                Some(bson::Bson::Int64(v)) => *v as u64,
+                Some(bson::Bson::Double(v)) => *v as u64,
+                _ => 0,
+            }
+        };
+
+        let index_sizes = storage
+            .get_document("indexSizes")
+            .map(|sizes| {
+                sizes
+                    .keys()
+                    .map(|k| (k.to_owned(), number(sizes, k)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            namespace: document.get_str("ns").unwrap_or_default().to_owned(),
+            count: number(storage, "count"),
+            size: number(storage, "size"),
+            storage_size: number(storage, "storageSize"),
+            avg_obj_size: number(storage, "avgObjSize"),
+            total_index_size: number(storage, "totalIndexSize"),
+            index_sizes,
+        })
+    }
+
+    /// Sums the `$collStats` documents of a collection, one per shard when it is sharded
+    fn from_documents(documents: &[Document]) -> Result<Self, IntegrationOSError> {
+        let mut stats = documents
+            .iter()
+            .map(Self::from_document)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        let mut total = stats.next().ok_or_else(|| {
+            InternalError::key_not_found("No collection stats", Some("coll_stats"))
+        })?;
+        for shard in stats {
+            total.count += shard.count;
+            total.size += shard.size;
+            total.storage_size += shard.storage_size;
+            total.total_index_size += shard.total_index_size;
+            for (index, size) in shard.index_sizes {
+                *total.index_sizes.entry(index).or_default() += size;
+            }
+        }
+        if let Some(avg_obj_size) = total.size.checked_div(total.count) {
+            total.avg_obj_size = avg_obj_size;
+        }
+        Ok(total)
+    }
+}
+
+/// Summed size of the documents of an `update` command sent by
+/// [`MongoStore::upsert_many`], leaving room within the 16MB limit of the command itself
+/// for the filters of the updates
+const UPSERT_MAX_BYTES: usize = 12 * 1024 * 1024;
+
+/// How [`MongoStore::create_many_with`] splits a batch into `insert_many` calls, keeping
+/// each well below Mongo's limits of 100,000 operations and 48MB per message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchWriteOptions {
+    pub max_count: usize,
+    /// Summed BSON size of the documents of a chunk. A document larger than this is
+    /// written on its own.
+    pub max_bytes: usize,
+    /// Stops at the first failed document, like a single ordered `insert_many`. When
+    /// unset every chunk is written and failures are reported per document.
+    pub ordered: bool,
+}
+
+impl Default for BatchWriteOptions {
+    fn default() -> Self {
+        Self {
+            max_count: 10_000,
+            max_bytes: 32 * 1024 * 1024,
+            ordered: true,
+        }
+    }
+}
+
+impl BatchWriteOptions {
+    pub fn unordered(self) -> Self {
+        Self {
+            ordered: false,
+            ..self
+        }
+    }
+
+    /// Ranges of the documents, given their sizes, written by each `insert_many`
+    fn chunks(&self, sizes: &[usize]) -> Vec<Range<usize>> {
+        let mut chunks = vec![];
+        let mut start = 0;
+        let mut bytes = 0;
+        for (index, size) in sizes.iter().enumerate() {
+            if index > start && (index - start >= self.max_count || bytes + size > self.max_bytes) {
+                chunks.push(start..index);
+                start = index;
+                bytes = 0;
+            }
+            bytes += size;
+        }
+        if start < sizes.len() {
+            chunks.push(start..sizes.len());
+        }
+        chunks
+    }
+}
+
+/// Reported after each chunk written by [`MongoStore::create_many_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub chunk: usize,
+    pub chunks: usize,
+    /// Documents attempted so far, failed ones included
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "outcome")]
+pub enum BulkItemOutcome {
+    Inserted,
+    Updated,
+    Failed { code: i32, message: String },
+}
+
+/// Per-document outcome of a bulk write, indexed like the input batch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkWriteReport {
+    pub results: Vec<BulkItemOutcome>,
+}
+
+impl BulkWriteReport {
+    fn from_failures(len: usize, failures: impl IntoIterator<Item = (usize, i32, String)>) -> Self {
+        let mut results = vec![BulkItemOutcome::Inserted; len];
+        for (index, code, message) in failures {
+            if let Some(result) = results.get_mut(index) {
+                *result = BulkItemOutcome::Failed { code, message };
+            }
+        }
+        Self { results }
+    }
+
+    /// Records the upserts and write errors of an `update` command response, whose
+    /// updates were made for the documents at `indexes`
+    fn record_update_response(&mut self, indexes: &[usize], response: &Document) {
+        let position = |document: &Document| {
+            let index = match document.get("index")? {
+                bson::Bson::Int32(index) => *index as usize,
+                bson::Bson::Int64(index) => *index as usize,
+                _ => return None,
+            };
+            indexes.get(index).copied()
+        };
+        for upserted in response.get_array("upserted").into_iter().flatten() {
+            if let Some(index) = upserted.as_document().and_then(position) {
+                self.results[index] = BulkItemOutcome::Inserted;
+            }
+        }
+        for error in response.get_array("writeErrors").into_iter().flatten() {
+            let Some(error) = error.as_document() else {
+                continue;
+            };
+            if let Some(index) = position(error) {
+                self.results[index] = BulkItemOutcome::Failed {
+                    code: error.get_i32("code").unwrap_or_default(),
+                    message: error.get_str("errmsg").unwrap_or_default().to_owned(),
+                };
+            }
+        }
+    }
+
+    pub fn inserted(&self) -> usize {
+        self.count(|r| matches!(r, BulkItemOutcome::Inserted))
+    }
+
+    pub fn updated(&self) -> usize {
+        self.count(|r| matches!(r, BulkItemOutcome::Updated))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|r| matches!(r, BulkItemOutcome::Failed { .. }))
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &BulkItemOutcome)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| matches!(r, BulkItemOutcome::Failed { .. }))
+    }
+
+    fn count(&self, f: impl Fn(&BulkItemOutcome) -> bool) -> usize {
+        self.results.iter().filter(|r| f(r)).count()
+    }
+}
+
+/// Whether soft-deleted records (see [`RecordMetadata::mark_deleted`]) are returned
+///
+/// [`RecordMetadata::mark_deleted`]: crate::prelude::shared::record_metadata::RecordMetadata::mark_deleted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeletedFilter {
+    #[default]
+    Exclude,
+    Include,
+    Only,
+}
+
+impl DeletedFilter {
+    pub fn apply(self, filter: Option<Document>) -> Document {
+        let mut filter = filter.unwrap_or_default();
+        match self {
+            DeletedFilter::Exclude => {
+                filter.insert("deleted", doc! { "$ne": true });
+            }
+            DeletedFilter::Only => {
+                filter.insert("deleted", true);
+            }
+            DeletedFilter::Include => {}
+        }
+        filter
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct MongoStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
@@ -38,6 +290,11 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
         Ok(self.collection.find_one(filter, None).await?)
     }
 
+    /// Same as [`MongoStore::get_one_by_id`] but also matches documents whose `_id` was stored as an `ObjectId`
+    pub async fn get_one_by_any_id(&self, id: &str) -> Result<Option<T>, IntegrationOSError> {
+        Ok(self.collection.find_one(id_filter(id), None).await?)
+    }
+
     /// Get all records from the collection
     ///
     /// Use this method with caution, as it can be very slow for large collections.
@@ -72,18 +329,206 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
         Ok(records)
     }
 
+    /// Same as [`MongoStore::get_one`], leaving out soft-deleted records unless asked to
+    pub async fn get_one_visible(
+        &self,
+        filter: Document,
+        deleted: DeletedFilter,
+    ) -> Result<Option<T>, IntegrationOSError> {
+        self.get_one(deleted.apply(Some(filter))).await
+    }
+
+    /// Same as [`MongoStore::get_many`], leaving out soft-deleted records unless asked to
+    pub async fn get_many_visible(
+        &self,
+        filter: Option<Document>,
+        deleted: DeletedFilter,
+        sort: Option<Document>,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        self.get_many(Some(deleted.apply(filter)), None, sort, limit, skip)
+            .await
+    }
+
+    /// Same as [`MongoStore::get_many`] with a backend agnostic [`Filter`] and [`Sort`]
+    pub async fn find(
+        &self,
+        filter: &Filter,
+        sort: Option<&Sort>,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<T>, IntegrationOSError> {
+        let sort = sort.map(MongoQuery::sort).transpose()?;
+        self.get_many(Some(MongoQuery::filter(filter)?), None, sort, limit, skip)
+            .await
+    }
+
+    pub async fn count_matching(
+        &self,
+        filter: &Filter,
+        limit: Option<u64>,
+    ) -> Result<u64, IntegrationOSError> {
+        self.count(MongoQuery::filter(filter)?, limit).await
+    }
+
     pub async fn create_one(&self, data: &T) -> Result<(), IntegrationOSError> {
         self.collection.insert_one(data, None).await?;
 
         Ok(())
     }
 
+    /// Inserts the records in order, in as many `insert_many` calls as needed to stay
+    /// within Mongo's batch limits
     pub async fn create_many(&self, data: &[T]) -> Result<(), IntegrationOSError> {
-        self.collection.insert_many(data, None).await?;
+        self.create_many_with(data, &BatchWriteOptions::default(), |_| {})
+            .await?;
 
         Ok(())
     }
 
+    /// Unordered insert that keeps going past individual failures (e.g. duplicate keys)
+    /// and reports the outcome of each document instead of failing the whole batch
+    pub async fn create_many_with_report(
+        &self,
+        data: &[T],
+    ) -> Result<BulkWriteReport, IntegrationOSError> {
+        self.create_many_with(data, &BatchWriteOptions::default().unordered(), |_| {})
+            .await
+    }
+
+    fn raw_documents(data: &[T]) -> Result<Vec<RawDocumentBuf>, IntegrationOSError> {
+        data.iter()
+            .map(|record| {
+                let bytes = bson::to_vec(record)
+                    .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+                RawDocumentBuf::from_bytes(bytes)
+                    .map_err(|e| InternalError::serialize_error(&e.to_string(), None))
+            })
+            .collect()
+    }
+
+    /// Inserts the records in chunks split by count and serialized size, calling
+    /// `on_progress` after each chunk. In ordered mode the first failure is returned as an
+    /// error, and the chunks reported before it were written.
+    pub async fn create_many_with(
+        &self,
+        data: &[T],
+        options: &BatchWriteOptions,
+        mut on_progress: impl FnMut(BatchProgress) + Send,
+    ) -> Result<BulkWriteReport, IntegrationOSError> {
+        let documents = Self::raw_documents(data)?;
+        let chunks = options.chunks(
+            &documents
+                .iter()
+                .map(|document| document.as_bytes().len())
+                .collect::<Vec<_>>(),
+        );
+        let collection = self.collection.clone_with_type::<RawDocumentBuf>();
+        let insert_options = InsertManyOptions::builder()
+            .ordered(options.ordered)
+            .build();
+
+        let mut failures = vec![];
+        for (chunk, range) in chunks.iter().enumerate() {
+            let offset = range.start;
+            match collection
+                .insert_many(&documents[range.clone()], insert_options.clone())
+                .await
+            {
+                Ok(_) => {}
+                Err(e) => match *e.kind {
+                    ErrorKind::BulkWrite(failure)
+                        if !options.ordered && failure.write_concern_error.is_none() =>
+                    {
+                        failures.extend(
+                            failure
+                                .write_errors
+                                .unwrap_or_default()
+                                .into_iter()
+                                .map(|e| (offset + e.index, e.code, e.message)),
+                        )
+                    }
+                    _ => return Err(e.into()),
+                },
+            }
+            on_progress(BatchProgress {
+                chunk: chunk + 1,
+                chunks: chunks.len(),
+                processed: range.end,
+                total: data.len(),
+            });
+        }
+        Ok(BulkWriteReport::from_failures(data.len(), failures))
+    }
+
+    /// Replaces each document by `_id`, inserting the ones that don't exist yet, with an
+    /// unordered `update` command per chunk
+    pub async fn upsert_many(&self, data: &[T]) -> Result<BulkWriteReport, IntegrationOSError> {
+        let documents = Self::raw_documents(data)?;
+        let options = BatchWriteOptions {
+            max_bytes: UPSERT_MAX_BYTES,
+            ..BatchWriteOptions::default().unordered()
+        };
+        let chunks = options.chunks(
+            &documents
+                .iter()
+                .map(|document| document.as_bytes().len())
+                .collect::<Vec<_>>(),
+        );
+        let namespace = self.collection.namespace();
+        let database = self.collection.client().database(&namespace.db);
+
+        let mut report = BulkWriteReport {
+            results: vec![BulkItemOutcome::Updated; data.len()],
+        };
+        for range in chunks {
+            // Indexes in `data` of the documents sent, the command reports by position
+            let mut indexes = vec![];
+            let mut updates = vec![];
+            for index in range {
+                let document = documents[index]
+                    .to_document()
+                    .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?;
+                let Some(id) = document.get("_id").cloned() else {
+                    report.results[index] = BulkItemOutcome::Failed {
+                        code: 0,
+                        message: "Document has no _id".to_owned(),
+                    };
+                    continue;
+                };
+                indexes.push(index);
+                updates.push(doc! { "q": { "_id": id }, "u": document, "upsert": true });
+            }
+            if updates.is_empty() {
+                continue;
+            }
+
+            let mut command = doc! {
+                "update": &namespace.coll,
+                "updates": updates,
+                "ordered": false,
+            };
+            if let Some(write_concern) = self.collection.write_concern() {
+                command.insert(
+                    "writeConcern",
+                    bson::to_document(write_concern)
+                        .map_err(|e| InternalError::serialize_error(&e.to_string(), None))?,
+                );
+            }
+            let response = database.run_command(command, None).await?;
+            if let Ok(error) = response.get_document("writeConcernError") {
+                return Err(InternalError::io_err(
+                    error.get_str("errmsg").unwrap_or("Write concern error"),
+                    Some("upsert_many"),
+                ));
+            }
+
+            report.record_update_response(&indexes, &response);
+        }
+        Ok(report)
+    }
+
     pub async fn update_one(&self, id: &str, data: Document) -> Result<(), IntegrationOSError> {
         let filter = doc! { "_id": id };
 
@@ -91,6 +536,27 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
         Ok(())
     }
 
+    /// Marks the record as deleted, keeping it around for the audit trail
+    pub async fn soft_delete_one(
+        &self,
+        id: &str,
+        deleted_by: &str,
+    ) -> Result<(), IntegrationOSError> {
+        self.update_one(
+            id,
+            soft_delete_update(deleted_by, Utc::now().timestamp_millis()),
+        )
+        .await
+    }
+
+    pub async fn restore_one(&self, id: &str, restored_by: &str) -> Result<(), IntegrationOSError> {
+        self.update_one(
+            id,
+            restore_update(restored_by, Utc::now().timestamp_millis()),
+        )
+        .await
+    }
+
     pub async fn update_many(
         &self,
         filter: Document,
@@ -123,4 +589,232 @@ impl<T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static> MongoStore
             .count_documents(filter, CountOptions::builder().limit(limit).build())
             .await?)
     }
+
+    /// Collection level statistics (document count, storage and index sizes) as reported by `$collStats`,
+    /// summed over the shards of sharded collections
+    pub async fn stats(&self) -> Result<CollectionStats, IntegrationOSError> {
+        let pipeline = vec![doc! { "$collStats": { "storageStats": {} } }];
+        let documents: Vec<Document> = self
+            .collection
+            .aggregate(pipeline, None)
+            .await?
+            .try_collect()
+            .await?;
+        if documents.is_empty() {
+            return Err(InternalError::key_not_found(
+                &format!("No stats for collection {}", self.collection.name()),
+                Some("coll_stats"),
+            ));
+        }
+
+        CollectionStats::from_documents(&documents)
+    }
+}
+
+/// Same changes as [`crate::prelude::shared::record_metadata::RecordMetadata::mark_deleted`],
+/// change log entry included
+fn soft_delete_update(deleted_by: &str, now: i64) -> Document {
+    doc! {
+        "$set": {
+            "deleted": true,
+            "deletedAt": now,
+            "deletedBy": deleted_by,
+            "updatedAt": now,
+            "lastModifiedBy": deleted_by,
+            format!("changeLog.Marked as deleted by {deleted_by}"): now,
+        }
+    }
+}
+
+/// Same changes as [`crate::prelude::shared::record_metadata::RecordMetadata::mark_undeleted`],
+/// change log entry included
+fn restore_update(restored_by: &str, now: i64) -> Document {
+    doc! {
+        "$set": {
+            "deleted": false,
+            "updatedAt": now,
+            "lastModifiedBy": restored_by,
+            format!("changeLog.Marked as undeleted by {restored_by}"): now,
+        },
+        "$unset": { "deletedAt": "", "deletedBy": "" },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::shared::record_metadata::RecordMetadata;
+
+    #[test]
+    fn test_collection_stats_from_document() {
+        let document = doc! {
+            "ns": "database.connections",
+            "storageStats": {
+                "count": 10_i32,
+                "size": 2048_i64,
+                "storageSize": 4096_i64,
+                "avgObjSize": 204.8_f64,
+                "totalIndexSize": 512_i32,
+                "indexSizes": { "_id_": 256_i32, "key_1": 256_i64 },
+            }
+        };
+
+        let stats = CollectionStats::from_document(&document).unwrap();
+        assert_eq!(stats.namespace, "database.connections");
+        assert_eq!(stats.count, 10);
+        assert_eq!(stats.size, 2048);
+        assert_eq!(stats.storage_size, 4096);
+        assert_eq!(stats.avg_obj_size, 204);
+        assert_eq!(stats.total_index_size, 512);
+        assert_eq!(stats.index_sizes.get("key_1"), Some(&256));
+
+        assert!(CollectionStats::from_document(&doc! {}).is_err());
+    }
+
+    #[test]
+    fn test_collection_stats_are_summed_over_shards() {
+        let shard = |count: i64, size: i64| {
+            doc! {
+                "ns": "database.events",
+                "shard": "shard",
+                "storageStats": {
+                    "count": count,
+                    "size": size,
+                    "storageSize": size * 2,
+                    "avgObjSize": size / count,
+                    "totalIndexSize": 100_i64,
+                    "indexSizes": { "_id_": 100_i64 },
+                }
+            }
+        };
+
+        let stats = CollectionStats::from_documents(&[shard(10, 1000), shard(30, 1000)]).unwrap();
+        assert_eq!(stats.namespace, "database.events");
+        assert_eq!(stats.count, 40);
+        assert_eq!(stats.size, 2000);
+        assert_eq!(stats.storage_size, 4000);
+        assert_eq!(stats.avg_obj_size, 50);
+        assert_eq!(stats.total_index_size, 200);
+        assert_eq!(stats.index_sizes.get("_id_"), Some(&200));
+
+        assert!(CollectionStats::from_documents(&[]).is_err());
+    }
+
+    #[test]
+    fn test_batch_chunks_by_count_and_size() {
+        let options = BatchWriteOptions {
+            max_count: 3,
+            max_bytes: 100,
+            ordered: true,
+        };
+        assert!(options.chunks(&[]).is_empty());
+        assert_eq!(
+            options.chunks(&[10, 10, 10, 10, 10, 10, 10]),
+            vec![0..3, 3..6, 6..7]
+        );
+        assert_eq!(
+            options.chunks(&[60, 30, 20, 150, 10]),
+            vec![0..2, 2..3, 3..4, 4..5]
+        );
+    }
+
+    #[test]
+    fn test_soft_delete_updates_log_the_change() {
+        let mut metadata = RecordMetadata::default();
+        metadata.mark_deleted("alice");
+        let deleted = soft_delete_update("alice", 1);
+        assert_eq!(
+            deleted
+                .get_document("$set")
+                .unwrap()
+                .get_i64("changeLog.Marked as deleted by alice"),
+            Ok(1)
+        );
+        assert!(metadata
+            .change_log
+            .contains_key("Marked as deleted by alice"));
+
+        metadata.mark_undeleted("bob");
+        let restored = restore_update("bob", 2);
+        assert_eq!(
+            restored
+                .get_document("$set")
+                .unwrap()
+                .get_i64("changeLog.Marked as undeleted by bob"),
+            Ok(2)
+        );
+        assert!(metadata
+            .change_log
+            .contains_key("Marked as undeleted by bob"));
+    }
+
+    #[test]
+    fn test_deleted_filter() {
+        assert_eq!(
+            DeletedFilter::Exclude.apply(Some(doc! { "key": "a" })),
+            doc! { "key": "a", "deleted": { "$ne": true } }
+        );
+        assert_eq!(DeletedFilter::Only.apply(None), doc! { "deleted": true });
+        assert_eq!(DeletedFilter::Include.apply(None), doc! {});
+    }
+
+    #[test]
+    fn test_update_response_is_mapped_by_index() {
+        let mut report = BulkWriteReport {
+            results: vec![BulkItemOutcome::Updated; 5],
+        };
+        // The document at index 1 had no _id and wasn't sent
+        report.results[1] = BulkItemOutcome::Failed {
+            code: 0,
+            message: "Document has no _id".to_owned(),
+        };
+        report.record_update_response(
+            &[0, 2, 3, 4],
+            &doc! {
+                "n": 3,
+                "nModified": 1,
+                "upserted": [{ "index": 1, "_id": "b" }, { "index": 3, "_id": "d" }],
+                "writeErrors": [{ "index": 2, "code": 11000, "errmsg": "duplicate key" }],
+                "ok": 1.0,
+            },
+        );
+
+        assert_eq!(report.inserted(), 2);
+        assert_eq!(report.updated(), 1);
+        assert_eq!(report.failed(), 2);
+        assert_eq!(report.results[0], BulkItemOutcome::Updated);
+        assert_eq!(report.results[2], BulkItemOutcome::Inserted);
+        assert_eq!(
+            report.results[3],
+            BulkItemOutcome::Failed {
+                code: 11000,
+                message: "duplicate key".to_owned()
+            }
+        );
+        assert_eq!(report.results[4], BulkItemOutcome::Inserted);
+    }
+
+    #[test]
+    fn test_bulk_write_report() {
+        let report = BulkWriteReport::from_failures(
+            4,
+            [
+                (1, 11000, "duplicate key".to_owned()),
+                (9, 1, "out of range".to_owned()),
+            ],
+        );
+        assert_eq!(report.inserted(), 3);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.updated(), 0);
+        assert_eq!(
+            report.failures().collect::<Vec<_>>(),
+            vec![(
+                1,
+                &BulkItemOutcome::Failed {
+                    code: 11000,
+                    message: "duplicate key".to_owned()
+                }
+            )]
+        );
+    }
 }
@@ -0,0 +1,237 @@
+use crate::{IntegrationOSError, InternalError, RedisCache};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Fixed window throughput counters. Every window gets its own hash, named after the
+/// window's index, that expires on its own once the window is over, so nothing has to
+/// reset the counters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThroughputWindow {
+    pub name: String,
+    pub window: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThroughputSnapshot {
+    /// Hits recorded in the current window
+    pub count: u64,
+    pub window: Duration,
+    /// Time until the current window ends and the count starts over
+    pub window_remaining: Duration,
+}
+
+impl ThroughputSnapshot {
+    /// Hits per second over the window
+    pub fn rate(&self) -> f64 {
+        self.count as f64 / self.window.as_secs_f64()
+    }
+
+    pub fn remaining(&self, limit: u64) -> u64 {
+        limit.saturating_sub(self.count)
+    }
+}
+
+impl ThroughputWindow {
+    pub fn new(name: impl Into<String>, window: Duration) -> Self {
+        Self {
+            name: name.into(),
+            window: window.max(Duration::from_millis(1)),
+        }
+    }
+
+    pub fn per_second(name: impl Into<String>) -> Self {
+        Self::new(name, Duration::from_secs(1))
+    }
+
+    pub fn per_minute(name: impl Into<String>) -> Self {
+        Self::new(name, Duration::from_secs(60))
+    }
+
+    fn window_millis(&self) -> i64 {
+        self.window.as_millis() as i64
+    }
+
+    /// Key of the window containing `now` (epoch ms)
+    pub fn key_at(&self, now: i64) -> String {
+        format!("{}::{}", self.name, now.div_euclid(self.window_millis()))
+    }
+
+    pub fn window_remaining_at(&self, now: i64) -> Duration {
+        let window = self.window_millis();
+        Duration::from_millis((window - now.rem_euclid(window)) as u64)
+    }
+
+    pub fn snapshot_at(&self, count: u64, now: i64) -> ThroughputSnapshot {
+        ThroughputSnapshot {
+            count,
+            window: self.window,
+            window_remaining: self.window_remaining_at(now),
+        }
+    }
+}
+
+/// Counts hits per key, e.g. per connection, in a [`ThroughputWindow`]
+#[async_trait]
+pub trait ThroughputCounter: Send + Sync {
+    /// Adds `cost` hits for `key` to the current window
+    async fn record(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+        cost: u64,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError>;
+
+    async fn current(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError>;
+}
+
+#[derive(Clone)]
+pub struct RedisThroughputCounter {
+    cache: RedisCache,
+}
+
+impl RedisThroughputCounter {
+    pub fn new(cache: RedisCache) -> Self {
+        Self { cache }
+    }
+}
+
+fn throughput_error(e: redis::RedisError) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("throughput"))
+}
+
+#[async_trait]
+impl ThroughputCounter for RedisThroughputCounter {
+    async fn record(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+        cost: u64,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError> {
+        let now = Utc::now().timestamp_millis();
+        let bucket = window.key_at(now);
+        let mut conn = self.cache.clone();
+        // Kept for a second window so the previous count stays readable meanwhile
+        let (count, _): (u64, bool) = redis::pipe()
+            .atomic()
+            .hincr(&bucket, key, cost)
+            .pexpire(&bucket, window.window_millis() as usize * 2)
+            .query_async(&mut conn)
+            .await
+            .map_err(throughput_error)?;
+        Ok(window.snapshot_at(count, now))
+    }
+
+    async fn current(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError> {
+        let now = Utc::now().timestamp_millis();
+        let mut conn = self.cache.clone();
+        let count: Option<u64> = redis::cmd("HGET")
+            .arg(window.key_at(now))
+            .arg(key)
+            .query_async(&mut conn)
+            .await
+            .map_err(throughput_error)?;
+        Ok(window.snapshot_at(count.unwrap_or_default(), now))
+    }
+}
+
+/// Process local counters, for tests and single instance deployments
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryThroughputCounter {
+    counts: Arc<Mutex<HashMap<String, (String, u64)>>>,
+}
+
+impl InMemoryThroughputCounter {
+    fn entry_key(window: &ThroughputWindow, key: &str) -> String {
+        format!("{}::{key}", window.name)
+    }
+
+    pub fn record_at(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+        cost: u64,
+        now: i64,
+    ) -> ThroughputSnapshot {
+        let bucket = window.key_at(now);
+        let mut counts = self.counts.lock().expect("throughput lock poisoned");
+        let entry = counts
+            .entry(Self::entry_key(window, key))
+            .or_insert_with(|| (bucket.clone(), 0));
+        if entry.0 != bucket {
+            *entry = (bucket, 0);
+        }
+        entry.1 += cost;
+        window.snapshot_at(entry.1, now)
+    }
+
+    pub fn current_at(&self, window: &ThroughputWindow, key: &str, now: i64) -> ThroughputSnapshot {
+        let bucket = window.key_at(now);
+        let counts = self.counts.lock().expect("throughput lock poisoned");
+        let count = counts
+            .get(&Self::entry_key(window, key))
+            .filter(|(entry_bucket, _)| *entry_bucket == bucket)
+            .map_or(0, |(_, count)| *count);
+        window.snapshot_at(count, now)
+    }
+}
+
+#[async_trait]
+impl ThroughputCounter for InMemoryThroughputCounter {
+    async fn record(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+        cost: u64,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError> {
+        Ok(self.record_at(window, key, cost, Utc::now().timestamp_millis()))
+    }
+
+    async fn current(
+        &self,
+        window: &ThroughputWindow,
+        key: &str,
+    ) -> Result<ThroughputSnapshot, IntegrationOSError> {
+        Ok(self.current_at(window, key, Utc::now().timestamp_millis()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_throughput_window_rolls_over() {
+        let window = ThroughputWindow::per_second("event_throughput");
+        let counter = InMemoryThroughputCounter::default();
+
+        assert_eq!(window.key_at(12_345), "event_throughput::12");
+        assert_eq!(
+            window.window_remaining_at(12_345),
+            Duration::from_millis(655)
+        );
+
+        counter.record_at(&window, "conn", 3, 12_000);
+        let snapshot = counter.record_at(&window, "conn", 2, 12_999);
+        assert_eq!(snapshot.count, 5);
+        assert_eq!(snapshot.rate(), 5.0);
+        assert_eq!(snapshot.remaining(8), 3);
+        assert_eq!(counter.current_at(&window, "other", 12_500).count, 0);
+
+        // The next window starts from zero without anything deleting the counter
+        assert_eq!(counter.current_at(&window, "conn", 13_000).count, 0);
+        assert_eq!(counter.record_at(&window, "conn", 1, 13_001).count, 1);
+    }
+}
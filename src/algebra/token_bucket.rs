@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// Classic token bucket. Unused tokens carry over between cycles up to `capacity`,
+/// which lets short bursts through while bounding the sustained rate to `rate` per second.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64, capacity: u64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        Self {
+            capacity,
+            rate: rate as f64,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Bucket refilling `requests` tokens per minute, for budgets below one per second
+    pub fn per_minute(requests: u64, capacity: u64) -> Self {
+        Self {
+            rate: requests as f64 / 60.0,
+            ..Self::new(0, capacity)
+        }
+    }
+
+    /// A bucket with no limit, used when a budget is disabled
+    pub fn unlimited() -> Self {
+        Self {
+            capacity: f64::INFINITY,
+            rate: f64::INFINITY,
+            tokens: f64::INFINITY,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.rate.is_infinite()
+    }
+
+    pub fn available(&self) -> u64 {
+        self.tokens.min(u64::MAX as f64) as u64
+    }
+
+    fn refill_at(&mut self, now: Instant) {
+        if self.is_unlimited() {
+            return;
+        }
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn try_acquire_at(&mut self, tokens: u64, now: Instant) -> Result<(), Duration> {
+        self.refill_at(now);
+        let tokens = (tokens as f64).min(self.capacity);
+        if self.tokens >= tokens {
+            self.tokens -= tokens;
+            return Ok(());
+        }
+        if self.rate <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((tokens - self.tokens) / self.rate))
+    }
+
+    pub fn try_acquire(&mut self, tokens: u64) -> bool {
+        self.try_acquire_at(tokens, Instant::now()).is_ok()
+    }
+
+    /// Waits until enough tokens are available. Requests larger than the capacity are
+    /// clamped to it so they can't block forever.
+    pub async fn acquire(&mut self, tokens: u64) {
+        while let Err(wait) = self.try_acquire_at(tokens, Instant::now()) {
+            tokio::time::sleep(wait.min(Duration::from_secs(1))).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refill_and_carry_over() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10, 20);
+        bucket.last_refill = start;
+
+        assert!(bucket.try_acquire_at(20, start).is_ok());
+        let wait = bucket.try_acquire_at(5, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+
+        // One second refills 10 tokens
+        assert!(bucket
+            .try_acquire_at(10, start + Duration::from_secs(1))
+            .is_ok());
+        assert!(bucket
+            .try_acquire_at(1, start + Duration::from_secs(1))
+            .is_err());
+
+        // Unused tokens carry over, but never above the capacity
+        bucket.refill_at(start + Duration::from_secs(60));
+        assert_eq!(bucket.available(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_bucket() {
+        let mut bucket = TokenBucket::unlimited();
+        assert!(bucket.is_unlimited());
+        bucket.acquire(1_000_000).await;
+        assert!(bucket.try_acquire(u64::MAX));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{IntegrationOSError, MongoStore};
+use async_trait::async_trait;
+use bson::{doc, Document};
+use futures::future::BoxFuture;
+use mongodb::{
+    error::UNKNOWN_TRANSACTION_COMMIT_RESULT, options::TransactionOptions, Client, ClientSession,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+const MAX_COMMIT_ATTEMPTS: usize = 3;
+
+/// Session handed to a transaction body. Every write made through it is committed or
+/// rolled back together with the others.
+pub struct TransactionContext {
+    session: ClientSession,
+}
+
+impl TransactionContext {
+    pub fn session(&mut self) -> &mut ClientSession {
+        &mut self.session
+    }
+
+    pub async fn get_one<T>(
+        &mut self,
+        store: &MongoStore<T>,
+        filter: Document,
+    ) -> Result<Option<T>, IntegrationOSError>
+    where
+        T: Serialize + DeserializeOwned + Unpin + Sync + Send,
+    {
+        Ok(store
+            .collection
+            .find_one_with_session(filter, None, &mut self.session)
+            .await?)
+    }
+
+    pub async fn create_one<T>(
+        &mut self,
+        store: &MongoStore<T>,
+        data: &T,
+    ) -> Result<(), IntegrationOSError>
+    where
+        T: Serialize + DeserializeOwned + Unpin + Sync + Send,
+    {
+        store
+            .collection
+            .insert_one_with_session(data, None, &mut self.session)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_one<T>(
+        &mut self,
+        store: &MongoStore<T>,
+        id: &str,
+        data: Document,
+    ) -> Result<(), IntegrationOSError>
+    where
+        T: Serialize + DeserializeOwned + Unpin + Sync + Send,
+    {
+        store
+            .collection
+            .update_one_with_session(doc! { "_id": id }, data, None, &mut self.session)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_one<T>(
+        &mut self,
+        store: &MongoStore<T>,
+        id: &str,
+    ) -> Result<(), IntegrationOSError>
+    where
+        T: Serialize + DeserializeOwned + Unpin + Sync + Send,
+    {
+        store
+            .collection
+            .delete_one_with_session(doc! { "_id": id }, None, &mut self.session)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait TransactionalStore {
+    /// Runs `f` inside a transaction, committing when it succeeds and aborting when it
+    /// returns an error. Requires a replica set or sharded cluster.
+    async fn with_transaction<R, F>(&self, f: F) -> Result<R, IntegrationOSError>
+    where
+        R: Send,
+        F: for<'a> FnOnce(
+                &'a mut TransactionContext,
+            ) -> BoxFuture<'a, Result<R, IntegrationOSError>>
+            + Send;
+}
+
+#[async_trait]
+impl TransactionalStore for Client {
+    async fn with_transaction<R, F>(&self, f: F) -> Result<R, IntegrationOSError>
+    where
+        R: Send,
+        F: for<'a> FnOnce(
+                &'a mut TransactionContext,
+            ) -> BoxFuture<'a, Result<R, IntegrationOSError>>
+            + Send,
+    {
+        let mut context = TransactionContext {
+            session: self.start_session(None).await?,
+        };
+        context
+            .session
+            .start_transaction(TransactionOptions::default())
+            .await?;
+
+        let result = match f(&mut context).await {
+            Ok(result) => result,
+            Err(e) => {
+                if let Err(abort) = context.session.abort_transaction().await {
+                    warn!("Could not abort transaction: {abort}");
+                }
+                return Err(e);
+            }
+        };
+
+        let mut attempt = 1;
+        loop {
+            match context.session.commit_transaction().await {
+                Ok(()) => return Ok(result),
+                Err(e)
+                    if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT)
+                        && attempt < MAX_COMMIT_ATTEMPTS =>
+                {
+                    warn!("Retrying transaction commit after unknown result: {e}");
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T> TransactionalStore for MongoStore<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    async fn with_transaction<R, F>(&self, f: F) -> Result<R, IntegrationOSError>
+    where
+        R: Send,
+        F: for<'a> FnOnce(
+                &'a mut TransactionContext,
+            ) -> BoxFuture<'a, Result<R, IntegrationOSError>>
+            + Send,
+    {
+        self.collection.client().with_transaction(f).await
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::{
+        connection::{
+            connection_model_definition::{ConnectionModelDefinition, PlatformInfo},
+            Connection, OAuth,
+        },
+        shared::settings::Settings,
+    },
+    ApplicationError, IntegrationOSError, MongoStore,
+};
+use bson::doc;
+use reqwest::Url;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use strum::{AsRefStr, Display as StrumDisplay};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, StrumDisplay, AsRefStr,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ValidationCode {
+    /// The field is empty
+    Required,
+    /// The field is set but malformed, e.g. an id with the wrong prefix
+    Invalid,
+    /// The field contradicts another field of the record
+    Inconsistent,
+}
+
+/// Why one field of a record is invalid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    /// Path of the field, e.g. `oauth.connectionOauthDefinitionId`
+    pub field: String,
+    pub code: ValidationCode,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: impl Into<String>, code: ValidationCode, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.field, self.code, self.message)
+    }
+}
+
+/// Internal consistency of a domain record, checked before it is persisted
+pub trait Validate {
+    /// Every problem found, empty when the record is valid
+    fn validate(&self) -> Vec<ValidationError>;
+
+    fn is_valid(&self) -> bool {
+        self.validate().is_empty()
+    }
+
+    /// `UnprocessableEntity` listing the problems found, if any
+    fn ensure_valid(&self) -> Result<(), IntegrationOSError> {
+        let errors = self.validate();
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let message = errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(ApplicationError::unprocessable_entity(
+            &message,
+            Some("validation"),
+        ))
+    }
+}
+
+/// Collects the errors of one record, field by field
+#[derive(Debug, Default)]
+struct Validator(Vec<ValidationError>);
+
+impl Validator {
+    fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.0.push(ValidationError::new(
+                field,
+                ValidationCode::Required,
+                "must not be empty",
+            ));
+        }
+        self
+    }
+
+    fn prefix(&mut self, field: &str, id: &Id, prefix: IdPrefix) -> &mut Self {
+        if id.prefix() != prefix {
+            self.0.push(ValidationError::new(
+                field,
+                ValidationCode::Invalid,
+                format!("must be a {prefix} id, got {id}"),
+            ));
+        }
+        self
+    }
+
+    /// Versions are free form, e.g. `1.0.0` or `2024-01`, but end up in keys and paths
+    fn version(&mut self, field: &str, version: &str) -> &mut Self {
+        self.required(field, version);
+        if version.chars().any(|c| c.is_whitespace()) || version.contains("::") {
+            self.0.push(ValidationError::new(
+                field,
+                ValidationCode::Invalid,
+                "must not contain whitespace or `::`",
+            ));
+        }
+        self
+    }
+
+    /// Settings the pipeline can't honour, with their paths under `field`
+    fn settings(&mut self, field: &str, settings: &Settings) -> &mut Self {
+        for (path, message) in settings.invalid_fields() {
+            self.0.push(ValidationError::new(
+                format!("{field}.{path}"),
+                ValidationCode::Invalid,
+                message,
+            ));
+        }
+        self
+    }
+
+    fn check(&mut self, valid: bool, error: impl FnOnce() -> ValidationError) -> &mut Self {
+        if !valid {
+            self.0.push(error());
+        }
+        self
+    }
+
+    fn finish(&mut self) -> Vec<ValidationError> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl Validate for Connection {
+    fn validate(&self) -> Vec<ValidationError> {
+        let oauth_enabled = matches!(self.oauth, Some(OAuth::Enabled { .. }));
+        Validator::default()
+            .prefix("_id", &self.id, IdPrefix::Connection)
+            .prefix(
+                "connectionDefinitionId",
+                &self.connection_definition_id,
+                IdPrefix::ConnectionDefinition,
+            )
+            .prefix(
+                "eventAccessId",
+                &self.event_access_id,
+                IdPrefix::EventAccess,
+            )
+            .required("key", &self.key)
+            .required("name", &self.name)
+            .required("group", &self.group)
+            .required("platform", &self.platform)
+            .version("platformVersion", &self.platform_version)
+            .check(self.throughput.limit > 0, || {
+                ValidationError::new(
+                    "throughput.limit",
+                    ValidationCode::Invalid,
+                    "must be greater than 0",
+                )
+            })
+            .check(!self.settings.oauth || oauth_enabled, || {
+                ValidationError::new(
+                    "oauth",
+                    ValidationCode::Inconsistent,
+                    "settings enable OAuth but no OAuth definition is set",
+                )
+            })
+            .check(self.settings.oauth || !oauth_enabled, || {
+                ValidationError::new(
+                    "settings.oauth",
+                    ValidationCode::Inconsistent,
+                    "an OAuth definition is set but settings disable OAuth",
+                )
+            })
+            .settings("settings", &self.settings)
+            .finish()
+    }
+}
+
+impl Validate for ConnectionModelDefinition {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut validator = Validator::default();
+        validator
+            .prefix("_id", &self.id, IdPrefix::ConnectionModelDefinition)
+            .prefix(
+                "connectionDefinitionId",
+                &self.connection_definition_id,
+                IdPrefix::ConnectionDefinition,
+            )
+            .required("key", &self.key)
+            .required("connectionPlatform", &self.connection_platform)
+            .required("modelName", &self.model_name)
+            .version("platformVersion", &self.platform_version);
+
+        let PlatformInfo::Api(config) = &self.platform_info;
+        validator.check(Url::parse(&config.uri()).is_ok(), || {
+            ValidationError::new(
+                "baseUrl",
+                ValidationCode::Invalid,
+                format!("{} is not a valid URL", config.uri()),
+            )
+        });
+
+        if let Some(mapping) = &self.mapping {
+            validator.check(mapping.action == self.action_name, || {
+                ValidationError::new(
+                    "mapping.action",
+                    ValidationCode::Inconsistent,
+                    format!(
+                        "{} does not match actionName {}",
+                        mapping.action, self.action_name
+                    ),
+                )
+            });
+        }
+        if let Some(extractor) = &self.extractor_config {
+            validator.check(extractor.batch_size > 0, || {
+                ValidationError::new(
+                    "batchSize",
+                    ValidationCode::Invalid,
+                    "must be greater than 0",
+                )
+            });
+        }
+        validator.finish()
+    }
+}
+
+/// [`MongoStore`] refusing to write records failing [`Validate`], as `UnprocessableEntity`
+#[derive(Debug, Clone)]
+pub struct ValidatedStore<T: Serialize + DeserializeOwned + Unpin + Sync> {
+    pub store: MongoStore<T>,
+}
+
+impl<T> ValidatedStore<T>
+where
+    T: Validate + Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    pub fn new(store: MongoStore<T>) -> Self {
+        Self { store }
+    }
+
+    pub async fn create_one(&self, data: &T) -> Result<(), IntegrationOSError> {
+        data.ensure_valid()?;
+        self.store.create_one(data).await
+    }
+
+    /// Writes nothing unless every record is valid
+    pub async fn create_many(&self, data: &[T]) -> Result<(), IntegrationOSError> {
+        data.iter().try_for_each(Validate::ensure_valid)?;
+        self.store.create_many(data).await
+    }
+
+    /// Replaces the record with id `id`, or creates it
+    pub async fn save(&self, id: &str, data: &T) -> Result<(), IntegrationOSError> {
+        data.ensure_valid()?;
+        self.store
+            .collection
+            .replace_one(
+                doc! { "_id": id },
+                data,
+                mongodb::options::ReplaceOptions::builder()
+                    .upsert(true)
+                    .build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::connection::connection_model_definition::{CrudAction, CrudMapping};
+    use crate::prelude::shared::settings::ConcurrencyLimit;
+
+    #[test]
+    fn test_connection_validation() {
+        let definition_id = Id::now(IdPrefix::ConnectionDefinition);
+        let mut connection = Connection::builder("stripe", definition_id).build();
+        assert!(connection.is_valid());
+
+        connection.key = "".into();
+        connection.platform_version = "1 0".to_owned();
+        connection.settings.oauth = true;
+        let errors = connection.validate();
+        assert_eq!(
+            errors
+                .iter()
+                .map(|e| (e.field.as_str(), e.code))
+                .collect::<Vec<_>>(),
+            vec![
+                ("key", ValidationCode::Required),
+                ("platformVersion", ValidationCode::Invalid),
+                ("oauth", ValidationCode::Inconsistent),
+            ]
+        );
+        let error = connection.ensure_valid().unwrap_err();
+        assert!(error.to_string().contains("key (required)"));
+
+        let mut connection = Connection::builder("stripe", definition_id).build();
+        connection.settings.concurrency_limit = Some(ConcurrencyLimit {
+            max_concurrent: 0,
+            lease_secs: 0,
+        });
+        assert_eq!(
+            connection
+                .validate()
+                .into_iter()
+                .map(|e| e.field)
+                .collect::<Vec<_>>(),
+            vec![
+                "settings.concurrencyLimit.maxConcurrent",
+                "settings.concurrencyLimit.leaseSecs"
+            ]
+        );
+
+        let mut model = ConnectionModelDefinition::builder(
+            definition_id,
+            "stripe",
+            "v1",
+            "Customers",
+            CrudAction::GetMany,
+        )
+        .endpoint("https://api.stripe.com", "/v1/customers")
+        .build();
+        assert!(model.is_valid());
+        model.connection_definition_id = Id::now(IdPrefix::Connection);
+        model.mapping = Some(CrudMapping {
+            action: CrudAction::Create,
+            common_model_name: "Customers".to_owned(),
+            from_common_model: None,
+            to_common_model: None,
+        });
+        assert_eq!(
+            model
+                .validate()
+                .into_iter()
+                .map(|e| e.field)
+                .collect::<Vec<_>>(),
+            vec!["connectionDefinitionId", "mapping.action"]
+        );
+    }
+}
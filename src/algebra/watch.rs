@@ -0,0 +1,223 @@
+use crate::{IntegrationOSError, InternalError, MongoStore};
+use async_trait::async_trait;
+use bson::{doc, Document};
+use futures::{stream::BoxStream, StreamExt};
+use mongodb::{
+    change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken},
+    options::{ChangeStreamOptions, FullDocumentType, ReplaceOptions},
+    Collection,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Replace,
+    Delete,
+    Other(String),
+}
+
+impl From<OperationType> for ChangeOperation {
+    fn from(operation: OperationType) -> Self {
+        match operation {
+            OperationType::Insert => ChangeOperation::Insert,
+            OperationType::Update => ChangeOperation::Update,
+            OperationType::Replace => ChangeOperation::Replace,
+            OperationType::Delete => ChangeOperation::Delete,
+            OperationType::Other(other) => ChangeOperation::Other(other),
+            other => ChangeOperation::Other(format!("{other:?}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangeEvent<T> {
+    pub operation: ChangeOperation,
+    pub document_key: Option<Document>,
+    /// Current version of the document, looked up for updates
+    pub document: Option<T>,
+    pub updated_fields: Option<Document>,
+    pub removed_fields: Vec<String>,
+    pub resume_token: ResumeToken,
+}
+
+impl<T> From<ChangeStreamEvent<T>> for ChangeEvent<T> {
+    fn from(event: ChangeStreamEvent<T>) -> Self {
+        let (updated_fields, removed_fields) = match event.update_description {
+            Some(description) => (Some(description.updated_fields), description.removed_fields),
+            None => (None, vec![]),
+        };
+        Self {
+            operation: event.operation_type.into(),
+            document_key: event.document_key,
+            document: event.full_document,
+            updated_fields,
+            removed_fields,
+            resume_token: event.id,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ResumeTokenStore: Send + Sync {
+    async fn load(&self, stream: &str) -> Result<Option<ResumeToken>, IntegrationOSError>;
+    async fn save(&self, stream: &str, token: &ResumeToken) -> Result<(), IntegrationOSError>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryResumeTokens(Arc<Mutex<HashMap<String, ResumeToken>>>);
+
+#[async_trait]
+impl ResumeTokenStore for InMemoryResumeTokens {
+    async fn load(&self, stream: &str) -> Result<Option<ResumeToken>, IntegrationOSError> {
+        Ok(self
+            .0
+            .lock()
+            .expect("resume tokens lock poisoned")
+            .get(stream)
+            .cloned())
+    }
+
+    async fn save(&self, stream: &str, token: &ResumeToken) -> Result<(), IntegrationOSError> {
+        self.0
+            .lock()
+            .expect("resume tokens lock poisoned")
+            .insert(stream.to_owned(), token.clone());
+        Ok(())
+    }
+}
+
+/// Resume tokens kept in a collection, one document per named stream
+#[derive(Debug, Clone)]
+pub struct MongoResumeTokens {
+    collection: Collection<Document>,
+}
+
+impl MongoResumeTokens {
+    pub fn new(collection: Collection<Document>) -> Self {
+        Self { collection }
+    }
+}
+
+fn token_error(e: impl ToString) -> IntegrationOSError {
+    InternalError::serialize_error(&e.to_string(), Some("resume_token"))
+}
+
+#[async_trait]
+impl ResumeTokenStore for MongoResumeTokens {
+    async fn load(&self, stream: &str) -> Result<Option<ResumeToken>, IntegrationOSError> {
+        let Some(document) = self
+            .collection
+            .find_one(doc! { "_id": stream }, None)
+            .await?
+        else {
+            return Ok(None);
+        };
+        document
+            .get("token")
+            .cloned()
+            .map(bson::from_bson)
+            .transpose()
+            .map_err(token_error)
+    }
+
+    async fn save(&self, stream: &str, token: &ResumeToken) -> Result<(), IntegrationOSError> {
+        let token = bson::to_bson(token).map_err(token_error)?;
+        self.collection
+            .replace_one(
+                doc! { "_id": stream },
+                doc! { "_id": stream, "token": token },
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait WatchExt<T> {
+    /// Streams the changes matching `pipeline`, resuming after the last token saved for
+    /// `stream`. A token is saved once the consumer asks for the next event, so an event
+    /// is delivered again after a restart unless it was fully handled.
+    async fn watch(
+        &self,
+        stream: &str,
+        pipeline: Vec<Document>,
+        tokens: Arc<dyn ResumeTokenStore>,
+    ) -> Result<BoxStream<'static, Result<ChangeEvent<T>, IntegrationOSError>>, IntegrationOSError>;
+}
+
+#[async_trait]
+impl<T> WatchExt<T> for MongoStore<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Send + Sync + 'static,
+{
+    async fn watch(
+        &self,
+        stream: &str,
+        pipeline: Vec<Document>,
+        tokens: Arc<dyn ResumeTokenStore>,
+    ) -> Result<BoxStream<'static, Result<ChangeEvent<T>, IntegrationOSError>>, IntegrationOSError>
+    {
+        let options = ChangeStreamOptions::builder()
+            .full_document(Some(FullDocumentType::UpdateLookup))
+            .resume_after(tokens.load(stream).await?)
+            .build();
+        let changes = self.collection.watch(pipeline, options).await?;
+
+        let state = (changes, tokens, stream.to_owned(), None::<ResumeToken>);
+        let stream =
+            futures::stream::unfold(state, |(mut changes, tokens, stream, pending)| async move {
+                if let Some(token) = pending {
+                    if let Err(e) = tokens.save(&stream, &token).await {
+                        return Some((Err(e), (changes, tokens, stream, Some(token))));
+                    }
+                }
+                match changes.next().await? {
+                    Ok(event) => {
+                        let token = event.id.clone();
+                        Some((Ok(event.into()), (changes, tokens, stream, Some(token))))
+                    }
+                    Err(e) => Some((Err(e.into()), (changes, tokens, stream, None))),
+                }
+            });
+
+        Ok(stream.boxed())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_resume_tokens() {
+        let tokens = InMemoryResumeTokens::default();
+        let token: ResumeToken =
+            bson::from_bson(bson::Bson::Document(doc! { "_data": "8263" })).unwrap();
+
+        assert!(tokens.load("connections").await.unwrap().is_none());
+        tokens.save("connections", &token).await.unwrap();
+        assert_eq!(
+            bson::to_bson(&tokens.load("connections").await.unwrap().unwrap()).unwrap(),
+            bson::to_bson(&token).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_change_operation_from_operation_type() {
+        assert_eq!(
+            ChangeOperation::from(OperationType::Insert),
+            ChangeOperation::Insert
+        );
+        assert_eq!(
+            ChangeOperation::from(OperationType::Invalidate),
+            ChangeOperation::Other("Invalidate".to_owned())
+        );
+    }
+}
@@ -24,6 +24,10 @@ pub struct AccessKeyData {
     pub timestamp_path: Option<String>,
     #[prost(string, optional, tag = "8")]
     pub parent_access_key: Option<String>,
+    /// Milliseconds timestamp after which the key is rejected. Kept in the encrypted data
+    /// rather than the prefix so that it can't be altered without the password.
+    #[prost(int64, optional, tag = "9")]
+    pub expires_at: Option<i64>,
 }
 
 impl TryFrom<&[u8]> for AccessKeyData {
@@ -35,6 +39,15 @@ impl TryFrom<&[u8]> for AccessKeyData {
 }
 
 impl AccessKeyData {
+    pub fn with_expiry(mut self, expires_at: i64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
     pub fn from_slice(vec: &[u8]) -> Result<Self, IntegrationOSError> {
         AccessKeyData::decode(vec).map_err(|e| {
             InternalError::decryption_error(
@@ -71,6 +84,7 @@ mod tests {
             event_object_id_path: Some("quuz".to_owned()),
             timestamp_path: None,
             parent_access_key: None,
+            expires_at: None,
         };
         let vec = access_key_data.to_vec().unwrap();
         assert_eq!(access_key_data, AccessKeyData::from_slice(&vec).unwrap());
@@ -1,5 +1,12 @@
-use super::{access_key_prefix::AccessKeyPrefix, encrypted_data::EncryptedData};
-use crate::{IntegrationOSError, InternalError};
+use super::{
+    access_key_prefix::AccessKeyPrefix, encrypted_data::EncryptedData, event_type::EventType,
+};
+#[cfg(feature = "backend")]
+use crate::prelude::{connection::Connection, event::event_access::EventAccess};
+use crate::{
+    prelude::configuration::environment::Environment, ApplicationError, ErrorMeta,
+    IntegrationOSError, InternalError,
+};
 use base64ct::{Base64UrlUnpadded, Encoding};
 use std::{
     borrow::Cow,
@@ -7,6 +14,40 @@ use std::{
     hash::{Hash, Hasher},
     str,
 };
+use thiserror::Error;
+
+/// Header API requests authenticate with
+pub const ACCESS_KEY_HEADER: &str = "x-integrationos-secret";
+
+/// An access key used with a connection or event access it was not issued for, caught
+/// from its prefix before attempting to decrypt it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum AccessKeyMismatch {
+    #[error("A {found} access key cannot be used with a {expected} {resource}")]
+    Environment {
+        resource: &'static str,
+        expected: Environment,
+        found: Environment,
+    },
+    #[error(
+        "A `{found}` access key cannot be used with a {resource} expecting a `{expected}` key"
+    )]
+    EventType {
+        resource: &'static str,
+        expected: EventType,
+        found: EventType,
+    },
+}
+
+impl From<AccessKeyMismatch> for IntegrationOSError {
+    fn from(error: AccessKeyMismatch) -> Self {
+        let subtype = match error {
+            AccessKeyMismatch::Environment { .. } => "access_key_environment_mismatch",
+            AccessKeyMismatch::EventType { .. } => "access_key_type_mismatch",
+        };
+        ApplicationError::forbidden(&error.to_string(), Some(subtype))
+    }
+}
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct EncryptedAccessKey<'a> {
@@ -87,6 +128,79 @@ impl<'a> EncryptedAccessKey<'a> {
         }
         Ok(EncryptedData::new(remainder))
     }
+
+    /// Rejects keys of another environment than the `resource` they are used with
+    pub fn ensure_environment(
+        &self,
+        resource: &'static str,
+        environment: Environment,
+    ) -> Result<(), AccessKeyMismatch> {
+        if self.prefix.environment == environment {
+            Ok(())
+        } else {
+            Err(AccessKeyMismatch::Environment {
+                resource,
+                expected: environment,
+                found: self.prefix.environment,
+            })
+        }
+    }
+
+    pub fn ensure_event_type(
+        &self,
+        resource: &'static str,
+        event_type: EventType,
+    ) -> Result<(), AccessKeyMismatch> {
+        if self.prefix.event_type == event_type {
+            Ok(())
+        } else {
+            Err(AccessKeyMismatch::EventType {
+                resource,
+                expected: event_type,
+                found: self.prefix.event_type,
+            })
+        }
+    }
+
+    /// Checks the key against the environment of the connection and the type of the key
+    /// it was created with, when that one can be parsed
+    #[cfg(feature = "backend")]
+    pub fn ensure_matches_connection(
+        &self,
+        connection: &Connection,
+    ) -> Result<(), AccessKeyMismatch> {
+        self.ensure_matches(
+            "connection",
+            connection.environment,
+            connection.access_key.expose_secret(),
+        )
+    }
+
+    #[cfg(feature = "backend")]
+    pub fn ensure_matches_event_access(
+        &self,
+        event_access: &EventAccess,
+    ) -> Result<(), AccessKeyMismatch> {
+        self.ensure_matches(
+            "event access",
+            event_access.environment,
+            event_access.access_key.expose_secret(),
+        )
+    }
+
+    #[cfg(feature = "backend")]
+    fn ensure_matches(
+        &self,
+        resource: &'static str,
+        environment: Environment,
+        issued: &str,
+    ) -> Result<(), AccessKeyMismatch> {
+        self.ensure_environment(resource, environment)?;
+        match EncryptedAccessKey::parse(issued) {
+            Ok(issued) => self.ensure_event_type(resource, issued.prefix.event_type),
+            Err(_) => Ok(()),
+        }
+    }
 }
 
 impl Hash for EncryptedAccessKey<'_> {
@@ -95,6 +209,30 @@ impl Hash for EncryptedAccessKey<'_> {
     }
 }
 
+impl EncryptedAccessKey<'static> {
+    /// Key sent in the [`ACCESS_KEY_HEADER`] header, `Unauthorized` when it is missing or
+    /// malformed
+    pub fn from_header(value: Option<&str>) -> Result<Self, IntegrationOSError> {
+        let value = value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| {
+                ApplicationError::unauthorized(
+                    &format!("Missing {ACCESS_KEY_HEADER} header"),
+                    Some("access_key"),
+                )
+            })?;
+        EncryptedAccessKey::parse(value)
+            .map(EncryptedAccessKey::to_static)
+            .map_err(|e| {
+                ApplicationError::unauthorized(
+                    &format!("Invalid access key: {}", e.message()),
+                    Some("access_key"),
+                )
+            })
+    }
+}
+
 impl Display for EncryptedAccessKey<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}_{}", self.prefix, self.data)
@@ -152,4 +290,69 @@ mod test {
         let encrypted = EncryptedAccessKey::parse(key).unwrap();
         assert_eq!(key, encrypted.to_string());
     }
+
+    #[test]
+    fn test_from_header() {
+        let key = EncryptedAccessKey::from_header(Some(" sk_test_1_foo ")).unwrap();
+        assert_eq!(key.prefix.environment, Environment::Test);
+        assert_eq!(
+            Environment::from_headers(None, Some("sk_test_1_foo")).unwrap(),
+            Environment::Test
+        );
+        assert_eq!(
+            Environment::from_headers(Some("live"), Some("sk_test_1_foo")).unwrap(),
+            Environment::Live
+        );
+
+        let missing = EncryptedAccessKey::from_header(Some("")).unwrap_err();
+        assert_eq!(
+            missing.key().to_string(),
+            "err::application::unauthorized::access_key"
+        );
+        assert!(EncryptedAccessKey::from_header(Some("sk")).is_err());
+        assert!(Environment::from_headers(Some("staging"), None).is_err());
+        assert!(Environment::from_headers(None, None).is_err());
+    }
+
+    #[cfg(feature = "backend")]
+    #[test]
+    fn test_mismatched_access_keys() {
+        let key = EncryptedAccessKey::parse("sk_test_1_foo").unwrap();
+
+        assert!(key
+            .ensure_matches("connection", Environment::Test, "sk_test_1_bar")
+            .is_ok());
+        // Keys that cannot be parsed do not decide the expected type
+        assert!(key
+            .ensure_matches("connection", Environment::Test, "")
+            .is_ok());
+
+        let err = key
+            .ensure_matches("connection", Environment::Live, "sk_live_1_bar")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            AccessKeyMismatch::Environment {
+                resource: "connection",
+                expected: Environment::Live,
+                found: Environment::Test,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "A test access key cannot be used with a live connection"
+        );
+        assert_eq!(
+            key.ensure_matches("event access", Environment::Test, "id_test_1_bar"),
+            Err(AccessKeyMismatch::EventType {
+                resource: "event access",
+                expected: EventType::Id,
+                found: EventType::SecretKey,
+            })
+        );
+        assert_eq!(
+            http::StatusCode::from(&IntegrationOSError::from(err)),
+            http::StatusCode::FORBIDDEN
+        );
+    }
 }
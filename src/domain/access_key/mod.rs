@@ -26,18 +26,37 @@ pub mod access_key_prefix;
 pub mod encrypted_access_key;
 pub mod encrypted_data;
 pub mod event_type;
+pub mod rotation;
 
 use self::{
     access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
     encrypted_access_key::EncryptedAccessKey,
 };
-use crate::IntegrationOSError;
+use crate::{stable_code::EXPIRED_ACCESS_KEY_SUBTYPE, ApplicationError, IntegrationOSError};
 use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::Utc;
 use encrypted_data::{EncryptedData, IV_LENGTH, PASSWORD_LENGTH};
 use std::str;
+use thiserror::Error;
 
 const EVENT_VERSION: &str = "v1";
 
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AccessKeyError {
+    #[error("Access key expired at {expires_at}")]
+    ExpiredAccessKey { expires_at: i64 },
+}
+
+impl From<AccessKeyError> for IntegrationOSError {
+    fn from(error: AccessKeyError) -> Self {
+        match error {
+            AccessKeyError::ExpiredAccessKey { .. } => {
+                ApplicationError::unauthorized(&error.to_string(), Some(EXPIRED_ACCESS_KEY_SUBTYPE))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct AccessKey {
     pub prefix: AccessKeyPrefix,
@@ -65,10 +84,23 @@ impl AccessKey {
     pub fn parse(
         access_key: &EncryptedAccessKey,
         password: &[u8; PASSWORD_LENGTH],
+    ) -> Result<Self, IntegrationOSError> {
+        Self::parse_at(access_key, password, Utc::now().timestamp_millis())
+    }
+
+    /// Same as [`AccessKey::parse`], failing with [`AccessKeyError::ExpiredAccessKey`]
+    /// when the key expired by `now`
+    pub fn parse_at(
+        access_key: &EncryptedAccessKey,
+        password: &[u8; PASSWORD_LENGTH],
+        now: i64,
     ) -> Result<Self, IntegrationOSError> {
         let mut encrypted_content = access_key.get_encrypted_data()?;
         let decrypted_content = encrypted_content.verify_and_decrypt(password)?;
         let data = AccessKeyData::from_slice(decrypted_content)?;
+        if let Some(expires_at) = data.expires_at.filter(|_| data.is_expired(now)) {
+            return Err(AccessKeyError::ExpiredAccessKey { expires_at }.into());
+        }
 
         Ok(AccessKey {
             prefix: access_key.prefix,
@@ -117,6 +149,7 @@ mod tests {
                 event_object_id_path: Some("foo.bar".to_owned()),
                 timestamp_path: Some("foo.bar".to_owned()),
                 parent_access_key: Some("foo.bar".to_owned()),
+                expires_at: None,
             },
         };
 
@@ -125,6 +158,37 @@ mod tests {
         assert_eq!(data, decrypted);
     }
 
+    #[test]
+    fn test_expired_access_key() {
+        let data = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "build-2e76c839f5fd419db6b34682f4cdff1e".to_owned(),
+                namespace: "default".to_owned(),
+                event_type: "webhook".to_owned(),
+                group: "my-webhook".to_owned(),
+                event_path: "event.received".to_owned(),
+                event_object_id_path: None,
+                timestamp_path: None,
+                parent_access_key: None,
+                expires_at: None,
+            }
+            .with_expiry(1_000),
+        };
+
+        let encrypted = data.encode(VALID_PASSWORD, &[0u8; IV_LENGTH]).unwrap();
+        assert_eq!(
+            AccessKey::parse_at(&encrypted, VALID_PASSWORD, 999).unwrap(),
+            data
+        );
+        let error = AccessKey::parse_at(&encrypted, VALID_PASSWORD, 1_000).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unauthorized: Access key expired at 1000"
+        );
+        assert!(AccessKey::parse(&encrypted, VALID_PASSWORD).is_err());
+    }
+
     #[test]
     fn test_parse_valid_access_key() {
         let data = AccessKey {
@@ -142,6 +206,7 @@ mod tests {
                 event_object_id_path: Some("foo.bar".to_owned()),
                 timestamp_path: Some("foo.bar".to_owned()),
                 parent_access_key: Some("foo.bar".to_owned()),
+                expires_at: None,
             },
         };
 
@@ -180,6 +245,7 @@ mod tests {
                 event_object_id_path: None,
                 timestamp_path: None,
                 parent_access_key: None,
+                expires_at: None,
             },
         };
 
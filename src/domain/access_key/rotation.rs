@@ -0,0 +1,240 @@
+use super::{
+    access_key_data::AccessKeyData,
+    access_key_prefix::AccessKeyPrefix,
+    encrypted_access_key::EncryptedAccessKey,
+    encrypted_data::{IV_LENGTH, PASSWORD_LENGTH},
+    event_type::EventType,
+    AccessKey,
+};
+use crate::{
+    prelude::configuration::environment::Environment, ApplicationError, HashExt, HashKecAlg,
+    IntegrationOSError, InternalError,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Password of one access key version. Keys of a version stay valid until `not_after`,
+/// which is only set once a newer version replaces it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AccessKeyVersion {
+    pub version: u32,
+    password: [u8; PASSWORD_LENGTH],
+    pub created_at: i64,
+    pub not_after: Option<i64>,
+}
+
+impl AccessKeyVersion {
+    pub fn new(version: u32, password: [u8; PASSWORD_LENGTH], created_at: i64) -> Self {
+        Self {
+            version,
+            password,
+            created_at,
+            not_after: None,
+        }
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        self.not_after.is_none_or(|not_after| now < not_after)
+    }
+}
+
+impl std::fmt::Debug for AccessKeyVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessKeyVersion")
+            .field("version", &self.version)
+            .field("created_at", &self.created_at)
+            .field("not_after", &self.not_after)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fingerprints of access keys revoked before their version expires, e.g. after a leak
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationList {
+    pub fingerprints: BTreeSet<String>,
+}
+
+impl RevocationList {
+    pub fn fingerprint(access_key: &str) -> String {
+        HashKecAlg.hash(access_key).unwrap_or_default()
+    }
+
+    pub fn revoke(&mut self, access_key: &str) {
+        self.fingerprints.insert(Self::fingerprint(access_key));
+    }
+
+    pub fn is_revoked(&self, access_key: &str) -> bool {
+        self.fingerprints.contains(&Self::fingerprint(access_key))
+    }
+}
+
+/// Access key versions of an ownership. New keys are issued with the latest version while
+/// keys of the previous versions keep working through their overlap window, so clients
+/// can be cut over one at a time.
+#[derive(Debug, Clone, Default)]
+pub struct AccessKeyRing {
+    versions: BTreeMap<u32, AccessKeyVersion>,
+    pub revoked: RevocationList,
+}
+
+impl AccessKeyRing {
+    pub fn new(first: AccessKeyVersion) -> Self {
+        Self {
+            versions: BTreeMap::from([(first.version, first)]),
+            revoked: RevocationList::default(),
+        }
+    }
+
+    pub fn with_revocations(mut self, revoked: RevocationList) -> Self {
+        self.revoked = revoked;
+        self
+    }
+
+    pub fn insert(&mut self, version: AccessKeyVersion) {
+        self.versions.insert(version.version, version);
+    }
+
+    pub fn current(&self) -> Option<&AccessKeyVersion> {
+        self.versions.values().next_back()
+    }
+
+    pub fn allowed_versions(&self, now: i64) -> Vec<u32> {
+        self.versions
+            .values()
+            .filter(|version| version.is_active(now))
+            .map(|version| version.version)
+            .collect()
+    }
+
+    /// Adds a version with the given password and makes the previous ones expire after
+    /// `overlap_millis`. Returns the new version number.
+    pub fn rotate(
+        &mut self,
+        password: [u8; PASSWORD_LENGTH],
+        overlap_millis: i64,
+        now: i64,
+    ) -> u32 {
+        let version = self.current().map_or(1, |current| current.version + 1);
+        for previous in self.versions.values_mut() {
+            let not_after = now + overlap_millis;
+            previous.not_after = Some(previous.not_after.map_or(not_after, |n| n.min(not_after)));
+        }
+        self.insert(AccessKeyVersion::new(version, password, now));
+        version
+    }
+
+    /// Expires a version right away, invalidating every key issued with it
+    pub fn revoke_version(&mut self, version: u32, now: i64) {
+        if let Some(version) = self.versions.get_mut(&version) {
+            version.not_after = Some(now);
+        }
+    }
+
+    /// Issues a key with the current version
+    pub fn issue(
+        &self,
+        environment: Environment,
+        event_type: EventType,
+        data: AccessKeyData,
+        iv: &[u8; IV_LENGTH],
+    ) -> Result<EncryptedAccessKey<'static>, IntegrationOSError> {
+        let current = self.current().ok_or_else(|| {
+            InternalError::configuration_error("No access key version", Some("access_key"))
+        })?;
+        AccessKey {
+            prefix: AccessKeyPrefix::new(environment, event_type, current.version),
+            data,
+        }
+        .encode(&current.password, iv)
+        .map(EncryptedAccessKey::to_static)
+    }
+
+    /// Decrypts a key, checking it was issued with a version that is still allowed and
+    /// wasn't revoked
+    pub fn validate(&self, access_key: &str, now: i64) -> Result<AccessKey, IntegrationOSError> {
+        let encrypted = EncryptedAccessKey::parse(access_key)?;
+        let version = self
+            .versions
+            .get(&encrypted.prefix.version)
+            .filter(|version| version.is_active(now))
+            .ok_or_else(|| {
+                ApplicationError::unauthorized(
+                    &format!(
+                        "Access key version {} is not allowed",
+                        encrypted.prefix.version
+                    ),
+                    Some("access_key_version"),
+                )
+            })?;
+        if self.revoked.is_revoked(access_key) {
+            return Err(ApplicationError::unauthorized(
+                "Access key was revoked",
+                Some("access_key_revoked"),
+            ));
+        }
+        AccessKey::parse_at(&encrypted, &version.password, now)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIRST: &[u8; PASSWORD_LENGTH] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+    const SECOND: &[u8; PASSWORD_LENGTH] = b"vOVH6sdmpNWjRRIqCc7rdxs01lxHzfr3";
+
+    fn data() -> AccessKeyData {
+        AccessKeyData {
+            id: "build-2e76c839f5fd419db6b34682f4cdff1e".to_owned(),
+            namespace: "default".to_owned(),
+            event_type: "webhook".to_owned(),
+            group: "my-webhook".to_owned(),
+            event_path: "event.received".to_owned(),
+            event_object_id_path: None,
+            timestamp_path: None,
+            parent_access_key: None,
+            expires_at: None,
+        }
+    }
+
+    fn issue(ring: &AccessKeyRing) -> String {
+        ring.issue(Environment::Live, EventType::Id, data(), &[0u8; IV_LENGTH])
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_version_valid_during_overlap() {
+        let mut ring = AccessKeyRing::new(AccessKeyVersion::new(1, *FIRST, 0));
+        let old = issue(&ring);
+        assert!(old.starts_with("id_live_1_"));
+
+        assert_eq!(ring.rotate(*SECOND, 1_000, 10), 2);
+        let new = issue(&ring);
+        assert!(new.starts_with("id_live_2_"));
+        assert_eq!(ring.allowed_versions(500), vec![1, 2]);
+
+        assert_eq!(ring.validate(&old, 500).unwrap().data, data());
+        assert_eq!(ring.validate(&new, 500).unwrap().data, data());
+        assert!(ring.validate(&old, 1_010).is_err());
+        assert!(ring.validate(&new, 1_010).is_ok());
+    }
+
+    #[test]
+    fn test_revoked_keys_and_versions_are_rejected() {
+        let mut ring = AccessKeyRing::new(AccessKeyVersion::new(1, *FIRST, 0));
+        let key = issue(&ring);
+        ring.revoked.revoke(&key);
+        assert!(ring
+            .validate(&key, 0)
+            .unwrap_err()
+            .to_string()
+            .contains("revoked"));
+
+        let mut ring = AccessKeyRing::new(AccessKeyVersion::new(1, *FIRST, 0));
+        ring.revoke_version(1, 5);
+        assert!(ring.validate(&key, 5).is_err());
+        assert!(ring.allowed_versions(5).is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::{
+        plan::{PlanLimit, PlanLimits},
+        shared::{ownership::Ownership, record_metadata::RecordMetadata},
+    },
+};
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum UsageMetric {
+    Requests,
+    Events,
+    Connections,
+    PipelineFailures,
+}
+
+impl UsageMetric {
+    /// Plan allowance over `window_secs`, `None` when the plan doesn't limit the metric
+    pub fn quota(&self, limits: &PlanLimits, window_secs: u64) -> Option<u64> {
+        match self {
+            UsageMetric::Requests => limits
+                .get(PlanLimit::RequestsPerMinute)
+                .map(|rpm| rpm.saturating_mul(window_secs.div_ceil(60))),
+            UsageMetric::Events => limits
+                .get(PlanLimit::EventsPerDay)
+                .map(|epd| epd.saturating_mul(window_secs.div_ceil(86_400))),
+            UsageMetric::Connections => limits.get(PlanLimit::MaxConnections),
+            UsageMetric::PipelineFailures => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum AlertThreshold {
+    Absolute {
+        value: u64,
+    },
+    /// Share of the plan quota, e.g. 80 to be warned before hitting the limit
+    PercentOfQuota {
+        percent: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum NotifyChannel {
+    Email { address: String },
+    Webhook { url: String },
+    Slack { webhook_url: String },
+}
+
+/// Usage of a metric aggregated over a time window, as produced by the analytics rollups
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRollup {
+    pub metric: UsageMetric,
+    pub buildable_id: String,
+    pub window_start: i64,
+    pub window_end: i64,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertTrigger {
+    pub alert_id: Id,
+    pub metric: UsageMetric,
+    pub observed: u64,
+    pub threshold: u64,
+    pub channels: Vec<NotifyChannel>,
+    pub triggered_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct UsageAlert {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub ownership: Ownership,
+    pub metric: UsageMetric,
+    pub threshold: AlertThreshold,
+    pub window_secs: u64,
+    pub channels: Vec<NotifyChannel>,
+    /// Set when the alert fires, it doesn't fire again within the same window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_triggered_at: Option<i64>,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl UsageAlert {
+    pub fn new(
+        ownership: Ownership,
+        metric: UsageMetric,
+        threshold: AlertThreshold,
+        window_secs: u64,
+        channels: Vec<NotifyChannel>,
+    ) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Settings),
+            ownership,
+            metric,
+            threshold,
+            window_secs,
+            channels,
+            last_triggered_at: None,
+            record_metadata: Default::default(),
+        }
+    }
+
+    pub fn threshold_value(&self, limits: &PlanLimits) -> Option<u64> {
+        match self.threshold {
+            AlertThreshold::Absolute { value } => Some(value),
+            AlertThreshold::PercentOfQuota { percent } => self
+                .metric
+                .quota(limits, self.window_secs)
+                .map(|quota| quota.saturating_mul(percent as u64) / 100),
+        }
+    }
+
+    /// Sums the rollups of the alert's metric and ownership overlapping the last window
+    /// and fires once the threshold is reached. `now` is in epoch millis.
+    pub fn evaluate(
+        &self,
+        rollups: &[UsageRollup],
+        limits: &PlanLimits,
+        now: i64,
+    ) -> Option<AlertTrigger> {
+        if !self.record_metadata.active || self.record_metadata.deleted {
+            return None;
+        }
+        let since = now - (self.window_secs as i64).saturating_mul(1_000);
+        if self.last_triggered_at.is_some_and(|at| at > since) {
+            return None;
+        }
+        let threshold = self.threshold_value(limits)?;
+        let observed = rollups
+            .iter()
+            .filter(|r| {
+                r.metric == self.metric
+                    && r.buildable_id == self.ownership.id.as_ref()
+                    && r.window_end > since
+                    && r.window_start <= now
+            })
+            .map(|r| r.value)
+            .sum();
+
+        (observed >= threshold).then(|| AlertTrigger {
+            alert_id: self.id,
+            metric: self.metric,
+            observed,
+            threshold,
+            channels: self.channels.clone(),
+            triggered_at: now,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::plan::PlanTier;
+
+    fn rollup(metric: UsageMetric, window_end: i64, value: u64) -> UsageRollup {
+        UsageRollup {
+            metric,
+            buildable_id: "owner".to_owned(),
+            window_start: window_end - 60_000,
+            window_end,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_usage_alert_evaluate() {
+        let ownership = Ownership {
+            id: "owner".into(),
+            ..Default::default()
+        };
+        let mut alert = UsageAlert::new(
+            ownership,
+            UsageMetric::Events,
+            AlertThreshold::PercentOfQuota { percent: 80 },
+            86_400,
+            vec![NotifyChannel::Email {
+                address: "ops@example.com".to_owned(),
+            }],
+        );
+        let limits = PlanLimits::for_tier(PlanTier::Free);
+        let now = 100 * 86_400_000;
+
+        assert_eq!(alert.threshold_value(&limits), Some(8_000));
+        let rollups = vec![
+            rollup(UsageMetric::Events, now - 1_000, 5_000),
+            rollup(UsageMetric::Events, now - 3_600_000, 3_000),
+            rollup(UsageMetric::Events, now - 2 * 86_400_000, 9_000),
+            rollup(UsageMetric::Requests, now, 9_000),
+        ];
+        let trigger = alert.evaluate(&rollups, &limits, now).unwrap();
+        assert_eq!(trigger.observed, 8_000);
+        assert_eq!(trigger.threshold, 8_000);
+
+        alert.last_triggered_at = Some(now - 1_000);
+        assert!(alert.evaluate(&rollups, &limits, now).is_none());
+
+        let unlimited = PlanLimits::default();
+        alert.last_triggered_at = None;
+        assert!(alert.evaluate(&rollups, &unlimited, now).is_none());
+    }
+}
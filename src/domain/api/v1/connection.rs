@@ -0,0 +1,236 @@
+use super::from_value;
+use crate::{
+    id::Id,
+    prelude::{
+        configuration::environment::Environment,
+        connection::{
+            connection_status::{ConnectionStatus, ConnectionStatusError},
+            Connection, ConnectionType, OAuth,
+        },
+        shared::settings::Settings,
+    },
+    IntegrationOSError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Public representation of a [`Connection`]. Storage-only fields such as the
+/// secrets service id, the access key or the throughput key are never part of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionResponse {
+    pub id: Id,
+    pub platform_version: String,
+    pub connection_definition_id: Id,
+    pub r#type: ConnectionType,
+    pub name: String,
+    pub key: String,
+    pub group: String,
+    pub environment: Environment,
+    pub platform: String,
+    pub settings: Settings,
+    pub oauth_enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub active: bool,
+    /// Responses of services predating the lifecycle are of active connections
+    #[serde(default)]
+    pub status: ConnectionStatus,
+}
+
+impl From<&Connection> for ConnectionResponse {
+    fn from(connection: &Connection) -> Self {
+        Self {
+            id: connection.id,
+            platform_version: connection.platform_version.clone(),
+            connection_definition_id: connection.connection_definition_id,
+            r#type: connection.r#type.clone(),
+            name: connection.name.clone(),
+            key: connection.key.to_string(),
+            group: connection.group.clone(),
+            environment: connection.environment,
+            platform: connection.platform.to_string(),
+            settings: connection.settings.clone(),
+            oauth_enabled: matches!(connection.oauth, Some(OAuth::Enabled { .. })),
+            created_at: connection.record_metadata.created_at,
+            updated_at: connection.record_metadata.updated_at,
+            active: connection.record_metadata.active,
+            status: connection.status,
+        }
+    }
+}
+
+impl From<Connection> for ConnectionResponse {
+    fn from(connection: Connection) -> Self {
+        Self::from(&connection)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateConnectionRequest {
+    pub connection_definition_id: Id,
+    pub name: Option<String>,
+    pub group: String,
+    #[serde(default)]
+    pub payload: Option<Value>,
+}
+
+impl TryFrom<Value> for CreateConnectionRequest {
+    type Error = IntegrationOSError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        from_value(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateConnectionRequest {
+    pub name: Option<String>,
+    pub group: Option<String>,
+    pub settings: Option<Settings>,
+    /// Resumes or pauses the connection through its status lifecycle
+    pub active: Option<bool>,
+}
+
+impl TryFrom<Value> for UpdateConnectionRequest {
+    type Error = IntegrationOSError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        from_value(value)
+    }
+}
+
+impl UpdateConnectionRequest {
+    /// Applies the changes made by `actor`. Activating or deactivating the connection
+    /// goes through [`Connection::transition`], so it fails for connections the lifecycle
+    /// doesn't allow it for, e.g. revoked ones.
+    pub fn apply(
+        self,
+        connection: &mut Connection,
+        actor: &str,
+    ) -> Result<(), ConnectionStatusError> {
+        if let Some(active) = self.active {
+            let to = if active {
+                ConnectionStatus::Active
+            } else {
+                ConnectionStatus::Paused
+            };
+            if connection.status != to {
+                connection.transition(to, actor, None)?;
+            }
+        }
+        if let Some(name) = self.name {
+            connection.name = name;
+        }
+        if let Some(group) = self.group {
+            connection.group = group;
+        }
+        if let Some(settings) = self.settings {
+            connection.settings = settings;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_create_connection_request_denies_unknown_fields() {
+        let valid = json!({
+            "connectionDefinitionId": "conn_def::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "group": "group",
+        });
+        let request = CreateConnectionRequest::try_from(valid).unwrap();
+        assert_eq!(request.group, "group");
+        assert!(request.name.is_none());
+
+        let invalid = json!({
+            "connectionDefinitionId": "conn_def::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "group": "group",
+            "secretsServiceId": "secret",
+        });
+        assert!(CreateConnectionRequest::try_from(invalid).is_err());
+    }
+
+    #[test]
+    fn test_connection_response_ignores_unknown_fields() {
+        let response = json!({
+            "id": "conn::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "platformVersion": "1.0.0",
+            "connectionDefinitionId": "conn_def::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "type": { "api": {} },
+            "name": "Stripe",
+            "key": "stripe::test",
+            "group": "group",
+            "environment": "test",
+            "platform": "stripe",
+            "settings": {
+                "parseWebhookBody": false,
+                "showSecret": false,
+                "allowCustomEvents": false,
+                "oauth": false
+            },
+            "oauthEnabled": false,
+            "createdAt": 0,
+            "updatedAt": 0,
+            "active": true,
+            "addedInALaterVersion": true,
+        });
+        let response: ConnectionResponse = serde_json::from_value(response).unwrap();
+        assert_eq!(response.name, "Stripe");
+    }
+
+    #[test]
+    fn test_update_connection_request_goes_through_the_lifecycle() {
+        let mut connection = Connection::builder(
+            "stripe",
+            Id::now(crate::id::prefix::IdPrefix::ConnectionDefinition),
+        )
+        .build();
+        let deactivate = UpdateConnectionRequest {
+            active: Some(false),
+            ..Default::default()
+        };
+        deactivate.clone().apply(&mut connection, "alice").unwrap();
+        assert_eq!(connection.status, ConnectionStatus::Paused);
+        assert!(!connection.record_metadata.active);
+        assert_eq!(connection.status_change.as_ref().unwrap().actor, "alice");
+        assert!(connection
+            .record_metadata
+            .change_log
+            .keys()
+            .any(|entry| entry.starts_with("Status changed from active to paused by alice")));
+        assert_eq!(
+            ConnectionResponse::from(&connection).status,
+            ConnectionStatus::Paused
+        );
+
+        // Repeating it is a no-op
+        let changed_at = connection.status_change.as_ref().unwrap().changed_at;
+        deactivate.apply(&mut connection, "bob").unwrap();
+        assert_eq!(connection.status_change.unwrap().changed_at, changed_at);
+
+        let mut connection = Connection::builder(
+            "stripe",
+            Id::now(crate::id::prefix::IdPrefix::ConnectionDefinition),
+        )
+        .build();
+        connection.revoke("alice", None).unwrap();
+        let activate = UpdateConnectionRequest {
+            name: Some("Renamed".to_owned()),
+            active: Some(true),
+            ..Default::default()
+        };
+        assert!(matches!(
+            activate.apply(&mut connection, "alice"),
+            Err(ConnectionStatusError::InvalidTransition { .. })
+        ));
+        assert_eq!(connection.status, ConnectionStatus::Revoked);
+        assert_ne!(connection.name, "Renamed");
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{
+    id::Id,
+    prelude::{
+        configuration::environment::Environment,
+        event::{event_access::EventAccess, event_state::EventState, Event},
+    },
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSummary {
+    pub id: Id,
+    pub key: Id,
+    pub name: String,
+    pub r#type: String,
+    pub group: String,
+    pub topic: String,
+    pub environment: Environment,
+    pub state: EventState,
+    pub arrived_at: DateTime<Utc>,
+    pub payload_byte_length: usize,
+}
+
+impl From<&Event> for EventSummary {
+    fn from(event: &Event) -> Self {
+        Self {
+            id: event.id,
+            key: event.key,
+            name: event.name.clone(),
+            r#type: event.r#type.clone(),
+            group: event.group.clone(),
+            topic: event.topic.clone(),
+            environment: event.environment,
+            state: event.state.clone(),
+            arrived_at: event.arrived_at,
+            payload_byte_length: event.payload_byte_length,
+        }
+    }
+}
+
+impl From<Event> for EventSummary {
+    fn from(event: Event) -> Self {
+        Self::from(&event)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAccessResponse {
+    pub id: Id,
+    pub name: String,
+    pub key: String,
+    pub namespace: String,
+    pub platform: String,
+    pub group: String,
+    pub environment: Environment,
+    pub throughput: u64,
+    pub created_at: i64,
+    pub active: bool,
+}
+
+impl From<&EventAccess> for EventAccessResponse {
+    fn from(event_access: &EventAccess) -> Self {
+        Self {
+            id: event_access.id,
+            name: event_access.name.clone(),
+            key: event_access.key.clone(),
+            namespace: event_access.namespace.clone(),
+            platform: event_access.platform.clone(),
+            group: event_access.group.clone(),
+            environment: event_access.environment,
+            throughput: event_access.throughput,
+            created_at: event_access.record_metadata.created_at,
+            active: event_access.record_metadata.active,
+        }
+    }
+}
+
+impl From<EventAccess> for EventAccessResponse {
+    fn from(event_access: EventAccess) -> Self {
+        Self::from(&event_access)
+    }
+}
@@ -0,0 +1,16 @@
+pub mod connection;
+pub mod event;
+
+pub use connection::*;
+pub use event::*;
+
+use crate::{IntegrationOSError, InternalError};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub const API_VERSION: &str = "v1";
+
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, IntegrationOSError> {
+    serde_json::from_value(value)
+        .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("api_v1_payload")))
+}
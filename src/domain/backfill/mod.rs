@@ -0,0 +1,210 @@
+use super::background::{JobKind, JobState};
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::{ownership::Ownership, record_metadata::RecordMetadata, unknown_variant},
+    IntegrationOSError, InternalError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum BackfillStatus {
+    Pending,
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+    Failed,
+    /// Status set by a newer service, kept as is so it's written back unchanged
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
+}
+
+impl BackfillStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            BackfillStatus::Cancelled | BackfillStatus::Completed | BackfillStatus::Failed
+        )
+    }
+}
+
+/// A page of historical records fetched from the upstream platform
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackfillPage {
+    pub records: Vec<Value>,
+    pub next_cursor: Option<String>,
+    /// Total number of records upstream, when the platform reports it
+    pub total: Option<u64>,
+}
+
+/// Initial sync of the historical data of a connection, paged through within a request
+/// budget and resumable from its last cursor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackfillJob {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub connection_key: String,
+    pub model: String,
+    pub status: BackfillStatus,
+    pub cursor: Option<String>,
+    pub processed: u64,
+    pub pages: u64,
+    pub estimated_total: Option<u64>,
+    pub page_size: u32,
+    pub requests_per_minute: u32,
+    pub error: Option<String>,
+    pub ownership: Ownership,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl BackfillJob {
+    pub fn new(connection_key: &str, model: &str, ownership: Ownership) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Backfill),
+            connection_key: connection_key.to_owned(),
+            model: model.to_owned(),
+            status: BackfillStatus::Pending,
+            cursor: None,
+            processed: 0,
+            pages: 0,
+            estimated_total: None,
+            page_size: 100,
+            requests_per_minute: 60,
+            error: None,
+            ownership,
+            record_metadata: Default::default(),
+        }
+    }
+
+    pub fn with_budget(mut self, page_size: u32, requests_per_minute: u32) -> Self {
+        self.page_size = page_size;
+        self.requests_per_minute = requests_per_minute;
+        self
+    }
+
+    pub fn progress_percent(&self) -> Option<f64> {
+        if self.status == BackfillStatus::Completed {
+            return Some(100.0);
+        }
+        self.estimated_total
+            .filter(|total| *total > 0)
+            .map(|total| (self.processed as f64 / total as f64 * 100.0).min(100.0))
+    }
+
+    fn transition(
+        &mut self,
+        allowed: &[BackfillStatus],
+        to: BackfillStatus,
+    ) -> Result<(), IntegrationOSError> {
+        if !allowed.contains(&self.status) {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "Cannot move backfill {} from {} to {to}",
+                    self.id, self.status
+                ),
+                Some("backfill"),
+            ));
+        }
+        self.status = to;
+        Ok(())
+    }
+
+    pub fn start(&mut self) -> Result<(), IntegrationOSError> {
+        self.transition(
+            &[BackfillStatus::Pending, BackfillStatus::Running],
+            BackfillStatus::Running,
+        )
+    }
+
+    pub fn pause(&mut self) -> Result<(), IntegrationOSError> {
+        self.transition(
+            &[BackfillStatus::Pending, BackfillStatus::Running],
+            BackfillStatus::Paused,
+        )
+    }
+
+    /// Resumed jobs restart from their last cursor
+    pub fn resume(&mut self) -> Result<(), IntegrationOSError> {
+        self.transition(&[BackfillStatus::Paused], BackfillStatus::Running)
+    }
+
+    pub fn cancel(&mut self) -> Result<(), IntegrationOSError> {
+        self.transition(
+            &[
+                BackfillStatus::Pending,
+                BackfillStatus::Running,
+                BackfillStatus::Paused,
+            ],
+            BackfillStatus::Cancelled,
+        )
+    }
+
+    pub fn fail(&mut self, error: &str) {
+        self.status = BackfillStatus::Failed;
+        self.error = Some(error.to_owned());
+    }
+
+    /// Records a processed page; a page without a next cursor completes the job
+    pub fn record_page(&mut self, records: u64, next_cursor: Option<String>, total: Option<u64>) {
+        self.pages += 1;
+        self.processed += records;
+        if total.is_some() {
+            self.estimated_total = total;
+        }
+        if next_cursor.is_none() {
+            self.status = BackfillStatus::Completed;
+        }
+        self.cursor = next_cursor;
+    }
+}
+
+/// Lets backfills be scheduled and resumed through the generic job queue
+impl JobState for BackfillJob {
+    fn kind() -> JobKind {
+        JobKind::Backfill
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backfill_lifecycle() {
+        let mut job = BackfillJob::new("stripe::test", "Customers", Ownership::default());
+        assert!(job.resume().is_err());
+        job.start().unwrap();
+
+        job.record_page(25, Some("cus_25".to_owned()), Some(100));
+        assert_eq!(job.progress_percent(), Some(25.0));
+        assert_eq!(job.cursor.as_deref(), Some("cus_25"));
+
+        job.pause().unwrap();
+        job.resume().unwrap();
+        job.record_page(10, None, None);
+        assert_eq!(job.status, BackfillStatus::Completed);
+        assert_eq!(job.processed, 35);
+        assert_eq!(job.progress_percent(), Some(100.0));
+        assert!(job.cancel().is_err());
+    }
+
+    #[test]
+    fn test_unknown_status_round_trips() {
+        let mut value = serde_json::to_value(BackfillJob::new(
+            "stripe",
+            "Customers",
+            Ownership::default(),
+        ))
+        .unwrap();
+        value["status"] = "throttled".into();
+        let job: BackfillJob = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(job.status, BackfillStatus::Unknown("throttled".into()));
+        assert_eq!(serde_json::to_value(&job).unwrap(), value);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::{record_metadata::RecordMetadata, unknown_variant},
+};
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use strum::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobKind {
+    Archival,
+    Backfill,
+    Export,
+    Migration,
+    Custom(String),
+}
+
+impl std::fmt::Display for JobKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobKind::Archival => write!(f, "archival"),
+            JobKind::Backfill => write!(f, "backfill"),
+            JobKind::Export => write!(f, "export"),
+            JobKind::Migration => write!(f, "migration"),
+            JobKind::Custom(kind) => write!(f, "{kind}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum BackgroundJobStatus {
+    Queued,
+    Running,
+    Cancelled,
+    Completed,
+    Failed,
+    /// Status set by a newer service, kept as is so it's written back unchanged
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
+}
+
+impl BackgroundJobStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            BackgroundJobStatus::Cancelled
+                | BackgroundJobStatus::Completed
+                | BackgroundJobStatus::Failed
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobProgress {
+    pub completed: u64,
+    pub total: Option<u64>,
+}
+
+impl JobProgress {
+    pub fn new(completed: u64, total: Option<u64>) -> Self {
+        Self { completed, total }
+    }
+
+    pub fn percent(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.completed as f64 / total as f64 * 100.0).min(100.0))
+    }
+}
+
+/// Typed state of a background job, persisted with it on every checkpoint so the job can
+/// resume from it on another worker
+pub trait JobState: Serialize + DeserializeOwned + Clone + Send + Sync + Unpin + 'static {
+    fn kind() -> JobKind;
+}
+
+/// A long-running, resumable unit of work (archival, exports, migrations, backfills)
+/// claimed by workers through a lease kept alive by heartbeats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "S: JobState")]
+pub struct BackgroundJob<S: JobState> {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub kind: JobKind,
+    pub status: BackgroundJobStatus,
+    pub state: S,
+    pub progress: JobProgress,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub lease_owner: Option<String>,
+    pub lease_expires_at: Option<i64>,
+    pub heartbeat_at: Option<i64>,
+    pub cancel_requested: bool,
+    pub error: Option<String>,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl<S: JobState> BackgroundJob<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Job),
+            kind: S::kind(),
+            status: BackgroundJobStatus::Queued,
+            state,
+            progress: Default::default(),
+            attempts: 0,
+            max_attempts: 3,
+            lease_owner: None,
+            lease_expires_at: None,
+            heartbeat_at: None,
+            cancel_requested: false,
+            error: None,
+            record_metadata: Default::default(),
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn is_lease_expired(&self, now: i64) -> bool {
+        self.lease_expires_at.is_none_or(|expires| expires <= now)
+    }
+
+    /// Whether a failed attempt should go back to the queue
+    pub fn can_retry(&self) -> bool {
+        !self.cancel_requested && self.attempts < self.max_attempts
+    }
+
+    pub fn heartbeat_age_millis(&self) -> Option<i64> {
+        self.heartbeat_at
+            .map(|at| Utc::now().timestamp_millis() - at)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct ExportState {
+        cursor: Option<String>,
+    }
+
+    impl JobState for ExportState {
+        fn kind() -> JobKind {
+            JobKind::Export
+        }
+    }
+
+    #[test]
+    fn test_background_job() {
+        let job = BackgroundJob::new(ExportState { cursor: None }).with_max_attempts(2);
+        assert_eq!(job.kind, JobKind::Export);
+        assert!(job.is_lease_expired(0));
+        assert!(job.can_retry());
+
+        let value = serde_json::to_value(&job).unwrap();
+        assert_eq!(value["status"], "queued");
+        assert_eq!(value["state"], json!({ "cursor": null }));
+        let decoded: BackgroundJob<ExportState> = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded, job);
+
+        assert_eq!(JobProgress::new(5, Some(20)).percent(), Some(25.0));
+        assert_eq!(JobProgress::new(5, None).percent(), None);
+        assert_eq!(JobKind::Custom("reindex".to_owned()).to_string(), "reindex");
+    }
+
+    #[test]
+    fn test_unknown_status() {
+        let status: BackgroundJobStatus = serde_json::from_value(json!("suspended")).unwrap();
+        assert_eq!(status, BackgroundJobStatus::Unknown(json!("suspended")));
+        assert!(!status.is_terminal());
+        assert_eq!(serde_json::to_value(&status).unwrap(), json!("suspended"));
+        assert_eq!(
+            serde_json::from_value::<BackgroundJobStatus>(json!("running")).unwrap(),
+            BackgroundJobStatus::Running
+        );
+    }
+}
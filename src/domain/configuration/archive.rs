@@ -0,0 +1,67 @@
+use crate::prelude::shared::redacted::Redacted;
+use envconfig::Envconfig;
+use std::fmt::{Display, Formatter};
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct ArchiveConfig {
+    /// Events that arrived longer ago are moved to the object store
+    #[envconfig(from = "ARCHIVE_AFTER_DAYS", default = "30")]
+    pub after_days: u32,
+    /// Events moved per run
+    #[envconfig(from = "ARCHIVE_BATCH_SIZE", default = "500")]
+    pub batch_size: u64,
+    /// Prefix of the object keys, so the bucket can be shared
+    #[envconfig(from = "ARCHIVE_PREFIX", default = "events")]
+    pub prefix: String,
+}
+
+impl Display for ArchiveConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ARCHIVE_AFTER_DAYS: {}", self.after_days)?;
+        writeln!(f, "ARCHIVE_BATCH_SIZE: {}", self.batch_size)?;
+        writeln!(f, "ARCHIVE_PREFIX: {}", self.prefix)
+    }
+}
+
+/// Bucket of an S3 compatible object store. Google Cloud Storage works through its XML
+/// API with HMAC keys, `ARCHIVE_S3_ENDPOINT` being `https://storage.googleapis.com`.
+#[derive(Envconfig, Debug, Clone)]
+pub struct S3Config {
+    #[envconfig(from = "ARCHIVE_S3_BUCKET", default = "")]
+    pub bucket: String,
+    #[envconfig(from = "ARCHIVE_S3_REGION", default = "us-east-1")]
+    pub region: String,
+    /// Overrides the regional endpoint, e.g. for GCS, MinIO or a VPC endpoint
+    #[envconfig(from = "ARCHIVE_S3_ENDPOINT", default = "")]
+    pub endpoint: String,
+    #[envconfig(from = "AWS_ACCESS_KEY_ID", default = "")]
+    pub access_key_id: String,
+    #[envconfig(from = "AWS_SECRET_ACCESS_KEY", default = "")]
+    pub secret_access_key: Redacted<String>,
+    #[envconfig(from = "AWS_SESSION_TOKEN", default = "")]
+    pub session_token: String,
+}
+
+impl S3Config {
+    pub fn session_token(&self) -> Option<&str> {
+        Some(self.session_token.as_str()).filter(|token| !token.is_empty())
+    }
+
+    pub fn endpoint(&self) -> String {
+        Some(self.endpoint.as_str())
+            .filter(|endpoint| !endpoint.is_empty())
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.region))
+    }
+}
+
+impl Display for S3Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ARCHIVE_S3_BUCKET: {}", self.bucket)?;
+        writeln!(f, "ARCHIVE_S3_REGION: {}", self.region)?;
+        writeln!(f, "ARCHIVE_S3_ENDPOINT: {}", self.endpoint())?;
+        writeln!(f, "AWS_ACCESS_KEY_ID: {}", self.access_key_id)?;
+        writeln!(f, "AWS_SECRET_ACCESS_KEY: ***")?;
+        writeln!(f, "AWS_SESSION_TOKEN: ***")
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ThroughputWindow;
 use envconfig::Envconfig;
 use std::fmt::{Display, Formatter};
 
@@ -24,6 +25,18 @@ impl Default for CacheConfig {
     }
 }
 
+impl CacheConfig {
+    /// Events received per connection, counted per second
+    pub fn event_throughput_window(&self) -> ThroughputWindow {
+        ThroughputWindow::per_second(&self.event_throughput_key)
+    }
+
+    /// API calls per connection, counted per minute
+    pub fn api_throughput_window(&self) -> ThroughputWindow {
+        ThroughputWindow::per_minute(&self.api_throughput_key)
+    }
+}
+
 impl Display for CacheConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "REDIS_URL: {}", self.url)?;
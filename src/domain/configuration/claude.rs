@@ -0,0 +1,38 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::prelude::shared::redacted::Redacted;
+use envconfig::Envconfig;
+use serde::{Deserialize, Serialize};
+
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+fn default_base_url() -> String {
+    ANTHROPIC_BASE_URL.to_owned()
+}
+
+fn default_version() -> String {
+    ANTHROPIC_VERSION.to_owned()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Envconfig)]
+pub struct ClaudeConfig {
+    /// The Anthropic API key
+    #[envconfig(from = "ANTHROPIC_API_KEY", default = "")]
+    pub api_key: Redacted<String>,
+    #[envconfig(from = "ANTHROPIC_BASE_URL", default = "https://api.anthropic.com/v1")]
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
+    /// Sent as the `anthropic-version` header
+    #[envconfig(from = "ANTHROPIC_VERSION", default = "2023-06-01")]
+    #[serde(default = "default_version")]
+    pub version: String,
+}
+
+impl Display for ClaudeConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ANTHROPIC_API_KEY: ***")?;
+        writeln!(f, "ANTHROPIC_BASE_URL: {}", self.base_url)?;
+        writeln!(f, "ANTHROPIC_VERSION: {}", self.version)
+    }
+}
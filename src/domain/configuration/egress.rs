@@ -0,0 +1,35 @@
+use envconfig::Envconfig;
+use std::fmt::{Display, Formatter};
+
+/// Per deployment exceptions to the egress guard, e.g. an on-premise deployment calling
+/// services on its private network
+#[derive(Envconfig, Debug, Clone, Default)]
+pub struct EgressConfig {
+    /// Comma separated hosts reachable even when they resolve to a blocked address
+    #[envconfig(from = "EGRESS_ALLOWED_HOSTS", default = "")]
+    pub allowed_hosts: String,
+    /// Comma separated CIDR ranges exempted from the private range checks
+    #[envconfig(from = "EGRESS_ALLOWED_NETWORKS", default = "")]
+    pub allowed_networks: String,
+}
+
+impl EgressConfig {
+    pub fn allowed_hosts(&self) -> impl Iterator<Item = &str> {
+        split_list(&self.allowed_hosts)
+    }
+
+    pub fn allowed_networks(&self) -> impl Iterator<Item = &str> {
+        split_list(&self.allowed_networks)
+    }
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+impl Display for EgressConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "EGRESS_ALLOWED_HOSTS: {}", self.allowed_hosts)?;
+        writeln!(f, "EGRESS_ALLOWED_NETWORKS: {}", self.allowed_networks)
+    }
+}
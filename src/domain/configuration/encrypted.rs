@@ -0,0 +1,114 @@
+use super::{
+    cache::CacheConfig, claude::ClaudeConfig, database::DatabaseConfig, openai::OpenAiConfig,
+};
+use crate::{
+    prelude::get_secret_request::GetSecretRequest, CryptoExt, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Resolves a value of the form `enc:<buildable id>/<secret id>` through the secrets
+/// provider. Plain values are returned as they are.
+pub async fn decrypt_value<C>(crypto: &C, value: &str) -> Result<String, IntegrationOSError>
+where
+    C: CryptoExt + Sync + ?Sized,
+{
+    let Some(reference) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+        return Ok(value.to_owned());
+    };
+    let (buildable_id, id) = reference.split_once('/').ok_or_else(|| {
+        InternalError::invalid_argument(
+            "Encrypted config values must look like enc:<buildable id>/<secret id>",
+            Some("config"),
+        )
+    })?;
+    let request = GetSecretRequest {
+        id: id.to_owned(),
+        buildable_id: buildable_id.to_owned(),
+    };
+    match crypto.decrypt(&request).await? {
+        Value::String(value) => Ok(value),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// Configs holding values that may be supplied as `enc:` ciphertext
+#[async_trait]
+pub trait EncryptedConfig: Sized + Send {
+    fn encrypted_fields(&mut self) -> Vec<&mut String>;
+
+    /// Replaces every encrypted field with its decrypted value
+    async fn decrypt<C>(mut self, crypto: &C) -> Result<Self, IntegrationOSError>
+    where
+        C: CryptoExt + Sync + ?Sized,
+    {
+        for field in self.encrypted_fields() {
+            if is_encrypted(field) {
+                *field = decrypt_value(crypto, field).await?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+impl EncryptedConfig for DatabaseConfig {
+    fn encrypted_fields(&mut self) -> Vec<&mut String> {
+        vec![
+            &mut self.control_db_url,
+            &mut self.udm_db_url,
+            &mut self.event_db_url,
+            &mut self.context_db_url,
+        ]
+    }
+}
+
+impl EncryptedConfig for CacheConfig {
+    fn encrypted_fields(&mut self) -> Vec<&mut String> {
+        vec![&mut self.url]
+    }
+}
+
+impl EncryptedConfig for OpenAiConfig {
+    fn encrypted_fields(&mut self) -> Vec<&mut String> {
+        vec![self.api_key.expose_secret_mut()]
+    }
+}
+
+impl EncryptedConfig for ClaudeConfig {
+    fn encrypted_fields(&mut self) -> Vec<&mut String> {
+        vec![self.api_key.expose_secret_mut()]
+    }
+}
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use super::*;
+    use crate::testkit::MockSecrets;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_decrypt_config() {
+        let secrets = MockSecrets::new();
+        secrets.insert("db", "ops", json!("mongodb://user:pass@db:27017"));
+
+        let config = DatabaseConfig {
+            control_db_url: "enc:ops/db".to_owned(),
+            ..Default::default()
+        }
+        .decrypt(&secrets)
+        .await
+        .unwrap();
+
+        assert_eq!(config.control_db_url, "mongodb://user:pass@db:27017");
+        assert_eq!(config.udm_db_url, "mongodb://localhost:27017");
+        assert!(decrypt_value(&secrets, "enc:missing-separator")
+            .await
+            .is_err());
+    }
+}
@@ -1,10 +1,16 @@
-use crate::{IntegrationOSError, InternalError};
+use crate::{
+    prelude::access_key::encrypted_access_key::{EncryptedAccessKey, ACCESS_KEY_HEADER},
+    ApplicationError, ErrorMeta, IntegrationOSError, InternalError,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{Display, Formatter},
     str::FromStr,
 };
 
+/// Header naming the environment of a request explicitly
+pub const ENVIRONMENT_HEADER: &str = "x-integrationos-environment";
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize, Hash)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -15,6 +21,33 @@ pub enum Environment {
     Production,
 }
 
+impl Environment {
+    /// Whether connections in this environment talk to the platform's sandbox
+    pub fn is_sandbox(&self) -> bool {
+        matches!(self, Environment::Test | Environment::Development)
+    }
+
+    /// Environment of a request, from the [`ENVIRONMENT_HEADER`] header when sent or else
+    /// from the prefix of the access key
+    pub fn from_headers(
+        environment: Option<&str>,
+        access_key: Option<&str>,
+    ) -> Result<Self, IntegrationOSError> {
+        match environment.map(str::trim).filter(|value| !value.is_empty()) {
+            Some(environment) => Environment::try_from(environment).map_err(|e| {
+                ApplicationError::bad_request(e.message().as_ref(), Some("environment"))
+            }),
+            None if access_key.is_some() => {
+                EncryptedAccessKey::from_header(access_key).map(|key| key.prefix.environment)
+            }
+            None => Err(ApplicationError::bad_request(
+                &format!("Missing {ENVIRONMENT_HEADER} or {ACCESS_KEY_HEADER} header"),
+                Some("environment"),
+            )),
+        }
+    }
+}
+
 impl TryFrom<&str> for Environment {
     type Error = IntegrationOSError;
 
@@ -0,0 +1,81 @@
+use crate::prelude::shared::redacted::Redacted;
+use envconfig::Envconfig;
+use std::fmt::{Display, Formatter};
+
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value).filter(|v| !v.is_empty())
+}
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct GcpKmsConfig {
+    /// Full resource name, `projects/*/locations/*/keyRings/*/cryptoKeys/*`
+    #[envconfig(from = "GCP_KMS_KEY_NAME", default = "")]
+    pub key_name: String,
+    #[envconfig(from = "GCP_KMS_ENDPOINT", default = "https://cloudkms.googleapis.com")]
+    pub endpoint: String,
+    /// Where access tokens are fetched from, the metadata server when running in GCP
+    #[envconfig(
+        from = "GCP_KMS_TOKEN_URL",
+        default = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token"
+    )]
+    pub token_url: String,
+    /// Static access token, used instead of fetching one when set
+    #[envconfig(from = "GCP_KMS_ACCESS_TOKEN", default = "")]
+    pub access_token: String,
+}
+
+impl GcpKmsConfig {
+    pub fn access_token(&self) -> Option<&str> {
+        non_empty(&self.access_token)
+    }
+}
+
+impl Display for GcpKmsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "GCP_KMS_KEY_NAME: {}", self.key_name)?;
+        writeln!(f, "GCP_KMS_ENDPOINT: {}", self.endpoint)?;
+        writeln!(f, "GCP_KMS_TOKEN_URL: {}", self.token_url)?;
+        writeln!(f, "GCP_KMS_ACCESS_TOKEN: ***")
+    }
+}
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct AwsKmsConfig {
+    /// Key id, ARN or alias of the KMS key
+    #[envconfig(from = "AWS_KMS_KEY_ID", default = "")]
+    pub key_id: String,
+    #[envconfig(from = "AWS_REGION", default = "us-east-1")]
+    pub region: String,
+    #[envconfig(from = "AWS_ACCESS_KEY_ID", default = "")]
+    pub access_key_id: String,
+    #[envconfig(from = "AWS_SECRET_ACCESS_KEY", default = "")]
+    pub secret_access_key: Redacted<String>,
+    #[envconfig(from = "AWS_SESSION_TOKEN", default = "")]
+    pub session_token: String,
+    /// Overrides the regional endpoint, e.g. for a VPC endpoint or a local emulator
+    #[envconfig(from = "AWS_KMS_ENDPOINT", default = "")]
+    pub endpoint: String,
+}
+
+impl AwsKmsConfig {
+    pub fn session_token(&self) -> Option<&str> {
+        non_empty(&self.session_token)
+    }
+
+    pub fn endpoint(&self) -> String {
+        non_empty(&self.endpoint)
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("https://kms.{}.amazonaws.com", self.region))
+    }
+}
+
+impl Display for AwsKmsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "AWS_KMS_KEY_ID: {}", self.key_id)?;
+        writeln!(f, "AWS_REGION: {}", self.region)?;
+        writeln!(f, "AWS_ACCESS_KEY_ID: {}", self.access_key_id)?;
+        writeln!(f, "AWS_SECRET_ACCESS_KEY: ***")?;
+        writeln!(f, "AWS_SESSION_TOKEN: ***")?;
+        writeln!(f, "AWS_KMS_ENDPOINT: {}", self.endpoint())
+    }
+}
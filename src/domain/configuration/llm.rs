@@ -0,0 +1,119 @@
+use super::{claude::ClaudeConfig, openai::OpenAiConfig};
+use envconfig::Envconfig;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use strum::{Display as StrumDisplay, EnumString};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, EnumString, StrumDisplay,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum LlmProvider {
+    #[strum(serialize = "openai", serialize = "open-ai")]
+    OpenAi,
+    #[strum(serialize = "anthropic", serialize = "claude")]
+    Anthropic,
+}
+
+impl LlmProvider {
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            LlmProvider::OpenAi => "gpt-4o",
+            LlmProvider::Anthropic => "claude-3-5-sonnet-20240620",
+        }
+    }
+
+    /// `None` for providers without an embeddings API
+    pub fn default_embedding_model(&self) -> Option<&'static str> {
+        match self {
+            LlmProvider::OpenAi => Some("text-embedding-3-small"),
+            LlmProvider::Anthropic => None,
+        }
+    }
+}
+
+/// Monthly token allowances of LLM calls, input and output tokens together. `None`
+/// means unlimited.
+#[derive(Envconfig, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmBudget {
+    /// Per ownership, across its connections
+    #[envconfig(from = "LLM_MONTHLY_TOKEN_BUDGET")]
+    pub monthly_tokens: Option<u64>,
+    /// Per connection
+    #[envconfig(from = "LLM_CONNECTION_MONTHLY_TOKEN_BUDGET")]
+    pub connection_monthly_tokens: Option<u64>,
+}
+
+impl Display for LlmBudget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let limit = |tokens: Option<u64>| tokens.map_or("unlimited".to_owned(), |t| t.to_string());
+        writeln!(
+            f,
+            "LLM_MONTHLY_TOKEN_BUDGET: {}",
+            limit(self.monthly_tokens)
+        )?;
+        writeln!(
+            f,
+            "LLM_CONNECTION_MONTHLY_TOKEN_BUDGET: {}",
+            limit(self.connection_monthly_tokens)
+        )
+    }
+}
+
+/// Language model the services call, chosen by `LLM_PROVIDER` so models can be swapped
+/// without code changes
+#[derive(Envconfig, Debug, Clone)]
+pub struct LlmConfig {
+    #[envconfig(from = "LLM_PROVIDER", default = "openai")]
+    pub provider: LlmProvider,
+    /// Model used for chat, the provider's default when empty
+    #[envconfig(from = "LLM_MODEL", default = "")]
+    pub model: String,
+    /// Model used for embeddings, the provider's default when empty
+    #[envconfig(from = "LLM_EMBEDDING_MODEL", default = "")]
+    pub embedding_model: String,
+    /// Tokens generated per response when the request doesn't say
+    #[envconfig(from = "LLM_MAX_TOKENS", default = "4096")]
+    pub max_tokens: u32,
+    #[envconfig(from = "LLM_TIMEOUT_SECS", default = "120")]
+    pub timeout_secs: u64,
+    #[envconfig(nested = true)]
+    pub openai: OpenAiConfig,
+    #[envconfig(nested = true)]
+    pub claude: ClaudeConfig,
+    #[envconfig(nested = true)]
+    pub budget: LlmBudget,
+}
+
+impl LlmConfig {
+    pub fn model(&self) -> &str {
+        Some(self.model.as_str())
+            .filter(|model| !model.is_empty())
+            .unwrap_or_else(|| self.provider.default_model())
+    }
+
+    pub fn embedding_model(&self) -> Option<&str> {
+        Some(self.embedding_model.as_str())
+            .filter(|model| !model.is_empty())
+            .or_else(|| self.provider.default_embedding_model())
+    }
+}
+
+impl Display for LlmConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "LLM_PROVIDER: {}", self.provider)?;
+        writeln!(f, "LLM_MODEL: {}", self.model())?;
+        writeln!(
+            f,
+            "LLM_EMBEDDING_MODEL: {}",
+            self.embedding_model().unwrap_or_default()
+        )?;
+        writeln!(f, "LLM_MAX_TOKENS: {}", self.max_tokens)?;
+        writeln!(f, "LLM_TIMEOUT_SECS: {}", self.timeout_secs)?;
+        write!(f, "{}", self.openai)?;
+        write!(f, "{}", self.claude)?;
+        write!(f, "{}", self.budget)
+    }
+}
@@ -0,0 +1,35 @@
+use envconfig::Envconfig;
+use std::fmt::{Display, Formatter};
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct KafkaConfig {
+    /// Comma separated `host:port` list
+    #[envconfig(from = "KAFKA_BROKERS", default = "localhost:9092")]
+    pub brokers: String,
+    /// Consumer group subscribers join, so each delivery is handled by one of them
+    #[envconfig(from = "KAFKA_GROUP_ID", default = "integrationos")]
+    pub group_id: String,
+}
+
+impl Display for KafkaConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "KAFKA_BROKERS: {}", self.brokers)?;
+        writeln!(f, "KAFKA_GROUP_ID: {}", self.group_id)
+    }
+}
+
+#[derive(Envconfig, Debug, Clone)]
+pub struct NatsConfig {
+    #[envconfig(from = "NATS_URL", default = "nats://localhost:4222")]
+    pub url: String,
+    /// JetStream stream capturing the subjects published to
+    #[envconfig(from = "NATS_STREAM", default = "integrationos")]
+    pub stream: String,
+}
+
+impl Display for NatsConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "NATS_URL: {}", self.url)?;
+        writeln!(f, "NATS_STREAM: {}", self.stream)
+    }
+}
@@ -1,7 +1,31 @@
+#[cfg(feature = "backend")]
+pub mod archive;
+#[cfg(feature = "backend")]
 pub mod cache;
+#[cfg(feature = "backend")]
+pub mod claude;
+#[cfg(feature = "backend")]
 pub mod database;
+#[cfg(feature = "backend")]
+pub mod egress;
+#[cfg(feature = "backend")]
+pub mod encrypted;
 pub mod environment;
+#[cfg(feature = "backend")]
+pub mod kms;
+#[cfg(feature = "backend")]
+pub mod layered;
+#[cfg(feature = "backend")]
+pub mod llm;
+#[cfg(feature = "backend")]
+pub mod message_bus;
+#[cfg(feature = "backend")]
 pub mod openai;
+#[cfg(feature = "backend")]
 pub mod pipeline;
+#[cfg(feature = "backend")]
 pub mod secrets;
+#[cfg(feature = "backend")]
+pub mod validation;
+#[cfg(feature = "backend")]
 pub mod watchdog;
@@ -1,17 +1,29 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::prelude::shared::redacted::Redacted;
 use envconfig::Envconfig;
 use serde::{Deserialize, Serialize};
 
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+fn default_base_url() -> String {
+    OPENAI_BASE_URL.to_owned()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Envconfig)]
 pub struct OpenAiConfig {
     /// The OpenAI API key
     #[envconfig(from = "OPENAI_API_KEY", default = "")]
-    pub api_key: String,
+    pub api_key: Redacted<String>,
+    /// Overrides the API, e.g. for Azure OpenAI or a compatible gateway
+    #[envconfig(from = "OPENAI_BASE_URL", default = "https://api.openai.com/v1")]
+    #[serde(default = "default_base_url")]
+    pub base_url: String,
 }
 
 impl Display for OpenAiConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        writeln!(f, "OPENAI_API_KEY: ***")
+        writeln!(f, "OPENAI_API_KEY: ***")?;
+        writeln!(f, "OPENAI_BASE_URL: {}", self.base_url)
     }
 }
@@ -1,4 +1,4 @@
-use crate::policies::{Policies, RetryPolicy};
+use crate::policies::{BackoffStrategy, ErrorClass, Policies, RetryPolicy};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
@@ -16,6 +16,10 @@ impl Default for PipelineConfig {
                 retry: RetryPolicy {
                     maximum_attempts: 3,
                     initial_interval: "1 second".to_owned(),
+                    backoff: BackoffStrategy::Exponential { multiplier: 2 },
+                    maximum_interval: Some("1 minute".to_owned()),
+                    jitter: true,
+                    retry_on: ErrorClass::transient(),
                 },
             },
             start_to_close_timeout: "10 seconds".to_owned(),
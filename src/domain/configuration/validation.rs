@@ -0,0 +1,283 @@
+use super::{
+    cache::CacheConfig,
+    database::DatabaseConfig,
+    egress::EgressConfig,
+    encrypted::is_encrypted,
+    kms::{AwsKmsConfig, GcpKmsConfig},
+    secrets::SecretsConfig,
+    watchdog::WatchdogConfig,
+};
+use crate::{IntegrationOSError, InternalError, IpNetwork};
+use reqwest::Url;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub key: String,
+    pub message: String,
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+/// Every problem found in a config, so they can all be fixed in one go instead of
+/// surfacing one by one at first use
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn error(&mut self, key: &str, message: impl Into<String>) -> &mut Self {
+        self.issues.push(ConfigIssue {
+            key: key.to_owned(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn merge(&mut self, other: ConfigReport) -> &mut Self {
+        self.issues.extend(other.issues);
+        self
+    }
+
+    pub fn check_present(&mut self, key: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.error(key, "is required");
+        }
+        self
+    }
+
+    /// Checks the value parses as a URL with one of the given schemes and a usable port
+    pub fn check_url(&mut self, key: &str, value: &str, schemes: &[&str]) -> &mut Self {
+        if is_encrypted(value) {
+            return self.error(key, "is still encrypted, decrypt the config first");
+        }
+        match Url::parse(value) {
+            Err(e) => self.error(key, format!("is not a valid URL: {e}")),
+            Ok(url) if !schemes.contains(&url.scheme()) => self.error(
+                key,
+                format!(
+                    "has scheme {}, expected one of {}",
+                    url.scheme(),
+                    schemes.join(", ")
+                ),
+            ),
+            Ok(url) if url.port() == Some(0) => self.error(key, "has port 0"),
+            Ok(_) => self,
+        }
+    }
+
+    pub fn check_range(&mut self, key: &str, value: u64, min: u64, max: u64) -> &mut Self {
+        if value < min || value > max {
+            self.error(key, format!("is {value}, expected {min} to {max}"));
+        }
+        self
+    }
+
+    /// Checks at most one of the options is set
+    pub fn check_exclusive(&mut self, options: &[(&str, bool)]) -> &mut Self {
+        let set: Vec<&str> = options
+            .iter()
+            .filter(|(_, set)| *set)
+            .map(|(key, _)| *key)
+            .collect();
+        if set.len() > 1 {
+            self.error(&set.join(", "), "are mutually exclusive");
+        }
+        self
+    }
+
+    pub fn into_result(self) -> Result<(), IntegrationOSError> {
+        if self.is_ok() {
+            return Ok(());
+        }
+        Err(InternalError::configuration_error(
+            &self.to_string(),
+            Some("config_validation"),
+        ))
+    }
+}
+
+impl Display for ConfigReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let issues: Vec<String> = self.issues.iter().map(ToString::to_string).collect();
+        write!(f, "{}", issues.join("; "))
+    }
+}
+
+pub trait ValidateConfig {
+    fn validate(&self) -> ConfigReport;
+}
+
+const MONGO_SCHEMES: &[&str] = &["mongodb", "mongodb+srv"];
+const REDIS_SCHEMES: &[&str] = &["redis", "rediss"];
+
+impl ValidateConfig for DatabaseConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_url("CONTROL_DATABASE_URL", &self.control_db_url, MONGO_SCHEMES)
+            .check_present("CONTROL_DATABASE_NAME", &self.control_db_name)
+            .check_url("UDM_DATABASE_URL", &self.udm_db_url, MONGO_SCHEMES)
+            .check_present("UDM_DATABASE_NAME", &self.udm_db_name)
+            .check_url("EVENT_DATABASE_URL", &self.event_db_url, MONGO_SCHEMES)
+            .check_present("EVENT_DATABASE_NAME", &self.event_db_name)
+            .check_url("CONTEXT_DATABASE_URL", &self.context_db_url, MONGO_SCHEMES)
+            .check_present("CONTEXT_DATABASE_NAME", &self.context_db_name)
+            .check_present("CONTEXT_COLLECTION_NAME", &self.context_collection_name);
+        report
+    }
+}
+
+impl ValidateConfig for CacheConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_url("REDIS_URL", &self.url, REDIS_SCHEMES)
+            .check_present("REDIS_QUEUE_NAME", &self.queue_name)
+            .check_present("REDIS_EVENT_THROUGHPUT_KEY", &self.event_throughput_key)
+            .check_present("REDIS_API_THROUGHPUT_KEY", &self.api_throughput_key);
+        report
+    }
+}
+
+impl ValidateConfig for SecretsConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_url(
+                "SECRETS_SERVICE_BASE_URL",
+                &self.base_url,
+                &["http", "https"],
+            )
+            .check_present("SECRETS_SERVICE_GET_PATH", &self.get_path)
+            .check_present("SECRETS_SERVICE_CREATE_PATH", &self.create_path);
+        report
+    }
+}
+
+impl ValidateConfig for EgressConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        for network in self.allowed_networks() {
+            if IpNetwork::parse(network).is_err() {
+                report.error(
+                    "EGRESS_ALLOWED_NETWORKS",
+                    format!("{network} is not a valid CIDR range"),
+                );
+            }
+        }
+        report
+    }
+}
+
+impl ValidateConfig for GcpKmsConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_present("GCP_KMS_KEY_NAME", &self.key_name)
+            .check_url("GCP_KMS_ENDPOINT", &self.endpoint, &["http", "https"]);
+        if self.access_token().is_none() {
+            report.check_url("GCP_KMS_TOKEN_URL", &self.token_url, &["http", "https"]);
+        }
+        report
+    }
+}
+
+impl ValidateConfig for AwsKmsConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_present("AWS_KMS_KEY_ID", &self.key_id)
+            .check_present("AWS_REGION", &self.region)
+            .check_present("AWS_ACCESS_KEY_ID", &self.access_key_id)
+            .check_present(
+                "AWS_SECRET_ACCESS_KEY",
+                self.secret_access_key.expose_secret(),
+            )
+            .check_url("AWS_KMS_ENDPOINT", &self.endpoint(), &["http", "https"]);
+        report
+    }
+}
+
+impl ValidateConfig for WatchdogConfig {
+    fn validate(&self) -> ConfigReport {
+        let mut report = ConfigReport::new();
+        report
+            .check_range("POLL_DURATION", self.poll_duration, 1, 3_600)
+            .check_range("EVENT_TIMEOUT", self.event_timeout, 1, u64::MAX);
+        if self.max_db_ops_per_second > 0 && self.db_ops_burst < self.max_db_ops_per_second {
+            report.error(
+                "DB_OPS_BURST",
+                format!(
+                    "is {}, must be at least MAX_DB_OPS_PER_SECOND ({})",
+                    self.db_ops_burst, self.max_db_ops_per_second
+                ),
+            );
+        }
+        report
+            .merge(self.redis.validate())
+            .merge(self.db.validate());
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_configs_are_valid() {
+        assert!(DatabaseConfig::default().validate().is_ok());
+        assert!(CacheConfig::default().validate().is_ok());
+        assert!(SecretsConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_report_lists_every_issue() {
+        let config = DatabaseConfig {
+            control_db_url: "postgres://localhost".to_owned(),
+            udm_db_url: "not a url".to_owned(),
+            event_db_name: "".to_owned(),
+            context_db_url: "enc:ops/db".to_owned(),
+            ..Default::default()
+        };
+        let report = config.validate();
+        let keys: Vec<&str> = report.issues.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "CONTROL_DATABASE_URL",
+                "UDM_DATABASE_URL",
+                "EVENT_DATABASE_NAME",
+                "CONTEXT_DATABASE_URL"
+            ]
+        );
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn test_checks() {
+        let mut report = ConfigReport::new();
+        report
+            .check_range("PORT", 70_000, 1, 65_535)
+            .check_exclusive(&[("A", true), ("B", false), ("C", true)])
+            .check_exclusive(&[("D", true), ("E", false)]);
+        assert_eq!(
+            report.to_string(),
+            "PORT: is 70000, expected 1 to 65535; A, C: are mutually exclusive"
+        );
+    }
+}
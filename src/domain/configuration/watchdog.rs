@@ -1,6 +1,21 @@
 use crate::{cache::CacheConfig, database::DatabaseConfig};
 use envconfig::Envconfig;
-use std::fmt::{Display, Formatter};
+use std::fmt::{self, Formatter};
+use strum::{AsRefStr, Display, EnumString};
+
+/// What the watchdog does with an event chain it found dead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, AsRefStr, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ReconciliationPolicy {
+    /// Puts the event back onto the queue
+    Republish,
+    /// Marks the chain as dropped
+    Drop,
+    /// Parks the event in the dead letters and marks the chain as dropped
+    Quarantine,
+    /// Publishes a notice onto the notification topic and marks the chain as dropped
+    Notify,
+}
 
 #[derive(Envconfig, Clone)] // Intentionally no Debug so secret is not printed
 pub struct WatchdogConfig {
@@ -8,17 +23,107 @@ pub struct WatchdogConfig {
     pub event_timeout: u64,
     #[envconfig(from = "POLL_DURATION", default = "10")] // 10 seconds
     pub poll_duration: u64,
+    #[envconfig(from = "MAX_KEYS_PER_CYCLE", default = "1000")] // 0 disables the limit
+    pub max_keys_per_cycle: u64,
+    #[envconfig(from = "MAX_DB_OPS_PER_SECOND", default = "100")] // 0 disables the limit
+    pub max_db_ops_per_second: u64,
+    #[envconfig(from = "DB_OPS_BURST", default = "200")]
+    pub db_ops_burst: u64,
+    #[envconfig(from = "CONTEXT_SCAN_WINDOW", default = "86400")] // 1 day, 0 scans everything
+    pub context_scan_window: u64,
+    #[envconfig(from = "CONTEXT_RETENTION", default = "86400")]
+    // raw contexts kept 1 day once summarized
+    pub context_retention: u64,
+    #[envconfig(from = "CONTEXT_COMPACTION_AGE", default = "3600")]
+    // superseded contexts older than 1 hour are deleted, 0 disables compaction
+    pub context_compaction_age: u64,
+    #[envconfig(from = "RECONCILIATION_POLICY", default = "republish")]
+    pub reconciliation_policy: ReconciliationPolicy,
+    #[envconfig(from = "MAX_REPUBLISH_ATTEMPTS", default = "5")] // 0 disables the limit
+    pub max_republish_attempts: u32,
+    #[envconfig(from = "EXHAUSTED_RECONCILIATION_POLICY", default = "quarantine")]
+    // applied once an event was republished MAX_REPUBLISH_ATTEMPTS times
+    pub exhausted_reconciliation_policy: ReconciliationPolicy,
+    #[envconfig(
+        from = "RECONCILIATION_NOTIFY_TOPIC",
+        default = "watchdog-notifications"
+    )]
+    pub reconciliation_notify_topic: String,
     #[envconfig(nested = true)]
     pub redis: CacheConfig,
     #[envconfig(nested = true)]
     pub db: DatabaseConfig,
 }
 
-impl Display for WatchdogConfig {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl WatchdogConfig {
+    /// Policy for a dead chain that was already republished `attempts` times
+    pub fn reconciliation_for(&self, attempts: u32) -> ReconciliationPolicy {
+        match self.reconciliation_policy {
+            ReconciliationPolicy::Republish
+                if self.max_republish_attempts > 0 && attempts >= self.max_republish_attempts =>
+            {
+                self.exhausted_reconciliation_policy
+            }
+            policy => policy,
+        }
+    }
+}
+
+impl fmt::Display for WatchdogConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         writeln!(f, "POLL_DURATION: {}", self.poll_duration)?;
         writeln!(f, "EVENT_TIMEOUT: {}", self.event_timeout)?;
+        writeln!(f, "MAX_KEYS_PER_CYCLE: {}", self.max_keys_per_cycle)?;
+        writeln!(f, "MAX_DB_OPS_PER_SECOND: {}", self.max_db_ops_per_second)?;
+        writeln!(f, "DB_OPS_BURST: {}", self.db_ops_burst)?;
+        writeln!(f, "CONTEXT_SCAN_WINDOW: {}", self.context_scan_window)?;
+        writeln!(f, "CONTEXT_RETENTION: {}", self.context_retention)?;
+        writeln!(f, "CONTEXT_COMPACTION_AGE: {}", self.context_compaction_age)?;
+        writeln!(f, "RECONCILIATION_POLICY: {}", self.reconciliation_policy)?;
+        writeln!(f, "MAX_REPUBLISH_ATTEMPTS: {}", self.max_republish_attempts)?;
+        writeln!(
+            f,
+            "EXHAUSTED_RECONCILIATION_POLICY: {}",
+            self.exhausted_reconciliation_policy
+        )?;
+        writeln!(
+            f,
+            "RECONCILIATION_NOTIFY_TOPIC: {}",
+            self.reconciliation_notify_topic
+        )?;
         writeln!(f, "{}", self.redis)?;
         writeln!(f, "{}", self.db)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_republish_escalates_once_attempts_are_exhausted() {
+        let mut config = WatchdogConfig::init_from_hashmap(&HashMap::from([(
+            "RECONCILIATION_POLICY".to_owned(),
+            "republish".to_owned(),
+        )]))
+        .unwrap();
+        assert_eq!(
+            config.reconciliation_for(4),
+            ReconciliationPolicy::Republish
+        );
+        assert_eq!(
+            config.reconciliation_for(5),
+            ReconciliationPolicy::Quarantine
+        );
+
+        config.max_republish_attempts = 0;
+        assert_eq!(
+            config.reconciliation_for(100),
+            ReconciliationPolicy::Republish
+        );
+
+        config.reconciliation_policy = "notify".parse().unwrap();
+        assert_eq!(config.reconciliation_for(0), ReconciliationPolicy::Notify);
+    }
+}
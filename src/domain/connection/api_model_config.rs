@@ -1,11 +1,13 @@
-use http::HeaderMap;
 use js_sandbox_ios::Script;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::BTreeMap;
+use std::collections::HashMap;
 
+use super::http_params::{HeaderParams, QueryParams};
 use crate::{prelude::schema::json_schema::JsonSchema, IntegrationOSError, InternalError};
 
+pub use crate::common_model::Lang;
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -13,14 +15,10 @@ pub struct ApiModelConfig {
     pub base_url: String,
     pub path: String,
     pub auth_method: AuthMethod,
-    #[serde(
-        with = "http_serde_ext::header_map::option",
-        skip_serializing_if = "Option::is_none",
-        default
-    )]
-    pub headers: Option<HeaderMap>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub headers: Option<HeaderParams>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub query_params: Option<BTreeMap<String, String>>,
+    pub query_params: Option<QueryParams>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub content: Option<ContentType>,
     pub schemas: SchemasInput,
@@ -28,6 +26,8 @@ pub struct ApiModelConfig {
     pub responses: Vec<ResponseBody>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paths: Option<ModelPaths>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<PaginationConfig>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize, Default)]
@@ -52,6 +52,143 @@ impl ApiModelConfig {
         }
         base_url + &path
     }
+
+    /// Checks the configured headers and query params, so a broken config is rejected
+    /// when it is saved rather than when a request is built from it
+    pub fn validate(&self) -> Result<(), IntegrationOSError> {
+        if let Some(headers) = &self.headers {
+            headers.validate()?;
+        }
+        if let Some(query_params) = &self.query_params {
+            query_params.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// How a list endpoint pages through its results. Items are selected from each page with
+/// the response `object` path of the config's [`ModelPaths`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum PaginationConfig {
+    /// The response carries the cursor of the next page at `cursor_path` (JSONPath),
+    /// sent back in the `param` query param
+    #[serde(rename_all = "camelCase")]
+    Cursor { param: String, cursor_path: String },
+    /// Pages are numbered from `first_page`, until one comes back empty
+    #[serde(rename_all = "camelCase")]
+    PageNumber {
+        param: String,
+        #[serde(default = "default_first_page")]
+        first_page: u64,
+    },
+    /// Items are skipped by `offset_param`, until a page has less than `limit` items
+    #[serde(rename_all = "camelCase")]
+    Offset {
+        offset_param: String,
+        limit_param: String,
+        limit: u64,
+    },
+    /// The URL of the next page is the `rel="next"` entry of the `Link` header (RFC 8288)
+    LinkHeader,
+}
+
+fn default_first_page() -> u64 {
+    1
+}
+
+/// Where the next page of a list is
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NextPage {
+    /// Same request with these query params overriding the current ones
+    Query(Vec<(String, String)>),
+    /// Absolute URL, query included
+    Url(String),
+}
+
+impl PaginationConfig {
+    /// Query params of the first page request
+    pub fn first_page(&self) -> Vec<(String, String)> {
+        match self {
+            PaginationConfig::PageNumber { param, first_page } => {
+                vec![(param.clone(), first_page.to_string())]
+            }
+            PaginationConfig::Offset {
+                offset_param,
+                limit_param,
+                limit,
+            } => vec![
+                (offset_param.clone(), "0".to_owned()),
+                (limit_param.clone(), limit.to_string()),
+            ],
+            PaginationConfig::Cursor { .. } | PaginationConfig::LinkHeader => vec![],
+        }
+    }
+
+    /// The page after the one requested with `query` that returned `body`, `headers`
+    /// and `item_count` items, or `None` when the list is exhausted
+    pub fn next_page(
+        &self,
+        query: &HashMap<String, String>,
+        body: &Value,
+        headers: &http::HeaderMap,
+        item_count: usize,
+    ) -> Option<NextPage> {
+        let current = |param: &str, default: u64| {
+            query
+                .get(param)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        match self {
+            PaginationConfig::Cursor { param, cursor_path } => {
+                let cursor = jsonpath_lib::select(body, cursor_path)
+                    .ok()?
+                    .into_iter()
+                    .next()
+                    .and_then(|cursor| match cursor {
+                        Value::String(cursor) if !cursor.is_empty() => Some(cursor.clone()),
+                        Value::Number(cursor) => Some(cursor.to_string()),
+                        _ => None,
+                    })?;
+                Some(NextPage::Query(vec![(param.clone(), cursor)]))
+            }
+            PaginationConfig::PageNumber { param, first_page } => (item_count > 0).then(|| {
+                let page = current(param, *first_page) + 1;
+                NextPage::Query(vec![(param.clone(), page.to_string())])
+            }),
+            PaginationConfig::Offset {
+                offset_param,
+                limit_param,
+                limit,
+            } => (item_count as u64 >= *limit && item_count > 0).then(|| {
+                let offset = current(offset_param, 0) + item_count as u64;
+                NextPage::Query(vec![
+                    (offset_param.clone(), offset.to_string()),
+                    (limit_param.clone(), limit.to_string()),
+                ])
+            }),
+            PaginationConfig::LinkHeader => headers
+                .get_all(http::header::LINK)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .find_map(|link| {
+                    let (url, params) = link.trim().split_once(';')?;
+                    params
+                        .split(';')
+                        .any(|param| {
+                            matches!(
+                                param.trim().split_once('='),
+                                Some(("rel", rel)) if rel.trim_matches('"').split_whitespace().any(|rel| rel == "next")
+                            )
+                        })
+                        .then(|| url.trim().trim_start_matches('<').trim_end_matches('>'))
+                        .map(|url| NextPage::Url(url.to_owned()))
+                }),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -77,7 +214,7 @@ pub struct ResponseModelPaths {
     pub cursor: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
 pub struct SamplesInput {
@@ -93,7 +230,7 @@ pub struct SamplesInput {
     pub body: Option<Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
 pub struct SchemasInput {
@@ -168,13 +305,3 @@ pub struct Compute {
     pub function: String,
     pub language: Lang,
 }
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Default)]
-#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
-#[serde(rename_all = "lowercase")]
-pub enum Lang {
-    #[default]
-    JavaScript,
-    TypeScript,
-    Rust,
-}
@@ -0,0 +1,146 @@
+use super::{Connection, OAuth};
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::record_metadata::RecordMetadata,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// How connections of a platform move from one API version to the next
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiVersionMigration {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub connection_platform: String,
+    pub from_version: String,
+    pub to_version: String,
+    /// Secret fields the new version expects under another name
+    #[serde(default)]
+    pub field_renames: Vec<FieldRename>,
+    /// OAuth scopes the new version needs, which existing tokens were not granted
+    #[serde(default)]
+    pub required_scopes: Vec<String>,
+    /// Every connection has to be authorized again, e.g. for a new OAuth app
+    #[serde(default)]
+    pub requires_reauth: bool,
+    #[serde(default)]
+    pub progress: MigrationProgress,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldRename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationFailure {
+    pub connection_id: Id,
+    pub error: String,
+}
+
+/// Progress of a fleet-wide migration, handed to the progress callback after every
+/// connection and stored on the migration once it ran
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationProgress {
+    pub total: usize,
+    pub migrated: usize,
+    /// Connections left on the old version until their owner authorizes them again
+    #[serde(default)]
+    pub pending_reauth: Vec<Id>,
+    #[serde(default)]
+    pub failures: Vec<MigrationFailure>,
+}
+
+impl MigrationProgress {
+    pub fn processed(&self) -> usize {
+        self.migrated + self.pending_reauth.len() + self.failures.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.processed() == self.total
+    }
+}
+
+impl ApiVersionMigration {
+    pub fn new(connection_platform: String, from_version: String, to_version: String) -> Self {
+        Self {
+            id: Id::now(IdPrefix::ApiVersionMigration),
+            connection_platform,
+            from_version,
+            to_version,
+            field_renames: vec![],
+            required_scopes: vec![],
+            requires_reauth: false,
+            progress: MigrationProgress::default(),
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+
+    pub fn applies_to(&self, connection: &Connection) -> bool {
+        *connection.platform == *self.connection_platform
+            && connection.platform_version == self.from_version
+    }
+
+    /// Whether the connection cannot be migrated without its owner authorizing it again.
+    /// OAuth tokens never carry scopes added by the new version.
+    pub fn needs_reauth(&self, connection: &Connection) -> bool {
+        self.requires_reauth
+            || (!self.required_scopes.is_empty()
+                && matches!(connection.oauth, Some(OAuth::Enabled { .. })))
+    }
+
+    /// Renames the top level fields of a connection secret, returning whether any was
+    /// renamed. Fields already present under their new name are kept.
+    pub fn rename_fields(&self, secret: &mut Value) -> bool {
+        let Some(fields) = secret.as_object_mut() else {
+            return false;
+        };
+        let mut renamed = false;
+        for rename in &self.field_renames {
+            if fields.contains_key(&rename.to) {
+                continue;
+            }
+            if let Some(value) = fields.remove(&rename.from) {
+                fields.insert(rename.to.clone(), value);
+                renamed = true;
+            }
+        }
+        renamed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rename_fields() {
+        let mut migration =
+            ApiVersionMigration::new("hubspot".to_owned(), "v1".to_owned(), "v3".to_owned());
+        migration.field_renames = vec![
+            FieldRename {
+                from: "api_key".to_owned(),
+                to: "HUBSPOT_API_KEY".to_owned(),
+            },
+            FieldRename {
+                from: "portal".to_owned(),
+                to: "PORTAL_ID".to_owned(),
+            },
+        ];
+
+        let mut secret = json!({ "api_key": "k", "portal": "1", "PORTAL_ID": "2" });
+        assert!(migration.rename_fields(&mut secret));
+        assert_eq!(
+            secret,
+            json!({ "HUBSPOT_API_KEY": "k", "portal": "1", "PORTAL_ID": "2" })
+        );
+        assert!(!migration.rename_fields(&mut secret));
+    }
+}
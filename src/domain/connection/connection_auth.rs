@@ -0,0 +1,196 @@
+use super::OAuth;
+use crate::{prelude::oauth_secret::OAuthSecret, IntegrationOSError, InternalError};
+use base64ct::{Base64, Encoding};
+use http::{header::AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const API_KEY_SECRET: &str = "API_KEY";
+pub const BEARER_TOKEN_SECRET: &str = "BEARER_TOKEN";
+pub const USERNAME_SECRET: &str = "USERNAME";
+pub const PASSWORD_SECRET: &str = "PASSWORD";
+
+/// How requests to the platform of a connection are authenticated. The credentials
+/// themselves live in the connection's secret, under the keys named on each variant.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ConnectionAuth {
+    /// Access token of the connection's OAuth secret, see [`OAuth`]
+    #[serde(rename = "oauth")]
+    OAuth,
+    /// `API_KEY` sent in `header`, after `prefix` if any
+    #[serde(rename_all = "camelCase")]
+    ApiKey {
+        header: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// `USERNAME` and `PASSWORD`
+    BasicAuth,
+    /// `BEARER_TOKEN`
+    BearerToken,
+    /// The secret value under `secret` sent as is in `header`
+    #[serde(rename_all = "camelCase")]
+    CustomHeader { header: String, secret: String },
+    #[default]
+    None,
+}
+
+fn secret_value<'a>(secret: &'a Value, key: &str) -> Result<&'a str, IntegrationOSError> {
+    secret.get(key).and_then(Value::as_str).ok_or_else(|| {
+        InternalError::key_not_found(
+            &format!("{key} in connection secret"),
+            Some("connection_auth"),
+        )
+    })
+}
+
+fn header_value(value: String) -> Result<HeaderValue, IntegrationOSError> {
+    let mut value = HeaderValue::try_from(value)
+        .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("connection_auth")))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+fn header_name(name: &str) -> Result<HeaderName, IntegrationOSError> {
+    HeaderName::try_from(name)
+        .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("connection_auth")))
+}
+
+impl ConnectionAuth {
+    /// Auth method of connections created before it was stored, which only recorded
+    /// whether they use OAuth
+    pub fn from_oauth(oauth: Option<&OAuth>) -> Self {
+        match oauth {
+            Some(OAuth::Enabled { .. }) => ConnectionAuth::OAuth,
+            _ => ConnectionAuth::None,
+        }
+    }
+
+    /// Header authenticating an outbound request with the decrypted `secret`
+    pub fn header(
+        &self,
+        secret: &Value,
+    ) -> Result<Option<(HeaderName, HeaderValue)>, IntegrationOSError> {
+        let (name, value) = match self {
+            ConnectionAuth::OAuth => {
+                let secret: OAuthSecret = serde_json::from_value(secret.clone()).map_err(|e| {
+                    InternalError::invalid_argument(&e.to_string(), Some("oauth_secret"))
+                })?;
+                let token_type = secret.token_type.as_deref().unwrap_or("Bearer");
+                (
+                    AUTHORIZATION,
+                    format!("{token_type} {}", secret.access_token.expose_secret()),
+                )
+            }
+            ConnectionAuth::ApiKey { header, prefix } => {
+                let key = secret_value(secret, API_KEY_SECRET)?;
+                let value = match prefix {
+                    Some(prefix) => format!("{prefix} {key}"),
+                    None => key.to_owned(),
+                };
+                (header_name(header)?, value)
+            }
+            ConnectionAuth::BasicAuth => {
+                let credentials = format!(
+                    "{}:{}",
+                    secret_value(secret, USERNAME_SECRET)?,
+                    secret_value(secret, PASSWORD_SECRET)?
+                );
+                (
+                    AUTHORIZATION,
+                    format!("Basic {}", Base64::encode_string(credentials.as_bytes())),
+                )
+            }
+            ConnectionAuth::BearerToken => (
+                AUTHORIZATION,
+                format!("Bearer {}", secret_value(secret, BEARER_TOKEN_SECRET)?),
+            ),
+            ConnectionAuth::CustomHeader {
+                header,
+                secret: key,
+            } => (header_name(header)?, secret_value(secret, key)?.to_owned()),
+            ConnectionAuth::None => return Ok(None),
+        };
+        Ok(Some((name, header_value(value)?)))
+    }
+
+    /// Adds the auth header to `headers`, replacing any value already set
+    pub fn apply(&self, headers: &mut HeaderMap, secret: &Value) -> Result<(), IntegrationOSError> {
+        if let Some((name, value)) = self.header(secret)? {
+            headers.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_auth_headers() {
+        let secret = json!({
+            "API_KEY": "key_123",
+            "USERNAME": "user",
+            "PASSWORD": "pass",
+            "BEARER_TOKEN": "token",
+            "X_TENANT": "acme",
+        });
+        let mut headers = HeaderMap::new();
+
+        ConnectionAuth::ApiKey {
+            header: "X-Api-Key".to_owned(),
+            prefix: Some("Token".to_owned()),
+        }
+        .apply(&mut headers, &secret)
+        .unwrap();
+        assert_eq!(headers["X-Api-Key"], "Token key_123");
+
+        ConnectionAuth::BasicAuth
+            .apply(&mut headers, &secret)
+            .unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Basic dXNlcjpwYXNz");
+        assert!(headers[AUTHORIZATION].is_sensitive());
+
+        ConnectionAuth::BearerToken
+            .apply(&mut headers, &secret)
+            .unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer token");
+
+        ConnectionAuth::CustomHeader {
+            header: "X-Tenant".to_owned(),
+            secret: "X_TENANT".to_owned(),
+        }
+        .apply(&mut headers, &secret)
+        .unwrap();
+        assert_eq!(headers["X-Tenant"], "acme");
+
+        assert!(ConnectionAuth::None.header(&secret).unwrap().is_none());
+        assert!(ConnectionAuth::OAuth.header(&secret).is_err());
+        assert!(ConnectionAuth::BasicAuth.header(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_auth_method_serde() {
+        let method: ConnectionAuth =
+            serde_json::from_value(json!({ "type": "apiKey", "header": "X-Api-Key" })).unwrap();
+        assert_eq!(
+            method,
+            ConnectionAuth::ApiKey {
+                header: "X-Api-Key".to_owned(),
+                prefix: None
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(ConnectionAuth::BasicAuth).unwrap(),
+            json!({ "type": "basicAuth" })
+        );
+        assert_eq!(
+            serde_json::to_value(ConnectionAuth::OAuth).unwrap(),
+            json!({ "type": "oauth" })
+        );
+        assert_eq!(ConnectionAuth::from_oauth(None), ConnectionAuth::None);
+    }
+}
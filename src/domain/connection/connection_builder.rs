@@ -0,0 +1,424 @@
+use super::{
+    api_model_config::{ApiModelConfig, AuthMethod, SamplesInput, SchemasInput},
+    connection_auth::ConnectionAuth,
+    connection_model_definition::{
+        ConnectionModelDefinition, CrudAction, CrudMapping, ExtractorConfig, PlatformInfo,
+        TestConnection,
+    },
+    connection_status::ConnectionStatus,
+    Connection, ConnectionType, OAuth, Throughput,
+};
+use crate::{
+    id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator},
+    prelude::{
+        configuration::environment::Environment,
+        shared::{ownership::Ownership, record_metadata::RecordMetadata, settings::Settings},
+    },
+};
+use std::sync::Arc;
+
+/// Default number of events per throughput window of a new connection
+pub const DEFAULT_THROUGHPUT_LIMIT: u64 = 100;
+
+/// Builds a [`Connection`] from its platform and definition, every other field having a
+/// default: a test environment, default settings, a throughput keyed by the owner and
+/// ids and group from the [`IdGenerator`] given to [`ConnectionBuilder::build_with`].
+#[derive(Debug, Clone)]
+pub struct ConnectionBuilder {
+    platform: Arc<str>,
+    connection_definition_id: Id,
+    id: Option<Id>,
+    platform_version: String,
+    r#type: ConnectionType,
+    name: Option<String>,
+    key: Option<Arc<str>>,
+    group: Option<String>,
+    environment: Environment,
+    secrets_service_id: String,
+    event_access_id: Option<Id>,
+    access_key: String,
+    settings: Settings,
+    throughput_limit: u64,
+    ownership: Ownership,
+    oauth: Option<OAuth>,
+    auth: Option<ConnectionAuth>,
+}
+
+impl Connection {
+    pub fn builder(
+        platform: impl Into<Arc<str>>,
+        connection_definition_id: Id,
+    ) -> ConnectionBuilder {
+        ConnectionBuilder::new(platform, connection_definition_id)
+    }
+}
+
+impl ConnectionBuilder {
+    pub fn new(platform: impl Into<Arc<str>>, connection_definition_id: Id) -> Self {
+        Self {
+            platform: platform.into(),
+            connection_definition_id,
+            id: None,
+            platform_version: "1.0.0".to_owned(),
+            r#type: ConnectionType::Api {},
+            name: None,
+            key: None,
+            group: None,
+            environment: Environment::Test,
+            secrets_service_id: String::new(),
+            event_access_id: None,
+            access_key: String::new(),
+            settings: Settings::default(),
+            throughput_limit: DEFAULT_THROUGHPUT_LIMIT,
+            ownership: Ownership::default(),
+            oauth: None,
+            auth: None,
+        }
+    }
+
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn platform_version(mut self, platform_version: impl Into<String>) -> Self {
+        self.platform_version = platform_version.into();
+        self
+    }
+
+    pub fn r#type(mut self, r#type: ConnectionType) -> Self {
+        self.r#type = r#type;
+        self
+    }
+
+    /// Defaults to the platform
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Defaults to `{environment}::{platform}::{group}`
+    pub fn key(mut self, key: impl Into<Arc<str>>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn secrets_service_id(mut self, secrets_service_id: impl Into<String>) -> Self {
+        self.secrets_service_id = secrets_service_id.into();
+        self
+    }
+
+    pub fn event_access_id(mut self, event_access_id: Id) -> Self {
+        self.event_access_id = Some(event_access_id);
+        self
+    }
+
+    pub fn access_key(mut self, access_key: impl Into<String>) -> Self {
+        self.access_key = access_key.into();
+        self
+    }
+
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn throughput_limit(mut self, limit: u64) -> Self {
+        self.throughput_limit = limit;
+        self
+    }
+
+    pub fn ownership(mut self, ownership: Ownership) -> Self {
+        self.ownership = ownership;
+        self
+    }
+
+    pub fn oauth(mut self, oauth: OAuth) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    pub fn auth(mut self, auth: ConnectionAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    pub fn build(self) -> Connection {
+        self.build_with(&SystemIdGenerator)
+    }
+
+    pub fn build_with(self, generator: &dyn IdGenerator) -> Connection {
+        let id = self
+            .id
+            .unwrap_or_else(|| generator.id(IdPrefix::Connection));
+        let group = self
+            .group
+            .unwrap_or_else(|| generator.uuid().simple().to_string());
+        let key = self
+            .key
+            .unwrap_or_else(|| format!("{}::{}::{group}", self.environment, self.platform).into());
+
+        Connection {
+            id,
+            platform_version: self.platform_version,
+            connection_definition_id: self.connection_definition_id,
+            r#type: self.r#type,
+            name: self.name.unwrap_or_else(|| self.platform.to_string()),
+            key,
+            group,
+            environment: self.environment,
+            secrets_service_id: self.secrets_service_id,
+            event_access_id: self
+                .event_access_id
+                .unwrap_or_else(|| generator.id_at(IdPrefix::EventAccess, id.time())),
+            access_key: self.access_key.into(),
+            settings: self.settings,
+            throughput: Throughput {
+                key: self.ownership.id.to_string(),
+                limit: self.throughput_limit,
+            },
+            ownership: self.ownership,
+            platform: self.platform,
+            oauth: self.oauth,
+            auth: self.auth,
+            status: ConnectionStatus::Active,
+            status_change: None,
+            record_metadata: RecordMetadata::created_at(id.time()),
+        }
+    }
+}
+
+/// Builds a [`ConnectionModelDefinition`] of an API platform, defaulting to an untested
+/// `GET` without auth, schemas or samples
+#[derive(Debug, Clone)]
+pub struct ConnectionModelDefinitionBuilder {
+    connection_definition_id: Id,
+    connection_platform: String,
+    platform_version: String,
+    model_name: String,
+    action_name: CrudAction,
+    id: Option<Id>,
+    key: Option<String>,
+    title: Option<String>,
+    name: Option<String>,
+    action: http::Method,
+    config: ApiModelConfig,
+    extractor_config: Option<ExtractorConfig>,
+    test_connection_status: TestConnection,
+    is_default_crud_mapping: Option<bool>,
+    mapping: Option<CrudMapping>,
+}
+
+impl ConnectionModelDefinition {
+    pub fn builder(
+        connection_definition_id: Id,
+        connection_platform: impl Into<String>,
+        platform_version: impl Into<String>,
+        model_name: impl Into<String>,
+        action_name: CrudAction,
+    ) -> ConnectionModelDefinitionBuilder {
+        ConnectionModelDefinitionBuilder::new(
+            connection_definition_id,
+            connection_platform,
+            platform_version,
+            model_name,
+            action_name,
+        )
+    }
+}
+
+impl ConnectionModelDefinitionBuilder {
+    pub fn new(
+        connection_definition_id: Id,
+        connection_platform: impl Into<String>,
+        platform_version: impl Into<String>,
+        model_name: impl Into<String>,
+        action_name: CrudAction,
+    ) -> Self {
+        Self {
+            connection_definition_id,
+            connection_platform: connection_platform.into(),
+            platform_version: platform_version.into(),
+            model_name: model_name.into(),
+            action_name,
+            id: None,
+            key: None,
+            title: None,
+            name: None,
+            action: http::Method::GET,
+            config: ApiModelConfig {
+                base_url: String::new(),
+                path: String::new(),
+                auth_method: AuthMethod::None,
+                headers: None,
+                query_params: None,
+                content: None,
+                schemas: SchemasInput::default(),
+                samples: SamplesInput::default(),
+                responses: vec![],
+                paths: None,
+                pagination: None,
+            },
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            is_default_crud_mapping: None,
+            mapping: None,
+        }
+    }
+
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Defaults to `api::{platform}::{version}::{model}::{action}`
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Defaults to the action and model, e.g. `getMany Order`
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Defaults to the title
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn action(mut self, action: http::Method) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn endpoint(mut self, base_url: impl Into<String>, path: impl Into<String>) -> Self {
+        self.config.base_url = base_url.into();
+        self.config.path = path.into();
+        self
+    }
+
+    pub fn auth_method(mut self, auth_method: AuthMethod) -> Self {
+        self.config.auth_method = auth_method;
+        self
+    }
+
+    /// Replaces the whole API configuration, including the endpoint and auth method
+    pub fn api_config(mut self, config: ApiModelConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn extractor_config(mut self, extractor_config: ExtractorConfig) -> Self {
+        self.extractor_config = Some(extractor_config);
+        self
+    }
+
+    pub fn test_connection_status(mut self, status: TestConnection) -> Self {
+        self.test_connection_status = status;
+        self
+    }
+
+    pub fn mapping(mut self, mapping: CrudMapping, is_default: bool) -> Self {
+        self.mapping = Some(mapping);
+        self.is_default_crud_mapping = Some(is_default);
+        self
+    }
+
+    pub fn build(self) -> ConnectionModelDefinition {
+        self.build_with(&SystemIdGenerator)
+    }
+
+    pub fn build_with(self, generator: &dyn IdGenerator) -> ConnectionModelDefinition {
+        let id = self
+            .id
+            .unwrap_or_else(|| generator.id(IdPrefix::ConnectionModelDefinition));
+        let key = self.key.unwrap_or_else(|| {
+            format!(
+                "api::{}::{}::{}::{}",
+                self.connection_platform, self.platform_version, self.model_name, self.action_name
+            )
+            .to_lowercase()
+        });
+        let title = self
+            .title
+            .unwrap_or_else(|| format!("{} {}", self.action_name, self.model_name));
+
+        ConnectionModelDefinition {
+            id,
+            connection_platform: self.connection_platform,
+            connection_definition_id: self.connection_definition_id,
+            platform_version: self.platform_version,
+            key,
+            name: self.name.unwrap_or_else(|| title.clone()),
+            title,
+            model_name: self.model_name,
+            action: self.action,
+            action_name: self.action_name,
+            platform_info: PlatformInfo::Api(self.config),
+            extractor_config: self.extractor_config,
+            test_connection_status: self.test_connection_status,
+            is_default_crud_mapping: self.is_default_crud_mapping,
+            mapping: self.mapping,
+            record_metadata: RecordMetadata::created_at(id.time()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::id::DeterministicIdGenerator;
+
+    #[test]
+    fn test_builders_fill_defaults() {
+        let definition_id = Id::now(IdPrefix::ConnectionDefinition);
+        let ownership = Ownership::new("owner".to_owned());
+        let connection = Connection::builder("stripe", definition_id)
+            .environment(Environment::Live)
+            .group("acme")
+            .ownership(ownership.clone())
+            .build();
+        assert_eq!(connection.key.as_ref(), "live::stripe::acme");
+        assert_eq!(connection.name, "stripe");
+        assert_eq!(connection.throughput.key, "owner");
+        assert_eq!(connection.throughput.limit, DEFAULT_THROUGHPUT_LIMIT);
+        assert_eq!(connection.ownership, ownership);
+        assert_eq!(connection.event_access_id.prefix(), IdPrefix::EventAccess);
+
+        let build = || {
+            Connection::builder("stripe", definition_id)
+                .build_with(&DeterministicIdGenerator::new(1))
+        };
+        assert_eq!(build().id, build().id);
+        assert_eq!(build().group, build().group);
+
+        let model = ConnectionModelDefinition::builder(
+            definition_id,
+            "shopify",
+            "2024-01",
+            "Orders",
+            CrudAction::GetMany,
+        )
+        .endpoint("https://shop.myshopify.com", "/orders.json")
+        .build();
+        assert_eq!(model.key, "api::shopify::2024-01::orders::getmany");
+        assert_eq!(model.title, "getMany Orders");
+        assert_eq!(model.name, model.title);
+        assert_eq!(model.action, http::Method::GET);
+        let PlatformInfo::Api(config) = &model.platform_info;
+        assert_eq!(config.uri(), "https://shop.myshopify.com/orders.json");
+    }
+}
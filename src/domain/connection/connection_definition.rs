@@ -1,7 +1,15 @@
-use super::{api_model_config::AuthMethod, ConnectionType};
-use crate::id::{prefix::IdPrefix, Id};
-use crate::prelude::shared::{record_metadata::RecordMetadata, settings::Settings};
+use super::{
+    api_model_config::{ApiModelConfig, AuthMethod},
+    connection_oauth_definition::OAuthApiConfig,
+    ConnectionType,
+};
+use crate::id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator};
+use crate::prelude::configuration::environment::Environment;
+use crate::prelude::shared::{
+    record_metadata::RecordMetadata, settings::Settings, unknown_variant,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use strum::{self, AsRefStr, Display};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -25,6 +33,8 @@ pub struct ConnectionDefinition {
     pub settings: Settings,
     pub hidden: bool,
     pub test_connection: Option<Id>,
+    #[serde(default, skip_serializing_if = "EnvironmentEndpoints::is_empty")]
+    pub endpoints: EnvironmentEndpoints,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -52,6 +62,9 @@ pub enum ConnectionStatus {
     Beta,
     Alpha,
     GenerallyAvailable,
+    /// Status set by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -71,11 +84,35 @@ impl ConnectionDefinition {
         category: String,
         image: String,
         tags: Vec<String>,
+    ) -> Self {
+        Self::new_with_generator(
+            name,
+            description,
+            platform,
+            platform_version,
+            category,
+            image,
+            tags,
+            &SystemIdGenerator,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_generator(
+        name: String,
+        description: String,
+        platform: String,
+        platform_version: String,
+        category: String,
+        image: String,
+        tags: Vec<String>,
+        generator: &dyn IdGenerator,
     ) -> Self {
         let key = format!("api::{}::{}", platform, platform_version);
+        let id = generator.id(IdPrefix::ConnectionDefinition);
 
         Self {
-            id: Id::now(IdPrefix::ConnectionDefinition),
+            id,
             platform_version,
             platform: platform.clone(),
             r#type: ConnectionDefinitionType::Api,
@@ -99,6 +136,7 @@ impl ConnectionDefinition {
                 },
             },
             test_connection: None,
+            endpoints: EnvironmentEndpoints::default(),
             auth_secrets: vec![],
             auth_method: None,
             paths: Paths {
@@ -115,9 +153,12 @@ impl ConnectionDefinition {
                 show_secret: false,
                 allow_custom_events: false,
                 oauth: false,
+                skip_dedup: false,
+                strict_schema_validation: false,
+                concurrency_limit: None,
             },
             hidden: true,
-            record_metadata: RecordMetadata::default(),
+            record_metadata: RecordMetadata::created_at(id.time()),
         }
     }
 
@@ -136,6 +177,80 @@ impl ConnectionDefinition {
     pub fn set_oauth(&mut self, oauth: bool) {
         self.settings.oauth = oauth;
     }
+
+    /// Points `config` at the host of `environment`, if the definition overrides it
+    pub fn resolve_config(
+        &self,
+        environment: Environment,
+        config: &ApiModelConfig,
+    ) -> ApiModelConfig {
+        let mut config = config.clone();
+        if let Some(base_url) = self
+            .endpoints
+            .for_environment(environment)
+            .and_then(|endpoints| endpoints.base_url.as_ref())
+        {
+            config.base_url.clone_from(base_url);
+        }
+        config
+    }
+
+    /// Points the OAuth init and refresh configs at the auth host of `environment`, if
+    /// the definition overrides it
+    pub fn resolve_oauth_config(
+        &self,
+        environment: Environment,
+        config: &OAuthApiConfig,
+    ) -> OAuthApiConfig {
+        let mut config = config.clone();
+        if let Some(auth_url) = self
+            .endpoints
+            .for_environment(environment)
+            .and_then(|endpoints| endpoints.auth_url.as_ref())
+        {
+            config.init.base_url.clone_from(auth_url);
+            config.refresh.base_url.clone_from(auth_url);
+        }
+        config
+    }
+}
+
+/// Hosts of the platform per environment, for platforms with separate sandbox and
+/// production hosts. Unset values keep the ones of the model and OAuth definitions.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentEndpoints {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<Endpoints>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production: Option<Endpoints>,
+}
+
+impl EnvironmentEndpoints {
+    pub fn is_empty(&self) -> bool {
+        self.sandbox.is_none() && self.production.is_none()
+    }
+
+    pub fn for_environment(&self, environment: Environment) -> Option<&Endpoints> {
+        if environment.is_sandbox() {
+            self.sandbox.as_ref()
+        } else {
+            self.production.as_ref()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// Replaces the base URL of connection model definitions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Replaces the base URL of the OAuth init and refresh requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -211,3 +326,79 @@ pub struct Paths {
     pub signature: Option<String>,
     pub cursor: Option<String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::api_model_config::{SamplesInput, SchemasInput};
+
+    fn config(base_url: &str) -> ApiModelConfig {
+        ApiModelConfig {
+            base_url: base_url.to_owned(),
+            path: "/v1/customers".to_owned(),
+            auth_method: AuthMethod::None,
+            headers: None,
+            query_params: None,
+            content: None,
+            schemas: SchemasInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            samples: SamplesInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            responses: vec![],
+            paths: None,
+            pagination: None,
+        }
+    }
+
+    #[test]
+    fn test_environment_endpoints() {
+        let mut definition = ConnectionDefinition::new(
+            "Sage".to_owned(),
+            "Accounting".to_owned(),
+            "sage".to_owned(),
+            "v1".to_owned(),
+            "Accounting".to_owned(),
+            "image".to_owned(),
+            vec![],
+        );
+        let api = config("https://api.sage.com");
+        assert_eq!(
+            definition.resolve_config(Environment::Test, &api).base_url,
+            "https://api.sage.com"
+        );
+
+        definition.endpoints.sandbox = Some(Endpoints {
+            base_url: Some("https://sandbox.sage.com".to_owned()),
+            auth_url: Some("https://auth.sandbox.sage.com".to_owned()),
+        });
+        assert_eq!(
+            definition.resolve_config(Environment::Test, &api).base_url,
+            "https://sandbox.sage.com"
+        );
+        assert_eq!(
+            definition.resolve_config(Environment::Live, &api).base_url,
+            "https://api.sage.com"
+        );
+
+        let oauth = definition.resolve_oauth_config(
+            Environment::Development,
+            &OAuthApiConfig {
+                init: config("https://auth.sage.com"),
+                refresh: config("https://auth.sage.com"),
+            },
+        );
+        assert_eq!(oauth.refresh.base_url, "https://auth.sandbox.sage.com");
+
+        let json = serde_json::to_value(&definition.endpoints).unwrap();
+        assert_eq!(json["sandbox"]["authUrl"], "https://auth.sandbox.sage.com");
+        assert!(json.get("production").is_none());
+    }
+}
@@ -1,6 +1,13 @@
 use crate::{
     id::{prefix::IdPrefix, Id},
-    prelude::{schema::json_schema::JsonSchema, shared::record_metadata::RecordMetadata},
+    prelude::{
+        schema::{
+            diff::SchemaDiff,
+            json_schema::JsonSchema,
+            response_mapping::{MappingError, ResponseMapping},
+        },
+        shared::record_metadata::RecordMetadata,
+    },
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -24,6 +31,10 @@ pub struct ConnectionModelSchema {
     pub paths: Option<SchemaPaths>,
     #[cfg_attr(feature = "dummy", dummy(default))]
     pub mapping: Option<Mappings>,
+    /// Converts responses of the platform into the common model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub response_mapping: Option<ResponseMapping>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
@@ -90,6 +101,7 @@ pub struct ConnectionModelSchemaBuilder {
     pub schema: JsonSchema,
     pub paths: Option<SchemaPaths>,
     pub mapping: Option<Mappings>,
+    pub response_mapping: Option<ResponseMapping>,
 }
 
 impl ConnectionModelSchema {
@@ -113,7 +125,30 @@ impl ConnectionModelSchema {
             schema: input.schema,
             paths: input.paths,
             mapping: input.mapping,
+            response_mapping: input.response_mapping,
             record_metadata: RecordMetadata::default(),
         }
     }
+
+    /// Checks the expressions of the response mapping, if any
+    pub fn validate_mapping(&self) -> Result<(), MappingError> {
+        match &self.response_mapping {
+            Some(mapping) => mapping.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Changes from this version of the model's schema to `newer`
+    pub fn diff(&self, newer: &ConnectionModelSchema) -> SchemaDiff {
+        SchemaDiff::between(&self.schema, &newer.schema)
+    }
+
+    /// Maps a platform response to the common model, or returns it as is when the schema
+    /// has no response mapping
+    pub fn map_response(&self, response: &Value) -> Result<Value, MappingError> {
+        match &self.response_mapping {
+            Some(mapping) => mapping.apply(response),
+            None => Ok(response.clone()),
+        }
+    }
 }
@@ -1,13 +1,17 @@
 use super::api_model_config::{ApiModelConfig, Function};
 use crate::{
     id::Id,
-    prelude::{ownership::Ownership, shared::record_metadata::RecordMetadata},
+    prelude::{
+        ownership::Ownership,
+        shared::{record_metadata::RecordMetadata, redacted::Redacted},
+    },
     Feature, Hook,
 };
 use bson::doc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::ops::Not;
+use strum::AsRefStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
@@ -23,10 +27,53 @@ pub struct ConnectionOAuthDefinition {
     pub is_full_template_enabled: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub hooks: Option<Hook>,
+    #[serde(default)]
+    pub grant_type: OAuthGrantType,
+    /// Challenge method, when the platform requires PKCE on the authorization request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkce: Option<PkceMethod>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OAuthGrantType {
+    /// The user consents through the platform and we exchange the code we get back
+    #[default]
+    AuthorizationCode,
+    /// The integration authenticates as itself with its client id and secret
+    ClientCredentials,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub enum PkceMethod {
+    S256,
+    #[serde(rename = "plain")]
+    #[strum(serialize = "plain")]
+    Plain,
+}
+
+impl ConnectionOAuthDefinition {
+    pub fn with_grant_type(mut self, grant_type: OAuthGrantType) -> Self {
+        self.grant_type = grant_type;
+        self
+    }
+
+    pub fn with_pkce(mut self, method: PkceMethod) -> Self {
+        self.pkce = Some(method);
+        self
+    }
+
+    /// Whether connecting goes through the platform's consent screen
+    pub fn requires_user_consent(&self) -> bool {
+        self.grant_type == OAuthGrantType::AuthorizationCode
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
@@ -76,9 +123,9 @@ fn default_separator() -> Option<String> {
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase")]
 pub struct OAuthResponse {
-    pub access_token: String,
+    pub access_token: Redacted<String>,
     pub expires_in: i32,
-    pub refresh_token: Option<String>,
+    pub refresh_token: Option<Redacted<String>>,
     pub token_type: Option<String>,
 }
 
@@ -140,5 +187,5 @@ pub struct ConnectedPlatformSecret {
 #[serde(rename_all = "UPPERCASE")]
 pub struct PlatformSecret {
     pub client_id: String,
-    pub client_secret: String,
+    pub client_secret: Redacted<String>,
 }
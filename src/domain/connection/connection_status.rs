@@ -0,0 +1,232 @@
+use super::Connection;
+use crate::{
+    stable_code::{CONNECTION_INACTIVE_SUBTYPE, CONNECTION_SUBTYPE},
+    ApplicationError, Id, IntegrationOSError,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display, EnumString};
+use thiserror::Error;
+
+/// Lifecycle of a [`Connection`]. Connections are created as drafts until their secret
+/// is verified, can be paused and resumed while active, and are revoked for good.
+/// Connections stored before the status was are active.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    AsRefStr,
+    EnumString,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ConnectionStatus {
+    Draft,
+    #[default]
+    Active,
+    Paused,
+    Revoked,
+}
+
+impl ConnectionStatus {
+    /// Allowed transitions: drafts get activated, active and paused connections are paused
+    /// and resumed, and anything but a revoked connection can be revoked
+    pub fn can_transition_to(self, to: ConnectionStatus) -> bool {
+        use ConnectionStatus::*;
+        matches!(
+            (self, to),
+            (Draft, Active)
+                | (Active, Paused)
+                | (Paused, Active)
+                | (Draft | Active | Paused, Revoked)
+        )
+    }
+
+    pub fn is_terminal(self) -> bool {
+        self == ConnectionStatus::Revoked
+    }
+}
+
+/// Last status change of a connection, who made it and why. Its time is also recorded
+/// in the change log of the record metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatusChange {
+    pub from: ConnectionStatus,
+    pub to: ConnectionStatus,
+    pub actor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub changed_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConnectionStatusError {
+    #[error("Connection {id} cannot go from {from} to {to}")]
+    InvalidTransition {
+        id: Id,
+        from: ConnectionStatus,
+        to: ConnectionStatus,
+    },
+    #[error("Connection {id} is {status}")]
+    Inactive { id: Id, status: ConnectionStatus },
+    #[error("Connection {id} is deleted")]
+    Deleted { id: Id },
+}
+
+impl From<ConnectionStatusError> for IntegrationOSError {
+    fn from(error: ConnectionStatusError) -> Self {
+        match error {
+            ConnectionStatusError::InvalidTransition { .. } => {
+                ApplicationError::conflict(&error.to_string(), Some(CONNECTION_SUBTYPE))
+            }
+            ConnectionStatusError::Inactive { .. } | ConnectionStatusError::Deleted { .. } => {
+                ApplicationError::forbidden(&error.to_string(), Some(CONNECTION_INACTIVE_SUBTYPE))
+            }
+        }
+    }
+}
+
+impl Connection {
+    /// Fails unless the connection can be used to call its platform
+    pub fn ensure_active(&self) -> Result<(), ConnectionStatusError> {
+        if self.record_metadata.deleted {
+            return Err(ConnectionStatusError::Deleted { id: self.id });
+        }
+        match self.status {
+            ConnectionStatus::Active if self.record_metadata.active => Ok(()),
+            // Connections deactivated before the status was stored
+            ConnectionStatus::Active => Err(ConnectionStatusError::Inactive {
+                id: self.id,
+                status: ConnectionStatus::Paused,
+            }),
+            status => Err(ConnectionStatusError::Inactive {
+                id: self.id,
+                status,
+            }),
+        }
+    }
+
+    /// Moves the connection to `to`, recording the change in its metadata
+    pub fn transition(
+        &mut self,
+        to: ConnectionStatus,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<(), ConnectionStatusError> {
+        let from = self.status;
+        if !from.can_transition_to(to) {
+            return Err(ConnectionStatusError::InvalidTransition {
+                id: self.id,
+                from,
+                to,
+            });
+        }
+
+        self.record_metadata.mark_updated(actor);
+        let changed_at = Utc::now().timestamp_millis();
+        // Timestamped so that repeating a change doesn't overwrite the earlier entry
+        self.record_metadata.change_log.insert(
+            format!("Status changed from {from} to {to} by {actor} at {changed_at}"),
+            changed_at,
+        );
+        self.record_metadata.updated_at = changed_at;
+        self.record_metadata.active = to == ConnectionStatus::Active;
+        self.status = to;
+        self.status_change = Some(ConnectionStatusChange {
+            from,
+            to,
+            actor: actor.to_owned(),
+            reason: reason.map(str::to_owned),
+            changed_at,
+        });
+        Ok(())
+    }
+
+    pub fn pause(
+        &mut self,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<(), ConnectionStatusError> {
+        self.transition(ConnectionStatus::Paused, actor, reason)
+    }
+
+    pub fn revoke(
+        &mut self,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<(), ConnectionStatusError> {
+        self.transition(ConnectionStatus::Revoked, actor, reason)
+    }
+
+    pub fn activate(
+        &mut self,
+        actor: &str,
+        reason: Option<&str>,
+    ) -> Result<(), ConnectionStatusError> {
+        self.transition(ConnectionStatus::Active, actor, reason)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{id::prefix::IdPrefix, stable_code::StableErrorCode};
+
+    #[test]
+    fn test_connection_lifecycle() {
+        let mut connection =
+            Connection::builder("stripe", Id::now(IdPrefix::ConnectionDefinition)).build();
+        assert_eq!(connection.status, ConnectionStatus::Active);
+        assert!(connection.ensure_active().is_ok());
+
+        connection.pause("alice", Some("Too many errors")).unwrap();
+        assert_eq!(connection.status, ConnectionStatus::Paused);
+        assert!(!connection.record_metadata.active);
+        assert_eq!(connection.record_metadata.last_modified_by, "alice");
+        let change = connection.status_change.clone().unwrap();
+        assert_eq!(change.from, ConnectionStatus::Active);
+        assert_eq!(change.reason.as_deref(), Some("Too many errors"));
+        let error = IntegrationOSError::from(connection.ensure_active().unwrap_err());
+        assert_eq!(error.stable_code(), StableErrorCode::ConnInactive);
+
+        connection.activate("alice", None).unwrap();
+        assert!(connection.ensure_active().is_ok());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        connection.pause("alice", None).unwrap();
+        connection.activate("alice", None).unwrap();
+        let pauses = connection
+            .record_metadata
+            .change_log
+            .keys()
+            .filter(|entry| entry.starts_with("Status changed from active to paused by alice"))
+            .count();
+        assert_eq!(pauses, 2);
+        connection.revoke("bob", Some("Compromised")).unwrap();
+        assert!(connection.status.is_terminal());
+        let error = connection.activate("bob", None).unwrap_err();
+        assert!(matches!(
+            error,
+            ConnectionStatusError::InvalidTransition {
+                from: ConnectionStatus::Revoked,
+                to: ConnectionStatus::Active,
+                ..
+            }
+        ));
+        assert_eq!(
+            IntegrationOSError::from(error).stable_code(),
+            StableErrorCode::Conflict
+        );
+
+        let value = serde_json::to_value(&connection).unwrap();
+        assert_eq!(value["status"], "revoked");
+        assert_eq!(value["statusChange"]["actor"], "bob");
+    }
+}
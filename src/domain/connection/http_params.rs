@@ -0,0 +1,397 @@
+//! Typed headers and query params of an [`ApiModelConfig`]. Values are plain strings on
+//! the wire, which may reference a key of the connection's secret (`{{API_KEY}}`) or be
+//! a handlebars template, so configs can be checked when saved instead of failing when a
+//! request is built.
+//!
+//! [`ApiModelConfig`]: super::api_model_config::ApiModelConfig
+
+use crate::{DefaultTemplate, IntegrationOSError, InternalError, TemplateExt};
+use handlebars::Template;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+use std::{collections::HashSet, fmt::Display};
+
+fn param_error(message: &str) -> IntegrationOSError {
+    InternalError::invalid_argument(message, Some("http_params"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub enum ParamValue {
+    Literal(String),
+    /// A key of the connection's secret, written `{{KEY}}`
+    Secret(String),
+    /// Any other handlebars template, rendered against the connection's secret
+    Template(String),
+}
+
+impl ParamValue {
+    pub fn parse(value: &str) -> Self {
+        let secret = value
+            .strip_prefix("{{")
+            .and_then(|v| v.strip_suffix("}}"))
+            .map(str::trim)
+            .filter(|key| {
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            });
+        match secret {
+            Some(key) => ParamValue::Secret(key.to_owned()),
+            None if value.contains("{{") => ParamValue::Template(value.to_owned()),
+            None => ParamValue::Literal(value.to_owned()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), IntegrationOSError> {
+        match self {
+            ParamValue::Template(template) => Template::compile(template)
+                .map(|_| ())
+                .map_err(|e| param_error(&format!("Invalid template {template:?}: {e}"))),
+            ParamValue::Literal(_) | ParamValue::Secret(_) => Ok(()),
+        }
+    }
+
+    pub fn resolve(&self, secret: Option<&Value>) -> Result<String, IntegrationOSError> {
+        match self {
+            ParamValue::Literal(value) => Ok(value.clone()),
+            ParamValue::Secret(key) => secret
+                .and_then(|secret| secret.get(key))
+                .map(|value| match value {
+                    Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                })
+                .ok_or_else(|| {
+                    InternalError::key_not_found(
+                        &format!("{key} in connection secret"),
+                        Some("http_params"),
+                    )
+                }),
+            ParamValue::Template(template) => DefaultTemplate::default().render(template, secret),
+        }
+    }
+}
+
+impl Display for ParamValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamValue::Literal(value) | ParamValue::Template(value) => f.write_str(value),
+            ParamValue::Secret(key) => write!(f, "{{{{{key}}}}}"),
+        }
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(value: &str) -> Self {
+        Self::parse(value)
+    }
+}
+
+impl Serialize for ParamValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ParamValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub struct HeaderParam {
+    pub name: String,
+    pub values: Vec<ParamValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(ParamValue),
+    Many(Vec<ParamValue>),
+}
+
+/// Headers in the order they were configured. Stored as a map of names to a value or a
+/// list of values, like the `HeaderMap`s they replace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub struct HeaderParams(pub Vec<HeaderParam>);
+
+impl HeaderParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<ParamValue>) -> Self {
+        self.0.push(HeaderParam {
+            name: name.into(),
+            values: vec![value.into()],
+        });
+        self
+    }
+
+    /// Rejects illegal header names or values, malformed templates, and names configured
+    /// more than once
+    pub fn validate(&self) -> Result<(), IntegrationOSError> {
+        let mut seen = HashSet::new();
+        for HeaderParam { name, values } in &self.0 {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| param_error(&format!("Illegal header name {name:?}")))?;
+            if !seen.insert(header) {
+                return Err(param_error(&format!(
+                    "Header {name:?} is set more than once"
+                )));
+            }
+            for value in values {
+                value.validate()?;
+                if let ParamValue::Literal(literal) = value {
+                    HeaderValue::from_str(literal)
+                        .map_err(|_| param_error(&format!("Illegal value for header {name:?}")))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the values against the connection's secret
+    pub fn to_header_map(&self, secret: Option<&Value>) -> Result<HeaderMap, IntegrationOSError> {
+        let mut headers = HeaderMap::new();
+        for HeaderParam { name, values } in &self.0 {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| param_error(&format!("Illegal header name {name:?}")))?;
+            for value in values {
+                let sensitive = !matches!(value, ParamValue::Literal(_));
+                let mut value = HeaderValue::try_from(value.resolve(secret)?)
+                    .map_err(|_| param_error(&format!("Illegal value for header {name}")))?;
+                value.set_sensitive(sensitive);
+                headers.append(name.clone(), value);
+            }
+        }
+        Ok(headers)
+    }
+}
+
+impl Serialize for HeaderParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for HeaderParam { name, values } in &self.0 {
+            match values.as_slice() {
+                [value] => map.serialize_entry(name, value)?,
+                values => map.serialize_entry(name, values)?,
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeaderParamsVisitor;
+
+        impl<'de> Visitor<'de> for HeaderParamsVisitor {
+            type Value = HeaderParams;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of header names to a value or a list of values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut params = vec![];
+                while let Some((name, values)) = access.next_entry::<String, OneOrMany>()? {
+                    let values = match values {
+                        OneOrMany::One(value) => vec![value],
+                        OneOrMany::Many(values) => values,
+                    };
+                    params.push(HeaderParam { name, values });
+                }
+                Ok(HeaderParams(params))
+            }
+        }
+
+        deserializer.deserialize_map(HeaderParamsVisitor)
+    }
+}
+
+/// Query params in the order they were configured, stored as a map of names to values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub struct QueryParams(pub Vec<(String, ParamValue)>);
+
+impl QueryParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<ParamValue>) -> Self {
+        self.0.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, v)| v)
+    }
+
+    /// Rejects empty or repeated names and malformed templates
+    pub fn validate(&self) -> Result<(), IntegrationOSError> {
+        let mut seen = HashSet::new();
+        for (name, value) in &self.0 {
+            if name.is_empty() {
+                return Err(param_error("Query param names can't be empty"));
+            }
+            if !seen.insert(name) {
+                return Err(param_error(&format!(
+                    "Query param {name:?} is set more than once"
+                )));
+            }
+            value.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the values against the connection's secret, ready for
+    /// [`reqwest::RequestBuilder::query`]
+    pub fn to_pairs(
+        &self,
+        secret: Option<&Value>,
+    ) -> Result<Vec<(String, String)>, IntegrationOSError> {
+        self.0
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), value.resolve(secret)?)))
+            .collect()
+    }
+}
+
+impl Serialize for QueryParams {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0 {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryParams {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct QueryParamsVisitor;
+
+        impl<'de> Visitor<'de> for QueryParamsVisitor {
+            type Value = QueryParams;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of query param names to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+                let mut params = vec![];
+                while let Some(entry) = access.next_entry::<String, ParamValue>()? {
+                    params.push(entry);
+                }
+                Ok(QueryParams(params))
+            }
+        }
+
+        deserializer.deserialize_map(QueryParamsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_param_values() {
+        assert_eq!(
+            ParamValue::parse("{{ API_KEY }}"),
+            ParamValue::Secret("API_KEY".to_owned())
+        );
+        assert_eq!(
+            ParamValue::parse("Bearer {{TOKEN}}"),
+            ParamValue::Template("Bearer {{TOKEN}}".to_owned())
+        );
+        assert_eq!(
+            ParamValue::parse("application/json"),
+            ParamValue::Literal("application/json".to_owned())
+        );
+        assert!(ParamValue::parse("{{#if}}").validate().is_err());
+
+        let secret = json!({ "API_KEY": "key_123", "TOKEN": "abc" });
+        assert_eq!(
+            ParamValue::parse("{{API_KEY}}")
+                .resolve(Some(&secret))
+                .unwrap(),
+            "key_123"
+        );
+        assert_eq!(
+            ParamValue::parse("Bearer {{TOKEN}}")
+                .resolve(Some(&secret))
+                .unwrap(),
+            "Bearer abc"
+        );
+        assert!(ParamValue::parse("{{MISSING}}")
+            .resolve(Some(&secret))
+            .is_err());
+    }
+
+    #[test]
+    fn test_header_params() {
+        let value = json!({ "Accept": "application/json", "X-Api-Key": "{{API_KEY}}", "X-Tag": ["a", "b"] });
+        let headers: HeaderParams = serde_json::from_value(value.clone()).unwrap();
+        assert!(headers.validate().is_ok());
+        assert_eq!(serde_json::to_value(&headers).unwrap(), value);
+
+        let map = headers
+            .to_header_map(Some(&json!({ "API_KEY": "key_123" })))
+            .unwrap();
+        assert_eq!(map["X-Api-Key"], "key_123");
+        assert!(map["X-Api-Key"].is_sensitive());
+        assert_eq!(map.get_all("X-Tag").iter().count(), 2);
+
+        let duplicated = HeaderParams::new()
+            .with("Accept", "application/json")
+            .with("accept", "text/plain");
+        assert!(duplicated.validate().is_err());
+        assert!(HeaderParams::new()
+            .with("Bad Header", "value")
+            .validate()
+            .is_err());
+        assert!(HeaderParams::new()
+            .with("X-Line", "a\nb")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_query_params() {
+        let params: QueryParams =
+            serde_json::from_str(r#"{"limit": "10", "limit": "20"}"#).unwrap();
+        assert!(params.validate().is_err());
+
+        let params = QueryParams::new()
+            .with("limit", "10")
+            .with("key", "{{API_KEY}}");
+        assert!(params.validate().is_ok());
+        assert_eq!(
+            params
+                .to_pairs(Some(&json!({ "API_KEY": "key_123" })))
+                .unwrap(),
+            vec![
+                ("limit".to_owned(), "10".to_owned()),
+                ("key".to_owned(), "key_123".to_owned())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            json!({ "limit": "10", "key": "{{API_KEY}}" })
+        );
+    }
+}
@@ -1,14 +1,27 @@
 pub mod api_model_config;
+pub mod api_version_migration;
+pub mod connection_auth;
+pub mod connection_builder;
 pub mod connection_definition;
 pub mod connection_model_definition;
 pub mod connection_model_schema;
 pub mod connection_oauth_definition;
+pub mod connection_status;
+pub mod http_params;
+pub mod oauth_flow;
+pub mod platform_id;
+pub mod webhook_definition;
 
 use super::{
     configuration::environment::Environment,
-    shared::{ownership::Ownership, record_metadata::RecordMetadata, settings::Settings},
+    shared::{
+        ownership::Ownership, record_metadata::RecordMetadata, redacted::Redacted,
+        settings::Settings, unknown_variant,
+    },
 };
-use crate::id::Id;
+use crate::{id::Id, IntegrationOSError, InternalError};
+use connection_auth::ConnectionAuth;
+use connection_status::{ConnectionStatus, ConnectionStatusChange};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{hash::Hash, sync::Arc};
@@ -34,16 +47,26 @@ pub struct Connection {
     pub platform: Arc<str>,
     pub secrets_service_id: String,
     pub event_access_id: Id,
-    pub access_key: String,
+    pub access_key: Redacted<String>,
     pub settings: Settings,
     pub throughput: Throughput,
     pub ownership: Ownership,
     #[serde(default)]
     pub oauth: Option<OAuth>,
+    /// Unset on connections created before auth methods were stored, see
+    /// [`Connection::auth_method`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<ConnectionAuth>,
+    #[serde(default)]
+    pub status: ConnectionStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_change: Option<ConnectionStatusChange>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+/// Redacted view of a [`Connection`] that is safe to hand out to clients. It never carries
+/// the access key, the secrets service id, the event access id or the throughput key.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SanitizedConnection {
@@ -57,20 +80,60 @@ pub struct SanitizedConnection {
     pub group: String,
     pub environment: Environment,
     pub platform: Arc<str>,
-    pub secrets_service_id: String,
-    pub event_access_id: Id,
     pub settings: Settings,
-    pub throughput: Throughput,
+    pub throughput_limit: u64,
     pub ownership: Ownership,
     #[serde(default)]
     pub oauth: Option<OAuth>,
+    /// Unset on connections created before auth methods were stored, see
+    /// [`Connection::auth_method`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<ConnectionAuth>,
+    #[serde(default)]
+    pub status: ConnectionStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_change: Option<ConnectionStatusChange>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
 impl SanitizedConnection {
-    pub fn to_value(&self) -> Value {
-        serde_json::to_value(self).unwrap_or(Value::Null)
+    pub fn to_value(&self) -> Result<Value, IntegrationOSError> {
+        serde_json::to_value(self).map_err(|e| {
+            InternalError::serialize_error(&e.to_string(), Some("sanitized_connection"))
+        })
+    }
+}
+
+impl Connection {
+    /// How to authenticate against the platform, falling back on the `oauth` field for
+    /// connections stored before the auth method was
+    pub fn auth_method(&self) -> ConnectionAuth {
+        self.auth
+            .clone()
+            .unwrap_or_else(|| ConnectionAuth::from_oauth(self.oauth.as_ref()))
+    }
+
+    pub fn to_public(&self) -> SanitizedConnection {
+        SanitizedConnection {
+            id: self.id,
+            platform_version: self.platform_version.clone(),
+            connection_definition_id: self.connection_definition_id,
+            r#type: self.r#type.clone(),
+            name: self.name.clone(),
+            key: self.key.clone(),
+            group: self.group.clone(),
+            environment: self.environment,
+            platform: self.platform.clone(),
+            settings: self.settings.clone(),
+            throughput_limit: self.throughput.limit,
+            ownership: self.ownership.clone(),
+            oauth: self.oauth.clone(),
+            auth: self.auth.clone(),
+            status: self.status,
+            status_change: self.status_change.clone(),
+            record_metadata: self.record_metadata.clone(),
+        }
     }
 }
 
@@ -112,10 +175,14 @@ pub enum ConnectionType {
     FileSystem,
     Stream,
     Custom,
+    /// Type added by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    #[strum(to_string = "unknown")]
+    Unknown(Value),
 }
 
 #[derive(
-    Debug, Clone, Copy, Serialize, PartialEq, Eq, Deserialize, Display, AsRefStr, EnumString,
+    Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash, Deserialize, Display, AsRefStr, EnumString,
 )]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
@@ -138,3 +205,116 @@ pub struct Throughput {
     pub key: String,
     pub limit: u64,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    const SENSITIVE_FIELDS: [&str; 5] = [
+        "accessKey",
+        "secretsServiceId",
+        "eventAccessId",
+        "throughput",
+        "access_key",
+    ];
+
+    fn connection() -> Connection {
+        serde_json::from_value(json!({
+            "_id": "conn::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "platformVersion": "1.0.0",
+            "connectionDefinitionId": "conn_def::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "type": { "api": {} },
+            "name": "Stripe",
+            "key": "stripe::test",
+            "group": "group",
+            "environment": "test",
+            "platform": "stripe",
+            "secretsServiceId": "secret-id",
+            "eventAccessId": "evt_ac::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "accessKey": "id_test_1_super_secret",
+            "settings": {
+                "parseWebhookBody": false,
+                "showSecret": false,
+                "allowCustomEvents": false,
+                "oauth": false
+            },
+            "throughput": { "key": "throughput-key", "limit": 100 },
+            "ownership": { "buildableId": "owner", "clientId": "owner" }
+        }))
+        .expect("Failed to deserialize connection")
+    }
+
+    #[test]
+    fn test_sanitized_connection_never_contains_sensitive_fields() {
+        let connection = connection();
+        let public = connection.to_public();
+        let value = public.to_value().unwrap();
+        let object = value
+            .as_object()
+            .expect("Sanitized connection is an object");
+
+        for field in SENSITIVE_FIELDS {
+            assert!(!object.contains_key(field), "{field} leaked");
+        }
+
+        let serialized = serde_json::to_string(&public).unwrap();
+        assert!(!serialized.contains("super_secret"));
+        assert!(!serialized.contains("secret-id"));
+        assert!(!serialized.contains("throughput-key"));
+        assert_eq!(object["throughputLimit"], 100);
+    }
+
+    #[test]
+    fn test_sanitized_connection_drops_sensitive_fields_from_raw_documents() {
+        let connection = connection();
+        let mut value = serde_json::to_value(&connection).unwrap();
+        value["throughputLimit"] = json!(100);
+        let public: SanitizedConnection = serde_json::from_value(value).unwrap();
+
+        assert_eq!(public, connection.to_public());
+        let value = public.to_value().unwrap();
+        for field in SENSITIVE_FIELDS {
+            assert!(value.get(field).is_none(), "{field} leaked");
+        }
+    }
+
+    #[test]
+    fn test_unknown_platform_and_type() {
+        let raw = json!({ "graphql": { "endpoint": "/graphql", "batching": true } });
+        let r#type: ConnectionType = serde_json::from_value(raw.clone()).unwrap();
+        assert_eq!(r#type, ConnectionType::Unknown(raw.clone()));
+        assert_eq!(serde_json::to_value(&r#type).unwrap(), raw);
+        let bson = bson::to_bson(&r#type).unwrap();
+        assert_eq!(bson, bson::to_bson(&raw).unwrap());
+        assert_eq!(bson::from_bson::<ConnectionType>(bson).unwrap(), r#type);
+        assert_eq!(
+            serde_json::from_value::<ConnectionType>(json!({ "api": {} })).unwrap(),
+            ConnectionType::Api {}
+        );
+
+        let mut value = serde_json::to_value(connection()).unwrap();
+        value["platform"] = json!("netsuite");
+        value["type"] = json!("graphql");
+        assert!(serde_json::from_value::<Connection>(value).is_ok());
+    }
+
+    #[test]
+    fn test_auth_method_migration() {
+        let mut connection = connection();
+        assert_eq!(connection.auth_method(), ConnectionAuth::None);
+
+        connection.oauth = Some(OAuth::Enabled {
+            connection_oauth_definition_id: connection.connection_definition_id,
+            expires_in: None,
+            expires_at: None,
+        });
+        assert_eq!(connection.auth_method(), ConnectionAuth::OAuth);
+
+        connection.auth = Some(ConnectionAuth::BearerToken);
+        let value = serde_json::to_value(&connection).unwrap();
+        assert_eq!(value["auth"], json!({ "type": "bearerToken" }));
+        let decoded: Connection = serde_json::from_value(value).unwrap();
+        assert_eq!(decoded.auth_method(), ConnectionAuth::BearerToken);
+    }
+}
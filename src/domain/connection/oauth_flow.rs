@@ -0,0 +1,198 @@
+use super::connection_oauth_definition::PkceMethod;
+use crate::{id::Id, IntegrationOSError, InternalError, SignatureError, Signer};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Code verifier and challenge of a PKCE authorization (RFC 7636). The verifier stays
+/// server side until the code is exchanged, only the challenge goes to the platform.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PkceChallenge {
+    pub verifier: String,
+    pub challenge: String,
+    pub method: PkceMethod,
+}
+
+impl PkceChallenge {
+    /// Generates a fresh 64 character verifier
+    pub fn new(method: PkceMethod) -> Self {
+        let verifier = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect::<String>();
+        Self::from_verifier(verifier, method)
+    }
+
+    pub fn from_verifier(verifier: impl Into<String>, method: PkceMethod) -> Self {
+        let verifier = verifier.into();
+        let challenge = match method {
+            PkceMethod::S256 => Base64UrlUnpadded::encode_string(&Sha256::digest(&verifier)),
+            PkceMethod::Plain => verifier.clone(),
+        };
+        Self {
+            verifier,
+            challenge,
+            method,
+        }
+    }
+
+    /// `code_challenge` and `code_challenge_method` for the authorization URL
+    pub fn query_params(&self) -> [(&'static str, &str); 2] {
+        [
+            ("code_challenge", &self.challenge),
+            ("code_challenge_method", self.method.as_ref()),
+        ]
+    }
+}
+
+/// Token request body of a client credentials grant, used by platforms where the
+/// integration acts on its own behalf without a user consenting
+pub fn client_credentials_body(client_id: &str, client_secret: &str, scopes: &str) -> Value {
+    let mut body = Map::new();
+    body.insert("grant_type".to_owned(), json!("client_credentials"));
+    body.insert("client_id".to_owned(), json!(client_id));
+    body.insert("client_secret".to_owned(), json!(client_secret));
+    if !scopes.is_empty() {
+        body.insert("scope".to_owned(), json!(scopes));
+    }
+    Value::Object(body)
+}
+
+/// Token request body exchanging an authorization code, with the PKCE verifier if the
+/// authorization was started with a challenge
+pub fn authorization_code_body(
+    code: &str,
+    redirect_uri: &str,
+    pkce: Option<&PkceChallenge>,
+) -> Value {
+    let mut body = Map::new();
+    body.insert("grant_type".to_owned(), json!("authorization_code"));
+    body.insert("code".to_owned(), json!(code));
+    body.insert("redirect_uri".to_owned(), json!(redirect_uri));
+    if let Some(pkce) = pkce {
+        body.insert("code_verifier".to_owned(), json!(pkce.verifier));
+    }
+    Value::Object(body)
+}
+
+/// What the `state` parameter of an authorization carries through the platform's
+/// redirect back to us
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthState {
+    pub connection_definition_id: Id,
+    pub nonce: String,
+    /// Epoch seconds
+    pub issued_at: i64,
+}
+
+impl OAuthState {
+    pub fn new(connection_definition_id: Id, issued_at: i64) -> Self {
+        Self {
+            connection_definition_id,
+            nonce: uuid::Uuid::new_v4().simple().to_string(),
+            issued_at,
+        }
+    }
+}
+
+/// Signs the `state` parameter so callbacks can't be forged or replayed after the
+/// tolerance. The state is `{payload}.{timestamp}.{signature}`, with the payload
+/// base64url encoded so it survives the redirect untouched.
+#[derive(Debug, Clone)]
+pub struct OAuthStateSigner {
+    signer: Signer,
+}
+
+impl OAuthStateSigner {
+    pub fn new(secret: impl AsRef<[u8]>, tolerance_secs: u64) -> Self {
+        Self {
+            signer: Signer::new(secret, tolerance_secs),
+        }
+    }
+
+    pub fn sign(&self, state: &OAuthState) -> Result<String, IntegrationOSError> {
+        let payload = serde_json::to_vec(state)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("oauth_state")))?;
+        let header = self.signer.sign(&payload, state.issued_at);
+        let signature = header
+            .split_once(",v1=")
+            .map(|(_, s)| s)
+            .unwrap_or_default();
+        Ok(format!(
+            "{}.{}.{signature}",
+            Base64UrlUnpadded::encode_string(&payload),
+            state.issued_at
+        ))
+    }
+
+    /// Checks the signature and age of a state received at `now` (epoch seconds)
+    pub fn verify(&self, state: &str, now: i64) -> Result<OAuthState, IntegrationOSError> {
+        let malformed = || SignatureError::Malformed("invalid OAuth state".to_owned());
+        let mut parts = state.splitn(3, '.');
+        let (Some(payload), Some(timestamp), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed().into());
+        };
+        let payload = Base64UrlUnpadded::decode_vec(payload).map_err(|_| malformed())?;
+        self.signer
+            .verify(&format!("t={timestamp},v1={signature}"), &payload, now)?;
+        serde_json::from_slice(&payload).map_err(|_| malformed().into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::id::prefix::IdPrefix;
+
+    #[test]
+    fn test_pkce_challenge() {
+        // RFC 7636 appendix B
+        let pkce = PkceChallenge::from_verifier(
+            "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk",
+            PkceMethod::S256,
+        );
+        assert_eq!(
+            pkce.challenge,
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(pkce.query_params()[1], ("code_challenge_method", "S256"));
+
+        let generated = PkceChallenge::new(PkceMethod::Plain);
+        assert_eq!(generated.verifier.len(), 64);
+        assert_eq!(generated.challenge, generated.verifier);
+
+        let body = authorization_code_body("code", "https://app/callback", Some(&pkce));
+        assert_eq!(body["code_verifier"], pkce.verifier);
+        let body = client_credentials_body("id", "secret", "");
+        assert!(body.get("scope").is_none());
+    }
+
+    #[test]
+    fn test_oauth_state_signing() {
+        let signer = OAuthStateSigner::new("state-secret", 600);
+        let state = OAuthState::new(Id::now(IdPrefix::ConnectionDefinition), 1_700_000_000);
+        let signed = signer.sign(&state).unwrap();
+
+        assert_eq!(signer.verify(&signed, 1_700_000_300).unwrap(), state);
+        assert!(signer.verify(&signed, 1_700_001_000).is_err());
+        assert!(OAuthStateSigner::new("other", 600)
+            .verify(&signed, 1_700_000_000)
+            .is_err());
+        assert!(signer.verify("garbage", 1_700_000_000).is_err());
+
+        let (payload, rest) = signed.split_once('.').unwrap();
+        let mut forged = state.clone();
+        forged.nonce = "forged".to_owned();
+        let forged = Base64UrlUnpadded::encode_string(&serde_json::to_vec(&forged).unwrap());
+        assert_ne!(forged, payload);
+        assert!(signer
+            .verify(&format!("{forged}.{rest}"), 1_700_000_000)
+            .is_err());
+    }
+}
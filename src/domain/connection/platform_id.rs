@@ -0,0 +1,137 @@
+use super::Platform;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Name of a platform, including ones added after this crate was released. It
+/// serializes as the lowercase name, the same as [`Platform`], so the two can be used
+/// interchangeably in stored records.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct PlatformId(Arc<str>);
+
+impl PlatformId {
+    pub fn new(platform: impl AsRef<str>) -> Self {
+        Self(platform.as_ref().trim().to_lowercase().into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Variant of [`Platform`] for platforms known to this crate
+    pub fn known(&self) -> Option<Platform> {
+        Platform::from_str(&self.0).ok()
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        self.known().is_some()
+    }
+}
+
+impl Platform {
+    /// Every platform known to this crate
+    pub fn builtin() -> [Platform; 10] {
+        [
+            Platform::RabbitMq,
+            Platform::Xero,
+            Platform::PostgreSql,
+            Platform::MySql,
+            Platform::MariaDb,
+            Platform::MsSql,
+            Platform::Stripe,
+            Platform::Sage,
+            Platform::Shopify,
+            Platform::Snowflake,
+        ]
+    }
+}
+
+impl Display for PlatformId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for PlatformId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for PlatformId {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl From<&str> for PlatformId {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for PlatformId {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<PlatformId> for String {
+    fn from(value: PlatformId) -> Self {
+        value.0.to_string()
+    }
+}
+
+impl From<Platform> for PlatformId {
+    fn from(value: Platform) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&Platform> for PlatformId {
+    fn from(value: &Platform) -> Self {
+        Self::new(value)
+    }
+}
+
+impl PartialEq<Platform> for PlatformId {
+    fn eq(&self, other: &Platform) -> bool {
+        self.as_str() == other.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_platform_id_is_compatible_with_platform() {
+        for platform in Platform::builtin() {
+            let id = PlatformId::from(platform);
+            assert_eq!(id.known(), Some(platform));
+            assert_eq!(
+                serde_json::to_value(&id).unwrap(),
+                serde_json::to_value(platform).unwrap()
+            );
+            assert_eq!(
+                serde_json::from_value::<Platform>(serde_json::to_value(&id).unwrap()).unwrap(),
+                platform
+            );
+        }
+
+        let hubspot = PlatformId::new(" HubSpot ");
+        assert_eq!(hubspot.as_str(), "hubspot");
+        assert!(!hubspot.is_builtin());
+        assert_eq!(hubspot.known(), None);
+        assert_eq!(
+            serde_json::from_str::<PlatformId>("\"Stripe\"").unwrap(),
+            Platform::Stripe
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::record_metadata::RecordMetadata,
+};
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display};
+
+/// How a platform signs the webhooks it sends and where their event type is found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDefinition {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub connection_platform: String,
+    pub connection_definition_id: Id,
+    /// Header carrying the signature, e.g. `X-Shopify-Hmac-Sha256` or `Stripe-Signature`
+    pub signature_header: String,
+    #[serde(default)]
+    pub algorithm: SignatureAlgorithm,
+    pub scheme: SignatureScheme,
+    /// Key of the signing secret in the connection's secret
+    pub secret_key: String,
+    pub event_type: EventTypeSource,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl WebhookDefinition {
+    pub fn new(
+        connection_platform: String,
+        connection_definition_id: Id,
+        signature_header: String,
+        scheme: SignatureScheme,
+        secret_key: String,
+        event_type: EventTypeSource,
+    ) -> Self {
+        Self {
+            id: Id::now(IdPrefix::WebhookDefinition),
+            connection_platform,
+            connection_definition_id,
+            signature_header,
+            algorithm: SignatureAlgorithm::default(),
+            scheme,
+            secret_key,
+            event_type,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Display, AsRefStr,
+)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SignatureAlgorithm {
+    #[default]
+    HmacSha256,
+    HmacSha512,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SignatureScheme {
+    /// The header is the signature of the raw body, after `prefix` if any, e.g.
+    /// Shopify (base64) or GitHub (`sha256=` then hex)
+    #[serde(rename_all = "camelCase")]
+    Raw {
+        #[serde(default)]
+        encoding: SignatureEncoding,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prefix: Option<String>,
+    },
+    /// The header is `t={timestamp},v1={hex signature}` over `{timestamp}.{body}`, e.g.
+    /// Stripe, rejected outside the tolerance
+    #[serde(rename_all = "camelCase")]
+    Timestamped { tolerance_secs: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum EventTypeSource {
+    /// e.g. `X-Shopify-Topic`
+    Header { name: String },
+    /// JSONPath into the body, e.g. `$.type`
+    Body { path: String },
+}
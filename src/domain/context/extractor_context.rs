@@ -1,6 +1,8 @@
 use super::{PipelineContext, Transaction};
 use crate::{
     id::Id,
+    policies::RetryAttempts,
+    prelude::shared::{correlation_id::CorrelationId, unknown_variant},
     prelude::{PipelineExt, PipelineStatus},
 };
 use async_trait::async_trait;
@@ -20,6 +22,11 @@ pub struct ExtractorContext {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub timestamp: DateTime<Utc>,
     r#type: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<CorrelationId>,
+    /// Attempts of the last stage run under a retry policy
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub attempts: Option<RetryAttempts>,
 
     #[serde(flatten)]
     pub transaction: Option<Transaction>,
@@ -35,6 +42,8 @@ impl ExtractorContext {
             stage: Stage::New,
             timestamp: Utc::now(),
             r#type: "extractor".into(),
+            correlation_id: context.correlation_id.clone(),
+            attempts: None,
             transaction: None,
         }
     }
@@ -63,6 +72,9 @@ impl PipelineExt for ExtractorContext {
 pub enum Stage {
     New,
     FinishedExtractor(Value),
+    /// Stage written by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
 
 impl Display for Stage {
@@ -72,6 +84,7 @@ impl Display for Stage {
             Self::FinishedExtractor(v) => {
                 write!(f, "FinishedExtractor({v})")
             }
+            Self::Unknown(stage) => write!(f, "Unknown({stage})"),
         }
     }
 }
@@ -1,9 +1,13 @@
 pub mod extractor_context;
 pub mod pipeline_context;
+pub mod reconciliation;
 pub mod root_context;
+pub mod summary;
 pub mod transaction;
 
 pub use extractor_context::ExtractorContext;
 pub use pipeline_context::PipelineContext;
+pub use reconciliation::ReconciliationAttempts;
 pub use root_context::RootContext;
+pub use summary::ContextSummary;
 pub use transaction::Transaction;
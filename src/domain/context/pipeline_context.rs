@@ -1,6 +1,8 @@
 use super::{extractor_context::ExtractorContext, root_context::RootContext, Transaction};
 use crate::{
     id::Id,
+    policies::RetryAttempts,
+    prelude::shared::{correlation_id::CorrelationId, unknown_variant},
     prelude::{PipelineExt, PipelineStatus},
 };
 use async_trait::async_trait;
@@ -19,6 +21,11 @@ pub struct PipelineContext {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub timestamp: DateTime<Utc>,
     r#type: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<CorrelationId>,
+    /// Attempts of the last stage run under a retry policy
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub attempts: Option<RetryAttempts>,
 
     #[serde(flatten)]
     pub transaction: Option<Transaction>,
@@ -33,6 +40,8 @@ impl PipelineContext {
             stage: PipelineStage::New,
             timestamp: Utc::now(),
             r#type: "pipeline".into(),
+            correlation_id: context.correlation_id.clone(),
+            attempts: None,
             transaction: None,
         }
     }
@@ -64,6 +73,9 @@ pub enum PipelineStage {
     ExecutedExtractors(HashMap<String, Value>),
     ExecutedTransformer(Option<Value>),
     FinishedPipeline,
+    /// Stage written by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
 
 impl Display for PipelineStage {
@@ -91,6 +103,7 @@ impl Display for PipelineStage {
                 write!(f, "ExecutedTransformer()")
             }
             Self::FinishedPipeline => write!(f, "FinishedPipeline"),
+            Self::Unknown(stage) => write!(f, "Unknown({stage})"),
         }
     }
 }
@@ -0,0 +1,26 @@
+use crate::id::Id;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// How many times the watchdog republished an event chain it found dead. It lives next to
+/// the raw contexts and has no timestamp, so the watchdog aggregation never sees it as the
+/// latest context of the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconciliationAttempts {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub event_key: Id,
+    pub attempts: u32,
+    /// When the chain was last republished, in epoch ms
+    pub last_attempt_at: i64,
+    r#type: Arc<str>,
+}
+
+impl ReconciliationAttempts {
+    pub const TYPE: &'static str = "reconciliation";
+
+    pub fn id_for(event_key: &Id) -> String {
+        format!("{}::{event_key}", Self::TYPE)
+    }
+}
@@ -1,11 +1,13 @@
 use super::{pipeline_context::PipelineContext, Transaction};
 use crate::{
-    id::Id,
+    id::{Id, IdGenerator, SystemIdGenerator},
+    prelude::shared::{correlation_id::CorrelationId, unknown_variant},
     prelude::{PipelineExt, PipelineStatus},
 };
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{collections::HashMap, fmt::Display, sync::Arc};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,6 +19,8 @@ pub struct RootContext {
     #[serde(with = "chrono::serde::ts_milliseconds")]
     pub timestamp: DateTime<Utc>,
     r#type: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<CorrelationId>,
 
     #[serde(flatten)]
     pub transaction: Option<Transaction>,
@@ -24,16 +28,26 @@ pub struct RootContext {
 
 impl RootContext {
     pub fn new(event_key: Id) -> Self {
+        Self::new_with_generator(event_key, &SystemIdGenerator)
+    }
+
+    pub fn new_with_generator(event_key: Id, generator: &dyn IdGenerator) -> Self {
         Self {
             event_key,
             status: PipelineStatus::Succeeded,
             stage: RootStage::New,
-            timestamp: Utc::now(),
+            timestamp: generator.now(),
             r#type: "root".into(),
+            correlation_id: None,
             transaction: None,
         }
     }
 
+    pub fn with_correlation_id(mut self, correlation_id: Option<CorrelationId>) -> Self {
+        self.correlation_id = correlation_id;
+        self
+    }
+
     pub fn is_dropped(&self) -> bool {
         matches!(self.status, PipelineStatus::Dropped { .. })
     }
@@ -61,6 +75,9 @@ pub enum RootStage {
     ProcessedDuplicates,
     ProcessingPipelines(HashMap<String, PipelineContext>),
     Finished,
+    /// Stage written by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
 
 impl Display for RootStage {
@@ -77,6 +94,7 @@ impl Display for RootStage {
                 write!(f, ")")
             }
             Self::Finished => write!(f, "Finished"),
+            Self::Unknown(stage) => write!(f, "Unknown({stage})"),
         }
     }
 }
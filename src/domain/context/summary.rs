@@ -0,0 +1,71 @@
+use super::root_context::RootContext;
+use crate::{id::Id, prelude::PipelineStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Compact record of a completed event chain. It lives next to the raw contexts, which are
+/// marked for TTL deletion once the summary is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSummary {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub event_key: Id,
+    pub status: PipelineStatus,
+    pub stage: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub started_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub context_count: u64,
+    r#type: Arc<str>,
+}
+
+impl ContextSummary {
+    pub const TYPE: &'static str = "summary";
+
+    pub fn new(root: &RootContext, started_at: DateTime<Utc>, context_count: u64) -> Self {
+        Self {
+            id: Self::id_for(&root.event_key),
+            event_key: root.event_key,
+            status: root.status.clone(),
+            // Keeps the watchdog aggregation treating the chain as complete
+            stage: "Finished".to_owned(),
+            started_at,
+            timestamp: root.timestamp,
+            context_count,
+            r#type: Self::TYPE.into(),
+        }
+    }
+
+    pub fn id_for(event_key: &Id) -> String {
+        format!("{}::{event_key}", Self::TYPE)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::id::prefix::IdPrefix;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_context_summary_serialization() {
+        let mut root = RootContext::new(Id::now(IdPrefix::EventKey));
+        root.timestamp = Utc.timestamp_millis_opt(1_700_000_000_000).unwrap();
+        let summary = ContextSummary::new(&root, root.timestamp, 4);
+        let value = serde_json::to_value(&summary).unwrap();
+
+        assert_eq!(value["type"], "summary");
+        assert_eq!(value["stage"], "Finished");
+        assert_eq!(value["status"], "Succeeded");
+        assert_eq!(value["contextCount"], 4);
+        assert_eq!(
+            value["_id"],
+            format!("summary::{}", root.event_key).as_str()
+        );
+        let deserialized: ContextSummary = serde_json::from_value(value).unwrap();
+        assert_eq!(deserialized, summary);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    id::{prefix::IdPrefix, Id},
+    id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator},
     prelude::{
         configuration::environment::Environment,
         event::Event,
@@ -8,7 +8,6 @@ use crate::{
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -31,20 +30,31 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn new(event: &Event, key: String, input: String, output: String, state: String) -> Self {
-        let ts = Utc::now();
-        let id = Id::new(IdPrefix::Transaction, ts);
+        Self::new_with_generator(event, key, input, output, state, &SystemIdGenerator)
+    }
+
+    pub fn new_with_generator(
+        event: &Event,
+        key: String,
+        input: String,
+        output: String,
+        state: String,
+        generator: &dyn IdGenerator,
+    ) -> Self {
+        let ts = generator.now();
+        let id = generator.id_at(IdPrefix::Transaction, ts);
         Transaction {
             id,
             tx_key: key,
             input,
             output,
-            txn: Uuid::new_v4().simple().to_string(),
+            txn: generator.uuid().simple().to_string(),
             environment: event.environment,
             state,
             started_at: ts,
             ownership: event.ownership.clone(),
             event_id: event.id,
-            record_metadata: Default::default(),
+            record_metadata: RecordMetadata::created_at(ts),
         }
     }
 
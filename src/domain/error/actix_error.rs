@@ -1,6 +1,11 @@
+use crate::prelude::{
+    configuration::environment::{Environment, ENVIRONMENT_HEADER},
+    encrypted_access_key::{EncryptedAccessKey, ACCESS_KEY_HEADER},
+};
 use crate::IntegrationOSError;
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{dev::Payload, FromRequest, HttpRequest, HttpResponse, ResponseError};
+use std::future::{ready, Ready};
 
 impl<'a> From<&'a IntegrationOSError> for StatusCode {
     fn from(error: &'a IntegrationOSError) -> Self {
@@ -31,3 +36,36 @@ impl<'a> ResponseError for &'a IntegrationOSError {
         builder.json(&self.to_owned().as_application().as_json())
     }
 }
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Reads the access key from the `x-integrationos-secret` header
+impl FromRequest for EncryptedAccessKey<'static> {
+    type Error = IntegrationOSError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(EncryptedAccessKey::from_header(header(
+            req,
+            ACCESS_KEY_HEADER,
+        )))
+    }
+}
+
+/// Reads the environment from the `x-integrationos-environment` header, falling back to
+/// the one of the access key
+impl FromRequest for Environment {
+    type Error = IntegrationOSError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Environment::from_headers(
+            header(req, ENVIRONMENT_HEADER),
+            header(req, ACCESS_KEY_HEADER),
+        ))
+    }
+}
@@ -1,9 +1,15 @@
+use crate::prelude::{
+    configuration::environment::{Environment, ENVIRONMENT_HEADER},
+    encrypted_access_key::{EncryptedAccessKey, ACCESS_KEY_HEADER},
+};
 use crate::IntegrationOSError;
 use axum::{
+    async_trait,
+    extract::FromRequestParts,
     response::{IntoResponse, Response},
     Json,
 };
-use http::StatusCode;
+use http::{request::Parts, StatusCode};
 
 impl IntoResponse for IntegrationOSError {
     fn into_response(self) -> Response {
@@ -20,3 +26,34 @@ impl<'a> IntoResponse for &'a IntegrationOSError {
         (status, Json(body)).into_response()
     }
 }
+
+fn header<'a>(parts: &'a Parts, name: &str) -> Option<&'a str> {
+    parts
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Reads the access key from the `x-integrationos-secret` header, as the actix extractor
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for EncryptedAccessKey<'static> {
+    type Rejection = IntegrationOSError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        EncryptedAccessKey::from_header(header(parts, ACCESS_KEY_HEADER))
+    }
+}
+
+/// Reads the environment from the `x-integrationos-environment` header, falling back to
+/// the one of the access key, as the actix extractor
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Environment {
+    type Rejection = IntegrationOSError;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Environment::from_headers(
+            header(parts, ENVIRONMENT_HEADER),
+            header(parts, ACCESS_KEY_HEADER),
+        )
+    }
+}
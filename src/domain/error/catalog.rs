@@ -0,0 +1,152 @@
+use super::{ApplicationError, ErrorMeta, IntegrationOSError, InternalError};
+use crate::policies::ErrorClass;
+use http::StatusCode;
+use serde::Serialize;
+
+/// One kind of [`IntegrationOSError`], as documented for API clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorCatalogEntry {
+    pub code: u16,
+    /// `type` of the serialized error, e.g. `NotFound`
+    pub r#type: String,
+    /// Key without subtype, e.g. `err::application::not_found`. Errors may append a
+    /// `::{subtype}` to it.
+    pub key: String,
+    pub status: u16,
+    /// Whether the same request may succeed later, following the classes retried by
+    /// [`RetryPolicy`](crate::policies::RetryPolicy)
+    pub retryable: bool,
+    pub description: String,
+}
+
+/// Every error code the crate can produce, for API documentation and client SDKs
+pub struct ErrorCatalog;
+
+fn description(error: &IntegrationOSError) -> &'static str {
+    match error {
+        IntegrationOSError::Internal(error) => match error {
+            InternalError::UnknownError { .. } => "An unexpected error occurred",
+            InternalError::UniqueFieldViolation { .. } => {
+                "A record with the same unique field already exists"
+            }
+            InternalError::Timeout { .. } => "An operation did not complete in time",
+            InternalError::ConnectionError { .. } => "A downstream service could not be reached",
+            InternalError::KeyNotFound { .. } => "A record or key does not exist",
+            InternalError::InvalidArgument { .. } => "An argument is invalid",
+            InternalError::IOErr { .. } => "Reading from or writing to a backend failed",
+            InternalError::EncryptionError { .. } => "A value could not be encrypted",
+            InternalError::DecryptionError { .. } => "A value could not be decrypted",
+            InternalError::ConfigurationError { .. } => "A configuration is missing or invalid",
+            InternalError::SerializeError { .. } => "A value could not be serialized",
+            InternalError::DeserializeError { .. } => "A value could not be deserialized",
+            InternalError::ScriptError { .. } => "A JavaScript function failed to run",
+        },
+        IntegrationOSError::Application(error) => match error {
+            ApplicationError::BadRequest { .. } => "The request is malformed or invalid",
+            ApplicationError::Conflict { .. } => "The request conflicts with the current state",
+            ApplicationError::Forbidden { .. } => "The caller may not perform this request",
+            ApplicationError::InternalServerError { .. } => "The server failed unexpectedly",
+            ApplicationError::MethodNotAllowed { .. } => "The method is not supported here",
+            ApplicationError::NotFound { .. } => "The requested resource does not exist",
+            ApplicationError::NotImplemented { .. } => "The operation is not implemented",
+            ApplicationError::FailedDependency { .. } => "A platform or dependency failed",
+            ApplicationError::ServiceUnavailable { .. } => "The service is temporarily down",
+            ApplicationError::TooManyRequests { .. } => "A rate limit was exceeded",
+            ApplicationError::Unauthorized { .. } => "The credentials are missing or invalid",
+            ApplicationError::UnprocessableEntity { .. } => {
+                "The request is well formed but cannot be processed"
+            }
+        },
+    }
+}
+
+impl ErrorCatalog {
+    fn errors() -> Vec<IntegrationOSError> {
+        vec![
+            InternalError::unknown("", None),
+            InternalError::unique_field_violation("", None),
+            InternalError::timeout("", None),
+            InternalError::connection_error("", None),
+            InternalError::key_not_found("", None),
+            InternalError::invalid_argument("", None),
+            InternalError::io_err("", None),
+            InternalError::encryption_error("", None),
+            InternalError::decryption_error("", None),
+            InternalError::configuration_error("", None),
+            InternalError::script_error("", None),
+            InternalError::serialize_error("", None),
+            InternalError::deserialize_error("", None),
+            ApplicationError::bad_request("", None),
+            ApplicationError::conflict("", None),
+            ApplicationError::forbidden("", None),
+            ApplicationError::internal_server_error("", None),
+            ApplicationError::method_not_allowed("", None),
+            ApplicationError::not_found("", None),
+            ApplicationError::not_implemented("", None),
+            ApplicationError::failed_dependency("", None),
+            ApplicationError::service_unavailable("", None),
+            ApplicationError::too_many_requests("", None),
+            ApplicationError::unauthorized("", None),
+            ApplicationError::unprocessable_entity("", None),
+        ]
+    }
+
+    /// Entries ordered by code
+    pub fn entries() -> Vec<ErrorCatalogEntry> {
+        let mut entries = Self::errors()
+            .iter()
+            .map(|error| ErrorCatalogEntry {
+                code: error.code().as_u16(),
+                r#type: error.as_ref().to_owned(),
+                key: error.key().to_string(),
+                status: StatusCode::from(error).as_u16(),
+                retryable: ErrorClass::of(error).is_some(),
+                description: description(error).to_owned(),
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.code);
+        entries
+    }
+
+    pub fn export() -> serde_json::Value {
+        serde_json::json!({ "errors": Self::entries() })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_catalog_covers_every_code_once() {
+        let entries = ErrorCatalog::entries();
+        assert_eq!(entries.len(), 25);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.code)
+                .collect::<HashSet<_>>()
+                .len(),
+            entries.len()
+        );
+
+        let not_found = entries.iter().find(|entry| entry.code == 2005).unwrap();
+        assert_eq!(not_found.r#type, "NotFound");
+        assert_eq!(not_found.key, "err::application::not_found");
+        assert_eq!(not_found.status, 404);
+        assert!(!not_found.retryable);
+        assert!(
+            entries
+                .iter()
+                .find(|entry| entry.code == 1002)
+                .unwrap()
+                .retryable
+        );
+
+        let exported = ErrorCatalog::export();
+        assert_eq!(exported["errors"][0]["code"], 1000);
+        assert_eq!(exported["errors"][0]["key"], "err::internal::unknown");
+    }
+}
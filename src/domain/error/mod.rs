@@ -2,9 +2,19 @@
 pub mod actix_error;
 #[cfg(feature = "axum-error")]
 pub mod axum_error;
+#[cfg(feature = "backend")]
+pub mod catalog;
+pub mod stable_code;
+#[cfg(feature = "tonic-error")]
+pub mod tonic_error;
+
+#[cfg(feature = "backend")]
+pub use catalog::{ErrorCatalog, ErrorCatalogEntry};
+pub use stable_code::StableErrorCode;
 
 use crate::prelude::StringExt;
 use http::StatusCode;
+#[cfg(feature = "backend")]
 use mongodb::error::WriteFailure;
 use serde::Serialize;
 use std::convert::AsRef;
@@ -716,6 +726,7 @@ impl<'a> From<&'a IntegrationOSError> for StatusCode {
     }
 }
 
+#[cfg(feature = "backend")]
 impl From<mongodb::error::Error> for IntegrationOSError {
     fn from(err: mongodb::error::Error) -> Self {
         match *err.kind {
@@ -956,7 +967,10 @@ impl IntegrationOSError {
                 "code": self.code().as_u16(),
                 "status": StatusCode::from(self).as_u16(),
                 "key": self.key().to_string(),
-                "message": self.message().to_string()
+                "message": self.message().to_string(),
+                "errorCode": self.stable_code(),
+                "retryable": self.is_retryable(),
+                "meta": self.meta()
             }
         })
     }
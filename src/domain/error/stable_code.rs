@@ -0,0 +1,317 @@
+use super::{ApplicationError, IntegrationOSError, InternalError};
+use crate::prelude::StringExt;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use strum::{AsRefStr, Display, EnumIter, EnumString, IntoStaticStr};
+
+pub const CONNECTION_SUBTYPE: &str = "connection";
+pub const CONNECTION_INACTIVE_SUBTYPE: &str = "connection_inactive";
+pub const EXPIRED_ACCESS_KEY_SUBTYPE: &str = "expired_access_key";
+pub const BUDGET_EXCEEDED_SUBTYPE: &str = "budget_exceeded";
+
+/// Machine readable code of an [`IntegrationOSError`], stable across releases so clients
+/// can branch on it. Unlike [`ErrorCode`](super::ErrorCode) and
+/// [`ErrorKey`](super::ErrorKey), some subtypes get a code of their own, e.g. a missing
+/// connection is `ERR_CONN_NOT_FOUND` rather than `ERR_NOT_FOUND`. Codes are only ever
+/// added, never renamed.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    AsRefStr,
+    Display,
+    EnumString,
+    EnumIter,
+    IntoStaticStr,
+)]
+pub enum StableErrorCode {
+    #[strum(serialize = "ERR_UNKNOWN")]
+    Unknown,
+    #[strum(serialize = "ERR_UNIQUE_VIOLATION")]
+    UniqueViolation,
+    #[strum(serialize = "ERR_TIMEOUT")]
+    Timeout,
+    #[strum(serialize = "ERR_CONNECTION_FAILED")]
+    ConnectionFailed,
+    #[strum(serialize = "ERR_KEY_NOT_FOUND")]
+    KeyNotFound,
+    #[strum(serialize = "ERR_INVALID_ARGUMENT")]
+    InvalidArgument,
+    #[strum(serialize = "ERR_IO")]
+    Io,
+    #[strum(serialize = "ERR_ENCRYPTION")]
+    Encryption,
+    #[strum(serialize = "ERR_DECRYPTION")]
+    Decryption,
+    #[strum(serialize = "ERR_CONFIGURATION")]
+    Configuration,
+    #[strum(serialize = "ERR_SCRIPT")]
+    Script,
+    #[strum(serialize = "ERR_SERIALIZATION")]
+    Serialization,
+    #[strum(serialize = "ERR_DESERIALIZATION")]
+    Deserialization,
+    #[strum(serialize = "ERR_BAD_REQUEST")]
+    BadRequest,
+    #[strum(serialize = "ERR_CONFLICT")]
+    Conflict,
+    #[strum(serialize = "ERR_FORBIDDEN")]
+    Forbidden,
+    #[strum(serialize = "ERR_INTERNAL")]
+    Internal,
+    #[strum(serialize = "ERR_METHOD_NOT_ALLOWED")]
+    MethodNotAllowed,
+    #[strum(serialize = "ERR_NOT_FOUND")]
+    NotFound,
+    #[strum(serialize = "ERR_CONN_NOT_FOUND")]
+    ConnNotFound,
+    #[strum(serialize = "ERR_NOT_IMPLEMENTED")]
+    NotImplemented,
+    #[strum(serialize = "ERR_DEPENDENCY_FAILED")]
+    DependencyFailed,
+    #[strum(serialize = "ERR_UNAVAILABLE")]
+    Unavailable,
+    #[strum(serialize = "ERR_RATE_LIMITED")]
+    RateLimited,
+    #[strum(serialize = "ERR_BUDGET_EXCEEDED")]
+    BudgetExceeded,
+    #[strum(serialize = "ERR_UNAUTHORIZED")]
+    Unauthorized,
+    #[strum(serialize = "ERR_ACCESS_KEY_EXPIRED")]
+    AccessKeyExpired,
+    #[strum(serialize = "ERR_UNPROCESSABLE")]
+    Unprocessable,
+    #[strum(serialize = "ERR_CONN_INACTIVE")]
+    ConnInactive,
+}
+
+impl StableErrorCode {
+    /// Whether the same request may succeed later, following the error classes retried
+    /// by `RetryPolicy`. An exceeded budget is only reset the next month, so it isn't.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            StableErrorCode::Unknown
+                | StableErrorCode::Timeout
+                | StableErrorCode::ConnectionFailed
+                | StableErrorCode::Io
+                | StableErrorCode::Internal
+                | StableErrorCode::Unavailable
+                | StableErrorCode::RateLimited
+        )
+    }
+}
+
+impl Serialize for StableErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for StableErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        StableErrorCode::from_str(&code)
+            .map_err(|_| D::Error::custom(format!("Unknown error code {code}")))
+    }
+}
+
+impl InternalError {
+    pub fn subtype(&self) -> Option<&str> {
+        match self {
+            InternalError::UnknownError { subtype, .. }
+            | InternalError::UniqueFieldViolation { subtype, .. }
+            | InternalError::Timeout { subtype, .. }
+            | InternalError::ConnectionError { subtype, .. }
+            | InternalError::KeyNotFound { subtype, .. }
+            | InternalError::InvalidArgument { subtype, .. }
+            | InternalError::IOErr { subtype, .. }
+            | InternalError::EncryptionError { subtype, .. }
+            | InternalError::DecryptionError { subtype, .. }
+            | InternalError::ConfigurationError { subtype, .. }
+            | InternalError::SerializeError { subtype, .. }
+            | InternalError::DeserializeError { subtype, .. }
+            | InternalError::ScriptError { subtype, .. } => subtype.as_deref(),
+        }
+    }
+
+    pub fn stable_code(&self) -> StableErrorCode {
+        match self {
+            InternalError::UnknownError { .. } => StableErrorCode::Unknown,
+            InternalError::UniqueFieldViolation { .. } => StableErrorCode::UniqueViolation,
+            InternalError::Timeout { .. } => StableErrorCode::Timeout,
+            InternalError::ConnectionError { .. } => StableErrorCode::ConnectionFailed,
+            InternalError::KeyNotFound { subtype, .. } if is(subtype, CONNECTION_SUBTYPE) => {
+                StableErrorCode::ConnNotFound
+            }
+            InternalError::KeyNotFound { .. } => StableErrorCode::KeyNotFound,
+            InternalError::InvalidArgument { .. } => StableErrorCode::InvalidArgument,
+            InternalError::IOErr { .. } => StableErrorCode::Io,
+            InternalError::EncryptionError { .. } => StableErrorCode::Encryption,
+            InternalError::DecryptionError { .. } => StableErrorCode::Decryption,
+            InternalError::ConfigurationError { .. } => StableErrorCode::Configuration,
+            InternalError::ScriptError { .. } => StableErrorCode::Script,
+            InternalError::SerializeError { .. } => StableErrorCode::Serialization,
+            InternalError::DeserializeError { .. } => StableErrorCode::Deserialization,
+        }
+    }
+}
+
+impl ApplicationError {
+    /// `NotFound` with the connection subtype, `ERR_CONN_NOT_FOUND` for clients
+    pub fn connection_not_found(message: &str) -> IntegrationOSError {
+        Self::not_found(message, Some(CONNECTION_SUBTYPE))
+    }
+
+    pub fn subtype(&self) -> Option<&str> {
+        match self {
+            ApplicationError::BadRequest { subtype, .. }
+            | ApplicationError::Conflict { subtype, .. }
+            | ApplicationError::Forbidden { subtype, .. }
+            | ApplicationError::InternalServerError { subtype, .. }
+            | ApplicationError::MethodNotAllowed { subtype, .. }
+            | ApplicationError::NotFound { subtype, .. }
+            | ApplicationError::NotImplemented { subtype, .. }
+            | ApplicationError::FailedDependency { subtype, .. }
+            | ApplicationError::ServiceUnavailable { subtype, .. }
+            | ApplicationError::TooManyRequests { subtype, .. }
+            | ApplicationError::Unauthorized { subtype, .. }
+            | ApplicationError::UnprocessableEntity { subtype, .. } => subtype.as_deref(),
+        }
+    }
+
+    pub fn stable_code(&self) -> StableErrorCode {
+        match self {
+            ApplicationError::BadRequest { .. } => StableErrorCode::BadRequest,
+            ApplicationError::Conflict { .. } => StableErrorCode::Conflict,
+            ApplicationError::Forbidden { subtype, .. }
+                if is(subtype, CONNECTION_INACTIVE_SUBTYPE) =>
+            {
+                StableErrorCode::ConnInactive
+            }
+            ApplicationError::Forbidden { .. } => StableErrorCode::Forbidden,
+            ApplicationError::InternalServerError { .. } => StableErrorCode::Internal,
+            ApplicationError::MethodNotAllowed { .. } => StableErrorCode::MethodNotAllowed,
+            ApplicationError::NotFound { subtype, .. } if is(subtype, CONNECTION_SUBTYPE) => {
+                StableErrorCode::ConnNotFound
+            }
+            ApplicationError::NotFound { .. } => StableErrorCode::NotFound,
+            ApplicationError::NotImplemented { .. } => StableErrorCode::NotImplemented,
+            ApplicationError::FailedDependency { .. } => StableErrorCode::DependencyFailed,
+            ApplicationError::ServiceUnavailable { .. } => StableErrorCode::Unavailable,
+            ApplicationError::TooManyRequests { subtype, .. }
+                if is(subtype, BUDGET_EXCEEDED_SUBTYPE) =>
+            {
+                StableErrorCode::BudgetExceeded
+            }
+            ApplicationError::TooManyRequests { .. } => StableErrorCode::RateLimited,
+            ApplicationError::Unauthorized { subtype, .. }
+                if is(subtype, EXPIRED_ACCESS_KEY_SUBTYPE) =>
+            {
+                StableErrorCode::AccessKeyExpired
+            }
+            ApplicationError::Unauthorized { .. } => StableErrorCode::Unauthorized,
+            ApplicationError::UnprocessableEntity { .. } => StableErrorCode::Unprocessable,
+        }
+    }
+}
+
+/// Subtypes passed to the constructors are snake cased, those of
+/// [`IntegrationOSError::from_err_code`] are not
+fn is(subtype: &Option<String>, expected: &str) -> bool {
+    subtype
+        .as_ref()
+        .is_some_and(|subtype| subtype.clone().snake_case() == expected)
+}
+
+impl IntegrationOSError {
+    pub fn stable_code(&self) -> StableErrorCode {
+        match self {
+            IntegrationOSError::Internal(e) => e.stable_code(),
+            IntegrationOSError::Application(e) => e.stable_code(),
+        }
+    }
+
+    pub fn subtype(&self) -> Option<&str> {
+        match self {
+            IntegrationOSError::Internal(e) => e.subtype(),
+            IntegrationOSError::Application(e) => e.subtype(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.stable_code().is_retryable()
+    }
+
+    /// Details of the error beyond its code, serialized as `meta` in error bodies
+    pub fn meta(&self) -> BTreeMap<String, String> {
+        self.subtype()
+            .map(|subtype| BTreeMap::from([("subtype".to_owned(), subtype.to_owned())]))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use http::StatusCode;
+    use serde_json::json;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_stable_codes() {
+        assert_eq!(
+            ApplicationError::connection_not_found("gone").stable_code(),
+            StableErrorCode::ConnNotFound
+        );
+        assert_eq!(
+            ApplicationError::not_found("gone", None).stable_code(),
+            StableErrorCode::NotFound
+        );
+        assert_eq!(
+            IntegrationOSError::from_err_code(StatusCode::TOO_MANY_REQUESTS, "slow down", None)
+                .stable_code(),
+            StableErrorCode::RateLimited
+        );
+        assert_eq!(
+            ApplicationError::too_many_requests("over", Some("budget_exceeded")).stable_code(),
+            StableErrorCode::BudgetExceeded
+        );
+
+        let codes: Vec<String> = StableErrorCode::iter().map(|c| c.to_string()).collect();
+        assert!(codes.iter().all(|code| code.starts_with("ERR_")));
+        for code in StableErrorCode::iter() {
+            let serialized = serde_json::to_value(code).unwrap();
+            assert_eq!(
+                serde_json::from_value::<StableErrorCode>(serialized).unwrap(),
+                code
+            );
+        }
+
+        assert!(InternalError::timeout("took too long", None).is_retryable());
+        assert!(
+            !ApplicationError::too_many_requests("over", Some("budget_exceeded")).is_retryable()
+        );
+        let error = ApplicationError::connection_not_found("No connection conn::1");
+        assert!(!error.is_retryable());
+        assert_eq!(
+            error.as_json()["passthrough"],
+            json!({
+                "type": "NotFound",
+                "code": 2005,
+                "status": 404,
+                "key": "err::application::not_found::connection",
+                "message": "No connection conn::1",
+                "errorCode": "ERR_CONN_NOT_FOUND",
+                "retryable": false,
+                "meta": { "subtype": "connection" }
+            })
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use super::stable_code::StableErrorCode;
+use crate::IntegrationOSError;
+use bytes::Bytes;
+use tonic::{metadata::MetadataValue, Code, Status};
+
+pub const ERROR_CODE_METADATA: &str = "x-integrationos-error-code";
+
+impl StableErrorCode {
+    pub fn grpc_code(&self) -> Code {
+        match self {
+            StableErrorCode::Unknown => Code::Unknown,
+            StableErrorCode::UniqueViolation | StableErrorCode::Conflict => Code::AlreadyExists,
+            StableErrorCode::Timeout => Code::DeadlineExceeded,
+            StableErrorCode::ConnectionFailed
+            | StableErrorCode::Unavailable
+            | StableErrorCode::DependencyFailed => Code::Unavailable,
+            StableErrorCode::KeyNotFound
+            | StableErrorCode::NotFound
+            | StableErrorCode::ConnNotFound => Code::NotFound,
+            StableErrorCode::InvalidArgument
+            | StableErrorCode::Serialization
+            | StableErrorCode::Deserialization
+            | StableErrorCode::BadRequest => Code::InvalidArgument,
+            StableErrorCode::Unprocessable | StableErrorCode::ConnInactive => {
+                Code::FailedPrecondition
+            }
+            StableErrorCode::Forbidden => Code::PermissionDenied,
+            StableErrorCode::Unauthorized | StableErrorCode::AccessKeyExpired => {
+                Code::Unauthenticated
+            }
+            StableErrorCode::MethodNotAllowed | StableErrorCode::NotImplemented => {
+                Code::Unimplemented
+            }
+            StableErrorCode::RateLimited | StableErrorCode::BudgetExceeded => {
+                Code::ResourceExhausted
+            }
+            StableErrorCode::Io
+            | StableErrorCode::Encryption
+            | StableErrorCode::Decryption
+            | StableErrorCode::Configuration
+            | StableErrorCode::Script
+            | StableErrorCode::Internal => Code::Internal,
+        }
+    }
+}
+
+/// Same as the actix and axum responses, internal errors are converted to application
+/// ones first. The JSON body goes in the status details and the stable code in the
+/// `x-integrationos-error-code` metadata.
+impl From<&IntegrationOSError> for Status {
+    fn from(error: &IntegrationOSError) -> Self {
+        let error = error.as_application();
+        let code = error.stable_code();
+        let details = Bytes::from(error.as_json().to_string());
+        let mut status = Status::with_details(code.grpc_code(), error.to_string(), details);
+        status
+            .metadata_mut()
+            .insert(ERROR_CODE_METADATA, MetadataValue::from_static(code.into()));
+        status
+    }
+}
+
+impl From<IntegrationOSError> for Status {
+    fn from(error: IntegrationOSError) -> Self {
+        (&error).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ApplicationError;
+
+    #[test]
+    fn test_status_from_error() {
+        let status = Status::from(ApplicationError::too_many_requests("Slow down", None));
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(
+            status.metadata().get(ERROR_CODE_METADATA).unwrap(),
+            "ERR_RATE_LIMITED"
+        );
+        let details: serde_json::Value = serde_json::from_slice(status.details()).unwrap();
+        assert_eq!(details["passthrough"]["errorCode"], "ERR_RATE_LIMITED");
+        assert_eq!(details["passthrough"]["retryable"], true);
+    }
+}
@@ -0,0 +1,51 @@
+use super::Event;
+use crate::{id::Id, prelude::shared::ownership::Ownership};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Pointer left behind when an event is moved out of the events collection to the
+/// object store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedEvent {
+    /// Id of the archived event
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub ownership: Ownership,
+    pub object_key: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub arrived_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub archived_at: DateTime<Utc>,
+    pub byte_length: usize,
+}
+
+impl ArchivedEvent {
+    pub fn new(
+        event: &Event,
+        object_key: String,
+        byte_length: usize,
+        archived_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: event.id,
+            ownership: event.ownership.clone(),
+            object_key,
+            arrived_at: event.arrived_at,
+            archived_at,
+            byte_length,
+        }
+    }
+
+    /// Key the event is stored under, grouping events by ownership and arrival day so
+    /// lifecycle rules of the bucket can target them
+    pub fn object_key(prefix: &str, event: &Event) -> String {
+        format!(
+            "{}/{}/{}/{}.json",
+            prefix.trim_end_matches('/'),
+            event.ownership.id,
+            event.arrived_at.format("%Y/%m/%d"),
+            event.id
+        )
+    }
+}
@@ -0,0 +1,59 @@
+use super::event_with_context::EventWithContext;
+use crate::{
+    id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator},
+    prelude::shared::{ownership::Ownership, record_metadata::RecordMetadata},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// An event that kept failing, parked with its context until someone replays it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeadLetter {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub event_key: Id,
+    pub ownership: Ownership,
+    pub failure_count: u32,
+    pub last_error: String,
+    /// The payload as it was queued, replayed unchanged
+    pub payload: EventWithContext,
+    /// When the entry was last put back onto the queue, unset while it is parked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replayed_at: Option<i64>,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl DeadLetter {
+    pub fn new(payload: EventWithContext, failure_count: u32, last_error: String) -> Self {
+        Self::new_with_generator(payload, failure_count, last_error, &SystemIdGenerator)
+    }
+
+    pub fn new_with_generator(
+        payload: EventWithContext,
+        failure_count: u32,
+        last_error: String,
+        generator: &dyn IdGenerator,
+    ) -> Self {
+        let id = generator.id(IdPrefix::DeadLetter);
+        Self {
+            id,
+            event_key: payload.event.id,
+            ownership: payload.event.ownership.clone(),
+            failure_count,
+            last_error,
+            payload,
+            replayed_at: None,
+            record_metadata: RecordMetadata::created_at(id.time()),
+        }
+    }
+
+    pub fn is_replayed(&self) -> bool {
+        self.replayed_at.is_some()
+    }
+
+    pub fn mark_replayed(&mut self) {
+        self.replayed_at = Some(Utc::now().timestamp_millis());
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     prelude::{
         configuration::environment::Environment,
         connection::connection_definition::{ConnectionDefinitionType, Paths},
-        shared::{ownership::Ownership, record_metadata::RecordMetadata},
+        shared::{ownership::Ownership, record_metadata::RecordMetadata, redacted::Redacted},
     },
 };
 
@@ -24,7 +24,7 @@ pub struct EventAccess {
     pub ownership: Ownership,
     pub paths: Paths,
     #[cfg_attr(feature = "dummy", dummy(faker = "8..50"))]
-    pub access_key: String,
+    pub access_key: Redacted<String>,
     #[serde(default = "throughput_default")]
     pub throughput: u64,
     pub environment: Environment,
@@ -0,0 +1,105 @@
+use super::{duplicates::Duplicates, Event};
+use crate::{
+    id::{IdGenerator, SystemIdGenerator},
+    prelude::access_key::{encrypted_access_key::EncryptedAccessKey, AccessKey},
+};
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+/// Builds an [`Event`] received with an access key, defaulting to an empty body without
+/// headers. Topic, hashes and ownership are derived from the access key as in
+/// [`Event::new`].
+pub struct EventBuilder<'a> {
+    access_key: &'a AccessKey,
+    encrypted_access_key: &'a EncryptedAccessKey<'a>,
+    name: String,
+    headers: HeaderMap,
+    body: String,
+    idempotency_key: Option<String>,
+    duplicates: Option<Duplicates>,
+}
+
+impl Event {
+    pub fn builder<'a>(
+        access_key: &'a AccessKey,
+        encrypted_access_key: &'a EncryptedAccessKey<'a>,
+        name: impl Into<String>,
+    ) -> EventBuilder<'a> {
+        EventBuilder::new(access_key, encrypted_access_key, name)
+    }
+}
+
+impl<'a> EventBuilder<'a> {
+    pub fn new(
+        access_key: &'a AccessKey,
+        encrypted_access_key: &'a EncryptedAccessKey<'a>,
+        name: impl Into<String>,
+    ) -> Self {
+        Self {
+            access_key,
+            encrypted_access_key,
+            name: name.into(),
+            headers: HeaderMap::new(),
+            body: String::new(),
+            idempotency_key: None,
+            duplicates: None,
+        }
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Adds a header, ignored when the name or value is invalid
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Serializes `body` as the JSON body of the event
+    pub fn json_body(self, body: &serde_json::Value) -> Self {
+        self.body(body.to_string())
+    }
+
+    pub fn idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(idempotency_key.into());
+        self
+    }
+
+    pub fn duplicates(mut self, duplicates: Duplicates) -> Self {
+        self.duplicates = Some(duplicates);
+        self
+    }
+
+    pub fn build(self) -> Event {
+        self.build_with(&SystemIdGenerator)
+    }
+
+    pub fn build_with(self, generator: &dyn IdGenerator) -> Event {
+        let mut event = Event::new_with_generator(
+            self.access_key,
+            self.encrypted_access_key,
+            &self.name,
+            self.headers,
+            self.body,
+            generator,
+        );
+        if let Some(idempotency_key) = self.idempotency_key {
+            event = event.with_idempotency_key(idempotency_key);
+        }
+        if let Some(duplicates) = self.duplicates {
+            event = event.add_duplicates(duplicates);
+        }
+        event
+    }
+}
@@ -1,15 +1,70 @@
 use super::Event;
-use crate::RootContext;
+use crate::{
+    prelude::shared::trace_context::TraceContext, IntegrationOSError, InternalError, MessageBusExt,
+    RootContext,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventWithContext {
     pub event: Event,
     pub context: RootContext,
+    /// Trace the consumer's spans continue, the event's unless the producer set its own
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub trace_context: Option<TraceContext>,
 }
 
 impl EventWithContext {
     pub fn new(event: Event, context: RootContext) -> Self {
-        Self { event, context }
+        let context = match context.correlation_id {
+            Some(_) => context,
+            None => {
+                let correlation_id = event.correlation_id.clone();
+                context.with_correlation_id(correlation_id)
+            }
+        };
+        let trace_context = event.trace_context.clone();
+        Self {
+            event,
+            context,
+            trace_context,
+        }
+    }
+
+    pub fn with_trace_context(mut self, trace_context: Option<TraceContext>) -> Self {
+        self.trace_context = trace_context;
+        self
+    }
+
+    /// Sets the trace context to the active OpenTelemetry span before publishing, keeping
+    /// the current one outside of a span
+    #[cfg(feature = "otel")]
+    pub fn inject_current_trace(&mut self) {
+        if let Some(trace_context) = TraceContext::current() {
+            self.trace_context = Some(trace_context);
+        }
+    }
+
+    /// Context to parent the consumer's spans to, empty when the payload carries no trace
+    #[cfg(feature = "otel")]
+    pub fn extract_trace(&self) -> opentelemetry::Context {
+        self.trace_context
+            .as_ref()
+            .map(TraceContext::to_context)
+            .unwrap_or_default()
+    }
+
+    /// Serialized form consumers read off the queue
+    pub fn to_payload(&self) -> Result<Vec<u8>, IntegrationOSError> {
+        serde_json::to_vec(self)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("event_with_context")))
+    }
+
+    pub async fn publish<B: MessageBusExt + Sync>(
+        &self,
+        bus: &B,
+        topic: &str,
+    ) -> Result<(), IntegrationOSError> {
+        bus.publish(topic, &self.to_payload()?).await
     }
 }
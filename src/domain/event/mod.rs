@@ -1,15 +1,19 @@
+pub mod archived_event;
+pub mod dead_letter;
 pub mod duplicates;
 pub mod event_access;
+pub mod event_builder;
 pub mod event_response;
 pub mod event_state;
 pub mod event_with_context;
 pub mod hashes;
+pub mod storm;
 
 use chrono::{DateTime, SubsecRound, Utc};
 use http::HeaderMap;
 use serde::{Deserialize, Serialize};
 
-use crate::id::{prefix::IdPrefix, Id};
+use crate::id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator};
 
 use self::{
     duplicates::Duplicates,
@@ -20,7 +24,10 @@ use self::{
 use super::{
     access_key::{encrypted_access_key::EncryptedAccessKey, AccessKey},
     configuration::environment::Environment,
-    shared::{ownership::Ownership, record_metadata::RecordMetadata},
+    shared::{
+        correlation_id::CorrelationId, ownership::Ownership, record_metadata::RecordMetadata,
+        trace_context::TraceContext,
+    },
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
@@ -48,10 +55,21 @@ pub struct Event {
     pub payload_byte_length: usize,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub duplicates: Option<Duplicates>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub correlation_id: Option<CorrelationId>,
+    /// Key the producer sent to deduplicate retries of the same event, see
+    /// [`IdempotencyGuard`](crate::IdempotencyGuard)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub idempotency_key: Option<String>,
+    /// Trace of the request that created the event, as `traceparent` and `baggage`
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub trace_context: Option<TraceContext>,
     #[serde(flatten, default)]
     pub record_metadata: RecordMetadata,
 }
 
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
 struct IntermediateEventFields<'a> {
     access_key: &'a AccessKey,
     encrypted_access_key: &'a EncryptedAccessKey<'a>,
@@ -71,9 +89,30 @@ impl Event {
         headers: HeaderMap,
         body: String,
     ) -> Self {
-        let timestamp = Utc::now().round_subsecs(3);
-        let id = Id::new(IdPrefix::Event, timestamp);
-        let key = Id::new(IdPrefix::EventKey, timestamp);
+        Self::new_with_generator(
+            access_key,
+            encrypted_access_key,
+            event_name,
+            headers,
+            body,
+            &SystemIdGenerator,
+        )
+    }
+
+    /// Event whose arrival time, ids and correlation id come from `generator`
+    pub fn new_with_generator(
+        access_key: &AccessKey,
+        encrypted_access_key: &EncryptedAccessKey,
+        event_name: &str,
+        headers: HeaderMap,
+        body: String,
+        generator: &dyn IdGenerator,
+    ) -> Self {
+        let timestamp = generator.now().round_subsecs(3);
+        let id = generator.id_at(IdPrefix::Event, timestamp);
+        let key = generator.id_at(IdPrefix::EventKey, timestamp);
+        let correlation_id = CorrelationId::from_headers(&headers)
+            .unwrap_or_else(|| CorrelationId::from(generator.uuid().simple().to_string()));
         let fields = IntermediateEventFields {
             access_key,
             encrypted_access_key,
@@ -84,7 +123,15 @@ impl Event {
             id,
             key,
         };
-        Self::new_with_timestamp_and_ids(fields)
+        Self {
+            correlation_id: Some(correlation_id),
+            ..Self::new_with_timestamp_and_ids(fields)
+        }
+    }
+
+    pub fn with_idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(idempotency_key.into());
+        self
     }
 
     pub fn add_duplicates(mut self, duplicates: Duplicates) -> Self {
@@ -115,6 +162,14 @@ impl Event {
         let hashes = hashes.get_hashes();
 
         let payload_byte_length = fields.body.len();
+        let correlation_id = CorrelationId::from_headers_or_new(&fields.headers);
+        let idempotency_key = fields
+            .headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned);
+        let trace_context = TraceContext::from_headers(&fields.headers);
         Event {
             id: fields.id,
             key: fields.key,
@@ -133,7 +188,10 @@ impl Event {
             hashes,
             payload_byte_length,
             duplicates: None,
-            record_metadata: Default::default(),
+            correlation_id: Some(correlation_id),
+            idempotency_key,
+            trace_context,
+            record_metadata: RecordMetadata::created_at(fields.timestamp),
         }
     }
 }
@@ -174,6 +232,7 @@ mod test {
             event_object_id_path: None,
             timestamp_path: None,
             parent_access_key: None,
+            expires_at: None,
         },
     });
 
@@ -230,6 +289,39 @@ mod test {
             ]
         );
         assert_eq!(event.payload_byte_length, 11);
+        assert!(event.correlation_id.is_some());
+    }
+
+    #[test]
+    fn test_event_keeps_incoming_correlation_id() {
+        let mut headers = HEADERS.clone();
+        headers.insert(
+            crate::prelude::shared::correlation_id::CORRELATION_ID_HEADER,
+            HeaderValue::from_static("incoming"),
+        );
+        let event = Event::new(
+            &ACCESS_KEY,
+            &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+            "event.received",
+            headers,
+            "hello world".to_owned(),
+        );
+        assert_eq!(event.correlation_id, Some("incoming".into()));
+        assert_eq!(event.idempotency_key, None);
+    }
+
+    #[test]
+    fn test_event_reads_idempotency_key() {
+        let mut headers = HEADERS.clone();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("order-1"));
+        let event = Event::new(
+            &ACCESS_KEY,
+            &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+            "event.received",
+            headers,
+            "hello world".to_owned(),
+        );
+        assert_eq!(event.idempotency_key.as_deref(), Some("order-1"));
     }
 
     #[test]
@@ -0,0 +1,363 @@
+use super::{hashes::HashType, Event};
+use crate::prelude::shared::settings::Settings;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct StormConfig {
+    /// Length of the counting window in seconds
+    pub window: u64,
+    /// Number of identical events per connection allowed inside a window
+    pub threshold: u64,
+    /// How long the breaker stays open once tripped, in seconds
+    pub cooldown: u64,
+    /// Let every nth event through while the breaker is open. 0 drops everything
+    pub sample_every: u64,
+    /// Upper bound of tracked (connection, hash) pairs. Stale ones are evicted at most
+    /// once per window when it's reached, and while every tracked pair is live new pairs
+    /// aren't tracked and get [`StormDecision::Sample`]
+    pub max_tracked: usize,
+}
+
+impl Default for StormConfig {
+    fn default() -> Self {
+        Self {
+            window: 60,
+            threshold: 100,
+            cooldown: 300,
+            sample_every: 50,
+            max_tracked: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StormDecision {
+    Allow,
+    Sample,
+    Throttle,
+}
+
+impl StormDecision {
+    pub fn should_process(&self) -> bool {
+        !matches!(self, Self::Throttle)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StormAlert {
+    pub connection_key: String,
+    pub hash: String,
+    pub count: u64,
+    pub tripped_at: DateTime<Utc>,
+    pub open_until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct StormWindow {
+    started_at: DateTime<Utc>,
+    count: u64,
+    open_until: Option<DateTime<Utc>>,
+    suppressed: u64,
+}
+
+#[derive(Debug, Default)]
+struct StormWindows {
+    windows: HashMap<(String, String), StormWindow>,
+    last_pruned: Option<DateTime<Utc>>,
+}
+
+type StormNotifier = Arc<dyn Fn(&StormAlert) + Send + Sync>;
+
+/// Detects spikes of identical events per connection and opens a breaker for them,
+/// protecting pipelines from misconfigured upstream webhook loops.
+#[derive(Clone, Default)]
+pub struct StormGuard {
+    config: StormConfig,
+    windows: Arc<Mutex<StormWindows>>,
+    notifier: Option<StormNotifier>,
+}
+
+impl Debug for StormGuard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StormGuard")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StormGuard {
+    pub fn new(config: StormConfig) -> Self {
+        Self {
+            config,
+            windows: Default::default(),
+            notifier: None,
+        }
+    }
+
+    pub fn with_notifier<F>(mut self, notifier: F) -> Self
+    where
+        F: Fn(&StormAlert) + Send + Sync + 'static,
+    {
+        self.notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    pub fn check_event(&self, event: &Event) -> StormDecision {
+        let hash = event
+            .hashes
+            .iter()
+            .find(|h| h.r#type == HashType::Body)
+            .map(|h| h.hash.as_str())
+            .unwrap_or(event.body.as_str());
+
+        self.check_at(&event.access_key, hash, event.arrived_at)
+    }
+
+    /// Same as [`StormGuard::check_event`] for an event of a connection with `settings`.
+    /// Connections with [`Settings::skip_dedup`] are always allowed and not counted.
+    pub fn check_event_for(&self, event: &Event, settings: &Settings) -> StormDecision {
+        if settings.skip_dedup {
+            return StormDecision::Allow;
+        }
+        self.check_event(event)
+    }
+
+    pub fn check(&self, connection_key: &str, hash: &str) -> StormDecision {
+        self.check_at(connection_key, hash, Utc::now())
+    }
+
+    pub fn check_at(&self, connection_key: &str, hash: &str, now: DateTime<Utc>) -> StormDecision {
+        let window_length = Duration::seconds(self.config.window as i64);
+        let mut alert = None;
+
+        let decision = {
+            let mut state = match self.windows.lock() {
+                Ok(state) => state,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let StormWindows {
+                windows,
+                last_pruned,
+            } = &mut *state;
+            let key = (connection_key.to_owned(), hash.to_owned());
+
+            if windows.len() >= self.config.max_tracked && !windows.contains_key(&key) {
+                if last_pruned.is_none_or(|pruned| pruned + window_length <= now) {
+                    windows.retain(|_, w| {
+                        w.started_at + window_length > now || w.open_until.is_some_and(|u| u > now)
+                    });
+                    *last_pruned = Some(now);
+                }
+                if windows.len() >= self.config.max_tracked {
+                    return StormDecision::Sample;
+                }
+            }
+
+            let window = windows.entry(key).or_insert_with(|| StormWindow {
+                started_at: now,
+                count: 0,
+                open_until: None,
+                suppressed: 0,
+            });
+
+            if window.started_at + window_length <= now {
+                window.started_at = now;
+                window.count = 0;
+            }
+            window.count += 1;
+
+            if window.open_until.is_some_and(|until| until <= now) {
+                window.open_until = None;
+                window.suppressed = 0;
+            }
+
+            if window.open_until.is_none() && window.count > self.config.threshold {
+                let open_until = now + Duration::seconds(self.config.cooldown as i64);
+                window.open_until = Some(open_until);
+                alert = Some(StormAlert {
+                    connection_key: connection_key.to_owned(),
+                    hash: hash.to_owned(),
+                    count: window.count,
+                    tripped_at: now,
+                    open_until,
+                });
+            }
+
+            match window.open_until {
+                None => StormDecision::Allow,
+                Some(_) => {
+                    window.suppressed += 1;
+                    if self.config.sample_every > 0
+                        && window.suppressed % self.config.sample_every == 0
+                    {
+                        StormDecision::Sample
+                    } else {
+                        StormDecision::Throttle
+                    }
+                }
+            }
+        };
+
+        if let (Some(alert), Some(notifier)) = (alert, &self.notifier) {
+            notifier(&alert);
+        }
+
+        decision
+    }
+
+    pub fn is_open(&self, connection_key: &str, hash: &str) -> bool {
+        let now = Utc::now();
+        match self.windows.lock() {
+            Ok(state) => state
+                .windows
+                .get(&(connection_key.to_owned(), hash.to_owned()))
+                .and_then(|w| w.open_until)
+                .is_some_and(|until| until > now),
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn config() -> StormConfig {
+        StormConfig {
+            window: 10,
+            threshold: 3,
+            cooldown: 30,
+            sample_every: 2,
+            max_tracked: 100,
+        }
+    }
+
+    #[test]
+    fn test_storm_guard_trips_and_samples() {
+        let alerts = Arc::new(AtomicU64::new(0));
+        let counter = alerts.clone();
+        let guard = StormGuard::new(config()).with_notifier(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let now = Utc.timestamp_opt(0, 0).single().unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(guard.check_at("conn", "hash", now), StormDecision::Allow);
+        }
+        assert_eq!(guard.check_at("conn", "hash", now), StormDecision::Throttle);
+        assert_eq!(guard.check_at("conn", "hash", now), StormDecision::Sample);
+        assert_eq!(guard.check_at("conn", "hash", now), StormDecision::Throttle);
+        assert_eq!(alerts.load(Ordering::SeqCst), 1);
+
+        assert_eq!(guard.check_at("conn", "other", now), StormDecision::Allow);
+        assert_eq!(guard.check_at("other", "hash", now), StormDecision::Allow);
+    }
+
+    #[test]
+    fn test_skip_dedup_bypasses_the_guard() {
+        use crate::prelude::access_key::{
+            access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+            encrypted_access_key::EncryptedAccessKey, event_type::EventType, AccessKey,
+        };
+        use crate::prelude::configuration::environment::Environment;
+
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "foo".to_owned(),
+                event_type: "bar".to_owned(),
+                group: "baz".to_owned(),
+                namespace: "qux".to_owned(),
+                event_path: "quux".to_owned(),
+                event_object_id_path: None,
+                timestamp_path: None,
+                parent_access_key: None,
+                expires_at: None,
+            },
+        };
+        let event = Event::new(
+            &access_key,
+            &EncryptedAccessKey::parse("id_test_1_foo").unwrap(),
+            "event.received",
+            Default::default(),
+            "hello".to_owned(),
+        );
+        let guard = StormGuard::new(config());
+        let skip = Settings {
+            skip_dedup: true,
+            ..Default::default()
+        };
+
+        for _ in 0..10 {
+            assert_eq!(guard.check_event_for(&event, &skip), StormDecision::Allow);
+        }
+        for _ in 0..3 {
+            assert_eq!(
+                guard.check_event_for(&event, &Settings::default()),
+                StormDecision::Allow
+            );
+        }
+        assert_eq!(
+            guard.check_event_for(&event, &Settings::default()),
+            StormDecision::Throttle
+        );
+    }
+
+    #[test]
+    fn test_storm_guard_tracks_at_most_max_tracked_pairs() {
+        let guard = StormGuard::new(config());
+        let now = Utc.timestamp_opt(0, 0).single().unwrap();
+        let tracked = || guard.windows.lock().unwrap().windows.len();
+
+        for i in 0..100 {
+            assert_eq!(
+                guard.check_at("conn", &i.to_string(), now),
+                StormDecision::Allow
+            );
+        }
+        for i in 100..250 {
+            assert_eq!(
+                guard.check_at("conn", &i.to_string(), now),
+                StormDecision::Sample
+            );
+        }
+        assert_eq!(tracked(), 100);
+        // Tracked pairs are still counted
+        assert_eq!(guard.check_at("conn", "0", now), StormDecision::Allow);
+
+        // Once the windows are stale they're evicted to make room
+        let later = now + Duration::seconds(10);
+        assert_eq!(guard.check_at("conn", "new", later), StormDecision::Allow);
+        assert_eq!(tracked(), 1);
+    }
+
+    #[test]
+    fn test_storm_guard_closes_after_cooldown() {
+        let guard = StormGuard::new(config());
+        let now = Utc.timestamp_opt(0, 0).single().unwrap();
+
+        for _ in 0..4 {
+            guard.check_at("conn", "hash", now);
+        }
+        assert_eq!(
+            guard.check_at("conn", "hash", now + Duration::seconds(20)),
+            StormDecision::Sample
+        );
+        assert_eq!(
+            guard.check_at("conn", "hash", now + Duration::seconds(31)),
+            StormDecision::Allow
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use super::{prefix::IdPrefix, Id};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::sync::Mutex;
+use uuid::{Builder, Uuid};
+
+/// Source of the time and randomness ids are made of, so constructors taking one build
+/// the same records on every run when given a [`DeterministicIdGenerator`]
+pub trait IdGenerator: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn uuid(&self) -> Uuid;
+
+    fn id_at(&self, prefix: IdPrefix, time: DateTime<Utc>) -> Id {
+        Id::new_with_uuid(prefix, time, self.uuid())
+    }
+
+    fn id(&self, prefix: IdPrefix) -> Id {
+        self.id_at(prefix, self.now())
+    }
+}
+
+/// Wall clock and random v4 uuids, what [`Id::now`] uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemIdGenerator;
+
+impl IdGenerator for SystemIdGenerator {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+#[derive(Debug)]
+struct DeterministicState {
+    rng: u64,
+    time: DateTime<Utc>,
+}
+
+/// Generator for tests and fixtures: its clock starts at a fixed time and moves forward
+/// by `step` on every read, and its uuids come from a seeded sequence. Two generators
+/// with the same seed and clock produce the same ids in the same order.
+#[derive(Debug)]
+pub struct DeterministicIdGenerator {
+    seed: u64,
+    start: DateTime<Utc>,
+    step: Duration,
+    state: Mutex<DeterministicState>,
+}
+
+impl DeterministicIdGenerator {
+    /// Clock starting at 2024-01-01T00:00:00Z and moving by one millisecond
+    pub fn new(seed: u64) -> Self {
+        let start = Utc
+            .timestamp_millis_opt(1_704_067_200_000)
+            .single()
+            .unwrap_or_default();
+        Self::with_clock(seed, start, Duration::milliseconds(1))
+    }
+
+    pub fn with_clock(seed: u64, start: DateTime<Utc>, step: Duration) -> Self {
+        Self {
+            seed,
+            start,
+            step,
+            state: Mutex::new(DeterministicState {
+                rng: seed,
+                time: start,
+            }),
+        }
+    }
+
+    /// Starts the sequences over, as if the generator had just been built
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.rng = self.seed;
+        state.time = self.start;
+    }
+
+    // SplitMix64, enough to spread seeds over the uuid space without pulling in an RNG
+    fn next_u64(state: &mut DeterministicState) -> u64 {
+        state.rng = state.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl IdGenerator for DeterministicIdGenerator {
+    fn now(&self) -> DateTime<Utc> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = state.time;
+        state.time += self.step;
+        now
+    }
+
+    fn uuid(&self) -> Uuid {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let high = Self::next_u64(&mut state);
+        let low = Self::next_u64(&mut state);
+        let bytes = ((high as u128) << 64 | low as u128).to_be_bytes();
+        Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_ids_repeat_for_a_seed() {
+        let ids = |generator: &DeterministicIdGenerator| {
+            (0..3)
+                .map(|_| generator.id(IdPrefix::Connection))
+                .collect::<Vec<_>>()
+        };
+
+        let generator = DeterministicIdGenerator::new(7);
+        let first = ids(&generator);
+        assert_eq!(first, ids(&DeterministicIdGenerator::new(7)));
+        assert_ne!(first, ids(&DeterministicIdGenerator::new(8)));
+        assert_eq!(first[1].time() - first[0].time(), Duration::milliseconds(1));
+        assert_eq!(first[0].to_string().split("::").count(), 3);
+
+        generator.reset();
+        assert_eq!(first, ids(&generator));
+
+        assert_ne!(
+            SystemIdGenerator.id(IdPrefix::Connection),
+            SystemIdGenerator.id(IdPrefix::Connection)
+        );
+    }
+}
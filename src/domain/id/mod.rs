@@ -1,4 +1,6 @@
+pub mod generator;
 pub mod prefix;
+pub mod typed;
 
 use crate::{id::prefix::IdPrefix, IntegrationOSError, InternalError};
 use base64ct::{Base64UrlUnpadded, Encoding};
@@ -10,6 +12,9 @@ use std::{
 };
 use uuid::Uuid;
 
+pub use generator::*;
+pub use typed::*;
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(try_from = "String", into = "String")]
@@ -39,6 +44,14 @@ impl Id {
     pub fn new_with_uuid(prefix: IdPrefix, time: DateTime<Utc>, uuid: Uuid) -> Self {
         Self { prefix, time, uuid }
     }
+
+    pub fn prefix(&self) -> IdPrefix {
+        self.prefix
+    }
+
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
 }
 
 impl Display for Id {
@@ -4,6 +4,8 @@ use std::{convert::TryFrom, fmt::Display, fmt::Formatter};
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 pub enum IdPrefix {
+    ApiVersionMigration,
+    Backfill,
     CommonModel,
     CommonEnum,
     Connection,
@@ -12,6 +14,7 @@ pub enum IdPrefix {
     ConnectionModelSchema,
     ConnectionOAuthDefinition,
     Cursor,
+    DeadLetter,
     EmbedToken,
     SessionId,
     Event,
@@ -25,6 +28,7 @@ pub enum IdPrefix {
     LinkToken,
     Log,
     LogTracking,
+    Outbox,
     Pipeline,
     Platform,
     PlatformPage,
@@ -32,11 +36,14 @@ pub enum IdPrefix {
     Settings,
     Transaction,
     UnitTest,
+    WebhookDefinition,
 }
 
 impl Display for IdPrefix {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            IdPrefix::ApiVersionMigration => write!(f, "api_ver_mig"),
+            IdPrefix::Backfill => write!(f, "bf"),
             IdPrefix::CommonModel => write!(f, "cm"),
             IdPrefix::CommonEnum => write!(f, "ce"),
             IdPrefix::Connection => write!(f, "conn"),
@@ -45,6 +52,7 @@ impl Display for IdPrefix {
             IdPrefix::ConnectionModelSchema => write!(f, "conn_mod_sch"),
             IdPrefix::ConnectionOAuthDefinition => write!(f, "conn_oauth_def"),
             IdPrefix::Cursor => write!(f, "crs"),
+            IdPrefix::DeadLetter => write!(f, "dlq"),
             IdPrefix::EmbedToken => write!(f, "embed_tk"),
             IdPrefix::SessionId => write!(f, "session_id"),
             IdPrefix::Event => write!(f, "evt"),
@@ -58,6 +66,7 @@ impl Display for IdPrefix {
             IdPrefix::LinkToken => write!(f, "ln_tk"),
             IdPrefix::Log => write!(f, "log"),
             IdPrefix::LogTracking => write!(f, "log_trk"),
+            IdPrefix::Outbox => write!(f, "obx"),
             IdPrefix::Pipeline => write!(f, "pipe"),
             IdPrefix::Platform => write!(f, "plf"),
             IdPrefix::PlatformPage => write!(f, "plf_pg"),
@@ -65,6 +74,7 @@ impl Display for IdPrefix {
             IdPrefix::Settings => write!(f, "st"),
             IdPrefix::Transaction => write!(f, "tx"),
             IdPrefix::UnitTest => write!(f, "ut"),
+            IdPrefix::WebhookDefinition => write!(f, "wh_def"),
         }
     }
 }
@@ -74,6 +84,8 @@ impl TryFrom<&str> for IdPrefix {
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         match s {
+            "api_ver_mig" => Ok(IdPrefix::ApiVersionMigration),
+            "bf" => Ok(IdPrefix::Backfill),
             "cm" => Ok(IdPrefix::CommonModel),
             "ce" => Ok(IdPrefix::CommonEnum),
             "conn" => Ok(IdPrefix::Connection),
@@ -82,6 +94,7 @@ impl TryFrom<&str> for IdPrefix {
             "conn_mod_sch" => Ok(IdPrefix::ConnectionModelSchema),
             "conn_oauth_def" => Ok(IdPrefix::ConnectionOAuthDefinition),
             "crs" => Ok(IdPrefix::Cursor),
+            "dlq" => Ok(IdPrefix::DeadLetter),
             "embed_tk" => Ok(IdPrefix::EmbedToken),
             "session_id" => Ok(IdPrefix::SessionId),
             "evt" => Ok(IdPrefix::Event),
@@ -95,6 +108,7 @@ impl TryFrom<&str> for IdPrefix {
             "ln_tk" => Ok(IdPrefix::LinkToken),
             "log" => Ok(IdPrefix::Log),
             "log_trk" => Ok(IdPrefix::LogTracking),
+            "obx" => Ok(IdPrefix::Outbox),
             "pipe" => Ok(IdPrefix::Pipeline),
             "plf" => Ok(IdPrefix::Platform),
             "plf_pg" => Ok(IdPrefix::PlatformPage),
@@ -102,6 +116,7 @@ impl TryFrom<&str> for IdPrefix {
             "st" => Ok(IdPrefix::Settings),
             "tx" => Ok(IdPrefix::Transaction),
             "ut" => Ok(IdPrefix::UnitTest),
+            "wh_def" => Ok(IdPrefix::WebhookDefinition),
             _ => Err(InternalError::invalid_argument(
                 &format!("Invalid ID prefix: {}", s),
                 None,
@@ -113,6 +128,8 @@ impl TryFrom<&str> for IdPrefix {
 impl From<IdPrefix> for String {
     fn from(id: IdPrefix) -> Self {
         match id {
+            IdPrefix::ApiVersionMigration => "api_ver_mig".to_string(),
+            IdPrefix::Backfill => "bf".to_string(),
             IdPrefix::CommonModel => "cm".to_string(),
             IdPrefix::CommonEnum => "ce".to_string(),
             IdPrefix::Connection => "conn".to_string(),
@@ -121,6 +138,7 @@ impl From<IdPrefix> for String {
             IdPrefix::ConnectionModelSchema => "conn_mod_sch".to_string(),
             IdPrefix::ConnectionOAuthDefinition => "conn_oauth_def".to_string(),
             IdPrefix::Cursor => "crs".to_string(),
+            IdPrefix::DeadLetter => "dlq".to_string(),
             IdPrefix::EmbedToken => "embed_tk".to_string(),
             IdPrefix::SessionId => "session_id".to_string(),
             IdPrefix::Event => "evt".to_string(),
@@ -134,6 +152,7 @@ impl From<IdPrefix> for String {
             IdPrefix::LinkToken => "ln_tk".to_string(),
             IdPrefix::Log => "log".to_string(),
             IdPrefix::LogTracking => "log_trk".to_string(),
+            IdPrefix::Outbox => "obx".to_string(),
             IdPrefix::Pipeline => "pipe".to_string(),
             IdPrefix::Platform => "plf".to_string(),
             IdPrefix::PlatformPage => "plf_pg".to_string(),
@@ -141,6 +160,7 @@ impl From<IdPrefix> for String {
             IdPrefix::Settings => "st".to_string(),
             IdPrefix::Transaction => "tx".to_string(),
             IdPrefix::UnitTest => "ut".to_string(),
+            IdPrefix::WebhookDefinition => "wh_def".to_string(),
         }
     }
 }
@@ -180,6 +200,7 @@ mod test {
             IdPrefix::try_from("plf_pg").unwrap(),
             IdPrefix::PlatformPage
         );
+        assert_eq!(IdPrefix::try_from("bf").unwrap(), IdPrefix::Backfill);
         assert_eq!(IdPrefix::try_from("cm").unwrap(), IdPrefix::CommonModel);
         assert_eq!(IdPrefix::try_from("ce").unwrap(), IdPrefix::CommonEnum);
         assert_eq!(IdPrefix::try_from("conn").unwrap(), IdPrefix::Connection);
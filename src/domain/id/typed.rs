@@ -0,0 +1,135 @@
+use super::{prefix::IdPrefix, Id};
+use crate::{IntegrationOSError, InternalError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// Generates an [`Id`] newtype per entity, so an id of one entity can't be passed where
+/// another's is expected. The string form is the one of `Id`, whose prefix already names
+/// the entity, so ids stored as plain `Id`s deserialize as is. Parsing or converting an id
+/// with another prefix fails.
+macro_rules! typed_ids {
+    ($($(#[$meta:meta])* $name:ident => $prefix:ident),+ $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Copy, Clone, Ord, PartialOrd, PartialEq, Eq, Hash, Serialize, Deserialize)]
+            #[serde(try_from = "String", into = "String")]
+            pub struct $name(Id);
+
+            impl $name {
+                pub const PREFIX: IdPrefix = IdPrefix::$prefix;
+
+                pub fn new(time: DateTime<Utc>) -> Self {
+                    Self(Id::new(Self::PREFIX, time))
+                }
+
+                pub fn now() -> Self {
+                    Self(Id::now(Self::PREFIX))
+                }
+
+                pub fn as_id(&self) -> &Id {
+                    &self.0
+                }
+            }
+
+            impl From<$name> for Id {
+                fn from(value: $name) -> Id {
+                    value.0
+                }
+            }
+
+            impl TryFrom<Id> for $name {
+                type Error = IntegrationOSError;
+
+                fn try_from(id: Id) -> Result<Self, Self::Error> {
+                    if id.prefix() == Self::PREFIX {
+                        Ok(Self(id))
+                    } else {
+                        Err(InternalError::invalid_argument(
+                            &format!("Expected a {} id, got {id}", Self::PREFIX),
+                            Some(stringify!($name)),
+                        ))
+                    }
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = IntegrationOSError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Id::from_str(s)?.try_into()
+                }
+            }
+
+            impl TryFrom<String> for $name {
+                type Error = IntegrationOSError;
+
+                fn try_from(value: String) -> Result<Self, Self::Error> {
+                    value.parse()
+                }
+            }
+
+            impl From<$name> for String {
+                fn from(value: $name) -> String {
+                    value.0.to_string()
+                }
+            }
+
+            impl Display for $name {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+
+            impl PartialEq<Id> for $name {
+                fn eq(&self, other: &Id) -> bool {
+                    self.0 == *other
+                }
+            }
+        )+
+    };
+}
+
+typed_ids!(
+    CommonModelId => CommonModel,
+    ConnectionId => Connection,
+    ConnectionDefinitionId => ConnectionDefinition,
+    ConnectionModelDefinitionId => ConnectionModelDefinition,
+    EventId => Event,
+    /// Key shared by every context of an event chain
+    EventKeyId => EventKey,
+    PipelineId => Pipeline,
+    TransactionId => Transaction,
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_typed_ids_check_their_prefix() {
+        let legacy = Id::new_with_uuid(
+            IdPrefix::Connection,
+            Utc.timestamp_opt(0, 0).single().unwrap(),
+            Uuid::nil(),
+        );
+        let json = serde_json::to_string(&legacy).unwrap();
+
+        let id: ConnectionId = serde_json::from_str(&json).unwrap();
+        assert_eq!(id, legacy);
+        assert_eq!(serde_json::to_string(&id).unwrap(), json);
+        assert_eq!(Id::from(id), legacy);
+        assert_eq!(ConnectionId::try_from(legacy).unwrap(), id);
+
+        assert!(serde_json::from_str::<EventId>(&json).is_err());
+        assert!(EventId::try_from(legacy).is_err());
+        assert!(EventId::from_str("conn::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA").is_err());
+        assert!(EventId::from_str("evt::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA").is_ok());
+        assert_eq!(PipelineId::now().as_id().prefix(), IdPrefix::Pipeline);
+    }
+}
@@ -1,8 +1,9 @@
 pub mod stage;
-use super::shared::record_metadata::RecordMetadata;
+use super::shared::{record_metadata::RecordMetadata, unknown_variant};
 use crate::id::{prefix::IdPrefix, Id};
 use bson::doc;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use strum::Display;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -59,4 +60,7 @@ pub enum JobStatus {
     Canceled,
     /// Job has failed
     Failed,
+    /// Status set by a newer service
+    #[serde(untagged, deserialize_with = "unknown_variant::deserialize")]
+    Unknown(Value),
 }
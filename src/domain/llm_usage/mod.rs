@@ -0,0 +1,184 @@
+use crate::{
+    configuration::llm::LlmBudget, id::Id, prelude::shared::ownership::Ownership, ApplicationError,
+    IntegrationOSError,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use thiserror::Error;
+
+pub const BUDGET_EXCEEDED: &str = crate::stable_code::BUDGET_EXCEEDED_SUBTYPE;
+
+/// Calendar month usage is accounted over, e.g. `2024-05`
+pub fn usage_period(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m").to_string()
+}
+
+/// Rough token count of a text, for calls whose usage isn't reported
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(4) as u64
+}
+
+/// Tokens an ownership, or one of its connections, used over a month. There is one
+/// record per ownership, connection and month, incremented by each call, plus one for the
+/// whole ownership without `connection_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmUsageRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub ownership: Ownership,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<Id>,
+    pub period: String,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub requests: u64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+impl LlmUsageRecord {
+    pub fn id_for(buildable_id: &str, connection_id: Option<&Id>, period: &str) -> String {
+        match connection_id {
+            Some(connection_id) => format!("{buildable_id}::{connection_id}::{period}"),
+            None => format!("{buildable_id}::{period}"),
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens.saturating_add(self.output_tokens)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "id")]
+pub enum BudgetScope {
+    Ownership(String),
+    Connection(Id),
+}
+
+impl Display for BudgetScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetScope::Ownership(buildable_id) => write!(f, "ownership {buildable_id}"),
+            BudgetScope::Connection(connection_id) => write!(f, "connection {connection_id}"),
+        }
+    }
+}
+
+/// A call that would take usage over its monthly budget. Turns into a too many requests
+/// error with the `budget_exceeded` subtype.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Error)]
+#[serde(rename_all = "camelCase")]
+#[error("LLM token budget of {budget} for {scope} exceeded in {period}: {used} used, {requested} requested")]
+pub struct BudgetExceeded {
+    pub scope: BudgetScope,
+    pub period: String,
+    pub budget: u64,
+    pub used: u64,
+    pub requested: u64,
+}
+
+impl From<BudgetExceeded> for IntegrationOSError {
+    fn from(value: BudgetExceeded) -> Self {
+        ApplicationError::too_many_requests(&value.to_string(), Some(BUDGET_EXCEEDED))
+    }
+}
+
+impl LlmBudget {
+    /// Fails when using `requested` more tokens would go over the ownership's budget, or
+    /// the connection's when `connection` is given. Budgets already used up reject calls
+    /// of any size.
+    pub fn check(
+        &self,
+        ownership: &LlmUsageRecord,
+        connection: Option<&LlmUsageRecord>,
+        requested: u64,
+    ) -> Result<(), BudgetExceeded> {
+        let ownership_scope = BudgetScope::Ownership(ownership.ownership.id.to_string());
+        let limits = [
+            (self.monthly_tokens, Some((ownership, ownership_scope))),
+            (
+                self.connection_monthly_tokens,
+                connection.and_then(|usage| {
+                    usage
+                        .connection_id
+                        .map(|id| (usage, BudgetScope::Connection(id)))
+                }),
+            ),
+        ];
+        for (budget, usage) in limits {
+            let (Some(budget), Some((usage, scope))) = (budget, usage) else {
+                continue;
+            };
+            let used = usage.total_tokens();
+            if used >= budget || used.saturating_add(requested) > budget {
+                return Err(BudgetExceeded {
+                    scope,
+                    period: usage.period.clone(),
+                    budget,
+                    used,
+                    requested,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{id::prefix::IdPrefix, ErrorMeta};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_budget_check() {
+        let period = usage_period(Utc.with_ymd_and_hms(2024, 5, 31, 23, 0, 0).unwrap());
+        assert_eq!(period, "2024-05");
+        let ownership = Ownership {
+            id: "build-1".into(),
+            ..Default::default()
+        };
+        let connection_id = Id::now(IdPrefix::Connection);
+        let usage =
+            |connection_id: Option<Id>, input_tokens: u64, output_tokens: u64| LlmUsageRecord {
+                id: LlmUsageRecord::id_for(&ownership.id, connection_id.as_ref(), &period),
+                ownership: ownership.clone(),
+                connection_id,
+                period: period.clone(),
+                input_tokens,
+                output_tokens,
+                requests: 1,
+                updated_at: 0,
+            };
+        let budget = LlmBudget {
+            monthly_tokens: Some(1_000),
+            connection_monthly_tokens: Some(300),
+        };
+
+        let total = usage(None, 600, 100);
+        assert!(budget.check(&total, None, 300).is_ok());
+        let exceeded = budget.check(&total, None, 301).unwrap_err();
+        assert_eq!(exceeded.scope, BudgetScope::Ownership("build-1".to_owned()));
+        assert_eq!(exceeded.used, 700);
+
+        let exceeded = budget
+            .check(&total, Some(&usage(Some(connection_id), 250, 50)), 1)
+            .unwrap_err();
+        assert_eq!(exceeded.scope, BudgetScope::Connection(connection_id));
+        assert!(LlmBudget::default()
+            .check(&usage(None, u64::MAX, 0), None, 1)
+            .is_ok());
+
+        let error = IntegrationOSError::from(exceeded);
+        assert_eq!(
+            error.key().to_string(),
+            "err::application::too_many_requests::budget_exceeded"
+        );
+    }
+}
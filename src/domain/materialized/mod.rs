@@ -0,0 +1,128 @@
+use crate::prelude::shared::record_metadata::RecordMetadata;
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshPolicy {
+    /// Seconds during which a record is served without refreshing it upstream
+    pub ttl: u64,
+    /// Extra seconds during which a stale record can still be served while it is being refreshed
+    pub stale_while_revalidate: u64,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self {
+            ttl: 300,
+            stale_while_revalidate: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+/// A transformed common-model record (Customer, Order, ...) persisted per connection so
+/// unified reads can be answered without calling the upstream platform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializedRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub connection_key: String,
+    pub common_model: String,
+    pub external_id: String,
+    pub data: Value,
+    pub synced_at: i64,
+    pub fresh_until: i64,
+    pub expires_at: i64,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl MaterializedRecord {
+    pub fn key(connection_key: &str, common_model: &str, external_id: &str) -> String {
+        format!("{connection_key}::{common_model}::{external_id}")
+    }
+
+    pub fn new(
+        connection_key: &str,
+        common_model: &str,
+        external_id: &str,
+        data: Value,
+        policy: &RefreshPolicy,
+    ) -> Self {
+        let now = Utc::now().timestamp_millis();
+        let fresh_until = now + (policy.ttl as i64) * 1000;
+        Self {
+            id: Self::key(connection_key, common_model, external_id),
+            connection_key: connection_key.to_owned(),
+            common_model: common_model.to_owned(),
+            external_id: external_id.to_owned(),
+            data,
+            synced_at: now,
+            fresh_until,
+            expires_at: fresh_until + (policy.stale_while_revalidate as i64) * 1000,
+            record_metadata: Default::default(),
+        }
+    }
+
+    pub fn freshness_at(&self, now: i64) -> Freshness {
+        if now < self.fresh_until {
+            Freshness::Fresh
+        } else if now < self.expires_at {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+
+    pub fn freshness(&self) -> Freshness {
+        self.freshness_at(Utc::now().timestamp_millis())
+    }
+
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(self.data.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_materialized_record_freshness() {
+        let policy = RefreshPolicy {
+            ttl: 10,
+            stale_while_revalidate: 20,
+        };
+        let record = MaterializedRecord::new(
+            "stripe::test",
+            "Customers",
+            "cus_1",
+            json!({ "id": "cus_1" }),
+            &policy,
+        );
+
+        assert_eq!(record.id, "stripe::test::Customers::cus_1");
+        assert_eq!(record.freshness_at(record.synced_at), Freshness::Fresh);
+        assert_eq!(
+            record.freshness_at(record.synced_at + 15_000),
+            Freshness::Stale
+        );
+        assert_eq!(
+            record.freshness_at(record.synced_at + 30_000),
+            Freshness::Expired
+        );
+    }
+}
@@ -1,37 +1,97 @@
 pub mod access_key;
+#[cfg(feature = "backend")]
+pub mod alert;
+#[cfg(feature = "backend")]
+pub mod api;
+#[cfg(feature = "backend")]
+pub mod backfill;
+#[cfg(feature = "backend")]
+pub mod background;
 pub mod configuration;
+#[cfg(feature = "backend")]
 pub mod connection;
+#[cfg(feature = "backend")]
 pub mod context;
 pub mod error;
+#[cfg(feature = "backend")]
 pub mod event;
+#[cfg(feature = "backend")]
 pub mod hook;
+#[cfg(feature = "backend")]
 pub mod http;
 pub mod id;
+#[cfg(feature = "backend")]
 pub mod jobs;
+#[cfg(feature = "backend")]
+pub mod llm_usage;
+#[cfg(feature = "backend")]
+pub mod materialized;
+#[cfg(feature = "backend")]
 pub mod microservice;
+#[cfg(feature = "backend")]
 pub mod pipeline;
+#[cfg(feature = "backend")]
+pub mod plan;
+#[cfg(feature = "backend")]
 pub mod platform;
+#[cfg(feature = "backend")]
+pub mod projection;
+#[cfg(feature = "backend")]
+pub mod queue;
+#[cfg(feature = "backend")]
+pub mod retention;
 pub mod schema;
 pub mod secret;
 pub mod shared;
+#[cfg(feature = "backend")]
 pub mod store;
+#[cfg(feature = "backend")]
 pub mod token;
 
 pub use access_key::*;
+#[cfg(feature = "backend")]
+pub use alert::*;
+#[cfg(feature = "backend")]
+pub use backfill::*;
+#[cfg(feature = "backend")]
+pub use background::*;
 pub use configuration::*;
+#[cfg(feature = "backend")]
 pub use connection::*;
+#[cfg(feature = "backend")]
 pub use context::*;
 pub use error::*;
+#[cfg(feature = "backend")]
 pub use event::*;
+#[cfg(feature = "backend")]
 pub use hook::*;
+#[cfg(feature = "backend")]
 pub use http::*;
 pub use id::*;
+#[cfg(feature = "backend")]
 pub use jobs::*;
+#[cfg(feature = "backend")]
+pub use llm_usage::*;
+#[cfg(feature = "backend")]
+pub use materialized::*;
+#[cfg(feature = "backend")]
 pub use microservice::*;
+#[cfg(feature = "backend")]
 pub use pipeline::*;
+#[cfg(feature = "backend")]
+pub use plan::*;
+#[cfg(feature = "backend")]
 pub use platform::*;
+#[cfg(feature = "backend")]
+pub use projection::*;
+#[cfg(feature = "backend")]
+pub use queue::*;
+#[cfg(feature = "backend")]
+pub use retention::*;
 pub use schema::*;
 pub use secret::*;
 pub use shared::*;
+#[cfg(feature = "backend")]
 pub use store::*;
+#[cfg(feature = "backend")]
 pub use token::*;
@@ -4,6 +4,7 @@ pub mod middleware;
 pub mod policies;
 pub mod signature;
 pub mod source;
+pub mod yaml;
 
 use serde::{Deserialize, Serialize};
 
@@ -1,4 +1,6 @@
-use crate::{IntegrationOSError, InternalError};
+use crate::{ApplicationError, IntegrationOSError, InternalError};
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -15,35 +17,145 @@ pub struct RetryPolicy {
     #[cfg_attr(feature = "dummy", dummy(faker = "0..10"))]
     pub maximum_attempts: u64,
     pub initial_interval: String,
+    #[serde(default)]
+    pub backoff: BackoffStrategy,
+    /// Upper bound of the delay between attempts, e.g. `5 minutes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum_interval: Option<String>,
+    /// Waits a random delay between half and all of the backoff, so stages failing
+    /// together do not retry together
+    #[serde(default)]
+    pub jitter: bool,
+    #[serde(default = "ErrorClass::transient")]
+    pub retry_on: Vec<ErrorClass>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum BackoffStrategy {
+    /// Always waits the initial interval
+    #[default]
+    Fixed,
+    /// Multiplies the initial interval by `multiplier` after every attempt
+    Exponential { multiplier: u32 },
+}
+
+/// Kinds of failure a [`RetryPolicy`] may retry
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorClass {
+    Timeout,
+    Connection,
+    RateLimited,
+    Unavailable,
+    /// Any other internal error, e.g. IO
+    Internal,
+}
+
+impl ErrorClass {
+    pub fn transient() -> Vec<ErrorClass> {
+        vec![
+            ErrorClass::Timeout,
+            ErrorClass::Connection,
+            ErrorClass::RateLimited,
+            ErrorClass::Unavailable,
+        ]
+    }
+
+    /// Class of an error, if it is worth retrying at all. Invalid input and
+    /// configuration never succeed on a later attempt.
+    pub fn of(error: &IntegrationOSError) -> Option<ErrorClass> {
+        match error {
+            IntegrationOSError::Internal(error) => match error {
+                InternalError::Timeout { .. } => Some(ErrorClass::Timeout),
+                InternalError::ConnectionError { .. } => Some(ErrorClass::Connection),
+                InternalError::UnknownError { .. } | InternalError::IOErr { .. } => {
+                    Some(ErrorClass::Internal)
+                }
+                _ => None,
+            },
+            IntegrationOSError::Application(error) => match error {
+                ApplicationError::TooManyRequests { .. } => Some(ErrorClass::RateLimited),
+                ApplicationError::ServiceUnavailable { .. } => Some(ErrorClass::Unavailable),
+                ApplicationError::InternalServerError { .. } => Some(ErrorClass::Internal),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// What happened while running a stage under a [`RetryPolicy`], stored on its context
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryAttempts {
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(
+        default,
+        with = "chrono::serde::ts_milliseconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_attempt_at: Option<DateTime<Utc>>,
 }
 
 impl RetryPolicy {
+    pub fn retries(&self, error: &IntegrationOSError) -> bool {
+        ErrorClass::of(error).is_some_and(|class| self.retry_on.contains(&class))
+    }
+
+    /// Delay before the attempt following `attempt` (1 being the first attempt)
+    pub fn delay(&self, attempt: u32) -> Result<Duration, IntegrationOSError> {
+        let initial = self.get_interval()?;
+        let delay = match self.backoff {
+            BackoffStrategy::Fixed => initial,
+            BackoffStrategy::Exponential { multiplier } => {
+                initial.saturating_mul(multiplier.max(1).saturating_pow(attempt.saturating_sub(1)))
+            }
+        };
+        let delay = match &self.maximum_interval {
+            Some(maximum) => delay.min(parse_interval(maximum)?),
+            None => delay,
+        };
+        Ok(if self.jitter && !delay.is_zero() {
+            rand::thread_rng().gen_range(delay / 2..=delay)
+        } else {
+            delay
+        })
+    }
+
     pub fn get_interval(&self) -> Result<Duration, IntegrationOSError> {
-        let mut parts = self.initial_interval.split(' ');
-        let num = parts
-            .next()
-            .ok_or(InternalError::configuration_error(
-                "No number in retry policy interval",
-                None,
-            ))?
-            .parse()
-            .map_err(|e| {
-                InternalError::configuration_error(
-                    &format!("Invalid retry policy interval number: {}", e),
-                    None,
-                )
-            })?;
-        let amount = parts.next().ok_or(InternalError::configuration_error(
-            "No amount in retry policy interval",
+        parse_interval(&self.initial_interval)
+    }
+}
+
+pub(crate) fn parse_interval(interval: &str) -> Result<Duration, IntegrationOSError> {
+    let mut parts = interval.split(' ');
+    let num = parts
+        .next()
+        .ok_or(InternalError::configuration_error(
+            "No number in retry policy interval",
             None,
-        ))?;
-        match amount {
-            "seconds" | "second" => Ok(Duration::from_secs(num)),
-            "minute" | "minutes" => Ok(Duration::from_secs(num * 60)),
-            x => Err(InternalError::configuration_error(
-                &format!("Invalid retry policy interval amount: {}", x),
+        ))?
+        .parse()
+        .map_err(|e| {
+            InternalError::configuration_error(
+                &format!("Invalid retry policy interval number: {}", e),
                 None,
-            )),
-        }
+            )
+        })?;
+    let amount = parts.next().ok_or(InternalError::configuration_error(
+        "No amount in retry policy interval",
+        None,
+    ))?;
+    match amount {
+        "seconds" | "second" => Ok(Duration::from_secs(num)),
+        "minute" | "minutes" => Ok(Duration::from_secs(num * 60)),
+        x => Err(InternalError::configuration_error(
+            &format!("Invalid retry policy interval amount: {}", x),
+            None,
+        )),
     }
 }
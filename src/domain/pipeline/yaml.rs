@@ -0,0 +1,305 @@
+use super::{middleware::Middleware, policies::parse_interval, Pipeline};
+use crate::{IntegrationOSError, InternalError};
+use std::{collections::HashSet, fmt::Display};
+use thiserror::Error;
+
+/// A problem found in a pipeline definition, with the line it is on when it could be
+/// located
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineIssue {
+    /// Path of the offending field, e.g. `middleware[1].url`
+    pub path: String,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl Display for PipelineIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}: {}", self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PipelineYamlError {
+    /// The document is not YAML or does not have the shape of a pipeline
+    #[error("Invalid pipeline YAML at line {line}, column {column}: {message}")]
+    Syntax {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    #[error("Invalid pipeline: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<PipelineIssue>),
+}
+
+impl From<PipelineYamlError> for IntegrationOSError {
+    fn from(error: PipelineYamlError) -> Self {
+        InternalError::invalid_argument(&error.to_string(), Some("pipeline_yaml"))
+    }
+}
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn segments(path: &str) -> Vec<Segment<'_>> {
+    path.split('.')
+        .flat_map(|part| {
+            let (key, indexes) = part.split_once('[').unwrap_or((part, ""));
+            std::iter::once(Segment::Key(key)).chain(
+                indexes
+                    .split('[')
+                    .filter_map(|index| index.trim_end_matches(']').parse().ok())
+                    .map(Segment::Index),
+            )
+        })
+        .collect()
+}
+
+/// Indentation and content of a line, counting the `- ` of a list item as indentation
+fn indented(line: &str) -> (isize, bool, &str) {
+    let content = line.trim_start();
+    let indent = (line.len() - content.len()) as isize;
+    match content.strip_prefix("- ") {
+        Some(item) => (indent + 2, true, item.trim_start()),
+        None => (indent, false, content),
+    }
+}
+
+/// Line (1-based) of the field at `path` in a block style document
+fn locate(yaml: &str, path: &str) -> Option<usize> {
+    let lines = yaml.lines().collect::<Vec<_>>();
+    let mut position = 0;
+    let mut parent_indent = -1;
+    for segment in segments(path) {
+        match segment {
+            Segment::Key(key) => {
+                let prefix = format!("{key}:");
+                position = (position..lines.len()).find(|&j| {
+                    let (indent, _, content) = indented(lines[j]);
+                    indent > parent_indent && content.starts_with(&prefix)
+                })?;
+                parent_indent = indented(lines[position]).0;
+            }
+            Segment::Index(index) => {
+                let mut items = (position + 1..lines.len())
+                    .filter(|&j| !lines[j].trim().is_empty())
+                    .take_while(|&j| {
+                        let (indent, item, _) = indented(lines[j]);
+                        indent > parent_indent || (item && indent + 2 > parent_indent)
+                    })
+                    .filter(|&j| indented(lines[j]).1);
+                let first = items.next()?;
+                let item_indent = indented(lines[first]).0;
+                position = std::iter::once(first)
+                    .chain(items.filter(|&j| indented(lines[j]).0 == item_indent))
+                    .nth(index)?;
+                parent_indent = item_indent - 1;
+            }
+        }
+    }
+    Some(position + 1)
+}
+
+struct Issues<'a> {
+    yaml: Option<&'a str>,
+    issues: Vec<PipelineIssue>,
+}
+
+impl Issues<'_> {
+    fn push(&mut self, path: String, message: impl Into<String>) {
+        let line = self.yaml.and_then(|yaml| locate(yaml, &path));
+        self.issues.push(PipelineIssue {
+            path,
+            message: message.into(),
+            line,
+        });
+    }
+
+    fn non_empty(&mut self, path: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.push(path.to_owned(), "must not be empty");
+        }
+    }
+
+    fn interval(&mut self, path: String, value: &str) {
+        if let Err(e) = parse_interval(value) {
+            self.push(path, e.to_string());
+        }
+    }
+}
+
+impl Pipeline {
+    /// Reads a pipeline from YAML, e.g. kept next to the code it serves, and validates it
+    pub fn from_yaml(yaml: &str) -> Result<Self, PipelineYamlError> {
+        let pipeline: Pipeline =
+            serde_yaml::from_str(yaml).map_err(|e| PipelineYamlError::Syntax {
+                message: e.to_string(),
+                line: e.location().map_or(0, |location| location.line()),
+                column: e.location().map_or(0, |location| location.column()),
+            })?;
+        pipeline.check(Some(yaml))?;
+        Ok(pipeline)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, IntegrationOSError> {
+        serde_yaml::to_string(self)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("pipeline_yaml")))
+    }
+
+    /// Checks what the types alone cannot: required values, unique extractor keys and
+    /// parseable URLs and intervals
+    pub fn validate(&self) -> Result<(), PipelineYamlError> {
+        self.check(None)
+    }
+
+    fn check(&self, yaml: Option<&str>) -> Result<(), PipelineYamlError> {
+        let mut issues = Issues {
+            yaml,
+            issues: vec![],
+        };
+        issues.non_empty("name", &self.name);
+        issues.non_empty("key", &self.key);
+        if self.key.contains(char::is_whitespace) {
+            issues.push("key".to_owned(), "must not contain whitespace");
+        }
+        if self.source.events.is_empty() {
+            issues.push("source.events".to_owned(), "must list at least one event");
+        }
+        for (i, event) in self.source.events.iter().enumerate() {
+            issues.non_empty(&format!("source.events[{i}]"), event);
+        }
+        issues.non_empty("destination.platform", &self.destination.platform);
+        issues.non_empty(
+            "destination.connectionKey",
+            &self.destination.connection_key,
+        );
+
+        let mut extractor_keys = HashSet::new();
+        for (i, middleware) in self.middleware.iter().enumerate() {
+            let Middleware::HttpExtractor(extractor) = middleware else {
+                continue;
+            };
+            issues.non_empty(&format!("middleware[{i}].key"), &extractor.key);
+            if !extractor_keys.insert(extractor.key.as_str()) {
+                issues.push(
+                    format!("middleware[{i}].key"),
+                    format!("duplicate extractor key {}", extractor.key),
+                );
+            }
+            if let Err(e) = reqwest::Url::parse(&extractor.url) {
+                issues.push(format!("middleware[{i}].url"), format!("invalid URL: {e}"));
+            }
+            issues.interval(
+                format!("middleware[{i}].policies.retry.initialInterval"),
+                &extractor.policies.retry.initial_interval,
+            );
+            issues.interval(
+                format!("middleware[{i}].startToCloseTimeout"),
+                &extractor.start_to_close_timeout,
+            );
+        }
+
+        if let Some(config) = &self.config {
+            issues.interval(
+                "config.policies.retry.initialInterval".to_owned(),
+                &config.policies.retry.initial_interval,
+            );
+            issues.interval(
+                "config.startToCloseTimeout".to_owned(),
+                &config.start_to_close_timeout,
+            );
+        }
+
+        if issues.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(PipelineYamlError::Invalid(issues.issues))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PIPELINE: &str = r#"
+_id: pipe::shopify-orders
+environment: test
+name: Shopify orders
+key: shopify-orders
+source:
+  type: shopify
+  events:
+    - orders/create
+  group: orders
+destination:
+  platform: hubspot
+  action: !Unified
+    name: Deal
+    action: create
+  connectionKey: hubspot-1
+middleware:
+  - _type: transformer
+    language: javascript
+    code: "function transform(event) { return event; }"
+  - _type: extractor::http
+    key: customer
+    url: not a url
+    method: GET
+    headers: "{}"
+    data: "{}"
+    policies:
+      retry:
+        maximumAttempts: 3
+        initialInterval: 1 fortnight
+    startToCloseTimeout: 10 seconds
+ownership:
+  buildableId: acme
+  clientId: acme
+signature:
+  header: X-Signature
+  algorithm: sha256
+  secrets: [a, b]
+config: null
+"#;
+
+    #[test]
+    fn test_validation_issues_point_at_their_line() {
+        let Err(PipelineYamlError::Invalid(issues)) = Pipeline::from_yaml(PIPELINE) else {
+            panic!("pipeline should be invalid");
+        };
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.path.as_str(), issue.line))
+                .collect::<Vec<_>>(),
+            vec![
+                ("middleware[1].url", Some(23)),
+                ("middleware[1].policies.retry.initialInterval", Some(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let yaml = PIPELINE
+            .replace("not a url", "https://api.example.com/customers")
+            .replace("1 fortnight", "2 seconds");
+        let pipeline = Pipeline::from_yaml(&yaml).unwrap();
+        assert_eq!(pipeline.middleware.len(), 2);
+        assert_eq!(
+            Pipeline::from_yaml(&pipeline.to_yaml().unwrap()).unwrap(),
+            pipeline
+        );
+
+        match Pipeline::from_yaml(&yaml.replace("connectionKey", "connection")) {
+            Err(PipelineYamlError::Syntax { line, .. }) => assert_eq!(line, 12),
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+}
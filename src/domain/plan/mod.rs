@@ -0,0 +1,164 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::{ownership::Ownership, record_metadata::RecordMetadata},
+    ApplicationError, IntegrationOSError, TokenBucket,
+};
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, Display, AsRefStr,
+)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum PlanTier {
+    #[default]
+    Free,
+    Pro,
+    Enterprise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, AsRefStr)]
+#[strum(serialize_all = "camelCase")]
+pub enum PlanLimit {
+    RequestsPerMinute,
+    EventsPerDay,
+    MaxConnections,
+    PipelineConcurrency,
+}
+
+/// Throttling limits of a billing plan. `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct PlanLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events_per_day: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline_concurrency: Option<u64>,
+}
+
+impl PlanLimits {
+    pub fn for_tier(tier: PlanTier) -> Self {
+        match tier {
+            PlanTier::Free => Self {
+                requests_per_minute: Some(60),
+                events_per_day: Some(10_000),
+                max_connections: Some(5),
+                pipeline_concurrency: Some(2),
+            },
+            PlanTier::Pro => Self {
+                requests_per_minute: Some(600),
+                events_per_day: Some(1_000_000),
+                max_connections: Some(100),
+                pipeline_concurrency: Some(20),
+            },
+            PlanTier::Enterprise => Self::default(),
+        }
+    }
+
+    /// Limits set in `overrides` take precedence over these
+    pub fn with_overrides(self, overrides: &PlanLimits) -> Self {
+        Self {
+            requests_per_minute: overrides.requests_per_minute.or(self.requests_per_minute),
+            events_per_day: overrides.events_per_day.or(self.events_per_day),
+            max_connections: overrides.max_connections.or(self.max_connections),
+            pipeline_concurrency: overrides.pipeline_concurrency.or(self.pipeline_concurrency),
+        }
+    }
+
+    pub fn get(&self, limit: PlanLimit) -> Option<u64> {
+        match limit {
+            PlanLimit::RequestsPerMinute => self.requests_per_minute,
+            PlanLimit::EventsPerDay => self.events_per_day,
+            PlanLimit::MaxConnections => self.max_connections,
+            PlanLimit::PipelineConcurrency => self.pipeline_concurrency,
+        }
+    }
+
+    /// Fails with a too many requests error when using `requested` more on top of `used`
+    /// would go over the limit
+    pub fn check(
+        &self,
+        limit: PlanLimit,
+        used: u64,
+        requested: u64,
+    ) -> Result<(), IntegrationOSError> {
+        match self.get(limit) {
+            Some(max) if used.saturating_add(requested) > max => {
+                Err(ApplicationError::too_many_requests(
+                    &format!("Plan limit {limit} of {max} reached"),
+                    Some("plan_limits"),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rate limiter for the requests per minute allowance
+    pub fn request_bucket(&self) -> TokenBucket {
+        match self.requests_per_minute {
+            Some(requests) => TokenBucket::per_minute(requests, requests),
+            None => TokenBucket::unlimited(),
+        }
+    }
+}
+
+/// The plan an ownership is on, with any limits negotiated on top of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct OwnershipPlan {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub ownership: Ownership,
+    pub tier: PlanTier,
+    #[serde(default)]
+    pub overrides: PlanLimits,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl OwnershipPlan {
+    pub fn new(ownership: Ownership, tier: PlanTier) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Settings),
+            ownership,
+            tier,
+            overrides: PlanLimits::default(),
+            record_metadata: Default::default(),
+        }
+    }
+
+    pub fn limits(&self) -> PlanLimits {
+        PlanLimits::for_tier(self.tier).with_overrides(&self.overrides)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plan_limits() {
+        let mut plan = OwnershipPlan::new(Ownership::default(), PlanTier::Free);
+        plan.overrides.max_connections = Some(10);
+        let limits = plan.limits();
+
+        assert_eq!(limits.requests_per_minute, Some(60));
+        assert_eq!(limits.max_connections, Some(10));
+        assert!(limits.check(PlanLimit::MaxConnections, 9, 1).is_ok());
+        assert!(limits.check(PlanLimit::MaxConnections, 10, 1).is_err());
+
+        let enterprise = PlanLimits::for_tier(PlanTier::Enterprise);
+        assert!(enterprise
+            .check(PlanLimit::EventsPerDay, u64::MAX, 1)
+            .is_ok());
+        assert!(enterprise.request_bucket().is_unlimited());
+    }
+}
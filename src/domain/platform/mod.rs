@@ -1,4 +1,6 @@
 pub mod page;
+pub mod quirks;
+pub mod registry;
 pub mod r#type;
 
 use crate::{
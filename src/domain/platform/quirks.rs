@@ -0,0 +1,357 @@
+use crate::prelude::connection::Platform;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum IdFormat {
+    /// Resource prefixed ids such as `cus_123`
+    Prefixed {
+        separator: char,
+    },
+    Numeric,
+    Uuid,
+    /// Global ids such as `gid://shopify/Order/123`, also accepting their numeric tail
+    Gid {
+        scheme: String,
+    },
+    Opaque,
+}
+
+impl IdFormat {
+    pub fn is_valid(&self, id: &str) -> bool {
+        if id.is_empty() {
+            return false;
+        }
+        match self {
+            IdFormat::Prefixed { separator } => id
+                .split_once(*separator)
+                .is_some_and(|(prefix, rest)| !prefix.is_empty() && !rest.is_empty()),
+            IdFormat::Numeric => id.chars().all(|c| c.is_ascii_digit()),
+            IdFormat::Uuid => Uuid::parse_str(id).is_ok(),
+            IdFormat::Gid { scheme } => {
+                id.chars().all(|c| c.is_ascii_digit())
+                    || id
+                        .strip_prefix(&format!("gid://{scheme}/"))
+                        .is_some_and(|rest| rest.contains('/'))
+            }
+            IdFormat::Opaque => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResetFormat {
+    /// Seconds to wait before the limit resets
+    DeltaSeconds,
+    /// Unix timestamp at which the limit resets
+    EpochSeconds,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitSemantics {
+    pub remaining_header: Option<String>,
+    pub reset_header: Option<String>,
+    pub reset_format: ResetFormat,
+    pub retry_after_header: Option<String>,
+    /// Leaky bucket usage header expressed as `used/capacity`, e.g. Shopify's call limit
+    pub bucket_header: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub remaining: Option<u64>,
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitSemantics {
+    pub fn parse(&self, headers: &HeaderMap, now_epoch_secs: u64) -> RateLimitStatus {
+        let header = |name: &Option<String>| {
+            name.as_deref()
+                .and_then(|n| headers.get(n))
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+        };
+
+        let remaining = header(&self.remaining_header)
+            .and_then(|v| v.parse::<u64>().ok())
+            .or_else(|| {
+                header(&self.bucket_header).and_then(|v| {
+                    let (used, capacity) = v.split_once('/')?;
+                    let used = used.trim().parse::<u64>().ok()?;
+                    let capacity = capacity.trim().parse::<u64>().ok()?;
+                    Some(capacity.saturating_sub(used))
+                })
+            });
+
+        let retry_after = header(&self.retry_after_header)
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .or_else(|| {
+                let reset = header(&self.reset_header)?.parse::<u64>().ok()?;
+                Some(Duration::from_secs(match self.reset_format {
+                    ResetFormat::DeltaSeconds => reset,
+                    ResetFormat::EpochSeconds => reset.saturating_sub(now_epoch_secs),
+                }))
+            });
+
+        RateLimitStatus {
+            remaining,
+            retry_after,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignatureEncoding {
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum WebhookSignatureScheme {
+    None,
+    /// HMAC-SHA256 over the raw body
+    HmacSha256 {
+        header: String,
+        encoding: SignatureEncoding,
+    },
+    /// HMAC-SHA256 over `{timestamp}.{body}`, with both values carried in a single header
+    /// (`t=...,v1=...`), as done by Stripe
+    TimestampedHmacSha256 {
+        header: String,
+        timestamp_key: String,
+        signature_key: String,
+        tolerance_secs: u64,
+    },
+}
+
+impl WebhookSignatureScheme {
+    /// Delivery timestamp (epoch seconds) carried in the signature header, for schemes
+    /// that sign one
+    pub fn timestamp(&self, headers: &HeaderMap) -> Option<i64> {
+        let WebhookSignatureScheme::TimestampedHmacSha256 {
+            header,
+            timestamp_key,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        headers
+            .get(header)?
+            .to_str()
+            .ok()?
+            .split(',')
+            .filter_map(|part| part.trim().split_once('='))
+            .find(|(key, _)| key == timestamp_key)
+            .and_then(|(_, value)| value.parse().ok())
+    }
+
+    pub fn tolerance_secs(&self) -> Option<u64> {
+        match self {
+            WebhookSignatureScheme::TimestampedHmacSha256 { tolerance_secs, .. } => {
+                Some(*tolerance_secs)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Platform specific behaviour kept as data instead of conditionals scattered across the
+/// executor, the webhook verifier and the normalizers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformQuirks {
+    pub platform: Platform,
+    pub api_version_header: Option<String>,
+    pub default_api_version: Option<String>,
+    pub rate_limit: RateLimitSemantics,
+    pub id_format: IdFormat,
+    pub webhook_signature: WebhookSignatureScheme,
+}
+
+impl PlatformQuirks {
+    pub fn generic(platform: Platform) -> Self {
+        Self {
+            platform,
+            api_version_header: None,
+            default_api_version: None,
+            rate_limit: RateLimitSemantics {
+                remaining_header: None,
+                reset_header: None,
+                reset_format: ResetFormat::DeltaSeconds,
+                retry_after_header: Some("Retry-After".to_owned()),
+                bucket_header: None,
+            },
+            id_format: IdFormat::Opaque,
+            webhook_signature: WebhookSignatureScheme::None,
+        }
+    }
+
+    pub fn for_platform(platform: Platform) -> Self {
+        let generic = Self::generic(platform);
+        match platform {
+            Platform::Stripe => Self {
+                api_version_header: Some("Stripe-Version".to_owned()),
+                default_api_version: Some("2023-08-16".to_owned()),
+                id_format: IdFormat::Prefixed { separator: '_' },
+                webhook_signature: WebhookSignatureScheme::TimestampedHmacSha256 {
+                    header: "Stripe-Signature".to_owned(),
+                    timestamp_key: "t".to_owned(),
+                    signature_key: "v1".to_owned(),
+                    tolerance_secs: 300,
+                },
+                ..generic
+            },
+            Platform::Shopify => Self {
+                rate_limit: RateLimitSemantics {
+                    bucket_header: Some("X-Shopify-Shop-Api-Call-Limit".to_owned()),
+                    ..generic.rate_limit.clone()
+                },
+                id_format: IdFormat::Gid {
+                    scheme: "shopify".to_owned(),
+                },
+                webhook_signature: WebhookSignatureScheme::HmacSha256 {
+                    header: "X-Shopify-Hmac-Sha256".to_owned(),
+                    encoding: SignatureEncoding::Base64,
+                },
+                ..generic
+            },
+            Platform::Xero => Self {
+                rate_limit: RateLimitSemantics {
+                    remaining_header: Some("X-MinLimit-Remaining".to_owned()),
+                    ..generic.rate_limit.clone()
+                },
+                id_format: IdFormat::Uuid,
+                webhook_signature: WebhookSignatureScheme::HmacSha256 {
+                    header: "x-xero-signature".to_owned(),
+                    encoding: SignatureEncoding::Base64,
+                },
+                ..generic
+            },
+            _ => generic,
+        }
+    }
+
+    /// Adds the platform API version header unless the caller already pinned one
+    pub fn apply_version_header(&self, headers: &mut HeaderMap, version: Option<&str>) {
+        let (Some(name), Some(version)) = (
+            self.api_version_header.as_deref(),
+            version.or(self.default_api_version.as_deref()),
+        ) else {
+            return;
+        };
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(version),
+        ) else {
+            return;
+        };
+        headers.entry(name).or_insert(value);
+    }
+}
+
+/// Quirks for every known platform, with optional overrides loaded from configuration
+#[derive(Debug, Clone, Default)]
+pub struct PlatformQuirksRegistry {
+    overrides: HashMap<Platform, PlatformQuirks>,
+}
+
+impl PlatformQuirksRegistry {
+    pub fn new(overrides: impl IntoIterator<Item = PlatformQuirks>) -> Self {
+        Self {
+            overrides: overrides.into_iter().map(|q| (q.platform, q)).collect(),
+        }
+    }
+
+    pub fn get(&self, platform: Platform) -> PlatformQuirks {
+        self.overrides
+            .get(&platform)
+            .cloned()
+            .unwrap_or_else(|| PlatformQuirks::for_platform(platform))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_id_formats() {
+        let stripe = PlatformQuirks::for_platform(Platform::Stripe);
+        assert!(stripe.id_format.is_valid("cus_OT8j94jEraNXbW"));
+        assert!(!stripe.id_format.is_valid("123"));
+
+        let shopify = PlatformQuirks::for_platform(Platform::Shopify);
+        assert!(shopify.id_format.is_valid("gid://shopify/Order/123"));
+        assert!(shopify.id_format.is_valid("123"));
+        assert!(!shopify.id_format.is_valid("gid://other/Order/123"));
+
+        let xero = PlatformQuirks::for_platform(Platform::Xero);
+        assert!(xero
+            .id_format
+            .is_valid("a3c1b7d4-5f2e-4c1a-9b7e-2d5f8a1c3e4b"));
+    }
+
+    #[test]
+    fn test_rate_limit_semantics() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Shopify-Shop-Api-Call-Limit",
+            HeaderValue::from_static("32/40"),
+        );
+        headers.insert("Retry-After", HeaderValue::from_static("2"));
+        let status = PlatformQuirks::for_platform(Platform::Shopify)
+            .rate_limit
+            .parse(&headers, 0);
+        assert_eq!(status.remaining, Some(8));
+        assert_eq!(status.retry_after, Some(Duration::from_secs(2)));
+
+        let semantics = RateLimitSemantics {
+            remaining_header: Some("x-remaining".to_owned()),
+            reset_header: Some("x-reset".to_owned()),
+            reset_format: ResetFormat::EpochSeconds,
+            retry_after_header: None,
+            bucket_header: None,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("x-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-reset", HeaderValue::from_static("1060"));
+        let status = semantics.parse(&headers, 1000);
+        assert_eq!(status.remaining, Some(0));
+        assert_eq!(status.retry_after, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_version_header_and_overrides() {
+        let mut headers = HeaderMap::new();
+        let stripe = PlatformQuirks::for_platform(Platform::Stripe);
+        stripe.apply_version_header(&mut headers, None);
+        assert_eq!(headers.get("Stripe-Version").unwrap(), "2023-08-16");
+        stripe.apply_version_header(&mut headers, Some("2024-01-01"));
+        assert_eq!(headers.get("Stripe-Version").unwrap(), "2023-08-16");
+
+        let registry = PlatformQuirksRegistry::new([PlatformQuirks {
+            default_api_version: Some("2024-01-01".to_owned()),
+            ..stripe
+        }]);
+        assert_eq!(
+            registry
+                .get(Platform::Stripe)
+                .default_api_version
+                .as_deref(),
+            Some("2024-01-01")
+        );
+        assert_eq!(
+            registry.get(Platform::Sage),
+            PlatformQuirks::generic(Platform::Sage)
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    id::Id,
+    prelude::connection::{connection_definition::ConnectionDefinition, platform_id::PlatformId},
+    ApplicationError, DeletedFilter, IntegrationOSError, MongoStore, Platform,
+};
+use bson::doc;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A platform connections can be made to, and the versions of its API that have a
+/// connection definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredPlatform {
+    pub id: PlatformId,
+    pub name: String,
+    pub versions: BTreeSet<String>,
+    pub connection_definition_ids: Vec<Id>,
+}
+
+impl RegisteredPlatform {
+    fn new(id: PlatformId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            versions: BTreeSet::new(),
+            connection_definition_ids: vec![],
+        }
+    }
+
+    /// Platforms known to this crate have no definition to list their versions from, and
+    /// accept any
+    pub fn supports_version(&self, version: &str) -> bool {
+        self.versions.is_empty() || self.versions.contains(version)
+    }
+}
+
+/// Platforms known at runtime, from the built in [`Platform`]s and the connection
+/// definitions in store, so integrations can be added without releasing this crate
+#[derive(Debug, Clone, Default)]
+pub struct PlatformRegistry {
+    platforms: BTreeMap<PlatformId, RegisteredPlatform>,
+}
+
+impl PlatformRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry of the platforms listed in [`Platform::builtin`]
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        for platform in Platform::builtin() {
+            let id = PlatformId::from(&platform);
+            registry
+                .platforms
+                .entry(id.clone())
+                .or_insert_with(|| RegisteredPlatform::new(id, platform.to_string()));
+        }
+        registry
+    }
+
+    pub fn from_definitions<'a>(
+        definitions: impl IntoIterator<Item = &'a ConnectionDefinition>,
+    ) -> Self {
+        let mut registry = Self::with_builtin();
+        for definition in definitions {
+            registry.register(definition);
+        }
+        registry
+    }
+
+    /// Loads the platforms of every connection definition that isn't deleted
+    pub async fn load(
+        store: &MongoStore<ConnectionDefinition>,
+    ) -> Result<Self, IntegrationOSError> {
+        let definitions = store
+            .get_many_visible(
+                None,
+                DeletedFilter::Exclude,
+                Some(doc! { "_id": 1 }),
+                None,
+                None,
+            )
+            .await?;
+        Ok(Self::from_definitions(&definitions))
+    }
+
+    pub fn register(&mut self, definition: &ConnectionDefinition) {
+        let id = PlatformId::new(&definition.platform);
+        let platform = self
+            .platforms
+            .entry(id.clone())
+            .or_insert_with(|| RegisteredPlatform::new(id, definition.name.clone()));
+        platform
+            .versions
+            .insert(definition.platform_version.clone());
+        if !platform.connection_definition_ids.contains(&definition.id) {
+            platform.connection_definition_ids.push(definition.id);
+        }
+    }
+
+    pub fn get(&self, platform: &str) -> Option<&RegisteredPlatform> {
+        self.platforms.get(&PlatformId::new(platform))
+    }
+
+    pub fn contains(&self, platform: &str) -> bool {
+        self.get(platform).is_some()
+    }
+
+    pub fn platforms(&self) -> impl Iterator<Item = &RegisteredPlatform> {
+        self.platforms.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.platforms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.platforms.is_empty()
+    }
+
+    /// Id of a registered platform, `BadRequest` for unknown ones
+    pub fn validate(&self, platform: &str) -> Result<PlatformId, IntegrationOSError> {
+        self.get(platform)
+            .map(|registered| registered.id.clone())
+            .ok_or_else(|| {
+                ApplicationError::bad_request(
+                    &format!("Unknown platform {platform}"),
+                    Some("platform_registry"),
+                )
+            })
+    }
+
+    /// Id of a registered platform supporting `version`, `BadRequest` otherwise
+    pub fn validate_version(
+        &self,
+        platform: &str,
+        version: &str,
+    ) -> Result<PlatformId, IntegrationOSError> {
+        let id = self.validate(platform)?;
+        match self.platforms.get(&id) {
+            Some(registered) if registered.supports_version(version) => Ok(id),
+            _ => Err(ApplicationError::bad_request(
+                &format!("Platform {id} has no version {version}"),
+                Some("platform_registry"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_registry_adds_platforms_from_definitions() {
+        let definition = |platform: &str, version: &str| {
+            ConnectionDefinition::new(
+                "HubSpot".to_owned(),
+                String::new(),
+                platform.to_owned(),
+                version.to_owned(),
+                "CRM".to_owned(),
+                String::new(),
+                vec![],
+            )
+        };
+        let definitions = [
+            definition("hubspot", "v3"),
+            definition("HubSpot", "v4"),
+            definition("stripe", "2023-10-16"),
+        ];
+        let registry = PlatformRegistry::from_definitions(&definitions);
+
+        assert_eq!(registry.len(), Platform::builtin().len() + 1);
+        let hubspot = registry.get("HUBSPOT").unwrap();
+        assert_eq!(
+            hubspot.versions.iter().collect::<Vec<_>>(),
+            vec!["v3", "v4"]
+        );
+        assert_eq!(hubspot.connection_definition_ids.len(), 2);
+
+        assert!(registry.validate_version("hubspot", "v4").is_ok());
+        assert!(registry.validate_version("hubspot", "v5").is_err());
+        assert!(registry.validate_version("stripe", "2022-11-15").is_err());
+        assert!(registry.validate_version("shopify", "2024-01").is_ok());
+        assert!(registry.validate("quickbooks").is_err());
+    }
+}
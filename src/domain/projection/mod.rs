@@ -0,0 +1,227 @@
+use crate::prelude::shared::record_metadata::RecordMetadata;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use strum::{AsRefStr, Display};
+
+/// Facts emitted by the services that read models are built from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum SystemEvent {
+    #[serde(rename_all = "camelCase")]
+    EventReceived {
+        connection_key: String,
+        platform: String,
+        at: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeliverySucceeded { connection_key: String, at: i64 },
+    #[serde(rename_all = "camelCase")]
+    DeliveryFailed {
+        connection_key: String,
+        at: i64,
+        error: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    ConnectionDeleted { connection_key: String, at: i64 },
+}
+
+impl SystemEvent {
+    pub fn connection_key(&self) -> &str {
+        match self {
+            SystemEvent::EventReceived { connection_key, .. }
+            | SystemEvent::DeliverySucceeded { connection_key, .. }
+            | SystemEvent::DeliveryFailed { connection_key, .. }
+            | SystemEvent::ConnectionDeleted { connection_key, .. } => connection_key,
+        }
+    }
+
+    pub fn at(&self) -> i64 {
+        match self {
+            SystemEvent::EventReceived { at, .. }
+            | SystemEvent::DeliverySucceeded { at, .. }
+            | SystemEvent::DeliveryFailed { at, .. }
+            | SystemEvent::ConnectionDeleted { at, .. } => *at,
+        }
+    }
+}
+
+/// Outcome of applying an event to a read model
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectionChange<M> {
+    Upsert(M),
+    Delete,
+    Unchanged,
+}
+
+/// Folds [`SystemEvent`]s into a denormalized read model, one document per key. Applying
+/// must not depend on anything but the current model and the event.
+pub trait Projection: Send + Sync {
+    type Model: Serialize + DeserializeOwned + Unpin + Send + Sync + 'static;
+
+    fn name(&self) -> &str;
+
+    /// Id of the read model document the event affects, `None` when it's irrelevant
+    fn key(&self, event: &SystemEvent) -> Option<String>;
+
+    fn apply(
+        &self,
+        current: Option<Self::Model>,
+        event: &SystemEvent,
+    ) -> ProjectionChange<Self::Model>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ConnectionHealth {
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+/// Dashboard summary of a connection's traffic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionOverview {
+    #[serde(rename = "_id")]
+    pub connection_key: String,
+    pub platform: Option<String>,
+    pub event_count: u64,
+    pub last_event_at: Option<i64>,
+    pub failure_count: u64,
+    pub consecutive_failures: u32,
+    pub last_failure_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub health: ConnectionHealth,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl ConnectionOverview {
+    pub fn new(connection_key: &str) -> Self {
+        Self {
+            connection_key: connection_key.to_owned(),
+            platform: None,
+            event_count: 0,
+            last_event_at: None,
+            failure_count: 0,
+            consecutive_failures: 0,
+            last_failure_at: None,
+            last_error: None,
+            health: ConnectionHealth::Healthy,
+            record_metadata: RecordMetadata::default(),
+        }
+    }
+}
+
+/// Maintains [`ConnectionOverview`]s. A connection is degraded after one failed delivery
+/// and failing after `failing_after` in a row, until a delivery succeeds again.
+#[derive(Debug, Clone)]
+pub struct ConnectionOverviewProjection {
+    pub failing_after: u32,
+}
+
+impl Default for ConnectionOverviewProjection {
+    fn default() -> Self {
+        Self { failing_after: 5 }
+    }
+}
+
+impl Projection for ConnectionOverviewProjection {
+    type Model = ConnectionOverview;
+
+    fn name(&self) -> &str {
+        "connection-overview"
+    }
+
+    fn key(&self, event: &SystemEvent) -> Option<String> {
+        Some(event.connection_key().to_owned())
+    }
+
+    fn apply(
+        &self,
+        current: Option<ConnectionOverview>,
+        event: &SystemEvent,
+    ) -> ProjectionChange<ConnectionOverview> {
+        let mut overview =
+            current.unwrap_or_else(|| ConnectionOverview::new(event.connection_key()));
+        match event {
+            SystemEvent::EventReceived { platform, at, .. } => {
+                overview.platform = Some(platform.clone());
+                overview.event_count += 1;
+                overview.last_event_at = overview.last_event_at.max(Some(*at));
+            }
+            SystemEvent::DeliverySucceeded { .. } => {
+                overview.consecutive_failures = 0;
+            }
+            SystemEvent::DeliveryFailed { at, error, .. } => {
+                overview.failure_count += 1;
+                overview.consecutive_failures += 1;
+                overview.last_failure_at = overview.last_failure_at.max(Some(*at));
+                overview.last_error = Some(error.clone());
+            }
+            SystemEvent::ConnectionDeleted { .. } => return ProjectionChange::Delete,
+        }
+        overview.health = match overview.consecutive_failures {
+            0 => ConnectionHealth::Healthy,
+            n if n >= self.failing_after => ConnectionHealth::Failing,
+            _ => ConnectionHealth::Degraded,
+        };
+        overview.record_metadata.mark_updated("projection");
+        ProjectionChange::Upsert(overview)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fold(events: &[SystemEvent]) -> Option<ConnectionOverview> {
+        let projection = ConnectionOverviewProjection { failing_after: 2 };
+        events.iter().fold(None, |current, event| {
+            match projection.apply(current, event) {
+                ProjectionChange::Upsert(overview) => Some(overview),
+                ProjectionChange::Delete => None,
+                ProjectionChange::Unchanged => None,
+            }
+        })
+    }
+
+    #[test]
+    fn test_connection_overview_tracks_health() {
+        let key = "live::stripe::default".to_owned();
+        let received = SystemEvent::EventReceived {
+            connection_key: key.clone(),
+            platform: "stripe".to_owned(),
+            at: 10,
+        };
+        let failed = |at| SystemEvent::DeliveryFailed {
+            connection_key: key.clone(),
+            at,
+            error: "timeout".to_owned(),
+        };
+
+        let overview = fold(&[received.clone(), failed(20)]).unwrap();
+        assert_eq!(overview.event_count, 1);
+        assert_eq!(overview.last_event_at, Some(10));
+        assert_eq!(overview.health, ConnectionHealth::Degraded);
+
+        let overview = fold(&[received.clone(), failed(20), failed(30)]).unwrap();
+        assert_eq!(overview.health, ConnectionHealth::Failing);
+        assert_eq!(overview.failure_count, 2);
+        assert_eq!(overview.last_error.as_deref(), Some("timeout"));
+
+        let recovered = SystemEvent::DeliverySucceeded {
+            connection_key: key.clone(),
+            at: 40,
+        };
+        let overview = fold(&[received.clone(), failed(20), failed(30), recovered]).unwrap();
+        assert_eq!(overview.health, ConnectionHealth::Healthy);
+        assert_eq!(overview.failure_count, 2);
+
+        let deleted = SystemEvent::ConnectionDeleted {
+            connection_key: key,
+            at: 50,
+        };
+        assert!(fold(&[received, deleted]).is_none());
+    }
+}
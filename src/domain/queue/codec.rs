@@ -0,0 +1,208 @@
+use crate::{IntegrationOSError, InternalError};
+use serde::{de::DeserializeOwned, Serialize};
+use strum::{AsRefStr, Display, EnumString};
+
+/// First byte of a framed payload. JSON never starts with it, so unframed JSON published
+/// before codecs existed is still told apart.
+const FRAME_MARKER: u8 = 0x00;
+const FRAME_VERSION: u8 = 1;
+const FRAME_LEN: usize = 3;
+
+/// Serialization format of queue payloads and inter-service bodies.
+///
+/// JSON payloads are written as is. Other codecs prefix the payload with a three bytes
+/// frame (marker, frame version, codec) so consumers pick the codec from the payload itself
+/// and producers can switch formats without coordinating a deployment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, AsRefStr, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum PayloadCodec {
+    #[default]
+    Json,
+    /// Smaller and faster to parse than JSON on large events
+    MessagePack,
+    /// Only for [`prost::Message`] types, see [`PayloadCodec::encode_message`]
+    Protobuf,
+}
+
+impl PayloadCodec {
+    fn id(&self) -> u8 {
+        match self {
+            PayloadCodec::Json => 0,
+            PayloadCodec::MessagePack => 1,
+            PayloadCodec::Protobuf => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(PayloadCodec::Json),
+            1 => Some(PayloadCodec::MessagePack),
+            2 => Some(PayloadCodec::Protobuf),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PayloadCodec::Json => "application/json",
+            PayloadCodec::MessagePack => "application/msgpack",
+            PayloadCodec::Protobuf => "application/protobuf",
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+        match mime.as_str() {
+            "application/json" => Some(PayloadCodec::Json),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(PayloadCodec::MessagePack)
+            }
+            "application/protobuf" | "application/x-protobuf" => Some(PayloadCodec::Protobuf),
+            _ => None,
+        }
+    }
+
+    /// First codec of an `Accept` header among `supported`, in the client's order,
+    /// falling back to JSON
+    pub fn negotiate(accept: &str, supported: &[PayloadCodec]) -> Self {
+        accept
+            .split(',')
+            .filter_map(|media| {
+                let mut params = media.split(';');
+                let mime = params.next()?.trim();
+                let quality = params
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (quality > 0.0).then_some((mime, quality))
+            })
+            .fold(None::<(PayloadCodec, f32)>, |best, (mime, quality)| {
+                match PayloadCodec::from_content_type(mime) {
+                    Some(codec)
+                        if supported.contains(&codec)
+                            && best.map(|(_, q)| quality > q).unwrap_or(true) =>
+                    {
+                        Some((codec, quality))
+                    }
+                    _ => best,
+                }
+            })
+            .map(|(codec, _)| codec)
+            .unwrap_or_default()
+    }
+
+    fn frame(&self, mut body: Vec<u8>) -> Vec<u8> {
+        match self {
+            PayloadCodec::Json => body,
+            codec => {
+                body.splice(0..0, [FRAME_MARKER, FRAME_VERSION, codec.id()]);
+                body
+            }
+        }
+    }
+
+    /// Codec of a payload and the body without its frame
+    pub fn detect(payload: &[u8]) -> Result<(Self, &[u8]), IntegrationOSError> {
+        match payload {
+            [FRAME_MARKER, FRAME_VERSION, id, ..] => PayloadCodec::from_id(*id)
+                .map(|codec| (codec, &payload[FRAME_LEN..]))
+                .ok_or_else(|| {
+                    InternalError::deserialize_error(
+                        &format!("Unknown payload codec {id}"),
+                        Some("codec"),
+                    )
+                }),
+            [FRAME_MARKER, version, ..] => Err(InternalError::deserialize_error(
+                &format!("Unsupported payload frame version {version}"),
+                Some("codec"),
+            )),
+            _ => Ok((PayloadCodec::Json, payload)),
+        }
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, IntegrationOSError> {
+        let body = match self {
+            PayloadCodec::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            // Named fields keep flattened and renamed fields working
+            PayloadCodec::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            PayloadCodec::Protobuf => Err("Protobuf payloads need a prost message".to_owned()),
+        }
+        .map_err(|e| InternalError::serialize_error(&e, Some("codec")))?;
+        Ok(self.frame(body))
+    }
+
+    /// Decodes a payload written by [`PayloadCodec::encode`] with any codec
+    pub fn decode<T: DeserializeOwned>(payload: &[u8]) -> Result<T, IntegrationOSError> {
+        let (codec, body) = PayloadCodec::detect(payload)?;
+        match codec {
+            PayloadCodec::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+            PayloadCodec::MessagePack => rmp_serde::from_slice(body).map_err(|e| e.to_string()),
+            PayloadCodec::Protobuf => Err("Protobuf payloads need a prost message".to_owned()),
+        }
+        .map_err(|e| InternalError::deserialize_error(&e, Some("codec")))
+    }
+
+    pub fn encode_message<M: prost::Message>(message: &M) -> Vec<u8> {
+        PayloadCodec::Protobuf.frame(message.encode_to_vec())
+    }
+
+    pub fn decode_message<M: prost::Message + Default>(
+        payload: &[u8],
+    ) -> Result<M, IntegrationOSError> {
+        match PayloadCodec::detect(payload)? {
+            (PayloadCodec::Protobuf, body) => M::decode(body)
+                .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("codec"))),
+            (codec, _) => Err(InternalError::deserialize_error(
+                &format!("Expected a protobuf payload, got {codec}"),
+                Some("codec"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::queue::{QueueMessage, ScheduledTrigger};
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    #[test]
+    fn test_codecs_round_trip_and_negotiate() {
+        let message = QueueMessage::ScheduledTrigger(ScheduledTrigger {
+            name: "sync".to_owned(),
+            connection_key: Some("stripe::test".to_owned()),
+            scheduled_at: Utc.timestamp_millis_opt(1_000).unwrap(),
+            payload: json!({ "full": true, "pages": [1, 2] }),
+        });
+        let json = PayloadCodec::Json.encode(&message).unwrap();
+        assert_eq!(json, message.to_vec().unwrap());
+        let msgpack = PayloadCodec::MessagePack.encode(&message).unwrap();
+        assert_eq!(&msgpack[..FRAME_LEN], &[FRAME_MARKER, FRAME_VERSION, 1]);
+        assert!(msgpack.len() < json.len());
+        for payload in [json, msgpack] {
+            let decoded: QueueMessage = PayloadCodec::decode(&payload).unwrap();
+            assert_eq!(
+                serde_json::to_value(decoded).unwrap(),
+                serde_json::to_value(&message).unwrap()
+            );
+        }
+        assert!(PayloadCodec::Protobuf.encode(&message).is_err());
+        assert!(PayloadCodec::decode::<QueueMessage>(&[FRAME_MARKER, 9, 1]).is_err());
+
+        let supported = [PayloadCodec::Json, PayloadCodec::MessagePack];
+        assert_eq!(
+            PayloadCodec::negotiate("application/msgpack, application/json;q=0.5", &supported),
+            PayloadCodec::MessagePack
+        );
+        assert_eq!(
+            PayloadCodec::negotiate("application/protobuf, */*", &supported),
+            PayloadCodec::Json
+        );
+        assert_eq!(
+            PayloadCodec::negotiate("application/json, application/x-msgpack;q=0", &supported),
+            PayloadCodec::Json
+        );
+        assert_eq!("message-pack".parse(), Ok(PayloadCodec::MessagePack));
+    }
+}
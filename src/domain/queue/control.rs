@@ -0,0 +1,126 @@
+use crate::id::{prefix::IdPrefix, Id};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "command")]
+pub enum ControlCommand {
+    PauseConnection { connection_key: String },
+    ResumeConnection { connection_key: String },
+    InvalidateCache { key: String },
+    ReloadDefinitions,
+}
+
+/// A control command broadcast to every live worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlEnvelope {
+    pub id: Id,
+    pub command: ControlCommand,
+    pub issued_by: Option<String>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub issued_at: DateTime<Utc>,
+}
+
+impl ControlEnvelope {
+    pub fn new(command: ControlCommand, issued_by: Option<String>) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Queue),
+            command,
+            issued_by,
+            issued_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum AckStatus {
+    Applied,
+    Failed { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlAck {
+    pub command_id: Id,
+    pub worker_id: String,
+    #[serde(flatten)]
+    pub status: AckStatus,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub acked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AckSummary {
+    pub applied: BTreeSet<String>,
+    pub failed: HashMap<String, String>,
+    pub pending: BTreeSet<String>,
+}
+
+impl AckSummary {
+    /// Compares the acknowledgments received against the workers the command was sent to
+    pub fn new<'a>(recipients: impl IntoIterator<Item = &'a str>, acks: &[ControlAck]) -> Self {
+        let mut summary = AckSummary {
+            pending: recipients.into_iter().map(ToOwned::to_owned).collect(),
+            ..Default::default()
+        };
+        for ack in acks {
+            summary.pending.remove(&ack.worker_id);
+            match &ack.status {
+                AckStatus::Applied => {
+                    summary.applied.insert(ack.worker_id.clone());
+                }
+                AckStatus::Failed { message } => {
+                    summary
+                        .failed
+                        .insert(ack.worker_id.clone(), message.clone());
+                }
+            }
+        }
+        summary
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ack_summary() {
+        let envelope = ControlEnvelope::new(
+            ControlCommand::InvalidateCache {
+                key: "connection::stripe".to_owned(),
+            },
+            Some("operator".to_owned()),
+        );
+        let ack = |worker_id: &str, status: AckStatus| ControlAck {
+            command_id: envelope.id,
+            worker_id: worker_id.to_owned(),
+            status,
+            acked_at: Utc::now(),
+        };
+        let acks = vec![
+            ack("worker-1", AckStatus::Applied),
+            ack(
+                "worker-2",
+                AckStatus::Failed {
+                    message: "boom".to_owned(),
+                },
+            ),
+        ];
+
+        let summary = AckSummary::new(["worker-1", "worker-2", "worker-3"], &acks);
+        assert_eq!(summary.applied, BTreeSet::from(["worker-1".to_owned()]));
+        assert_eq!(summary.failed["worker-2"], "boom");
+        assert_eq!(summary.pending, BTreeSet::from(["worker-3".to_owned()]));
+        assert!(!summary.is_complete());
+        assert!(AckSummary::new(["worker-1", "worker-2"], &acks).is_complete());
+    }
+}
@@ -0,0 +1,204 @@
+use super::{codec::PayloadCodec, control::ControlEnvelope};
+use crate::{
+    prelude::event::event_with_context::EventWithContext, Id, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::{AsRefStr, Display};
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTrigger {
+    pub name: String,
+    pub connection_key: Option<String>,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    pub scheduled_at: DateTime<Utc>,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayRequest {
+    pub event_keys: Vec<Id>,
+    pub requested_by: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, AsRefStr)]
+#[strum(serialize_all = "camelCase")]
+pub enum QueueMessageKind {
+    EventWithContext,
+    ScheduledTrigger,
+    ReplayRequest,
+    ControlCommand,
+}
+
+/// Envelope of everything published on the worker queues, discriminated by `kind`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "payload")]
+pub enum QueueMessage {
+    EventWithContext(Box<EventWithContext>),
+    ScheduledTrigger(ScheduledTrigger),
+    ReplayRequest(ReplayRequest),
+    ControlCommand(ControlEnvelope),
+}
+
+impl From<EventWithContext> for QueueMessage {
+    fn from(message: EventWithContext) -> Self {
+        QueueMessage::EventWithContext(Box::new(message))
+    }
+}
+
+impl QueueMessage {
+    pub fn kind(&self) -> QueueMessageKind {
+        match self {
+            QueueMessage::EventWithContext(_) => QueueMessageKind::EventWithContext,
+            QueueMessage::ScheduledTrigger(_) => QueueMessageKind::ScheduledTrigger,
+            QueueMessage::ReplayRequest(_) => QueueMessageKind::ReplayRequest,
+            QueueMessage::ControlCommand(_) => QueueMessageKind::ControlCommand,
+        }
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, IntegrationOSError> {
+        serde_json::to_vec(self)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("queue_message")))
+    }
+
+    pub fn encode(&self, codec: PayloadCodec) -> Result<Vec<u8>, IntegrationOSError> {
+        codec.encode(self)
+    }
+
+    /// Decodes an envelope in whichever codec it was written, falling back to a bare
+    /// [`EventWithContext`] for payloads published before the envelope existed
+    pub fn from_slice(payload: &[u8]) -> Result<Self, IntegrationOSError> {
+        PayloadCodec::decode::<QueueMessage>(payload).or_else(|e| {
+            PayloadCodec::decode::<EventWithContext>(payload)
+                .map(Into::into)
+                .map_err(|_| e)
+        })
+    }
+
+    pub async fn dispatch<C: QueueConsumer + ?Sized>(
+        self,
+        consumer: &C,
+    ) -> Result<(), IntegrationOSError> {
+        match self {
+            QueueMessage::EventWithContext(message) => consumer.on_event(*message).await,
+            QueueMessage::ScheduledTrigger(trigger) => consumer.on_scheduled_trigger(trigger).await,
+            QueueMessage::ReplayRequest(request) => consumer.on_replay_request(request).await,
+            QueueMessage::ControlCommand(command) => consumer.on_control_command(command).await,
+        }
+    }
+}
+
+fn ignore(kind: QueueMessageKind) -> Result<(), IntegrationOSError> {
+    warn!("Ignoring {kind} message, consumer does not handle it");
+    Ok(())
+}
+
+/// Per-kind handlers for queue messages. Kinds a consumer doesn't override are logged and
+/// acknowledged so workers can share a queue without knowing every workflow.
+#[async_trait]
+pub trait QueueConsumer: Send + Sync {
+    async fn on_event(&self, _message: EventWithContext) -> Result<(), IntegrationOSError> {
+        ignore(QueueMessageKind::EventWithContext)
+    }
+
+    async fn on_scheduled_trigger(
+        &self,
+        _trigger: ScheduledTrigger,
+    ) -> Result<(), IntegrationOSError> {
+        ignore(QueueMessageKind::ScheduledTrigger)
+    }
+
+    async fn on_replay_request(&self, _request: ReplayRequest) -> Result<(), IntegrationOSError> {
+        ignore(QueueMessageKind::ReplayRequest)
+    }
+
+    async fn on_control_command(
+        &self,
+        _command: ControlEnvelope,
+    ) -> Result<(), IntegrationOSError> {
+        ignore(QueueMessageKind::ControlCommand)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::queue::control::ControlCommand;
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl QueueConsumer for Recorder {
+        async fn on_scheduled_trigger(
+            &self,
+            trigger: ScheduledTrigger,
+        ) -> Result<(), IntegrationOSError> {
+            self.0.lock().unwrap().push(trigger.name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_envelope_format() {
+        let message = QueueMessage::ScheduledTrigger(ScheduledTrigger {
+            name: "sync".to_owned(),
+            connection_key: Some("stripe::test".to_owned()),
+            scheduled_at: Utc.timestamp_millis_opt(1_000).unwrap(),
+            payload: json!({ "full": true }),
+        });
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "kind": "scheduledTrigger",
+                "payload": {
+                    "name": "sync",
+                    "connectionKey": "stripe::test",
+                    "scheduledAt": 1_000,
+                    "payload": { "full": true }
+                }
+            })
+        );
+
+        let command = QueueMessage::ControlCommand(ControlEnvelope::new(
+            ControlCommand::ReloadDefinitions,
+            None,
+        ));
+        let decoded = QueueMessage::from_slice(&command.to_vec().unwrap()).unwrap();
+        assert_eq!(decoded.kind(), QueueMessageKind::ControlCommand);
+        assert!(QueueMessage::from_slice(b"{\"kind\":\"unknown\"}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let recorder = Recorder::default();
+        QueueMessage::ScheduledTrigger(ScheduledTrigger {
+            name: "sync".to_owned(),
+            connection_key: None,
+            scheduled_at: Utc::now(),
+            payload: Value::Null,
+        })
+        .dispatch(&recorder)
+        .await
+        .unwrap();
+        QueueMessage::ReplayRequest(ReplayRequest {
+            event_keys: vec![],
+            requested_by: None,
+            reason: None,
+        })
+        .dispatch(&recorder)
+        .await
+        .unwrap();
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["sync".to_owned()]);
+    }
+}
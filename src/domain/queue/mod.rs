@@ -0,0 +1,9 @@
+pub mod codec;
+pub mod control;
+pub mod message;
+pub mod outbox;
+
+pub use codec::*;
+pub use control::*;
+pub use message::*;
+pub use outbox::*;
@@ -0,0 +1,76 @@
+use crate::{
+    id::{prefix::IdPrefix, Id, IdGenerator, SystemIdGenerator},
+    prelude::shared::record_metadata::RecordMetadata,
+    IntegrationOSError, InternalError,
+};
+use serde::{Deserialize, Serialize};
+
+/// A message written in the same transaction as the record it announces, published to
+/// its queue by the outbox relay afterwards
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxMessage {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    /// Redis list the payload is pushed onto
+    pub queue: String,
+    /// The exact bytes published, as JSON
+    pub payload: String,
+    #[serde(default)]
+    pub attempts: u32,
+    /// Until when a relay holds the message, in epoch ms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl OutboxMessage {
+    pub fn new<T: Serialize>(
+        queue: impl Into<String>,
+        payload: &T,
+    ) -> Result<Self, IntegrationOSError> {
+        Self::new_with_generator(queue, payload, &SystemIdGenerator)
+    }
+
+    pub fn new_with_generator<T: Serialize>(
+        queue: impl Into<String>,
+        payload: &T,
+        generator: &dyn IdGenerator,
+    ) -> Result<Self, IntegrationOSError> {
+        let payload = serde_json::to_string(payload)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("outbox")))?;
+        let id = generator.id(IdPrefix::Outbox);
+        Ok(Self {
+            id,
+            queue: queue.into(),
+            payload,
+            attempts: 0,
+            locked_until: None,
+            last_error: None,
+            record_metadata: RecordMetadata::created_at(id.time()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_outbox_message_keeps_exact_payload() {
+        let message = OutboxMessage::new("events", &json!({ "name": "order.created" })).unwrap();
+        assert_eq!(message.payload, r#"{"name":"order.created"}"#);
+
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(value["queue"], "events");
+        assert!(value.get("lockedUntil").is_none());
+        assert_eq!(
+            serde_json::from_value::<OutboxMessage>(value).unwrap(),
+            message
+        );
+    }
+}
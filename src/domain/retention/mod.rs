@@ -0,0 +1,100 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::shared::{ownership::Ownership, record_metadata::RecordMetadata},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How long the events of an ownership are kept, overriding the platform default to
+/// honour customer contracts (e.g. 30 days for one customer, 13 months for another)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionSetting {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub ownership: Ownership,
+    pub retention_days: u32,
+    #[serde(flatten, default)]
+    pub record_metadata: RecordMetadata,
+}
+
+impl RetentionSetting {
+    pub fn new(ownership: Ownership, retention_days: u32) -> Self {
+        Self {
+            id: Id::now(IdPrefix::Settings),
+            ownership,
+            retention_days,
+            record_metadata: Default::default(),
+        }
+    }
+}
+
+/// Resolved retention windows, keyed by the ownership (buildable) id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub default_days: u32,
+    pub overrides: HashMap<String, u32>,
+}
+
+impl RetentionPolicy {
+    pub fn new(default_days: u32, settings: &[RetentionSetting]) -> Self {
+        Self {
+            default_days,
+            overrides: settings
+                .iter()
+                .filter(|s| s.record_metadata.active && !s.record_metadata.deleted)
+                .map(|s| (s.ownership.id.to_string(), s.retention_days))
+                .collect(),
+        }
+    }
+
+    pub fn days_for(&self, ownership_id: &str) -> u32 {
+        self.overrides
+            .get(ownership_id)
+            .copied()
+            .unwrap_or(self.default_days)
+    }
+
+    pub fn cutoff(days: u32, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(days as i64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ownership(id: &str) -> Ownership {
+        Ownership {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        let mut inactive = RetentionSetting::new(ownership("c"), 1);
+        inactive.record_metadata.active = false;
+        let policy = RetentionPolicy::new(
+            90,
+            &[
+                RetentionSetting::new(ownership("a"), 30),
+                RetentionSetting::new(ownership("b"), 395),
+                inactive,
+            ],
+        );
+
+        assert_eq!(policy.days_for("a"), 30);
+        assert_eq!(policy.days_for("b"), 395);
+        assert_eq!(policy.days_for("c"), 90);
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap();
+        assert_eq!(
+            RetentionPolicy::cutoff(30, now),
+            Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()
+        );
+    }
+}
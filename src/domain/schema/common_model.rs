@@ -1,9 +1,10 @@
 use crate::{
-    api_model_config::Lang,
     id::{prefix::IdPrefix, Id},
-    prelude::{shared::record_metadata::RecordMetadata, MongoStore, StringExt},
-    IntegrationOSError, InternalError,
+    prelude::{shared::record_metadata::RecordMetadata, StringExt},
 };
+#[cfg(feature = "backend")]
+use crate::{prelude::MongoStore, IntegrationOSError, InternalError};
+#[cfg(feature = "backend")]
 use async_recursion::async_recursion;
 use bson::doc;
 use indexmap::IndexMap;
@@ -17,6 +18,16 @@ use std::{
     ops::Deref,
 };
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Default)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    JavaScript,
+    TypeScript,
+    Rust,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 pub struct CommonModel {
@@ -552,6 +563,7 @@ impl CommonModel {
     /// * `lang` - The language to generate the model in
     /// * `cm_store` - The store for common models
     /// * `ce_store` - The store for common enums
+    #[cfg(feature = "backend")]
     pub async fn generate_as_expanded(
         &self,
         lang: &Lang,
@@ -597,6 +609,7 @@ impl CommonModel {
         )
     }
 
+    #[cfg(feature = "backend")]
     async fn as_typescript_expanded(
         &self,
         cm_store: &MongoStore<CommonModel>,
@@ -684,6 +697,7 @@ impl CommonModel {
         }
     }
 
+    #[cfg(feature = "backend")]
     async fn as_rust_expanded(
         &self,
         cm_store: &MongoStore<CommonModel>,
@@ -899,6 +913,7 @@ impl CommonModel {
         models
     }
 
+    #[cfg(feature = "backend")]
     pub async fn expand_all(
         &self,
         cm_store: MongoStore<CommonModel>,
@@ -909,6 +924,7 @@ impl CommonModel {
             .await
     }
 
+    #[cfg(feature = "backend")]
     #[async_recursion]
     async fn expand_all_recursive(
         &self,
@@ -1053,6 +1069,7 @@ impl CommonModel {
     ///
     /// # Returns
     /// A vector of all the enum references and flat enums that are not common
+    #[cfg(feature = "backend")]
     pub async fn fetch_all_enum_references(
         &self,
         cm_store: MongoStore<CommonModel>,
@@ -1136,6 +1153,7 @@ impl CommonModel {
     ///
     /// * A map of the children models with their names as keys
     /// * A set of the names of the children models that were not found
+    #[cfg(feature = "backend")]
     pub async fn fetch_all_children_common_models(
         &self,
         store: MongoStore<CommonModel>,
@@ -1216,6 +1234,7 @@ impl CommonModel {
         Ok((map, not_found))
     }
 
+    #[cfg(feature = "backend")]
     pub async fn get_all_common_models(
         store: MongoStore<CommonModel>,
     ) -> Result<Vec<String>, IntegrationOSError> {
@@ -1267,6 +1286,7 @@ impl CommonModel {
     }
 }
 
+#[cfg(feature = "backend")]
 impl Expandable {
     pub async fn expand(&self, store: MongoStore<CommonModel>) -> Result<Self, IntegrationOSError> {
         Ok(match self {
@@ -1289,7 +1309,9 @@ impl Expandable {
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "backend")]
     use crate::Store;
+    #[cfg(feature = "backend")]
     use mongodb::Client;
 
     use super::*;
@@ -1337,6 +1359,7 @@ mod tests {
         assert_eq!(data_type.as_rust_ref("String".into()), "Vec<String>");
     }
 
+    #[cfg(feature = "backend")]
     #[tokio::test]
     async fn test_common_model_as_rust_struct_is_correct() {
         let common_model = CommonModel {
@@ -0,0 +1,341 @@
+use super::json_schema::{JsonSchema, Property};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// One difference between two versions of a schema. Fields are named by their dotted path
+/// from the root, with `[]` for the items of arrays, e.g. `lineItems[].sku`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "change")]
+pub enum FieldChange {
+    Added {
+        path: String,
+        r#type: String,
+        required: bool,
+    },
+    Removed {
+        path: String,
+        r#type: String,
+        required: bool,
+    },
+    /// A field removed along with a field of the same type added next to it under the
+    /// same name once case and separators are ignored, e.g. `first_name` to `firstName`
+    Renamed {
+        from: String,
+        to: String,
+        r#type: String,
+    },
+    TypeChanged {
+        path: String,
+        from: String,
+        to: String,
+    },
+    BecameRequired {
+        path: String,
+    },
+    BecameOptional {
+        path: String,
+    },
+}
+
+impl FieldChange {
+    /// Whether consumers of the older version may break on the newer one: anything
+    /// removed, renamed or retyped, and fields that are newly required
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            FieldChange::Added { required, .. } => *required,
+            FieldChange::Removed { .. }
+            | FieldChange::Renamed { .. }
+            | FieldChange::TypeChanged { .. }
+            | FieldChange::BecameRequired { .. } => true,
+            FieldChange::BecameOptional { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Compatibility {
+    Identical,
+    /// Only additions, so the `platform_version` may stay the same
+    Compatible,
+    /// The newer schema needs a new `platform_version`
+    Breaking,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FieldInfo {
+    r#type: String,
+    required: bool,
+}
+
+fn collect(
+    properties: &HashMap<String, Property>,
+    required: &[String],
+    prefix: &str,
+    fields: &mut BTreeMap<String, FieldInfo>,
+) {
+    for (name, property) in properties {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{prefix}.{name}")
+        };
+        collect_property(property, required.contains(name), path, fields);
+    }
+}
+
+fn collect_property(
+    property: &Property,
+    required: bool,
+    path: String,
+    fields: &mut BTreeMap<String, FieldInfo>,
+) {
+    if let Some(properties) = &property.properties {
+        collect(properties, &[], &path, fields);
+    }
+    if let Some(items) = &property.items {
+        collect_property(items, false, format!("{path}[]"), fields);
+    }
+    fields.insert(
+        path,
+        FieldInfo {
+            r#type: property.r#type.clone(),
+            required,
+        },
+    );
+}
+
+fn fields(schema: &JsonSchema) -> BTreeMap<String, FieldInfo> {
+    let mut fields = BTreeMap::new();
+    collect(
+        &schema.properties,
+        schema.required.as_deref().unwrap_or_default(),
+        "",
+        &mut fields,
+    );
+    if let Some(items) = &schema.items {
+        collect_property(items, false, "[]".to_owned(), &mut fields);
+    }
+    fields
+}
+
+/// Parent path and name of a field, with the name lowercased and stripped of separators
+fn rename_key(path: &str) -> (&str, String) {
+    let (parent, name) = path.rsplit_once('.').unwrap_or(("", path));
+    let name = name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '[' || *c == ']')
+        .flat_map(char::to_lowercase)
+        .collect();
+    (parent, name)
+}
+
+/// Changes from one version of a schema to the next
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDiff {
+    pub changes: Vec<FieldChange>,
+}
+
+impl SchemaDiff {
+    pub fn between(old: &JsonSchema, new: &JsonSchema) -> Self {
+        let (old, new) = (fields(old), fields(new));
+        let mut changes = vec![];
+
+        let mut removed = old
+            .keys()
+            .filter(|path| !new.contains_key(*path))
+            .collect::<BTreeSet<_>>();
+        let mut added = new
+            .keys()
+            .filter(|path| !old.contains_key(*path))
+            .collect::<BTreeSet<_>>();
+
+        for from in removed.clone() {
+            if !removed.contains(from) {
+                continue;
+            }
+            let to = added
+                .iter()
+                .find(|to| {
+                    rename_key(from) == rename_key(to) && old[from].r#type == new[**to].r#type
+                })
+                .copied();
+            if let Some(to) = to {
+                // Nested fields of a renamed object move with it
+                let (from_prefix, to_prefix) = (format!("{from}."), format!("{to}."));
+                removed.retain(|path| *path != from && !path.starts_with(&from_prefix));
+                added.retain(|path| *path != to && !path.starts_with(&to_prefix));
+                changes.push(FieldChange::Renamed {
+                    from: from.clone(),
+                    to: to.clone(),
+                    r#type: old[from].r#type.clone(),
+                });
+            }
+        }
+
+        changes.extend(removed.into_iter().map(|path| FieldChange::Removed {
+            path: path.clone(),
+            r#type: old[path].r#type.clone(),
+            required: old[path].required,
+        }));
+        changes.extend(added.into_iter().map(|path| FieldChange::Added {
+            path: path.clone(),
+            r#type: new[path].r#type.clone(),
+            required: new[path].required,
+        }));
+
+        for (path, before) in &old {
+            let Some(after) = new.get(path) else {
+                continue;
+            };
+            if before.r#type != after.r#type {
+                changes.push(FieldChange::TypeChanged {
+                    path: path.clone(),
+                    from: before.r#type.clone(),
+                    to: after.r#type.clone(),
+                });
+            }
+            match (before.required, after.required) {
+                (false, true) => changes.push(FieldChange::BecameRequired { path: path.clone() }),
+                (true, false) => changes.push(FieldChange::BecameOptional { path: path.clone() }),
+                _ => {}
+            }
+        }
+
+        Self { changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &FieldChange> {
+        self.changes.iter().filter(|change| change.is_breaking())
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.breaking_changes().next().is_some()
+    }
+
+    pub fn compatibility(&self) -> Compatibility {
+        if self.is_empty() {
+            Compatibility::Identical
+        } else if self.is_breaking() {
+            Compatibility::Breaking
+        } else {
+            Compatibility::Compatible
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_schema_diff_reports_breaking_changes() {
+        let old = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "first_name": { "type": "string" },
+                "total": { "type": "number" },
+                "legacy": { "type": "string" },
+                "note": { "type": "string" },
+                "lines": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "sku": { "type": "string" } }
+                    }
+                }
+            },
+            "required": ["id", "legacy"]
+        }))
+        .unwrap();
+        let new = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "firstName": { "type": "string" },
+                "total": { "type": "string" },
+                "note": { "type": "string" },
+                "lines": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "sku": { "type": "string" },
+                            "quantity": { "type": "integer" }
+                        }
+                    }
+                },
+                "currency": { "type": "string" }
+            },
+            "required": ["id", "note"]
+        }))
+        .unwrap();
+
+        let diff = SchemaDiff::between(&old, &new);
+        assert_eq!(
+            diff.changes,
+            vec![
+                FieldChange::Renamed {
+                    from: "first_name".to_owned(),
+                    to: "firstName".to_owned(),
+                    r#type: "string".to_owned(),
+                },
+                FieldChange::Removed {
+                    path: "legacy".to_owned(),
+                    r#type: "string".to_owned(),
+                    required: true,
+                },
+                FieldChange::Added {
+                    path: "currency".to_owned(),
+                    r#type: "string".to_owned(),
+                    required: false,
+                },
+                FieldChange::Added {
+                    path: "lines[].quantity".to_owned(),
+                    r#type: "integer".to_owned(),
+                    required: false,
+                },
+                FieldChange::BecameRequired {
+                    path: "note".to_owned(),
+                },
+                FieldChange::TypeChanged {
+                    path: "total".to_owned(),
+                    from: "number".to_owned(),
+                    to: "string".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(diff.compatibility(), Compatibility::Breaking);
+        assert_eq!(diff.breaking_changes().count(), 4);
+
+        let additive = SchemaDiff::between(
+            &old,
+            &JsonSchema {
+                properties: old
+                    .properties
+                    .clone()
+                    .into_iter()
+                    .chain(
+                        new.properties
+                            .clone()
+                            .into_iter()
+                            .filter(|(k, _)| k == "currency"),
+                    )
+                    .collect(),
+                ..old.clone()
+            },
+        );
+        assert_eq!(additive.compatibility(), Compatibility::Compatible);
+        assert_eq!(
+            SchemaDiff::between(&old, &old).compatibility(),
+            Compatibility::Identical
+        );
+    }
+}
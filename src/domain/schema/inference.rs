@@ -0,0 +1,245 @@
+#[cfg(feature = "backend")]
+use crate::{prelude::event::Event, IntegrationOSError, InternalError};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InferenceConfig {
+    /// Maximum number of distinct string values for a field to be proposed as an enum
+    pub max_enum_values: usize,
+    /// Minimum number of observations of a field before proposing an enum
+    pub min_enum_samples: u64,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            max_enum_values: 10,
+            min_enum_samples: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Observation {
+    samples: u64,
+    types: BTreeSet<&'static str>,
+    values: BTreeSet<String>,
+    too_many_values: bool,
+    objects: u64,
+    properties: BTreeMap<String, (u64, Observation)>,
+    items: Option<Box<Observation>>,
+}
+
+impl Observation {
+    fn observe(&mut self, value: &Value, config: &InferenceConfig) {
+        self.samples += 1;
+        match value {
+            Value::Null => {
+                self.types.insert("null");
+            }
+            Value::Bool(_) => {
+                self.types.insert("boolean");
+            }
+            Value::Number(n) if n.is_i64() || n.is_u64() => {
+                self.types.insert("integer");
+            }
+            Value::Number(_) => {
+                self.types.insert("number");
+            }
+            Value::String(s) => {
+                self.types.insert("string");
+                if !self.too_many_values {
+                    self.values.insert(s.to_owned());
+                    if self.values.len() > config.max_enum_values {
+                        self.too_many_values = true;
+                        self.values.clear();
+                    }
+                }
+            }
+            Value::Array(items) => {
+                self.types.insert("array");
+                let observation = self.items.get_or_insert_with(Default::default);
+                for item in items {
+                    observation.observe(item, config);
+                }
+            }
+            Value::Object(map) => {
+                self.types.insert("object");
+                self.objects += 1;
+                for (key, value) in map {
+                    let (seen, observation) = self.properties.entry(key.to_owned()).or_default();
+                    *seen += 1;
+                    observation.observe(value, config);
+                }
+            }
+        }
+    }
+
+    fn to_schema(&self, config: &InferenceConfig) -> Value {
+        let mut schema = Map::new();
+
+        let mut types: Vec<&str> = self.types.iter().copied().collect();
+        if types.contains(&"number") {
+            types.retain(|t| *t != "integer");
+        }
+        match types.as_slice() {
+            [] => {}
+            [single] => {
+                schema.insert("type".to_owned(), json!(single));
+            }
+            many => {
+                schema.insert("type".to_owned(), json!(many));
+            }
+        }
+
+        let non_null_strings = self.types.iter().all(|t| *t == "string" || *t == "null");
+        if non_null_strings
+            && !self.too_many_values
+            && !self.values.is_empty()
+            && self.samples >= config.min_enum_samples
+            && (self.values.len() as u64) < self.samples
+        {
+            schema.insert("enum".to_owned(), json!(self.values));
+        }
+
+        if self.types.contains("object") {
+            let properties: Map<String, Value> = self
+                .properties
+                .iter()
+                .map(|(key, (_, observation))| (key.to_owned(), observation.to_schema(config)))
+                .collect();
+            let required: Vec<&String> = self
+                .properties
+                .iter()
+                .filter(|(_, (seen, _))| *seen == self.objects)
+                .map(|(key, _)| key)
+                .collect();
+            schema.insert("properties".to_owned(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".to_owned(), json!(required));
+            }
+        }
+
+        if let Some(items) = &self.items {
+            schema.insert("items".to_owned(), items.to_schema(config));
+        }
+
+        Value::Object(schema)
+    }
+}
+
+/// Accumulates payloads of event types that don't have a model yet and proposes a
+/// draft JSON Schema for them, to be reviewed by the connector author.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaInferencer {
+    config: InferenceConfig,
+    observations: HashMap<String, Observation>,
+}
+
+impl SchemaInferencer {
+    pub fn new(config: InferenceConfig) -> Self {
+        Self {
+            config,
+            observations: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event_type: &str, payload: &Value) {
+        self.observations
+            .entry(event_type.to_owned())
+            .or_default()
+            .observe(payload, &self.config);
+    }
+
+    #[cfg(feature = "backend")]
+    pub fn observe_event(&mut self, event: &Event) -> Result<(), IntegrationOSError> {
+        let payload: Value = serde_json::from_str(&event.body).map_err(|e| {
+            InternalError::deserialize_error(&e.to_string(), Some("schema_inference"))
+        })?;
+        self.observe(&event.name, &payload);
+        Ok(())
+    }
+
+    pub fn event_types(&self) -> Vec<&str> {
+        let mut types: Vec<&str> = self.observations.keys().map(String::as_str).collect();
+        types.sort();
+        types
+    }
+
+    pub fn samples(&self, event_type: &str) -> u64 {
+        self.observations
+            .get(event_type)
+            .map(|o| o.samples)
+            .unwrap_or_default()
+    }
+
+    pub fn infer(&self, event_type: &str) -> Option<Value> {
+        let observation = self.observations.get(event_type)?;
+        let mut schema = observation.to_schema(&self.config);
+        if let Value::Object(map) = &mut schema {
+            map.insert("$schema".to_owned(), json!(DRAFT));
+            map.insert("title".to_owned(), json!(event_type));
+        }
+        Some(schema)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_infer_types_and_optionality() {
+        let mut inferencer = SchemaInferencer::default();
+        inferencer.observe(
+            "customer.created",
+            &json!({ "id": 1, "email": "a@b.c", "amount": 1.5, "tags": ["x"] }),
+        );
+        inferencer.observe(
+            "customer.created",
+            &json!({ "id": 2, "email": null, "amount": 2, "address": { "city": "Paris" } }),
+        );
+
+        let schema = inferencer.infer("customer.created").unwrap();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["title"], "customer.created");
+        assert_eq!(schema["required"], json!(["amount", "email", "id"]));
+        assert_eq!(schema["properties"]["id"]["type"], "integer");
+        assert_eq!(schema["properties"]["amount"]["type"], "number");
+        assert_eq!(
+            schema["properties"]["email"]["type"],
+            json!(["null", "string"])
+        );
+        assert_eq!(schema["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(
+            schema["properties"]["address"]["properties"]["city"]["type"],
+            "string"
+        );
+        assert_eq!(inferencer.samples("customer.created"), 2);
+        assert!(inferencer.infer("unknown").is_none());
+    }
+
+    #[test]
+    fn test_infer_enum_candidates() {
+        let mut inferencer = SchemaInferencer::new(InferenceConfig {
+            max_enum_values: 2,
+            min_enum_samples: 3,
+        });
+        for status in ["paid", "open", "paid", "open"] {
+            inferencer.observe("invoice", &json!({ "status": status, "number": status }));
+        }
+        inferencer.observe("invoice", &json!({ "status": "paid", "number": "other" }));
+
+        let schema = inferencer.infer("invoice").unwrap();
+        assert_eq!(
+            schema["properties"]["status"]["enum"],
+            json!(["open", "paid"])
+        );
+        assert!(schema["properties"]["number"].get("enum").is_none());
+    }
+}
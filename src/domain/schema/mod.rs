@@ -1,3 +1,6 @@
 pub mod common_model;
+pub mod diff;
+pub mod inference;
 pub mod json_mapper;
 pub mod json_schema;
+pub mod response_mapping;
@@ -0,0 +1,326 @@
+use crate::{ApplicationError, IntegrationOSError, InternalError};
+use jsonpath_lib::Compiled;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use strum::{AsRefStr, Display};
+use thiserror::Error;
+
+/// Declarative conversion of a platform response into a common model. Each output field
+/// is selected with a JSONPath expression evaluated against the response, or against the
+/// parent value for nested fields.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+pub struct ResponseMapping(pub BTreeMap<String, FieldMapping>);
+
+/// A field is either a bare expression, e.g. `"$.customer.email"`, or the full form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase", from = "FieldMappingRepr")]
+pub struct FieldMapping {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<MappedType>,
+    /// Used when the expression selects nothing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub default: Option<Value>,
+    /// Maps the selected object, or every element of the selected array
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "dummy", dummy(default))]
+    pub fields: Option<ResponseMapping>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FieldMappingRepr {
+    Path(String),
+    #[serde(rename_all = "camelCase")]
+    Full {
+        path: String,
+        #[serde(default)]
+        required: bool,
+        #[serde(default)]
+        r#type: Option<MappedType>,
+        #[serde(default)]
+        default: Option<Value>,
+        #[serde(default)]
+        fields: Option<ResponseMapping>,
+    },
+}
+
+impl From<FieldMappingRepr> for FieldMapping {
+    fn from(repr: FieldMappingRepr) -> Self {
+        match repr {
+            FieldMappingRepr::Path(path) => FieldMapping::new(path),
+            FieldMappingRepr::Full {
+                path,
+                required,
+                r#type,
+                default,
+                fields,
+            } => FieldMapping {
+                path,
+                required,
+                r#type,
+                default,
+                fields,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, AsRefStr)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum MappedType {
+    String,
+    Number,
+    Boolean,
+    Object,
+    Array,
+}
+
+impl MappedType {
+    fn matches(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (_, Value::Null)
+                | (MappedType::String, Value::String(_))
+                | (MappedType::Number, Value::Number(_))
+                | (MappedType::Boolean, Value::Bool(_))
+                | (MappedType::Object, Value::Object(_))
+                | (MappedType::Array, Value::Array(_))
+        )
+    }
+}
+
+/// Failures carry the path of the output field, e.g. `customer.addresses[1].city`
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MappingError {
+    #[error("Invalid expression {expression:?} for field {field}: {reason}")]
+    InvalidExpression {
+        field: String,
+        expression: String,
+        reason: String,
+    },
+    #[error("Missing required field {field}, {expression:?} selected nothing")]
+    Missing { field: String, expression: String },
+    #[error("Field {field} should be a {expected}, got {found}")]
+    TypeMismatch {
+        field: String,
+        expected: MappedType,
+        found: String,
+    },
+}
+
+impl From<MappingError> for IntegrationOSError {
+    fn from(error: MappingError) -> Self {
+        match error {
+            MappingError::InvalidExpression { .. } => InternalError::configuration_error(
+                &error.to_string(),
+                Some("invalid_mapping_expression"),
+            ),
+            MappingError::Missing { .. } | MappingError::TypeMismatch { .. } => {
+                ApplicationError::failed_dependency(&error.to_string(), Some("response_mapping"))
+            }
+        }
+    }
+}
+
+fn child(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+fn compile(field: &str, expression: &str) -> Result<Compiled, MappingError> {
+    Compiled::compile(expression).map_err(|reason| MappingError::InvalidExpression {
+        field: field.to_owned(),
+        expression: expression.to_owned(),
+        reason,
+    })
+}
+
+impl FieldMapping {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            required: false,
+            r#type: None,
+            default: None,
+            fields: None,
+        }
+    }
+
+    fn apply(&self, field: &str, data: &Value) -> Result<Value, MappingError> {
+        let selected = compile(field, &self.path)?.select(data).map_err(|e| {
+            MappingError::InvalidExpression {
+                field: field.to_owned(),
+                expression: self.path.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+
+        let value = match selected.as_slice() {
+            [] => match (&self.default, self.required) {
+                (Some(default), _) => default.clone(),
+                (None, true) => {
+                    return Err(MappingError::Missing {
+                        field: field.to_owned(),
+                        expression: self.path.clone(),
+                    })
+                }
+                (None, false) => Value::Null,
+            },
+            [value] => (*value).clone(),
+            // Wildcards and filters select several values
+            values => Value::Array(values.iter().map(|v| (*v).clone()).collect()),
+        };
+
+        let value = match (&self.fields, value) {
+            (Some(fields), Value::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| fields.apply_at(&format!("{field}[{i}]"), item))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (Some(fields), value @ Value::Object(_)) => fields.apply_at(field, &value)?,
+            (_, value) => value,
+        };
+
+        if let Some(expected) = self.r#type {
+            if !expected.matches(&value) {
+                return Err(MappingError::TypeMismatch {
+                    field: field.to_owned(),
+                    expected,
+                    found: value.to_string(),
+                });
+            }
+        }
+        Ok(value)
+    }
+}
+
+impl ResponseMapping {
+    pub fn with(mut self, field: impl Into<String>, mapping: FieldMapping) -> Self {
+        self.0.insert(field.into(), mapping);
+        self
+    }
+
+    /// Compiles every expression, so broken mappings are rejected when the schema is
+    /// saved rather than when a response is mapped
+    pub fn validate(&self) -> Result<(), MappingError> {
+        self.validate_at("")
+    }
+
+    fn validate_at(&self, parent: &str) -> Result<(), MappingError> {
+        self.0.iter().try_for_each(|(key, mapping)| {
+            let field = child(parent, key);
+            compile(&field, &mapping.path)?;
+            match &mapping.fields {
+                Some(fields) => fields.validate_at(&field),
+                None => Ok(()),
+            }
+        })
+    }
+
+    pub fn apply(&self, data: &Value) -> Result<Value, MappingError> {
+        self.apply_at("", data)
+    }
+
+    fn apply_at(&self, parent: &str, data: &Value) -> Result<Value, MappingError> {
+        let mut output = Map::new();
+        for (key, mapping) in &self.0 {
+            output.insert(key.clone(), mapping.apply(&child(parent, key), data)?);
+        }
+        Ok(Value::Object(output))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn mapping() -> ResponseMapping {
+        serde_json::from_value(json!({
+            "id": "$.id",
+            "email": { "path": "$.contact.email", "required": true, "type": "string" },
+            "currency": { "path": "$.currency", "default": "usd" },
+            "addresses": {
+                "path": "$.addresses",
+                "fields": {
+                    "city": { "path": "$.city", "required": true },
+                    "zip": "$.postal_code",
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply_mapping() {
+        let customer = json!({
+            "id": "cus_123",
+            "contact": { "email": "jane@example.com" },
+            "addresses": [
+                { "city": "Toronto", "postal_code": "M5V" },
+                { "city": "Lisbon" },
+            ],
+        });
+
+        assert_eq!(
+            mapping().apply(&customer).unwrap(),
+            json!({
+                "id": "cus_123",
+                "email": "jane@example.com",
+                "currency": "usd",
+                "addresses": [
+                    { "city": "Toronto", "zip": "M5V" },
+                    { "city": "Lisbon", "zip": null },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_mapping_errors_carry_field_path() {
+        let customer = json!({
+            "contact": { "email": "jane@example.com" },
+            "addresses": [{ "city": "Toronto" }, { "postal_code": "1000" }],
+        });
+        assert_eq!(
+            mapping().apply(&customer).unwrap_err(),
+            MappingError::Missing {
+                field: "addresses[1].city".to_owned(),
+                expression: "$.city".to_owned(),
+            }
+        );
+
+        let customer = json!({ "contact": { "email": 42 } });
+        assert!(matches!(
+            mapping().apply(&customer).unwrap_err(),
+            MappingError::TypeMismatch { field, expected: MappedType::String, .. } if field == "email"
+        ));
+
+        let broken = mapping().with(
+            "name",
+            FieldMapping {
+                fields: Some(ResponseMapping::default().with("first", FieldMapping::new("$.[?("))),
+                ..FieldMapping::new("$.name")
+            },
+        );
+        assert!(mapping().validate().is_ok());
+        assert!(matches!(
+            broken.validate().unwrap_err(),
+            MappingError::InvalidExpression { field, .. } if field == "name.first"
+        ));
+    }
+}
@@ -3,4 +3,5 @@ pub mod create_secret_response;
 pub mod get_secret_request;
 pub mod get_secret_response;
 pub mod hashed_secret;
+#[cfg(feature = "backend")]
 pub mod oauth_secret;
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::prelude::connection_oauth_definition::OAuthResponse;
+use crate::prelude::{connection_oauth_definition::OAuthResponse, shared::redacted::Redacted};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -9,13 +9,13 @@ pub struct OAuthSecret {
     #[serde(rename = "OAUTH_CLIENT_ID")]
     pub client_id: String,
     #[serde(rename = "OAUTH_CLIENT_SECRET")]
-    pub client_secret: String,
+    pub client_secret: Redacted<String>,
     #[serde(rename = "OAUTH_ACCESS_TOKEN")]
-    pub access_token: String,
+    pub access_token: Redacted<String>,
     #[serde(rename = "OAUTH_TOKEN_TYPE")]
     pub token_type: Option<String>,
     #[serde(rename = "OAUTH_REFRESH_TOKEN")]
-    pub refresh_token: Option<String>,
+    pub refresh_token: Option<Redacted<String>>,
     #[serde(rename = "OAUTH_EXPIRES_IN")]
     pub expires_in: i32,
     #[serde(rename = "OAUTH_METADATA")]
@@ -28,7 +28,7 @@ impl OAuthSecret {
     pub fn from_init(
         oauth_response: OAuthResponse,
         client_id: String,
-        client_secret: String,
+        client_secret: Redacted<String>,
         metadata: Value,
         request_payload: Option<Value>,
     ) -> Self {
@@ -48,7 +48,7 @@ impl OAuthSecret {
         &self,
         oauth_response: OAuthResponse,
         client_id: Option<String>,
-        client_secret: Option<String>,
+        client_secret: Option<Redacted<String>>,
         metadata: Value,
     ) -> Self {
         OAuthSecret {
@@ -0,0 +1,133 @@
+//! Tolerant deserializers for documents that were created outside of this crate, where
+//! `_id`s may be `ObjectId`s and timestamps may be stored as BSON dates or RFC 3339 strings.
+
+use bson::{oid::ObjectId, Bson, Document};
+use chrono::DateTime;
+use serde::{de::Error, Deserialize, Deserializer};
+
+/// Builds a filter matching `_id` whether it was stored as a string or as an `ObjectId`
+pub fn id_filter(id: &str) -> Document {
+    match ObjectId::parse_str(id) {
+        Ok(oid) => bson::doc! { "_id": { "$in": [id, oid] } },
+        Err(_) => bson::doc! { "_id": id },
+    }
+}
+
+pub fn bson_to_string_id(value: &Bson) -> Option<String> {
+    match value {
+        Bson::String(s) => Some(s.to_owned()),
+        Bson::ObjectId(oid) => Some(oid.to_hex()),
+        _ => None,
+    }
+}
+
+pub fn bson_to_timestamp_millis(value: &Bson) -> Option<i64> {
+    match value {
+        Bson::Int64(v) => Some(*v),
+        Bson::Int32(v) => Some(*v as i64),
+        Bson::Double(v) => Some(*v as i64),
+        Bson::DateTime(d) => Some(d.timestamp_millis()),
+        Bson::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|d| d.timestamp_millis())
+            .or_else(|| s.parse().ok()),
+        _ => None,
+    }
+}
+
+/// Accepts either a string id or an `ObjectId`, always producing its string form
+pub mod string_id {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Bson::deserialize(deserializer)?;
+        bson_to_string_id(&value)
+            .ok_or_else(|| D::Error::custom(format!("Expected a string or ObjectId, got {value}")))
+    }
+}
+
+/// Accepts epoch milliseconds, BSON dates or RFC 3339 strings, producing epoch milliseconds
+pub mod timestamp_millis {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<i64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Bson::deserialize(deserializer)?;
+        bson_to_timestamp_millis(&value)
+            .ok_or_else(|| D::Error::custom(format!("Expected a timestamp, got {value}")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bson::doc;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct External {
+        #[serde(rename = "_id", with = "string_id")]
+        id: String,
+        #[serde(with = "timestamp_millis")]
+        created_at: i64,
+    }
+
+    #[test]
+    fn test_deserialize_object_id_and_bson_date() {
+        let oid = ObjectId::parse_str("65a5f4b2c0e7a3b1d2e3f4a5").unwrap();
+        let document = doc! {
+            "_id": oid,
+            "createdAt": bson::DateTime::from_millis(1_700_000_000_000),
+        };
+        let external: External = bson::from_document(document).unwrap();
+        assert_eq!(external.id, "65a5f4b2c0e7a3b1d2e3f4a5");
+        assert_eq!(external.created_at, 1_700_000_000_000);
+    }
+
+    #[test]
+    fn test_deserialize_legacy_shapes() {
+        let external: External = serde_json::from_value(json!({
+            "_id": "conn::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "createdAt": "2023-11-14T22:13:20Z",
+        }))
+        .unwrap();
+        assert_eq!(external.created_at, 1_700_000_000_000);
+
+        let external: External = serde_json::from_value(json!({
+            "_id": { "$oid": "65a5f4b2c0e7a3b1d2e3f4a5" },
+            "createdAt": 1_700_000_000_000_i64,
+        }))
+        .unwrap();
+        assert_eq!(external.id, "65a5f4b2c0e7a3b1d2e3f4a5");
+    }
+
+    #[test]
+    fn test_record_metadata_accepts_bson_dates() {
+        #[derive(Debug, Deserialize)]
+        struct Record {
+            #[serde(flatten)]
+            record_metadata: crate::prelude::shared::record_metadata::RecordMetadata,
+        }
+
+        let record: Record = bson::from_document(doc! {
+            "createdAt": bson::DateTime::from_millis(1_700_000_000_000),
+            "updatedAt": 1_700_000_000_001_i64,
+        })
+        .unwrap();
+        assert_eq!(record.record_metadata.created_at, 1_700_000_000_000);
+        assert_eq!(record.record_metadata.updated_at, 1_700_000_000_001);
+    }
+
+    #[test]
+    fn test_id_filter() {
+        assert_eq!(id_filter("foo"), doc! { "_id": "foo" });
+        let filter = id_filter("65a5f4b2c0e7a3b1d2e3f4a5");
+        assert!(filter.get_document("_id").unwrap().contains_key("$in"));
+    }
+}
@@ -0,0 +1,93 @@
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+pub const CORRELATION_ID_HEADER: &str = "x-integrationos-correlation-id";
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(transparent)]
+pub struct CorrelationId(String);
+
+impl CorrelationId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4().simple().to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Reads the correlation id sent by the caller, if any
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(CORRELATION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| Self(v.to_owned()))
+    }
+
+    pub fn from_headers_or_new(headers: &HeaderMap) -> Self {
+        Self::from_headers(headers).unwrap_or_default()
+    }
+
+    /// Adds the correlation id to the outbound headers without overriding an existing one
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if headers.contains_key(CORRELATION_ID_HEADER) {
+            return;
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.0) {
+            headers.insert(CORRELATION_ID_HEADER, value);
+        }
+    }
+}
+
+impl Default for CorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for CorrelationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<String> for CorrelationId {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for CorrelationId {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_correlation_id_headers_roundtrip() {
+        let mut headers = HeaderMap::new();
+        assert!(CorrelationId::from_headers(&headers).is_none());
+
+        let id = CorrelationId::new();
+        id.inject(&mut headers);
+        assert_eq!(CorrelationId::from_headers(&headers), Some(id.clone()));
+
+        CorrelationId::from("other").inject(&mut headers);
+        assert_eq!(CorrelationId::from_headers_or_new(&headers), id);
+    }
+
+    #[test]
+    fn test_correlation_id_serde() {
+        let id = CorrelationId::from("abc");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"abc\"");
+    }
+}
@@ -1,3 +1,8 @@
+pub mod bson_compat;
+pub mod correlation_id;
 pub mod ownership;
 pub mod record_metadata;
+pub mod redacted;
 pub mod settings;
+pub mod trace_context;
+pub mod unknown_variant;
@@ -1,3 +1,4 @@
+use super::bson_compat;
 use chrono::prelude::*;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -7,13 +8,19 @@ use std::collections::BTreeMap;
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
 #[serde(rename_all = "camelCase", default)]
 pub struct RecordMetadata {
+    #[serde(deserialize_with = "bson_compat::timestamp_millis::deserialize")]
     pub created_at: i64,
+    #[serde(deserialize_with = "bson_compat::timestamp_millis::deserialize")]
     pub updated_at: i64,
     pub updated: bool,
     #[cfg_attr(feature = "dummy", dummy(expr = "Version::new(1,0,0)"))]
     pub version: Version,
     pub last_modified_by: String,
     pub deleted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<String>,
     pub change_log: BTreeMap<String, i64>,
     pub tags: Vec<String>,
     pub active: bool,
@@ -22,7 +29,14 @@ pub struct RecordMetadata {
 
 impl Default for RecordMetadata {
     fn default() -> Self {
-        let now = Utc::now().timestamp_millis();
+        Self::created_at(Utc::now())
+    }
+}
+
+impl RecordMetadata {
+    /// Metadata of a record created at `time`
+    pub fn created_at(time: DateTime<Utc>) -> Self {
+        let now = time.timestamp_millis();
         RecordMetadata {
             created_at: now,
             updated_at: now,
@@ -30,15 +44,15 @@ impl Default for RecordMetadata {
             version: Version::new(1, 0, 0),
             last_modified_by: String::from("system"),
             deleted: false,
+            deleted_at: None,
+            deleted_by: None,
             change_log: BTreeMap::new(),
             tags: Vec::new(),
             active: true,
             deprecated: false,
         }
     }
-}
 
-impl RecordMetadata {
     // Mark record as updated
     pub fn mark_updated(&mut self, modifier: &str) {
         let now = Utc::now().timestamp_millis();
@@ -58,6 +72,8 @@ impl RecordMetadata {
     pub fn mark_deleted(&mut self, modifier: &str) {
         let now = Utc::now().timestamp_millis();
         self.deleted = true;
+        self.deleted_at = Some(now);
+        self.deleted_by = Some(modifier.to_string());
         let log_entry = format!("Marked as deleted by {}", modifier);
         self.change_log.insert(log_entry, now);
     }
@@ -66,6 +82,8 @@ impl RecordMetadata {
     pub fn mark_undeleted(&mut self, modifier: &str) {
         let now = Utc::now().timestamp_millis();
         self.deleted = false;
+        self.deleted_at = None;
+        self.deleted_by = None;
         let log_entry = format!("Marked as undeleted by {}", modifier);
         self.change_log.insert(log_entry, now);
     }
@@ -75,3 +93,28 @@ impl RecordMetadata {
         self.tags.push(tag.to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soft_delete_and_restore() {
+        let mut metadata = RecordMetadata::default();
+        metadata.mark_deleted("alice");
+        assert!(metadata.deleted);
+        assert!(metadata.deleted_at.is_some());
+        assert_eq!(metadata.deleted_by.as_deref(), Some("alice"));
+
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value["deletedBy"], "alice");
+
+        metadata.mark_undeleted("bob");
+        assert!(!metadata.deleted);
+        assert_eq!(metadata.deleted_at, None);
+        assert_eq!(metadata.deleted_by, None);
+
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert!(value.get("deletedAt").is_none());
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    str::FromStr,
+};
+
+pub const REDACTED: &str = "***";
+
+/// Secret value, e.g. an access key, API key or OAuth token, masked in `Debug` and
+/// `Display` so it can't end up in logs by accident. It serializes as is, since it has to
+/// be persisted; fields that shouldn't leave the process serialize with [`mask`] instead.
+/// The value is only reachable through [`Redacted::expose_secret`].
+#[derive(Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Redacted<T>(T);
+
+/// Fakes the inner value with the same config, so `#[dummy(faker = "8..50")]` keeps
+/// working on fields that became redacted
+#[cfg(feature = "dummy")]
+impl<F, T: fake::Dummy<F>> fake::Dummy<F> for Redacted<T> {
+    fn dummy_with_rng<R: rand::prelude::Rng + ?Sized>(config: &F, rng: &mut R) -> Self {
+        Self(T::dummy_with_rng(config, rng))
+    }
+}
+
+impl<T> Redacted<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+
+    /// For replacing the value in place, e.g. with its decrypted form
+    pub fn expose_secret_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl Redacted<String> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T> Debug for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Display for Redacted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Redacted<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+/// Lets configuration read secrets straight from the environment
+impl<T: FromStr> FromStr for Redacted<T> {
+    type Err = T::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+/// Serializes any value as `***`, for `#[serde(serialize_with = "mask")]` on fields of
+/// responses and logged payloads
+pub fn mask<T, S: Serializer>(_: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redacted() {
+        let secret = Redacted::from("sk-live-123");
+        assert_eq!(format!("{secret:?}"), "***");
+        assert_eq!(secret.to_string(), "***");
+        assert_eq!(secret.expose_secret(), "sk-live-123");
+        assert_eq!(format!("{:?}", Some(secret.clone())), "Some(***)");
+
+        assert_eq!(serde_json::to_value(&secret).unwrap(), json!("sk-live-123"));
+        assert_eq!(
+            serde_json::from_value::<Redacted<String>>(json!("sk-live-123")).unwrap(),
+            secret
+        );
+        assert_eq!("sk-live-123".parse::<Redacted<String>>().unwrap(), secret);
+
+        #[derive(Serialize)]
+        struct Response {
+            #[serde(serialize_with = "mask")]
+            token: Redacted<String>,
+        }
+        assert_eq!(
+            serde_json::to_value(Response { token: secret }).unwrap(),
+            json!({ "token": "***" })
+        );
+    }
+
+    #[cfg(all(feature = "dummy", feature = "backend"))]
+    #[test]
+    fn test_dummy_uses_the_inner_faker() {
+        use crate::prelude::event::event_access::EventAccess;
+        use fake::{Fake, Faker};
+
+        let access: EventAccess = Faker.fake();
+        assert!((8..50).contains(&access.access_key.expose_secret().len()));
+    }
+}
@@ -1,4 +1,6 @@
+use crate::{IntegrationOSError, InternalError};
 use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, Display, EnumIter, EnumString};
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "dummy", derive(fake::Dummy))]
@@ -8,4 +10,174 @@ pub struct Settings {
     pub show_secret: bool,
     pub allow_custom_events: bool,
     pub oauth: bool,
+    /// Lets events through the idempotency and storm guards even when they repeat
+    #[serde(default)]
+    pub skip_dedup: bool,
+    /// Rejects payloads that don't match their schema instead of only reporting it
+    #[serde(default)]
+    pub strict_schema_validation: bool,
+    /// Caps how many pipelines may call the upstream platform at once for a connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency_limit: Option<ConcurrencyLimit>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyLimit {
+    pub max_concurrent: u32,
+    /// How long a permit is held before it is considered abandoned and reclaimed
+    #[serde(default = "ConcurrencyLimit::default_lease_secs")]
+    pub lease_secs: u64,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_concurrent: u32) -> Self {
+        Self {
+            max_concurrent,
+            lease_secs: Self::default_lease_secs(),
+        }
+    }
+
+    fn default_lease_secs() -> u64 {
+        60
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    AsRefStr,
+    EnumString,
+    EnumIter,
+)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum SettingsToggle {
+    ParseWebhookBody,
+    ShowSecret,
+    AllowCustomEvents,
+    OAuth,
+    SkipDedup,
+    StrictSchemaValidation,
+}
+
+impl Settings {
+    pub fn is_enabled(&self, toggle: SettingsToggle) -> bool {
+        match toggle {
+            SettingsToggle::ParseWebhookBody => self.parse_webhook_body,
+            SettingsToggle::ShowSecret => self.show_secret,
+            SettingsToggle::AllowCustomEvents => self.allow_custom_events,
+            SettingsToggle::OAuth => self.oauth,
+            SettingsToggle::SkipDedup => self.skip_dedup,
+            SettingsToggle::StrictSchemaValidation => self.strict_schema_validation,
+        }
+    }
+
+    pub fn set(&mut self, toggle: SettingsToggle, enabled: bool) {
+        let field = match toggle {
+            SettingsToggle::ParseWebhookBody => &mut self.parse_webhook_body,
+            SettingsToggle::ShowSecret => &mut self.show_secret,
+            SettingsToggle::AllowCustomEvents => &mut self.allow_custom_events,
+            SettingsToggle::OAuth => &mut self.oauth,
+            SettingsToggle::SkipDedup => &mut self.skip_dedup,
+            SettingsToggle::StrictSchemaValidation => &mut self.strict_schema_validation,
+        };
+        *field = enabled;
+    }
+
+    /// Paths of the fields the pipeline can't honour, with why
+    pub fn invalid_fields(&self) -> Vec<(&'static str, &'static str)> {
+        let mut fields = vec![];
+        if self.strict_schema_validation && !self.parse_webhook_body {
+            fields.push((
+                "strictSchemaValidation",
+                "Strict schema validation requires the webhook body to be parsed",
+            ));
+        }
+        if let Some(limit) = self.concurrency_limit {
+            if limit.max_concurrent == 0 {
+                fields.push((
+                    "concurrencyLimit.maxConcurrent",
+                    "Concurrency limit must be greater than zero",
+                ));
+            }
+            if limit.lease_secs == 0 {
+                fields.push((
+                    "concurrencyLimit.leaseSecs",
+                    "Concurrency lease must be greater than zero",
+                ));
+            }
+        }
+        fields
+    }
+
+    /// Checks that the combination of toggles is one the pipeline can honour
+    pub fn validate(&self) -> Result<(), IntegrationOSError> {
+        match self.invalid_fields().first() {
+            Some((_, message)) => Err(InternalError::invalid_argument(message, Some("settings"))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_settings_defaults_for_legacy_documents() {
+        let settings: Settings = serde_json::from_value(json!({
+            "parseWebhookBody": true,
+            "showSecret": false,
+            "allowCustomEvents": false,
+            "oauth": false
+        }))
+        .unwrap();
+
+        assert!(!settings.skip_dedup);
+        assert!(!settings.strict_schema_validation);
+        assert!(settings.concurrency_limit.is_none());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn test_settings_toggles() {
+        let mut settings = Settings::default();
+        for toggle in SettingsToggle::iter() {
+            assert!(!settings.is_enabled(toggle));
+            settings.set(toggle, true);
+            assert!(settings.is_enabled(toggle));
+        }
+
+        settings.set(SettingsToggle::ParseWebhookBody, false);
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_settings_concurrency_limit() {
+        let mut settings: Settings = serde_json::from_value(json!({
+            "parseWebhookBody": false,
+            "showSecret": false,
+            "allowCustomEvents": false,
+            "oauth": false,
+            "concurrencyLimit": { "maxConcurrent": 2 }
+        }))
+        .unwrap();
+
+        assert_eq!(settings.concurrency_limit, Some(ConcurrencyLimit::new(2)));
+        assert!(settings.validate().is_ok());
+
+        settings.concurrency_limit = Some(ConcurrencyLimit::new(0));
+        assert!(settings.validate().is_err());
+    }
 }
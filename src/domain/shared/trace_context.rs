@@ -0,0 +1,234 @@
+use http::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const BAGGAGE_HEADER: &str = "baggage";
+
+/// W3C trace context carried by events across the queue, so the spans of every service
+/// handling an event link back to the request that created it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "dummy", derive(fake::Dummy))]
+#[serde(rename_all = "camelCase")]
+pub struct TraceContext {
+    /// `{version}-{trace id}-{parent span id}-{flags}`, e.g.
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+    pub traceparent: String,
+    /// Comma separated `key=value` pairs propagated along the trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baggage: Option<String>,
+}
+
+fn is_lower_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+impl TraceContext {
+    /// Trace context with a valid `traceparent`, `None` otherwise
+    pub fn new(traceparent: impl Into<String>, baggage: Option<String>) -> Option<Self> {
+        let traceparent = traceparent.into().trim().to_ascii_lowercase();
+        Self::is_valid_traceparent(&traceparent).then(|| Self {
+            traceparent,
+            baggage: baggage.filter(|b| !b.trim().is_empty()),
+        })
+    }
+
+    pub fn is_valid_traceparent(traceparent: &str) -> bool {
+        let parts = traceparent.split('-').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [version, trace_id, span_id, flags, ..] => {
+                is_lower_hex(version, 2)
+                    && *version != "ff"
+                    // Version 00 has exactly four fields, later ones may append some
+                    && (*version != "00" || parts.len() == 4)
+                    && is_lower_hex(trace_id, 32)
+                    && trace_id.bytes().any(|b| b != b'0')
+                    && is_lower_hex(span_id, 16)
+                    && span_id.bytes().any(|b| b != b'0')
+                    && is_lower_hex(flags, 2)
+            }
+            _ => false,
+        }
+    }
+
+    fn part(&self, index: usize) -> &str {
+        self.traceparent.split('-').nth(index).unwrap_or_default()
+    }
+
+    pub fn trace_id(&self) -> &str {
+        self.part(1)
+    }
+
+    pub fn span_id(&self) -> &str {
+        self.part(2)
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        u8::from_str_radix(self.part(3), 16).is_ok_and(|flags| flags & 1 == 1)
+    }
+
+    /// Baggage entries, without their properties
+    pub fn baggage_entries(&self) -> Vec<(String, String)> {
+        self.baggage
+            .iter()
+            .flat_map(|baggage| baggage.split(','))
+            .filter_map(|entry| {
+                let (key, value) = entry.split(';').next()?.split_once('=')?;
+                let key = key.trim();
+                (!key.is_empty()).then(|| (key.to_owned(), value.trim().to_owned()))
+            })
+            .collect()
+    }
+
+    /// Reads the trace context sent by the caller, if any
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        Self::new(header(TRACEPARENT_HEADER)?, header(BAGGAGE_HEADER))
+    }
+
+    /// Adds the trace context to the outbound headers without overriding an existing one
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if headers.contains_key(TRACEPARENT_HEADER) {
+            return;
+        }
+        if let Ok(value) = HeaderValue::from_str(&self.traceparent) {
+            headers.insert(TRACEPARENT_HEADER, value);
+        }
+        if let Some(Ok(value)) = self.baggage.as_deref().map(HeaderValue::from_str) {
+            headers.insert(BAGGAGE_HEADER, value);
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use super::TraceContext;
+    use opentelemetry::{
+        baggage::BaggageExt,
+        trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+        Context, KeyValue,
+    };
+
+    impl TraceContext {
+        /// Trace context of the active span of `context`, `None` outside of a span
+        pub fn from_context(context: &Context) -> Option<Self> {
+            let span = context.span();
+            let span_context = span.span_context();
+            if !span_context.is_valid() {
+                return None;
+            }
+            let traceparent = format!(
+                "00-{}-{}-{:02x}",
+                span_context.trace_id(),
+                span_context.span_id(),
+                span_context.trace_flags().to_u8()
+            );
+            let baggage = context
+                .baggage()
+                .iter()
+                .map(|(key, (value, _))| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            Self::new(traceparent, Some(baggage))
+        }
+
+        pub fn current() -> Option<Self> {
+            Self::from_context(&Context::current())
+        }
+
+        /// Context to parent the spans of a consumer to, its remote span being the
+        /// producer's
+        pub fn to_context(&self) -> Context {
+            let span_context = match (
+                TraceId::from_hex(self.trace_id()),
+                SpanId::from_hex(self.span_id()),
+            ) {
+                (Ok(trace_id), Ok(span_id)) => SpanContext::new(
+                    trace_id,
+                    span_id,
+                    if self.is_sampled() {
+                        TraceFlags::SAMPLED
+                    } else {
+                        TraceFlags::default()
+                    },
+                    true,
+                    TraceState::default(),
+                ),
+                _ => return Context::new(),
+            };
+            Context::new()
+                .with_remote_span_context(span_context)
+                .with_baggage(
+                    self.baggage_entries()
+                        .into_iter()
+                        .map(|(key, value)| KeyValue::new(key, value)),
+                )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_trace_context_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(TraceContext::from_headers(&headers).is_none());
+
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static(TRACEPARENT));
+        headers.insert(
+            BAGGAGE_HEADER,
+            HeaderValue::from_static("tenant=acme;prop=1, region = eu"),
+        );
+        let context = TraceContext::from_headers(&headers).unwrap();
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id(), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(
+            context.baggage_entries(),
+            vec![
+                ("tenant".to_owned(), "acme".to_owned()),
+                ("region".to_owned(), "eu".to_owned())
+            ]
+        );
+
+        let mut outbound = HeaderMap::new();
+        context.inject(&mut outbound);
+        assert_eq!(outbound[TRACEPARENT_HEADER], TRACEPARENT);
+
+        assert!(TraceContext::new(
+            "00-0000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(TraceContext::new(
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(
+            TraceContext::new("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7", None)
+                .is_none()
+        );
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_round_trip_through_opentelemetry() {
+        let context = TraceContext::new(TRACEPARENT, Some("tenant=acme".to_owned())).unwrap();
+        assert_eq!(
+            TraceContext::from_context(&context.to_context()),
+            Some(context)
+        );
+    }
+}
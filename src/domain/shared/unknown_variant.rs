@@ -0,0 +1,33 @@
+//! Fallbacks for enums stored in documents that newer services may extend with variants
+//! this build doesn't know about. The unknown variant keeps the raw variant, payload
+//! included, so the document can still be read and is written back unchanged, and a
+//! warning is logged every time one is seen.
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tracing::warn;
+
+/// Tag of an externally tagged variant, either a bare string or a single key object
+pub fn tag(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(tag) => Some(tag),
+        Value::Object(map) if map.len() == 1 => map.keys().next().map(String::as_str),
+        _ => None,
+    }
+}
+
+/// For `Unknown(Value)` variants: accepts a bare tag or an externally tagged variant
+/// with a payload, keeping it verbatim
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Value, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    let Some(tag) = tag(&value) else {
+        return Err(serde::de::Error::custom(format!(
+            "expected an enum variant, got {value}"
+        )));
+    };
+    warn!("Encountered unknown enum variant {tag:?}, it was probably written by a newer service");
+    Ok(value)
+}
@@ -0,0 +1,236 @@
+use super::Store;
+use bson::{doc, Bson, Document};
+use serde::{Deserialize, Serialize};
+
+/// An index of a collection, as declared or as found in Mongo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSpec {
+    pub name: String,
+    pub keys: Document,
+    #[serde(default)]
+    pub unique: bool,
+    #[serde(default)]
+    pub sparse: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_after_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial_filter: Option<Document>,
+}
+
+impl IndexSpec {
+    pub fn new(name: impl Into<String>, keys: Document) -> Self {
+        Self {
+            name: name.into(),
+            keys,
+            unique: false,
+            sparse: false,
+            expire_after_secs: None,
+            partial_filter: None,
+        }
+    }
+
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    pub fn sparse(mut self) -> Self {
+        self.sparse = true;
+        self
+    }
+
+    pub fn expire_after(mut self, secs: u64) -> Self {
+        self.expire_after_secs = Some(secs);
+        self
+    }
+
+    pub fn partial_filter(mut self, filter: Document) -> Self {
+        self.partial_filter = Some(filter);
+        self
+    }
+
+    /// Key pattern with directions as `i32`, Mongo reporting `1` as an int, a long or a
+    /// double depending on who created the index
+    fn key_pattern(&self) -> Vec<(String, Bson)> {
+        self.keys
+            .iter()
+            .map(|(field, direction)| {
+                let direction = match direction {
+                    Bson::Int64(d) => Bson::Int32(*d as i32),
+                    Bson::Double(d) => Bson::Int32(*d as i32),
+                    other => other.clone(),
+                };
+                (field.clone(), direction)
+            })
+            .collect()
+    }
+
+    fn same_options(&self, other: &IndexSpec) -> bool {
+        self.unique == other.unique
+            && self.sparse == other.sparse
+            && self.expire_after_secs == other.expire_after_secs
+            && self.partial_filter == other.partial_filter
+    }
+}
+
+/// An index present with the declared keys but other options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexDivergence {
+    pub expected: IndexSpec,
+    pub actual: IndexSpec,
+}
+
+/// Difference between the declared and the present indexes of a collection. Indexes are
+/// matched on their key pattern, names are informative only.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionDrift {
+    pub collection: String,
+    pub missing: Vec<IndexSpec>,
+    /// Present but not declared, never dropped since other services may rely on them
+    pub extra: Vec<IndexSpec>,
+    pub divergent: Vec<IndexDivergence>,
+}
+
+impl CollectionDrift {
+    pub fn compare(
+        collection: impl Into<String>,
+        expected: &[IndexSpec],
+        actual: &[IndexSpec],
+    ) -> Self {
+        let find = |specs: &[IndexSpec], spec: &IndexSpec| {
+            specs
+                .iter()
+                .find(|other| other.key_pattern() == spec.key_pattern())
+                .cloned()
+        };
+
+        let mut drift = CollectionDrift {
+            collection: collection.into(),
+            ..Default::default()
+        };
+        for spec in expected {
+            match find(actual, spec) {
+                None => drift.missing.push(spec.clone()),
+                Some(present) if !present.same_options(spec) => {
+                    drift.divergent.push(IndexDivergence {
+                        expected: spec.clone(),
+                        actual: present,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        drift.extra = actual
+            .iter()
+            .filter(|spec| spec.name != "_id_" && find(expected, spec).is_none())
+            .cloned()
+            .collect();
+        drift
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.divergent.is_empty()
+    }
+}
+
+/// Drift of every reconciled collection, as produced by the
+/// [`SchemaReconciler`](crate::prelude::schema_reconciler::SchemaReconciler)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDriftReport {
+    pub collections: Vec<CollectionDrift>,
+    /// Indexes created or rebuilt by the auto-fix mode, as `{collection}.{index}`
+    #[serde(default)]
+    pub fixed: Vec<String>,
+}
+
+impl SchemaDriftReport {
+    pub fn has_drift(&self) -> bool {
+        self.collections.iter().any(|drift| !drift.is_clean())
+    }
+}
+
+impl Store {
+    /// Stores with declared indexes
+    pub fn indexed() -> Vec<Store> {
+        vec![
+            Store::Events,
+            Store::Connections,
+            Store::DeadLetters,
+            Store::Outbox,
+            Store::MaterializedRecords,
+        ]
+    }
+
+    /// Indexes backing the queries the services run on this store
+    pub fn recommended_indexes(&self) -> Vec<IndexSpec> {
+        match self {
+            Store::Events => vec![IndexSpec::new(
+                "ownership_arrived_at",
+                doc! { "ownership.buildableId": 1, "arrivedAt": 1 },
+            )],
+            Store::Connections => vec![
+                IndexSpec::new("key", doc! { "key": 1 }).unique(),
+                IndexSpec::new("ownership", doc! { "ownership.buildableId": 1 }),
+            ],
+            Store::DeadLetters => vec![IndexSpec::new(
+                "ownership_replayed_at",
+                doc! { "ownership.buildableId": 1, "replayedAt": 1 },
+            )],
+            Store::Outbox => vec![IndexSpec::new(
+                "created_at_locked_until",
+                doc! { "createdAt": 1, "lockedUntil": 1 },
+            )],
+            Store::MaterializedRecords => vec![IndexSpec::new(
+                "connection_common_model",
+                doc! { "connectionKey": 1, "commonModel": 1 },
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_reports_missing_extra_and_divergent() {
+        let expected = vec![
+            IndexSpec::new("key", doc! { "key": 1 }).unique(),
+            IndexSpec::new("ownership", doc! { "ownership.buildableId": 1 }),
+            IndexSpec::new("created_at", doc! { "createdAt": -1 }),
+        ];
+        let actual = vec![
+            IndexSpec::new("_id_", doc! { "_id": 1 }),
+            IndexSpec::new("key_1", doc! { "key": 1_i64 }),
+            IndexSpec::new("owner", doc! { "ownership.buildableId": 1.0 }),
+            IndexSpec::new("platform_1", doc! { "platform": 1 }),
+        ];
+
+        let drift = CollectionDrift::compare("connections", &expected, &actual);
+        assert_eq!(drift.missing, vec![expected[2].clone()]);
+        assert_eq!(drift.extra, vec![actual[3].clone()]);
+        assert_eq!(drift.divergent.len(), 1);
+        assert_eq!(drift.divergent[0].actual.name, "key_1");
+        assert!(!drift.is_clean());
+
+        let report = SchemaDriftReport {
+            collections: vec![
+                drift,
+                CollectionDrift::compare("outbox", &expected, &expected),
+            ],
+            fixed: vec![],
+        };
+        assert!(report.has_drift());
+        assert!(report.collections[1].is_clean());
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            value["collections"][0]["divergent"][0]["expected"]["unique"],
+            true
+        );
+    }
+}
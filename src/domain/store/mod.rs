@@ -1,4 +1,7 @@
 pub mod cursor;
+pub mod index;
+
+pub use index::*;
 
 use bson::doc;
 use serde::{Deserialize, Serialize};
@@ -77,5 +80,31 @@ generate_stores!(
     PublicConnectionModelSchemas,
     "connection-model-schema",
     Transactions,
-    "event-transactions"
+    "event-transactions",
+    MaterializedRecords,
+    "materialized-records",
+    BackfillJobs,
+    "backfill-jobs",
+    RetentionSettings,
+    "retention-settings",
+    AuditLog,
+    "audit-log",
+    OwnershipPlans,
+    "ownership-plans",
+    UsageAlerts,
+    "usage-alerts",
+    ConnectionOverviews,
+    "connection-overviews",
+    WebhookDefinitions,
+    "webhook-definitions",
+    DeadLetters,
+    "dead-letters",
+    ApiVersionMigrations,
+    "api-version-migrations",
+    Outbox,
+    "outbox",
+    LlmUsage,
+    "llm-usage",
+    ArchivedEvents,
+    "archived-events"
 );
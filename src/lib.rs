@@ -1,13 +1,18 @@
 pub mod algebra;
 pub mod domain;
+#[cfg(feature = "backend")]
 pub mod service;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 pub use crate::algebra::*;
 pub use crate::domain::*;
+#[cfg(feature = "backend")]
 pub use crate::service::*;
 
 pub mod prelude {
     pub use crate::algebra::*;
     pub use crate::domain::*;
+    #[cfg(feature = "backend")]
     pub use crate::service::*;
 }
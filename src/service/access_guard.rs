@@ -0,0 +1,541 @@
+use crate::{
+    prelude::{
+        access_key::{
+            encrypted_access_key::{AccessKeyMismatch, EncryptedAccessKey, ACCESS_KEY_HEADER},
+            encrypted_data::PASSWORD_LENGTH,
+            rotation::{AccessKeyRing, AccessKeyVersion, RevocationList},
+            AccessKey,
+        },
+        configuration::environment::{Environment, ENVIRONMENT_HEADER},
+        event::event_access::EventAccess,
+        get_secret_request::GetSecretRequest,
+    },
+    stable_code::{StableErrorCode, EXPIRED_ACCESS_KEY_SUBTYPE},
+    ApplicationError, CryptoExt, ErrorMeta, IntegrationOSError, InternalError, MongoStore,
+};
+use bson::doc;
+use chrono::Utc;
+use http::HeaderMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+const REVOKED_SUBTYPE: &str = "access_key_revoked";
+
+/// Why a request was turned away by an [`AccessGuard`]. Turns into an unauthorized
+/// error, or forbidden for a key of the wrong environment.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum AccessRejection {
+    #[error("Missing {ACCESS_KEY_HEADER} header")]
+    Missing,
+    #[error("Invalid access key: {0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Expired(String),
+    #[error("Access key was revoked")]
+    Revoked,
+    #[error("No event access for this access key")]
+    Unknown,
+    #[error("Event access of this access key is inactive")]
+    Inactive,
+    #[error(transparent)]
+    Mismatch(#[from] AccessKeyMismatch),
+    /// The key couldn't be checked, e.g. the secrets service or Mongo is down
+    #[error(transparent)]
+    Unavailable(IntegrationOSError),
+}
+
+impl From<AccessRejection> for IntegrationOSError {
+    fn from(value: AccessRejection) -> Self {
+        let message = value.to_string();
+        match value {
+            AccessRejection::Missing => {
+                ApplicationError::unauthorized(&message, Some("missing_access_key"))
+            }
+            AccessRejection::Invalid(_) => {
+                ApplicationError::unauthorized(&message, Some("invalid_access_key"))
+            }
+            AccessRejection::Expired(_) => {
+                ApplicationError::unauthorized(&message, Some(EXPIRED_ACCESS_KEY_SUBTYPE))
+            }
+            AccessRejection::Revoked => {
+                ApplicationError::unauthorized(&message, Some(REVOKED_SUBTYPE))
+            }
+            AccessRejection::Unknown => {
+                ApplicationError::unauthorized(&message, Some("unknown_access_key"))
+            }
+            AccessRejection::Inactive => {
+                ApplicationError::unauthorized(&message, Some("inactive_access_key"))
+            }
+            AccessRejection::Mismatch(mismatch) => mismatch.into(),
+            AccessRejection::Unavailable(error) => error,
+        }
+    }
+}
+
+/// An access key that passed the [`AccessGuard`], with the event access it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessGrant {
+    pub access_key: AccessKey,
+    pub event_access: EventAccess,
+}
+
+impl AccessGrant {
+    pub fn environment(&self) -> Environment {
+        self.access_key.prefix.environment
+    }
+}
+
+type Grants = HashMap<String, (Instant, Arc<AccessGrant>)>;
+
+/// Checks the access key of API requests: parses the `x-integrationos-secret` header,
+/// validates the key against the [`AccessKeyRing`], loads its [`EventAccess`] and checks
+/// both are of the requested environment.
+///
+/// Every request goes through the key ring, so revoked keys, retired versions and expired
+/// keys are turned away right away. Only the event access lookup is cached, for the TTL
+/// and keyed by the key's fingerprint.
+#[derive(Clone)]
+pub struct AccessGuard {
+    keys: Arc<RwLock<AccessKeyRing>>,
+    event_access: MongoStore<EventAccess>,
+    ttl: Duration,
+    grants: Arc<RwLock<Grants>>,
+}
+
+impl AccessGuard {
+    pub fn new(keys: AccessKeyRing, event_access: MongoStore<EventAccess>, ttl: Duration) -> Self {
+        Self {
+            keys: Arc::new(RwLock::new(keys)),
+            event_access,
+            ttl,
+            grants: Default::default(),
+        }
+    }
+
+    /// Guard with a single key version, whose password is read from the secrets service
+    pub async fn from_secret<C: CryptoExt + Sync>(
+        crypto: &C,
+        password_secret: &GetSecretRequest,
+        event_access: MongoStore<EventAccess>,
+        ttl: Duration,
+    ) -> Result<Self, IntegrationOSError> {
+        let secret = crypto.decrypt(password_secret).await?;
+        let password: [u8; PASSWORD_LENGTH] = secret
+            .as_str()
+            .and_then(|password| password.as_bytes().try_into().ok())
+            .ok_or_else(|| {
+                InternalError::configuration_error(
+                    &format!("The access key password must be {PASSWORD_LENGTH} bytes"),
+                    Some("access_guard"),
+                )
+            })?;
+        let keys = AccessKeyRing::new(AccessKeyVersion::new(1, password, 0));
+        Ok(Self::new(keys, event_access, ttl))
+    }
+
+    /// Replaces the key ring, e.g. after a rotation or when the revocations were reloaded
+    pub fn set_keys(&self, keys: AccessKeyRing) {
+        *self.keys.write().expect("access guard lock poisoned") = keys;
+        self.invalidate();
+    }
+
+    /// Turns the key away from now on, before its version expires
+    pub fn revoke(&self, access_key: &str) {
+        self.keys
+            .write()
+            .expect("access guard lock poisoned")
+            .revoked
+            .revoke(access_key);
+        self.grants
+            .write()
+            .expect("access guard lock poisoned")
+            .remove(&RevocationList::fingerprint(access_key));
+    }
+
+    fn validate(&self, access_key: &str, now: i64) -> Result<AccessKey, AccessRejection> {
+        let keys = self.keys.read().expect("access guard lock poisoned");
+        if keys.revoked.is_revoked(access_key) {
+            return Err(AccessRejection::Revoked);
+        }
+        keys.validate(access_key, now).map_err(|e| {
+            if e.stable_code() == StableErrorCode::AccessKeyExpired {
+                AccessRejection::Expired(e.message().to_string())
+            } else if e.subtype() == Some(REVOKED_SUBTYPE) {
+                AccessRejection::Revoked
+            } else {
+                AccessRejection::Invalid(e.message().to_string())
+            }
+        })
+    }
+
+    fn cached(&self, fingerprint: &str, now: i64) -> Option<Arc<AccessGrant>> {
+        self.grants
+            .read()
+            .expect("access guard lock poisoned")
+            .get(fingerprint)
+            .filter(|(at, grant)| at.elapsed() < self.ttl && !grant.access_key.data.is_expired(now))
+            .map(|(_, grant)| grant.clone())
+    }
+
+    /// Checks the key, and that it is of `environment` when given
+    pub async fn authorize(
+        &self,
+        access_key: Option<&str>,
+        environment: Option<Environment>,
+    ) -> Result<Arc<AccessGrant>, AccessRejection> {
+        let access_key = access_key
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .ok_or(AccessRejection::Missing)?;
+        let encrypted = EncryptedAccessKey::parse(access_key)
+            .map_err(|e| AccessRejection::Invalid(e.message().to_string()))?;
+        if let Some(environment) = environment {
+            encrypted.ensure_environment("request", environment)?;
+        }
+
+        let now = Utc::now().timestamp_millis();
+        let decrypted = self.validate(access_key, now)?;
+        let fingerprint = RevocationList::fingerprint(access_key);
+        if let Some(grant) = self.cached(&fingerprint, now) {
+            return Ok(grant);
+        }
+
+        let event_access = self
+            .event_access
+            .get_one(doc! { "accessKey": access_key, "deleted": false })
+            .await
+            .map_err(AccessRejection::Unavailable)?;
+        let event_access = Self::check_event_access(&encrypted, event_access)?;
+
+        let grant = Arc::new(AccessGrant {
+            access_key: decrypted,
+            event_access,
+        });
+        self.remember(fingerprint, grant.clone());
+        Ok(grant)
+    }
+
+    /// The event access found for the key, as long as it's active and of the key's
+    /// environment and type. Rejected ones are never cached.
+    fn check_event_access(
+        encrypted: &EncryptedAccessKey,
+        event_access: Option<EventAccess>,
+    ) -> Result<EventAccess, AccessRejection> {
+        let event_access = event_access.ok_or(AccessRejection::Unknown)?;
+        if !event_access.record_metadata.active {
+            return Err(AccessRejection::Inactive);
+        }
+        encrypted.ensure_matches_event_access(&event_access)?;
+        Ok(event_access)
+    }
+
+    fn remember(&self, fingerprint: String, grant: Arc<AccessGrant>) {
+        let mut grants = self.grants.write().expect("access guard lock poisoned");
+        grants.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        grants.insert(fingerprint, (Instant::now(), grant));
+    }
+
+    /// Same as [`AccessGuard::authorize`] with the `x-integrationos-secret` and
+    /// `x-integrationos-environment` headers
+    pub async fn authorize_headers(
+        &self,
+        headers: &HeaderMap,
+    ) -> Result<Arc<AccessGrant>, IntegrationOSError> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let environment = match header(ENVIRONMENT_HEADER) {
+            Some(environment) => Some(Environment::from_headers(Some(environment), None)?),
+            None => None,
+        };
+        Ok(self
+            .authorize(header(ACCESS_KEY_HEADER), environment)
+            .await?)
+    }
+
+    /// Forgets every cached key, e.g. after event accesses were deleted
+    pub fn invalidate(&self) {
+        self.grants
+            .write()
+            .expect("access guard lock poisoned")
+            .clear();
+    }
+}
+
+#[cfg(feature = "actix-error")]
+mod actix {
+    use super::{AccessGrant, AccessGuard};
+    use crate::{
+        prelude::{
+            access_key::encrypted_access_key::ACCESS_KEY_HEADER,
+            configuration::environment::{Environment, ENVIRONMENT_HEADER},
+        },
+        ApplicationError, IntegrationOSError,
+    };
+    use actix_web::{
+        body::MessageBody,
+        dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+        Error, FromRequest, HttpMessage, HttpRequest,
+    };
+    use futures::future::LocalBoxFuture;
+    use std::{
+        future::{ready, Ready},
+        rc::Rc,
+        sync::Arc,
+    };
+
+    /// Rejects requests without a valid access key, leaving the [`AccessGrant`] in the
+    /// request extensions for handlers to extract
+    impl<S, B> Transform<S, ServiceRequest> for AccessGuard
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+        B: MessageBody + 'static,
+    {
+        type Response = ServiceResponse<B>;
+        type Error = Error;
+        type Transform = AccessGuardMiddleware<S>;
+        type InitError = ();
+        type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+        fn new_transform(&self, service: S) -> Self::Future {
+            ready(Ok(AccessGuardMiddleware {
+                service: Rc::new(service),
+                guard: self.clone(),
+            }))
+        }
+    }
+
+    pub struct AccessGuardMiddleware<S> {
+        service: Rc<S>,
+        guard: AccessGuard,
+    }
+
+    impl<S, B> Service<ServiceRequest> for AccessGuardMiddleware<S>
+    where
+        S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+        B: MessageBody + 'static,
+    {
+        type Response = ServiceResponse<B>;
+        type Error = Error;
+        type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+        forward_ready!(service);
+
+        fn call(&self, req: ServiceRequest) -> Self::Future {
+            let service = self.service.clone();
+            let guard = self.guard.clone();
+            Box::pin(async move {
+                let header = |name| {
+                    req.headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_owned)
+                };
+                let environment = match header(ENVIRONMENT_HEADER) {
+                    Some(environment) => Some(Environment::from_headers(Some(&environment), None)?),
+                    None => None,
+                };
+                let grant = guard
+                    .authorize(header(ACCESS_KEY_HEADER).as_deref(), environment)
+                    .await
+                    .map_err(IntegrationOSError::from)?;
+                req.extensions_mut().insert(grant);
+                service.call(req).await
+            })
+        }
+    }
+
+    /// The grant left by the [`AccessGuard`] middleware, or `ReqData<Arc<AccessGrant>>`
+    /// to skip the clone
+    impl FromRequest for AccessGrant {
+        type Error = IntegrationOSError;
+        type Future = Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+            ready(
+                req.extensions()
+                    .get::<Arc<AccessGrant>>()
+                    .map(|grant| grant.as_ref().clone())
+                    .ok_or_else(|| {
+                        ApplicationError::unauthorized(
+                            "The route isn't behind an access guard",
+                            Some("access_guard"),
+                        )
+                    }),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "actix-error")]
+pub use actix::AccessGuardMiddleware;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        prelude::access_key::{access_key_data::AccessKeyData, event_type::EventType},
+        store::Store,
+    };
+    use serde_json::json;
+
+    const PASSWORD: &[u8; PASSWORD_LENGTH] = b"32KFFT_i4UpkJmyPwY2TGzgHpxfXs7zS";
+
+    fn data() -> AccessKeyData {
+        AccessKeyData {
+            id: "build-2e76c839f5fd419db6b34682f4cdff1e".to_owned(),
+            namespace: "default".to_owned(),
+            event_type: "webhook".to_owned(),
+            group: "my-webhook".to_owned(),
+            event_path: "event.received".to_owned(),
+            event_object_id_path: None,
+            timestamp_path: None,
+            parent_access_key: None,
+            expires_at: None,
+        }
+    }
+
+    async fn guard(keys: AccessKeyRing) -> AccessGuard {
+        // Never connects, every key below is answered from the cache or the key ring
+        let client = mongodb::Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let store = MongoStore::new(&client.database("test"), &Store::EventAccess)
+            .await
+            .unwrap();
+        AccessGuard::new(keys, store, Duration::from_secs(60))
+    }
+
+    fn event_access(access_key: &str) -> EventAccess {
+        serde_json::from_value(json!({
+            "_id": "evt_ac::AAAAAAAAAAA::AAAAAAAAAAAAAAAAAAAAAA",
+            "name": "webhook",
+            "key": "webhook",
+            "namespace": "default",
+            "platform": "stripe",
+            "type": "api",
+            "group": "my-webhook",
+            "ownership": { "buildableId": "owner", "clientId": "owner" },
+            "paths": {},
+            "accessKey": access_key,
+            "environment": "test"
+        }))
+        .unwrap()
+    }
+
+    fn grant(guard: &AccessGuard, access_key: &str) {
+        let event_access = event_access(access_key);
+        let access_key_data = guard.validate(access_key, 0).unwrap();
+        guard.remember(
+            RevocationList::fingerprint(access_key),
+            Arc::new(AccessGrant {
+                access_key: access_key_data,
+                event_access,
+            }),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_keys_skip_the_cache() {
+        let now = Utc::now().timestamp_millis();
+        let mut keys = AccessKeyRing::new(AccessKeyVersion::new(1, *PASSWORD, 0));
+        let issue = |keys: &AccessKeyRing, data| {
+            keys.issue(Environment::Test, EventType::SecretKey, data, &[7; 16])
+                .unwrap()
+                .to_string()
+        };
+        let key = issue(&keys, data());
+        let expiring = issue(&keys, data().with_expiry(now + 60_000));
+        let guard = guard(keys.clone()).await;
+        grant(&guard, &key);
+        grant(&guard, &expiring);
+
+        assert!(guard.authorize(Some(&key), None).await.is_ok());
+        assert_eq!(
+            guard.authorize(Some(&key), Some(Environment::Live)).await,
+            Err(AccessRejection::Mismatch(AccessKeyMismatch::Environment {
+                resource: "request",
+                expected: Environment::Live,
+                found: Environment::Test,
+            }))
+        );
+
+        guard.revoke(&key);
+        assert_eq!(
+            guard.authorize(Some(&key), None).await,
+            Err(AccessRejection::Revoked)
+        );
+
+        let expired = issue(&keys, data().with_expiry(now - 1));
+        assert!(matches!(
+            guard.authorize(Some(&expired), None).await,
+            Err(AccessRejection::Expired(_))
+        ));
+
+        assert!(guard.authorize(Some(&expiring), None).await.is_ok());
+        keys.rotate(*b"vOVH6sdmpNWjRRIqCc7rdxs01lxHzfr3", 0, now);
+        guard.set_keys(keys);
+        assert!(matches!(
+            guard.authorize(Some(&expiring), None).await,
+            Err(AccessRejection::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_inactive_event_access_is_rejected() {
+        let keys = AccessKeyRing::new(AccessKeyVersion::new(1, *PASSWORD, 0));
+        let key = keys
+            .issue(Environment::Test, EventType::SecretKey, data(), &[7; 16])
+            .unwrap()
+            .to_string();
+        let encrypted = EncryptedAccessKey::parse(&key).unwrap();
+
+        let mut record = event_access(&key);
+        assert_eq!(
+            AccessGuard::check_event_access(&encrypted, Some(record.clone())),
+            Ok(record.clone())
+        );
+        assert_eq!(
+            AccessGuard::check_event_access(&encrypted, None),
+            Err(AccessRejection::Unknown)
+        );
+
+        record.record_metadata.active = false;
+        assert_eq!(
+            AccessGuard::check_event_access(&encrypted, Some(record)),
+            Err(AccessRejection::Inactive)
+        );
+        assert_eq!(
+            IntegrationOSError::from(AccessRejection::Inactive).stable_code(),
+            StableErrorCode::Unauthorized
+        );
+    }
+
+    #[test]
+    fn test_rejections() {
+        let codes = [
+            AccessRejection::Missing,
+            AccessRejection::Invalid("No version in access key".to_owned()),
+            AccessRejection::Expired("Access key expired".to_owned()),
+            AccessRejection::Mismatch(AccessKeyMismatch::Environment {
+                resource: "request",
+                expected: Environment::Live,
+                found: Environment::Test,
+            }),
+        ]
+        .into_iter()
+        .map(|rejection| IntegrationOSError::from(rejection).stable_code())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            codes,
+            vec![
+                StableErrorCode::Unauthorized,
+                StableErrorCode::Unauthorized,
+                StableErrorCode::AccessKeyExpired,
+                StableErrorCode::Forbidden,
+            ]
+        );
+    }
+}
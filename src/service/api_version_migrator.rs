@@ -0,0 +1,160 @@
+use crate::{
+    prelude::{
+        connection::{
+            api_version_migration::{ApiVersionMigration, MigrationFailure, MigrationProgress},
+            Connection,
+        },
+        get_secret_request::GetSecretRequest,
+    },
+    ApplicationError, CryptoExt, IntegrationOSError, InternalError, MongoStore,
+};
+use bson::doc;
+use chrono::Utc;
+use tracing::{error, info};
+
+/// Moves every connection of a platform to a new API version according to an
+/// [`ApiVersionMigration`]
+pub struct ApiVersionMigrator<C> {
+    crypto: C,
+    connections: MongoStore<Connection>,
+    migrations: MongoStore<ApiVersionMigration>,
+}
+
+impl<C: CryptoExt + Sync> ApiVersionMigrator<C> {
+    pub fn new(
+        crypto: C,
+        connections: MongoStore<Connection>,
+        migrations: MongoStore<ApiVersionMigration>,
+    ) -> Self {
+        Self {
+            crypto,
+            connections,
+            migrations,
+        }
+    }
+
+    /// Migrates a single connection, renaming its secret fields under a new secret if
+    /// needed. Fails with a conflict when the connection changed meanwhile.
+    pub async fn migrate_connection(
+        &self,
+        migration: &ApiVersionMigration,
+        connection: &Connection,
+    ) -> Result<(), IntegrationOSError> {
+        let mut secrets_service_id = connection.secrets_service_id.clone();
+        if !migration.field_renames.is_empty() {
+            let secret = GetSecretRequest {
+                id: connection.secrets_service_id.clone(),
+                buildable_id: connection.ownership.id.to_string(),
+            };
+            let mut value = self.crypto.decrypt(&secret).await?;
+            if migration.rename_fields(&mut value) {
+                secrets_service_id = self
+                    .crypto
+                    .encrypt(connection.ownership.id.to_string(), &value)
+                    .await?
+                    .id;
+            }
+        }
+
+        let result = self
+            .connections
+            .collection
+            .update_one(
+                doc! {
+                    "_id": connection.id.to_string(),
+                    "platformVersion": &migration.from_version,
+                    "secretsServiceId": &connection.secrets_service_id,
+                },
+                doc! {
+                    "$set": {
+                        "platformVersion": &migration.to_version,
+                        "secretsServiceId": secrets_service_id,
+                        "updatedAt": Utc::now().timestamp_millis(),
+                    },
+                },
+                None,
+            )
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ApplicationError::conflict(
+                &format!("Connection {} changed concurrently", connection.id),
+                Some("api_version_migration"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Migrates every connection still on the migration's `from_version`. Connections
+    /// needing to be authorized again are left alone and reported, failures are recorded
+    /// and don't stop the run. The progress is stored on the migration at the end.
+    pub async fn run(
+        &self,
+        migration: &ApiVersionMigration,
+        mut on_progress: impl FnMut(&MigrationProgress),
+    ) -> Result<MigrationProgress, IntegrationOSError> {
+        let connections = self
+            .connections
+            .get_many(
+                Some(doc! {
+                    "platform": &migration.connection_platform,
+                    "platformVersion": &migration.from_version,
+                    "deleted": false,
+                }),
+                None,
+                Some(doc! { "_id": 1 }),
+                None,
+                None,
+            )
+            .await?;
+
+        let mut progress = MigrationProgress {
+            total: connections.len(),
+            ..Default::default()
+        };
+        for connection in &connections {
+            if migration.needs_reauth(connection) {
+                progress.pending_reauth.push(connection.id);
+            } else {
+                match self.migrate_connection(migration, connection).await {
+                    Ok(()) => progress.migrated += 1,
+                    Err(e) => {
+                        error!(
+                            "Could not migrate connection {} to {}: {e}",
+                            connection.id, migration.to_version
+                        );
+                        progress.failures.push(MigrationFailure {
+                            connection_id: connection.id,
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+            on_progress(&progress);
+        }
+
+        let stored = bson::to_bson(&progress).map_err(|e| {
+            InternalError::serialize_error(&e.to_string(), Some("api_version_migration"))
+        })?;
+        self.migrations
+            .update_one(
+                &migration.id.to_string(),
+                doc! {
+                    "$set": {
+                        "progress": stored,
+                        "updatedAt": Utc::now().timestamp_millis(),
+                    },
+                },
+            )
+            .await?;
+
+        info!(
+            "Migrated {} of {} {} connections to {}, {} pending authorization",
+            progress.migrated,
+            progress.total,
+            migration.connection_platform,
+            migration.to_version,
+            progress.pending_reauth.len()
+        );
+        Ok(progress)
+    }
+}
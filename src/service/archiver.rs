@@ -0,0 +1,140 @@
+use crate::{
+    configuration::archive::ArchiveConfig,
+    prelude::event::{archived_event::ArchivedEvent, Event},
+    IntegrationOSError, InternalError, MongoStore, ObjectStoreExt,
+};
+use bson::doc;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::options::ReplaceOptions;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Moves events older than [`ArchiveConfig::after_days`] to an object store, leaving an
+/// [`ArchivedEvent`] pointer in their place, and reads them back transparently.
+///
+/// The object is written before the pointer, and the pointer before the event is
+/// deleted, so an interrupted run leaves the event in place to be archived again.
+#[derive(Clone)]
+pub struct Archiver {
+    events: MongoStore<Event>,
+    archived: MongoStore<ArchivedEvent>,
+    objects: Arc<dyn ObjectStoreExt>,
+    config: ArchiveConfig,
+}
+
+impl Archiver {
+    pub fn new(
+        events: MongoStore<Event>,
+        archived: MongoStore<ArchivedEvent>,
+        objects: Arc<dyn ObjectStoreExt>,
+        config: ArchiveConfig,
+    ) -> Self {
+        Self {
+            events,
+            archived,
+            objects,
+            config,
+        }
+    }
+
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.config.after_days as i64)
+    }
+
+    /// Archives the oldest batch of events past the cutoff, returning how many were moved
+    pub async fn run_once(&self) -> Result<u64, IntegrationOSError> {
+        let cutoff = self.cutoff(Utc::now()).timestamp_millis();
+        let events = self
+            .events
+            .get_many(
+                Some(doc! { "arrivedAt": { "$lt": cutoff } }),
+                None,
+                Some(doc! { "arrivedAt": 1 }),
+                Some(self.config.batch_size),
+                None,
+            )
+            .await?;
+
+        let mut archived = 0;
+        for event in &events {
+            match self.archive(event).await {
+                Ok(_) => archived += 1,
+                Err(e) => warn!("Could not archive event {}: {e}", event.id),
+            }
+        }
+        if archived > 0 {
+            info!("Archived {archived} events that arrived before {cutoff}");
+        }
+        Ok(archived)
+    }
+
+    pub async fn archive(&self, event: &Event) -> Result<ArchivedEvent, IntegrationOSError> {
+        let body = serde_json::to_vec(event)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("archiver")))?;
+        let object_key = ArchivedEvent::object_key(&self.config.prefix, event);
+        let pointer = ArchivedEvent::new(event, object_key, body.len(), Utc::now());
+
+        self.objects
+            .put(&pointer.object_key, body, "application/json")
+            .await?;
+        self.archived
+            .collection
+            .replace_one(
+                doc! { "_id": pointer.id.to_string() },
+                &pointer,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        self.events
+            .collection
+            .delete_one(doc! { "_id": event.id.to_string() }, None)
+            .await?;
+        Ok(pointer)
+    }
+
+    /// The event, from the events collection or from the object store once archived
+    pub async fn get(&self, id: &str) -> Result<Option<Event>, IntegrationOSError> {
+        if let Some(event) = self.events.get_one_by_id(id).await? {
+            return Ok(Some(event));
+        }
+        let Some(pointer) = self.archived.get_one_by_id(id).await? else {
+            return Ok(None);
+        };
+        let Some(body) = self.objects.get(&pointer.object_key).await? else {
+            return Err(InternalError::key_not_found(
+                &format!(
+                    "Event {id} was archived to {} which no longer exists",
+                    pointer.object_key
+                ),
+                Some("archiver"),
+            ));
+        };
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("archiver")))
+    }
+
+    /// Moves an archived event back to the events collection
+    pub async fn restore(&self, id: &str) -> Result<Option<Event>, IntegrationOSError> {
+        let Some(pointer) = self.archived.get_one_by_id(id).await? else {
+            return Ok(None);
+        };
+        let Some(event) = self.get(id).await? else {
+            return Ok(None);
+        };
+        self.events
+            .collection
+            .replace_one(
+                doc! { "_id": id },
+                &event,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        self.archived
+            .collection
+            .delete_one(doc! { "_id": id }, None)
+            .await?;
+        self.objects.delete(&pointer.object_key).await?;
+        Ok(Some(event))
+    }
+}
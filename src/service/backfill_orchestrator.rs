@@ -0,0 +1,162 @@
+use crate::{
+    prelude::backfill::{BackfillJob, BackfillPage, BackfillStatus},
+    ApplicationError, Id, IntegrationOSError, InternalError, MongoStore, TokenBucket,
+};
+use async_trait::async_trait;
+use bson::doc;
+use serde_json::Value;
+use tracing::{error, info};
+
+#[async_trait]
+pub trait BackfillSource: Send + Sync {
+    async fn fetch_page(
+        &self,
+        job: &BackfillJob,
+        cursor: Option<&str>,
+    ) -> Result<BackfillPage, IntegrationOSError>;
+}
+
+#[async_trait]
+pub trait BackfillSink: Send + Sync {
+    async fn write(&self, job: &BackfillJob, records: Vec<Value>)
+        -> Result<(), IntegrationOSError>;
+}
+
+/// Runs backfill jobs page by page within their request budget, persisting the cursor
+/// after every page so a paused or interrupted job resumes where it stopped.
+pub struct BackfillOrchestrator<S, K> {
+    store: MongoStore<BackfillJob>,
+    source: S,
+    sink: K,
+}
+
+impl<S: BackfillSource, K: BackfillSink> BackfillOrchestrator<S, K> {
+    pub fn new(store: MongoStore<BackfillJob>, source: S, sink: K) -> Self {
+        Self {
+            store,
+            source,
+            sink,
+        }
+    }
+
+    pub async fn create(&self, job: BackfillJob) -> Result<BackfillJob, IntegrationOSError> {
+        self.store.create_one(&job).await?;
+        Ok(job)
+    }
+
+    pub async fn get(&self, id: &Id) -> Result<BackfillJob, IntegrationOSError> {
+        self.store
+            .get_one_by_id(&id.to_string())
+            .await?
+            .ok_or_else(|| {
+                InternalError::key_not_found(&format!("Backfill {id} not found"), Some("backfill"))
+            })
+    }
+
+    /// Persists the job unless its status changed underneath us, e.g. an operator paused
+    /// it while a page was in flight. Returns whether the write went through.
+    async fn save(
+        &self,
+        job: &BackfillJob,
+        expected: BackfillStatus,
+    ) -> Result<bool, IntegrationOSError> {
+        let document = bson::to_document(job)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("backfill")))?;
+        // Serialized rather than displayed so unknown statuses match their raw value
+        let expected = bson::to_bson(&expected)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("backfill")))?;
+        let result = self
+            .store
+            .collection
+            .update_one(
+                doc! { "_id": job.id.to_string(), "status": expected },
+                doc! { "$set": document },
+                None,
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn transition(
+        &self,
+        id: &Id,
+        f: impl FnOnce(&mut BackfillJob) -> Result<(), IntegrationOSError>,
+    ) -> Result<BackfillJob, IntegrationOSError> {
+        let mut job = self.get(id).await?;
+        let expected = job.status.clone();
+        f(&mut job)?;
+        if !self.save(&job, expected).await? {
+            return Err(ApplicationError::conflict(
+                &format!("Backfill {id} changed concurrently"),
+                Some("backfill"),
+            ));
+        }
+        Ok(job)
+    }
+
+    pub async fn pause(&self, id: &Id) -> Result<BackfillJob, IntegrationOSError> {
+        self.transition(id, BackfillJob::pause).await
+    }
+
+    pub async fn resume(&self, id: &Id) -> Result<BackfillJob, IntegrationOSError> {
+        self.transition(id, BackfillJob::resume).await
+    }
+
+    pub async fn cancel(&self, id: &Id) -> Result<BackfillJob, IntegrationOSError> {
+        self.transition(id, BackfillJob::cancel).await
+    }
+
+    /// Pages through the job until it completes, fails, or is paused or cancelled
+    pub async fn run(&self, id: &Id) -> Result<BackfillJob, IntegrationOSError> {
+        let mut job = self.get(id).await?;
+        if job.status == BackfillStatus::Pending {
+            job = self.transition(id, BackfillJob::start).await?;
+        }
+        let mut budget = TokenBucket::per_minute(
+            job.requests_per_minute as u64,
+            job.requests_per_minute as u64,
+        );
+
+        while job.status == BackfillStatus::Running {
+            budget.acquire(1).await;
+
+            let page = match self.source.fetch_page(&job, job.cursor.as_deref()).await {
+                Ok(page) => page,
+                Err(e) => return self.fail(job, e).await,
+            };
+            let BackfillPage {
+                records,
+                next_cursor,
+                total,
+            } = page;
+            let count = records.len() as u64;
+            if let Err(e) = self.sink.write(&job, records).await {
+                return self.fail(job, e).await;
+            }
+
+            job.record_page(count, next_cursor, total);
+            if !self.save(&job, BackfillStatus::Running).await? {
+                info!("Backfill {id} was paused or cancelled, stopping");
+                return self.get(id).await;
+            }
+        }
+
+        info!(
+            "Backfill {id} stopped as {} after {} records",
+            job.status, job.processed
+        );
+        Ok(job)
+    }
+
+    async fn fail(
+        &self,
+        mut job: BackfillJob,
+        e: IntegrationOSError,
+    ) -> Result<BackfillJob, IntegrationOSError> {
+        error!("Backfill {} failed: {e}", job.id);
+        let message = e.to_string();
+        job.fail(&message);
+        self.save(&job, BackfillStatus::Running).await?;
+        Err(e)
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     api_model_config::{ApiModelConfig, AuthMethod},
-    prelude::oauth_secret::OAuthSecret,
-    IntegrationOSError, InternalError,
+    prelude::{
+        connection::connection_auth::ConnectionAuth, oauth_secret::OAuthSecret,
+        shared::correlation_id::CorrelationId,
+    },
+    EgressGuard, IntegrationOSError, InternalError,
 };
 use http::HeaderMap;
 use reqwest::{Client, Response};
@@ -13,6 +16,9 @@ pub struct CallerClient<'a> {
     config: &'a ApiModelConfig,
     action: http::Method,
     client: &'a Client,
+    correlation_id: Option<&'a CorrelationId>,
+    auth: Option<&'a ConnectionAuth>,
+    egress_guard: Option<&'a EgressGuard>,
 }
 
 impl<'a> CallerClient<'a> {
@@ -21,9 +27,32 @@ impl<'a> CallerClient<'a> {
             config,
             action,
             client,
+            correlation_id: None,
+            auth: None,
+            egress_guard: None,
         }
     }
 
+    pub fn with_correlation_id(mut self, correlation_id: &'a CorrelationId) -> Self {
+        self.correlation_id = Some(correlation_id);
+        self
+    }
+
+    /// Authenticates with the connection's `auth` instead of the `auth_method` of the
+    /// config, unless it is [`ConnectionAuth::None`]
+    pub fn with_auth(mut self, auth: &'a ConnectionAuth) -> Self {
+        self.auth = Some(auth).filter(|auth| **auth != ConnectionAuth::None);
+        self
+    }
+
+    /// Checks the endpoint against the guard before sending the request. The client
+    /// should come from [`EgressGuard::client`], which checks the hosts it connects to
+    /// and every redirect target.
+    pub fn with_egress_guard(mut self, egress_guard: &'a EgressGuard) -> Self {
+        self.egress_guard = Some(egress_guard);
+        self
+    }
+
     pub async fn make_request(
         &self,
         payload: Option<Vec<u8>>,
@@ -37,24 +66,35 @@ impl<'a> CallerClient<'a> {
             format!("{}/{}", self.config.base_url, self.config.path)
         };
 
+        if let Some(guard) = self.egress_guard {
+            guard.check(&endpoint)?;
+        }
         let mut request_builder = self.client.request(self.action.clone(), &endpoint);
 
         let mut merged_headers = headers.unwrap_or_default();
 
         if let Some(model_headers) = &self.config.headers {
-            merged_headers.extend(model_headers.clone());
+            merged_headers.extend(model_headers.to_header_map(secret)?);
         }
 
         merged_headers.remove(http::header::CONTENT_LENGTH);
         merged_headers.remove(http::header::ACCEPT_ENCODING);
         merged_headers.remove(http::header::HOST);
 
+        if let Some(correlation_id) = self.correlation_id {
+            correlation_id.inject(&mut merged_headers);
+        }
+
+        if let Some(auth) = self.auth {
+            auth.apply(&mut merged_headers, secret.unwrap_or(&Value::Null))?;
+        }
+
         for (key, value) in merged_headers.iter() {
             request_builder = request_builder.header(key, value);
         }
 
         if let Some(model_query_params) = &self.config.query_params {
-            request_builder = request_builder.query(model_query_params);
+            request_builder = request_builder.query(&model_query_params.to_pairs(secret)?);
         }
 
         if let Some(custom_query_params) = query_params {
@@ -66,6 +106,7 @@ impl<'a> CallerClient<'a> {
         }
 
         request_builder = match &self.config.auth_method {
+            _ if self.auth.is_some() => request_builder,
             AuthMethod::BearerToken { value } => request_builder.bearer_auth(value),
             AuthMethod::ApiKey { key, value } => request_builder.header(key, value),
             AuthMethod::BasicAuth { username, password } => {
@@ -84,7 +125,7 @@ impl<'a> CallerClient<'a> {
                     format!(
                         "{} {}",
                         secret.token_type.unwrap_or("Bearer".into()),
-                        secret.access_token
+                        secret.access_token.expose_secret()
                     ),
                 )
             }
@@ -151,6 +192,7 @@ mod tests {
             },
             responses: vec![],
             paths: None,
+            pagination: None,
         };
 
         let stripe_model_config = ConnectionModelDefinition {
@@ -224,6 +266,7 @@ mod tests {
             },
             responses: vec![],
             paths: None,
+            pagination: None,
         };
 
         let stripe_model_config = ConnectionModelDefinition {
@@ -259,4 +302,167 @@ mod tests {
         let response = res.bytes().await.unwrap();
         assert_eq!(response, "Not found".as_bytes().to_vec());
     }
+
+    #[tokio::test]
+    async fn test_make_request_forwards_correlation_id() {
+        let mut mock_server = Server::new_async().await;
+
+        let mock = mock_server
+            .mock("GET", "/api/customers")
+            .match_header(
+                crate::prelude::shared::correlation_id::CORRELATION_ID_HEADER,
+                "corr-123",
+            )
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let api_model_config = ApiModelConfig {
+            base_url: mock_server.url() + "/api",
+            path: "customers".to_string(),
+            auth_method: AuthMethod::None,
+            headers: None,
+            content: None,
+            query_params: None,
+            schemas: SchemasInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            samples: SamplesInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            responses: vec![],
+            paths: None,
+            pagination: None,
+        };
+
+        let client = Client::new();
+        let correlation_id = CorrelationId::from("corr-123");
+        let res = CallerClient::new(&api_model_config, http::Method::GET, &client)
+            .with_correlation_id(&correlation_id)
+            .make_request(None, None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_make_request_uses_connection_auth() {
+        let mut mock_server = Server::new_async().await;
+
+        let mock = mock_server
+            .mock("GET", "/api/customers")
+            .match_header("x-api-key", "Token key_123")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let api_model_config = ApiModelConfig {
+            base_url: mock_server.url() + "/api",
+            path: "customers".to_string(),
+            auth_method: AuthMethod::BearerToken {
+                value: "sample-key".to_string(),
+            },
+            headers: None,
+            content: None,
+            query_params: None,
+            schemas: SchemasInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            samples: SamplesInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            responses: vec![],
+            paths: None,
+            pagination: None,
+        };
+
+        let client = Client::new();
+        let auth = ConnectionAuth::ApiKey {
+            header: "X-Api-Key".to_owned(),
+            prefix: Some("Token".to_owned()),
+        };
+        let secret = serde_json::json!({ "API_KEY": "key_123" });
+        let res = CallerClient::new(&api_model_config, http::Method::GET, &client)
+            .with_auth(&auth)
+            .make_request(None, Some(&secret), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_make_request_checks_egress_guard() {
+        let mut mock_server = Server::new_async().await;
+
+        let mock = mock_server
+            .mock("GET", "/api/customers")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let api_model_config = ApiModelConfig {
+            base_url: mock_server.url() + "/api",
+            path: "customers".to_string(),
+            auth_method: AuthMethod::None,
+            headers: None,
+            content: None,
+            query_params: None,
+            schemas: SchemasInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            samples: SamplesInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            responses: vec![],
+            paths: None,
+            pagination: None,
+        };
+
+        let guard = EgressGuard::default();
+        let client = guard.client().unwrap();
+        let error = CallerClient::new(&api_model_config, http::Method::GET, &client)
+            .with_egress_guard(&guard)
+            .make_request(None, None, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("blocked address"));
+
+        let guard = EgressGuard::new(&crate::egress::EgressConfig {
+            allowed_networks: "127.0.0.1".to_owned(),
+            ..Default::default()
+        })
+        .unwrap();
+        let client = guard.client().unwrap();
+        let res = CallerClient::new(&api_model_config, http::Method::GET, &client)
+            .with_egress_guard(&guard)
+            .make_request(None, None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        mock.assert_async().await;
+    }
 }
@@ -3,3 +3,4 @@ pub mod secrets_client;
 #[cfg(feature = "unified")]
 pub mod unified_destination_client;
 pub mod watchdog_client;
+pub mod watchdog_status;
@@ -11,8 +11,8 @@ use crate::{
     get_secret_request::GetSecretRequest,
     hashed_secret::HashedSecret,
     id::{prefix::IdPrefix, Id},
-    prelude::{CryptoExt, MongoStore, TimedExt},
-    Connection, ErrorMeta, IntegrationOSError, Store,
+    prelude::{definition_cache::DefinitionCache, CryptoExt, MongoStore, TimedExt},
+    Connection, EgressGuard, ErrorMeta, IntegrationOSError, Store,
 };
 use bson::doc;
 use chrono::Utc;
@@ -47,11 +47,13 @@ pub struct UnifiedDestination {
     connection_model_definitions_destination_cache:
         Cache<Destination, Arc<ConnectionModelDefinition>>,
     connection_model_definitions_store: MongoStore<ConnectionModelDefinition>,
+    definition_cache: Option<DefinitionCache>,
     connection_model_schemas_cache: Cache<(Arc<str>, Arc<str>), Arc<ConnectionModelSchema>>,
     connection_model_schemas_store: MongoStore<ConnectionModelSchema>,
     secrets_client: Arc<dyn CryptoExt + Sync + Send>,
     secrets_cache: Cache<Connection, Arc<Value>>,
     http_client: reqwest::Client,
+    egress_guard: Option<EgressGuard>,
     renderer: Option<Arc<RwLock<Handlebars<'static>>>>,
 }
 
@@ -214,11 +216,13 @@ impl UnifiedDestination {
             connection_model_definitions_cache,
             connection_model_definitions_destination_cache,
             connection_model_definitions_store,
+            definition_cache: None,
             connection_model_schemas_cache,
             connection_model_schemas_store,
             secrets_client,
             secrets_cache,
             http_client,
+            egress_guard: None,
             renderer: if cache_size == 0 {
                 None
             } else {
@@ -227,6 +231,23 @@ impl UnifiedDestination {
         })
     }
 
+    /// Only calls platforms, and follows redirects, that `egress_guard` allows. Replaces
+    /// the HTTP client with one built by the guard, shared by every call.
+    pub fn with_egress_guard(
+        mut self,
+        egress_guard: EgressGuard,
+    ) -> Result<Self, IntegrationOSError> {
+        self.http_client = egress_guard.client()?;
+        self.egress_guard = Some(egress_guard);
+        Ok(self)
+    }
+
+    /// Looks unified definitions up in `definition_cache` instead of querying Mongo
+    pub fn with_definition_cache(mut self, definition_cache: DefinitionCache) -> Self {
+        self.definition_cache = Some(definition_cache);
+        self
+    }
+
     pub async fn get_connection_model_definition(
         &self,
         destination: &Destination,
@@ -276,25 +297,31 @@ impl UnifiedDestination {
                     Ok(None)
                 }
             }
-            Action::Unified { name, action, .. } => Ok(self
-                .connection_model_definitions_store
-                .collection
-                .find_one(
-                    doc! {
-                        "connectionPlatform": destination.platform.as_ref(),
-                        "mapping.commonModelName": name.as_ref(),
-                        "actionName": action.to_string()
-                    },
-                    FindOneOptions::builder()
-                        .collation(Some(
-                            Collation::builder()
-                                .strength(CollationStrength::Secondary)
-                                .locale("en")
-                                .build(),
-                        ))
-                        .build(),
-                )
-                .await?),
+            Action::Unified { name, action, .. } => match &self.definition_cache {
+                Some(cache) => Ok(cache
+                    .get(&destination.platform, name, action.clone())
+                    .await?
+                    .map(|definition| definition.as_ref().clone())),
+                None => Ok(self
+                    .connection_model_definitions_store
+                    .collection
+                    .find_one(
+                        doc! {
+                            "connectionPlatform": destination.platform.as_ref(),
+                            "mapping.commonModelName": name.as_ref(),
+                            "actionName": action.to_string()
+                        },
+                        FindOneOptions::builder()
+                            .collation(Some(
+                                Collation::builder()
+                                    .strength(CollationStrength::Secondary)
+                                    .locale("en")
+                                    .build(),
+                            ))
+                            .build(),
+                    )
+                    .await?),
+            },
         }
     }
 
@@ -344,6 +371,10 @@ impl UnifiedDestination {
         match config.platform_info {
             PlatformInfo::Api(ref c) => {
                 let api_caller = CallerClient::new(c, config.action, &self.http_client);
+                let api_caller = match &self.egress_guard {
+                    Some(guard) => api_caller.with_egress_guard(guard),
+                    None => api_caller,
+                };
 
                 let response = api_caller
                     .make_request(context, Some(secret), Some(headers), Some(query_params))
@@ -1,28 +1,37 @@
+use super::watchdog_status::WatchdogStatusHandle;
 use crate::{
     cache::CacheConfig,
     database::DatabaseConfig,
     event_with_context::EventWithContext,
     pipeline_context::PipelineStage,
-    prelude::{MongoStore, RedisCache},
+    prelude::event::dead_letter::DeadLetter,
+    prelude::{
+        context_compaction::ContextCompaction, context_retention::ContextRetention,
+        task_supervisor::TaskSupervisor, MessageBusExt, MongoStore, RedisCache, TokenBucket,
+    },
     root_context::RootStage,
-    watchdog::WatchdogConfig,
-    Event, ExtractorContext, IntegrationOSError, InternalError, PipelineContext, RootContext,
-    Store,
+    watchdog::{ReconciliationPolicy, WatchdogConfig},
+    Event, ExtractorContext, IntegrationOSError, InternalError, PipelineContext, PipelineStatus,
+    ReconciliationAttempts, RootContext, Store,
 };
 use bson::{doc, Bson, Document};
 use chrono::Utc;
 use futures::{future::join_all, TryStreamExt};
-use mongodb::options::FindOneOptions;
-use redis::{AsyncCommands, LposOptions, RedisResult};
+use mongodb::{
+    options::{FindOneOptions, UpdateOptions},
+    Collection,
+};
 use std::fmt::Display;
-use std::time::Duration;
-use tokio::task::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
+#[derive(Clone)]
 pub struct WatchdogClient {
     watchdog: WatchdogConfig,
     cache: CacheConfig,
     database: DatabaseConfig,
+    status: WatchdogStatusHandle,
 }
 
 impl Display for WatchdogClient {
@@ -44,41 +53,94 @@ impl WatchdogClient {
             watchdog,
             cache,
             database,
+            status: WatchdogStatusHandle::default(),
         }
     }
 
-    pub fn start(self) -> JoinHandle<Result<(), IntegrationOSError>> {
-        tokio::spawn(self.run())
+    /// Live status of the watchdog, to be taken before starting it
+    pub fn status(&self) -> WatchdogStatusHandle {
+        self.status.clone()
+    }
+
+    /// Runs the watchdog under a [`TaskSupervisor`], restarting it whenever it fails until
+    /// the supervisor is shut down
+    pub fn start(self) -> TaskSupervisor {
+        let mut supervisor = TaskSupervisor::new();
+        supervisor.spawn("watchdog", move |shutdown| {
+            self.clone().run_until_cancelled(shutdown)
+        });
+        supervisor
     }
 
     pub async fn run(self) -> Result<(), IntegrationOSError> {
+        self.run_until_cancelled(CancellationToken::new()).await
+    }
+
+    /// Same as [`WatchdogClient::run`], returning once `shutdown` is cancelled
+    pub async fn run_until_cancelled(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), IntegrationOSError> {
         info!("Starting watchdog");
-        let mut cache = RedisCache::new(&self.cache, 3).await.map_err(|e| {
+        let cache = RedisCache::new(&self.cache, 3).await.map_err(|e| {
             error!("Could not connect to cache: {e}");
             InternalError::io_err(e.to_string().as_str(), None)
         })?;
-        let key = self.cache.event_throughput_key.clone();
-
-        info!("Initializing connection to cache");
+        self.run_with_bus(cache, shutdown).await
+    }
 
-        let mut redis_clone = cache.clone();
-        tokio::spawn(async move {
-            loop {
-                let _: RedisResult<String> = async { redis_clone.del(key.clone()).await }.await;
-                tokio::time::sleep(Duration::from_secs(1)).await;
+    /// Applies a policy other than republishing to a dead chain, which is then marked as
+    /// dropped so the next cycles leave it alone
+    async fn reconcile<B: MessageBusExt + Sync>(
+        &self,
+        policy: ReconciliationPolicy,
+        bus: &B,
+        dead_letters: &MongoStore<DeadLetter>,
+        root_coll: &Collection<RootContext>,
+        event_with_context: &EventWithContext,
+        attempts: u32,
+    ) -> Result<(), IntegrationOSError> {
+        let event_key = event_with_context.context.event_key;
+        let reason = format!("Unresponsive after {attempts} republish attempts");
+        match policy {
+            ReconciliationPolicy::Republish | ReconciliationPolicy::Drop => {}
+            ReconciliationPolicy::Quarantine => {
+                let letter = DeadLetter::new(event_with_context.clone(), attempts, reason.clone());
+                dead_letters.create_one(&letter).await?;
             }
-        });
-
-        let key = self.cache.api_throughput_key.clone();
-        let mut redis_clone = cache.clone();
-        tokio::spawn(async move {
-            loop {
-                let _: RedisResult<String> = async { redis_clone.del(key.clone()).await }.await;
-                tokio::time::sleep(Duration::from_secs(60)).await;
+            ReconciliationPolicy::Notify => {
+                let notice = serde_json::json!({
+                    "eventKey": event_key,
+                    "attempts": attempts,
+                    "reason": reason,
+                });
+                let payload = serde_json::to_vec(&notice).map_err(|e| {
+                    InternalError::serialize_error(&e.to_string(), Some("watchdog"))
+                })?;
+                bus.publish(&self.watchdog.reconciliation_notify_topic, &payload)
+                    .await?;
             }
-        });
+        }
+
+        let mut root_context = event_with_context.context.clone();
+        root_context.status = PipelineStatus::Dropped { reason };
+        root_context.timestamp = Utc::now();
+        root_coll.insert_one(&root_context, None).await?;
+        warn!("Applied the {policy} reconciliation policy to unresponsive context {event_key}");
+        Ok(())
+    }
 
-        info!("Initialized connection to cache");
+    /// Same as [`WatchdogClient::run`], republishing onto the given message bus instead of
+    /// Redis. The bus is only asked whether an event is already queued, so on log based
+    /// brokers an event may be republished while its first copy still waits.
+    pub async fn run_with_bus<B>(
+        self,
+        bus: B,
+        shutdown: CancellationToken,
+    ) -> Result<(), IntegrationOSError>
+    where
+        B: MessageBusExt + Send + Sync + 'static,
+    {
         info!("Intializing connection to storage");
 
         let mongo = mongodb::Client::with_uri_str(self.database.context_db_url.clone())
@@ -94,6 +156,8 @@ impl WatchdogClient {
             db.collection::<PipelineContext>(&self.database.context_collection_name);
         let extractor_coll =
             db.collection::<ExtractorContext>(&self.database.context_collection_name);
+        let attempts_coll =
+            db.collection::<ReconciliationAttempts>(&self.database.context_collection_name);
         let event_client = mongodb::Client::with_uri_str(self.database.event_db_url.clone())
             .await
             .map_err(|e| {
@@ -108,16 +172,69 @@ impl WatchdogClient {
                 error!("Could not connect to event db: {e}");
                 InternalError::io_err(e.to_string().as_str(), None)
             })?;
+        let dead_letters: MongoStore<DeadLetter> =
+            MongoStore::new(&event_db, &Store::DeadLetters).await?;
 
         info!("Initialized connection to storage");
+        self.status.update(|status| {
+            status.started_at = Some(Utc::now().timestamp_millis());
+            status.mongo_connected = true;
+            status.bus_connected = true;
+        });
+
+        let retention = ContextRetention::new(
+            coll.clone(),
+            self.watchdog.context_scan_window,
+            self.watchdog.context_retention,
+            self.watchdog.max_keys_per_cycle.max(100),
+        );
+        if let Err(e) = retention.ensure_indexes().await {
+            warn!("Could not create context retention indexes: {e}");
+        }
+        let compaction = (self.watchdog.context_compaction_age > 0).then(|| {
+            ContextCompaction::new(
+                coll.clone(),
+                self.watchdog.context_compaction_age,
+                self.watchdog.max_keys_per_cycle.max(100),
+            )
+        });
+
+        // Budget of Mongo operations shared by every cycle, unused tokens carry over
+        let mut budget = match self.watchdog.max_db_ops_per_second {
+            0 => TokenBucket::unlimited(),
+            rate => TokenBucket::new(rate, self.watchdog.db_ops_burst.max(rate)),
+        };
 
         loop {
+            if shutdown.is_cancelled() {
+                info!("Watchdog shut down");
+                return Ok(());
+            }
             info!("Polling for unresponsive contexts");
+            let cycle_started = Instant::now();
             let mut count = 0;
             let timestamp =
                 Utc::now().timestamp_millis() - (self.watchdog.event_timeout * 1_000) as i64;
 
-            let pipeline = vec![
+            budget.acquire(1).await;
+            if let Err(e) = retention.run_once().await {
+                error!("Failed to summarize completed contexts: {e}");
+            }
+            if let Some(compaction) = &compaction {
+                if let Err(e) = compaction.run_once(&mut budget).await {
+                    error!("Failed to compact superseded contexts: {e}");
+                }
+            }
+
+            let mut filter = doc! { "type": { "$ne": ReconciliationAttempts::TYPE } };
+            // Only contexts written in the recent window are candidates, keeping the scan flat as history grows
+            if let Some(window) =
+                ContextRetention::window_filter(self.watchdog.context_scan_window, Utc::now())
+            {
+                filter.extend(window);
+            }
+            let mut pipeline = vec![doc! { "$match": filter }];
+            pipeline.extend([
                 // Sort by timestamp to get latest contexts first
                 doc! {
                   "$sort": {
@@ -158,15 +275,24 @@ impl WatchdogClient {
                     "status": { "$eq": "Succeeded" }
                   }
                 },
-            ];
+            ]);
+
+            // Remaining dead contexts are picked up by the next cycles
+            if self.watchdog.max_keys_per_cycle > 0 {
+                pipeline.push(doc! { "$limit": self.watchdog.max_keys_per_cycle as i64 });
+            }
+
+            budget.acquire(1).await;
 
             let mut event_keys = match coll.clone().aggregate(pipeline, None).await {
                 Ok(e) => e,
                 Err(e) => {
                     error!("Failed to fetch event keys: {e}");
+                    self.status.update(|status| status.mongo_connected = false);
                     continue;
                 }
             };
+            self.status.update(|status| status.mongo_connected = true);
 
             info!("Fetched event keys");
 
@@ -181,6 +307,7 @@ impl WatchdogClient {
                     .build();
 
                 // Get the latest root context, then also get all latest pipeline contexts and extractor contexts if applicable
+                budget.acquire(1).await;
                 let root_context = match root_coll
                     .clone()
                     .find_one(
@@ -204,6 +331,7 @@ impl WatchdogClient {
                 };
 
                 if let RootStage::ProcessingPipelines(ref mut pipelines) = root_context.stage {
+                    budget.acquire(pipelines.len() as u64).await;
                     let futs = pipelines.values().map(|p| {
                         pipeline_coll.find_one(
                             doc! {
@@ -226,6 +354,7 @@ impl WatchdogClient {
                                 if let PipelineStage::ExecutingExtractors(ref mut extractors) =
                                     context.stage
                                 {
+                                    budget.acquire(extractors.len() as u64).await;
                                     let futs = extractors.values().map(|e| {
                                         let filter = doc! {
                                             "eventKey": e.event_key.to_string(),
@@ -263,8 +392,18 @@ impl WatchdogClient {
                     }
                 }
 
-                info!("Republishing unresponsive context {event_key}");
-
+                budget.acquire(2).await;
+                let attempts_id = ReconciliationAttempts::id_for(&root_context.event_key);
+                let attempts = match attempts_coll
+                    .find_one(doc! { "_id": &attempts_id }, None)
+                    .await
+                {
+                    Ok(attempts) => attempts.map_or(0, |a| a.attempts),
+                    Err(e) => {
+                        error!("Could not fetch republish attempts for {event_key}: {e}");
+                        continue;
+                    }
+                };
                 let Some(event) = event_store.get_one_by_id(event_key).await.map_err(|e| {
                     error!("Could not fetch event for context {event_key}: {e}");
                     InternalError::io_err(e.to_string().as_str(), None)
@@ -276,42 +415,108 @@ impl WatchdogClient {
 
                 let event_with_context = EventWithContext::new(event, root_context);
 
-                let payload = match serde_json::to_vec(&event_with_context) {
+                let policy = self.watchdog.reconciliation_for(attempts);
+                if policy != ReconciliationPolicy::Republish {
+                    budget.acquire(2).await;
+                    if let Err(e) = self
+                        .reconcile(
+                            policy,
+                            &bus,
+                            &dead_letters,
+                            &root_coll,
+                            &event_with_context,
+                            attempts,
+                        )
+                        .await
+                    {
+                        error!("Could not reconcile unresponsive context {event_key}: {e}");
+                    }
+                    continue;
+                }
+
+                info!("Republishing unresponsive context {event_key}");
+
+                let payload = match event_with_context.to_payload() {
                     Ok(c) => c,
                     Err(e) => {
                         error!("Could not serialize payload {event_with_context:?}: {e}");
                         continue;
                     }
                 };
-                let matching_idx = cache
-                    .lpos::<&str, &[u8], Option<isize>>(
-                        &self.cache.queue_name,
-                        &payload,
-                        LposOptions::default(),
-                    )
+                let pending = bus
+                    .is_pending(&self.cache.queue_name, &payload)
                     .await
-                    .map_err(|e| {
-                        error!("Could not check if context is already in redis: {e}");
-                        InternalError::io_err(e.to_string().as_str(), None)
+                    .inspect_err(|e| {
+                        error!("Could not check if context is already queued: {e}");
+                        self.status.update(|status| status.bus_connected = false);
                     })?;
 
-                if (matching_idx).is_some() {
-                    warn!("Unresponsive context is already in redis {event_key}");
+                if pending {
+                    warn!("Unresponsive context is already queued {event_key}");
                     continue;
                 }
 
-                match cache.lpush(&self.cache.queue_name, payload).await {
-                    Ok(()) => count += 1,
-                    Err(e) => error!("Could not publish event to redis: {e}"),
+                match bus.publish(&self.cache.queue_name, &payload).await {
+                    Ok(()) => {
+                        count += 1;
+                        // Counted once published, so poison events stop looping once exhausted
+                        budget.acquire(1).await;
+                        let update = attempts_coll
+                            .update_one(
+                                doc! { "_id": &attempts_id },
+                                doc! {
+                                    "$inc": { "attempts": 1 },
+                                    "$set": { "lastAttemptAt": Utc::now().timestamp_millis() },
+                                    "$setOnInsert": {
+                                        "eventKey": event_key,
+                                        "type": ReconciliationAttempts::TYPE,
+                                    },
+                                },
+                                UpdateOptions::builder().upsert(true).build(),
+                            )
+                            .await;
+                        if let Err(e) = update {
+                            error!("Could not record republish attempt for {event_key}: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        error!("Could not publish event: {e}");
+                        self.status.update(|status| status.bus_connected = false);
+                    }
                 }
             }
 
+            let lag = bus.pending_count(&self.cache.queue_name).await;
+            if let Err(e) = &lag {
+                error!("Could not measure the queue lag: {e}");
+            }
+            self.status.update(|status| {
+                status.last_loop_at = Some(Utc::now().timestamp_millis());
+                status.last_loop_duration_ms = Some(cycle_started.elapsed().as_millis() as u64);
+                status.loops += 1;
+                status.republished += count;
+                status.last_republished = count;
+                match lag {
+                    Ok(Some(lag)) => {
+                        status.bus_connected = true;
+                        status
+                            .queue_lag
+                            .insert(self.cache.queue_name.clone(), lag as u64);
+                    }
+                    Ok(None) => status.bus_connected = true,
+                    Err(_) => status.bus_connected = false,
+                }
+            });
+
             if count > 0 {
                 info!("Republished {count} new events");
             }
 
             info!("Sleeping for {} seconds", self.watchdog.poll_duration);
-            tokio::time::sleep(Duration::from_secs(self.watchdog.poll_duration)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(self.watchdog.poll_duration)) => {}
+                _ = shutdown.cancelled() => {}
+            }
         }
     }
 }
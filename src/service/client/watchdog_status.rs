@@ -0,0 +1,177 @@
+use chrono::Utc;
+use http::StatusCode;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// Observable state of a running watchdog, for health checks and alerting
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchdogStatus {
+    /// When the watchdog started, in epoch ms
+    pub started_at: Option<i64>,
+    /// When the last polling cycle completed, in epoch ms
+    pub last_loop_at: Option<i64>,
+    pub last_loop_duration_ms: Option<u64>,
+    pub loops: u64,
+    /// Events republished since the start
+    pub republished: u64,
+    /// Events republished by the last cycle
+    pub last_republished: u64,
+    /// Messages waiting per queue, for the buses able to tell
+    pub queue_lag: BTreeMap<String, u64>,
+    pub mongo_connected: bool,
+    /// Whether the message bus, Redis by default, answered the last command
+    pub bus_connected: bool,
+}
+
+impl WatchdogStatus {
+    /// Whether the watchdog went through a cycle in the last `max_silence`, e.g. a few
+    /// poll durations, and can reach its backends
+    pub fn is_healthy(&self, max_silence: Duration) -> bool {
+        let now = Utc::now().timestamp_millis();
+        let last_activity = self.last_loop_at.or(self.started_at);
+        self.mongo_connected
+            && self.bus_connected
+            && last_activity.is_some_and(|at| now - at <= max_silence.as_millis() as i64)
+    }
+
+    /// Prometheus text exposition of the status
+    #[cfg(feature = "metrics")]
+    pub fn to_prometheus(&self) -> String {
+        let mut metrics = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+            metrics.push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"));
+            for (labels, value) in samples {
+                metrics.push_str(&format!("{name}{labels} {value}\n"));
+            }
+        };
+        let gauge = |value: f64| vec![(String::new(), value)];
+        metric(
+            "watchdog_last_loop_timestamp_seconds",
+            "gauge",
+            "When the last polling cycle completed",
+            self.last_loop_at
+                .map(|at| gauge(at as f64 / 1_000.0))
+                .unwrap_or_default(),
+        );
+        metric(
+            "watchdog_last_loop_duration_seconds",
+            "gauge",
+            "How long the last polling cycle took",
+            self.last_loop_duration_ms
+                .map(|ms| gauge(ms as f64 / 1_000.0))
+                .unwrap_or_default(),
+        );
+        metric(
+            "watchdog_loops_total",
+            "counter",
+            "Polling cycles completed",
+            gauge(self.loops as f64),
+        );
+        metric(
+            "watchdog_republished_total",
+            "counter",
+            "Unresponsive events republished",
+            gauge(self.republished as f64),
+        );
+        metric(
+            "watchdog_queue_lag",
+            "gauge",
+            "Messages waiting on the queue",
+            self.queue_lag
+                .iter()
+                .map(|(queue, lag)| (format!("{{queue=\"{queue}\"}}"), *lag as f64))
+                .collect(),
+        );
+        metric(
+            "watchdog_mongo_up",
+            "gauge",
+            "Whether Mongo is reachable",
+            gauge(self.mongo_connected as u8 as f64),
+        );
+        metric(
+            "watchdog_bus_up",
+            "gauge",
+            "Whether the message bus is reachable",
+            gauge(self.bus_connected as u8 as f64),
+        );
+        metrics
+    }
+}
+
+/// Shared view of a [`WatchdogStatus`], updated by the watchdog while it runs
+#[derive(Debug, Clone, Default)]
+pub struct WatchdogStatusHandle(Arc<RwLock<WatchdogStatus>>);
+
+impl WatchdogStatusHandle {
+    pub fn get(&self) -> WatchdogStatus {
+        self.0
+            .read()
+            .expect("watchdog status lock poisoned")
+            .clone()
+    }
+
+    pub(crate) fn update(&self, f: impl FnOnce(&mut WatchdogStatus)) {
+        f(&mut self.0.write().expect("watchdog status lock poisoned"));
+    }
+
+    /// Response of a health endpoint: `200` with the status when healthy, `503` otherwise
+    pub fn health(&self, max_silence: Duration) -> (StatusCode, serde_json::Value) {
+        let status = self.get();
+        let code = if status.is_healthy(max_silence) {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (code, serde_json::to_value(status).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stalled_watchdog_is_unhealthy() {
+        let handle = WatchdogStatusHandle::default();
+        assert_eq!(
+            handle.health(Duration::from_secs(60)).0,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        handle.update(|status| {
+            status.mongo_connected = true;
+            status.bus_connected = true;
+            status.last_loop_at = Some(Utc::now().timestamp_millis());
+            status.queue_lag.insert("events".to_owned(), 3);
+        });
+        let (code, body) = handle.health(Duration::from_secs(60));
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body["queueLag"]["events"], 3);
+
+        handle.update(|status| status.last_loop_at = Some(0));
+        assert!(!handle.get().is_healthy(Duration::from_secs(60)));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_status_as_prometheus() {
+        let status = WatchdogStatus {
+            loops: 2,
+            republished: 5,
+            queue_lag: BTreeMap::from([("events".to_owned(), 7)]),
+            mongo_connected: true,
+            ..Default::default()
+        };
+        let metrics = status.to_prometheus();
+        assert!(metrics.contains("# TYPE watchdog_republished_total counter\n"));
+        assert!(metrics.contains("watchdog_republished_total 5\n"));
+        assert!(metrics.contains("watchdog_queue_lag{queue=\"events\"} 7\n"));
+        assert!(metrics.contains("watchdog_mongo_up 1\n"));
+        assert!(metrics.contains("watchdog_bus_up 0\n"));
+    }
+}
@@ -0,0 +1,112 @@
+use super::context_retention::EXPIRES_AT_FIELD;
+use crate::{ContextSummary, IntegrationOSError, TokenBucket};
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::{options::AggregateOptions, Collection};
+use tracing::info;
+
+/// Deletes intermediate contexts superseded by a later one of the same chain step.
+///
+/// Every stage transition writes a new context, but recovery only ever reads the latest
+/// context of an event key and the latest of each of its pipelines and extractors. The
+/// earlier contexts of each of those steps are redundant once they are older than
+/// `min_age`, and removing them never changes what recovery sees.
+#[derive(Debug, Clone)]
+pub struct ContextCompaction {
+    collection: Collection<Document>,
+    min_age: Duration,
+    batch_size: i64,
+}
+
+impl ContextCompaction {
+    pub fn new(collection: Collection<Document>, min_age: u64, batch_size: u64) -> Self {
+        Self {
+            collection,
+            min_age: Duration::seconds(min_age as i64),
+            batch_size: batch_size as i64,
+        }
+    }
+
+    /// Groups the contexts older than `min_age` by chain step, listing the ids of each
+    /// group newest first and keeping only the groups with superseded contexts
+    pub fn pipeline(&self, now: DateTime<Utc>) -> Vec<Document> {
+        vec![
+            doc! {
+                "$match": {
+                    "timestamp": { "$lt": (now - self.min_age).timestamp_millis() },
+                    "type": { "$ne": ContextSummary::TYPE },
+                    EXPIRES_AT_FIELD: { "$exists": false },
+                },
+            },
+            doc! { "$sort": { "timestamp": -1 } },
+            doc! {
+                "$group": {
+                    "_id": {
+                        "eventKey": "$eventKey",
+                        "type": "$type",
+                        "pipelineKey": "$pipelineKey",
+                        "extractorKey": "$extractorKey",
+                    },
+                    "ids": { "$push": "$_id" },
+                },
+            },
+            doc! { "$match": { "ids.1": { "$exists": true } } },
+            doc! { "$limit": self.batch_size },
+        ]
+    }
+
+    /// Ids of a group that are superseded by its latest context
+    fn superseded(group: &Document) -> Vec<Bson> {
+        group
+            .get_array("ids")
+            .map(|ids| ids.iter().skip(1).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Compacts up to one batch of chain steps, taking one token of `budget` per database
+    /// operation. Returns how many contexts were deleted.
+    pub async fn run_once(&self, budget: &mut TokenBucket) -> Result<u64, IntegrationOSError> {
+        budget.acquire(1).await;
+        let options = AggregateOptions::builder().allow_disk_use(true).build();
+        let mut groups = self
+            .collection
+            .aggregate(self.pipeline(Utc::now()), options)
+            .await?;
+
+        let mut deleted = 0;
+        while let Some(group) = groups.try_next().await? {
+            let ids = Self::superseded(&group);
+            if ids.is_empty() {
+                continue;
+            }
+            budget.acquire(1).await;
+            let result = self
+                .collection
+                .delete_many(doc! { "_id": { "$in": ids } }, None)
+                .await?;
+            deleted += result.deleted_count;
+        }
+
+        if deleted > 0 {
+            info!("Compacted {deleted} superseded contexts");
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_superseded_keeps_latest() {
+        let group = doc! { "_id": { "eventKey": "evt" }, "ids": ["c", "b", "a"] };
+        assert_eq!(
+            ContextCompaction::superseded(&group),
+            vec![Bson::from("b"), Bson::from("a")]
+        );
+        assert!(ContextCompaction::superseded(&doc! { "ids": ["a"] }).is_empty());
+        assert!(ContextCompaction::superseded(&doc! {}).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{ContextSummary, IntegrationOSError, RootContext};
+use bson::{doc, Document};
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use mongodb::{
+    options::{FindOneOptions, FindOptions, IndexOptions, UpdateOptions},
+    Collection, IndexModel,
+};
+use tracing::{error, info};
+
+pub const EXPIRES_AT_FIELD: &str = "expiresAt";
+
+/// Summarizes completed event chains and marks their raw contexts for TTL deletion so the
+/// context collection, and the watchdog aggregation over it, stay bounded.
+#[derive(Debug, Clone)]
+pub struct ContextRetention {
+    collection: Collection<Document>,
+    window: Duration,
+    retention: Duration,
+    batch_size: i64,
+}
+
+impl ContextRetention {
+    pub fn new(
+        collection: Collection<Document>,
+        window: u64,
+        retention: u64,
+        batch_size: u64,
+    ) -> Self {
+        Self {
+            collection,
+            window: Duration::seconds(window as i64),
+            retention: Duration::seconds(retention as i64),
+            batch_size: batch_size as i64,
+        }
+    }
+
+    /// Filter restricting a scan to the contexts written in the recent window
+    pub fn window_filter(window: u64, now: DateTime<Utc>) -> Option<Document> {
+        (window > 0).then(|| {
+            let since = now - Duration::seconds(window as i64);
+            doc! { "timestamp": { "$gte": since.timestamp_millis() } }
+        })
+    }
+
+    fn completed_roots_filter(&self, now: DateTime<Utc>) -> Document {
+        let mut filter = doc! {
+            "type": "root",
+            EXPIRES_AT_FIELD: { "$exists": false },
+            "$or": [
+                { "stage": "Finished" },
+                { "status.Dropped": { "$exists": true } },
+            ],
+        };
+        if self.window > Duration::zero() {
+            filter.insert(
+                "timestamp",
+                doc! { "$gte": (now - self.window).timestamp_millis() },
+            );
+        }
+        filter
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), IntegrationOSError> {
+        let ttl = IndexModel::builder()
+            .keys(doc! { EXPIRES_AT_FIELD: 1 })
+            .options(
+                IndexOptions::builder()
+                    .expire_after(std::time::Duration::from_secs(0))
+                    .build(),
+            )
+            .build();
+        let scan = IndexModel::builder()
+            .keys(doc! { "timestamp": -1, "eventKey": 1 })
+            .build();
+        self.collection.create_indexes([ttl, scan], None).await?;
+        Ok(())
+    }
+
+    /// Summarizes up to one batch of completed chains, returning how many were summarized
+    pub async fn run_once(&self) -> Result<u64, IntegrationOSError> {
+        let now = Utc::now();
+        let options = FindOptions::builder().limit(self.batch_size).build();
+        let mut roots = self
+            .collection
+            .clone_with_type::<RootContext>()
+            .find(self.completed_roots_filter(now), options)
+            .await?;
+
+        let expires_at = bson::DateTime::from_millis((now + self.retention).timestamp_millis());
+        let mut count = 0;
+        while let Some(root) = roots.try_next().await? {
+            let event_key = root.event_key.to_string();
+            let raw_filter = doc! {
+                "eventKey": &event_key,
+                "type": { "$ne": ContextSummary::TYPE },
+            };
+
+            let context_count = self
+                .collection
+                .count_documents(raw_filter.clone(), None)
+                .await?;
+            let first = self
+                .collection
+                .find_one(
+                    raw_filter.clone(),
+                    FindOneOptions::builder()
+                        .sort(doc! { "timestamp": 1 })
+                        .build(),
+                )
+                .await?;
+            let started_at = first
+                .and_then(|d| d.get_i64("timestamp").ok())
+                .and_then(DateTime::from_timestamp_millis)
+                .unwrap_or(root.timestamp);
+
+            let summary = ContextSummary::new(&root, started_at, context_count);
+            let summary = match bson::to_document(&summary) {
+                Ok(summary) => summary,
+                Err(e) => {
+                    error!("Could not serialize context summary for {event_key}: {e}");
+                    continue;
+                }
+            };
+            self.collection
+                .update_one(
+                    doc! { "_id": ContextSummary::id_for(&root.event_key) },
+                    doc! { "$setOnInsert": summary },
+                    UpdateOptions::builder().upsert(true).build(),
+                )
+                .await?;
+
+            self.collection
+                .update_many(
+                    raw_filter,
+                    doc! { "$set": { EXPIRES_AT_FIELD: expires_at } },
+                    None,
+                )
+                .await?;
+            count += 1;
+        }
+
+        if count > 0 {
+            info!("Summarized {count} completed event chains");
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_window_filter() {
+        let now = Utc.timestamp_opt(100_000, 0).single().unwrap();
+        assert_eq!(ContextRetention::window_filter(0, now), None);
+        assert_eq!(
+            ContextRetention::window_filter(10, now),
+            Some(doc! { "timestamp": { "$gte": 99_990_000_i64 } })
+        );
+    }
+}
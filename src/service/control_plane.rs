@@ -0,0 +1,216 @@
+use crate::{
+    prelude::queue::{
+        control::{AckStatus, AckSummary, ControlAck, ControlCommand, ControlEnvelope},
+        message::{QueueConsumer, QueueMessage},
+    },
+    Id, IntegrationOSError, InternalError, RedisCache,
+};
+use chrono::{Duration, Utc};
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use tracing::{error, warn};
+
+const WORKERS_KEY: &str = "control::workers";
+
+fn inbox_key(worker_id: &str) -> String {
+    format!("control::inbox::{worker_id}")
+}
+
+fn acks_key(command_id: &Id) -> String {
+    format!("control::acks::{command_id}")
+}
+
+fn recipients_key(command_id: &Id) -> String {
+    format!("control::recipients::{command_id}")
+}
+
+fn redis_error(e: redis::RedisError) -> IntegrationOSError {
+    InternalError::io_err(&e.to_string(), Some("control_plane"))
+}
+
+/// Broadcasts operator commands (pause a connection, invalidate a cache key, reload
+/// definitions) to every live worker through a per-worker inbox and tracks their
+/// acknowledgments.
+///
+/// Receiving blocks the underlying connection, so workers should receive on a dedicated
+/// [`RedisCache`].
+#[derive(Clone)]
+pub struct ControlPlane {
+    cache: RedisCache,
+    worker_id: String,
+    liveness: Duration,
+    ack_ttl: usize,
+}
+
+impl ControlPlane {
+    pub fn new(cache: RedisCache, worker_id: impl Into<String>) -> Self {
+        Self {
+            cache,
+            worker_id: worker_id.into(),
+            liveness: Duration::seconds(60),
+            ack_ttl: 86_400,
+        }
+    }
+
+    /// How long a worker is considered live after its last heartbeat
+    pub fn with_liveness(mut self, liveness: Duration) -> Self {
+        self.liveness = liveness;
+        self
+    }
+
+    pub fn with_ack_ttl(mut self, seconds: usize) -> Self {
+        self.ack_ttl = seconds;
+        self
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    pub async fn heartbeat(&self) -> Result<(), IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        conn.zadd(WORKERS_KEY, &self.worker_id, Utc::now().timestamp_millis())
+            .await
+            .map_err(redis_error)
+    }
+
+    pub async fn live_workers(&self) -> Result<Vec<String>, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let since = (Utc::now() - self.liveness).timestamp_millis();
+        let _: i64 = conn
+            .zrembyscore(WORKERS_KEY, "-inf", since - 1)
+            .await
+            .map_err(redis_error)?;
+        conn.zrangebyscore(WORKERS_KEY, since, "+inf")
+            .await
+            .map_err(redis_error)
+    }
+
+    pub async fn broadcast(
+        &self,
+        command: ControlCommand,
+        issued_by: Option<String>,
+    ) -> Result<ControlEnvelope, IntegrationOSError> {
+        let envelope = ControlEnvelope::new(command, issued_by);
+        let payload = QueueMessage::ControlCommand(envelope.clone()).to_vec()?;
+        let workers = self.live_workers().await?;
+        if workers.is_empty() {
+            warn!("No live workers to receive control command {}", envelope.id);
+            return Ok(envelope);
+        }
+
+        let mut conn = self.cache.clone();
+        let recipients = recipients_key(&envelope.id);
+        let mut pipe = redis::pipe();
+        for worker in &workers {
+            pipe.lpush(inbox_key(worker), payload.as_slice()).ignore();
+        }
+        pipe.sadd(&recipients, &workers)
+            .ignore()
+            .expire(&recipients, self.ack_ttl)
+            .ignore();
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(redis_error)?;
+
+        Ok(envelope)
+    }
+
+    /// Waits up to `timeout` seconds for the next command sent to this worker
+    pub async fn receive(
+        &self,
+        timeout: u64,
+    ) -> Result<Option<ControlEnvelope>, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let message: Option<(String, Vec<u8>)> = conn
+            .brpop(inbox_key(&self.worker_id), timeout as f64)
+            .await
+            .map_err(redis_error)?;
+        let Some((_, payload)) = message else {
+            return Ok(None);
+        };
+        match QueueMessage::from_slice(&payload)? {
+            QueueMessage::ControlCommand(envelope) => Ok(Some(envelope)),
+            other => Err(InternalError::invalid_argument(
+                &format!("Unexpected {} message in control inbox", other.kind()),
+                Some("control_plane"),
+            )),
+        }
+    }
+
+    pub async fn acknowledge(
+        &self,
+        envelope: &ControlEnvelope,
+        status: AckStatus,
+    ) -> Result<ControlAck, IntegrationOSError> {
+        let ack = ControlAck {
+            command_id: envelope.id,
+            worker_id: self.worker_id.clone(),
+            status,
+            acked_at: Utc::now(),
+        };
+        let payload = serde_json::to_string(&ack)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("control_plane")))?;
+
+        let mut conn = self.cache.clone();
+        let key = acks_key(&envelope.id);
+        redis::pipe()
+            .hset(&key, &self.worker_id, payload)
+            .ignore()
+            .expire(&key, self.ack_ttl)
+            .ignore()
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(redis_error)?;
+        Ok(ack)
+    }
+
+    /// Receives the next command, hands it to the consumer and acknowledges the outcome
+    pub async fn process_next<C: QueueConsumer + ?Sized>(
+        &self,
+        consumer: &C,
+        timeout: u64,
+    ) -> Result<Option<ControlAck>, IntegrationOSError> {
+        let Some(envelope) = self.receive(timeout).await? else {
+            return Ok(None);
+        };
+        let status = match consumer.on_control_command(envelope.clone()).await {
+            Ok(()) => AckStatus::Applied,
+            Err(e) => {
+                error!("Could not apply control command {}: {e}", envelope.id);
+                AckStatus::Failed {
+                    message: e.to_string(),
+                }
+            }
+        };
+        self.acknowledge(&envelope, status).await.map(Some)
+    }
+
+    pub async fn acks(&self, command_id: &Id) -> Result<Vec<ControlAck>, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let acks: HashMap<String, String> = conn
+            .hgetall(acks_key(command_id))
+            .await
+            .map_err(redis_error)?;
+        acks.values()
+            .map(|ack| {
+                serde_json::from_str(ack).map_err(|e| {
+                    InternalError::deserialize_error(&e.to_string(), Some("control_plane"))
+                })
+            })
+            .collect()
+    }
+
+    pub async fn summary(&self, command_id: &Id) -> Result<AckSummary, IntegrationOSError> {
+        let mut conn = self.cache.clone();
+        let recipients: Vec<String> = conn
+            .smembers(recipients_key(command_id))
+            .await
+            .map_err(redis_error)?;
+        let acks = self.acks(command_id).await?;
+        Ok(AckSummary::new(
+            recipients.iter().map(String::as_str),
+            &acks,
+        ))
+    }
+}
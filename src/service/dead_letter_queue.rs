@@ -0,0 +1,189 @@
+use crate::{
+    prelude::event::{dead_letter::DeadLetter, event_with_context::EventWithContext},
+    ApplicationError, IntegrationOSError, MessageBusExt, MongoStore,
+};
+use bson::{doc, Document};
+use chrono::Utc;
+use tracing::{info, warn};
+
+/// Parks events that failed `max_failures` times instead of retrying them forever, and
+/// puts them back onto the event queue on demand
+pub struct DeadLetterQueue<B> {
+    store: MongoStore<DeadLetter>,
+    bus: B,
+    queue_name: String,
+    max_failures: u32,
+}
+
+impl<B: MessageBusExt + Sync> DeadLetterQueue<B> {
+    pub fn new(
+        store: MongoStore<DeadLetter>,
+        bus: B,
+        queue_name: impl Into<String>,
+        max_failures: u32,
+    ) -> Self {
+        Self {
+            store,
+            bus,
+            queue_name: queue_name.into(),
+            max_failures: max_failures.max(1),
+        }
+    }
+
+    pub fn is_exhausted(&self, failure_count: u32) -> bool {
+        failure_count >= self.max_failures
+    }
+
+    /// Moves the payload to the dead letters once it failed `max_failures` times,
+    /// returning the entry. Below that the caller retries it as usual.
+    pub async fn record_failure(
+        &self,
+        payload: &EventWithContext,
+        failure_count: u32,
+        error: &str,
+    ) -> Result<Option<DeadLetter>, IntegrationOSError> {
+        if !self.is_exhausted(failure_count) {
+            return Ok(None);
+        }
+        let letter = DeadLetter::new(payload.clone(), failure_count, error.to_owned());
+        self.store.create_one(&letter).await?;
+        warn!(
+            "Moved event {} to the dead letters after {failure_count} failures: {error}",
+            letter.event_key
+        );
+        Ok(Some(letter))
+    }
+
+    /// Dead letters of an ownership, newest first, leaving out replayed ones unless asked to
+    pub async fn list(
+        &self,
+        ownership_id: Option<&str>,
+        include_replayed: bool,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<DeadLetter>, IntegrationOSError> {
+        self.store
+            .get_many(
+                Some(list_filter(ownership_id, include_replayed)),
+                None,
+                None,
+                limit,
+                skip,
+            )
+            .await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<DeadLetter>, IntegrationOSError> {
+        self.store.get_one_by_id(id).await
+    }
+
+    /// Pushes the original payload back onto the event queue and marks the entry as
+    /// replayed. An entry is only replayed once.
+    pub async fn replay(&self, id: &str) -> Result<DeadLetter, IntegrationOSError> {
+        let mut letter = self.get(id).await?.ok_or_else(|| {
+            ApplicationError::not_found(&format!("Dead letter {id}"), Some("dead_letter"))
+        })?;
+        if letter.is_replayed() {
+            return Err(ApplicationError::conflict(
+                &format!("Dead letter {id} was already replayed"),
+                Some("dead_letter"),
+            ));
+        }
+
+        letter.payload.publish(&self.bus, &self.queue_name).await?;
+        letter.mark_replayed();
+        let update = doc! {
+            "$set": {
+                "replayedAt": letter.replayed_at,
+                "updatedAt": Utc::now().timestamp_millis(),
+            }
+        };
+        self.store.update_one(id, update).await?;
+        info!("Replayed dead letter {id} of event {}", letter.event_key);
+        Ok(letter)
+    }
+}
+
+fn list_filter(ownership_id: Option<&str>, include_replayed: bool) -> Document {
+    let mut filter = doc! {};
+    if let Some(ownership_id) = ownership_id {
+        filter.insert("ownership.buildableId", ownership_id);
+    }
+    if !include_replayed {
+        filter.insert("replayedAt", doc! { "$exists": false });
+    }
+    filter
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        prelude::access_key::{
+            access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+            encrypted_access_key::EncryptedAccessKey, event_type::EventType, AccessKey,
+        },
+        prelude::configuration::environment::Environment,
+        CacheExt, Event, InMemoryCache, RootContext,
+    };
+    use http::HeaderMap;
+
+    fn payload() -> EventWithContext {
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "acme".to_owned(),
+                event_type: "webhook".to_owned(),
+                group: "dlq".to_owned(),
+                ..Default::default()
+            },
+        };
+        let event = Event::new(
+            &access_key,
+            &EncryptedAccessKey::parse("id_test_1_foo").unwrap(),
+            "order.created",
+            HeaderMap::new(),
+            "{}".to_owned(),
+        );
+        let context = RootContext::new(event.id);
+        EventWithContext::new(event, context)
+    }
+
+    #[test]
+    fn test_dead_letter_keeps_original_payload() {
+        let payload = payload();
+        let letter = DeadLetter::new(payload.clone(), 5, "timeout".to_owned());
+        assert_eq!(letter.event_key, payload.event.id);
+        assert_eq!(&*letter.ownership.id, "acme");
+        assert!(!letter.is_replayed());
+
+        let value = serde_json::to_value(&letter).unwrap();
+        assert_eq!(value["failureCount"], 5);
+        assert!(value.get("replayedAt").is_none());
+        let letter: DeadLetter = serde_json::from_value(value).unwrap();
+        assert_eq!(letter.payload.event, payload.event);
+    }
+
+    #[test]
+    fn test_list_filter() {
+        assert_eq!(
+            list_filter(Some("acme"), false),
+            doc! { "ownership.buildableId": "acme", "replayedAt": { "$exists": false } }
+        );
+        assert_eq!(list_filter(None, true), doc! {});
+    }
+
+    #[tokio::test]
+    async fn test_replay_publishes_payload() {
+        let cache = InMemoryCache::new();
+        let payload = payload();
+        payload.publish(&cache, "events").await.unwrap();
+
+        let serialized = serde_json::to_vec(&payload).unwrap();
+        assert_eq!(cache.list_len("events").await.unwrap(), 1);
+        assert_eq!(
+            cache.list_position("events", &serialized).await.unwrap(),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{
+    prelude::connection::connection_model_definition::{ConnectionModelDefinition, CrudAction},
+    ChangeEvent, ChangeOperation, InMemoryResumeTokens, IntegrationOSError, MongoStore, WatchExt,
+};
+use futures::StreamExt;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DefinitionKey {
+    pub platform: String,
+    /// Lowercased, as common model names are matched case insensitively
+    pub common_model: String,
+    pub action: CrudAction,
+}
+
+impl DefinitionKey {
+    pub fn new(platform: &str, common_model: &str, action: CrudAction) -> Self {
+        Self {
+            platform: platform.to_owned(),
+            common_model: common_model.to_lowercase(),
+            action,
+        }
+    }
+
+    /// Key of a unified definition, `None` for passthrough only ones
+    fn of(definition: &ConnectionModelDefinition) -> Option<Self> {
+        definition.mapping.as_ref().map(|mapping| {
+            Self::new(
+                &definition.connection_platform,
+                &mapping.common_model_name,
+                definition.action_name.clone(),
+            )
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Definitions {
+    by_key: HashMap<DefinitionKey, Arc<ConnectionModelDefinition>>,
+    keys: HashMap<String, DefinitionKey>,
+    loaded_at: Option<Instant>,
+}
+
+impl Definitions {
+    fn insert(&mut self, definition: ConnectionModelDefinition) {
+        let id = definition.id.to_string();
+        self.remove(&id);
+        if let Some(key) = DefinitionKey::of(&definition) {
+            self.keys.insert(id, key.clone());
+            self.by_key.insert(key, Arc::new(definition));
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(key) = self.keys.remove(id) {
+            self.by_key.remove(&key);
+        }
+    }
+}
+
+/// Unified model definitions by (platform, common model, action), loaded all at once so
+/// the request path doesn't query Mongo for data that rarely changes.
+///
+/// The whole set is reloaded once older than the TTL, and
+/// [`DefinitionCache::start_invalidation`] keeps it current in between from the change
+/// stream of the collection.
+#[derive(Debug, Clone)]
+pub struct DefinitionCache {
+    store: MongoStore<ConnectionModelDefinition>,
+    ttl: Duration,
+    definitions: Arc<RwLock<Definitions>>,
+}
+
+impl DefinitionCache {
+    pub fn new(store: MongoStore<ConnectionModelDefinition>, ttl: Duration) -> Self {
+        Self {
+            store,
+            ttl,
+            definitions: Default::default(),
+        }
+    }
+
+    /// Loads every definition, returning how many unified ones are cached
+    pub async fn preload(&self) -> Result<usize, IntegrationOSError> {
+        let mut definitions = Definitions::default();
+        for definition in self.store.get_all().await? {
+            definitions.insert(definition);
+        }
+        definitions.loaded_at = Some(Instant::now());
+        let count = definitions.by_key.len();
+        *self
+            .definitions
+            .write()
+            .expect("definition cache lock poisoned") = definitions;
+        Ok(count)
+    }
+
+    pub async fn get(
+        &self,
+        platform: &str,
+        common_model: &str,
+        action: CrudAction,
+    ) -> Result<Option<Arc<ConnectionModelDefinition>>, IntegrationOSError> {
+        let stale = self
+            .definitions
+            .read()
+            .expect("definition cache lock poisoned")
+            .loaded_at
+            .filter(|at| at.elapsed() < self.ttl)
+            .is_none();
+        if stale {
+            self.preload().await?;
+        }
+        Ok(self
+            .definitions
+            .read()
+            .expect("definition cache lock poisoned")
+            .by_key
+            .get(&DefinitionKey::new(platform, common_model, action))
+            .cloned())
+    }
+
+    /// Makes the next lookup reload every definition
+    pub fn invalidate(&self) {
+        self.definitions
+            .write()
+            .expect("definition cache lock poisoned")
+            .loaded_at = None;
+    }
+
+    fn apply(&self, event: ChangeEvent<ConnectionModelDefinition>) {
+        let mut definitions = self
+            .definitions
+            .write()
+            .expect("definition cache lock poisoned");
+        let id = event
+            .document_key
+            .as_ref()
+            .and_then(|key| key.get_str("_id").ok());
+        match (event.operation, event.document, id) {
+            (
+                ChangeOperation::Insert | ChangeOperation::Update | ChangeOperation::Replace,
+                Some(definition),
+                _,
+            ) => definitions.insert(definition),
+            (ChangeOperation::Delete | ChangeOperation::Update, _, Some(id)) => {
+                definitions.remove(id)
+            }
+            // Drops, renames and changes that can't be attributed to a definition
+            _ => definitions.loaded_at = None,
+        }
+    }
+
+    /// Applies the changes of the collection until the change stream fails, invalidating
+    /// the cache when it does so the definitions are reloaded on the next lookup
+    pub fn start_invalidation(&self) -> JoinHandle<Result<(), IntegrationOSError>> {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            let result = cache.watch_changes().await;
+            cache.invalidate();
+            if let Err(e) = &result {
+                warn!("Definition cache stopped following changes: {e}");
+            }
+            result
+        })
+    }
+
+    async fn watch_changes(&self) -> Result<(), IntegrationOSError> {
+        let mut changes = self
+            .store
+            .watch(
+                "definition_cache",
+                vec![],
+                Arc::new(InMemoryResumeTokens::default()),
+            )
+            .await?;
+        info!("Following connection model definition changes");
+        while let Some(change) = changes.next().await {
+            self.apply(change?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api_model_config::{ApiModelConfig, AuthMethod, SamplesInput, SchemasInput},
+        connection_model_definition::{CrudMapping, PlatformInfo, TestConnection},
+        id::{prefix::IdPrefix, Id},
+    };
+
+    fn definition(common_model: &str, action: CrudAction) -> ConnectionModelDefinition {
+        ConnectionModelDefinition {
+            id: Id::now(IdPrefix::ConnectionModelDefinition),
+            connection_platform: "shopify".to_owned(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            platform_version: "2024-01".to_owned(),
+            key: "api::shopify::2024-01::orders".to_owned(),
+            title: "List Orders".to_owned(),
+            name: "List Orders".to_owned(),
+            model_name: "Order".to_owned(),
+            action: http::Method::GET,
+            action_name: action.clone(),
+            platform_info: PlatformInfo::Api(ApiModelConfig {
+                base_url: "https://shop.myshopify.com".to_owned(),
+                path: "/orders.json".to_owned(),
+                auth_method: AuthMethod::BearerToken {
+                    value: "{{SHOPIFY_TOKEN}}".to_owned(),
+                },
+                headers: None,
+                query_params: None,
+                content: None,
+                schemas: SchemasInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                samples: SamplesInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                responses: vec![],
+                paths: None,
+                pagination: None,
+            }),
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            is_default_crud_mapping: None,
+            mapping: Some(CrudMapping {
+                action,
+                common_model_name: common_model.to_owned(),
+                from_common_model: None,
+                to_common_model: None,
+            }),
+            record_metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_definitions_follow_changes_by_id() {
+        let mut definitions = Definitions::default();
+        let mut orders = definition("Orders", CrudAction::GetMany);
+        definitions.insert(orders.clone());
+        let key = DefinitionKey::new("shopify", "orders", CrudAction::GetMany);
+        assert_eq!(definitions.by_key[&key].id, orders.id);
+
+        orders.mapping.as_mut().unwrap().common_model_name = "Customers".to_owned();
+        definitions.insert(orders.clone());
+        assert!(!definitions.by_key.contains_key(&key));
+        assert!(definitions.by_key.contains_key(&DefinitionKey::new(
+            "shopify",
+            "customers",
+            CrudAction::GetMany
+        )));
+
+        definitions.remove(&orders.id.to_string());
+        assert!(definitions.by_key.is_empty());
+
+        let mut passthrough = definition("Orders", CrudAction::Create);
+        passthrough.mapping = None;
+        definitions.insert(passthrough);
+        assert!(definitions.by_key.is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    prelude::retention::{RetentionPolicy, RetentionSetting},
+    IntegrationOSError, MongoStore,
+};
+use bson::{doc, Document};
+use chrono::{DateTime, Utc};
+use mongodb::Collection;
+use tracing::info;
+
+/// Deletes events past the retention window of their ownership, falling back to the
+/// default window for ownerships without a [`RetentionSetting`]
+#[derive(Debug, Clone)]
+pub struct EventRetention {
+    events: Collection<Document>,
+    settings: MongoStore<RetentionSetting>,
+    default_days: u32,
+}
+
+impl EventRetention {
+    pub fn new(
+        events: Collection<Document>,
+        settings: MongoStore<RetentionSetting>,
+        default_days: u32,
+    ) -> Self {
+        Self {
+            events,
+            settings,
+            default_days,
+        }
+    }
+
+    pub async fn policy(&self) -> Result<RetentionPolicy, IntegrationOSError> {
+        let settings = self
+            .settings
+            .get_many(Some(doc! { "deleted": false }), None, None, None, None)
+            .await?;
+        Ok(RetentionPolicy::new(self.default_days, &settings))
+    }
+
+    /// Filters matching the expired events, one per overridden ownership plus one for
+    /// everyone else
+    pub fn expired_filters(policy: &RetentionPolicy, now: DateTime<Utc>) -> Vec<Document> {
+        let cutoff = |days| RetentionPolicy::cutoff(days, now).timestamp_millis();
+        let mut overridden: Vec<&String> = policy.overrides.keys().collect();
+        overridden.sort();
+
+        let mut filters: Vec<Document> = overridden
+            .iter()
+            .map(|id| {
+                doc! {
+                    "ownership.buildableId": id.as_str(),
+                    "arrivedAt": { "$lt": cutoff(policy.overrides[*id]) },
+                }
+            })
+            .collect();
+        filters.push(doc! {
+            "ownership.buildableId": { "$nin": overridden },
+            "arrivedAt": { "$lt": cutoff(policy.default_days) },
+        });
+        filters
+    }
+
+    pub async fn run_once(&self) -> Result<u64, IntegrationOSError> {
+        let policy = self.policy().await?;
+        let mut deleted = 0;
+        for filter in Self::expired_filters(&policy, Utc::now()) {
+            deleted += self.events.delete_many(filter, None).await?.deleted_count;
+        }
+        if deleted > 0 {
+            info!("Deleted {deleted} events past their retention window");
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_expired_filters() {
+        let policy = RetentionPolicy {
+            default_days: 10,
+            overrides: HashMap::from([("a".to_owned(), 1)]),
+        };
+        let now = Utc.timestamp_millis_opt(864_000_000).unwrap();
+        assert_eq!(
+            EventRetention::expired_filters(&policy, now),
+            vec![
+                doc! { "ownership.buildableId": "a", "arrivedAt": { "$lt": 777_600_000_i64 } },
+                doc! {
+                    "ownership.buildableId": { "$nin": ["a"] },
+                    "arrivedAt": { "$lt": 0_i64 },
+                },
+            ]
+        );
+    }
+}
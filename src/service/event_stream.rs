@@ -0,0 +1,202 @@
+use crate::{
+    prelude::configuration::environment::Environment, Event, IntegrationOSError, MongoStore,
+    ResumeTokenStore, WatchExt,
+};
+use bson::doc;
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{info, warn};
+
+pub const EVENT_STREAM: &str = "event_stream";
+
+/// Which events a subscriber sees. Subscribers only ever see events of their own
+/// ownership, optionally narrowed to an environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventFilter {
+    pub buildable_id: Arc<str>,
+    pub environment: Option<Environment>,
+}
+
+impl EventFilter {
+    pub fn new(buildable_id: impl Into<Arc<str>>) -> Self {
+        Self {
+            buildable_id: buildable_id.into(),
+            environment: None,
+        }
+    }
+
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        event.ownership.id == self.buildable_id
+            && (self.environment.is_none() || self.environment == Some(event.environment))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamMessage {
+    Event(Arc<Event>),
+    /// The subscriber fell behind and this many events, of any ownership, were skipped
+    Lagged(u64),
+}
+
+/// Fans new events out to live subscribers, e.g. WebSocket or SSE connections.
+///
+/// The hub never waits on subscribers: every subscriber gets a buffer of `capacity`
+/// events and one that falls further behind skips the oldest ones, receiving a
+/// [`StreamMessage::Lagged`] so it can tell its client some events were missed.
+#[derive(Debug, Clone)]
+pub struct EventHub {
+    sender: broadcast::Sender<Arc<Event>>,
+}
+
+impl EventHub {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Publishes an event, e.g. one consumed from a queue, returning how many subscribers
+    /// were listening
+    pub fn publish(&self, event: impl Into<Arc<Event>>) -> usize {
+        self.sender.send(event.into()).unwrap_or_default()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    pub fn subscribe(&self, filter: EventFilter) -> EventSubscription {
+        EventSubscription {
+            receiver: self.sender.subscribe(),
+            filter,
+        }
+    }
+
+    /// Publishes every event inserted in `events` until the change stream ends, resuming
+    /// after the last event published by a previous run
+    pub async fn tail(
+        &self,
+        events: &MongoStore<Event>,
+        tokens: Arc<dyn ResumeTokenStore>,
+    ) -> Result<(), IntegrationOSError> {
+        let pipeline = vec![doc! { "$match": { "operationType": "insert" } }];
+        let mut changes = events.watch(EVENT_STREAM, pipeline, tokens).await?;
+        info!("Tailing new events");
+        while let Some(change) = changes.next().await {
+            if let Some(event) = change?.document {
+                self.publish(event);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct EventSubscription {
+    receiver: broadcast::Receiver<Arc<Event>>,
+    filter: EventFilter,
+}
+
+impl EventSubscription {
+    /// Next matching event, or `None` once the hub is dropped
+    pub async fn recv(&mut self) -> Option<StreamMessage> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event) => {
+                    return Some(StreamMessage::Event(event))
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Event subscriber of {} lagged, skipped {skipped} events",
+                        self.filter.buildable_id
+                    );
+                    return Some(StreamMessage::Lagged(skipped));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> BoxStream<'static, StreamMessage> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            let message = subscription.recv().await?;
+            Some((message, subscription))
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::access_key::{
+        access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix, event_type::EventType,
+        AccessKey,
+    };
+    use crate::prelude::encrypted_access_key::EncryptedAccessKey;
+    use http::HeaderMap;
+
+    fn event(buildable_id: &str, environment: Environment) -> Event {
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(environment, EventType::Id, 1),
+            data: AccessKeyData {
+                id: buildable_id.to_owned(),
+                event_type: "webhook".to_owned(),
+                group: "stream".to_owned(),
+                event_path: "event.received".to_owned(),
+                ..Default::default()
+            },
+        };
+        Event::new(
+            &access_key,
+            &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+            "event.received",
+            HeaderMap::new(),
+            "{}".to_owned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_only_see_their_events() {
+        let hub = EventHub::new(16);
+        let mut acme = hub.subscribe(EventFilter::new("acme").with_environment(Environment::Live));
+        let mut globex = hub.subscribe(EventFilter::new("globex"));
+
+        hub.publish(event("acme", Environment::Test));
+        hub.publish(event("globex", Environment::Test));
+        let live = event("acme", Environment::Live);
+        assert_eq!(hub.publish(live.clone()), 2);
+
+        assert_eq!(acme.recv().await, Some(StreamMessage::Event(live.into())));
+        match globex.recv().await {
+            Some(StreamMessage::Event(event)) => assert_eq!(&*event.ownership.id, "globex"),
+            other => panic!("unexpected message {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscribers_lag_instead_of_blocking() {
+        let hub = EventHub::new(2);
+        let mut subscription = hub.subscribe(EventFilter::new("acme"));
+        for _ in 0..5 {
+            hub.publish(event("acme", Environment::Test));
+        }
+
+        assert_eq!(subscription.recv().await, Some(StreamMessage::Lagged(3)));
+        assert!(matches!(
+            subscription.recv().await,
+            Some(StreamMessage::Event(_))
+        ));
+
+        drop(hub);
+        let mut stream = subscription.into_stream();
+        assert!(matches!(stream.next().await, Some(StreamMessage::Event(_))));
+        assert_eq!(stream.next().await, None);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{
+    prelude::background::{BackgroundJob, BackgroundJobStatus, JobProgress, JobState},
+    ApplicationError, Id, IntegrationOSError, InternalError, MongoStore,
+};
+use async_trait::async_trait;
+use bson::{doc, Bson, Document};
+use chrono::Utc;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+fn to_bson<T: Serialize>(value: &T) -> Result<Bson, IntegrationOSError> {
+    bson::to_bson(value)
+        .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("job_queue")))
+}
+
+/// Store backed queue of [`BackgroundJob`]s. Workers claim jobs under a lease and keep it
+/// alive with heartbeats; jobs whose lease expires are picked up again by other workers.
+#[derive(Debug, Clone)]
+pub struct JobQueue<S: JobState> {
+    store: MongoStore<BackgroundJob<S>>,
+    lease_millis: i64,
+}
+
+impl<S: JobState> JobQueue<S> {
+    pub fn new(store: MongoStore<BackgroundJob<S>>, lease_secs: u64) -> Self {
+        Self {
+            store,
+            lease_millis: lease_secs as i64 * 1000,
+        }
+    }
+
+    pub async fn enqueue(&self, job: BackgroundJob<S>) -> Result<Id, IntegrationOSError> {
+        self.store.create_one(&job).await?;
+        Ok(job.id)
+    }
+
+    pub async fn get(&self, id: &Id) -> Result<Option<BackgroundJob<S>>, IntegrationOSError> {
+        self.store.get_one_by_id(&id.to_string()).await
+    }
+
+    async fn find_and_update(
+        &self,
+        filter: Document,
+        update: Document,
+    ) -> Result<Option<BackgroundJob<S>>, IntegrationOSError> {
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "createdAt": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self
+            .store
+            .collection
+            .find_one_and_update(filter, update, options)
+            .await?)
+    }
+
+    /// Claims the oldest queued job, or a running one whose worker stopped heartbeating
+    pub async fn claim(
+        &self,
+        worker: &str,
+    ) -> Result<Option<BackgroundJob<S>>, IntegrationOSError> {
+        let now = Utc::now().timestamp_millis();
+        self.find_and_update(
+            doc! {
+                "kind": to_bson(&S::kind())?,
+                "cancelRequested": false,
+                "$or": [
+                    { "status": BackgroundJobStatus::Queued.to_string() },
+                    {
+                        "status": BackgroundJobStatus::Running.to_string(),
+                        "leaseExpiresAt": { "$lte": now },
+                    },
+                ],
+            },
+            doc! {
+                "$set": {
+                    "status": BackgroundJobStatus::Running.to_string(),
+                    "leaseOwner": worker,
+                    "leaseExpiresAt": now + self.lease_millis,
+                    "heartbeatAt": now,
+                },
+                "$inc": { "attempts": 1 },
+            },
+        )
+        .await
+    }
+
+    /// Persists the job state and progress and extends the lease. Returns `None` when the
+    /// lease was lost to another worker.
+    pub async fn heartbeat(
+        &self,
+        job: &BackgroundJob<S>,
+        worker: &str,
+    ) -> Result<Option<BackgroundJob<S>>, IntegrationOSError> {
+        let now = Utc::now().timestamp_millis();
+        self.find_and_update(
+            doc! {
+                "_id": job.id.to_string(),
+                "leaseOwner": worker,
+                "status": BackgroundJobStatus::Running.to_string(),
+            },
+            doc! {
+                "$set": {
+                    "state": to_bson(&job.state)?,
+                    "progress": to_bson(&job.progress)?,
+                    "heartbeatAt": now,
+                    "leaseExpiresAt": now + self.lease_millis,
+                },
+            },
+        )
+        .await
+    }
+
+    async fn finish(
+        &self,
+        job: &BackgroundJob<S>,
+        worker: &str,
+        status: BackgroundJobStatus,
+        error: Option<String>,
+    ) -> Result<(), IntegrationOSError> {
+        self.store
+            .collection
+            .update_one(
+                doc! { "_id": job.id.to_string(), "leaseOwner": worker },
+                doc! {
+                    "$set": {
+                        "status": status.to_string(),
+                        "state": to_bson(&job.state)?,
+                        "progress": to_bson(&job.progress)?,
+                        "error": error,
+                        "leaseOwner": Bson::Null,
+                        "leaseExpiresAt": Bson::Null,
+                        "updatedAt": Utc::now().timestamp_millis(),
+                    },
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Requests cancellation. Queued jobs are cancelled right away, running ones stop at
+    /// their next checkpoint.
+    pub async fn cancel(&self, id: &Id) -> Result<(), IntegrationOSError> {
+        self.store
+            .collection
+            .update_one(
+                doc! { "_id": id.to_string(), "status": BackgroundJobStatus::Queued.to_string() },
+                doc! { "$set": { "status": BackgroundJobStatus::Cancelled.to_string() } },
+                None,
+            )
+            .await?;
+        self.store
+            .collection
+            .update_one(
+                doc! { "_id": id.to_string() },
+                doc! { "$set": { "cancelRequested": true } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Claims the next job and runs it to completion, failure or cancellation
+    pub async fn run_next<H: JobHandler<S> + ?Sized>(
+        &self,
+        worker: &str,
+        handler: &H,
+    ) -> Result<Option<BackgroundJobStatus>, IntegrationOSError> {
+        let Some(job) = self.claim(worker).await? else {
+            return Ok(None);
+        };
+        let id = job.id;
+        info!("Worker {worker} claimed {} job {id}", job.kind);
+
+        let mut context = JobContext {
+            queue: self,
+            worker: worker.to_owned(),
+            job,
+            interrupted: None,
+        };
+        let result = handler.run(&mut context).await;
+        let JobContext {
+            job, interrupted, ..
+        } = context;
+
+        let status = match (interrupted, result) {
+            (Some(Interruption::LeaseLost), _) => {
+                warn!("Worker {worker} lost the lease on job {id}");
+                return Ok(None);
+            }
+            (Some(Interruption::Cancelled), _) => {
+                self.finish(&job, worker, BackgroundJobStatus::Cancelled, None)
+                    .await?;
+                BackgroundJobStatus::Cancelled
+            }
+            (None, Ok(())) => {
+                self.finish(&job, worker, BackgroundJobStatus::Completed, None)
+                    .await?;
+                BackgroundJobStatus::Completed
+            }
+            (None, Err(e)) => {
+                error!("Job {id} failed on attempt {}: {e}", job.attempts);
+                let status = if job.can_retry() {
+                    BackgroundJobStatus::Queued
+                } else {
+                    BackgroundJobStatus::Failed
+                };
+                self.finish(&job, worker, status.clone(), Some(e.to_string()))
+                    .await?;
+                status
+            }
+        };
+        Ok(Some(status))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Interruption {
+    Cancelled,
+    LeaseLost,
+}
+
+/// Handle given to a running job to read and checkpoint its state
+pub struct JobContext<'a, S: JobState> {
+    queue: &'a JobQueue<S>,
+    worker: String,
+    job: BackgroundJob<S>,
+    interrupted: Option<Interruption>,
+}
+
+impl<S: JobState> JobContext<'_, S> {
+    pub fn job(&self) -> &BackgroundJob<S> {
+        &self.job
+    }
+
+    pub fn state(&self) -> &S {
+        &self.job.state
+    }
+
+    /// Saves the state and progress and renews the lease. Fails when the job was
+    /// cancelled or taken over, in which case the handler should return the error as is.
+    pub async fn checkpoint(
+        &mut self,
+        state: S,
+        progress: JobProgress,
+    ) -> Result<(), IntegrationOSError> {
+        self.job.state = state;
+        self.job.progress = progress;
+        self.heartbeat().await
+    }
+
+    pub async fn heartbeat(&mut self) -> Result<(), IntegrationOSError> {
+        match self.queue.heartbeat(&self.job, &self.worker).await? {
+            None => {
+                self.interrupted = Some(Interruption::LeaseLost);
+                Err(ApplicationError::conflict(
+                    &format!("Lease on job {} was lost", self.job.id),
+                    Some("job_queue"),
+                ))
+            }
+            Some(current) if current.cancel_requested => {
+                self.interrupted = Some(Interruption::Cancelled);
+                Err(ApplicationError::conflict(
+                    &format!("Job {} was cancelled", self.job.id),
+                    Some("job_queue"),
+                ))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+pub trait JobHandler<S: JobState>: Send + Sync {
+    async fn run(&self, context: &mut JobContext<'_, S>) -> Result<(), IntegrationOSError>;
+}
@@ -0,0 +1,170 @@
+use crate::{
+    prelude::{
+        materialized::{Freshness, MaterializedRecord, RefreshPolicy},
+        MongoStore,
+    },
+    IntegrationOSError, InternalError, Store,
+};
+use bson::doc;
+use chrono::Utc;
+use mongodb::{options::ReplaceOptions, Database};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// A record read from the materialized store along with how fresh it is
+#[derive(Debug, Clone, PartialEq)]
+pub struct Materialized<T> {
+    pub data: T,
+    pub freshness: Freshness,
+    pub synced_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaterializedStore {
+    store: MongoStore<MaterializedRecord>,
+    policy: RefreshPolicy,
+}
+
+impl MaterializedStore {
+    pub async fn new(
+        database: &Database,
+        policy: RefreshPolicy,
+    ) -> Result<Self, IntegrationOSError> {
+        let store = MongoStore::new(database, &Store::MaterializedRecords).await?;
+        Ok(Self { store, policy })
+    }
+
+    pub fn policy(&self) -> &RefreshPolicy {
+        &self.policy
+    }
+
+    /// Inserts or replaces the record identified by its external id
+    pub async fn upsert(
+        &self,
+        connection_key: &str,
+        common_model: &str,
+        external_id: &str,
+        data: Value,
+    ) -> Result<(), IntegrationOSError> {
+        let record = MaterializedRecord::new(
+            connection_key,
+            common_model,
+            external_id,
+            data,
+            &self.policy,
+        );
+        self.store
+            .collection
+            .replace_one(
+                doc! { "_id": &record.id },
+                &record,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn upsert_many(
+        &self,
+        connection_key: &str,
+        common_model: &str,
+        records: impl IntoIterator<Item = (String, Value)>,
+    ) -> Result<usize, IntegrationOSError> {
+        let mut count = 0;
+        for (external_id, data) in records {
+            self.upsert(connection_key, common_model, &external_id, data)
+                .await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns the record unless it's expired. Stale records are returned so the caller
+    /// can serve them while refreshing upstream.
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        connection_key: &str,
+        common_model: &str,
+        external_id: &str,
+    ) -> Result<Option<Materialized<T>>, IntegrationOSError> {
+        let key = MaterializedRecord::key(connection_key, common_model, external_id);
+        let Some(record) = self.store.get_one_by_id(&key).await? else {
+            return Ok(None);
+        };
+        Self::to_materialized(record)
+    }
+
+    pub async fn list<T: DeserializeOwned>(
+        &self,
+        connection_key: &str,
+        common_model: &str,
+        limit: Option<u64>,
+        skip: Option<u64>,
+    ) -> Result<Vec<Materialized<T>>, IntegrationOSError> {
+        let filter = doc! {
+            "connectionKey": connection_key,
+            "commonModel": common_model,
+            "expiresAt": { "$gt": Utc::now().timestamp_millis() },
+        };
+        let records = self
+            .store
+            .get_many(
+                Some(filter),
+                None,
+                Some(doc! { "syncedAt": -1 }),
+                limit,
+                skip,
+            )
+            .await?;
+
+        let mut result = Vec::with_capacity(records.len());
+        for record in records {
+            result.extend(Self::to_materialized(record)?);
+        }
+        Ok(result)
+    }
+
+    pub async fn invalidate(
+        &self,
+        connection_key: &str,
+        common_model: Option<&str>,
+    ) -> Result<u64, IntegrationOSError> {
+        let mut filter = doc! { "connectionKey": connection_key };
+        if let Some(common_model) = common_model {
+            filter.insert("commonModel", common_model);
+        }
+        Ok(self
+            .store
+            .collection
+            .delete_many(filter, None)
+            .await?
+            .deleted_count)
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64, IntegrationOSError> {
+        let filter = doc! { "expiresAt": { "$lte": Utc::now().timestamp_millis() } };
+        Ok(self
+            .store
+            .collection
+            .delete_many(filter, None)
+            .await?
+            .deleted_count)
+    }
+
+    fn to_materialized<T: DeserializeOwned>(
+        record: MaterializedRecord,
+    ) -> Result<Option<Materialized<T>>, IntegrationOSError> {
+        let freshness = record.freshness();
+        if freshness == Freshness::Expired {
+            return Ok(None);
+        }
+        let data = record.data_as().map_err(|e| {
+            InternalError::deserialize_error(&e.to_string(), Some("materialized_record"))
+        })?;
+        Ok(Some(Materialized {
+            data,
+            freshness,
+            synced_at: record.synced_at,
+        }))
+    }
+}
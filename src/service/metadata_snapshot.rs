@@ -0,0 +1,309 @@
+use crate::{
+    prelude::connection::{
+        connection_definition::ConnectionDefinition,
+        connection_model_definition::ConnectionModelDefinition,
+        connection_model_schema::ConnectionModelSchema,
+        connection_oauth_definition::ConnectionOAuthDefinition,
+    },
+    IntegrationOSError, InternalError, MongoStore,
+};
+use bson::doc;
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use strum::{AsRefStr, Display};
+use tracing::info;
+
+/// Format version of [`MetadataSnapshot`], bumped on breaking layout changes
+pub const METADATA_SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Display, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum MetadataKind {
+    ConnectionDefinition,
+    ConnectionModelDefinition,
+    ConnectionModelSchema,
+    ConnectionOAuthDefinition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MetadataChangeKind {
+    Added,
+    Modified,
+    /// Only present in the target deployment, removed when applying with pruning
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataChange {
+    pub kind: MetadataKind,
+    pub id: String,
+    pub change: MetadataChangeKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataDiff {
+    pub changes: Vec<MetadataChange>,
+}
+
+impl MetadataDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn count(&self, change: MetadataChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.change == change).count()
+    }
+}
+
+/// Stores of the deployment metadata, no tenant data
+#[derive(Debug, Clone)]
+pub struct MetadataStores {
+    pub connection_definitions: MongoStore<ConnectionDefinition>,
+    pub connection_model_definitions: MongoStore<ConnectionModelDefinition>,
+    pub connection_model_schemas: MongoStore<ConnectionModelSchema>,
+    pub connection_oauth_definitions: MongoStore<ConnectionOAuthDefinition>,
+}
+
+/// Every connector definition of a deployment in one versioned artifact, used to promote
+/// connector metadata from one deployment to another, e.g. staging to production
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataSnapshot {
+    pub version: u32,
+    pub captured_at: i64,
+    pub connection_definitions: Vec<ConnectionDefinition>,
+    pub connection_model_definitions: Vec<ConnectionModelDefinition>,
+    pub connection_model_schemas: Vec<ConnectionModelSchema>,
+    pub connection_oauth_definitions: Vec<ConnectionOAuthDefinition>,
+}
+
+fn by_id<T: Serialize>(records: &[T]) -> Result<BTreeMap<String, Value>, IntegrationOSError> {
+    records
+        .iter()
+        .map(|record| {
+            let value = serde_json::to_value(record).map_err(|e| {
+                InternalError::serialize_error(&e.to_string(), Some("metadata_snapshot"))
+            })?;
+            let id = value
+                .get("_id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            Ok((id, value))
+        })
+        .collect()
+}
+
+/// Changes turning `current` into `incoming`, records being matched by id
+pub fn diff_records<T: Serialize>(
+    kind: MetadataKind,
+    current: &[T],
+    incoming: &[T],
+) -> Result<Vec<MetadataChange>, IntegrationOSError> {
+    let current = by_id(current)?;
+    let incoming = by_id(incoming)?;
+    let change = |id: &String, change| MetadataChange {
+        kind,
+        id: id.clone(),
+        change,
+    };
+
+    let mut changes: Vec<MetadataChange> = incoming
+        .iter()
+        .filter_map(|(id, value)| match current.get(id) {
+            None => Some(change(id, MetadataChangeKind::Added)),
+            Some(existing) if existing != value => Some(change(id, MetadataChangeKind::Modified)),
+            Some(_) => None,
+        })
+        .collect();
+    changes.extend(
+        current
+            .keys()
+            .filter(|id| !incoming.contains_key(*id))
+            .map(|id| change(id, MetadataChangeKind::Removed)),
+    );
+    Ok(changes)
+}
+
+async fn apply_records<T>(
+    store: &MongoStore<T>,
+    records: &[T],
+    changes: &[MetadataChange],
+    prune: bool,
+) -> Result<(), IntegrationOSError>
+where
+    T: Serialize + DeserializeOwned + Unpin + Sync + Send + 'static,
+{
+    let report = store.upsert_many(records).await?;
+    if let Some((index, failure)) = report.failures().next() {
+        return Err(InternalError::io_err(
+            &format!("Could not apply record {index}: {failure:?}"),
+            Some("metadata_snapshot"),
+        ));
+    }
+    if prune {
+        let removed: Vec<&str> = changes
+            .iter()
+            .filter(|c| c.change == MetadataChangeKind::Removed)
+            .map(|c| c.id.as_str())
+            .collect();
+        if !removed.is_empty() {
+            store
+                .collection
+                .delete_many(doc! { "_id": { "$in": removed } }, None)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+impl MetadataSnapshot {
+    pub async fn capture(stores: &MetadataStores) -> Result<Self, IntegrationOSError> {
+        Ok(Self {
+            version: METADATA_SNAPSHOT_VERSION,
+            captured_at: Utc::now().timestamp_millis(),
+            connection_definitions: stores.connection_definitions.get_all().await?,
+            connection_model_definitions: stores.connection_model_definitions.get_all().await?,
+            connection_model_schemas: stores.connection_model_schemas.get_all().await?,
+            connection_oauth_definitions: stores.connection_oauth_definitions.get_all().await?,
+        })
+    }
+
+    /// Changes applying this snapshot over `current` would make
+    pub fn diff(&self, current: &MetadataSnapshot) -> Result<MetadataDiff, IntegrationOSError> {
+        let mut changes = diff_records(
+            MetadataKind::ConnectionDefinition,
+            &current.connection_definitions,
+            &self.connection_definitions,
+        )?;
+        changes.extend(diff_records(
+            MetadataKind::ConnectionModelDefinition,
+            &current.connection_model_definitions,
+            &self.connection_model_definitions,
+        )?);
+        changes.extend(diff_records(
+            MetadataKind::ConnectionModelSchema,
+            &current.connection_model_schemas,
+            &self.connection_model_schemas,
+        )?);
+        changes.extend(diff_records(
+            MetadataKind::ConnectionOAuthDefinition,
+            &current.connection_oauth_definitions,
+            &self.connection_oauth_definitions,
+        )?);
+        Ok(MetadataDiff { changes })
+    }
+
+    /// Changes [`MetadataSnapshot::apply`] would make to the deployment, without making them
+    pub async fn preview(
+        &self,
+        stores: &MetadataStores,
+    ) -> Result<MetadataDiff, IntegrationOSError> {
+        self.diff(&Self::capture(stores).await?)
+    }
+
+    /// Upserts every record of the snapshot, also deleting the ones missing from it when
+    /// `prune` is set, and returns the changes made
+    pub async fn apply(
+        &self,
+        stores: &MetadataStores,
+        prune: bool,
+    ) -> Result<MetadataDiff, IntegrationOSError> {
+        if self.version != METADATA_SNAPSHOT_VERSION {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "Unsupported metadata snapshot version {}, expected {METADATA_SNAPSHOT_VERSION}",
+                    self.version
+                ),
+                Some("metadata_snapshot"),
+            ));
+        }
+        let diff = self.preview(stores).await?;
+        let changes_of = |kind| -> Vec<MetadataChange> {
+            diff.changes
+                .iter()
+                .filter(|c| c.kind == kind)
+                .cloned()
+                .collect()
+        };
+
+        apply_records(
+            &stores.connection_definitions,
+            &self.connection_definitions,
+            &changes_of(MetadataKind::ConnectionDefinition),
+            prune,
+        )
+        .await?;
+        apply_records(
+            &stores.connection_model_definitions,
+            &self.connection_model_definitions,
+            &changes_of(MetadataKind::ConnectionModelDefinition),
+            prune,
+        )
+        .await?;
+        apply_records(
+            &stores.connection_model_schemas,
+            &self.connection_model_schemas,
+            &changes_of(MetadataKind::ConnectionModelSchema),
+            prune,
+        )
+        .await?;
+        apply_records(
+            &stores.connection_oauth_definitions,
+            &self.connection_oauth_definitions,
+            &changes_of(MetadataKind::ConnectionOAuthDefinition),
+            prune,
+        )
+        .await?;
+
+        info!(
+            "Applied metadata snapshot: {} added, {} modified, {} removed",
+            diff.count(MetadataChangeKind::Added),
+            diff.count(MetadataChangeKind::Modified),
+            if prune {
+                diff.count(MetadataChangeKind::Removed)
+            } else {
+                0
+            }
+        );
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_records() {
+        let current = vec![
+            json!({ "_id": "a", "name": "a" }),
+            json!({ "_id": "b", "name": "b" }),
+            json!({ "_id": "c", "name": "c" }),
+        ];
+        let incoming = vec![
+            json!({ "_id": "a", "name": "a" }),
+            json!({ "_id": "b", "name": "b2" }),
+            json!({ "_id": "d", "name": "d" }),
+        ];
+        let changes =
+            diff_records(MetadataKind::ConnectionDefinition, &current, &incoming).unwrap();
+        let summary: Vec<(&str, MetadataChangeKind)> =
+            changes.iter().map(|c| (c.id.as_str(), c.change)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("b", MetadataChangeKind::Modified),
+                ("d", MetadataChangeKind::Added),
+                ("c", MetadataChangeKind::Removed),
+            ]
+        );
+    }
+}
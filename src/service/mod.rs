@@ -1,2 +1,28 @@
+pub mod access_guard;
+pub mod api_version_migrator;
+pub mod archiver;
+pub mod backfill_orchestrator;
 pub mod client;
+pub mod context_compaction;
+pub mod context_retention;
+pub mod control_plane;
+pub mod dead_letter_queue;
+pub mod definition_cache;
+pub mod event_retention;
+pub mod event_stream;
+pub mod job_queue;
+pub mod materialized_store;
+pub mod metadata_snapshot;
+pub mod model_executor;
+pub mod oauth_refresher;
+pub mod outbox_relay;
+pub mod paginator;
+pub mod plan_resolver;
+pub mod projector;
+pub mod schema_reconciler;
+pub mod schema_validator;
+pub mod secret_rotation;
+pub mod task_supervisor;
 pub mod telemetry;
+pub mod usage_tracker;
+pub mod webhook_verifier;
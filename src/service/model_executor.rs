@@ -0,0 +1,399 @@
+use super::client::caller_client::CallerClient;
+use crate::{
+    connection_model_definition::{ConnectionModelDefinition, PlatformInfo},
+    prelude::connection::{connection_auth::ConnectionAuth, Connection},
+    DefaultTemplate, EgressGuard, IntegrationOSError, InternalError, TemplateExt,
+};
+use http::{HeaderMap, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{collections::HashMap, time::Duration};
+use tracing::warn;
+
+/// Caller supplied parameters of a model definition call
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExecutionParams {
+    /// Values for the placeholders of the definition's path, e.g. `{{id}}`
+    pub path_params: HashMap<String, String>,
+    pub query_params: HashMap<String, String>,
+    pub headers: HeaderMap,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionResponse<T> {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: T,
+}
+
+/// Executes connection model definitions against their platform: renders the
+/// definition's templates with the connection secret and path params, sends the request
+/// with a per attempt timeout and retries failures that are safe to retry.
+///
+/// Rate limited requests are always retried. Transport errors, timeouts and 5xx
+/// responses are only retried for idempotent methods, since the platform may have
+/// applied the request.
+#[derive(Debug, Clone)]
+pub struct ModelDefinitionExecutor {
+    http_client: reqwest::Client,
+    template: DefaultTemplate,
+    timeout: Duration,
+    max_retries: u32,
+    backoff: Duration,
+    egress_guard: Option<EgressGuard>,
+}
+
+impl Default for ModelDefinitionExecutor {
+    fn default() -> Self {
+        Self::new(reqwest::Client::new())
+    }
+}
+
+impl ModelDefinitionExecutor {
+    pub fn new(http_client: reqwest::Client) -> Self {
+        Self {
+            http_client,
+            template: DefaultTemplate::default(),
+            timeout: Duration::from_secs(30),
+            max_retries: 2,
+            backoff: Duration::from_millis(200),
+            egress_guard: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries after `backoff`, doubling it on every attempt
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Only calls platforms, and follows redirects and next page links, that `egress_guard`
+    /// allows. Replaces the HTTP client with one built by the guard, shared by every call.
+    pub fn with_egress_guard(
+        mut self,
+        egress_guard: EgressGuard,
+    ) -> Result<Self, IntegrationOSError> {
+        self.http_client = egress_guard.client()?;
+        self.egress_guard = Some(egress_guard);
+        Ok(self)
+    }
+
+    /// Template data of a call, the secret with the path params on top
+    fn template_data(secret: &Value, params: &ExecutionParams) -> Value {
+        let mut data = match secret {
+            Value::Object(secret) => secret.clone(),
+            _ => Default::default(),
+        };
+        data.extend(
+            params
+                .path_params
+                .iter()
+                .map(|(key, value)| (key.clone(), Value::String(value.clone()))),
+        );
+        Value::Object(data)
+    }
+
+    /// Renders the templates of `definition` for a call
+    pub fn render(
+        &self,
+        definition: &ConnectionModelDefinition,
+        params: &ExecutionParams,
+        secret: &Value,
+    ) -> Result<ConnectionModelDefinition, IntegrationOSError> {
+        self.template
+            .render_as(definition, Some(&Self::template_data(secret, params)))
+    }
+
+    fn is_retryable(method: &Method, status: Option<StatusCode>) -> bool {
+        match status {
+            Some(StatusCode::TOO_MANY_REQUESTS) => true,
+            Some(status) if !status.is_server_error() => false,
+            _ => method.is_idempotent(),
+        }
+    }
+
+    /// Same as [`ModelDefinitionExecutor::execute`], refusing connections that are
+    /// paused, revoked or not activated yet
+    pub async fn execute_for<T: DeserializeOwned>(
+        &self,
+        connection: &Connection,
+        definition: &ConnectionModelDefinition,
+        params: ExecutionParams,
+        secret: &Value,
+    ) -> Result<ExecutionResponse<T>, IntegrationOSError> {
+        connection.ensure_active()?;
+        self.call(definition, params, secret, Some(&connection.auth_method()))
+            .await
+    }
+
+    #[deprecated(note = "use `execute_for`, which refuses inactive connections")]
+    pub async fn execute<T: DeserializeOwned>(
+        &self,
+        definition: &ConnectionModelDefinition,
+        params: ExecutionParams,
+        secret: &Value,
+    ) -> Result<ExecutionResponse<T>, IntegrationOSError> {
+        self.call(definition, params, secret, None).await
+    }
+
+    /// Authenticates with `auth` when set, otherwise with the definition's auth method
+    async fn call<T: DeserializeOwned>(
+        &self,
+        definition: &ConnectionModelDefinition,
+        params: ExecutionParams,
+        secret: &Value,
+        auth: Option<&ConnectionAuth>,
+    ) -> Result<ExecutionResponse<T>, IntegrationOSError> {
+        let rendered = self.render(definition, &params, secret)?;
+        let PlatformInfo::Api(config) = &rendered.platform_info;
+        let data = Self::template_data(secret, &params);
+        let body = params
+            .body
+            .as_ref()
+            .map(serde_json::to_vec)
+            .transpose()
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("model_executor")))?;
+        let caller = CallerClient::new(config, rendered.action.clone(), &self.http_client);
+        let caller = match auth {
+            Some(auth) => caller.with_auth(auth),
+            None => caller,
+        };
+        let caller = match &self.egress_guard {
+            Some(guard) => caller.with_egress_guard(guard),
+            None => caller,
+        };
+
+        let mut attempt = 0;
+        loop {
+            let call = async {
+                let response = caller
+                    .make_request(
+                        body.clone(),
+                        Some(&data),
+                        Some(params.headers.clone()),
+                        Some(&params.query_params),
+                    )
+                    .await?;
+                let status = response.status();
+                let headers = response.headers().clone();
+                let bytes = response
+                    .bytes()
+                    .await
+                    .map_err(|e| InternalError::io_err(&e.to_string(), Some("model_executor")))?;
+                Ok::<_, IntegrationOSError>((status, headers, bytes))
+            };
+            let result = tokio::time::timeout(self.timeout, call)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(InternalError::timeout(
+                        &format!(
+                            "Calling {} timed out after {:?}",
+                            definition.key, self.timeout
+                        ),
+                        Some("model_executor"),
+                    ))
+                });
+
+            let status = result.as_ref().ok().map(|(status, _, _)| *status);
+            let failed = !status.is_some_and(|status| status.is_success());
+            if failed && attempt < self.max_retries && Self::is_retryable(&rendered.action, status)
+            {
+                let delay = self.backoff * 2u32.saturating_pow(attempt);
+                warn!(
+                    "Call to {} failed on attempt {}, retrying in {delay:?}",
+                    definition.key,
+                    attempt + 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let (status, headers, bytes) = result?;
+            if !status.is_success() {
+                return Err(IntegrationOSError::from_err_code(
+                    status,
+                    &String::from_utf8_lossy(&bytes),
+                    Some("model_executor"),
+                ));
+            }
+            let body = if bytes.is_empty() {
+                serde_json::from_value(Value::Null)
+            } else {
+                serde_json::from_slice(&bytes)
+            }
+            .map_err(|e| {
+                InternalError::deserialize_error(&e.to_string(), Some("model_executor"))
+            })?;
+
+            return Ok(ExecutionResponse {
+                status,
+                headers,
+                body,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api_model_config::{ApiModelConfig, AuthMethod, SamplesInput, SchemasInput},
+        connection_model_definition::{CrudAction, TestConnection},
+        id::{prefix::IdPrefix, Id},
+    };
+    use mockito::Server;
+    use serde_json::json;
+
+    fn connection() -> Connection {
+        Connection::builder("stripe", Id::now(IdPrefix::ConnectionDefinition)).build()
+    }
+
+    fn definition(base_url: String, action: Method) -> ConnectionModelDefinition {
+        ConnectionModelDefinition {
+            id: Id::now(IdPrefix::ConnectionModelDefinition),
+            connection_platform: "stripe".to_owned(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            platform_version: "v1".to_owned(),
+            key: "api::stripe::v1::customer::getOne".to_owned(),
+            title: "Get Customer".to_owned(),
+            name: "Get Customer".to_owned(),
+            model_name: "Customer".to_owned(),
+            action,
+            action_name: CrudAction::GetOne,
+            platform_info: PlatformInfo::Api(ApiModelConfig {
+                base_url,
+                path: "/customers/{{id}}".to_owned(),
+                auth_method: AuthMethod::BearerToken {
+                    value: "{{STRIPE_SECRET_KEY}}".to_owned(),
+                },
+                headers: None,
+                query_params: None,
+                content: None,
+                schemas: SchemasInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                samples: SamplesInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                responses: vec![],
+                paths: None,
+                pagination: None,
+            }),
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            is_default_crud_mapping: None,
+            mapping: None,
+            record_metadata: Default::default(),
+        }
+    }
+
+    fn params() -> ExecutionParams {
+        ExecutionParams {
+            path_params: HashMap::from([("id".to_owned(), "cus_123".to_owned())]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_renders_and_retries() {
+        let mut server = Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/customers/cus_123")
+            .match_header("authorization", "Bearer sk_test")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+        let executor = ModelDefinitionExecutor::default().with_retries(2, Duration::from_millis(1));
+        let connection = connection();
+        let definition = definition(server.url(), Method::GET);
+        let secret = json!({ "STRIPE_SECRET_KEY": "sk_test" });
+
+        let first = executor
+            .execute_for::<Value>(&connection, &definition, params(), &secret)
+            .await;
+        assert!(first.is_err());
+        unavailable.assert_async().await;
+        unavailable.remove_async().await;
+
+        let ok = server
+            .mock("GET", "/customers/cus_123")
+            .with_status(200)
+            .with_body(r#"{"id":"cus_123"}"#)
+            .create_async()
+            .await;
+        let response = executor
+            .execute_for::<Value>(&connection, &definition, params(), &secret)
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["id"], "cus_123");
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_execute_does_not_retry_unsafe_requests() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/customers/cus_123")
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+        let executor = ModelDefinitionExecutor::default().with_retries(3, Duration::from_millis(1));
+
+        let err = executor
+            .execute_for::<Value>(
+                &connection(),
+                &definition(server.url(), Method::POST),
+                params(),
+                &json!({ "STRIPE_SECRET_KEY": "sk_test" }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(StatusCode::from(&err), StatusCode::INTERNAL_SERVER_ERROR);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_execute_refuses_inactive_connections() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/customers/cus_123")
+            .expect(0)
+            .create_async()
+            .await;
+        let mut connection = connection();
+        connection.pause("alice", None).unwrap();
+
+        let err = ModelDefinitionExecutor::default()
+            .execute_for::<Value>(
+                &connection,
+                &definition(server.url(), Method::GET),
+                params(),
+                &json!({ "STRIPE_SECRET_KEY": "sk_test" }),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.stable_code(),
+            crate::stable_code::StableErrorCode::ConnInactive
+        );
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,385 @@
+use crate::{
+    api_model_config::{ApiModelConfig, ContentType},
+    prelude::{
+        connection::{Connection, OAuth},
+        connection_oauth_definition::{Computation, ConnectionOAuthDefinition, OAuthResponse},
+        get_secret_request::GetSecretRequest,
+        oauth_secret::OAuthSecret,
+    },
+    ApplicationError, CryptoExt, DefaultTemplate, EgressGuard, IntegrationOSError, InternalError,
+    MongoStore, TemplateExt,
+};
+use bson::doc;
+use chrono::Utc;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Request sent to the platform's token endpoint, with the templates already rendered
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshRequest {
+    pub url: String,
+    pub headers: HeaderMap,
+    pub query_params: BTreeMap<String, String>,
+    pub body: Option<Value>,
+    pub content: ContentType,
+}
+
+impl RefreshRequest {
+    /// Merges the rendered refresh config with the values computed by the definition's
+    /// refresh function. Computed headers and query params win over configured ones.
+    pub fn build(
+        config: &ApiModelConfig,
+        computation: Option<Computation>,
+    ) -> Result<Self, IntegrationOSError> {
+        let mut headers = match &config.headers {
+            Some(headers) => headers.to_header_map(None)?,
+            None => HeaderMap::new(),
+        };
+        let mut query_params = match &config.query_params {
+            Some(query_params) => query_params.to_pairs(None)?.into_iter().collect(),
+            None => BTreeMap::new(),
+        };
+        let mut body = None;
+
+        if let Some(computation) = computation {
+            for (name, value) in string_pairs(computation.headers)? {
+                let name = HeaderName::try_from(name.as_str()).map_err(invalid_header)?;
+                let value = HeaderValue::try_from(value.as_str()).map_err(invalid_header)?;
+                headers.insert(name, value);
+            }
+            query_params.extend(string_pairs(computation.query_params)?);
+            body = computation.body;
+        }
+
+        Ok(Self {
+            url: config.uri(),
+            headers,
+            query_params,
+            body,
+            content: config.content.clone().unwrap_or_default(),
+        })
+    }
+}
+
+fn invalid_header(e: impl ToString) -> IntegrationOSError {
+    InternalError::invalid_argument(&e.to_string(), Some("oauth_refresh"))
+}
+
+fn string_pairs(value: Option<Value>) -> Result<Vec<(String, String)>, IntegrationOSError> {
+    match value {
+        None | Some(Value::Null) => Ok(vec![]),
+        Some(Value::Object(map)) => Ok(map
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::String(value) => (key, value),
+                other => (key, other.to_string()),
+            })
+            .collect()),
+        Some(other) => Err(InternalError::invalid_argument(
+            &format!("Expected an object of strings, got {other}"),
+            Some("oauth_refresh"),
+        )),
+    }
+}
+
+/// Secret after a refresh. Platforms that don't rotate refresh tokens omit them from the
+/// response, in which case the current one is kept.
+pub fn refreshed_secret(secret: &OAuthSecret, mut response: OAuthResponse) -> OAuthSecret {
+    if response.refresh_token.is_none() {
+        response.refresh_token = secret.refresh_token.clone();
+    }
+    secret.from_refresh(response, None, None, secret.metadata.clone())
+}
+
+/// Refreshes the OAuth tokens of connections against their platform's token endpoint,
+/// stores the new secret and records when it expires on the connection.
+pub struct OAuthRefresher<C> {
+    crypto: C,
+    connections: MongoStore<Connection>,
+    http_client: reqwest::Client,
+    template: DefaultTemplate,
+    egress_guard: Option<EgressGuard>,
+}
+
+impl<C: CryptoExt + Sync> OAuthRefresher<C> {
+    pub fn new(crypto: C, connections: MongoStore<Connection>) -> Self {
+        Self {
+            crypto,
+            connections,
+            http_client: reqwest::Client::new(),
+            template: DefaultTemplate::default(),
+            egress_guard: None,
+        }
+    }
+
+    /// Only sends refresh requests to token endpoints that `egress_guard` allows. Replaces
+    /// the HTTP client with one built by the guard, shared by every refresh.
+    pub fn with_egress_guard(
+        mut self,
+        egress_guard: EgressGuard,
+    ) -> Result<Self, IntegrationOSError> {
+        self.http_client = egress_guard.client()?;
+        self.egress_guard = Some(egress_guard);
+        Ok(self)
+    }
+
+    /// With an egress guard, `http_client` should be built by [`EgressGuard::client`]
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Renders the definition's refresh config against the current secret
+    pub fn refresh_request(
+        &self,
+        definition: &ConnectionOAuthDefinition,
+        secret: &OAuthSecret,
+    ) -> Result<RefreshRequest, IntegrationOSError> {
+        let payload = secret.as_json();
+        let config = self
+            .template
+            .render_as(&definition.configuration.refresh, Some(&payload))?;
+        let computation = definition
+            .compute
+            .refresh
+            .computation
+            .as_ref()
+            .map(|function| function.compute::<Computation>(&payload))
+            .transpose()?;
+        RefreshRequest::build(&config, computation)
+    }
+
+    async fn send(
+        &self,
+        request: RefreshRequest,
+        definition: &ConnectionOAuthDefinition,
+    ) -> Result<OAuthResponse, IntegrationOSError> {
+        if let Some(guard) = &self.egress_guard {
+            guard.check(&request.url)?;
+        }
+        let mut builder = self
+            .http_client
+            .post(&request.url)
+            .headers(request.headers)
+            .query(&request.query_params);
+        if let Some(body) = &request.body {
+            builder = match request.content {
+                ContentType::Form => builder.form(body),
+                ContentType::Json | ContentType::Other => builder.json(body),
+            };
+        }
+
+        let response = builder.send().await.map_err(|e| {
+            InternalError::io_err(
+                &format!("Failed to send request: {e}"),
+                Some("oauth_refresh"),
+            )
+        })?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| InternalError::io_err(&e.to_string(), Some("oauth_refresh")))?;
+        if !status.is_success() {
+            return Err(IntegrationOSError::from_err_code(
+                status,
+                &body,
+                Some("oauth_refresh"),
+            ));
+        }
+
+        let body: Value = serde_json::from_str(&body)
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("oauth_refresh")))?;
+        definition.compute.refresh.response.compute(&body)
+    }
+
+    /// Refreshes the tokens of `connection` and returns it pointing to the new secret.
+    /// Fails unless the connection is active, and with a conflict when its secret changed
+    /// meanwhile, e.g. because another worker refreshed it first.
+    pub async fn refresh(
+        &self,
+        connection: &Connection,
+        definition: &ConnectionOAuthDefinition,
+    ) -> Result<Connection, IntegrationOSError> {
+        connection.ensure_active()?;
+        let Some(OAuth::Enabled {
+            connection_oauth_definition_id,
+            ..
+        }) = &connection.oauth
+        else {
+            return Err(ApplicationError::bad_request(
+                &format!("Connection {} does not use OAuth", connection.id),
+                Some("oauth_refresh"),
+            ));
+        };
+        if *connection_oauth_definition_id != definition.id {
+            return Err(InternalError::invalid_argument(
+                &format!(
+                    "Connection {} uses OAuth definition {connection_oauth_definition_id}, not {}",
+                    connection.id, definition.id
+                ),
+                Some("oauth_refresh"),
+            ));
+        }
+
+        let buildable_id = connection.ownership.id.to_string();
+        let secret = self
+            .crypto
+            .decrypt(&GetSecretRequest {
+                id: connection.secrets_service_id.clone(),
+                buildable_id: buildable_id.clone(),
+            })
+            .await?;
+        let secret: OAuthSecret = serde_json::from_value(secret)
+            .map_err(|e| InternalError::invalid_argument(&e.to_string(), Some("oauth_secret")))?;
+
+        let request = self.refresh_request(definition, &secret)?;
+        let response = self.send(request, definition).await?;
+        let secret = refreshed_secret(&secret, response);
+        let encrypted = self.crypto.encrypt(buildable_id, &secret.as_json()).await?;
+
+        let now = Utc::now().timestamp_millis();
+        let oauth = OAuth::Enabled {
+            connection_oauth_definition_id: definition.id,
+            expires_in: Some(secret.expires_in),
+            expires_at: Some(now + secret.expires_in as i64 * 1000),
+        };
+        let oauth_bson = bson::to_bson(&oauth)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("oauth_refresh")))?;
+
+        let result = self
+            .connections
+            .collection
+            .update_one(
+                doc! {
+                    "_id": connection.id.to_string(),
+                    "secretsServiceId": &connection.secrets_service_id,
+                },
+                doc! {
+                    "$set": {
+                        "secretsServiceId": &encrypted.id,
+                        "oauth": oauth_bson,
+                        "updatedAt": now,
+                    },
+                },
+                None,
+            )
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ApplicationError::conflict(
+                &format!(
+                    "Secret of connection {} changed concurrently",
+                    connection.id
+                ),
+                Some("oauth_refresh"),
+            ));
+        }
+        info!("Refreshed OAuth tokens of connection {}", connection.id);
+
+        let mut connection = connection.clone();
+        connection.secrets_service_id = encrypted.id;
+        connection.oauth = Some(oauth);
+        connection.record_metadata.updated_at = now;
+        Ok(connection)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api_model_config::{AuthMethod, SamplesInput, SchemasInput},
+        http_params::{HeaderParams, QueryParams},
+        prelude::shared::redacted::Redacted,
+    };
+    use serde_json::json;
+
+    fn secret() -> OAuthSecret {
+        OAuthSecret {
+            client_id: "client".to_owned(),
+            client_secret: "shh".into(),
+            access_token: "old-access".into(),
+            token_type: Some("Bearer".to_owned()),
+            refresh_token: Some("refresh".into()),
+            expires_in: 3600,
+            metadata: json!({ "tenant": "acme" }),
+            request_payload: None,
+        }
+    }
+
+    #[test]
+    fn test_refresh_request_rendering() {
+        let config = ApiModelConfig {
+            base_url: "https://{{OAUTH_METADATA.tenant}}.example.com/".to_owned(),
+            path: "/oauth/token".to_owned(),
+            auth_method: AuthMethod::None,
+            headers: Some(HeaderParams::new().with("Accept", "application/json")),
+            query_params: Some(QueryParams::new().with("client_id", "{{OAUTH_CLIENT_ID}}")),
+            content: Some(ContentType::Form),
+            schemas: SchemasInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            samples: SamplesInput {
+                headers: None,
+                query_params: None,
+                path_params: None,
+                body: None,
+            },
+            responses: vec![],
+            paths: None,
+            pagination: None,
+        };
+        let config = DefaultTemplate::default()
+            .render_as(&config, Some(&secret().as_json()))
+            .unwrap();
+
+        let request = RefreshRequest::build(
+            &config,
+            Some(Computation {
+                headers: Some(json!({ "Accept": "application/x-www-form-urlencoded" })),
+                query_params: None,
+                body: Some(json!({ "grant_type": "refresh_token", "refresh_token": "refresh" })),
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(request.url, "https://acme.example.com/oauth/token");
+        assert_eq!(
+            request.headers[http::header::ACCEPT],
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(request.query_params["client_id"], "client");
+        assert_eq!(request.body.unwrap()["grant_type"], "refresh_token");
+        assert_eq!(request.content, ContentType::Form);
+    }
+
+    #[test]
+    fn test_refreshed_secret_keeps_refresh_token() {
+        let refreshed = refreshed_secret(
+            &secret(),
+            OAuthResponse {
+                access_token: "new-access".into(),
+                expires_in: 1800,
+                refresh_token: None,
+                token_type: None,
+            },
+        );
+
+        assert_eq!(refreshed.access_token.expose_secret(), "new-access");
+        assert_eq!(
+            refreshed
+                .refresh_token
+                .as_ref()
+                .map(Redacted::expose_secret),
+            Some(&"refresh".to_owned())
+        );
+        assert_eq!(refreshed.expires_in, 1800);
+        assert_eq!(refreshed.client_secret.expose_secret(), "shh");
+        assert_eq!(refreshed.metadata, json!({ "tenant": "acme" }));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{prelude::queue::OutboxMessage, CacheExt, IntegrationOSError, MongoStore};
+use bson::{doc, Bson};
+use chrono::Utc;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// Drains the outbox onto Redis with at-least-once semantics.
+///
+/// Messages are claimed under a lease, pushed, then deleted. A relay crashing between the
+/// push and the delete leaves the message to be pushed again once its lease expires, so
+/// consumers have to tolerate duplicates, e.g. with an
+/// [`IdempotencyGuard`](crate::IdempotencyGuard).
+#[derive(Debug, Clone)]
+pub struct OutboxRelay {
+    outbox: MongoStore<OutboxMessage>,
+    batch_size: usize,
+    lease: Duration,
+    poll_interval: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(outbox: MongoStore<OutboxMessage>) -> Self {
+        Self {
+            outbox,
+            batch_size: 100,
+            lease: Duration::from_secs(30),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Claims the oldest message not held by another relay
+    async fn claim(&self) -> Result<Option<OutboxMessage>, IntegrationOSError> {
+        let now = Utc::now().timestamp_millis();
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "createdAt": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self
+            .outbox
+            .collection
+            .find_one_and_update(
+                doc! {
+                    "$or": [
+                        { "lockedUntil": { "$exists": false } },
+                        { "lockedUntil": Bson::Null },
+                        { "lockedUntil": { "$lte": now } },
+                    ],
+                },
+                doc! {
+                    "$set": { "lockedUntil": now + self.lease.as_millis() as i64 },
+                    "$inc": { "attempts": 1 },
+                },
+                options,
+            )
+            .await?)
+    }
+
+    async fn release(&self, message: &OutboxMessage, error: &IntegrationOSError) {
+        let result = self
+            .outbox
+            .update_one(
+                &message.id.to_string(),
+                doc! {
+                    "$set": { "lastError": error.to_string() },
+                    "$unset": { "lockedUntil": "" },
+                },
+            )
+            .await;
+        if let Err(e) = result {
+            error!("Could not release outbox message {}: {e}", message.id);
+        }
+    }
+
+    /// Publishes up to a batch of messages, returning how many were published
+    pub async fn run_once<C: CacheExt + Sync>(
+        &self,
+        cache: &C,
+    ) -> Result<usize, IntegrationOSError> {
+        let mut published = 0;
+        while published < self.batch_size {
+            let Some(message) = self.claim().await? else {
+                break;
+            };
+            if let Err(e) = cache
+                .list_push(&message.queue, message.payload.as_bytes())
+                .await
+            {
+                error!("Could not publish outbox message {}: {e}", message.id);
+                self.release(&message, &e).await;
+                return Err(e);
+            }
+            self.outbox
+                .collection
+                .delete_one(doc! { "_id": message.id.to_string() }, None)
+                .await?;
+            published += 1;
+        }
+        Ok(published)
+    }
+
+    /// Relays until the cache or the outbox fails, sleeping whenever the outbox is empty
+    pub async fn run<C: CacheExt + Sync>(self, cache: C) -> Result<(), IntegrationOSError> {
+        info!("Starting outbox relay");
+        loop {
+            let published = self.run_once(&cache).await?;
+            if published > 0 {
+                info!("Relayed {published} outbox messages");
+            }
+            if published < self.batch_size {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    pub fn start<C>(self, cache: C) -> JoinHandle<Result<(), IntegrationOSError>>
+    where
+        C: CacheExt + Send + Sync + 'static,
+    {
+        tokio::spawn(self.run(cache))
+    }
+}
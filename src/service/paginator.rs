@@ -0,0 +1,338 @@
+use super::model_executor::{ExecutionParams, ModelDefinitionExecutor};
+use crate::{
+    api_model_config::{ApiModelConfig, NextPage, PaginationConfig},
+    connection_model_definition::{ConnectionModelDefinition, PlatformInfo},
+    prelude::connection::Connection,
+    IntegrationOSError, InternalError,
+};
+use futures::{stream, Stream, TryStreamExt};
+use serde_json::Value;
+
+/// Items of a page, selected with the response `object` path of the config. Without one
+/// the page is the list itself, or a single item.
+pub fn page_items(config: &ApiModelConfig, body: Value) -> Vec<Value> {
+    let path = config
+        .paths
+        .as_ref()
+        .and_then(|paths| paths.response.as_ref())
+        .and_then(|response| response.object.as_deref());
+    let selected = match path {
+        Some(path) => match jsonpath_lib::select(&body, path) {
+            Ok(selected) => match selected.as_slice() {
+                [Value::Array(items)] => items.clone(),
+                items => items.iter().map(|item| (*item).clone()).collect(),
+            },
+            Err(_) => vec![],
+        },
+        None => match body {
+            Value::Array(items) => items,
+            Value::Null => vec![],
+            item => vec![item],
+        },
+    };
+    selected
+        .into_iter()
+        .filter(|item| !item.is_null())
+        .collect()
+}
+
+struct PageState {
+    definition: ConnectionModelDefinition,
+    params: ExecutionParams,
+    pages: u32,
+}
+
+/// Walks a paginated list endpoint through the [`ModelDefinitionExecutor`], following the
+/// definition's [`PaginationConfig`] until the platform reports no more pages. Every page
+/// is requested with [`ModelDefinitionExecutor::execute_for`] the connection.
+pub struct Paginator<'a> {
+    executor: &'a ModelDefinitionExecutor,
+    connection: &'a Connection,
+    definition: &'a ConnectionModelDefinition,
+    secret: &'a Value,
+    max_pages: Option<u32>,
+}
+
+impl<'a> Paginator<'a> {
+    pub fn new(
+        executor: &'a ModelDefinitionExecutor,
+        connection: &'a Connection,
+        definition: &'a ConnectionModelDefinition,
+        secret: &'a Value,
+    ) -> Self {
+        Self {
+            executor,
+            connection,
+            definition,
+            secret,
+            max_pages: None,
+        }
+    }
+
+    /// Stops after `max_pages` requests even if the platform has more
+    pub fn with_max_pages(mut self, max_pages: u32) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    fn pagination(&self) -> Option<&'a PaginationConfig> {
+        let PlatformInfo::Api(config) = &self.definition.platform_info;
+        config.pagination.as_ref()
+    }
+
+    /// Items of every page, in order. A definition without pagination yields the items of
+    /// a single request.
+    pub fn items(
+        self,
+        mut params: ExecutionParams,
+    ) -> impl Stream<Item = Result<Value, IntegrationOSError>> + 'a {
+        let pagination = self.pagination();
+        if let Some(pagination) = pagination {
+            params.query_params.extend(pagination.first_page());
+        }
+        let state = Some(PageState {
+            definition: self.definition.clone(),
+            params,
+            pages: 0,
+        });
+
+        stream::try_unfold(state, move |state| async move {
+            let Some(mut state) = state else {
+                return Ok(None);
+            };
+            let response = self
+                .executor
+                .execute_for::<Value>(
+                    self.connection,
+                    &state.definition,
+                    state.params.clone(),
+                    self.secret,
+                )
+                .await?;
+            state.pages += 1;
+
+            let PlatformInfo::Api(config) = &state.definition.platform_info;
+            let items = page_items(config, response.body.clone());
+            let next = pagination.and_then(|pagination| {
+                pagination.next_page(
+                    &state.params.query_params,
+                    &response.body,
+                    &response.headers,
+                    items.len(),
+                )
+            });
+
+            let exhausted = self.max_pages.is_some_and(|max| state.pages >= max);
+            let next = match next {
+                Some(next) if !exhausted => Some(Self::advance(state, next)?),
+                _ => None,
+            };
+            Ok::<_, IntegrationOSError>(Some((items, next)))
+        })
+        .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    fn advance(mut state: PageState, next: NextPage) -> Result<PageState, IntegrationOSError> {
+        match next {
+            NextPage::Query(params) => state.params.query_params.extend(params),
+            NextPage::Url(url) => {
+                let url = reqwest::Url::parse(&url).map_err(|e| {
+                    InternalError::invalid_argument(
+                        &format!("Invalid next page URL {url}: {e}"),
+                        Some("paginator"),
+                    )
+                })?;
+                state.params.query_params = url.query_pairs().into_owned().collect();
+                let PlatformInfo::Api(config) = &mut state.definition.platform_info;
+                config.base_url = url.origin().ascii_serialization();
+                config.path = url.path().to_owned();
+                config.query_params = None;
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        api_model_config::{
+            AuthMethod, ModelPaths, ResponseModelPaths, SamplesInput, SchemasInput,
+        },
+        connection_model_definition::{CrudAction, TestConnection},
+        id::{prefix::IdPrefix, Id},
+    };
+    use http::{HeaderMap, Method};
+    use mockito::{Matcher, Server};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn definition(base_url: String, pagination: PaginationConfig) -> ConnectionModelDefinition {
+        ConnectionModelDefinition {
+            id: Id::now(IdPrefix::ConnectionModelDefinition),
+            connection_platform: "stripe".to_owned(),
+            connection_definition_id: Id::now(IdPrefix::ConnectionDefinition),
+            platform_version: "v1".to_owned(),
+            key: "api::stripe::v1::customer::getMany".to_owned(),
+            title: "List Customers".to_owned(),
+            name: "List Customers".to_owned(),
+            model_name: "Customer".to_owned(),
+            action: Method::GET,
+            action_name: CrudAction::GetMany,
+            platform_info: PlatformInfo::Api(ApiModelConfig {
+                base_url,
+                path: "/customers".to_owned(),
+                auth_method: AuthMethod::None,
+                headers: None,
+                query_params: None,
+                content: None,
+                schemas: SchemasInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                samples: SamplesInput {
+                    headers: None,
+                    query_params: None,
+                    path_params: None,
+                    body: None,
+                },
+                responses: vec![],
+                paths: Some(ModelPaths {
+                    request: None,
+                    response: Some(ResponseModelPaths {
+                        object: Some("$.data".to_owned()),
+                        id: None,
+                        cursor: None,
+                    }),
+                }),
+                pagination: Some(pagination),
+            }),
+            extractor_config: None,
+            test_connection_status: TestConnection::default(),
+            is_default_crud_mapping: None,
+            mapping: None,
+            record_metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_next_page() {
+        let query = HashMap::from([("page".to_owned(), "3".to_owned())]);
+        let headers = HeaderMap::new();
+        let page = PaginationConfig::PageNumber {
+            param: "page".to_owned(),
+            first_page: 1,
+        };
+        assert_eq!(
+            page.next_page(&query, &json!({}), &headers, 10),
+            Some(NextPage::Query(vec![("page".to_owned(), "4".to_owned())]))
+        );
+        assert_eq!(page.next_page(&query, &json!({}), &headers, 0), None);
+
+        let offset = PaginationConfig::Offset {
+            offset_param: "offset".to_owned(),
+            limit_param: "limit".to_owned(),
+            limit: 2,
+        };
+        assert_eq!(
+            offset.next_page(&HashMap::new(), &json!({}), &headers, 2),
+            Some(NextPage::Query(vec![
+                ("offset".to_owned(), "2".to_owned()),
+                ("limit".to_owned(), "2".to_owned())
+            ]))
+        );
+        assert_eq!(
+            offset.next_page(&HashMap::new(), &json!({}), &headers, 1),
+            None
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::LINK,
+            r#"<https://api.github.com/repos?page=1>; rel="prev", <https://api.github.com/repos?page=3>; rel="next""#
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            PaginationConfig::LinkHeader.next_page(&query, &json!({}), &headers, 1),
+            Some(NextPage::Url(
+                "https://api.github.com/repos?page=3".to_owned()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginator_follows_cursor() {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/customers")
+            .match_query(Matcher::Missing)
+            .with_body(r#"{"data":[{"id":1},{"id":2}],"next":"abc"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/customers")
+            .match_query(Matcher::UrlEncoded("cursor".into(), "abc".into()))
+            .with_body(r#"{"data":[{"id":3}],"next":null}"#)
+            .create_async()
+            .await;
+
+        let executor = ModelDefinitionExecutor::default();
+        let connection =
+            Connection::builder("stripe", Id::now(IdPrefix::ConnectionDefinition)).build();
+        let definition = definition(
+            server.url(),
+            PaginationConfig::Cursor {
+                param: "cursor".to_owned(),
+                cursor_path: "$.next".to_owned(),
+            },
+        );
+        let secret = json!({});
+        let items = Paginator::new(&executor, &connection, &definition, &secret)
+            .items(ExecutionParams::default())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            items,
+            vec![json!({"id":1}), json!({"id":2}), json!({"id":3})]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginator_follows_link_header() {
+        let mut server = Server::new_async().await;
+        let next = format!("<{}/v2/customers?page=2>; rel=\"next\"", server.url());
+        server
+            .mock("GET", "/customers")
+            .with_header("link", &next)
+            .with_body(r#"{"data":[{"id":1}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/v2/customers")
+            .match_query(Matcher::UrlEncoded("page".into(), "2".into()))
+            .with_body(r#"{"data":[{"id":2}]}"#)
+            .create_async()
+            .await;
+
+        let executor = ModelDefinitionExecutor::default();
+        let connection =
+            Connection::builder("stripe", Id::now(IdPrefix::ConnectionDefinition)).build();
+        let definition = definition(server.url(), PaginationConfig::LinkHeader);
+        let secret = json!({});
+        let items = Paginator::new(&executor, &connection, &definition, &secret)
+            .with_max_pages(5)
+            .items(ExecutionParams::default())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(items, vec![json!({"id":1}), json!({"id":2})]);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    prelude::plan::{OwnershipPlan, PlanLimit, PlanLimits, PlanTier},
+    IntegrationOSError, MongoStore,
+};
+use bson::doc;
+
+/// Looks up the limits of an ownership's plan, falling back to a default tier for
+/// ownerships that were never assigned one
+#[derive(Debug, Clone)]
+pub struct PlanResolver {
+    store: MongoStore<OwnershipPlan>,
+    default_tier: PlanTier,
+}
+
+impl PlanResolver {
+    pub fn new(store: MongoStore<OwnershipPlan>, default_tier: PlanTier) -> Self {
+        Self {
+            store,
+            default_tier,
+        }
+    }
+
+    pub async fn resolve(&self, buildable_id: &str) -> Result<PlanLimits, IntegrationOSError> {
+        let plan = self
+            .store
+            .get_one(doc! {
+                "ownership.buildableId": buildable_id,
+                "active": true,
+                "deleted": false,
+            })
+            .await?;
+        Ok(plan
+            .map(|plan| plan.limits())
+            .unwrap_or_else(|| PlanLimits::for_tier(self.default_tier)))
+    }
+
+    /// Checks that `requested` more units of `limit` fit in the ownership's plan given
+    /// what it already `used`, as metered by the caller
+    pub async fn enforce(
+        &self,
+        buildable_id: &str,
+        limit: PlanLimit,
+        used: u64,
+        requested: u64,
+    ) -> Result<PlanLimits, IntegrationOSError> {
+        let limits = self.resolve(buildable_id).await?;
+        limits.check(limit, used, requested)?;
+        Ok(limits)
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    prelude::projection::{Projection, ProjectionChange, SystemEvent},
+    IntegrationOSError, MongoStore,
+};
+use bson::doc;
+use mongodb::options::ReplaceOptions;
+use tracing::debug;
+
+/// Keeps the read model of a [`Projection`] up to date, one event at a time
+#[derive(Debug, Clone)]
+pub struct Projector<P: Projection> {
+    projection: P,
+    store: MongoStore<P::Model>,
+}
+
+impl<P: Projection> Projector<P> {
+    pub fn new(projection: P, store: MongoStore<P::Model>) -> Self {
+        Self { projection, store }
+    }
+
+    pub fn projection(&self) -> &P {
+        &self.projection
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<P::Model>, IntegrationOSError> {
+        self.store.get_one_by_id(key).await
+    }
+
+    pub async fn handle(&self, event: &SystemEvent) -> Result<(), IntegrationOSError> {
+        let Some(key) = self.projection.key(event) else {
+            return Ok(());
+        };
+        let current = self.get(&key).await?;
+        match self.projection.apply(current, event) {
+            ProjectionChange::Upsert(model) => {
+                self.store
+                    .collection
+                    .replace_one(
+                        doc! { "_id": &key },
+                        model,
+                        ReplaceOptions::builder().upsert(true).build(),
+                    )
+                    .await?;
+            }
+            ProjectionChange::Delete => {
+                self.store
+                    .collection
+                    .delete_one(doc! { "_id": &key }, None)
+                    .await?;
+            }
+            ProjectionChange::Unchanged => {}
+        }
+        debug!("Projected {event:?} into {} {key}", self.projection.name());
+        Ok(())
+    }
+
+    /// Applies the events in order, stopping at the first failure
+    pub async fn handle_all(&self, events: &[SystemEvent]) -> Result<(), IntegrationOSError> {
+        for event in events {
+            self.handle(event).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{CollectionDrift, IndexSpec, IntegrationOSError, SchemaDriftReport, Store};
+use bson::Document;
+use futures::TryStreamExt;
+use mongodb::{options::IndexOptions, Collection, Database, IndexModel};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Compares the indexes present in Mongo with the ones declared by
+/// [`Store::recommended_indexes`], optionally creating the missing ones and rebuilding the
+/// divergent ones. Extra indexes are only reported. Running it again once fixed is a no-op,
+/// so services can call it on every startup.
+pub struct SchemaReconciler {
+    database: Database,
+    stores: Vec<Store>,
+    auto_fix: bool,
+}
+
+impl SchemaReconciler {
+    pub fn new(database: Database) -> Self {
+        Self {
+            database,
+            stores: Store::indexed(),
+            auto_fix: false,
+        }
+    }
+
+    pub fn with_stores(mut self, stores: Vec<Store>) -> Self {
+        self.stores = stores;
+        self
+    }
+
+    pub fn with_auto_fix(mut self, auto_fix: bool) -> Self {
+        self.auto_fix = auto_fix;
+        self
+    }
+
+    async fn present_indexes(
+        collection: &Collection<Document>,
+    ) -> Result<Vec<IndexSpec>, IntegrationOSError> {
+        let indexes = match collection.list_indexes(None).await {
+            Ok(cursor) => cursor.try_collect::<Vec<_>>().await?,
+            // Listing the indexes of a collection that doesn't exist yet fails
+            Err(e) if matches!(*e.kind, mongodb::error::ErrorKind::Command(ref c) if c.code == 26) =>
+            {
+                vec![]
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(indexes.into_iter().map(to_spec).collect())
+    }
+
+    pub async fn reconcile(&self) -> Result<SchemaDriftReport, IntegrationOSError> {
+        let mut report = SchemaDriftReport::default();
+        for store in &self.stores {
+            let collection = self.database.collection::<Document>(&store.to_string());
+            let drift = CollectionDrift::compare(
+                store.to_string(),
+                &store.recommended_indexes(),
+                &Self::present_indexes(&collection).await?,
+            );
+            if !drift.is_clean() {
+                warn!(
+                    "Indexes of {} drifted: {} missing, {} extra, {} divergent",
+                    drift.collection,
+                    drift.missing.len(),
+                    drift.extra.len(),
+                    drift.divergent.len()
+                );
+            }
+
+            if self.auto_fix {
+                for divergence in &drift.divergent {
+                    collection
+                        .drop_index(divergence.actual.name.as_str(), None)
+                        .await?;
+                    collection
+                        .create_index(to_model(&divergence.expected), None)
+                        .await?;
+                    report
+                        .fixed
+                        .push(format!("{}.{}", drift.collection, divergence.expected.name));
+                }
+                for spec in &drift.missing {
+                    collection.create_index(to_model(spec), None).await?;
+                    report
+                        .fixed
+                        .push(format!("{}.{}", drift.collection, spec.name));
+                }
+            }
+            report.collections.push(drift);
+        }
+        if !report.fixed.is_empty() {
+            info!("Fixed indexes {}", report.fixed.join(", "));
+        }
+        Ok(report)
+    }
+}
+
+fn to_spec(model: IndexModel) -> IndexSpec {
+    let options = model.options.unwrap_or_default();
+    IndexSpec {
+        name: options.name.unwrap_or_default(),
+        keys: model.keys,
+        unique: options.unique.unwrap_or_default(),
+        sparse: options.sparse.unwrap_or_default(),
+        expire_after_secs: options.expire_after.map(|d| d.as_secs()),
+        partial_filter: options.partial_filter_expression,
+    }
+}
+
+fn to_model(spec: &IndexSpec) -> IndexModel {
+    let options = IndexOptions::builder()
+        .name(spec.name.clone())
+        .unique(spec.unique.then_some(true))
+        .sparse(spec.sparse.then_some(true))
+        .expire_after(spec.expire_after_secs.map(Duration::from_secs))
+        .partial_filter_expression(spec.partial_filter.clone())
+        .build();
+    IndexModel::builder()
+        .keys(spec.keys.clone())
+        .options(options)
+        .build()
+}
@@ -0,0 +1,268 @@
+use crate::{
+    prelude::{
+        connection::connection_model_schema::ConnectionModelSchema,
+        schema::json_schema::JsonSchema, shared::settings::Settings,
+    },
+    ApplicationError, HashExt, HashKecAlg, IntegrationOSError, InternalError, MongoStore,
+};
+use jsonschema::{Draft, JSONSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// One reason a payload doesn't match its schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value, empty for the payload itself
+    pub path: String,
+    /// JSON Pointer to the keyword the value fails, e.g. `/properties/email/type`
+    pub schema_path: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+struct CompiledSchema {
+    hash: String,
+    schema: Arc<JSONSchema>,
+}
+
+/// Stored schemas as draft 2020-12 JSON Schemas. The stored format writes unset keywords
+/// as `null` and keeps properties in a map without order, so nulls are dropped and keys
+/// sorted to get a valid schema with a stable hash.
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries = object
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, normalize(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize).collect()),
+        value => value,
+    }
+}
+
+/// Validates payloads against the schemas of connection models, see
+/// [`ConnectionModelSchema::schema`].
+///
+/// Compiled schemas are cached by schema id along with the hash of the schema they were
+/// compiled from, so a schema is only compiled again once it changed.
+#[derive(Debug, Clone)]
+pub struct SchemaValidator {
+    store: MongoStore<ConnectionModelSchema>,
+    compiled: Arc<RwLock<HashMap<String, CompiledSchema>>>,
+}
+
+impl SchemaValidator {
+    pub fn new(store: MongoStore<ConnectionModelSchema>) -> Self {
+        Self {
+            store,
+            compiled: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn compile(schema: &JsonSchema) -> Result<JSONSchema, IntegrationOSError> {
+        let value = normalize(schema.to_value()?);
+        JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(&value)
+            .map_err(|e| {
+                InternalError::invalid_argument(
+                    &format!("Invalid JSON schema: {e}"),
+                    Some("schema_validator"),
+                )
+            })
+    }
+
+    /// Every violation of `schema` by `value`, empty when it matches
+    pub fn violations(schema: &JSONSchema, value: &Value) -> Vec<SchemaViolation> {
+        match schema.validate(value) {
+            Ok(()) => vec![],
+            Err(errors) => errors
+                .map(|error| SchemaViolation {
+                    path: error.instance_path.to_string(),
+                    schema_path: error.schema_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn hash(schema: &JsonSchema) -> Result<String, IntegrationOSError> {
+        HashKecAlg.hash(&normalize(schema.to_value()?).to_string())
+    }
+
+    /// Compiled version of a stored schema, compiling it when missing or outdated
+    pub fn compiled(
+        &self,
+        schema: &ConnectionModelSchema,
+    ) -> Result<Arc<JSONSchema>, IntegrationOSError> {
+        let id = schema.id.to_string();
+        let hash = Self::hash(&schema.schema)?;
+        if let Some(compiled) = self
+            .compiled
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&id)
+            .filter(|compiled| compiled.hash == hash)
+        {
+            return Ok(compiled.schema.clone());
+        }
+
+        let compiled = Arc::new(Self::compile(&schema.schema)?);
+        self.compiled
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(
+                id,
+                CompiledSchema {
+                    hash,
+                    schema: compiled.clone(),
+                },
+            );
+        Ok(compiled)
+    }
+
+    /// Violations of the schema stored under `schema_id` by `value`. Unknown schemas are
+    /// `NotFound`.
+    pub async fn validate_payload(
+        &self,
+        schema_id: &str,
+        value: &Value,
+    ) -> Result<Vec<SchemaViolation>, IntegrationOSError> {
+        let schema = self.store.get_one_by_id(schema_id).await?.ok_or_else(|| {
+            ApplicationError::not_found(
+                &format!("Connection model schema {schema_id}"),
+                Some("schema_validator"),
+            )
+        })?;
+        Ok(Self::violations(&*self.compiled(&schema)?, value))
+    }
+
+    /// Same as [`SchemaValidator::validate_payload`], `UnprocessableEntity` listing the
+    /// violations when there are some
+    pub async fn ensure_valid_payload(
+        &self,
+        schema_id: &str,
+        value: &Value,
+    ) -> Result<(), IntegrationOSError> {
+        let violations = self.validate_payload(schema_id, value).await?;
+        Self::enforce(violations, true).map(|_| ())
+    }
+
+    /// Validates the payload of a connection with `settings`: with
+    /// [`Settings::strict_schema_validation`] violations are an `UnprocessableEntity`,
+    /// otherwise they are returned for the caller to report
+    pub async fn check_payload(
+        &self,
+        schema_id: &str,
+        value: &Value,
+        settings: &Settings,
+    ) -> Result<Vec<SchemaViolation>, IntegrationOSError> {
+        let violations = self.validate_payload(schema_id, value).await?;
+        Self::enforce(violations, settings.strict_schema_validation)
+    }
+
+    fn enforce(
+        violations: Vec<SchemaViolation>,
+        strict: bool,
+    ) -> Result<Vec<SchemaViolation>, IntegrationOSError> {
+        if !strict || violations.is_empty() {
+            return Ok(violations);
+        }
+        let message = violations
+            .iter()
+            .map(|violation| format!("{}: {}", violation.path, violation.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(ApplicationError::unprocessable_entity(
+            &message,
+            Some("schema_validator"),
+        ))
+    }
+
+    pub fn invalidate(&self, schema_id: &str) {
+        self.compiled
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(schema_id);
+    }
+
+    pub fn clear(&self) {
+        self.compiled
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_violations_point_at_invalid_fields() {
+        let schema = JsonSchema::from_value(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "email": { "type": "string", "path": "$.email" },
+                "address": {
+                    "type": "object",
+                    "properties": { "zip": { "type": "string" } }
+                }
+            },
+            "required": ["id"],
+            "path": null
+        }))
+        .unwrap();
+        let compiled = SchemaValidator::compile(&schema).unwrap();
+
+        assert!(SchemaValidator::violations(
+            &compiled,
+            &json!({ "id": "cus_1", "email": "a@b.c" })
+        )
+        .is_empty());
+
+        let mut violations = SchemaValidator::violations(
+            &compiled,
+            &json!({ "email": 1, "address": { "zip": 75001 } }),
+        );
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            violations
+                .iter()
+                .map(|v| (v.path.as_str(), v.schema_path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("", "/required"),
+                ("/address/zip", "/properties/address/properties/zip/type"),
+                ("/email", "/properties/email/type"),
+            ]
+        );
+        assert_eq!(
+            SchemaValidator::hash(&schema).unwrap(),
+            SchemaValidator::hash(&schema.clone()).unwrap()
+        );
+
+        assert_eq!(
+            SchemaValidator::enforce(violations.clone(), false).unwrap(),
+            violations
+        );
+        let error = SchemaValidator::enforce(violations, true).unwrap_err();
+        assert!(error.to_string().contains("/email"));
+        assert!(SchemaValidator::enforce(vec![], true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    prelude::{connection::Connection, get_secret_request::GetSecretRequest},
+    ApplicationError, CryptoExt, Id, IntegrationOSError, MongoStore,
+};
+use bson::doc;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationFailure {
+    pub connection_id: Id,
+    pub error: String,
+}
+
+/// Progress of a batch rotation, handed to the progress callback after every connection
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationReport {
+    pub total: usize,
+    pub rotated: usize,
+    pub failures: Vec<RotationFailure>,
+}
+
+impl RotationReport {
+    pub fn processed(&self) -> usize {
+        self.rotated + self.failures.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.processed() == self.total
+    }
+}
+
+/// Re-encrypts connection secrets under a new key and points the connections to them
+pub struct SecretRotation<C> {
+    crypto: C,
+    connections: MongoStore<Connection>,
+}
+
+impl<C: CryptoExt + Sync> SecretRotation<C> {
+    pub fn new(crypto: C, connections: MongoStore<Connection>) -> Self {
+        Self {
+            crypto,
+            connections,
+        }
+    }
+
+    /// Rotates the secret of a connection from `old_key` to `new_key` and returns the new
+    /// secret id. Fails with a conflict when the connection's secret changed meanwhile.
+    pub async fn rotate_connection(
+        &self,
+        connection: &Connection,
+        old_key: &str,
+        new_key: &str,
+    ) -> Result<String, IntegrationOSError> {
+        let secret = GetSecretRequest {
+            id: connection.secrets_service_id.clone(),
+            buildable_id: old_key.to_owned(),
+        };
+        let rotated = self.crypto.rotate(&secret, new_key.to_owned()).await?;
+
+        let result = self
+            .connections
+            .collection
+            .update_one(
+                doc! {
+                    "_id": connection.id.to_string(),
+                    "secretsServiceId": &connection.secrets_service_id,
+                },
+                doc! {
+                    "$set": {
+                        "secretsServiceId": &rotated.id,
+                        "updatedAt": Utc::now().timestamp_millis(),
+                    },
+                },
+                None,
+            )
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ApplicationError::conflict(
+                &format!(
+                    "Secret of connection {} changed concurrently",
+                    connection.id
+                ),
+                Some("secret_rotation"),
+            ));
+        }
+        Ok(rotated.id)
+    }
+
+    /// Rotates the secrets of every connection of `platform`. Secrets are read with the
+    /// connection's buildable id and re-encrypted under the key `new_key` gives for it.
+    /// Failures are recorded and don't stop the batch.
+    pub async fn rotate_platform(
+        &self,
+        platform: &str,
+        new_key: impl Fn(&Connection) -> String,
+        mut on_progress: impl FnMut(&RotationReport),
+    ) -> Result<RotationReport, IntegrationOSError> {
+        let connections = self
+            .connections
+            .get_many(
+                Some(doc! { "platform": platform, "deleted": false }),
+                None,
+                Some(doc! { "_id": 1 }),
+                None,
+                None,
+            )
+            .await?;
+
+        let mut report = RotationReport {
+            total: connections.len(),
+            ..Default::default()
+        };
+        for connection in &connections {
+            let old_key = connection.ownership.id.to_string();
+            match self
+                .rotate_connection(connection, &old_key, &new_key(connection))
+                .await
+            {
+                Ok(_) => report.rotated += 1,
+                Err(e) => {
+                    error!(
+                        "Could not rotate secret of connection {}: {e}",
+                        connection.id
+                    );
+                    report.failures.push(RotationFailure {
+                        connection_id: connection.id,
+                        error: e.to_string(),
+                    });
+                }
+            }
+            on_progress(&report);
+        }
+
+        info!(
+            "Rotated {} of {} secrets for platform {platform}",
+            report.rotated, report.total
+        );
+        Ok(report)
+    }
+}
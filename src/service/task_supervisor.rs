@@ -0,0 +1,167 @@
+use crate::IntegrationOSError;
+use std::{future::Future, time::Duration};
+use tokio::{task::JoinHandle, time::Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Keeps background loops running until shut down.
+///
+/// A supervised task gets a [`CancellationToken`] it is expected to return on once
+/// cancelled. It is restarted with exponential backoff when it panics or fails, and
+/// stays stopped once it returns `Ok`. Dropping the supervisor cancels its tasks without
+/// waiting for them, [`TaskSupervisor::shutdown`] also joins them.
+pub struct TaskSupervisor {
+    token: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    grace_period: Duration,
+}
+
+impl Default for TaskSupervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            tasks: vec![],
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            grace_period: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// How long a cancelled task may take to return before it is aborted
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Runs the task built by `task` under supervision, building a new one on each restart
+    pub fn spawn<F, Fut>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: Fn(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), IntegrationOSError>> + Send + 'static,
+    {
+        let name = name.into();
+        let token = self.token.clone();
+        let (initial_backoff, max_backoff, grace_period) =
+            (self.initial_backoff, self.max_backoff, self.grace_period);
+
+        self.tasks.push(tokio::spawn(async move {
+            let mut backoff = initial_backoff;
+            while !token.is_cancelled() {
+                let started = Instant::now();
+                let mut handle = tokio::spawn(task(token.child_token()));
+                let result = tokio::select! {
+                    result = &mut handle => result,
+                    _ = token.cancelled() => {
+                        match tokio::time::timeout(grace_period, &mut handle).await {
+                            Ok(result) => result,
+                            Err(_) => {
+                                warn!("Task {name} did not stop within its grace period, aborting it");
+                                handle.abort();
+                                return;
+                            }
+                        }
+                    }
+                };
+                match result {
+                    Ok(Ok(())) => {
+                        info!("Task {name} finished");
+                        return;
+                    }
+                    Ok(Err(e)) => error!("Task {name} failed: {e}"),
+                    Err(e) if e.is_panic() => error!("Task {name} panicked"),
+                    Err(e) => error!("Task {name} stopped: {e}"),
+                }
+                if token.is_cancelled() {
+                    return;
+                }
+
+                // A task that ran for a while before failing starts over from the initial delay
+                if started.elapsed() > max_backoff {
+                    backoff = initial_backoff;
+                }
+                warn!("Restarting task {name} in {}ms", backoff.as_millis());
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = token.cancelled() => return,
+                }
+                backoff = (backoff * 2).min(max_backoff);
+            }
+        }));
+    }
+
+    /// Cancels every task and waits for them to stop
+    pub async fn shutdown(mut self) {
+        self.token.cancel();
+        for task in self.tasks.drain(..) {
+            if let Err(e) = task.await {
+                error!("Supervisor task stopped abnormally: {e}");
+            }
+        }
+    }
+}
+
+impl Drop for TaskSupervisor {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::InternalError;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    #[tokio::test]
+    async fn test_failed_tasks_restart_until_shutdown() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let mut supervisor =
+            TaskSupervisor::new().with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+        let counter = runs.clone();
+        supervisor.spawn("flaky", move |token| {
+            let counter = counter.clone();
+            async move {
+                match counter.fetch_add(1, Ordering::SeqCst) {
+                    0 => panic!("first run panics"),
+                    1 => Err(InternalError::io_err("second run fails", None)),
+                    _ => {
+                        token.cancelled().await;
+                        Ok(())
+                    }
+                }
+            }
+        });
+
+        while runs.load(Ordering::SeqCst) < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        supervisor.shutdown().await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
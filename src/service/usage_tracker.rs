@@ -0,0 +1,256 @@
+use crate::{
+    configuration::llm::{LlmBudget, LlmProvider},
+    id::Id,
+    prelude::{
+        llm_usage::{estimate_tokens, usage_period, BudgetExceeded, LlmUsageRecord},
+        shared::ownership::Ownership,
+    },
+    ChatRequest, ChatResponse, IntegrationOSError, InternalError, LlmExt, LlmUsage, MongoStore,
+    TokenStream,
+};
+use async_trait::async_trait;
+use bson::doc;
+use chrono::Utc;
+use futures::StreamExt;
+use mongodb::options::UpdateOptions;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tracing::warn;
+
+/// Accounts the tokens LLM calls use, per ownership and connection and per calendar
+/// month, and holds calls back once a monthly [`LlmBudget`] is used up
+#[derive(Debug, Clone)]
+pub struct UsageTracker {
+    store: MongoStore<LlmUsageRecord>,
+    budget: LlmBudget,
+    overrides: HashMap<String, LlmBudget>,
+}
+
+impl UsageTracker {
+    pub fn new(store: MongoStore<LlmUsageRecord>, budget: LlmBudget) -> Self {
+        Self {
+            store,
+            budget,
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Budget of an ownership in place of the default one
+    pub fn with_budget(mut self, buildable_id: impl Into<String>, budget: LlmBudget) -> Self {
+        self.overrides.insert(buildable_id.into(), budget);
+        self
+    }
+
+    pub fn budget(&self, buildable_id: &str) -> LlmBudget {
+        self.overrides
+            .get(buildable_id)
+            .copied()
+            .unwrap_or(self.budget)
+    }
+
+    /// Usage over the current month, zero when nothing was recorded yet
+    pub async fn usage(
+        &self,
+        ownership: &Ownership,
+        connection_id: Option<&Id>,
+    ) -> Result<LlmUsageRecord, IntegrationOSError> {
+        let period = usage_period(Utc::now());
+        let id = LlmUsageRecord::id_for(&ownership.id, connection_id, &period);
+        Ok(self
+            .store
+            .get_one_by_id(&id)
+            .await?
+            .unwrap_or_else(|| LlmUsageRecord {
+                id,
+                ownership: ownership.clone(),
+                connection_id: connection_id.copied(),
+                period,
+                input_tokens: 0,
+                output_tokens: 0,
+                requests: 0,
+                updated_at: 0,
+            }))
+    }
+
+    /// The budget that `requested` more tokens would go over, if any
+    pub async fn check(
+        &self,
+        ownership: &Ownership,
+        connection_id: Option<&Id>,
+        requested: u64,
+    ) -> Result<Option<BudgetExceeded>, IntegrationOSError> {
+        let budget = self.budget(&ownership.id);
+        if budget == LlmBudget::default() {
+            return Ok(None);
+        }
+        let total = self.usage(ownership, None).await?;
+        let connection = match connection_id {
+            Some(connection_id) if budget.connection_monthly_tokens.is_some() => {
+                Some(self.usage(ownership, Some(connection_id)).await?)
+            }
+            _ => None,
+        };
+        Ok(budget.check(&total, connection.as_ref(), requested).err())
+    }
+
+    /// Same as [`UsageTracker::check`], failing with the [`BudgetExceeded`] error
+    pub async fn enforce(
+        &self,
+        ownership: &Ownership,
+        connection_id: Option<&Id>,
+        requested: u64,
+    ) -> Result<(), IntegrationOSError> {
+        match self.check(ownership, connection_id, requested).await? {
+            Some(exceeded) => Err(exceeded.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the tokens of a call to the month's usage of the ownership, and of the
+    /// connection when given
+    pub async fn record(
+        &self,
+        ownership: &Ownership,
+        connection_id: Option<&Id>,
+        usage: LlmUsage,
+    ) -> Result<(), IntegrationOSError> {
+        let period = usage_period(Utc::now());
+        let ownership_bson = bson::to_bson(ownership)
+            .map_err(|e| InternalError::serialize_error(&e.to_string(), Some("usage_tracker")))?;
+        let scopes = std::iter::once(None).chain(connection_id.map(Some));
+        for connection_id in scopes {
+            let mut on_insert = doc! {
+                "ownership": ownership_bson.clone(),
+                "period": &period,
+            };
+            if let Some(connection_id) = connection_id {
+                on_insert.insert("connectionId", connection_id.to_string());
+            }
+            self.store
+                .collection
+                .update_one(
+                    doc! { "_id": LlmUsageRecord::id_for(&ownership.id, connection_id, &period) },
+                    doc! {
+                        "$setOnInsert": on_insert,
+                        "$inc": {
+                            "inputTokens": usage.input_tokens as i64,
+                            "outputTokens": usage.output_tokens as i64,
+                            "requests": 1_i64,
+                        },
+                        "$set": { "updatedAt": Utc::now().timestamp_millis() },
+                    },
+                    UpdateOptions::builder().upsert(true).build(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// LLM client whose calls are held to the budgets of `ownership` and recorded
+    pub fn meter(
+        &self,
+        llm: Arc<dyn LlmExt>,
+        ownership: Ownership,
+        connection_id: Option<Id>,
+    ) -> MeteredLlm {
+        MeteredLlm {
+            llm,
+            tracker: self.clone(),
+            ownership,
+            connection_id,
+        }
+    }
+}
+
+fn estimate_request(request: &ChatRequest) -> u64 {
+    request
+        .messages
+        .iter()
+        .map(|message| estimate_tokens(&message.content))
+        .sum()
+}
+
+/// [`LlmExt`] calling another on behalf of an ownership through a [`UsageTracker`].
+/// Streamed and embedding calls have no usage reported, so theirs is estimated from the
+/// text.
+#[derive(Clone)]
+pub struct MeteredLlm {
+    llm: Arc<dyn LlmExt>,
+    tracker: UsageTracker,
+    ownership: Ownership,
+    connection_id: Option<Id>,
+}
+
+impl MeteredLlm {
+    async fn enforce(&self, requested: u64) -> Result<(), IntegrationOSError> {
+        self.tracker
+            .enforce(&self.ownership, self.connection_id.as_ref(), requested)
+            .await
+    }
+
+    async fn record(&self, usage: LlmUsage) -> Result<(), IntegrationOSError> {
+        self.tracker
+            .record(&self.ownership, self.connection_id.as_ref(), usage)
+            .await
+    }
+}
+
+#[async_trait]
+impl LlmExt for MeteredLlm {
+    fn provider(&self) -> LlmProvider {
+        self.llm.provider()
+    }
+
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, IntegrationOSError> {
+        self.enforce(estimate_request(&request)).await?;
+        let response = self.llm.chat(request).await?;
+        self.record(response.usage).await?;
+        Ok(response)
+    }
+
+    async fn chat_stream(&self, request: ChatRequest) -> Result<TokenStream, IntegrationOSError> {
+        let input_tokens = estimate_request(&request);
+        self.enforce(input_tokens).await?;
+        let stream = self.llm.chat_stream(request).await?;
+
+        let output_tokens = Arc::new(AtomicU64::new(0));
+        let counted = output_tokens.clone();
+        let metered = self.clone();
+        let recorded = futures::stream::once(async move {
+            let usage = LlmUsage {
+                input_tokens,
+                output_tokens: output_tokens.load(Ordering::Relaxed),
+            };
+            if let Err(e) = metered.record(usage).await {
+                warn!("Could not record LLM usage: {e}");
+            }
+        })
+        .filter_map(|_| async { None });
+
+        Ok(stream
+            .inspect(move |token| {
+                if let Ok(token) = token {
+                    counted.fetch_add(estimate_tokens(token), Ordering::Relaxed);
+                }
+            })
+            .chain(recorded)
+            .boxed())
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, IntegrationOSError> {
+        let input_tokens = inputs.iter().map(|input| estimate_tokens(input)).sum();
+        self.enforce(input_tokens).await?;
+        let embeddings = self.llm.embed(inputs).await?;
+        self.record(LlmUsage {
+            input_tokens,
+            output_tokens: 0,
+        })
+        .await?;
+        Ok(embeddings)
+    }
+}
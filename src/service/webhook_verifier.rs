@@ -0,0 +1,287 @@
+use crate::{
+    algebra::unhex,
+    prelude::{
+        access_key::{encrypted_access_key::EncryptedAccessKey, AccessKey},
+        connection::webhook_definition::{
+            EventTypeSource, SignatureAlgorithm, SignatureEncoding, SignatureScheme,
+            WebhookDefinition,
+        },
+    },
+    ApplicationError, Event, IntegrationOSError, InternalError, SignatureError, Signer,
+};
+use base64ct::{Base64, Encoding};
+use hmac::{Hmac, Mac};
+use http::HeaderMap;
+use serde_json::Value;
+use sha2::{Sha256, Sha512};
+
+/// Checks incoming platform webhooks against their [`WebhookDefinition`] and turns the
+/// genuine ones into [`Event`]s
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebhookVerifier;
+
+fn raw_signature_matches(
+    algorithm: SignatureAlgorithm,
+    secret: &[u8],
+    body: &[u8],
+    signature: &[u8],
+) -> bool {
+    match algorithm {
+        SignatureAlgorithm::HmacSha256 => Hmac::<Sha256>::new_from_slice(secret)
+            .map(|mac| mac.chain_update(body).verify_slice(signature).is_ok())
+            .unwrap_or_default(),
+        SignatureAlgorithm::HmacSha512 => Hmac::<Sha512>::new_from_slice(secret)
+            .map(|mac| mac.chain_update(body).verify_slice(signature).is_ok())
+            .unwrap_or_default(),
+    }
+}
+
+impl WebhookVerifier {
+    /// Verifies the signature of `body` with the signing secret found in the connection's
+    /// decrypted `secret`, at `now` (epoch seconds)
+    pub fn verify(
+        &self,
+        definition: &WebhookDefinition,
+        secret: &Value,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: i64,
+    ) -> Result<(), IntegrationOSError> {
+        let signing_secret = secret
+            .get(&definition.secret_key)
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                InternalError::key_not_found(
+                    &format!("{} in connection secret", definition.secret_key),
+                    Some("webhook"),
+                )
+            })?;
+        let header = headers
+            .get(&definition.signature_header)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                SignatureError::Malformed(format!("missing {} header", definition.signature_header))
+            })?;
+
+        match &definition.scheme {
+            SignatureScheme::Raw { encoding, prefix } => {
+                let signature = match prefix {
+                    Some(prefix) => header.strip_prefix(prefix.as_str()).ok_or_else(|| {
+                        SignatureError::Malformed(format!("signature does not start with {prefix}"))
+                    })?,
+                    None => header,
+                };
+                let signature = match encoding {
+                    SignatureEncoding::Hex => unhex(signature),
+                    SignatureEncoding::Base64 => Base64::decode_vec(signature).ok(),
+                }
+                .ok_or_else(|| SignatureError::Malformed("undecodable signature".to_owned()))?;
+
+                if raw_signature_matches(
+                    definition.algorithm,
+                    signing_secret.as_bytes(),
+                    body,
+                    &signature,
+                ) {
+                    Ok(())
+                } else {
+                    Err(SignatureError::Invalid.into())
+                }
+            }
+            SignatureScheme::Timestamped { tolerance_secs } => {
+                if definition.algorithm != SignatureAlgorithm::HmacSha256 {
+                    return Err(InternalError::configuration_error(
+                        &format!(
+                            "Timestamped webhook signatures only support {}, not {}",
+                            SignatureAlgorithm::HmacSha256,
+                            definition.algorithm
+                        ),
+                        Some("webhook"),
+                    ));
+                }
+                Ok(Signer::new(signing_secret, *tolerance_secs).verify(header, body, now)?)
+            }
+        }
+    }
+
+    /// Name of the platform's event, used as the name of the [`Event`]
+    pub fn event_type(
+        &self,
+        definition: &WebhookDefinition,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<String, IntegrationOSError> {
+        let event_type = match &definition.event_type {
+            EventTypeSource::Header { name } => headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+            EventTypeSource::Body { path } => {
+                let body: Value = serde_json::from_slice(body).map_err(|e| {
+                    ApplicationError::bad_request(
+                        &format!("Webhook body is not JSON: {e}"),
+                        Some("webhook"),
+                    )
+                })?;
+                jsonpath_lib::select(&body, path)
+                    .map_err(|e| {
+                        InternalError::configuration_error(&e.to_string(), Some("webhook"))
+                    })?
+                    .into_iter()
+                    .next()
+                    .and_then(Value::as_str)
+                    .map(str::to_owned)
+            }
+        };
+        event_type.ok_or_else(|| {
+            ApplicationError::bad_request(
+                &format!(
+                    "Could not find the event type of a {} webhook",
+                    definition.connection_platform
+                ),
+                Some("webhook"),
+            )
+        })
+    }
+
+    /// Verifies a webhook and maps it to an event of the connection's access key
+    #[allow(clippy::too_many_arguments)]
+    pub fn to_event(
+        &self,
+        definition: &WebhookDefinition,
+        secret: &Value,
+        access_key: &AccessKey,
+        encrypted_access_key: &EncryptedAccessKey,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: i64,
+    ) -> Result<Event, IntegrationOSError> {
+        self.verify(definition, secret, headers, body, now)?;
+        let event_type = self.event_type(definition, headers, body)?;
+        let body = String::from_utf8(body.to_vec())
+            .map_err(|e| ApplicationError::bad_request(&e.to_string(), Some("webhook")))?;
+        Ok(Event::new(
+            access_key,
+            encrypted_access_key,
+            &event_type,
+            headers.clone(),
+            body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        id::{prefix::IdPrefix, Id},
+        prelude::{
+            access_key::{
+                access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+                event_type::EventType,
+            },
+            configuration::environment::Environment,
+        },
+    };
+    use http::StatusCode;
+    use serde_json::json;
+
+    fn shopify() -> WebhookDefinition {
+        WebhookDefinition::new(
+            "shopify".to_owned(),
+            Id::now(IdPrefix::ConnectionDefinition),
+            "X-Shopify-Hmac-Sha256".to_owned(),
+            SignatureScheme::Raw {
+                encoding: SignatureEncoding::Base64,
+                prefix: None,
+            },
+            "WEBHOOK_SECRET".to_owned(),
+            EventTypeSource::Header {
+                name: "X-Shopify-Topic".to_owned(),
+            },
+        )
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .unwrap()
+            .chain_update(body);
+        Base64::encode_string(&mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_raw_signature_to_event() {
+        let body = br#"{"id":1}"#;
+        let secret = json!({ "WEBHOOK_SECRET": "shpss_123" });
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Shopify-Hmac-Sha256",
+            sign("shpss_123", body).parse().unwrap(),
+        );
+        headers.insert("X-Shopify-Topic", "orders/create".parse().unwrap());
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Live, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "build-1".to_owned(),
+                event_type: "shopify".to_owned(),
+                group: "webhooks".to_owned(),
+                ..Default::default()
+            },
+        };
+
+        let event = WebhookVerifier
+            .to_event(
+                &shopify(),
+                &secret,
+                &access_key,
+                &EncryptedAccessKey::parse("id_live_1_foo").unwrap(),
+                &headers,
+                body,
+                0,
+            )
+            .unwrap();
+        assert_eq!(event.name, "orders/create");
+        assert_eq!(event.body, r#"{"id":1}"#);
+
+        let err = WebhookVerifier
+            .verify(&shopify(), &secret, &headers, br#"{"id":2}"#, 0)
+            .unwrap_err();
+        assert_eq!(StatusCode::from(&err), StatusCode::UNAUTHORIZED);
+        assert!(WebhookVerifier
+            .verify(&shopify(), &json!({}), &headers, body, 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_timestamped_signature() {
+        let mut stripe = shopify();
+        stripe.signature_header = "Stripe-Signature".to_owned();
+        stripe.scheme = SignatureScheme::Timestamped {
+            tolerance_secs: 300,
+        };
+        stripe.event_type = EventTypeSource::Body {
+            path: "$.type".to_owned(),
+        };
+        let body = br#"{"type":"invoice.paid"}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Stripe-Signature",
+            Signer::new("whsec_1", 300)
+                .sign(body, 1_700_000_000)
+                .parse()
+                .unwrap(),
+        );
+        let secret = json!({ "WEBHOOK_SECRET": "whsec_1" });
+
+        assert!(WebhookVerifier
+            .verify(&stripe, &secret, &headers, body, 1_700_000_100)
+            .is_ok());
+        assert!(WebhookVerifier
+            .verify(&stripe, &secret, &headers, body, 1_700_001_000)
+            .is_err());
+        assert_eq!(
+            WebhookVerifier.event_type(&stripe, &headers, body).unwrap(),
+            "invoice.paid"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    id::{prefix::IdPrefix, Id},
+    prelude::{
+        connection::connection_oauth_definition::ConnectionOAuthDefinition,
+        schema::json_schema::JsonSchema,
+    },
+    Platform,
+};
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureData {
+    customers: Vec<Value>,
+    orders: Vec<Value>,
+    schemas: HashMap<String, JsonSchema>,
+    oauth_definition: Value,
+}
+
+/// Canned data of a platform: sample Customers and Orders payloads as the platform returns
+/// them, the schemas they match and the platform's OAuth definition. Everything is built
+/// from the JSON under `src/testkit/fixtures`, so it is identical across runs and repos.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    platform: Platform,
+    data: FixtureData,
+}
+
+impl Fixture {
+    /// Platforms with fixtures
+    pub fn platforms() -> [Platform; 3] {
+        [Platform::Stripe, Platform::Shopify, Platform::Xero]
+    }
+
+    pub fn try_platform(platform: Platform) -> Option<Self> {
+        let json = match platform {
+            Platform::Stripe => include_str!("fixtures/stripe.json"),
+            Platform::Shopify => include_str!("fixtures/shopify.json"),
+            Platform::Xero => include_str!("fixtures/xero.json"),
+            _ => return None,
+        };
+        let data = serde_json::from_str(json)
+            .unwrap_or_else(|e| panic!("Invalid {platform} fixture: {e}"));
+        Some(Self { platform, data })
+    }
+
+    /// Fixtures of a platform, panicking for platforms not listed in [`Fixture::platforms`]
+    pub fn platform(platform: Platform) -> Self {
+        Self::try_platform(platform)
+            .unwrap_or_else(|| panic!("No fixtures for platform {platform}"))
+    }
+
+    pub fn connection_platform(&self) -> &str {
+        self.platform.as_ref()
+    }
+
+    pub fn customers(&self) -> &[Value] {
+        &self.data.customers
+    }
+
+    pub fn orders(&self) -> &[Value] {
+        &self.data.orders
+    }
+
+    /// Sample payloads of a common model, e.g. `Customers`
+    pub fn records(&self, common_model: &str) -> &[Value] {
+        match common_model.to_lowercase().as_str() {
+            "customers" => self.customers(),
+            "orders" => self.orders(),
+            _ => &[],
+        }
+    }
+
+    /// Schema the sample payloads of a common model match
+    pub fn schema(&self, common_model: &str) -> Option<&JsonSchema> {
+        self.data.schemas.get(&common_model.to_lowercase())
+    }
+
+    /// OAuth definition of the platform, with an id derived from the platform name
+    pub fn oauth_definition(&self) -> ConnectionOAuthDefinition {
+        let seed = self
+            .connection_platform()
+            .bytes()
+            .fold(0_u128, |seed, byte| {
+                seed.wrapping_mul(31).wrapping_add(byte as u128)
+            });
+        let id = Id::new_with_uuid(
+            IdPrefix::ConnectionOAuthDefinition,
+            Utc.timestamp_millis_opt(1_700_000_000_000)
+                .single()
+                .unwrap_or_default(),
+            Uuid::from_u128(seed),
+        );
+        let mut definition = self.data.oauth_definition.clone();
+        definition["_id"] = Value::String(id.to_string());
+        serde_json::from_value(definition)
+            .unwrap_or_else(|e| panic!("Invalid {} OAuth fixture: {e}", self.platform))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_match_their_schemas() {
+        for platform in Fixture::platforms() {
+            let fixture = Fixture::platform(platform);
+            for model in ["Customers", "Orders"] {
+                let schema = fixture.schema(model).unwrap();
+                let records = fixture.records(model);
+                assert!(!records.is_empty());
+                for record in records {
+                    for field in schema.required.iter().flatten() {
+                        assert!(record.get(field).is_some(), "{platform} {model} {field}");
+                    }
+                    for field in record.as_object().unwrap().keys() {
+                        assert!(
+                            schema.properties.contains_key(field),
+                            "{platform} {model} {field}"
+                        );
+                    }
+                }
+            }
+
+            let definition = fixture.oauth_definition();
+            assert_eq!(
+                definition.connection_platform,
+                fixture.connection_platform()
+            );
+            assert_eq!(definition.id, fixture.oauth_definition().id);
+        }
+        assert!(Fixture::try_platform(Platform::Sage).is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    prelude::{
+        access_key::{
+            access_key_data::AccessKeyData, access_key_prefix::AccessKeyPrefix,
+            encrypted_access_key::EncryptedAccessKey, event_type::EventType, AccessKey,
+        },
+        configuration::environment::Environment,
+        context::root_context::{RootContext, RootStage},
+        event::{event_with_context::EventWithContext, Event},
+    },
+    PipelineStatus,
+};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use http::HeaderMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// Manually advanced clock shared by everything taking part in a simulation
+#[derive(Debug, Clone)]
+pub struct SimClock(Arc<AtomicI64>);
+
+impl SimClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self(Arc::new(AtomicI64::new(start.timestamp_millis())))
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.0.load(Ordering::SeqCst))
+            .single()
+            .unwrap_or_default()
+    }
+
+    pub fn advance(&self, by: Duration) -> DateTime<Utc> {
+        let now = self.0.fetch_add(by.num_milliseconds(), Ordering::SeqCst) + by.num_milliseconds();
+        Utc.timestamp_millis_opt(now).single().unwrap_or_default()
+    }
+
+    pub fn set(&self, to: DateTime<Utc>) {
+        self.0.store(to.timestamp_millis(), Ordering::SeqCst);
+    }
+}
+
+/// Relative weights of the stage generated contexts are left in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StageDistribution {
+    pub new: f64,
+    pub verified: f64,
+    pub processed_duplicates: f64,
+    pub finished: f64,
+    pub dropped: f64,
+}
+
+impl Default for StageDistribution {
+    fn default() -> Self {
+        Self {
+            new: 0.05,
+            verified: 0.05,
+            processed_duplicates: 0.1,
+            finished: 0.75,
+            dropped: 0.05,
+        }
+    }
+}
+
+impl StageDistribution {
+    fn sample(&self, rng: &mut StdRng) -> (RootStage, PipelineStatus) {
+        let weights = [
+            self.new,
+            self.verified,
+            self.processed_duplicates,
+            self.finished,
+            self.dropped,
+        ];
+        let total: f64 = weights.iter().sum();
+        let mut roll = rng.gen_range(0.0..total.max(f64::EPSILON));
+        let index = weights
+            .iter()
+            .position(|weight| {
+                roll -= weight;
+                roll < 0.0
+            })
+            .unwrap_or(3);
+        match index {
+            0 => (RootStage::New, PipelineStatus::Succeeded),
+            1 => (RootStage::Verified, PipelineStatus::Succeeded),
+            2 => (RootStage::ProcessedDuplicates, PipelineStatus::Succeeded),
+            4 => (
+                RootStage::Verified,
+                PipelineStatus::Dropped {
+                    reason: "loadgen".to_owned(),
+                },
+            ),
+            _ => (RootStage::Finished, PipelineStatus::Succeeded),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadProfile {
+    pub count: usize,
+    /// Time between two consecutive events
+    pub interval: Duration,
+    /// Contexts are timestamped up to this much before or after their event
+    pub skew: Duration,
+    pub stages: StageDistribution,
+    pub body_bytes: usize,
+    pub seed: u64,
+}
+
+impl Default for LoadProfile {
+    fn default() -> Self {
+        Self {
+            count: 1_000,
+            interval: Duration::milliseconds(10),
+            skew: Duration::zero(),
+            stages: StageDistribution::default(),
+            body_bytes: 256,
+            seed: 42,
+        }
+    }
+}
+
+/// Deterministic generator of events and their contexts, timestamped from a [`SimClock`]
+/// that it advances by the profile's interval for every event
+#[derive(Debug)]
+pub struct LoadGenerator {
+    profile: LoadProfile,
+    clock: SimClock,
+    rng: StdRng,
+    access_key: AccessKey,
+    encrypted_access_key: EncryptedAccessKey<'static>,
+}
+
+impl LoadGenerator {
+    pub fn new(profile: LoadProfile, clock: SimClock) -> Self {
+        let access_key = AccessKey {
+            prefix: AccessKeyPrefix::new(Environment::Test, EventType::Id, 1),
+            data: AccessKeyData {
+                id: "build-loadgen".to_owned(),
+                namespace: "default".to_owned(),
+                event_type: "webhook".to_owned(),
+                group: "loadgen".to_owned(),
+                event_path: "event.received".to_owned(),
+                ..Default::default()
+            },
+        };
+        let encrypted_access_key = access_key
+            .encode(&[0u8; 32], &[0u8; 16])
+            .expect("Loadgen access key encodes")
+            .to_static();
+        Self {
+            rng: StdRng::seed_from_u64(profile.seed),
+            profile,
+            clock,
+            access_key,
+            encrypted_access_key,
+        }
+    }
+
+    pub fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    pub fn next_event(&mut self) -> EventWithContext {
+        let arrived_at = self.clock.advance(self.profile.interval);
+        let body = "x".repeat(self.profile.body_bytes);
+        let mut event = Event::new(
+            &self.access_key,
+            &self.encrypted_access_key,
+            "event.received",
+            HeaderMap::new(),
+            body,
+        );
+        event.arrived_at = arrived_at;
+        event.arrived_date = arrived_at;
+
+        let (stage, status) = self.profile.stages.sample(&mut self.rng);
+        let skew = self.profile.skew.num_milliseconds();
+        let skew = match skew {
+            0 => 0,
+            skew => self.rng.gen_range(-skew..=skew),
+        };
+        let mut context = RootContext::new(event.key);
+        context.stage = stage;
+        context.status = status;
+        context.timestamp = arrived_at + Duration::milliseconds(skew);
+
+        EventWithContext::new(event, context)
+    }
+
+    pub fn generate(&mut self) -> Vec<EventWithContext> {
+        (0..self.profile.count).map(|_| self.next_event()).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadReport {
+    pub items: usize,
+    pub elapsed: std::time::Duration,
+}
+
+impl LoadReport {
+    pub fn per_second(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Feeds every item to `consumer` one after the other and reports the throughput
+pub async fn drive<T, F, Fut>(items: Vec<T>, mut consumer: F) -> LoadReport
+where
+    F: FnMut(T) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let items_len = items.len();
+    let started = Instant::now();
+    for item in items {
+        consumer(item).await;
+    }
+    LoadReport {
+        items: items_len,
+        elapsed: started.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_generator_is_deterministic_and_paced() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let profile = LoadProfile {
+            count: 500,
+            skew: Duration::seconds(2),
+            ..Default::default()
+        };
+
+        let mut generator = LoadGenerator::new(profile.clone(), SimClock::new(start));
+        let events = generator.generate();
+        assert_eq!(events.len(), 500);
+        assert_eq!(
+            events[9].event.arrived_at,
+            start + Duration::milliseconds(100)
+        );
+        assert_eq!(generator.clock().now(), start + Duration::seconds(5));
+        assert!(events.iter().all(|e| {
+            (e.context.timestamp - e.event.arrived_at)
+                .num_milliseconds()
+                .abs()
+                <= 2_000
+        }));
+
+        let finished = events.iter().filter(|e| e.context.is_finished()).count();
+        assert!((300..450).contains(&finished), "{finished} finished");
+
+        let mut again = LoadGenerator::new(profile, SimClock::new(start));
+        let stages: Vec<String> = again
+            .generate()
+            .iter()
+            .map(|e| e.context.stage.to_string())
+            .collect();
+        assert_eq!(
+            stages,
+            events
+                .iter()
+                .map(|e| e.context.stage.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let report = drive(events, |_| async {}).await;
+        assert_eq!(report.items, 500);
+    }
+}
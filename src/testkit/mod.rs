@@ -0,0 +1,9 @@
+pub mod fixtures;
+pub mod loadgen;
+pub mod object_store;
+pub mod secrets;
+
+pub use fixtures::*;
+pub use loadgen::*;
+pub use object_store::*;
+pub use secrets::*;
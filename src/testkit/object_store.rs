@@ -0,0 +1,71 @@
+use crate::{IntegrationOSError, ObjectStoreExt};
+use async_trait::async_trait;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// Content type and body of each object, by key
+type Objects = BTreeMap<String, (String, Vec<u8>)>;
+
+/// In-memory stand-in for an object store, keeping the objects and their content type
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryObjectStore {
+    objects: Arc<Mutex<Objects>>,
+}
+
+impl InMemoryObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.objects
+            .lock()
+            .expect("object store lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    pub fn content_type(&self, key: &str) -> Option<String> {
+        self.objects
+            .lock()
+            .expect("object store lock poisoned")
+            .get(key)
+            .map(|(content_type, _)| content_type.clone())
+    }
+}
+
+#[async_trait]
+impl ObjectStoreExt for InMemoryObjectStore {
+    async fn put(
+        &self,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), IntegrationOSError> {
+        self.objects
+            .lock()
+            .expect("object store lock poisoned")
+            .insert(key.to_owned(), (content_type.to_owned(), body));
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, IntegrationOSError> {
+        Ok(self
+            .objects
+            .lock()
+            .expect("object store lock poisoned")
+            .get(key)
+            .map(|(_, body)| body.clone()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), IntegrationOSError> {
+        self.objects
+            .lock()
+            .expect("object store lock poisoned")
+            .remove(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    prelude::{
+        create_secret_response::{CreateSecretAuthor, CreateSecretResponse},
+        get_secret_request::GetSecretRequest,
+    },
+    CryptoExt, IntegrationOSError, InternalError,
+};
+use async_trait::async_trait;
+use base64ct::{Base64UrlUnpadded, Encoding};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+const PREFIX: &str = "mock:";
+
+/// In-memory stand-in for the secrets service. "Encryption" is a reversible base64url
+/// encoding of the value so tests can assert on it, and ids are sequential so they're
+/// stable across runs.
+#[derive(Debug, Clone, Default)]
+pub struct MockSecrets {
+    secrets: Arc<Mutex<HashMap<String, (String, Value)>>>,
+    next_id: Arc<AtomicU64>,
+    fail_encrypt: Arc<AtomicBool>,
+    fail_decrypt: Arc<AtomicBool>,
+}
+
+impl MockSecrets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn encode(value: &Value) -> String {
+        format!(
+            "{PREFIX}{}",
+            Base64UrlUnpadded::encode_string(value.to_string().as_bytes())
+        )
+    }
+
+    pub fn decode(encrypted: &str) -> Result<Value, IntegrationOSError> {
+        let encoded = encrypted.strip_prefix(PREFIX).ok_or_else(|| {
+            InternalError::decryption_error("Not a mock encrypted secret", Some("mock_secrets"))
+        })?;
+        let bytes = Base64UrlUnpadded::decode_vec(encoded)
+            .map_err(|e| InternalError::decryption_error(&e.to_string(), Some("mock_secrets")))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| InternalError::deserialize_error(&e.to_string(), Some("mock_secrets")))
+    }
+
+    /// Stores a secret under a known id, e.g. to seed fixtures that reference it
+    pub fn insert(&self, id: &str, buildable_id: &str, value: Value) {
+        self.secrets
+            .lock()
+            .expect("mock secrets lock poisoned")
+            .insert(id.to_owned(), (buildable_id.to_owned(), value));
+    }
+
+    pub fn get(&self, id: &str) -> Option<Value> {
+        self.secrets
+            .lock()
+            .expect("mock secrets lock poisoned")
+            .get(id)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.secrets
+            .lock()
+            .expect("mock secrets lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn fail_encrypt(&self, fail: bool) {
+        self.fail_encrypt.store(fail, Ordering::SeqCst);
+    }
+
+    pub fn fail_decrypt(&self, fail: bool) {
+        self.fail_decrypt.store(fail, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl CryptoExt for MockSecrets {
+    async fn encrypt(
+        &self,
+        key: String,
+        value: &Value,
+    ) -> Result<CreateSecretResponse, IntegrationOSError> {
+        if self.fail_encrypt.load(Ordering::SeqCst) {
+            return Err(InternalError::encryption_error(
+                "Injected encryption failure",
+                Some("mock_secrets"),
+            ));
+        }
+        let id = format!("secret-{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        self.insert(&id, &key, value.clone());
+        Ok(CreateSecretResponse {
+            id,
+            buildable_id: key,
+            created_at: 0.0,
+            author: CreateSecretAuthor {
+                id: "mock".to_owned(),
+            },
+            encrypted_secret: Self::encode(value),
+        })
+    }
+
+    async fn decrypt(&self, secret: &GetSecretRequest) -> Result<Value, IntegrationOSError> {
+        if self.fail_decrypt.load(Ordering::SeqCst) {
+            return Err(InternalError::decryption_error(
+                "Injected decryption failure",
+                Some("mock_secrets"),
+            ));
+        }
+        let secrets = self.secrets.lock().expect("mock secrets lock poisoned");
+        match secrets.get(&secret.id) {
+            Some((buildable_id, value)) if *buildable_id == secret.buildable_id => {
+                Ok(value.clone())
+            }
+            _ => Err(InternalError::key_not_found(
+                &format!("Secret {} not found", secret.id),
+                Some("mock_secrets"),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_mock_secrets_round_trip_and_failures() {
+        let secrets = MockSecrets::new();
+        let value = json!({ "apiKey": "sk_test" });
+
+        let created = secrets
+            .encrypt("buildable".to_owned(), &value)
+            .await
+            .unwrap();
+        assert_eq!(created.id, "secret-0");
+        assert_eq!(
+            MockSecrets::decode(&created.encrypted_secret).unwrap(),
+            value
+        );
+
+        let request = GetSecretRequest {
+            id: created.id.clone(),
+            buildable_id: "buildable".to_owned(),
+        };
+        assert_eq!(secrets.decrypt(&request).await.unwrap(), value);
+        assert!(secrets
+            .decrypt(&GetSecretRequest {
+                buildable_id: "other".to_owned(),
+                ..request.clone()
+            })
+            .await
+            .is_err());
+
+        secrets.fail_decrypt(true);
+        assert!(secrets.decrypt(&request).await.is_err());
+        secrets.fail_encrypt(true);
+        assert!(secrets
+            .encrypt("buildable".to_owned(), &value)
+            .await
+            .is_err());
+        assert_eq!(secrets.len(), 1);
+    }
+}